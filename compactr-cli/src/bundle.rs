@@ -0,0 +1,232 @@
+//! Compiles the schemas of an `OpenAPI` 3.x document into a schema bundle.
+//!
+//! Schemas that cannot be represented are skipped, and constructs that are
+//! parsed but not fully honored (unknown formats, `allOf`, ...) or hurt
+//! interoperability (see `compactr::schema::lint`) are reported as warnings,
+//! so build pipelines can surface them without failing.
+
+use compactr::schema::LintWarning;
+use compactr::{SchemaError, SchemaRegistry, SchemaType};
+use serde_json::Value as Json;
+
+/// Extension assigning a numeric schema ID, for `Message` envelopes.
+const ID_EXTENSION: &str = "x-compactr-id";
+
+const STRING_FORMATS: &[&str] = &[
+    "uuid",
+    "date-time",
+    "date-time-tz",
+    "timestamp",
+    "timestamp-seconds",
+    "timestamp-millis",
+    "timestamp-micros",
+    "timestamp-nanos",
+    "date",
+    "time",
+    "uri",
+    "ipv4",
+    "ipv6",
+    "socket-addr",
+    "geopoint",
+    "binary",
+    "byte",
+];
+const INTEGER_FORMATS: &[&str] = &[
+    "int8", "int16", "int32", "int64", "uint8", "uint16", "int128", "uint128",
+];
+// `decimal` requires the `decimal` feature of compactr, which is not enabled
+const NUMBER_FORMATS: &[&str] = &["float", "double"];
+
+/// Keywords ignored by [`SchemaType::from_json`], whose constraints are
+/// silently dropped.
+const UNSUPPORTED_KEYWORDS: &[&str] = &[
+    "allOf",
+    "anyOf",
+    "not",
+    "patternProperties",
+    "prefixItems",
+    "if",
+];
+
+/// Schemas registered from a document, with the warnings raised on the way.
+pub struct Compiled {
+    pub registry: SchemaRegistry,
+    pub warnings: Vec<String>,
+}
+
+/// Registers every schema of `doc` that can be represented.
+pub fn compile(doc: &Json) -> compactr::Result<Compiled> {
+    let invalid = |msg: &str| SchemaError::InvalidSchema(msg.to_owned());
+    if doc.get("swagger").is_some() {
+        return Err(
+            invalid("Swagger 2.0 documents are not supported, convert to OpenAPI 3.x").into(),
+        );
+    }
+    let schemas = doc
+        .pointer("/components/schemas")
+        .and_then(Json::as_object)
+        .ok_or_else(|| invalid("document has no components.schemas"))?;
+
+    let registry = SchemaRegistry::new();
+    let mut warnings = Vec::new();
+    if let Some(version) = doc.get("openapi").and_then(Json::as_str) {
+        if !version.starts_with("3.") {
+            warnings.push(format!("unexpected OpenAPI version {version}"));
+        }
+    }
+
+    for (name, json) in schemas {
+        let schema = match SchemaType::from_json(json) {
+            Ok(schema) => schema,
+            Err(e) => {
+                warnings.push(format!("{name}: skipped: {e}"));
+                continue;
+            }
+        };
+        lint(json, name, &mut warnings);
+        for warning in compactr::schema::lint(&schema) {
+            let path = format!("{name}{}", warning.path);
+            warnings.push(LintWarning { path, ..warning }.to_string());
+        }
+        registry.register(name.clone(), schema)?;
+
+        if let Some(id) = json.get(ID_EXTENSION) {
+            match id.as_u64().and_then(|id| u32::try_from(id).ok()) {
+                Some(id) => {
+                    if let Err(e) = registry.register_id(id, name) {
+                        warnings.push(format!("{name}: {e}"));
+                    }
+                }
+                None => warnings.push(format!("{name}: {ID_EXTENSION} must be a u32")),
+            }
+        }
+    }
+
+    // References to skipped or missing schemas only fail when encoding
+    for name in registry.names()? {
+        if let Some(schema) = registry.get(&name)? {
+            let mut references = Vec::new();
+            collect_references(&schema, &mut references);
+            for reference in references {
+                if let Err(e) = registry.resolve_ref(&reference) {
+                    warnings.push(format!("{name}: {e}"));
+                }
+            }
+        }
+    }
+
+    Ok(Compiled { registry, warnings })
+}
+
+/// Reports constructs of a parsed schema that are not fully honored.
+fn lint(json: &Json, path: &str, warnings: &mut Vec<String>) {
+    let Some(obj) = json.as_object() else {
+        return;
+    };
+    if obj.contains_key("$ref") {
+        return;
+    }
+    for keyword in UNSUPPORTED_KEYWORDS {
+        if obj.contains_key(*keyword) {
+            warnings.push(format!("{path}: {keyword} is not supported and is ignored"));
+        }
+    }
+    if obj
+        .get("additionalProperties")
+        .is_some_and(|additional| additional != &Json::Bool(false))
+    {
+        warnings.push(format!(
+            "{path}: additionalProperties is not supported, extra properties are dropped"
+        ));
+    }
+
+    let format = obj.get("format").and_then(Json::as_str);
+    let fallback = match obj.get("type").and_then(Json::as_str) {
+        Some("string") if obj.get("enum").is_none() => Some((STRING_FORMATS, "a plain string")),
+        Some("integer") => Some((INTEGER_FORMATS, "int64")),
+        Some("number") => Some((NUMBER_FORMATS, "double")),
+        _ => None,
+    };
+    if let (Some(format), Some((known, fallback))) = (format, fallback) {
+        if !known.contains(&format) {
+            warnings.push(format!(
+                "{path}: format {format} is not supported, encoded as {fallback}"
+            ));
+        }
+    }
+
+    if let Some(properties) = obj.get("properties").and_then(Json::as_object) {
+        for (name, property) in properties {
+            lint(property, &format!("{path}.{name}"), warnings);
+        }
+    }
+    if let Some(items) = obj.get("items") {
+        lint(items, &format!("{path}[]"), warnings);
+    }
+    if let Some(variants) = obj.get("oneOf").and_then(Json::as_array) {
+        for (i, variant) in variants.iter().enumerate() {
+            lint(variant, &format!("{path}.oneOf[{i}]"), warnings);
+        }
+    }
+}
+
+fn collect_references(schema: &SchemaType, references: &mut Vec<String>) {
+    match schema {
+        SchemaType::Reference(reference) => references.push(reference.clone()),
+        SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+            collect_references(items, references);
+        }
+        SchemaType::Object(properties) => {
+            for property in properties.values() {
+                collect_references(&property.schema_type, references);
+            }
+        }
+        SchemaType::OneOf { variants, .. } => {
+            for payload in variants.values() {
+                collect_references(payload, references);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compile() {
+        let doc = json!({
+            "openapi": "3.0.3",
+            "components": {"schemas": {
+                "User": {
+                    "type": "object",
+                    "x-compactr-id": 3,
+                    "properties": {
+                        "email": {"type": "string", "format": "email"},
+                        "extra": {"type": "object", "additionalProperties": true},
+                        "group": {"$ref": "#/components/schemas/Group"}
+                    }
+                },
+                "Group": {"anyOf": [{"type": "string"}, {"type": "integer"}]}
+            }}
+        });
+
+        let compiled = compile(&doc).unwrap();
+        assert_eq!(compiled.registry.names().unwrap(), ["User"]);
+        assert_eq!(compiled.registry.id_of("User").unwrap(), Some(3));
+        assert_eq!(compiled.warnings.len(), 5, "{:?}", compiled.warnings);
+        assert!(compiled.warnings[0].starts_with("Group: skipped"));
+        assert!(compiled.warnings[1].contains("format email"));
+        assert!(compiled.warnings[2].contains("additionalProperties"));
+        assert!(compiled.warnings[3].starts_with("User.email: string of unbounded length"));
+        assert!(compiled.warnings[4].contains("Group"));
+    }
+
+    #[test]
+    fn test_swagger_rejected() {
+        assert!(compile(&json!({"swagger": "2.0", "definitions": {}})).is_err());
+        assert!(compile(&json!({"openapi": "3.1.0"})).is_err());
+    }
+}
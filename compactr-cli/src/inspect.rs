@@ -0,0 +1,292 @@
+//! Annotated hex view of an encoded payload.
+//!
+//! The payload is walked along its schema, attributing every byte either to
+//! a structural header (property count, property index and size, array
+//! element size, variant index) or to a decoded value.
+
+use compactr::schema::{Property, StringFormat};
+use compactr::{DecodeError, Decoder, Result, SchemaRegistry, SchemaType, Value};
+use indexmap::IndexMap;
+use std::fmt::Write;
+
+const BYTES_PER_ROW: usize = 8;
+
+/// A span of the payload and what it encodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Line {
+    /// Offset of the span in the payload
+    offset: usize,
+    /// Length of the span
+    len: usize,
+    /// Nesting level, for indentation
+    depth: usize,
+    /// Description of the span
+    label: String,
+}
+
+/// Walks a payload along its schema, collecting [`Line`]s.
+pub struct Inspector<'a> {
+    data: &'a [u8],
+    registry: &'a SchemaRegistry,
+    lines: Vec<Line>,
+}
+
+impl<'a> Inspector<'a> {
+    pub fn new(data: &'a [u8], registry: &'a SchemaRegistry) -> Self {
+        Self {
+            data,
+            registry,
+            lines: Vec::new(),
+        }
+    }
+
+    /// Annotates the payload. On error, the lines collected up to the
+    /// invalid byte are kept so they can still be rendered.
+    pub fn run(&mut self, schema: &SchemaType) -> Result<()> {
+        let end = self.walk(schema, 0, self.data.len(), 0, false)?;
+        if end < self.data.len() {
+            self.push(end, self.data.len() - end, 0, "trailing bytes".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Renders the lines as offset, hex bytes and indented label columns.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for line in &self.lines {
+            let bytes = &self.data[line.offset..line.offset + line.len];
+            let mut rows = bytes.chunks(BYTES_PER_ROW);
+            let first = rows.next().unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{:06x}  {:<width$}  {:indent$}{}",
+                line.offset,
+                hex(first),
+                "",
+                line.label,
+                width = BYTES_PER_ROW * 3 - 1,
+                indent = line.depth * 2
+            );
+            for (i, row) in rows.enumerate() {
+                let offset = line.offset + (i + 1) * BYTES_PER_ROW;
+                let _ = writeln!(out, "{offset:06x}  {}", hex(row));
+            }
+        }
+        out
+    }
+
+    fn push(&mut self, offset: usize, len: usize, depth: usize, label: String) {
+        self.lines.push(Line {
+            offset,
+            len,
+            depth,
+            label,
+        });
+    }
+
+    /// Annotates the value at `pos`, which must end by `end`, returning the
+    /// position after it. `in_object` is set for property values, which
+    /// span their whole size.
+    fn walk(
+        &mut self,
+        schema: &SchemaType,
+        pos: usize,
+        end: usize,
+        depth: usize,
+        in_object: bool,
+    ) -> Result<usize> {
+        match schema {
+            SchemaType::Object(properties) => self.walk_object(properties, pos, end, depth),
+            SchemaType::Array(items) => self.walk_array(items, pos, end, depth),
+            SchemaType::Reference(name) => {
+                let resolved = self.registry.resolve_ref(name)?;
+                self.walk(&resolved, pos, end, depth, in_object)
+            }
+            SchemaType::Enum(_) | SchemaType::OneOf { .. } => {
+                let mut buf = &self.data[pos..end];
+                let (name, payload) = Decoder::decode_variant(&mut buf, schema)?;
+                let payload = payload.cloned();
+                self.push(pos, 1, depth, format!("variant {name:?}"));
+                match payload {
+                    Some(payload) => self.walk(&payload, pos + 1, end, depth, in_object),
+                    None => Ok(pos + 1),
+                }
+            }
+            // Plain strings inside objects are stored without a length prefix
+            SchemaType::String(StringFormat::Plain | StringFormat::Long) if in_object => {
+                let s = std::str::from_utf8(&self.data[pos..end])
+                    .map_err(|e| DecodeError::InvalidData(format!("Invalid UTF-8: {e}")))?;
+                self.push(pos, end - pos, depth, format!("{s:?}"));
+                Ok(end)
+            }
+            _ => {
+                let mut buf = &self.data[pos..end];
+                let value = Decoder::decode_with_registry(&mut buf, schema, self.registry)?;
+                let len = end - pos - buf.len();
+                self.push(pos, len, depth, label(&value));
+                Ok(pos + len)
+            }
+        }
+    }
+
+    fn walk_object(
+        &mut self,
+        properties: &IndexMap<String, Property>,
+        pos: usize,
+        end: usize,
+        depth: usize,
+    ) -> Result<usize> {
+        let count = *self.data[pos..end]
+            .first()
+            .ok_or(DecodeError::UnexpectedEof)?;
+        self.push(pos, 1, depth, format!("object, {count} properties"));
+
+        // Properties are indexed alphabetically, under their first alias if any
+        let mut sorted: Vec<_> = properties.iter().collect();
+        sorted.sort_by_key(|(name, property)| (property.index_name(name), name.as_str()));
+        let mut pos = pos + 1;
+        for _ in 0..count {
+            let index = usize::from(
+                *self.data[pos..end]
+                    .first()
+                    .ok_or(DecodeError::UnexpectedEof)?,
+            );
+            let (name, property) = sorted.get(index).ok_or_else(|| {
+                DecodeError::InvalidData(format!("Property index {index} out of range"))
+            })?;
+            let (size, header) = read_size(&self.data[pos + 1..end])?;
+            self.push(
+                pos,
+                1 + header,
+                depth + 1,
+                format!("{name}: property {index}, {size} bytes"),
+            );
+            pos += 1 + header;
+            if end - pos < size {
+                return Err(DecodeError::UnexpectedEof.into());
+            }
+            self.walk(&property.schema_type, pos, pos + size, depth + 2, true)?;
+            pos += size;
+        }
+        Ok(pos)
+    }
+
+    fn walk_array(
+        &mut self,
+        items: &SchemaType,
+        mut pos: usize,
+        end: usize,
+        depth: usize,
+    ) -> Result<usize> {
+        // Elements run until the end of the enclosing span
+        let mut index = 0;
+        while pos < end {
+            let size = usize::from(self.data[pos]);
+            self.push(pos, 1, depth, format!("[{index}] {size} bytes"));
+            pos += 1;
+            if end - pos < size {
+                return Err(DecodeError::UnexpectedEof.into());
+            }
+            self.walk(items, pos, pos + size, depth + 1, false)?;
+            pos += size;
+            index += 1;
+        }
+        Ok(pos)
+    }
+}
+
+/// Reads a property size, returning it with the number of bytes it takes.
+///
+/// Mirrors the decoder: a non-zero byte is the size itself, `0x00` is
+/// followed by a 1-byte size, or by `0x00` and the low byte of a 2-byte size.
+fn read_size(data: &[u8]) -> Result<(usize, usize)> {
+    match data {
+        [] | [0] => Err(DecodeError::UnexpectedEof.into()),
+        [0, next, ..] if *next > 0 || data.len() == 2 => Ok((usize::from(*next), 2)),
+        [0, next, low, ..] => Ok(((usize::from(*next) << 8) | usize::from(*low), 3)),
+        [size, ..] => Ok((usize::from(*size), 1)),
+    }
+}
+
+fn label(value: &Value) -> String {
+    value.to_json().to_string()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use compactr::Encoder;
+    use serde_json::json;
+
+    fn user() -> (SchemaType, SchemaRegistry) {
+        let schema = SchemaType::from_json(&json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": {"type": "string"},
+                "age": {"type": "integer", "format": "int32"}
+            }
+        }))
+        .unwrap();
+        (schema, SchemaRegistry::new())
+    }
+
+    fn encode(json: &serde_json::Value, schema: &SchemaType) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder
+            .encode(&Value::from_json(json, schema).unwrap(), schema)
+            .unwrap();
+        encoder.finish().to_vec()
+    }
+
+    #[test]
+    fn test_annotate_object() {
+        let (schema, registry) = user();
+        let bytes = encode(&json!({"name": "Al", "age": 30}), &schema);
+
+        let mut inspector = Inspector::new(&bytes, &registry);
+        inspector.run(&schema).unwrap();
+        let labels: Vec<_> = inspector.lines.iter().map(|l| l.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "object, 2 properties",
+                "age: property 0, 4 bytes",
+                "30",
+                "name: property 1, 2 bytes",
+                "\"Al\"",
+            ]
+        );
+        // Every byte is accounted for exactly once
+        let covered: usize = inspector.lines.iter().map(|l| l.len).sum();
+        assert_eq!(covered, bytes.len());
+        assert!(inspector.render().starts_with("000000  02"));
+    }
+
+    #[test]
+    fn test_truncated_payload() {
+        let (schema, registry) = user();
+        let bytes = encode(&json!({"name": "Alice"}), &schema);
+
+        // The headers read before the error are kept
+        let mut inspector = Inspector::new(&bytes[..bytes.len() - 1], &registry);
+        assert!(inspector.run(&schema).is_err());
+        assert_eq!(inspector.lines.len(), 2);
+    }
+
+    #[test]
+    fn test_read_size() {
+        assert_eq!(read_size(&[5]).unwrap(), (5, 1));
+        assert_eq!(read_size(&[0, 200]).unwrap(), (200, 2));
+        assert_eq!(read_size(&[0, 0, 9]).unwrap(), (9, 3));
+        assert!(read_size(&[0]).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! `compactr-cli`: encode JSON to Compactr, decode payloads back to JSON and
+//! inspect them byte by byte.
+//!
+//! Schemas are read from JSON files holding either a single schema or, with
+//! `--type`, an `OpenAPI` document (or `components.schemas` object), a
+//! Swagger 2.0 document or a schema bundle from which the named schema is
+//! used:
+//!
+//! ```text
+//! compactr-cli encode -s user.json user.json > user.bin
+//! compactr-cli decode -s openapi.json -t User --pretty user.bin
+//! compactr-cli inspect -s openapi.json -t User --hex dump.txt
+//! compactr-cli bundle openapi.yaml -o schemas.bin
+//! ```
+
+#![warn(clippy::all)]
+#![warn(clippy::pedantic)]
+
+mod bundle;
+mod inspect;
+
+use clap::{Args, Parser, Subcommand};
+use compactr::schema::resolve_external_refs;
+use compactr::{Decoder, EncodeError, Encoder, SchemaError, SchemaRegistry, SchemaType, Value};
+use inspect::Inspector;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Leading bytes of files written by [`SchemaRegistry::to_bundle`].
+const BUNDLE_MAGIC: &[u8] = b"CPRB";
+
+#[derive(Parser)]
+#[command(version, about = "Encode, decode and inspect Compactr payloads")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Encode a JSON value
+    Encode {
+        #[command(flatten)]
+        schema: SchemaArgs,
+        /// JSON file to encode (standard input if omitted or `-`)
+        input: Option<PathBuf>,
+        /// File to write (standard output if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Write the payload as hex text
+        #[arg(long)]
+        hex: bool,
+    },
+    /// Decode a payload to JSON
+    Decode {
+        #[command(flatten)]
+        schema: SchemaArgs,
+        /// Payload file (standard input if omitted or `-`)
+        input: Option<PathBuf>,
+        /// File to write (standard output if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Read the payload as hex text
+        #[arg(long)]
+        hex: bool,
+        /// Pretty-print the JSON
+        #[arg(long)]
+        pretty: bool,
+    },
+    /// Print an annotated hex view of a payload
+    Inspect {
+        #[command(flatten)]
+        schema: SchemaArgs,
+        /// Payload file (standard input if omitted or `-`)
+        input: Option<PathBuf>,
+        /// Read the payload as hex text
+        #[arg(long)]
+        hex: bool,
+    },
+    /// Compile the schemas of an `OpenAPI` 3.x document into a bundle
+    Bundle {
+        /// `OpenAPI` document (YAML if the extension is `.yaml` or `.yml`,
+        /// JSON otherwise). Schemas it references in other files are
+        /// bundled too
+        spec: PathBuf,
+        /// File to write (standard output if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+struct SchemaArgs {
+    /// JSON schema file, or `OpenAPI` document, Swagger 2.0 document or
+    /// schema bundle when used with `--type`
+    #[arg(short, long)]
+    schema: PathBuf,
+    /// Name of the schema to use from the document or bundle
+    #[arg(short = 't', long = "type")]
+    name: Option<String>,
+}
+
+impl SchemaArgs {
+    fn load(&self) -> Result<(SchemaType, SchemaRegistry)> {
+        let data = fs::read(&self.schema)?;
+        let registry = if data.starts_with(BUNDLE_MAGIC) {
+            SchemaRegistry::from_bundle(&mut data.as_slice())?
+        } else {
+            let json: serde_json::Value = serde_json::from_slice(&data)?;
+            if self.name.is_none() {
+                return Ok((SchemaType::from_json(&json)?, SchemaRegistry::new()));
+            }
+            let registry = SchemaRegistry::new();
+            if json.get("swagger").is_some() {
+                registry.register_swagger_definitions(&json)?;
+            } else {
+                registry.register_json_schemas(&resolve_document(&self.schema, &json)?)?;
+            }
+            registry
+        };
+        let name = self
+            .name
+            .as_deref()
+            .ok_or("--type is required with a bundle")?;
+        let schema = registry
+            .get(name)?
+            .ok_or_else(|| SchemaError::UnresolvedReference(name.to_owned()))?;
+        Ok((schema, registry))
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Encode {
+            schema,
+            input,
+            output,
+            hex,
+        } => {
+            let (schema, registry) = schema.load()?;
+            let json: serde_json::Value = serde_json::from_slice(&read_input(input.as_deref())?)?;
+            let bytes = encode(&json, &schema, &registry)?;
+            if hex {
+                write_output(
+                    output.as_deref(),
+                    format!("{}\n", to_hex(&bytes)).as_bytes(),
+                )
+            } else {
+                write_output(output.as_deref(), &bytes)
+            }
+        }
+        Command::Decode {
+            schema,
+            input,
+            output,
+            hex,
+            pretty,
+        } => {
+            let (schema, registry) = schema.load()?;
+            let bytes = read_payload(input.as_deref(), hex)?;
+            let json = decode(&bytes, &schema, &registry)?;
+            let mut text = if pretty {
+                serde_json::to_string_pretty(&json)?
+            } else {
+                json.to_string()
+            };
+            text.push('\n');
+            write_output(output.as_deref(), text.as_bytes())
+        }
+        Command::Inspect { schema, input, hex } => {
+            let (schema, registry) = schema.load()?;
+            let bytes = read_payload(input.as_deref(), hex)?;
+            let mut inspector = Inspector::new(&bytes, &registry);
+            let result = inspector.run(&schema);
+            // Show what could be annotated before reporting an error
+            print!("{}", inspector.render());
+            Ok(result?)
+        }
+        Command::Bundle { spec, output } => {
+            let doc = resolve_document(&spec, &read_document(&spec)?)?;
+            let compiled = bundle::compile(&doc)?;
+            for warning in &compiled.warnings {
+                eprintln!("warning: {warning}");
+            }
+            let names = compiled.registry.names()?;
+            write_output(output.as_deref(), &compiled.registry.to_bundle()?)?;
+            eprintln!("bundled {} schemas", names.len());
+            Ok(())
+        }
+    }
+}
+
+fn encode(
+    json: &serde_json::Value,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> compactr::Result<Vec<u8>> {
+    let value = Value::from_json_with_registry(json, schema, registry)?;
+    let mut encoder = Encoder::new();
+    encoder.encode_with_registry(&value, schema, registry)?;
+    Ok(encoder.finish().to_vec())
+}
+
+fn decode(
+    bytes: &[u8],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> compactr::Result<serde_json::Value> {
+    Ok(Decoder::decode_exact_with_registry(bytes, schema, registry)?.to_json())
+}
+
+fn read_input(path: Option<&Path>) -> io::Result<Vec<u8>> {
+    match path {
+        Some(path) if path != Path::new("-") => fs::read(path),
+        _ => {
+            let mut buf = Vec::new();
+            io::stdin().read_to_end(&mut buf)?;
+            Ok(buf)
+        }
+    }
+}
+
+/// Reads an `OpenAPI` document, as YAML if the extension is `.yaml` or
+/// `.yml` and as JSON otherwise.
+fn read_document(path: &Path) -> Result<serde_json::Value> {
+    let text = fs::read_to_string(path)?;
+    Ok(match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml" | "yml") => serde_yaml::from_str(&text)?,
+        _ => serde_json::from_str(&text)?,
+    })
+}
+
+/// Copies the schemas `doc` references in other files into it.
+fn resolve_document(path: &Path, doc: &serde_json::Value) -> Result<serde_json::Value> {
+    let resolved = resolve_external_refs(doc, &path.to_string_lossy(), |location| {
+        read_document(Path::new(location))
+            .map_err(|e| compactr::error::Error::Custom(format!("{location}: {e}")))
+    })?;
+    Ok(resolved)
+}
+
+fn read_payload(path: Option<&Path>, hex: bool) -> Result<Vec<u8>> {
+    let bytes = read_input(path)?;
+    if hex {
+        Ok(from_hex(std::str::from_utf8(&bytes)?)?)
+    } else {
+        Ok(bytes)
+    }
+}
+
+fn write_output(path: Option<&Path>, bytes: &[u8]) -> Result<()> {
+    match path {
+        Some(path) => fs::write(path, bytes)?,
+        None => io::stdout().write_all(bytes)?,
+    }
+    Ok(())
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        let _ = write!(out, "{b:02x}");
+    }
+    out
+}
+
+/// Parses hex text, ignoring whitespace and an optional `0x` prefix, so
+/// dumps copied from logs can be pasted as is.
+fn from_hex(text: &str) -> compactr::Result<Vec<u8>> {
+    let digits: String = text
+        .trim()
+        .trim_start_matches("0x")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    let invalid = || EncodeError::InvalidFormat("Invalid hex payload".to_owned());
+    if !digits.is_ascii() || digits.len() % 2 != 0 {
+        return Err(invalid().into());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|_| invalid().into()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn registry() -> SchemaRegistry {
+        let registry = SchemaRegistry::new();
+        registry
+            .register_json_schemas(&json!({
+                "components": {"schemas": {
+                    "User": {
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": {
+                            "name": {"type": "string"},
+                            "age": {"type": "integer", "format": "int32"},
+                            "tags": {"type": "array", "items": {"type": "string"}}
+                        }
+                    }
+                }}
+            }))
+            .unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_resolve_document() {
+        let dir = std::env::temp_dir().join(format!("compactr-cli-{}", std::process::id()));
+        fs::create_dir_all(dir.join("common")).unwrap();
+        let spec = dir.join("openapi.yaml");
+        fs::write(
+            &spec,
+            "components:\n  schemas:\n    Order:\n      type: object\n      properties:\n        \
+             total:\n          $ref: 'common/money.json#/components/schemas/Money'\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("common/money.json"),
+            r#"{"components": {"schemas": {"Money": {"type": "integer"}}}}"#,
+        )
+        .unwrap();
+
+        let doc = resolve_document(&spec, &read_document(&spec).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let compiled = bundle::compile(&doc).unwrap();
+        assert_eq!(compiled.registry.names().unwrap(), ["Money", "Order"]);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let registry = registry();
+        let schema = registry.get("User").unwrap().unwrap();
+        let user = json!({"name": "Alice", "age": 30, "tags": ["admin"]});
+
+        let bytes = encode(&user, &schema, &registry).unwrap();
+        assert_eq!(from_hex(&to_hex(&bytes)).unwrap(), bytes);
+        assert_eq!(decode(&bytes, &schema, &registry).unwrap(), user);
+        let mut padded = bytes.clone();
+        padded.push(0);
+        assert!(decode(&padded, &schema, &registry).is_err());
+    }
+
+    #[test]
+    fn test_from_hex() {
+        assert_eq!(from_hex("0x01 ff\n0a").unwrap(), [0x01, 0xff, 0x0a]);
+        assert!(from_hex("abc").is_err());
+        assert!(from_hex("zz").is_err());
+    }
+}
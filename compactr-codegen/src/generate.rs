@@ -0,0 +1,554 @@
+//! Translation of parsed schemas into Rust items.
+//!
+//! Objects become structs, string enumerations become enums of unit variants
+//! and `oneOf` schemas become enums tagged by their discriminator, all
+//! deriving `Compactr`. Inline objects and enumerations are hoisted into
+//! their own types, named after the enclosing type and property. Other
+//! top-level schemas become type aliases.
+
+use crate::naming::{field_ident, type_name};
+use crate::{Error, Result};
+use compactr::schema::{
+    IntegerFormat, NumberFormat, Property, SizeWidth, StringFormat, TimestampPrecision,
+};
+use compactr::{SchemaError, SchemaType};
+use indexmap::IndexMap;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use serde_json::Value as Json;
+use std::collections::{HashMap, HashSet};
+
+/// A schema of the document, with its JSON for descriptions.
+struct Definition<'a> {
+    schema: SchemaType,
+    json: &'a Json,
+}
+
+pub struct Generator<'a> {
+    definitions: IndexMap<String, Definition<'a>>,
+    items: Vec<TokenStream>,
+    /// Names of the generated types
+    types: HashSet<String>,
+    /// Generated types each type refers to
+    uses: HashMap<String, Vec<String>>,
+    /// Aliases being resolved, to catch reference cycles
+    resolving: Vec<String>,
+}
+
+impl<'a> Generator<'a> {
+    /// Parses the schemas of an `OpenAPI` document, or of its
+    /// `components.schemas` object.
+    pub fn new(doc: &'a Json) -> Result<Self> {
+        let schemas = doc
+            .pointer("/components/schemas")
+            .unwrap_or(doc)
+            .as_object()
+            .ok_or_else(|| {
+                SchemaError::InvalidSchema("expected an object of schemas".to_owned())
+            })?;
+        let mut definitions = IndexMap::new();
+        for (name, json) in schemas {
+            let schema = SchemaType::from_json(json)?;
+            definitions.insert(name.clone(), Definition { schema, json });
+        }
+        Ok(Self {
+            definitions,
+            items: Vec::new(),
+            types: HashSet::new(),
+            uses: HashMap::new(),
+            resolving: Vec::new(),
+        })
+    }
+
+    /// Generates the items for every schema.
+    pub fn run(mut self) -> Result<Vec<TokenStream>> {
+        let names: Vec<_> = self.definitions.keys().cloned().collect();
+        for name in names {
+            let definition = &self.definitions[&name];
+            let (schema, json) = (definition.schema.clone(), definition.json);
+            self.define(&type_name(&name), &schema, Some(json), &name)?;
+        }
+        self.check_recursion()?;
+        Ok(self.items)
+    }
+
+    /// Emits a type named `name` for `schema`.
+    fn define(
+        &mut self,
+        name: &str,
+        schema: &SchemaType,
+        json: Option<&Json>,
+        path: &str,
+    ) -> Result<()> {
+        if !self.types.insert(name.to_owned()) {
+            return Err(unsupported(
+                path,
+                format!("type name {name} is already used"),
+            ));
+        }
+        let ident = Ident::new(name, Span::call_site());
+        let docs = docs(json);
+        let mut uses = Vec::new();
+        // Hoisted inline types follow the type using them
+        let index = self.items.len();
+        self.items.push(TokenStream::new());
+
+        let item = match schema {
+            SchemaType::Object(properties) => {
+                let fields = self.fields(name, properties, json, path, &mut uses, true)?;
+                quote! {
+                    #docs
+                    #[derive(Debug, Clone, PartialEq, ::compactr::Compactr)]
+                    pub struct #ident {
+                        #fields
+                    }
+                }
+            }
+            SchemaType::Enum(values) => {
+                if values.is_empty() {
+                    return Err(unsupported(path, "enum without values"));
+                }
+                let variants = values
+                    .iter()
+                    .map(|value| variant(value))
+                    .collect::<Vec<_>>();
+                check_unique(&variants, path)?;
+                let variants = variants
+                    .iter()
+                    .map(|(ident, rename)| quote!(#rename #ident));
+                quote! {
+                    #docs
+                    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ::compactr::Compactr)]
+                    pub enum #ident {
+                        #(#variants,)*
+                    }
+                }
+            }
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            } => {
+                let variants = self.variants(name, variants, json, path, &mut uses)?;
+                quote! {
+                    #docs
+                    #[derive(Debug, Clone, PartialEq, ::compactr::Compactr)]
+                    #[compactr(tag = #discriminator)]
+                    pub enum #ident {
+                        #(#variants,)*
+                    }
+                }
+            }
+            _ => {
+                // Aliases only name the type; fields using them carry the format
+                let (ty, _) = self.field_type(schema, name, json, path, &mut uses)?;
+                quote! {
+                    #docs
+                    pub type #ident = #ty;
+                }
+            }
+        };
+        self.items[index] = item;
+        self.uses.insert(name.to_owned(), uses);
+        Ok(())
+    }
+
+    /// Struct fields for the properties of an object named `parent`, `pub`
+    /// unless they belong to an enum variant.
+    fn fields(
+        &mut self,
+        parent: &str,
+        properties: &IndexMap<String, Property>,
+        json: Option<&Json>,
+        path: &str,
+        uses: &mut Vec<String>,
+        public: bool,
+    ) -> Result<TokenStream> {
+        let vis = public.then(|| quote!(pub));
+        let mut idents = HashSet::new();
+        let mut fields = Vec::new();
+        for (wire_name, property) in properties {
+            let path = format!("{path}.{wire_name}");
+            let ident = field_ident(wire_name);
+            if !idents.insert(ident.to_string()) {
+                return Err(unsupported(
+                    &path,
+                    format!("field name {ident} is already used"),
+                ));
+            }
+            let json = json
+                .and_then(|json| json.get("properties"))
+                .and_then(|properties| properties.get(wire_name));
+            let hint = format!("{parent}{}", type_name(wire_name));
+            let (ty, format) = self.field_type(&property.schema_type, &hint, json, &path, uses)?;
+            let ty = if property.required {
+                ty
+            } else {
+                quote!(::std::option::Option<#ty>)
+            };
+
+            let mut attrs = Vec::new();
+            if syn::ext::IdentExt::unraw(&ident) != wire_name {
+                attrs.push(quote!(rename = #wire_name));
+            }
+            if let Some(format) = format {
+                attrs.push(quote!(format = #format));
+            }
+            if matches!(property.schema_type, SchemaType::RunLengthArray(_)) {
+                attrs.push(quote!(run_length));
+            }
+            if property.size_width != SizeWidth::Auto {
+                let width = property.size_width.to_string();
+                attrs.push(quote!(size_width = #width));
+            }
+            if property.sensitive {
+                attrs.push(quote!(sensitive));
+            }
+            for alias in &property.aliases {
+                attrs.push(quote!(alias = #alias));
+            }
+            let attrs = (!attrs.is_empty()).then(|| quote!(#[compactr(#(#attrs),*)]));
+            let docs = docs(json);
+            fields.push(quote! {
+                #docs
+                #attrs
+                #vis #ident: #ty
+            });
+        }
+        Ok(quote!(#(#fields,)*))
+    }
+
+    /// Enum variants for the variants of a `oneOf` named `parent`.
+    fn variants(
+        &mut self,
+        parent: &str,
+        variants: &IndexMap<String, SchemaType>,
+        json: Option<&Json>,
+        path: &str,
+        uses: &mut Vec<String>,
+    ) -> Result<Vec<TokenStream>> {
+        let idents: Vec<_> = variants.keys().map(|name| variant(name)).collect();
+        check_unique(&idents, path)?;
+
+        let mut out = Vec::new();
+        for (i, ((wire_name, payload), (ident, rename))) in variants.iter().zip(idents).enumerate()
+        {
+            let path = format!("{path}.{wire_name}");
+            // Variants are parsed in the order of the `oneOf` array
+            let json = json
+                .and_then(|json| json.get("oneOf"))
+                .and_then(|variants| variants.get(i));
+            let hint = format!("{parent}{ident}");
+            let variant = match payload {
+                SchemaType::Null => quote!(#ident),
+                SchemaType::Object(properties) => {
+                    let fields = self.fields(&hint, properties, json, &path, uses, false)?;
+                    quote!(#ident { #fields })
+                }
+                SchemaType::RunLengthArray(_) => {
+                    return Err(unsupported(
+                        &path,
+                        "variant payloads cannot be run-length arrays",
+                    ));
+                }
+                _ => {
+                    let (ty, format) = self.field_type(payload, &hint, json, &path, uses)?;
+                    if let Some(format) = format {
+                        return Err(unsupported(
+                            &path,
+                            format!("variant payloads cannot have format {format}"),
+                        ));
+                    }
+                    quote!(#ident(#ty))
+                }
+            };
+            out.push(quote!(#rename #variant));
+        }
+        Ok(out)
+    }
+
+    /// Rust type of a field with `schema`, with the `format` attribute it
+    /// needs when the type's own schema differs.
+    ///
+    /// Inline objects and enumerations are defined as `hint`. Generated
+    /// types the field refers to are added to `uses`.
+    fn field_type(
+        &mut self,
+        schema: &SchemaType,
+        hint: &str,
+        json: Option<&Json>,
+        path: &str,
+        uses: &mut Vec<String>,
+    ) -> Result<(TokenStream, Option<&'static str>)> {
+        let leaf = |ty: TokenStream| Ok((ty, None));
+        let formatted = |ty: TokenStream, format: &'static str| Ok((ty, Some(format)));
+        let string = quote!(::std::string::String);
+        let datetime = quote!(::chrono::DateTime<::chrono::Utc>);
+
+        match schema {
+            SchemaType::Boolean => leaf(quote!(bool)),
+            SchemaType::Integer(format) => match format {
+                IntegerFormat::Int32 => leaf(quote!(i32)),
+                IntegerFormat::Int64 => leaf(quote!(i64)),
+                IntegerFormat::Int8 => formatted(quote!(i32), "int8"),
+                IntegerFormat::Int16 => formatted(quote!(i32), "int16"),
+                IntegerFormat::Uint8 => formatted(quote!(i32), "uint8"),
+                IntegerFormat::Uint16 => formatted(quote!(i32), "uint16"),
+                IntegerFormat::Int128 | IntegerFormat::Uint128 => {
+                    Err(unsupported(path, "128-bit integers have no generated type"))
+                }
+            },
+            SchemaType::Number(NumberFormat::Float) => leaf(quote!(f32)),
+            SchemaType::Number(NumberFormat::Double) => leaf(quote!(f64)),
+            SchemaType::String(format) => match format {
+                StringFormat::Plain => leaf(string),
+                StringFormat::Long => formatted(string, "long"),
+                StringFormat::Uuid => leaf(quote!(::uuid::Uuid)),
+                StringFormat::DateTime => leaf(datetime),
+                StringFormat::Timestamp(precision) => formatted(
+                    datetime,
+                    match precision {
+                        TimestampPrecision::Seconds => "timestamp-seconds",
+                        TimestampPrecision::Millis => "timestamp-millis",
+                        TimestampPrecision::Micros => "timestamp-micros",
+                        TimestampPrecision::Nanos => "timestamp-nanos",
+                    },
+                ),
+                StringFormat::Ipv4 => leaf(quote!(::std::net::Ipv4Addr)),
+                StringFormat::Ipv6 => leaf(quote!(::std::net::Ipv6Addr)),
+                // Decoded to their string form
+                StringFormat::DateTimeTz => formatted(string, "date-time-tz"),
+                StringFormat::Date => formatted(string, "date"),
+                StringFormat::Time => formatted(string, "time"),
+                StringFormat::Uri => formatted(string, "uri"),
+                StringFormat::SocketAddr => formatted(string, "socket-addr"),
+                StringFormat::Binary | StringFormat::GeoPoint | StringFormat::Custom(_) => {
+                    Err(unsupported(
+                        path,
+                        format!("string format {format:?} has no generated type"),
+                    ))
+                }
+            },
+            SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+                if matches!(**items, SchemaType::RunLengthArray(_)) {
+                    return Err(unsupported(
+                        path,
+                        "nested run-length arrays have no generated type",
+                    ));
+                }
+                let json = json.and_then(|json| json.get("items"));
+                let hint = format!("{hint}Item");
+                let (ty, format) =
+                    self.field_type(items, &hint, json, &format!("{path}[]"), uses)?;
+                if let Some(format) = format {
+                    return Err(unsupported(
+                        path,
+                        format!("arrays of format {format} have no generated type"),
+                    ));
+                }
+                leaf(quote!(::std::vec::Vec<#ty>))
+            }
+            SchemaType::Reference(reference) => self.reference(reference, path, uses),
+            SchemaType::Object(_) | SchemaType::Enum(_) | SchemaType::OneOf { .. } => {
+                self.define(hint, schema, json, path)?;
+                uses.push(hint.to_owned());
+                let ident = Ident::new(hint, Span::call_site());
+                leaf(quote!(#ident))
+            }
+            _ => Err(unsupported(path, format!("{schema} has no generated type"))),
+        }
+    }
+
+    /// Rust type of a reference: the referenced type, or for aliases the
+    /// aliased type itself so that fields keep its format.
+    fn reference(
+        &mut self,
+        reference: &str,
+        path: &str,
+        uses: &mut Vec<String>,
+    ) -> Result<(TokenStream, Option<&'static str>)> {
+        let name = reference
+            .strip_prefix("#/components/schemas/")
+            .or_else(|| reference.strip_prefix("#/"))
+            .unwrap_or(reference);
+        let definition = self
+            .definitions
+            .get(name)
+            .ok_or_else(|| SchemaError::UnresolvedReference(name.to_owned()))?;
+        let (schema, json) = (definition.schema.clone(), definition.json);
+
+        if let SchemaType::Object(_) | SchemaType::Enum(_) | SchemaType::OneOf { .. } = schema {
+            let ty = type_name(name);
+            uses.push(ty.clone());
+            let ident = Ident::new(&ty, Span::call_site());
+            return Ok((quote!(#ident), None));
+        }
+        if self.resolving.iter().any(|n| n == name) {
+            return Err(SchemaError::CircularReference(name.to_owned()).into());
+        }
+        self.resolving.push(name.to_owned());
+        let resolved = self.field_type(&schema, &type_name(name), Some(json), path, uses);
+        self.resolving.pop();
+        resolved
+    }
+
+    /// Rejects types that refer to themselves: derived schemas inline the
+    /// schemas of nested types, so they would never end.
+    fn check_recursion(&self) -> Result<()> {
+        fn visit<'c>(
+            name: &'c str,
+            uses: &'c HashMap<String, Vec<String>>,
+            stack: &mut Vec<&'c str>,
+            done: &mut HashSet<&'c str>,
+        ) -> Result<()> {
+            if let Some(start) = stack.iter().position(|n| *n == name) {
+                let mut cycle = stack[start..].to_vec();
+                cycle.push(name);
+                return Err(unsupported(
+                    name,
+                    format!("recursive types are not supported ({})", cycle.join(" -> ")),
+                ));
+            }
+            if !done.insert(name) {
+                return Ok(());
+            }
+            stack.push(name);
+            for inner in uses.get(name).into_iter().flatten() {
+                visit(inner, uses, stack, done)?;
+            }
+            stack.pop();
+            Ok(())
+        }
+
+        let mut names: Vec<_> = self.uses.keys().collect();
+        names.sort();
+        let mut done = HashSet::new();
+        for name in names {
+            visit(name, &self.uses, &mut Vec::new(), &mut done)?;
+        }
+        Ok(())
+    }
+}
+
+/// Identifier of an enum variant, with the `rename` attribute it needs.
+fn variant(name: &str) -> (Ident, Option<TokenStream>) {
+    let ident = Ident::new(&type_name(name), Span::call_site());
+    let rename = (ident != name).then(|| quote!(#[compactr(rename = #name)]));
+    (ident, rename)
+}
+
+fn check_unique(variants: &[(Ident, Option<TokenStream>)], path: &str) -> Result<()> {
+    let mut seen = HashSet::new();
+    for (ident, _) in variants {
+        if !seen.insert(ident.to_string()) {
+            return Err(unsupported(
+                path,
+                format!("variant name {ident} is already used"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `#[doc]` attributes for the schema's `description`.
+fn docs(json: Option<&Json>) -> TokenStream {
+    let Some(description) = json
+        .and_then(|json| json.get("description"))
+        .and_then(Json::as_str)
+    else {
+        return TokenStream::new();
+    };
+    let lines = description.trim().lines().map(|line| format!(" {line}"));
+    quote!(#(#[doc = #lines])*)
+}
+
+fn unsupported(path: &str, reason: impl Into<String>) -> Error {
+    Error::Unsupported {
+        path: path.to_owned(),
+        reason: reason.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{generate, Error};
+    use serde_json::json;
+
+    fn unsupported(schemas: &serde_json::Value) -> String {
+        match generate(schemas) {
+            Err(Error::Unsupported { path, reason }) => format!("{path}: {reason}"),
+            other => panic!("expected an unsupported schema, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_hoisted_and_aliased_types() {
+        let source = generate(&json!({
+            "Day": {"type": "string", "format": "date"},
+            "Shift": {
+                "type": "object",
+                "required": ["day"],
+                "properties": {
+                    "day": {"$ref": "#/components/schemas/Day"},
+                    "slots": {"type": "array", "items": {
+                        "type": "object",
+                        "properties": {"start": {"type": "integer", "format": "int32"}}
+                    }}
+                }
+            }
+        }))
+        .unwrap();
+        assert!(source.contains("pub type Day = ::std::string::String;"));
+        // Fields of an alias type keep its format
+        assert!(
+            source.contains("#[compactr(format = \"date\")]\n    pub day: ::std::string::String,")
+        );
+        assert!(
+            source.contains("pub slots: ::std::option::Option<::std::vec::Vec<ShiftSlotsItem>>")
+        );
+        assert!(source.contains("pub struct ShiftSlotsItem {"));
+    }
+
+    #[test]
+    fn test_unsupported_schemas() {
+        assert_eq!(
+            unsupported(&json!({"File": {"type": "object", "properties": {
+                "data": {"type": "string", "format": "binary"}
+            }}})),
+            "File.data: string format Binary has no generated type"
+        );
+        assert_eq!(
+            unsupported(&json!({"Days": {"type": "array", "items": {
+                "type": "string", "format": "date"
+            }}})),
+            "Days: arrays of format date have no generated type"
+        );
+        assert_eq!(
+            unsupported(&json!({"Pair": {"type": "object", "properties": {
+                "userId": {"type": "string"},
+                "user_id": {"type": "string"}
+            }}})),
+            "Pair.user_id: field name user_id is already used"
+        );
+    }
+
+    #[test]
+    fn test_recursive_schemas_rejected() {
+        let reason = unsupported(&json!({
+            "Node": {"type": "object", "properties": {
+                "children": {"type": "array", "items": {"$ref": "#/components/schemas/Node"}}
+            }}
+        }));
+        assert_eq!(
+            reason,
+            "Node: recursive types are not supported (Node -> Node)"
+        );
+
+        assert!(matches!(
+            generate(&json!({
+                "A": {"$ref": "#/components/schemas/B"},
+                "B": {"$ref": "#/components/schemas/A"}
+            })),
+            Err(Error::Schema(_))
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+//! Rust code generation from `OpenAPI` schemas for
+//! [Compactr](https://docs.rs/compactr).
+//!
+//! Every schema of a document's `components.schemas` becomes a Rust type
+//! deriving `Compactr`, so application code works with typed structs and
+//! enums instead of dynamic `Value`s:
+//!
+//! - objects become structs, with optional properties as `Option` fields
+//! - string enumerations become enums of unit variants
+//! - `oneOf` schemas with a discriminator become tagged enums
+//! - other schemas become type aliases
+//!
+//! Inline objects and enumerations are hoisted into their own types, named
+//! after the enclosing type and property (`User.address` becomes
+//! `UserAddress`).
+//!
+//! The generated code needs the `derive` feature of `compactr`, and the
+//! `uuid` and `chrono` crates when schemas use the `uuid` or `date-time`
+//! formats.
+//!
+//! ## Build scripts
+//!
+//! ```rust,ignore
+//! // build.rs
+//! fn main() {
+//!     compactr_codegen::compile("openapi.yaml", "api.rs").unwrap();
+//! }
+//! ```
+//!
+//! ```rust,ignore
+//! // src/lib.rs
+//! #[allow(clippy::all)]
+//! pub mod api {
+//!     include!(concat!(env!("OUT_DIR"), "/api.rs"));
+//! }
+//! ```
+//!
+//! ## Limitations
+//!
+//! Binary, geopoint, decimal and 128-bit integer values, arrays of formatted
+//! strings (such as `date`) and recursive schemas have no generated
+//! representation and are reported as [`Error::Unsupported`]. Derived
+//! schemas inline the schemas of nested types, so a type cannot refer to
+//! itself, even through an array.
+
+#![warn(missing_docs)]
+#![warn(clippy::all)]
+#![warn(clippy::pedantic)]
+
+mod generate;
+mod naming;
+
+use generate::Generator;
+use serde_json::Value as Json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// A specialized `Result` type for code generation.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors that can occur while generating code.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Reading the document or writing the generated code failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// The document is not valid JSON or YAML
+    #[error("Invalid document: {0}")]
+    Parse(String),
+
+    /// A schema could not be parsed or a reference resolved
+    #[error(transparent)]
+    Schema(#[from] compactr::error::Error),
+
+    /// A schema has no generated Rust representation
+    #[error("{path}: {reason}")]
+    Unsupported {
+        /// Schema, property or variant path, e.g. `User.address`
+        path: String,
+        /// Why the schema is not supported
+        reason: String,
+    },
+}
+
+impl From<compactr::SchemaError> for Error {
+    fn from(e: compactr::SchemaError) -> Self {
+        Self::Schema(e.into())
+    }
+}
+
+/// Generates Rust source for the schemas of an `OpenAPI` document, or of its
+/// `components.schemas` object.
+///
+/// # Errors
+///
+/// Returns an error if a schema cannot be parsed, a reference cannot be
+/// resolved or a schema has no generated representation.
+///
+/// # Panics
+///
+/// Panics if the generated tokens are not valid Rust, which is a bug.
+pub fn generate(doc: &Json) -> Result<String> {
+    let mut out = "// @generated by compactr-codegen. Do not edit.\n".to_owned();
+    for item in Generator::new(doc)?.run()? {
+        let file: syn::File = syn::parse2(item).expect("generated code parses");
+        out.push('\n');
+        out.push_str(&prettyplease::unparse(&file));
+    }
+    Ok(out)
+}
+
+/// Reads an `OpenAPI` document, as YAML if the extension is `.yaml` or
+/// `.yml` and as JSON otherwise.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or parsed.
+pub fn read_document(path: impl AsRef<Path>) -> Result<Json> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let parse_error = |e: &dyn std::fmt::Display| Error::Parse(format!("{}: {e}", path.display()));
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml" | "yml") => serde_yaml::from_str(&text).map_err(|e| parse_error(&e)),
+        _ => serde_json::from_str(&text).map_err(|e| parse_error(&e)),
+    }
+}
+
+/// Generates Rust source for the `OpenAPI` document at `spec` into
+/// `out_file` under `OUT_DIR`, returning the path written.
+///
+/// Meant to be called from a build script: Cargo is told to rerun it when
+/// the document changes.
+///
+/// # Errors
+///
+/// Returns an error if `OUT_DIR` is not set, the document cannot be read or
+/// [`generate`] fails.
+pub fn compile(spec: impl AsRef<Path>, out_file: impl AsRef<Path>) -> Result<PathBuf> {
+    let spec = spec.as_ref();
+    println!("cargo:rerun-if-changed={}", spec.display());
+    let out_dir = std::env::var_os("OUT_DIR").ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "OUT_DIR is not set, compile must run from a build script",
+        )
+    })?;
+    let source = generate(&read_document(spec)?)?;
+    let path = Path::new(&out_dir).join(out_file);
+    fs::write(&path, source)?;
+    Ok(path)
+}
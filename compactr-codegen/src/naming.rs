@@ -0,0 +1,104 @@
+//! Rust identifiers for schema, property and enum value names.
+
+use proc_macro2::{Ident, Span};
+
+/// Strict and reserved keywords of the 2021 edition, which need a raw
+/// identifier (`r#type`) as field names.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Keywords that cannot be raw identifiers.
+const RESERVED: &[&str] = &["crate", "self", "super", "Self"];
+
+/// `PascalCase` name of a type or enum variant, e.g. `user-profile` to
+/// `UserProfile`.
+pub fn type_name(name: &str) -> String {
+    let mut out: String = split_words(name).iter().map(|w| capitalize(w)).collect();
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, 'N');
+    }
+    if RESERVED.contains(&out.as_str()) {
+        out.push('_');
+    }
+    out
+}
+
+/// `snake_case` identifier of a struct field, e.g. `createdAt` to
+/// `created_at`, using a raw identifier for keywords.
+pub fn field_ident(name: &str) -> Ident {
+    let mut out = split_words(name).join("_");
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    if RESERVED.contains(&out.as_str()) {
+        out.push('_');
+    }
+    if KEYWORDS.contains(&out.as_str()) {
+        Ident::new_raw(&out, Span::call_site())
+    } else {
+        Ident::new(&out, Span::call_site())
+    }
+}
+
+/// Splits a name into lowercase ASCII words on non-alphanumeric characters
+/// and lower-to-upper case transitions.
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && prev_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        current.push(c.to_ascii_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_type_name() {
+        assert_eq!(type_name("user-profile"), "UserProfile");
+        assert_eq!(type_name("userProfile"), "UserProfile");
+        assert_eq!(type_name("USER_ROLE"), "UserRole");
+        assert_eq!(type_name("404"), "N404");
+        assert_eq!(type_name(""), "N");
+        assert_eq!(type_name("self"), "Self_");
+    }
+
+    #[test]
+    fn test_field_ident() {
+        assert_eq!(field_ident("createdAt").to_string(), "created_at");
+        assert_eq!(field_ident("X-Request-Id").to_string(), "x_request_id");
+        assert_eq!(field_ident("type").to_string(), "r#type");
+        assert_eq!(field_ident("self").to_string(), "self_");
+        assert_eq!(field_ident("2fa").to_string(), "_2fa");
+    }
+}
@@ -0,0 +1,69 @@
+// @generated by compactr-codegen. Do not edit.
+
+pub type Email = ::std::string::String;
+
+/// Something that happened to a pet.
+#[derive(Debug, Clone, PartialEq, ::compactr::Compactr)]
+#[compactr(tag = "kind")]
+pub enum Event {
+    #[compactr(rename = "listed")]
+    Listed(Pet),
+    #[compactr(rename = "adopted")]
+    Adopted { #[compactr(rename = "ownerId")] owner_id: i64 },
+    #[compactr(rename = "closed")]
+    Closed,
+}
+
+#[derive(Debug, Clone, PartialEq, ::compactr::Compactr)]
+pub struct Owner {
+    pub address: ::std::option::Option<OwnerAddress>,
+    #[compactr(format = "uri")]
+    pub email: ::std::string::String,
+    pub ip: ::std::option::Option<::std::net::Ipv4Addr>,
+    #[compactr(rename = "lastLogin")]
+    pub last_login: ::std::option::Option<::chrono::DateTime<::chrono::Utc>>,
+    pub pets: ::std::option::Option<::std::vec::Vec<::std::string::String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, ::compactr::Compactr)]
+pub struct OwnerAddress {
+    pub city: ::std::option::Option<::std::string::String>,
+    #[compactr(format = "uint16")]
+    pub zip: ::std::option::Option<i32>,
+}
+
+/// A pet available for adoption.
+#[derive(Debug, Clone, PartialEq, ::compactr::Compactr)]
+pub struct Pet {
+    #[compactr(rename = "addedAt", format = "timestamp-millis")]
+    pub added_at: ::std::option::Option<::chrono::DateTime<::chrono::Utc>>,
+    /// Day of birth, if known.
+    #[compactr(rename = "birthDate", format = "date")]
+    pub birth_date: ::std::option::Option<::std::string::String>,
+    pub id: ::uuid::Uuid,
+    pub name: ::std::string::String,
+    pub owner: ::std::option::Option<Owner>,
+    pub status: PetStatus,
+    pub tags: ::std::vec::Vec<::std::string::String>,
+    pub r#type: PetType,
+    #[compactr(rename = "weightKg")]
+    pub weight_kg: ::std::option::Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ::compactr::Compactr)]
+pub enum PetType {
+    #[compactr(rename = "cat")]
+    Cat,
+    #[compactr(rename = "dog")]
+    Dog,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ::compactr::Compactr)]
+pub enum PetStatus {
+    #[compactr(rename = "available")]
+    Available,
+    #[compactr(rename = "pending")]
+    Pending,
+    #[compactr(rename = "sold_out")]
+    SoldOut,
+}
@@ -0,0 +1,100 @@
+//! Tests for the code generated from `fixtures/petstore.yaml`, checked in as
+//! `fixtures/petstore.rs`.
+
+use compactr::{Decoder, Encoder, Schema, SchemaRegistry, SchemaType};
+use serde_json::json;
+use std::path::PathBuf;
+
+#[allow(clippy::all, clippy::pedantic)]
+mod petstore {
+    include!("fixtures/petstore.rs");
+}
+
+use petstore::{Email, Event, Owner, OwnerAddress, Pet, PetStatus, PetType};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+fn registry() -> SchemaRegistry {
+    let doc = compactr_codegen::read_document(fixture("petstore.yaml")).unwrap();
+    let registry = SchemaRegistry::new();
+    registry.register_json_schemas(&doc).unwrap();
+    registry
+}
+
+fn pet() -> Pet {
+    Pet {
+        added_at: Some(chrono::DateTime::from_timestamp_millis(1_700_000_000_123).unwrap()),
+        birth_date: Some("2021-04-01".to_owned()),
+        id: uuid::Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap(),
+        name: "Rex".to_owned(),
+        owner: Some(Owner {
+            address: Some(OwnerAddress {
+                city: Some("Lyon".to_owned()),
+                zip: Some(6900),
+            }),
+            email: Email::from("mailto:alice@example.com"),
+            ip: Some("10.0.0.1".parse().unwrap()),
+            last_login: None,
+            pets: Some(vec!["Rex".to_owned()]),
+        }),
+        status: PetStatus::SoldOut,
+        tags: vec!["good".to_owned()],
+        r#type: PetType::Dog,
+        weight_kg: Some(12.5),
+    }
+}
+
+#[test]
+fn test_fixture_up_to_date() {
+    let doc = compactr_codegen::read_document(fixture("petstore.yaml")).unwrap();
+    let generated = compactr_codegen::generate(&doc).unwrap();
+    if std::env::var_os("UPDATE_FIXTURES").is_some() {
+        std::fs::write(fixture("petstore.rs"), &generated).unwrap();
+    }
+    assert_eq!(
+        generated,
+        std::fs::read_to_string(fixture("petstore.rs")).unwrap(),
+        "generated code changed, rerun with UPDATE_FIXTURES=1 to update the fixture"
+    );
+}
+
+#[test]
+fn test_generated_matches_spec() {
+    let registry = registry();
+    let schema = registry.get("Pet").unwrap().unwrap();
+
+    // Encoded with the derived schema, decoded dynamically with the spec's
+    let mut encoder = Encoder::new();
+    encoder
+        .encode_as(&pet(), &Pet::schema(), &registry)
+        .unwrap();
+    let bytes = encoder.finish();
+    let value = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(value.to_json()["status"], json!("sold_out"));
+    assert_eq!(value.to_json()["owner"]["address"]["zip"], json!(6900));
+
+    // And back into the generated type from the spec's schema
+    let decoded: Pet = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, pet());
+}
+
+#[test]
+fn test_generated_one_of() {
+    let registry = registry();
+    let schema = SchemaType::reference("#/components/schemas/Event");
+    for event in [
+        Event::Listed(pet()),
+        Event::Adopted { owner_id: 42 },
+        Event::Closed,
+    ] {
+        let mut encoder = Encoder::new();
+        encoder.encode_as(&event, &schema, &registry).unwrap();
+        let bytes = encoder.finish();
+        let decoded: Event = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(decoded, event);
+    }
+}
@@ -0,0 +1,200 @@
+//! Parsing of `#[compactr(...)]` attributes.
+
+use crate::case::RenameRule;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, Ident, LitStr, Path};
+
+/// Attributes placed on the struct or enum itself.
+#[derive(Default)]
+pub struct ContainerAttrs {
+    /// `rename_all = "..."`
+    pub rename_all: Option<RenameRule>,
+    /// `tag = "..."` (enums only)
+    pub tag: Option<LitStr>,
+}
+
+impl ContainerAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut out = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("compactr")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename_all") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    out.rename_all = Some(RenameRule::from_lit(&lit)?);
+                    Ok(())
+                } else if meta.path.is_ident("tag") {
+                    out.tag = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown compactr container attribute"))
+                }
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+/// Attributes placed on an enum variant.
+#[derive(Default)]
+pub struct VariantAttrs {
+    /// `rename = "..."`
+    pub rename: Option<String>,
+}
+
+impl VariantAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut out = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("compactr")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    out.rename = Some(lit.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown compactr variant attribute"))
+                }
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+/// Attributes placed on a struct field.
+#[derive(Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct FieldAttrs {
+    /// `rename = "..."`
+    pub rename: Option<String>,
+    /// `skip`
+    pub skip: bool,
+    /// `default`
+    pub default: bool,
+    /// `format = "..."`
+    pub format: Option<LitStr>,
+    /// `flatten`
+    pub flatten: bool,
+    /// `with = "module"`
+    pub with: Option<Path>,
+    /// `run_length`
+    pub run_length: bool,
+    /// `size_width = "u8" | "u16" | "u32"`, as the `SizeWidth` variant
+    pub size_width: Option<Ident>,
+    /// `sensitive`
+    pub sensitive: bool,
+    /// `alias = "..."`, repeatable
+    pub aliases: Vec<String>,
+}
+
+impl FieldAttrs {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut out = Self::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("compactr")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    out.rename = Some(lit.value());
+                } else if meta.path.is_ident("skip") {
+                    out.skip = true;
+                } else if meta.path.is_ident("default") {
+                    out.default = true;
+                } else if meta.path.is_ident("format") {
+                    out.format = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("flatten") {
+                    out.flatten = true;
+                } else if meta.path.is_ident("with") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    out.with = Some(lit.parse()?);
+                } else if meta.path.is_ident("run_length") {
+                    out.run_length = true;
+                } else if meta.path.is_ident("size_width") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    let variant = match lit.value().as_str() {
+                        "u8" => "U8",
+                        "u16" => "U16",
+                        "u32" => "U32",
+                        _ => return Err(meta.error("expected \"u8\", \"u16\" or \"u32\"")),
+                    };
+                    out.size_width = Some(Ident::new(variant, lit.span()));
+                } else if meta.path.is_ident("sensitive") {
+                    out.sensitive = true;
+                } else if meta.path.is_ident("alias") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    out.aliases.push(lit.value());
+                } else {
+                    return Err(meta.error("unknown compactr field attribute"));
+                }
+                Ok(())
+            })?;
+            if out.flatten
+                && (out.rename.is_some()
+                    || out.skip
+                    || out.default
+                    || out.format.is_some()
+                    || out.with.is_some()
+                    || out.run_length
+                    || out.size_width.is_some()
+                    || out.sensitive
+                    || !out.aliases.is_empty())
+            {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`flatten` cannot be combined with other field attributes",
+                ));
+            }
+            if out.with.is_some() && out.format.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`with` and `format` cannot be combined; the module provides the schema",
+                ));
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Maps a `format = "..."` name to the matching schema constructor.
+///
+/// Names follow the `OpenAPI` `format` keyword, plus `long` for strings with
+/// a u32 length prefix; anything unrecognized is treated as a custom format
+/// looked up in the registry at encode time.
+pub fn format_schema(format: &LitStr) -> TokenStream {
+    let precision = match format.value().as_str() {
+        "timestamp-seconds" => Some(quote!(Seconds)),
+        "timestamp" | "timestamp-millis" => Some(quote!(Millis)),
+        "timestamp-micros" => Some(quote!(Micros)),
+        "timestamp-nanos" => Some(quote!(Nanos)),
+        _ => None,
+    };
+    if let Some(precision) = precision {
+        return quote!(::compactr::SchemaType::timestamp(
+            ::compactr::TimestampPrecision::#precision
+        ));
+    }
+    let ctor = match format.value().as_str() {
+        "uuid" => quote!(string_uuid),
+        "date-time" => quote!(string_datetime),
+        "date-time-tz" => quote!(string_datetime_tz),
+        "date" => quote!(string_date),
+        "time" => quote!(string_time),
+        "uri" => quote!(string_uri),
+        "ipv4" => quote!(string_ipv4),
+        "ipv6" => quote!(string_ipv6),
+        "socket-addr" => quote!(string_socket_addr),
+        "geopoint" => quote!(geo_point),
+        "binary" | "byte" => quote!(binary),
+        "long" => quote!(long_string),
+        "int8" => quote!(int8),
+        "int16" => quote!(int16),
+        "int32" => quote!(int32),
+        "int64" => quote!(int64),
+        "uint8" => quote!(uint8),
+        "uint16" => quote!(uint16),
+        "int128" => quote!(int128),
+        "uint128" => quote!(uint128),
+        "float" => quote!(float),
+        "double" => quote!(double),
+        _ => return quote!(::compactr::SchemaType::custom(#format)),
+    };
+    quote!(::compactr::SchemaType::#ctor())
+}
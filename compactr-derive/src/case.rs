@@ -0,0 +1,129 @@
+//! Identifier case conversion for `rename_all`.
+
+use syn::LitStr;
+
+/// Naming convention applied to every field by `#[compactr(rename_all = "...")]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameRule {
+    /// `lowercase`
+    Lower,
+    /// `UPPERCASE`
+    Upper,
+    /// `camelCase`
+    Camel,
+    /// `PascalCase`
+    Pascal,
+    /// `snake_case`
+    Snake,
+    /// `SCREAMING_SNAKE_CASE`
+    ScreamingSnake,
+    /// `kebab-case`
+    Kebab,
+    /// `SCREAMING-KEBAB-CASE`
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    /// Parses a rule from its attribute spelling.
+    pub fn from_lit(lit: &LitStr) -> syn::Result<Self> {
+        Ok(match lit.value().as_str() {
+            "lowercase" => Self::Lower,
+            "UPPERCASE" => Self::Upper,
+            "camelCase" => Self::Camel,
+            "PascalCase" => Self::Pascal,
+            "snake_case" => Self::Snake,
+            "SCREAMING_SNAKE_CASE" => Self::ScreamingSnake,
+            "kebab-case" => Self::Kebab,
+            "SCREAMING-KEBAB-CASE" => Self::ScreamingKebab,
+            other => {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    format!("unknown rename_all rule: {other:?}"),
+                ))
+            }
+        })
+    }
+
+    /// Applies the rule to a `snake_case` field or `PascalCase` variant name.
+    pub fn apply(self, name: &str) -> String {
+        let words = split_words(name);
+        match self {
+            Self::Lower => words.concat(),
+            Self::Upper => words.concat().to_uppercase(),
+            Self::Camel => {
+                let mut out = String::new();
+                for (i, word) in words.iter().enumerate() {
+                    if i == 0 {
+                        out.push_str(word);
+                    } else {
+                        out.push_str(&capitalize(word));
+                    }
+                }
+                out
+            }
+            Self::Pascal => words.iter().map(|w| capitalize(w)).collect(),
+            Self::Snake => words.join("_"),
+            Self::ScreamingSnake => words.join("_").to_uppercase(),
+            Self::Kebab => words.join("-"),
+            Self::ScreamingKebab => words.join("-").to_uppercase(),
+        }
+    }
+}
+
+/// Splits an identifier into lowercase words on underscores and
+/// lower-to-upper case transitions.
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+
+    for c in name.chars() {
+        if c == '_' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_names() {
+        let name = "created_at_ms";
+        assert_eq!(RenameRule::Camel.apply(name), "createdAtMs");
+        assert_eq!(RenameRule::Pascal.apply(name), "CreatedAtMs");
+        assert_eq!(RenameRule::ScreamingSnake.apply(name), "CREATED_AT_MS");
+        assert_eq!(RenameRule::Kebab.apply(name), "created-at-ms");
+        assert_eq!(RenameRule::Lower.apply(name), "createdatms");
+    }
+
+    #[test]
+    fn test_variant_names() {
+        let name = "HttpRequest";
+        assert_eq!(RenameRule::Snake.apply(name), "http_request");
+        assert_eq!(RenameRule::Camel.apply(name), "httpRequest");
+        assert_eq!(RenameRule::ScreamingKebab.apply(name), "HTTP-REQUEST");
+        assert_eq!(RenameRule::Upper.apply(name), "HTTPREQUEST");
+    }
+}
@@ -0,0 +1,240 @@
+//! Expansion of `#[derive(Compactr)]` for enums.
+//!
+//! Enums made only of unit variants map to a string enumeration stored as a
+//! 1-byte index. Enums with data map to a `oneOf` schema whose discriminator
+//! property (`tag`, `"type"` by default) names the variant: unit variants
+//! carry no payload, newtype variants use the inner type's schema and struct
+//! variants an object schema of their fields.
+
+use crate::attr::{ContainerAttrs, VariantAttrs};
+use crate::fields::{decode_object, encode_object, object_schema, parse_fields, Field};
+use crate::generics::{decode_impls, with_bound, Mode};
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{DataEnum, DeriveInput, Fields, Type};
+
+/// Default discriminator property of `oneOf` schemas.
+const DEFAULT_TAG: &str = "type";
+
+enum Shape<'a> {
+    Unit,
+    Newtype(&'a Type),
+    Struct(Vec<Field<'a>>),
+}
+
+struct Variant<'a> {
+    ident: &'a Ident,
+    wire_name: String,
+    shape: Shape<'a>,
+}
+
+pub fn expand(
+    input: &DeriveInput,
+    data: &DataEnum,
+    container: &ContainerAttrs,
+) -> syn::Result<TokenStream> {
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Compactr cannot be derived for enums without variants",
+        ));
+    }
+
+    let mut variants = Vec::new();
+    for variant in &data.variants {
+        let attrs = VariantAttrs::parse(&variant.attrs)?;
+        let name = variant.ident.to_string();
+        let wire_name = match (attrs.rename, container.rename_all) {
+            (Some(rename), _) => rename,
+            (None, Some(rule)) => rule.apply(&name),
+            (None, None) => name,
+        };
+        let shape = match &variant.fields {
+            Fields::Unit => Shape::Unit,
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                Shape::Newtype(&fields.unnamed[0].ty)
+            }
+            Fields::Unnamed(_) => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "Compactr enum variants must be unit, newtype or struct variants",
+                ))
+            }
+            Fields::Named(fields) => Shape::Struct(parse_fields(&fields.named, None)?),
+        };
+        variants.push(Variant {
+            ident: &variant.ident,
+            wire_name,
+            shape,
+        });
+    }
+
+    let schema = expand_schema(input, &variants, container);
+    let encode = expand_encode(input, &variants);
+    let decode = expand_decode(input, &variants);
+    Ok(quote! {
+        #schema
+        #encode
+        #decode
+    })
+}
+
+fn expand_schema(
+    input: &DeriveInput,
+    variants: &[Variant],
+    container: &ContainerAttrs,
+) -> TokenStream {
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, &quote!(::compactr::Schema));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let all_unit = variants.iter().all(|v| matches!(v.shape, Shape::Unit));
+    let body = if all_unit && container.tag.is_none() {
+        let names = variants.iter().map(|v| &v.wire_name);
+        quote!(::compactr::SchemaType::string_enum([#(#names),*]))
+    } else {
+        let tag = container
+            .tag
+            .as_ref()
+            .map_or_else(|| DEFAULT_TAG.to_owned(), syn::LitStr::value);
+        let entries = variants.iter().map(|v| {
+            let wire_name = &v.wire_name;
+            let payload = match &v.shape {
+                Shape::Unit => quote!(::compactr::SchemaType::null()),
+                Shape::Newtype(ty) => quote!(<#ty as ::compactr::Schema>::schema()),
+                Shape::Struct(fields) => object_schema(fields),
+            };
+            quote!((::std::borrow::ToOwned::to_owned(#wire_name), #payload))
+        });
+        quote! {
+            ::compactr::SchemaType::one_of(
+                #tag,
+                ::std::iter::IntoIterator::into_iter([#(#entries),*]).collect(),
+            )
+        }
+    };
+
+    quote! {
+        impl #impl_generics ::compactr::Schema for #name #ty_generics #where_clause {
+            fn schema() -> ::compactr::SchemaType {
+                #body
+            }
+        }
+    }
+}
+
+fn expand_encode(input: &DeriveInput, variants: &[Variant]) -> TokenStream {
+    let name = &input.ident;
+    let generics = with_bound(&input.generics, &quote!(::compactr::Encode));
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let arms = variants.iter().map(|v| {
+        let ident = v.ident;
+        let wire_name = &v.wire_name;
+        let tag = quote!(::compactr::Encoder::encode_variant(buf, schema, #wire_name)?);
+        let payload = quote! {
+            #tag.ok_or_else(|| ::compactr::__private::missing_payload(#wire_name))?
+        };
+        match &v.shape {
+            Shape::Unit => quote! {
+                Self::#ident => {
+                    #tag;
+                    ::std::result::Result::Ok(())
+                }
+            },
+            Shape::Newtype(_) => quote! {
+                Self::#ident(value) => {
+                    let schema = #payload;
+                    ::compactr::Encoder::encode_into(buf, value, schema, registry)
+                }
+            },
+            Shape::Struct(fields) => {
+                let bindings = fields.iter().filter(|f| !f.attrs.skip).map(|f| {
+                    let ident = f.ident;
+                    let slot = f.slot();
+                    quote!(#ident: #slot)
+                });
+                let body = encode_object(fields, |f| {
+                    let slot = f.slot();
+                    quote!(#slot)
+                });
+                quote! {
+                    Self::#ident { #(#bindings,)* .. } => {
+                        let schema = #payload;
+                        #body
+                    }
+                }
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics ::compactr::Encode for #name #ty_generics #where_clause {
+            fn encode_with_schema(
+                &self,
+                buf: &mut ::compactr::__private::BytesMut,
+                schema: &::compactr::SchemaType,
+                registry: &::compactr::SchemaRegistry,
+            ) -> ::compactr::Result<()> {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+    }
+}
+
+fn expand_decode(input: &DeriveInput, variants: &[Variant]) -> TokenStream {
+    // Enumerations of unit variants never look at the payload schema
+    let payload = if variants.iter().all(|v| matches!(v.shape, Shape::Unit)) {
+        quote!(_)
+    } else {
+        quote!(payload)
+    };
+
+    decode_impls(input, |mode| {
+        let arms = variants.iter().map(|v| decode_arm(v, mode));
+        quote! {
+            let (variant, #payload) = ::compactr::Decoder::decode_variant(buf, schema)?;
+            match variant {
+                #(#arms)*
+                other => ::std::result::Result::Err(
+                    ::compactr::__private::unknown_variant(other),
+                ),
+            }
+        }
+    })
+}
+
+fn decode_arm(variant: &Variant, mode: Mode) -> TokenStream {
+    let ident = variant.ident;
+    let wire_name = &variant.wire_name;
+    let payload = quote! {
+        payload.ok_or_else(|| ::compactr::__private::missing_payload(#wire_name))?
+    };
+    match &variant.shape {
+        Shape::Unit => quote! {
+            #wire_name => ::std::result::Result::Ok(Self::#ident),
+        },
+        Shape::Newtype(_) => {
+            let decode = mode.decode();
+            quote! {
+                #wire_name => {
+                    let schema = #payload;
+                    ::std::result::Result::Ok(Self::#ident(#decode(buf, schema, registry)?))
+                }
+            }
+        }
+        Shape::Struct(fields) => {
+            let body = decode_object(fields, &quote!(Self::#ident), mode);
+            quote! {
+                #wire_name => {
+                    let schema = #payload;
+                    #body
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! Code generation shared by structs and struct-like enum variants.
+
+use crate::attr::{format_schema, FieldAttrs};
+use crate::case::RenameRule;
+use crate::generics::Mode;
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::punctuated::Punctuated;
+use syn::token::Comma;
+use syn::{Index, Type};
+
+/// A named field as it appears on the wire.
+pub struct Field<'a> {
+    pub ident: &'a Ident,
+    pub ty: &'a Type,
+    pub wire_name: String,
+    pub attrs: FieldAttrs,
+}
+
+impl Field<'_> {
+    /// Local variable holding the value of this field in generated code.
+    pub fn slot(&self) -> Ident {
+        format_ident!("__{}", self.ident.unraw())
+    }
+}
+
+/// Parses named fields, applying `rename` and the container's `rename_all`.
+pub fn parse_fields(
+    named: &Punctuated<syn::Field, Comma>,
+    rename_all: Option<RenameRule>,
+) -> syn::Result<Vec<Field<'_>>> {
+    named
+        .iter()
+        .map(|field| {
+            let ident = field.ident.as_ref().expect("named field");
+            let attrs = FieldAttrs::parse(&field.attrs)?;
+            let name = ident.unraw().to_string();
+            let wire_name = match (&attrs.rename, rename_all) {
+                (Some(rename), _) => rename.clone(),
+                (None, Some(rule)) => rule.apply(&name),
+                (None, None) => name,
+            };
+            Ok(Field {
+                ident,
+                ty: &field.ty,
+                wire_name,
+                attrs,
+            })
+        })
+        .collect()
+}
+
+/// Builds the object schema expression for the fields.
+pub fn object_schema(fields: &[Field]) -> TokenStream {
+    let property = |f: &Field| {
+        let wire_name = &f.wire_name;
+        let ty = f.ty;
+        let schema_type = match (&f.attrs.with, &f.attrs.format) {
+            (Some(with), _) => quote!(#with::schema()),
+            (None, Some(format)) => format_schema(format),
+            (None, None) => quote!(<#ty as ::compactr::Schema>::schema()),
+        };
+        let schema_type = if f.attrs.run_length {
+            quote!(::compactr::__private::run_length(#schema_type))
+        } else {
+            schema_type
+        };
+        let required = if f.attrs.default {
+            quote!(false)
+        } else if f.attrs.with.is_some() {
+            quote!(true)
+        } else {
+            quote!(!<#ty as ::compactr::Schema>::OPTIONAL)
+        };
+        let size_width = f
+            .attrs
+            .size_width
+            .clone()
+            .unwrap_or_else(|| format_ident!("Auto"));
+        let sensitive = f.attrs.sensitive;
+        let aliases = &f.attrs.aliases;
+        quote! {
+            (
+                ::std::borrow::ToOwned::to_owned(#wire_name),
+                ::compactr::Property {
+                    schema_type: #schema_type,
+                    required: #required,
+                    size_width: ::compactr::SizeWidth::#size_width,
+                    sensitive: #sensitive,
+                    aliases: ::std::vec![#(::std::borrow::ToOwned::to_owned(#aliases)),*],
+                    metadata: ::compactr::schema::Metadata::new(),
+                },
+            )
+        }
+    };
+
+    if !fields.iter().any(|f| f.attrs.flatten) {
+        let properties = encoded(fields).map(property);
+        return quote! {
+            ::compactr::SchemaType::object([#(#properties),*])
+        };
+    }
+
+    // Flattened properties take the place of the field, in declaration order
+    let extends = encoded(fields).map(|f| {
+        if f.attrs.flatten {
+            let ty = f.ty;
+            quote!(properties.extend(::compactr::__private::flattened_properties::<#ty>());)
+        } else {
+            let property = property(f);
+            quote!(properties.push(#property);)
+        }
+    });
+    quote! {{
+        let mut properties = ::std::vec::Vec::new();
+        #(#extends)*
+        ::compactr::SchemaType::object(properties)
+    }}
+}
+
+/// Encodes the fields as an object into `buf` with `schema`.
+///
+/// `access` maps a field to an expression borrowing its value.
+pub fn encode_object(fields: &[Field], access: impl Fn(&Field) -> TokenStream) -> TokenStream {
+    if fields.iter().any(|f| f.attrs.flatten) {
+        let collect = collect_fields(fields, access);
+        return quote! {
+            let mut __fields = ::std::vec::Vec::new();
+            {
+                let fields = &mut __fields;
+                #collect
+            }
+            ::compactr::Encoder::encode_fields(
+                buf,
+                schema,
+                registry,
+                __fields.iter().map(|(name, field)| (*name, field.as_encode())),
+            )
+        };
+    }
+
+    let entries: Vec<TokenStream> = encoded(fields)
+        .map(|f| {
+            let wire_name = &f.wire_name;
+            let value = access(f);
+            let value = match &f.attrs.with {
+                Some(with) => {
+                    quote!(&::compactr::__private::EncodeWith::new(#value, #with::encode))
+                }
+                None => value,
+            };
+            quote!((#wire_name, #value as &dyn ::compactr::Encode))
+        })
+        .collect();
+    let count = entries.len();
+
+    quote! {
+        let fields: [(&str, &dyn ::compactr::Encode); #count] = [#(#entries),*];
+        ::compactr::Encoder::encode_fields(buf, schema, registry, fields)
+    }
+}
+
+/// Pushes the wire name and `FlatField` of every field onto `fields`, a
+/// mutable reference to a vector, expanding flattened fields in place.
+pub fn collect_fields(fields: &[Field], access: impl Fn(&Field) -> TokenStream) -> TokenStream {
+    let pushes = encoded(fields).map(|f| {
+        let value = access(f);
+        let wire_name = &f.wire_name;
+        if f.attrs.flatten {
+            let ty = f.ty;
+            quote!(<#ty as ::compactr::__private::Flatten>::flatten_fields(#value, fields);)
+        } else if let Some(with) = &f.attrs.with {
+            quote! {
+                fields.push((
+                    #wire_name,
+                    ::compactr::__private::FlatField::Owned(::std::boxed::Box::new(
+                        ::compactr::__private::EncodeWith::new(#value, #with::encode),
+                    )),
+                ));
+            }
+        } else {
+            quote!(fields.push((#wire_name, ::compactr::__private::FlatField::Borrowed(#value)));)
+        }
+    });
+    quote!(#(#pushes)*)
+}
+
+/// Decodes an object from `buf` with `schema` and builds `constructor { .. }`.
+pub fn decode_object(fields: &[Field], constructor: &TokenStream, mode: Mode) -> TokenStream {
+    let decode_fields = mode.decode_fields();
+    let init = init_slots(fields, mode);
+    let visit = visit_slots(fields, mode);
+    let finish = finish_slots(fields, constructor, mode);
+
+    quote! {
+        let mut __slots = #init;
+        #decode_fields(buf, schema, registry, |name, schema, buf| {
+            #visit;
+            ::std::result::Result::Ok(())
+        })?;
+        #finish
+    }
+}
+
+/// Type of the decoding slots of the fields: an `Option` per field, or the
+/// `FlattenDecode` slots of flattened fields.
+pub fn slots_type(fields: &[Field], mode: Mode) -> TokenStream {
+    let flatten_trait = mode.flatten_trait();
+    let tys = encoded(fields).map(|f| {
+        let ty = f.ty;
+        if f.attrs.flatten {
+            quote!(<#ty as #flatten_trait>::Slots)
+        } else {
+            quote!(::std::option::Option<#ty>)
+        }
+    });
+    quote!((#(#tys,)*))
+}
+
+/// Expression creating empty slots.
+pub fn init_slots(fields: &[Field], mode: Mode) -> TokenStream {
+    let flatten_trait = mode.flatten_trait();
+    let inits = encoded(fields).map(|f| {
+        let ty = f.ty;
+        if f.attrs.flatten {
+            quote!(<#ty as #flatten_trait>::slots())
+        } else {
+            quote!(::std::option::Option::None)
+        }
+    });
+    quote!((#(#inits,)*))
+}
+
+/// Expression decoding the property `name` into `__slots`, evaluating to
+/// whether one of the fields claimed it.
+pub fn visit_slots(fields: &[Field], mode: Mode) -> TokenStream {
+    let decode_property = mode.decode_property();
+    let flatten_trait = mode.flatten_trait();
+
+    let mut arms = Vec::new();
+    let mut flattened = Vec::new();
+    for (index, f) in encoded(fields).enumerate() {
+        let index = Index::from(index);
+        let ty = f.ty;
+        if f.attrs.flatten {
+            flattened.push(quote! {
+                <#ty as #flatten_trait>::visit(&mut __slots.#index, name, schema, buf, registry)?
+            });
+        } else {
+            let wire_name = &f.wire_name;
+            let decode = if let Some(with) = &f.attrs.with {
+                quote!(::compactr::__private::decode_with(buf, schema, registry, #with::decode)?)
+            } else {
+                quote!(#decode_property(buf, schema, registry)?)
+            };
+            arms.push(quote! {
+                #wire_name => {
+                    __slots.#index = ::std::option::Option::Some(#decode);
+                    true
+                }
+            });
+        }
+    }
+
+    quote! {
+        match name {
+            #(#arms)*
+            _ => #(#flattened ||)* false,
+        }
+    }
+}
+
+/// Builds `constructor { .. }` from `__slots`, failing on missing required
+/// properties.
+pub fn finish_slots(fields: &[Field], constructor: &TokenStream, mode: Mode) -> TokenStream {
+    let decode_trait = mode.trait_path();
+    let flatten_trait = mode.flatten_trait();
+
+    let slots: Vec<Ident> = encoded(fields).map(Field::slot).collect();
+    let inits = fields.iter().map(|f| {
+        let ident = f.ident;
+        let ty = f.ty;
+        if f.attrs.skip {
+            return quote!(#ident: ::std::default::Default::default());
+        }
+        let slot = f.slot();
+        if f.attrs.flatten {
+            return quote!(#ident: <#ty as #flatten_trait>::finish(#slot)?);
+        }
+        let wire_name = &f.wire_name;
+        let missing = if f.attrs.default {
+            quote!(::std::default::Default::default())
+        } else if f.attrs.with.is_some() {
+            quote! {
+                return ::std::result::Result::Err(
+                    ::compactr::SchemaError::MissingField(
+                        ::std::borrow::ToOwned::to_owned(#wire_name),
+                    )
+                    .into(),
+                )
+            }
+        } else {
+            quote! {
+                <#ty as #decode_trait>::absent().ok_or_else(|| {
+                    ::compactr::SchemaError::MissingField(
+                        ::std::borrow::ToOwned::to_owned(#wire_name),
+                    )
+                })?
+            }
+        };
+        quote! {
+            #ident: match #slot {
+                ::std::option::Option::Some(value) => value,
+                ::std::option::Option::None => #missing,
+            }
+        }
+    });
+
+    quote! {
+        let (#(#slots,)*) = __slots;
+        ::std::result::Result::Ok(#constructor {
+            #(#inits),*
+        })
+    }
+}
+
+/// Fields that are part of the wire representation.
+fn encoded<'a, 'f>(fields: &'a [Field<'f>]) -> impl Iterator<Item = &'a Field<'f>> + Clone {
+    fields.iter().filter(|f| !f.attrs.skip)
+}
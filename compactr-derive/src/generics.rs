@@ -0,0 +1,172 @@
+//! Trait bounds and the decode impls of generic types.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, DeriveInput, Generics, Ident, Lifetime};
+
+/// Which decoding trait generated code targets.
+#[derive(Clone, Copy)]
+pub enum Mode {
+    /// `Decode`, reading from any `Buf`
+    Owned,
+    /// `DecodeBorrowed<'__de>`, reading from a `&'__de [u8]`
+    Borrowed,
+}
+
+impl Mode {
+    /// `Decoder` function decoding a top-level value.
+    pub fn decode(self) -> TokenStream {
+        match self {
+            Self::Owned => quote!(::compactr::Decoder::decode_as),
+            Self::Borrowed => quote!(::compactr::Decoder::decode_borrowed),
+        }
+    }
+
+    /// `Decoder` function decoding an object property.
+    pub fn decode_property(self) -> TokenStream {
+        match self {
+            Self::Owned => quote!(::compactr::Decoder::decode_property_as),
+            Self::Borrowed => quote!(::compactr::Decoder::decode_property_borrowed),
+        }
+    }
+
+    /// `Decoder` function walking object properties.
+    pub fn decode_fields(self) -> TokenStream {
+        match self {
+            Self::Owned => quote!(::compactr::Decoder::decode_fields),
+            Self::Borrowed => quote!(::compactr::Decoder::decode_fields_borrowed),
+        }
+    }
+
+    /// The decoding trait.
+    pub fn trait_path(self) -> TokenStream {
+        match self {
+            Self::Owned => quote!(::compactr::Decode),
+            Self::Borrowed => quote!(::compactr::DecodeBorrowed<'__de>),
+        }
+    }
+
+    /// Buffer type of a single object property.
+    pub fn property_buf(self) -> TokenStream {
+        match self {
+            Self::Owned => quote!(::compactr::__private::Bytes),
+            Self::Borrowed => quote!(&'__de [u8]),
+        }
+    }
+
+    /// The `FlattenDecode` trait for [`Mode::property_buf`].
+    pub fn flatten_trait(self) -> TokenStream {
+        let buf = self.property_buf();
+        quote!(::compactr::__private::FlattenDecode<#buf>)
+    }
+
+    /// Generics of the impls of `input` for this mode, or `None` if the type
+    /// cannot be decoded this way.
+    ///
+    /// Type parameters are bounded by the decoding trait. Borrowed impls
+    /// introduce `'__de`, outliving every lifetime of the type; owned
+    /// decoding cannot produce borrowed fields, so types with lifetimes only
+    /// get borrowed impls.
+    fn generics(self, input: &DeriveInput) -> Option<Generics> {
+        let mut generics = with_bound(&input.generics, &self.trait_path());
+        match self {
+            Self::Owned => input
+                .generics
+                .lifetimes()
+                .next()
+                .is_none()
+                .then_some(generics),
+            Self::Borrowed => {
+                let lifetimes: Vec<Lifetime> = input
+                    .generics
+                    .lifetimes()
+                    .map(|l| l.lifetime.clone())
+                    .collect();
+                generics.params.insert(0, parse_quote!('__de));
+                let where_clause = generics.make_where_clause();
+                for lifetime in lifetimes {
+                    where_clause.predicates.push(parse_quote!('__de: #lifetime));
+                }
+                Some(generics)
+            }
+        }
+    }
+}
+
+/// Adds `bound` to every type parameter.
+pub fn with_bound(generics: &Generics, bound: &TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<Ident> = generics.type_params().map(|p| p.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for ident in params {
+        where_clause.predicates.push(parse_quote!(#ident: #bound));
+    }
+    generics
+}
+
+/// Emits the decode impls of `input` around a body generated per [`Mode`].
+pub fn decode_impls(input: &DeriveInput, body: impl Fn(Mode) -> TokenStream) -> TokenStream {
+    let name = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let owned = Mode::Owned.generics(input).map(|generics| {
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
+        let body = body(Mode::Owned);
+        quote! {
+            impl #impl_generics ::compactr::Decode for #name #ty_generics #where_clause {
+                fn decode_with_schema(
+                    buf: &mut impl ::compactr::__private::Buf,
+                    schema: &::compactr::SchemaType,
+                    registry: &::compactr::SchemaRegistry,
+                ) -> ::compactr::Result<Self> {
+                    #body
+                }
+            }
+        }
+    });
+
+    let generics = Mode::Borrowed
+        .generics(input)
+        .expect("borrowed impls always apply");
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let body = body(Mode::Borrowed);
+
+    quote! {
+        #owned
+
+        impl #impl_generics ::compactr::DecodeBorrowed<'__de> for #name #ty_generics #where_clause {
+            fn decode_borrowed(
+                buf: &mut &'__de [u8],
+                schema: &::compactr::SchemaType,
+                registry: &::compactr::SchemaRegistry,
+            ) -> ::compactr::Result<Self> {
+                #body
+            }
+        }
+    }
+}
+
+/// Emits the `FlattenDecode` impls of `input` around items generated per
+/// [`Mode`].
+pub fn flatten_decode_impls(
+    input: &DeriveInput,
+    items: impl Fn(Mode) -> TokenStream,
+) -> TokenStream {
+    let name = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    [Mode::Owned, Mode::Borrowed]
+        .into_iter()
+        .filter_map(|mode| {
+            let generics = mode.generics(input)?;
+            let (impl_generics, _, where_clause) = generics.split_for_impl();
+            let flatten_trait = mode.flatten_trait();
+            let items = items(mode);
+            Some(quote! {
+                impl #impl_generics #flatten_trait for #name #ty_generics #where_clause {
+                    #items
+                }
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,212 @@
+//! Derive macros for [Compactr](https://docs.rs/compactr).
+//!
+//! Use through the `derive` feature of the `compactr` crate:
+//!
+//! ```rust,ignore
+//! use compactr::Compactr;
+//!
+//! #[derive(Compactr)]
+//! #[compactr(rename_all = "camelCase")]
+//! struct User {
+//!     #[compactr(format = "uuid")]
+//!     id: String,
+//!     display_name: String,
+//!     #[compactr(default)]
+//!     login_count: i32,
+//!     #[compactr(skip)]
+//!     cached: Option<String>,
+//! }
+//!
+//! #[derive(Compactr)]
+//! #[compactr(tag = "kind", rename_all = "snake_case")]
+//! enum Shape {
+//!     Empty,
+//!     Circle { radius: f64 },
+//!     Polygon(Polygon),
+//! }
+//! ```
+//!
+//! The derive implements `Schema`, `Encode` and `Decode`. Structs are laid
+//! out as an object schema with one property per field. Enums of unit
+//! variants become a string enumeration; enums with data become a `oneOf`
+//! schema with a discriminator property.
+//!
+//! Type parameters get the derived trait as a bound. Every type also
+//! implements `DecodeBorrowed`, so fields like `&'a str` or `Cow<'a, str>`
+//! can borrow from the input; types with lifetimes only implement that one.
+//!
+//! ## Attributes
+//!
+//! Container:
+//! - `rename_all = "..."`: renames every struct field or enum variant
+//!   (`camelCase`, `PascalCase`, `snake_case`, `SCREAMING_SNAKE_CASE`,
+//!   `kebab-case`, `SCREAMING-KEBAB-CASE`, `lowercase`, `UPPERCASE`)
+//! - `tag = "..."`: discriminator property of enums with data (`"type"` by
+//!   default); also turns an enum of unit variants into a `oneOf`
+//!
+//! Variant:
+//! - `rename = "..."`: wire name of the variant
+//!
+//! Field:
+//! - `rename = "..."`: wire name of the property
+//! - `skip`: leaves the field out of the schema; decoding uses `Default`
+//! - `default`: makes the property optional, decoding to `Default` when missing
+//! - `format = "..."`: overrides the field schema with an `OpenAPI` format
+//!   (`uuid`, `date-time`, `timestamp-millis`, `ipv4`, ...), `long` for
+//!   strings over 64 KiB, or a registered custom format
+//! - `flatten`: inlines the properties of a struct that derives `Compactr`
+//!   into the parent object instead of nesting it
+//! - `run_length`: stores an array field as runs of repeated items
+//!   (`SchemaType::RunLengthArray`)
+//! - `size_width = "u8" | "u16" | "u32"`: frames the field with a fixed-width
+//!   size instead of the compactr.js header, which holds at most 255 bytes
+//! - `sensitive`: marks the property as sensitive, so redaction replaces its
+//!   value with a placeholder
+//! - `alias = "..."`: a name the property was previously known by, so a field
+//!   can be renamed without breaking stored data; the first alias keeps the
+//!   property's index. Repeatable, oldest name first
+//! - `with = "module"`: encodes the field with the module's `schema`,
+//!   `encode` and `decode` functions, for types without `Compactr` impls:
+//!
+//! ```rust,ignore
+//! mod module {
+//!     pub fn schema() -> SchemaType;
+//!     pub fn encode(value: &T, buf: &mut BytesMut, schema: &SchemaType, registry: &SchemaRegistry) -> Result<()>;
+//!     pub fn decode(buf: &mut impl Buf, schema: &SchemaType, registry: &SchemaRegistry) -> Result<T>;
+//! }
+//! ```
+
+#![warn(missing_docs)]
+#![warn(clippy::all)]
+#![warn(clippy::pedantic)]
+
+mod attr;
+mod case;
+mod enums;
+mod fields;
+mod generics;
+
+use attr::ContainerAttrs;
+use fields::{
+    collect_fields, decode_object, encode_object, finish_slots, init_slots, object_schema,
+    parse_fields, slots_type, visit_slots, Field,
+};
+use generics::{decode_impls, flatten_decode_impls, with_bound};
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields};
+
+/// Derives `Schema`, `Encode` and `Decode` for a struct with named fields
+/// or an enum.
+#[proc_macro_derive(Compactr, attributes(compactr))]
+pub fn derive_compactr(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let container = ContainerAttrs::parse(&input.attrs)?;
+    match &input.data {
+        Data::Struct(data) => {
+            if let Some(tag) = &container.tag {
+                return Err(syn::Error::new_spanned(tag, "`tag` only applies to enums"));
+            }
+            match &data.fields {
+                Fields::Named(named) => {
+                    let fields = parse_fields(&named.named, container.rename_all)?;
+                    Ok(expand_struct(input, &fields))
+                }
+                _ => Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Compactr can only be derived for structs with named fields",
+                )),
+            }
+        }
+        Data::Enum(data) => enums::expand(input, data, &container),
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input.ident,
+            "Compactr cannot be derived for unions",
+        )),
+    }
+}
+
+fn expand_struct(input: &DeriveInput, fields: &[Field]) -> TokenStream2 {
+    let name = &input.ident;
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let schema_generics = with_bound(&input.generics, &quote!(::compactr::Schema));
+    let (schema_impl_generics, _, schema_where_clause) = schema_generics.split_for_impl();
+    let encode_generics = with_bound(&input.generics, &quote!(::compactr::Encode));
+    let (encode_impl_generics, _, encode_where_clause) = encode_generics.split_for_impl();
+
+    let schema = object_schema(fields);
+    let access = |f: &Field| {
+        let ident = f.ident;
+        quote!(&self.#ident)
+    };
+    let encode = encode_object(fields, access);
+    let flatten_fields = collect_fields(fields, access);
+    let decode = decode_impls(input, |mode| decode_object(fields, &quote!(Self), mode));
+    let flatten_decode = flatten_decode_impls(input, |mode| {
+        let slots = slots_type(fields, mode);
+        let init = init_slots(fields, mode);
+        let visit = visit_slots(fields, mode);
+        let finish = finish_slots(fields, &quote!(Self), mode);
+        let buf = mode.property_buf();
+        quote! {
+            type Slots = #slots;
+
+            fn slots() -> Self::Slots {
+                #init
+            }
+
+            fn visit(
+                __slots: &mut Self::Slots,
+                name: &str,
+                schema: &::compactr::SchemaType,
+                buf: &mut #buf,
+                registry: &::compactr::SchemaRegistry,
+            ) -> ::compactr::Result<bool> {
+                ::std::result::Result::Ok(#visit)
+            }
+
+            fn finish(__slots: Self::Slots) -> ::compactr::Result<Self> {
+                #finish
+            }
+        }
+    });
+
+    quote! {
+        impl #schema_impl_generics ::compactr::Schema for #name #ty_generics #schema_where_clause {
+            fn schema() -> ::compactr::SchemaType {
+                #schema
+            }
+        }
+
+        impl #encode_impl_generics ::compactr::Encode for #name #ty_generics #encode_where_clause {
+            fn encode_with_schema(
+                &self,
+                buf: &mut ::compactr::__private::BytesMut,
+                schema: &::compactr::SchemaType,
+                registry: &::compactr::SchemaRegistry,
+            ) -> ::compactr::Result<()> {
+                #encode
+            }
+        }
+
+        impl #encode_impl_generics ::compactr::__private::Flatten for #name #ty_generics #encode_where_clause {
+            fn flatten_fields<'__a>(
+                &'__a self,
+                fields: &mut ::std::vec::Vec<(&'__a str, ::compactr::__private::FlatField<'__a>)>,
+            ) {
+                #flatten_fields
+            }
+        }
+
+        #decode
+        #flatten_decode
+    }
+}
@@ -0,0 +1,271 @@
+//! Conversions between Python objects and [`Value`]s.
+//!
+//! Python objects are converted following the schema, so native objects
+//! (`uuid.UUID`, `datetime`, `bytes`, ...) and their JSON string forms are
+//! both accepted. Decoded values are always returned as native objects.
+
+use crate::py_err;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
+use compactr::schema::{IntegerFormat, NumberFormat, Property, StringFormat};
+use compactr::{EncodeError, SchemaError, SchemaRegistry, SchemaType, Value};
+use indexmap::IndexMap;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{
+    PyBool, PyByteArray, PyBytes, PyDateTime, PyDict, PyFloat, PyInt, PyList, PyString, PyTuple,
+};
+use pyo3::IntoPyObjectExt;
+use std::net::IpAddr;
+
+/// Converts a Python object into a [`Value`] matching `schema`.
+pub(crate) fn to_value(
+    obj: &Bound<'_, PyAny>,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> PyResult<Value> {
+    match schema {
+        SchemaType::Integer(format) => Ok(match format {
+            IntegerFormat::Int128 => Value::Int128(obj.extract()?),
+            IntegerFormat::Uint128 => Value::Uint128(obj.extract()?),
+            _ => Value::Integer(obj.extract()?),
+        }),
+        #[allow(clippy::cast_possible_truncation)]
+        SchemaType::Number(NumberFormat::Float) => Ok(Value::Float(obj.extract::<f64>()? as f32)),
+        SchemaType::Number(NumberFormat::Double) => Ok(Value::Double(obj.extract()?)),
+        SchemaType::String(format) if !obj.is_instance_of::<PyString>() => {
+            native_to_value(obj, *format, schema, registry)
+        }
+        SchemaType::Array(items) | SchemaType::RunLengthArray(items) if is_sequence(obj) => obj
+            .try_iter()?
+            .map(|item| to_value(&item?, items, registry))
+            .collect::<PyResult<Vec<_>>>()
+            .map(Value::Array),
+        SchemaType::Object(properties) => object_to_value(obj.cast()?, properties, registry),
+        SchemaType::Reference(name) => {
+            let resolved = registry.resolve_ref(name).map_err(py_err)?;
+            to_value(obj, &resolved, registry)
+        }
+        SchemaType::OneOf {
+            discriminator,
+            variants,
+        } => one_of_to_value(obj.cast()?, discriminator, variants, registry),
+        _ => json_to_value(obj, schema, registry),
+    }
+}
+
+/// Converts a native object for a string schema, e.g. a `uuid.UUID` for a
+/// `uuid` string.
+fn native_to_value(
+    obj: &Bound<'_, PyAny>,
+    format: StringFormat,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> PyResult<Value> {
+    Ok(match format {
+        StringFormat::Uuid => Value::Uuid(obj.extract()?),
+        StringFormat::DateTime | StringFormat::Timestamp(_) => {
+            Value::DateTime(datetime(obj)?.with_timezone(&Utc))
+        }
+        StringFormat::DateTimeTz => Value::DateTimeTz(datetime(obj)?),
+        StringFormat::Date if !obj.is_instance_of::<PyDateTime>() => Value::Date(obj.extract()?),
+        StringFormat::Time => Value::Time(obj.extract()?),
+        StringFormat::Ipv4 | StringFormat::Ipv6 => match obj.extract()? {
+            IpAddr::V4(ip) => Value::Ipv4(ip),
+            IpAddr::V6(ip) => Value::Ipv6(ip),
+        },
+        StringFormat::Binary => {
+            if let Ok(bytes) = obj.cast::<PyBytes>() {
+                Value::Binary(bytes.as_bytes().to_vec())
+            } else {
+                Value::Binary(obj.cast::<PyByteArray>()?.to_vec())
+            }
+        }
+        _ => return json_to_value(obj, schema, registry),
+    })
+}
+
+/// Converts a `datetime`, treating naive ones as UTC.
+fn datetime(obj: &Bound<'_, PyAny>) -> PyResult<DateTime<FixedOffset>> {
+    let dt = obj.cast::<PyDateTime>()?;
+    let offset = dt.call_method0("utcoffset")?;
+    if offset.is_none() {
+        let naive: NaiveDateTime = dt.extract()?;
+        return Ok(naive.and_utc().fixed_offset());
+    }
+
+    // Any tzinfo (zoneinfo, pytz, ...) resolves to a fixed offset for a
+    // given instant
+    let offset: chrono::Duration = offset.extract()?;
+    let offset = i32::try_from(offset.num_seconds())
+        .ok()
+        .and_then(FixedOffset::east_opt)
+        .ok_or_else(|| PyValueError::new_err(format!("Invalid UTC offset: {offset}")))?;
+    let kwargs = PyDict::new(obj.py());
+    kwargs.set_item("tzinfo", obj.py().None())?;
+    let local: NaiveDateTime = dt.call_method("replace", (), Some(&kwargs))?.extract()?;
+    Ok(DateTime::from_naive_utc_and_offset(local - offset, offset))
+}
+
+fn object_to_value(
+    dict: &Bound<'_, PyDict>,
+    properties: &IndexMap<String, Property>,
+    registry: &SchemaRegistry,
+) -> PyResult<Value> {
+    let mut obj = IndexMap::with_capacity(dict.len());
+    for (key, prop) in properties {
+        let Some(item) = dict.get_item(key)? else {
+            continue;
+        };
+        // `None` means "absent" unless the property is explicitly a null type
+        if item.is_none() && prop.schema_type != SchemaType::Null {
+            continue;
+        }
+        obj.insert(
+            key.as_str().into(),
+            to_value(&item, &prop.schema_type, registry)?,
+        );
+    }
+    Ok(Value::Object(obj))
+}
+
+fn one_of_to_value(
+    dict: &Bound<'_, PyDict>,
+    discriminator: &str,
+    variants: &IndexMap<String, SchemaType>,
+    registry: &SchemaRegistry,
+) -> PyResult<Value> {
+    let name: String = dict
+        .get_item(discriminator)?
+        .ok_or_else(|| py_err(SchemaError::MissingField(discriminator.to_owned()).into()))?
+        .extract()?;
+    let payload = variants.get(&name).ok_or_else(|| {
+        py_err(EncodeError::InvalidFormat(format!("Unknown variant: {name}")).into())
+    })?;
+
+    let mut obj = IndexMap::new();
+    obj.insert(discriminator.into(), Value::String(name.clone()));
+    if *payload != SchemaType::Null {
+        if let Value::Object(fields) = to_value(dict.as_any(), payload, registry)? {
+            obj.extend(fields);
+        }
+    }
+    Ok(Value::Object(obj))
+}
+
+/// Converts through the object's JSON form, which covers plain and
+/// formatted strings, enums, booleans and `null`.
+fn json_to_value(
+    obj: &Bound<'_, PyAny>,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> PyResult<Value> {
+    Value::from_json_with_registry(&to_json(obj)?, schema, registry).map_err(py_err)
+}
+
+fn to_json(obj: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    if obj.is_none() {
+        return Ok(serde_json::Value::Null);
+    }
+    if let Ok(b) = obj.cast::<PyBool>() {
+        return Ok(serde_json::Value::Bool(b.is_true()));
+    }
+    if let Ok(s) = obj.cast::<PyString>() {
+        return Ok(serde_json::Value::String(s.to_cow()?.into_owned()));
+    }
+    if obj.is_instance_of::<PyInt>() {
+        if let Ok(i) = obj.extract::<i64>() {
+            return Ok(i.into());
+        }
+        return Ok(obj.extract::<u64>()?.into());
+    }
+    if obj.is_instance_of::<PyFloat>() {
+        let f: f64 = obj.extract()?;
+        return serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .ok_or_else(|| PyValueError::new_err(format!("Cannot convert {f} to JSON")));
+    }
+    if let Ok(dict) = obj.cast::<PyDict>() {
+        let mut map = serde_json::Map::with_capacity(dict.len());
+        for (key, value) in dict {
+            map.insert(key.extract()?, to_json(&value)?);
+        }
+        return Ok(serde_json::Value::Object(map));
+    }
+    if is_sequence(obj) {
+        return obj
+            .try_iter()?
+            .map(|item| to_json(&item?))
+            .collect::<PyResult<_>>()
+            .map(serde_json::Value::Array);
+    }
+    // `decimal.Decimal` and other numeric types round-trip through their
+    // string form
+    let decimal = obj.py().import("decimal")?.getattr("Decimal")?;
+    if obj.is_instance(&decimal)? {
+        return Ok(serde_json::Value::String(obj.str()?.to_cow()?.into_owned()));
+    }
+    Err(PyTypeError::new_err(format!(
+        "Cannot convert {} to a compactr value",
+        obj.get_type().name()?
+    )))
+}
+
+fn is_sequence(obj: &Bound<'_, PyAny>) -> bool {
+    obj.is_instance_of::<PyList>() || obj.is_instance_of::<PyTuple>()
+}
+
+/// Converts a decoded [`Value`] into a native Python object.
+pub(crate) fn to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    match value {
+        Value::Boolean(b) => b.into_bound_py_any(py),
+        Value::Integer(i) => i.into_bound_py_any(py),
+        Value::Int128(i) => i.into_bound_py_any(py),
+        Value::Uint128(u) => u.into_bound_py_any(py),
+        Value::Float(f) => f64::from(*f).into_bound_py_any(py),
+        Value::Double(d) => d.into_bound_py_any(py),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(d) => d.into_bound_py_any(py),
+        Value::String(s) => s.into_bound_py_any(py),
+        Value::Uuid(u) => u.into_bound_py_any(py),
+        Value::DateTime(dt) => dt.into_bound_py_any(py),
+        Value::DateTimeTz(dt) => dt.into_bound_py_any(py),
+        Value::Date(d) => d.into_bound_py_any(py),
+        Value::Time(t) => t.into_bound_py_any(py),
+        Value::GeoPoint(p) => {
+            let dict = PyDict::new(py);
+            dict.set_item("lat", p.lat())?;
+            dict.set_item("lon", p.lon())?;
+            Ok(dict.into_any())
+        }
+        Value::Ipv4(ip) => ip.into_bound_py_any(py),
+        Value::Ipv6(ip) => ip.into_bound_py_any(py),
+        Value::SocketAddr(addr) => addr.to_string().into_bound_py_any(py),
+        Value::Binary(data) => Ok(PyBytes::new(py, data).into_any()),
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| to_py(py, item))
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(PyList::new(py, items)?.into_any())
+        }
+        Value::Object(obj) => {
+            let dict = PyDict::new(py);
+            for (key, value) in obj {
+                dict.set_item(&**key, to_py(py, value)?)?;
+            }
+            Ok(dict.into_any())
+        }
+        Value::Custom(custom) => to_py(py, custom.repr()),
+        Value::Null => Ok(py.None().into_bound(py)),
+        // Decimals, when `compactr/decimal` is enabled without this crate's
+        // `decimal` feature, become `decimal.Decimal` through their string
+        // form
+        #[allow(unreachable_patterns)]
+        other => match serde_json::to_value(other) {
+            Ok(serde_json::Value::String(s)) => {
+                py.import("decimal")?.getattr("Decimal")?.call1((s,))
+            }
+            _ => Err(PyTypeError::new_err("Cannot convert value to Python")),
+        },
+    }
+}
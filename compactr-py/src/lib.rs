@@ -0,0 +1,262 @@
+//! Python bindings for compactr.
+//!
+//! Schemas are described in `OpenAPI` JSON, given either as a `dict` or as a
+//! JSON string. Values are converted to and from native Python objects:
+//! `uuid.UUID`, `datetime.datetime`/`date`/`time`, `ipaddress` addresses and
+//! `bytes` map to their compactr formats, and strings are accepted wherever
+//! their JSON form would be.
+//!
+//! ```python
+//! import compactr
+//!
+//! registry = compactr.Registry()
+//! registry.register_openapi(spec)
+//! data = registry.encode("User", {"id": uuid.uuid4(), "name": "Alice"})
+//! user = registry.decode("User", data)
+//! ```
+
+#![warn(clippy::all)]
+#![warn(clippy::pedantic)]
+
+mod convert;
+
+use compactr::{Decoder, Encoder, SchemaError, SchemaRegistry, SchemaType};
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyString};
+
+create_exception!(
+    compactr,
+    CompactrError,
+    PyValueError,
+    "Raised when a schema is invalid or a value cannot be encoded or decoded."
+);
+
+/// Converts a compactr error into a Python [`CompactrError`].
+// Takes the error by value to be usable with `map_err`
+#[allow(clippy::needless_pass_by_value)]
+pub(crate) fn py_err(err: compactr::error::Error) -> PyErr {
+    CompactrError::new_err(err.to_string())
+}
+
+/// A set of named schemas, referenced from other schemas as
+/// `#/components/schemas/<name>`.
+#[pyclass(name = "Registry", module = "compactr", frozen)]
+pub struct Registry {
+    inner: SchemaRegistry,
+}
+
+#[pymethods]
+impl Registry {
+    #[new]
+    fn new() -> Self {
+        Self {
+            inner: SchemaRegistry::new(),
+        }
+    }
+
+    /// Registers a JSON schema under `name`.
+    fn register(&self, name: &str, schema: &Bound<'_, PyAny>) -> PyResult<()> {
+        let schema = parse_schema(schema)?;
+        self.inner.register(name, schema).map_err(py_err)
+    }
+
+    /// Registers the schemas of an `OpenAPI` document, or of its
+    /// `components.schemas` object.
+    fn register_openapi(&self, spec: &Bound<'_, PyAny>) -> PyResult<()> {
+        let json = to_json(spec)?;
+        self.inner.register_json_schemas(&json).map_err(py_err)
+    }
+
+    /// Encodes `obj` with the schema registered as `name`.
+    fn encode<'py>(
+        &self,
+        py: Python<'py>,
+        name: &str,
+        obj: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let schema = self.schema(name)?;
+        encode_with(py, &schema, obj, &self.inner)
+    }
+
+    /// Decodes `data` with the schema registered as `name`.
+    fn decode<'py>(&self, py: Python<'py>, name: &str, data: &[u8]) -> PyResult<Bound<'py, PyAny>> {
+        let schema = self.schema(name)?;
+        decode_with(py, &schema, data, &self.inner)
+    }
+}
+
+impl Registry {
+    fn schema(&self, name: &str) -> PyResult<SchemaType> {
+        self.inner
+            .get(name)
+            .map_err(py_err)?
+            .ok_or_else(|| py_err(SchemaError::UnresolvedReference(name.to_owned()).into()))
+    }
+}
+
+/// Encodes `obj` with a standalone JSON schema.
+#[pyfunction]
+fn encode<'py>(
+    py: Python<'py>,
+    schema: &Bound<'py, PyAny>,
+    obj: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let schema = parse_schema(schema)?;
+    encode_with(py, &schema, obj, &SchemaRegistry::new())
+}
+
+/// Decodes `data` with a standalone JSON schema.
+#[pyfunction]
+fn decode<'py>(
+    py: Python<'py>,
+    schema: &Bound<'py, PyAny>,
+    data: &[u8],
+) -> PyResult<Bound<'py, PyAny>> {
+    let schema = parse_schema(schema)?;
+    decode_with(py, &schema, data, &SchemaRegistry::new())
+}
+
+fn encode_with<'py>(
+    py: Python<'py>,
+    schema: &SchemaType,
+    obj: &Bound<'py, PyAny>,
+    registry: &SchemaRegistry,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let value = convert::to_value(obj, schema, registry)?;
+    let mut encoder = Encoder::new();
+    encoder
+        .encode_with_registry(&value, schema, registry)
+        .map_err(py_err)?;
+    Ok(PyBytes::new(py, &encoder.finish()))
+}
+
+fn decode_with<'py>(
+    py: Python<'py>,
+    schema: &SchemaType,
+    mut data: &[u8],
+    registry: &SchemaRegistry,
+) -> PyResult<Bound<'py, PyAny>> {
+    let value = Decoder::decode_with_registry(&mut data, schema, registry).map_err(py_err)?;
+    convert::to_py(py, &value)
+}
+
+/// Parses a schema given as a JSON string or as its `dict` form.
+fn parse_schema(schema: &Bound<'_, PyAny>) -> PyResult<SchemaType> {
+    SchemaType::from_json(&to_json(schema)?).map_err(py_err)
+}
+
+fn to_json(obj: &Bound<'_, PyAny>) -> PyResult<serde_json::Value> {
+    let text = if let Ok(s) = obj.cast::<PyString>() {
+        s.to_cow()?.into_owned()
+    } else {
+        let json = obj.py().import("json")?;
+        json.call_method1("dumps", (obj,))?.extract()?
+    };
+    serde_json::from_str(&text).map_err(|e| PyValueError::new_err(format!("Invalid JSON: {e}")))
+}
+
+#[pymodule]
+#[pyo3(name = "compactr")]
+fn compactr_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Registry>()?;
+    m.add_function(wrap_pyfunction!(encode, m)?)?;
+    m.add_function(wrap_pyfunction!(decode, m)?)?;
+    m.add("CompactrError", m.py().get_type::<CompactrError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+    use std::ffi::CStr;
+
+    /// Runs Python code with the module imported as `compactr`.
+    fn run(code: &CStr) {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "compactr")?;
+            compactr_py(&module)?;
+            let globals = PyDict::new(py);
+            globals.set_item("compactr", module)?;
+            py.run(code, Some(&globals), None)
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_native_roundtrip() {
+        run(cr#"
+import datetime, ipaddress, uuid
+
+registry = compactr.Registry()
+registry.register_openapi({
+    "components": {"schemas": {
+        "Event": {
+            "type": "object",
+            "required": ["id", "at", "payload"],
+            "properties": {
+                "id": {"type": "string", "format": "uuid"},
+                "at": {"type": "string", "format": "date-time"},
+                "day": {"type": "string", "format": "date"},
+                "source": {"type": "string", "format": "ipv4"},
+                "payload": {"type": "string", "format": "binary"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "score": {"type": "number", "format": "double"},
+            },
+        },
+    }},
+})
+
+event = {
+    "id": uuid.UUID("550e8400-e29b-41d4-a716-446655440000"),
+    "at": datetime.datetime(2024, 1, 15, 12, 30, tzinfo=datetime.timezone(datetime.timedelta(hours=2))),
+    "day": datetime.date(2024, 1, 15),
+    "source": ipaddress.IPv4Address("10.0.0.1"),
+    "payload": b"\x00\x01\xff",
+    "tags": ["a", "b"],
+    "score": 0.5,
+}
+decoded = registry.decode("Event", registry.encode("Event", event))
+assert decoded["id"] == event["id"]
+assert decoded["at"] == event["at"]
+assert decoded["at"].tzinfo is not None
+assert decoded == event, decoded
+
+# String forms encode to the same bytes
+as_strings = dict(event, id=str(event["id"]), at="2024-01-15T10:30:00Z", day="2024-01-15", source="10.0.0.1")
+assert registry.encode("Event", as_strings) == registry.encode("Event", event)
+"#);
+    }
+
+    #[test]
+    fn test_standalone_schema() {
+        run(cr#"
+schema = '{"type": "integer", "format": "int32"}'
+assert compactr.decode(schema, compactr.encode(schema, 42)) == 42
+assert compactr.encode({"type": "boolean"}, True) == b"\x01"
+"#);
+    }
+
+    #[test]
+    fn test_errors() {
+        run(cr#"
+registry = compactr.Registry()
+registry.register("Id", {"type": "string", "format": "uuid"})
+
+for call in [
+    lambda: registry.encode("Id", "not-a-uuid"),
+    lambda: registry.encode("Missing", "x"),
+    lambda: registry.decode("Id", b"\x00"),
+]:
+    try:
+        call()
+    except compactr.CompactrError:
+        pass
+    else:
+        raise AssertionError("expected CompactrError")
+"#);
+    }
+}
@@ -0,0 +1,86 @@
+//! Compactr against serde_json, bincode and MessagePack (rmp-serde) on the
+//! same data: encoded sizes, printed once, and encode and decode times.
+//!
+//! Compactr goes through the derived `Encode`/`Decode` implementations, its
+//! fastest path; the others through serde.
+
+use compactr::{Decoder, Encoder, SchemaRegistry};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+mod fixtures;
+use fixtures::Fixture;
+
+fn bench<T: Fixture>(c: &mut Criterion, name: &str, data: &T) {
+    let schema = T::schema();
+    let registry = SchemaRegistry::new();
+    let compactr = {
+        let mut encoder = Encoder::new();
+        encoder.encode_as(data, &schema, &registry).unwrap();
+        encoder.finish()
+    };
+    let json = serde_json::to_vec(data).unwrap();
+    let bincode = bincode::serialize(data).unwrap();
+    let msgpack = rmp_serde::to_vec_named(data).unwrap();
+    println!(
+        "{name}: compactr {} bytes, json {} ({:.1}x), bincode {} ({:.1}x), msgpack {} ({:.1}x)",
+        compactr.len(),
+        json.len(),
+        ratio(json.len(), compactr.len()),
+        bincode.len(),
+        ratio(bincode.len(), compactr.len()),
+        msgpack.len(),
+        ratio(msgpack.len(), compactr.len()),
+    );
+
+    let mut group = c.benchmark_group(format!("comparison/encode/{name}"));
+    group.bench_function("compactr", |b| {
+        b.iter(|| {
+            let mut encoder = Encoder::new();
+            encoder
+                .encode_as(black_box(data), &schema, &registry)
+                .unwrap();
+            encoder.finish()
+        });
+    });
+    group.bench_function("json", |b| {
+        b.iter(|| serde_json::to_vec(black_box(data)).unwrap());
+    });
+    group.bench_function("bincode", |b| {
+        b.iter(|| bincode::serialize(black_box(data)).unwrap());
+    });
+    group.bench_function("msgpack", |b| {
+        b.iter(|| rmp_serde::to_vec_named(black_box(data)).unwrap());
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group(format!("comparison/decode/{name}"));
+    group.bench_function("compactr", |b| {
+        b.iter(|| {
+            Decoder::decode_as::<T>(&mut black_box(compactr.as_ref()), &schema, &registry).unwrap()
+        });
+    });
+    group.bench_function("json", |b| {
+        b.iter(|| serde_json::from_slice::<T>(black_box(&json)).unwrap());
+    });
+    group.bench_function("bincode", |b| {
+        b.iter(|| bincode::deserialize::<T>(black_box(&bincode)).unwrap());
+    });
+    group.bench_function("msgpack", |b| {
+        b.iter(|| rmp_serde::from_slice::<T>(black_box(&msgpack)).unwrap());
+    });
+    group.finish();
+}
+
+/// Returns how many times larger `size` is than `baseline`.
+fn ratio(size: usize, baseline: usize) -> f64 {
+    size as f64 / baseline as f64
+}
+
+fn comparison(c: &mut Criterion) {
+    bench(c, "user", &fixtures::user());
+    bench(c, "order", &fixtures::order());
+    bench(c, "readings", &fixtures::readings());
+}
+
+criterion_group!(benches, comparison);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+//! Decoding throughput of the compactr APIs: `Value` trees, derived types
+//! and serde types.
+
+use compactr::{Decoder, SchemaRegistry};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+mod fixtures;
+use fixtures::Fixture;
+
+fn bench<T: Fixture>(c: &mut Criterion, name: &str, data: &T) {
+    let schema = T::schema();
+    let registry = SchemaRegistry::new();
+    let bytes = compactr::serde::to_bytes(data, &schema).unwrap();
+
+    let mut group = c.benchmark_group(format!("decode/{name}"));
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("value", |b| {
+        b.iter(|| Decoder::decode(&mut black_box(bytes.as_ref()), &schema).unwrap());
+    });
+    group.bench_function("typed", |b| {
+        b.iter(|| {
+            Decoder::decode_as::<T>(&mut black_box(bytes.as_ref()), &schema, &registry).unwrap()
+        });
+    });
+    group.bench_function("serde", |b| {
+        b.iter(|| compactr::serde::from_bytes::<T>(black_box(&bytes), &schema).unwrap());
+    });
+    group.finish();
+}
+
+fn decode(c: &mut Criterion) {
+    bench(c, "user", &fixtures::user());
+    bench(c, "order", &fixtures::order());
+    bench(c, "readings", &fixtures::readings());
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
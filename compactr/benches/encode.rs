@@ -0,0 +1,50 @@
+//! Encoding throughput of the compactr APIs: `Value` trees, derived types
+//! and serde types.
+
+use compactr::{Encoder, SchemaRegistry};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+mod fixtures;
+use fixtures::Fixture;
+
+fn bench<T: Fixture>(c: &mut Criterion, name: &str, data: &T) {
+    let schema = T::schema();
+    let value = compactr::serde::to_value(data, &schema).unwrap();
+    let registry = SchemaRegistry::new();
+    let size = compactr::serde::to_bytes(data, &schema).unwrap().len();
+
+    let mut group = c.benchmark_group(format!("encode/{name}"));
+    group.throughput(Throughput::Bytes(size as u64));
+    group.bench_function("value", |b| {
+        b.iter(|| {
+            let mut encoder = Encoder::new();
+            encoder.encode(black_box(&value), &schema).unwrap();
+            encoder.finish()
+        });
+    });
+    group.bench_function("pooled", |b| {
+        b.iter(|| compactr::encode_pooled(black_box(&value), &schema).unwrap());
+    });
+    group.bench_function("typed", |b| {
+        b.iter(|| {
+            let mut encoder = Encoder::new();
+            encoder
+                .encode_as(black_box(data), &schema, &registry)
+                .unwrap();
+            encoder.finish()
+        });
+    });
+    group.bench_function("serde", |b| {
+        b.iter(|| compactr::serde::to_bytes(black_box(data), &schema).unwrap());
+    });
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    bench(c, "user", &fixtures::user());
+    bench(c, "order", &fixtures::order());
+    bench(c, "readings", &fixtures::readings());
+}
+
+criterion_group!(benches, encode);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+//! Representative data shared by the benchmarks, deriving both the Compactr
+//! and the serde traits so every format encodes the same values.
+
+use compactr::{Compactr, Decode, Encode, Schema};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A small record, as sent by a typical CRUD API.
+#[derive(Debug, Clone, PartialEq, Compactr, Serialize, Deserialize)]
+pub struct User {
+    pub id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub age: i32,
+    pub active: bool,
+    pub score: f64,
+    pub tags: Vec<String>,
+    pub nickname: Option<String>,
+}
+
+/// A record with a nested array of records.
+#[derive(Debug, Clone, PartialEq, Compactr, Serialize, Deserialize)]
+pub struct Order {
+    pub id: i64,
+    pub customer: String,
+    #[compactr(size_width = "u16")]
+    pub items: Vec<LineItem>,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Compactr, Serialize, Deserialize)]
+pub struct LineItem {
+    pub sku: String,
+    pub quantity: i32,
+    pub price: f64,
+}
+
+/// A numeric sample, sent by the thousand.
+#[derive(Debug, Clone, PartialEq, Compactr, Serialize, Deserialize)]
+pub struct Reading {
+    pub sensor: i32,
+    pub timestamp: i64,
+    pub value: f32,
+}
+
+/// A type the benchmarks can run with every format.
+pub trait Fixture: Encode + Decode + Schema + Serialize + DeserializeOwned {}
+
+impl<T: Encode + Decode + Schema + Serialize + DeserializeOwned> Fixture for T {}
+
+pub fn user() -> User {
+    User {
+        id: Uuid::from_u128(0x550e_8400_e29b_41d4_a716_4466_5544_0000),
+        name: "Alice Martin".to_owned(),
+        email: "alice.martin@example.com".to_owned(),
+        age: 34,
+        active: true,
+        score: 4.75,
+        tags: vec!["admin".to_owned(), "beta".to_owned()],
+        nickname: None,
+    }
+}
+
+pub fn order() -> Order {
+    Order {
+        id: 1_048_576,
+        customer: "Alice Martin".to_owned(),
+        items: (0..10)
+            .map(|i| LineItem {
+                sku: format!("SKU-{i:04}"),
+                quantity: i % 3 + 1,
+                price: 9.99 + f64::from(i),
+            })
+            .collect(),
+        note: Some("Leave at the door".to_owned()),
+    }
+}
+
+pub fn readings() -> Vec<Reading> {
+    (0..1000)
+        .map(|i| Reading {
+            sensor: i % 16,
+            timestamp: 1_700_000_000_000 + i64::from(i) * 250,
+            value: 20.0 + (i % 50) as f32 / 10.0,
+        })
+        .collect()
+}
@@ -48,27 +48,27 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a complex value
     let mut obj = IndexMap::new();
     obj.insert(
-        "id".to_owned(),
+        "id".into(),
         Value::Uuid(Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000")?),
     );
-    obj.insert("name".to_owned(), Value::String("Bob Smith".to_owned()));
-    obj.insert("age".to_owned(), Value::Integer(42));
-    obj.insert("score".to_owned(), Value::Double(98.7));
-    obj.insert("created_at".to_owned(), Value::DateTime(Utc::now()));
+    obj.insert("name".into(), Value::String("Bob Smith".to_owned()));
+    obj.insert("age".into(), Value::Integer(42));
+    obj.insert("score".into(), Value::Double(98.7));
+    obj.insert("created_at".into(), Value::DateTime(Utc::now()));
     obj.insert(
-        "birth_date".to_owned(),
+        "birth_date".into(),
         Value::Date(NaiveDate::from_ymd_opt(1982, 5, 15).unwrap()),
     );
     obj.insert(
-        "ip_address".to_owned(),
+        "ip_address".into(),
         Value::Ipv4(Ipv4Addr::new(192, 168, 1, 100)),
     );
     obj.insert(
-        "ipv6_address".to_owned(),
+        "ipv6_address".into(),
         Value::Ipv6(Ipv6Addr::new(0x2001, 0x0db8, 0, 0, 0, 0, 0, 1)),
     );
     obj.insert(
-        "tags".to_owned(),
+        "tags".into(),
         Value::Array(vec![
             Value::String("rust".to_owned()),
             Value::String("serialization".to_owned()),
@@ -76,7 +76,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         ]),
     );
     obj.insert(
-        "metadata".to_owned(),
+        "metadata".into(),
         Value::Binary(vec![0xDE, 0xAD, 0xBE, 0xEF]),
     );
 
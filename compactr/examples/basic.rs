@@ -19,9 +19,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Create a user value
     let mut user = IndexMap::new();
-    user.insert("name".to_owned(), Value::String("Alice".to_owned()));
-    user.insert("age".to_owned(), Value::Integer(30));
-    user.insert("active".to_owned(), Value::Boolean(true));
+    user.insert("name".into(), Value::String("Alice".to_owned()));
+    user.insert("age".into(), Value::Integer(30));
+    user.insert("active".into(), Value::Boolean(true));
 
     let value = Value::Object(user);
 
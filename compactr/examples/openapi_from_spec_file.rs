@@ -147,6 +147,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn convert_schema(schema: &Schema) -> Result<SchemaType, String> {
     match &schema.schema_kind {
         SchemaKind::Type(Type::String(string_type)) => {
+            if !string_type.enumeration.is_empty() {
+                let variants = string_type.enumeration.iter().flatten().cloned();
+                return Ok(SchemaType::string_enum(variants));
+            }
             match &string_type.format {
                 VariantOrUnknownOrEmpty::Item(OpenAPIStringFormat::Date) => {
                     Ok(SchemaType::string_date())
@@ -159,8 +163,11 @@ fn convert_schema(schema: &Schema) -> Result<SchemaType, String> {
                 ) => Ok(SchemaType::binary()),
                 VariantOrUnknownOrEmpty::Unknown(s) => match s.as_str() {
                     "uuid" => Ok(SchemaType::string_uuid()),
+                    "time" => Ok(SchemaType::string_time()),
+                    "uri" => Ok(SchemaType::string_uri()),
                     "ipv4" => Ok(SchemaType::string_ipv4()),
                     "ipv6" => Ok(SchemaType::string_ipv6()),
+                    "socket-addr" => Ok(SchemaType::string_socket_addr()),
                     _ => Ok(SchemaType::string()), // email, etc. treated as plain string
                 },
                 _ => Ok(SchemaType::string()),
@@ -235,18 +242,18 @@ fn create_user(
 ) -> Result<Value, Box<dyn std::error::Error>> {
     let mut user = IndexMap::new();
 
-    user.insert("id".to_owned(), Value::Uuid(Uuid::parse_str(id)?));
-    user.insert("name".to_owned(), Value::String(name.to_owned()));
+    user.insert("id".into(), Value::Uuid(Uuid::parse_str(id)?));
+    user.insert("name".into(), Value::String(name.to_owned()));
 
     if let Some(e) = email {
-        user.insert("email".to_owned(), Value::String(e.to_owned()));
+        user.insert("email".into(), Value::String(e.to_owned()));
     }
 
     if let Some(a) = age {
-        user.insert("age".to_owned(), Value::Integer(i64::from(a)));
+        user.insert("age".into(), Value::Integer(i64::from(a)));
     }
 
-    user.insert("created_at".to_owned(), Value::DateTime(Utc::now()));
+    user.insert("created_at".into(), Value::DateTime(Utc::now()));
 
     Ok(Value::Object(user))
 }
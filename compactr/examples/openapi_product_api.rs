@@ -212,8 +212,8 @@ fn create_product_schema() -> SchemaType {
 /// Create a Category value
 fn create_category(id: i32, name: &str) -> Value {
     let mut category = IndexMap::new();
-    category.insert("id".to_owned(), Value::Integer(i64::from(id)));
-    category.insert("name".to_owned(), Value::String(name.to_owned()));
+    category.insert("id".into(), Value::Integer(i64::from(id)));
+    category.insert("name".into(), Value::String(name.to_owned()));
     Value::Object(category)
 }
 
@@ -229,23 +229,23 @@ fn create_product(
 ) -> Result<Value, Box<dyn std::error::Error>> {
     let mut product = IndexMap::new();
 
-    product.insert("id".to_owned(), Value::Uuid(Uuid::parse_str(id)?));
-    product.insert("name".to_owned(), Value::String(name.to_owned()));
-    product.insert("price".to_owned(), Value::Double(price));
-    product.insert("category".to_owned(), category);
+    product.insert("id".into(), Value::Uuid(Uuid::parse_str(id)?));
+    product.insert("name".into(), Value::String(name.to_owned()));
+    product.insert("price".into(), Value::Double(price));
+    product.insert("category".into(), category);
     product.insert(
-        "tags".to_owned(),
+        "tags".into(),
         Value::Array(
             tags.into_iter()
                 .map(|t| Value::String(t.to_owned()))
                 .collect(),
         ),
     );
-    product.insert("in_stock".to_owned(), Value::Boolean(in_stock));
+    product.insert("in_stock".into(), Value::Boolean(in_stock));
 
     // Only include discount if provided
     if let Some(d) = discount {
-        product.insert("discount".to_owned(), Value::Double(d));
+        product.insert("discount".into(), Value::Double(d));
     }
 
     Ok(Value::Object(product))
@@ -97,6 +97,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn convert_schema(schema: &Schema) -> Result<SchemaType, String> {
     match &schema.schema_kind {
         SchemaKind::Type(Type::String(string_type)) => {
+            if !string_type.enumeration.is_empty() {
+                let variants = string_type.enumeration.iter().flatten().cloned();
+                return Ok(SchemaType::string_enum(variants));
+            }
             match &string_type.format {
                 VariantOrUnknownOrEmpty::Item(OpenAPIStringFormat::Date) => {
                     Ok(SchemaType::string_date())
@@ -109,8 +113,11 @@ fn convert_schema(schema: &Schema) -> Result<SchemaType, String> {
                 ) => Ok(SchemaType::binary()),
                 VariantOrUnknownOrEmpty::Unknown(s) => match s.as_str() {
                     "uuid" => Ok(SchemaType::string_uuid()),
+                    "time" => Ok(SchemaType::string_time()),
+                    "uri" => Ok(SchemaType::string_uri()),
                     "ipv4" => Ok(SchemaType::string_ipv4()),
                     "ipv6" => Ok(SchemaType::string_ipv6()),
+                    "socket-addr" => Ok(SchemaType::string_socket_addr()),
                     _ => Ok(SchemaType::string()), // Unknown format, treat as plain string
                 },
                 _ => Ok(SchemaType::string()),
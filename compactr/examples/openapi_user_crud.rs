@@ -152,15 +152,15 @@ fn create_user(
 ) -> Result<Value, Box<dyn std::error::Error>> {
     let mut user = IndexMap::new();
 
-    user.insert("id".to_owned(), Value::Uuid(Uuid::parse_str(id)?));
-    user.insert("name".to_owned(), Value::String(name.to_owned()));
+    user.insert("id".into(), Value::Uuid(Uuid::parse_str(id)?));
+    user.insert("name".into(), Value::String(name.to_owned()));
 
     // Only include email if provided (optional field)
     if let Some(e) = email {
-        user.insert("email".to_owned(), Value::String(e.to_owned()));
+        user.insert("email".into(), Value::String(e.to_owned()));
     }
 
-    user.insert("created_at".to_owned(), Value::DateTime(Utc::now()));
+    user.insert("created_at".into(), Value::DateTime(Utc::now()));
 
     Ok(Value::Object(user))
 }
@@ -0,0 +1,499 @@
+//! Array wire layouts.
+
+use crate::codec::length::{put_length, read_length, LengthMode};
+use crate::codec::wire::{ELEMENT_COUNT_WIDTH, ELEMENT_SIZE_WIDTH};
+use crate::error::{DecodeError, EncodeError};
+use bytes::{Buf, Bytes, BytesMut};
+use std::cell::Cell;
+
+/// How array elements are delimited on the wire.
+///
+/// Both ends must agree on the layout: it is configured on the
+/// [`SchemaRegistry`](crate::SchemaRegistry) handed to the encoder and the
+/// decoder, with [`SchemaRegistry::with_array_format`](crate::SchemaRegistry::with_array_format).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ArrayFormat {
+    /// Each element is prefixed with its 1-byte size and there is no count,
+    /// as in compactr.js: `[size1, elem1, size2, elem2, ...]`. Top-level
+    /// arrays run to the end of the buffer and elements are limited to 255
+    /// bytes.
+    #[default]
+    SizePrefixed,
+    /// A big-endian `u32` element count followed by the elements back to
+    /// back: `[count, elem1, elem2, ...]`. Arrays are self-delimiting and
+    /// elements unbounded, but custom formats used as items must be
+    /// self-delimiting too.
+    CountPrefixed,
+    /// Like [`ArrayFormat::SizePrefixed`], but each size is an unsigned
+    /// LEB128 varint: one byte for elements under 128 bytes, two under 16
+    /// KiB, and so on, so elements of any size can be stored. This is the
+    /// size-prefixed layout under [`LengthMode::Varint`], which also
+    /// applies to the rest of the message.
+    VarintSizePrefixed,
+}
+
+/// Writes array elements in the given layout.
+pub(crate) fn encode_elements<T, E: From<EncodeError>>(
+    buf: &mut BytesMut,
+    format: ArrayFormat,
+    mode: LengthMode,
+    items: &[T],
+    mut encode: impl FnMut(&T, &mut BytesMut) -> Result<(), E>,
+) -> Result<(), E> {
+    if format == ArrayFormat::CountPrefixed {
+        put_count(buf, mode, items.len())?;
+        for item in items {
+            encode(item, buf)?;
+        }
+        return Ok(());
+    }
+
+    let mut elem_buf = BytesMut::new();
+    for item in items {
+        elem_buf.clear();
+        encode(item, &mut elem_buf)?;
+        put_element_size(buf, format, mode, elem_buf.len())?;
+        buf.extend_from_slice(&elem_buf);
+    }
+    Ok(())
+}
+
+/// Writes the size of an element of a size-prefixed array.
+pub(crate) fn put_element_size(
+    buf: &mut BytesMut,
+    format: ArrayFormat,
+    mode: LengthMode,
+    size: usize,
+) -> Result<(), EncodeError> {
+    put_length(
+        buf,
+        size_mode(format, mode),
+        ELEMENT_SIZE_WIDTH,
+        size,
+        "Array element size",
+    )
+}
+
+/// Reads the size of an element of a size-prefixed array.
+pub(crate) fn read_element_size(
+    buf: &mut impl Buf,
+    format: ArrayFormat,
+    mode: LengthMode,
+) -> Result<usize, DecodeError> {
+    read_length(buf, size_mode(format, mode), ELEMENT_SIZE_WIDTH)
+}
+
+fn size_mode(format: ArrayFormat, mode: LengthMode) -> LengthMode {
+    if format == ArrayFormat::VarintSizePrefixed {
+        LengthMode::Varint
+    } else {
+        mode
+    }
+}
+
+/// Reads array elements in the given layout.
+///
+/// Size-prefixed elements run until the buffer is exhausted and are handed
+/// to `framed` as their own buffer; count-prefixed ones are read by
+/// `inline` straight from `buf`. Arrays of more than `max_len` elements are
+/// rejected before decoding the elements beyond it.
+pub(crate) fn decode_elements<B: Buf, T, E: From<DecodeError>>(
+    buf: &mut B,
+    format: ArrayFormat,
+    mode: LengthMode,
+    max_len: usize,
+    mut framed: impl FnMut(&mut Bytes) -> Result<T, E>,
+    mut inline: impl FnMut(&mut B) -> Result<T, E>,
+) -> Result<Vec<T>, E> {
+    if format == ArrayFormat::CountPrefixed {
+        return with_expansion_budget(|| {
+            let count = read_element_count(buf, mode)?;
+            check_array_len(count, max_len)?;
+            let mut items = Vec::with_capacity(count.min(buf.remaining()));
+            for _ in 0..count {
+                items.push(inline(buf)?);
+            }
+            Ok(items)
+        });
+    }
+
+    let mut items = Vec::new();
+    while buf.has_remaining() {
+        let size = read_element_size(buf, format, mode)?;
+        if buf.remaining() < size {
+            return Err(DecodeError::UnexpectedEof.into());
+        }
+        check_array_len(items.len() + 1, max_len)?;
+        let mut elem_buf = buf.copy_to_bytes(size);
+        items.push(framed(&mut elem_buf)?);
+    }
+    Ok(items)
+}
+
+/// Checks that an array of `len` elements is within the decoder's limit.
+pub(crate) fn check_array_len(len: usize, max_len: usize) -> Result<(), DecodeError> {
+    if len > max_len {
+        return Err(DecodeError::InvalidData(format!(
+            "Array of {len} elements exceeds the limit of {max_len}"
+        )));
+    }
+    Ok(())
+}
+
+/// Largest number of elements the arrays of a value may decode to beyond
+/// one per input byte, together, bounding the memory a few input bytes can
+/// claim. Repeated elements of run-length encoded arrays count against it,
+/// as do elements of count-prefixed arrays that take no bytes, such as
+/// empty objects in [`ObjectFormat::Bitmap`](crate::ObjectFormat::Bitmap).
+pub(crate) const MAX_EXPANDED_ELEMENTS: usize = 1024 * 1024;
+
+thread_local! {
+    /// Elements the arrays of the value being decoded on this thread may
+    /// still expand to, `None` outside of a decode.
+    static EXPANSION_BUDGET: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Runs `decode` with one budget of [`MAX_EXPANDED_ELEMENTS`] for every
+/// array it decodes, so that nesting arrays or repeating them in other
+/// arrays and objects can't multiply the limit. Nested calls share the
+/// outermost budget.
+pub(crate) fn with_expansion_budget<T>(decode: impl FnOnce() -> T) -> T {
+    /// Ends the budget, even if `decode` panics.
+    struct Scope;
+    impl Drop for Scope {
+        fn drop(&mut self) {
+            EXPANSION_BUDGET.with(|budget| budget.set(None));
+        }
+    }
+
+    if EXPANSION_BUDGET.with(Cell::get).is_some() {
+        return decode();
+    }
+    EXPANSION_BUDGET.with(|budget| budget.set(Some(MAX_EXPANDED_ELEMENTS)));
+    let _scope = Scope;
+    decode()
+}
+
+/// Takes `elements` from the expansion budget.
+fn spend_expansion_budget(elements: usize) -> Result<(), DecodeError> {
+    EXPANSION_BUDGET.with(|budget| {
+        let left = budget.get().unwrap_or(MAX_EXPANDED_ELEMENTS);
+        if elements > left {
+            return Err(DecodeError::InvalidData(format!(
+                "Arrays expand to over {MAX_EXPANDED_ELEMENTS} elements beyond their input"
+            )));
+        }
+        budget.set(Some(left - elements));
+        Ok(())
+    })
+}
+
+/// Writes the elements of a [`SchemaType::RunLengthArray`] as runs of
+/// equal encodings: a run count (a big-endian `u32` in
+/// [`LengthMode::Fixed`]), then for each run a repeat count (`u16`), the
+/// element size (1 byte) and the element.
+///
+/// [`SchemaType::RunLengthArray`]: crate::schema::SchemaType::RunLengthArray
+pub(crate) fn encode_runs<T, E: From<EncodeError>>(
+    buf: &mut BytesMut,
+    mode: LengthMode,
+    items: &[T],
+    mut encode: impl FnMut(&T, &mut BytesMut) -> Result<(), E>,
+) -> Result<(), E> {
+    let mut runs = 0;
+    let mut body = BytesMut::new();
+    let mut run = BytesMut::new();
+    let mut repeat: usize = 0;
+    let mut elem_buf = BytesMut::new();
+    for item in items {
+        elem_buf.clear();
+        encode(item, &mut elem_buf)?;
+        if repeat > 0 && repeat < usize::from(u16::MAX) && elem_buf == run {
+            repeat += 1;
+            continue;
+        }
+        if repeat > 0 {
+            put_run(&mut body, mode, repeat, &run)?;
+            runs += 1;
+        }
+        std::mem::swap(&mut run, &mut elem_buf);
+        repeat = 1;
+    }
+    if repeat > 0 {
+        put_run(&mut body, mode, repeat, &run)?;
+        runs += 1;
+    }
+    put_length(buf, mode, 4, runs, "Array run count")?;
+    buf.extend_from_slice(&body);
+    Ok(())
+}
+
+fn put_run(
+    buf: &mut BytesMut,
+    mode: LengthMode,
+    repeat: usize,
+    elem: &[u8],
+) -> Result<(), EncodeError> {
+    put_length(buf, mode, 2, repeat, "Array run length")?;
+    put_length(buf, mode, 1, elem.len(), "Array element size")?;
+    buf.extend_from_slice(elem);
+    Ok(())
+}
+
+/// Reads the elements of a [`SchemaType::RunLengthArray`], using `take` to
+/// split each run's element off the buffer and decoding it once per
+/// repetition. Arrays of more than `max_len` elements are rejected before
+/// decoding the run that exceeds it.
+///
+/// [`SchemaType::RunLengthArray`]: crate::schema::SchemaType::RunLengthArray
+pub(crate) fn decode_runs<B: Buf, P: Clone, T, E: From<DecodeError>>(
+    buf: &mut B,
+    mode: LengthMode,
+    max_len: usize,
+    take: impl Fn(&mut B, usize) -> P,
+    mut decode: impl FnMut(&mut P) -> Result<T, E>,
+) -> Result<Vec<T>, E> {
+    with_expansion_budget(|| {
+        let runs = read_count(buf, mode)?;
+        let mut items = Vec::new();
+        for _ in 0..runs {
+            let repeat = read_length(buf, mode, 2)?;
+            let size = read_length(buf, mode, 1)?;
+            if buf.remaining() < size {
+                return Err(DecodeError::UnexpectedEof.into());
+            }
+            check_array_len(items.len().saturating_add(repeat), max_len)?;
+            spend_expansion_budget(repeat)?;
+            let elem = take(buf, size);
+            items.reserve(repeat);
+            for _ in 0..repeat {
+                items.push(decode(&mut elem.clone())?);
+            }
+        }
+        Ok(items)
+    })
+}
+
+/// Writes the element count of a count-prefixed array.
+pub(crate) fn put_count(
+    buf: &mut BytesMut,
+    mode: LengthMode,
+    count: usize,
+) -> Result<(), EncodeError> {
+    put_length(buf, mode, ELEMENT_COUNT_WIDTH, count, "Array element count")
+}
+
+/// Reads the element count of a count-prefixed array.
+pub(crate) fn read_count(buf: &mut impl Buf, mode: LengthMode) -> Result<usize, DecodeError> {
+    read_length(buf, mode, ELEMENT_COUNT_WIDTH)
+}
+
+/// Reads the element count of a count-prefixed array, taking the elements
+/// the remaining input can't hold a byte of each from the expansion budget.
+pub(crate) fn read_element_count(
+    buf: &mut impl Buf,
+    mode: LengthMode,
+) -> Result<usize, DecodeError> {
+    let count = read_count(buf, mode)?;
+    spend_expansion_budget(count.saturating_sub(buf.remaining()))?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::ObjectFormat;
+    use crate::schema::{Property, SchemaRegistry, SchemaType};
+    use crate::value::Value;
+    use crate::{Decoder, Encoder};
+    use indexmap::IndexMap;
+
+    fn roundtrip(value: &Value, schema: &SchemaType, registry: &SchemaRegistry) -> Bytes {
+        let mut enc = Encoder::new();
+        enc.encode_with_registry(value, schema, registry).unwrap();
+        let bytes = enc.finish();
+        let decoded = Decoder::decode_with_registry(&mut bytes.as_ref(), schema, registry);
+        assert_eq!(decoded.unwrap(), *value);
+        bytes
+    }
+
+    #[test]
+    fn test_count_prefixed() {
+        let registry = SchemaRegistry::new().with_array_format(ArrayFormat::CountPrefixed);
+        let schema = SchemaType::array(SchemaType::string());
+        let value = Value::Array(vec![Value::from("a"), Value::from("bc")]);
+        let bytes = roundtrip(&value, &schema, &registry);
+        assert_eq!(&bytes[..], [0, 0, 0, 2, 0, 1, b'a', 0, 2, b'b', b'c']);
+
+        // Elements are no longer limited to 255 bytes
+        let long = Value::Array(vec![Value::from("x".repeat(300))]);
+        roundtrip(&long, &schema, &registry);
+        let mut enc = Encoder::new();
+        assert!(enc.encode(&long, &schema).is_err());
+
+        let typed: Vec<String> =
+            Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(typed, ["a", "bc"]);
+        let mut buf = BytesMut::new();
+        Encoder::encode_into(&mut buf, &typed, &schema, &registry).unwrap();
+        assert_eq!(buf, bytes);
+
+        // Elements taking no bytes are bounded like repeated ones
+        let registry = registry.with_object_format(ObjectFormat::Bitmap);
+        let empty = SchemaType::array(SchemaType::Object(IndexMap::new()));
+        let value = Value::Array(vec![Value::Object(IndexMap::new()); 1000]);
+        let bytes = roundtrip(&value, &empty, &registry);
+        assert_eq!(bytes.len(), 4);
+        let bomb = [0xFF; 4];
+        let result = Decoder::decode_with_registry(&mut bomb.as_ref(), &empty, &registry);
+        assert!(result.is_err());
+        let result: crate::Result<Vec<Value>> =
+            Decoder::decode_borrowed(&mut bomb.as_ref(), &empty, &registry);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_varint_size_prefixed() {
+        let registry = SchemaRegistry::new().with_array_format(ArrayFormat::VarintSizePrefixed);
+        let schema = SchemaType::array(SchemaType::string());
+        let value = Value::Array(vec![
+            Value::from("a"),
+            Value::from("x".repeat(300)),
+            Value::from(""),
+        ]);
+        let bytes = roundtrip(&value, &schema, &registry);
+        // 3 bytes, then 302 bytes as 0xAE 0x02
+        assert_eq!(bytes[..6], [3, 0, 1, b'a', 0xAE, 0x02]);
+
+        let typed: Vec<String> =
+            Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(typed[1].len(), 300);
+        let mut buf = BytesMut::new();
+        Encoder::encode_into(&mut buf, &typed, &schema, &registry).unwrap();
+        assert_eq!(buf, bytes);
+        let borrowed: Vec<&str> =
+            Decoder::decode_borrowed(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(borrowed[2], "");
+
+        let overlong = [0xFF; 11];
+        let result = Decoder::decode_with_registry(&mut overlong.as_ref(), &schema, &registry);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_nested_count_prefixed() {
+        let registry = SchemaRegistry::new().with_array_format(ArrayFormat::CountPrefixed);
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "matrix".to_owned(),
+            Property::required(SchemaType::array(SchemaType::array(SchemaType::int32()))),
+        );
+        let schema = SchemaType::object(properties);
+        let row = |items: &[i64]| Value::Array(items.iter().copied().map(Value::Integer).collect());
+        let mut obj = IndexMap::new();
+        obj.insert("matrix".into(), Value::Array(vec![row(&[1, 2]), row(&[])]));
+        roundtrip(&Value::Object(obj), &schema, &registry);
+
+        let truncated = [0, 0, 0, 2, 0, 0, 0, 1];
+        let result = Decoder::decode_with_registry(
+            &mut truncated.as_ref(),
+            &SchemaType::array(SchemaType::int32()),
+            &registry,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_run_length() {
+        let registry = SchemaRegistry::new();
+        let schema = SchemaType::run_length_array(SchemaType::int32());
+        let flags = [3, 3, 3, 3, 0, 1, 1];
+        let value = Value::Array(flags.iter().map(|&f| Value::Integer(f.into())).collect());
+        let bytes = roundtrip(&value, &schema, &registry);
+        #[rustfmt::skip]
+        assert_eq!(
+            &bytes[..],
+            [0, 0, 0, 3, 0, 4, 4, 0, 0, 0, 3, 0, 1, 4, 0, 0, 0, 0, 0, 2, 4, 0, 0, 0, 1]
+        );
+
+        let typed: Vec<i32> = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(typed, flags);
+        let borrowed: Vec<i32> =
+            Decoder::decode_borrowed(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(borrowed, flags);
+        let mut buf = BytesMut::new();
+        Encoder::encode_into(&mut buf, &typed, &schema, &registry).unwrap();
+        assert_eq!(buf, bytes);
+
+        // The layout is the same whatever the registry's array format
+        let registry = registry.with_array_format(ArrayFormat::CountPrefixed);
+        assert_eq!(roundtrip(&value, &schema, &registry), bytes);
+
+        // A few bytes cannot claim unbounded memory
+        let mut bomb = vec![0, 0, 0, 20];
+        bomb.extend([0xFF, 0xFF, 4, 0, 0, 0, 0].repeat(20));
+        let result = Decoder::decode_with_registry(&mut bomb.as_slice(), &schema, &registry);
+        assert!(result.is_err());
+
+        // Nor can nesting run-length arrays, or repeating them, multiply the
+        // limit
+        let nested = SchemaType::run_length_array(schema.clone());
+        let mut inner = vec![0, 0, 0, 16];
+        inner.extend([0xFF, 0xFF, 4, 0, 0, 0, 0].repeat(16));
+        let mut bomb = vec![0, 0, 0, 1, 0xFF, 0xFF, u8::try_from(inner.len()).unwrap()];
+        bomb.extend(&inner);
+        let result = Decoder::decode_with_registry(&mut bomb.as_slice(), &nested, &registry);
+        assert!(result.is_err());
+        let repeated = SchemaType::array(schema);
+        let mut bomb = vec![0, 0, 0, 20];
+        bomb.extend(inner.repeat(20));
+        let result = Decoder::decode_with_registry(&mut bomb.as_slice(), &repeated, &registry);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_max_len_checked_before_decoding() {
+        let decoded = Cell::new(0);
+        let decode = |buf: &mut &[u8]| -> Result<u8, DecodeError> {
+            decoded.set(decoded.get() + 1);
+            Ok(buf.get_u8())
+        };
+
+        // Count-prefixed arrays are rejected by their count
+        let bytes = [0, 0, 0, 5, 1, 2, 3, 4, 5];
+        let result = decode_elements(
+            &mut &bytes[..],
+            ArrayFormat::CountPrefixed,
+            LengthMode::Fixed,
+            3,
+            |_: &mut Bytes| unreachable!(),
+            decode,
+        );
+        assert!(matches!(result, Err(DecodeError::InvalidData(_))));
+
+        // Size-prefixed arrays when reaching the element past the limit
+        let bytes = [1, 7].repeat(5);
+        let result = decode_elements(
+            &mut &bytes[..],
+            ArrayFormat::SizePrefixed,
+            LengthMode::Fixed,
+            3,
+            |elem: &mut Bytes| decode(&mut elem.as_ref()),
+            |_: &mut &[u8]| unreachable!(),
+        );
+        assert!(matches!(result, Err(DecodeError::InvalidData(_))));
+        assert_eq!(decoded.replace(0), 3);
+
+        // Run-length arrays by the repeat count of the run
+        let bytes = [0, 0, 0, 1, 0xFF, 0xFF, 1, 7];
+        let result = decode_runs(
+            &mut &bytes[..],
+            LengthMode::Fixed,
+            3,
+            Buf::copy_to_bytes,
+            |elem: &mut Bytes| decode(&mut elem.as_ref()),
+        );
+        assert!(matches!(result, Err(DecodeError::InvalidData(_))));
+        assert_eq!(decoded.get(), 0);
+    }
+}
@@ -0,0 +1,340 @@
+//! Decoding that borrows from the input buffer.
+
+use crate::codec::array::{
+    check_array_len, decode_runs, read_element_count, read_element_size, with_expansion_budget,
+};
+use crate::codec::buffer::read_prefixed_len;
+use crate::codec::traits::{array_items, fixed_length};
+use crate::codec::wire::{BINARY_LEN_WIDTH, STRING_LEN_WIDTH};
+use crate::codec::{ArrayFormat, Decode, Decoder};
+use crate::error::{DecodeError, Result};
+use crate::schema::{SchemaRegistry, SchemaType, StringFormat};
+use crate::value::Value;
+#[cfg(feature = "uuid")]
+use ::uuid::Uuid;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Trait for types that can be decoded from a byte slice, possibly
+/// borrowing from it.
+///
+/// This is the zero-copy counterpart of [`Decode`]: `&str` and `Cow<str>`
+/// point into the input when the schema is a plain string. The [`Decode`]
+/// types of this crate implement it as well, so borrowed types can be nested
+/// in `Option`, `Vec` and derived structs. Use it through
+/// [`Decoder::decode_borrowed`].
+pub trait DecodeBorrowed<'de>: Sized {
+    /// Decodes a value laid out according to `schema`.
+    ///
+    /// The schema is never a reference; [`Decoder::decode_borrowed`]
+    /// resolves those first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer doesn't contain valid data for the
+    /// schema or the data cannot be represented as `Self`.
+    fn decode_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self>;
+
+    /// Decodes a value stored as an object property.
+    ///
+    /// The default implementation calls [`DecodeBorrowed::decode_borrowed`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer doesn't contain valid data for the
+    /// schema or the data cannot be represented as `Self`.
+    fn decode_property_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        Self::decode_borrowed(buf, schema, registry)
+    }
+
+    /// Returns the value to use when an object property is missing, or
+    /// `None` if the property is mandatory (see [`Decode::absent`]).
+    #[must_use]
+    fn absent() -> Option<Self> {
+        None
+    }
+}
+
+/// Implements `DecodeBorrowed` for owned types by delegating to `Decode`.
+macro_rules! impl_via_decode {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl<'de> DecodeBorrowed<'de> for $ty {
+                fn decode_borrowed(
+                    buf: &mut &'de [u8],
+                    schema: &SchemaType,
+                    registry: &SchemaRegistry,
+                ) -> Result<Self> {
+                    <$ty as Decode>::decode_with_schema(buf, schema, registry)
+                }
+
+                fn decode_property_borrowed(
+                    buf: &mut &'de [u8],
+                    schema: &SchemaType,
+                    registry: &SchemaRegistry,
+                ) -> Result<Self> {
+                    <$ty as Decode>::decode_property(buf, schema, registry)
+                }
+            }
+        )*
+    };
+}
+
+impl_via_decode!(Value, bool, i32, i64, f32, f64, String, Ipv4Addr, Ipv6Addr,);
+#[cfg(feature = "uuid")]
+impl_via_decode!(Uuid);
+#[cfg(feature = "chrono")]
+impl_via_decode!(DateTime<Utc>);
+
+/// Plain strings are borrowed from the input; formatted strings cannot be.
+impl<'de: 'a, 'a> DecodeBorrowed<'de> for &'a str {
+    fn decode_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        expect_plain(schema)?;
+        let width = if *schema == SchemaType::String(StringFormat::Long) {
+            BINARY_LEN_WIDTH
+        } else {
+            STRING_LEN_WIDTH
+        };
+        let len = read_prefixed_len(buf, registry.length_mode(), width)?;
+        let (bytes, rest) = buf.split_at(len);
+        *buf = rest;
+        utf8(bytes)
+    }
+
+    fn decode_property_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        _registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        expect_plain(schema)?;
+        // For strings in objects: raw UTF-8 bytes (no length prefix)
+        utf8(std::mem::take(buf))
+    }
+}
+
+/// Plain strings are borrowed; formatted values are rendered into an owned
+/// string like [`String`]'s [`Decode`] implementation does.
+impl<'de: 'a, 'a> DecodeBorrowed<'de> for Cow<'a, str> {
+    fn decode_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        if is_plain(schema) {
+            <&str>::decode_borrowed(buf, schema, registry).map(Cow::Borrowed)
+        } else {
+            String::decode_with_schema(buf, schema, registry).map(Cow::Owned)
+        }
+    }
+
+    fn decode_property_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        if is_plain(schema) {
+            <&str>::decode_property_borrowed(buf, schema, registry).map(Cow::Borrowed)
+        } else {
+            String::decode_property(buf, schema, registry).map(Cow::Owned)
+        }
+    }
+}
+
+impl<'de, T: DecodeBorrowed<'de>> DecodeBorrowed<'de> for Option<T> {
+    fn decode_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        if buf.is_empty() {
+            Ok(None)
+        } else {
+            T::decode_borrowed(buf, schema, registry).map(Some)
+        }
+    }
+
+    fn decode_property_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        if buf.is_empty() {
+            Ok(None)
+        } else {
+            T::decode_property_borrowed(buf, schema, registry).map(Some)
+        }
+    }
+
+    fn absent() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<'de, T: DecodeBorrowed<'de>> DecodeBorrowed<'de> for Vec<T> {
+    fn decode_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        let items = array_items(schema, registry)?;
+        let mode = registry.length_mode();
+        let max_len = registry.decoder_options().max_array_len();
+        if matches!(schema, SchemaType::RunLengthArray(_)) {
+            let take = |buf: &mut &'de [u8], n| {
+                let (head, tail) = buf.split_at(n);
+                *buf = tail;
+                head
+            };
+            return decode_runs(buf, mode, max_len, take, |elem_buf| {
+                T::decode_borrowed(elem_buf, &items, registry)
+            });
+        }
+        if registry.array_format() == ArrayFormat::CountPrefixed {
+            return with_expansion_budget(|| {
+                let count = read_element_count(buf, mode)?;
+                check_array_len(count, max_len)?;
+                let mut out = Vec::with_capacity(count.min(buf.len()));
+                for _ in 0..count {
+                    out.push(T::decode_borrowed(buf, &items, registry)?);
+                }
+                Ok(out)
+            });
+        }
+        let mut out = Vec::new();
+        while !buf.is_empty() {
+            let size = read_element_size(buf, registry.array_format(), mode)?;
+            if buf.len() < size {
+                return Err(DecodeError::UnexpectedEof.into());
+            }
+            check_array_len(out.len() + 1, max_len)?;
+            let (mut elem_buf, rest) = buf.split_at(size);
+            *buf = rest;
+            out.push(T::decode_borrowed(&mut elem_buf, &items, registry)?);
+        }
+        Ok(out)
+    }
+}
+
+impl<'de, T: DecodeBorrowed<'de>, const N: usize> DecodeBorrowed<'de> for [T; N] {
+    fn decode_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        fixed_length(Vec::decode_borrowed(buf, schema, registry)?).map_err(Into::into)
+    }
+}
+
+impl<'de, T, S> DecodeBorrowed<'de> for HashMap<String, T, S>
+where
+    T: DecodeBorrowed<'de>,
+    S: BuildHasher + Default,
+{
+    fn decode_borrowed(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        let mut map = Self::default();
+        Decoder::decode_fields_borrowed(buf, schema, registry, |name, prop_schema, prop_buf| {
+            let value = Decoder::decode_property_borrowed(prop_buf, prop_schema, registry)?;
+            map.insert(name.to_owned(), value);
+            Ok(())
+        })?;
+        Ok(map)
+    }
+}
+
+fn is_plain(schema: &SchemaType) -> bool {
+    matches!(
+        schema,
+        SchemaType::String(StringFormat::Plain | StringFormat::Long)
+    )
+}
+
+fn expect_plain(schema: &SchemaType) -> Result<()> {
+    if is_plain(schema) {
+        Ok(())
+    } else {
+        Err(DecodeError::SchemaMismatch(format!(
+            "borrowed strings need a plain string schema, got {schema:?}"
+        ))
+        .into())
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Result<&str> {
+    std::str::from_utf8(bytes)
+        .map_err(|e| DecodeError::InvalidData(format!("Invalid UTF-8: {e}")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Encoder;
+    use crate::schema::Property;
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_borrowed_str() {
+        let registry = SchemaRegistry::new();
+        let schema = SchemaType::array(SchemaType::string());
+        let value = Value::Array(vec![Value::from("alpha"), Value::from("beta")]);
+
+        let mut encoder = Encoder::new();
+        encoder.encode(&value, &schema).unwrap();
+        let bytes = encoder.finish();
+
+        let mut buf = bytes.as_ref();
+        let names: Vec<&str> = Decoder::decode_borrowed(&mut buf, &schema, &registry).unwrap();
+        assert_eq!(names, ["alpha", "beta"]);
+        // The strings point into the encoded bytes
+        assert!(bytes.as_ptr_range().contains(&names[0].as_ptr()));
+    }
+
+    #[test]
+    fn test_borrowed_properties() {
+        let registry = SchemaRegistry::new();
+        let mut properties = IndexMap::new();
+        properties.insert("name".to_owned(), Property::required(SchemaType::string()));
+        properties.insert(
+            "ip".to_owned(),
+            Property::required(SchemaType::string_ipv4()),
+        );
+        let schema = SchemaType::object(properties);
+
+        let ip = "10.0.0.1";
+        let mut obj = IndexMap::new();
+        obj.insert("name".into(), Value::from("Alice"));
+        obj.insert("ip".into(), Value::from(ip));
+        let mut encoder = Encoder::new();
+        encoder.encode(&Value::Object(obj), &schema).unwrap();
+        let bytes = encoder.finish();
+
+        let map: HashMap<String, Cow<str>> =
+            Decoder::decode_borrowed(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert!(matches!(map["name"], Cow::Borrowed("Alice")));
+        assert!(matches!(&map["ip"], Cow::Owned(s) if s == ip));
+
+        // A formatted string cannot be borrowed
+        let result: Result<HashMap<String, &str>> =
+            Decoder::decode_borrowed(&mut bytes.as_ref(), &schema, &registry);
+        assert!(result.is_err());
+    }
+}
@@ -1,5 +1,7 @@
 //! Buffer utilities for reading and writing encoded data.
 
+use crate::codec::length::{put_length, read_length, LengthMode};
+use crate::codec::wire::{BINARY_LEN_WIDTH, STRING_LEN_WIDTH};
 use crate::error::{DecodeError, EncodeError};
 use bytes::{Buf, BufMut, BytesMut};
 
@@ -13,24 +15,7 @@ use bytes::{Buf, BufMut, BytesMut};
 ///
 /// Returns an error if the string is too long to encode.
 pub fn encode_string(buf: &mut BytesMut, s: &str) -> Result<(), EncodeError> {
-    // UTF-8 encoding: Rust strings are already UTF-8
-    let utf8_bytes = s.as_bytes();
-    let byte_len = utf8_bytes.len();
-
-    if byte_len > u16::MAX as usize {
-        return Err(EncodeError::InvalidFormat(format!(
-            "String too long: {} bytes (max {})",
-            byte_len,
-            u16::MAX
-        )));
-    }
-
-    #[allow(clippy::cast_possible_truncation)]
-    buf.put_u16(byte_len as u16); // Big-endian length prefix
-
-    buf.put_slice(utf8_bytes); // Raw UTF-8 bytes
-
-    Ok(())
+    put_prefixed(buf, LengthMode::Fixed, STRING_LEN_WIDTH, s.as_bytes())
 }
 
 /// Decodes a string from the buffer.
@@ -45,26 +30,29 @@ pub fn encode_string(buf: &mut BytesMut, s: &str) -> Result<(), EncodeError> {
 /// - The buffer has insufficient data
 /// - The data is not valid UTF-8
 pub fn decode_string(buf: &mut impl Buf) -> Result<String, DecodeError> {
-    if buf.remaining() < 2 {
-        return Err(DecodeError::UnexpectedEof);
-    }
-
-    let len = buf.get_u16() as usize; // Big-endian length prefix
-
-    if len == 0 {
-        return Ok(String::new());
-    }
-
-    if buf.remaining() < len {
-        return Err(DecodeError::UnexpectedEof);
-    }
+    read_prefixed_string(buf, LengthMode::Fixed, STRING_LEN_WIDTH)
+}
 
-    // Read raw UTF-8 bytes
-    let mut bytes = vec![0u8; len];
-    buf.copy_to_slice(&mut bytes);
+/// Encodes a string into the buffer with a 4-byte length prefix.
+///
+/// Format: 4 bytes (u32 big-endian) length + UTF-8 encoded bytes, the layout
+/// of [`StringFormat::Long`](crate::StringFormat::Long)
+///
+/// # Errors
+///
+/// Returns an error if the string exceeds `u32::MAX` bytes.
+pub fn encode_long_string(buf: &mut BytesMut, s: &str) -> Result<(), EncodeError> {
+    encode_binary(buf, s.as_bytes())
+}
 
-    // Validate and convert UTF-8 to String
-    String::from_utf8(bytes).map_err(|e| DecodeError::InvalidData(format!("Invalid UTF-8: {e}")))
+/// Decodes a string with a 4-byte length prefix.
+///
+/// # Errors
+///
+/// Returns an error if the buffer has insufficient data or the data is not
+/// valid UTF-8.
+pub fn decode_long_string(buf: &mut impl Buf) -> Result<String, DecodeError> {
+    read_prefixed_string(buf, LengthMode::Fixed, BINARY_LEN_WIDTH)
 }
 
 /// Encodes binary data into the buffer with a 4-byte length prefix.
@@ -75,20 +63,7 @@ pub fn decode_string(buf: &mut impl Buf) -> Result<String, DecodeError> {
 ///
 /// Returns an error if the binary data length exceeds `u32::MAX` bytes.
 pub fn encode_binary(buf: &mut BytesMut, data: &[u8]) -> Result<(), EncodeError> {
-    let len = data.len();
-
-    if len > u32::MAX as usize {
-        return Err(EncodeError::InvalidFormat(format!(
-            "Binary data too long: {} bytes (max {})",
-            len,
-            u32::MAX
-        )));
-    }
-
-    #[allow(clippy::cast_possible_truncation)]
-    buf.put_u32(len as u32); // Big-endian
-    buf.put_slice(data);
-    Ok(())
+    put_prefixed(buf, LengthMode::Fixed, BINARY_LEN_WIDTH, data)
 }
 
 /// Decodes binary data from the buffer.
@@ -99,20 +74,7 @@ pub fn encode_binary(buf: &mut BytesMut, data: &[u8]) -> Result<(), EncodeError>
 ///
 /// Returns an error if the buffer has insufficient data.
 pub fn decode_binary(buf: &mut impl Buf) -> Result<Vec<u8>, DecodeError> {
-    if buf.remaining() < 4 {
-        return Err(DecodeError::UnexpectedEof);
-    }
-
-    let len = buf.get_u32() as usize; // Big-endian
-
-    if buf.remaining() < len {
-        return Err(DecodeError::UnexpectedEof);
-    }
-
-    let mut bytes = vec![0u8; len];
-    buf.copy_to_slice(&mut bytes);
-
-    Ok(bytes)
+    read_prefixed(buf, LengthMode::Fixed, BINARY_LEN_WIDTH)
 }
 
 /// Returns the encoded size of a string (2 byte length + UTF-8 bytes).
@@ -130,6 +92,59 @@ pub fn binary_size(data: &[u8]) -> usize {
     4 + data.len()
 }
 
+/// Writes `data` after its length, prefixed as `mode` and `width` say.
+pub(crate) fn put_prefixed(
+    buf: &mut BytesMut,
+    mode: LengthMode,
+    width: usize,
+    data: &[u8],
+) -> Result<(), EncodeError> {
+    let what = if width == STRING_LEN_WIDTH {
+        "String length"
+    } else {
+        "Binary length"
+    };
+    put_length(buf, mode, width, data.len(), what)?;
+    buf.put_slice(data);
+    Ok(())
+}
+
+/// Reads the length written by [`put_prefixed`] and checks that the data
+/// follows.
+pub(crate) fn read_prefixed_len(
+    buf: &mut impl Buf,
+    mode: LengthMode,
+    width: usize,
+) -> Result<usize, DecodeError> {
+    let len = read_length(buf, mode, width)?;
+    if buf.remaining() < len {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    Ok(len)
+}
+
+/// Reads data written by [`put_prefixed`].
+pub(crate) fn read_prefixed(
+    buf: &mut impl Buf,
+    mode: LengthMode,
+    width: usize,
+) -> Result<Vec<u8>, DecodeError> {
+    let len = read_prefixed_len(buf, mode, width)?;
+    let mut bytes = vec![0u8; len];
+    buf.copy_to_slice(&mut bytes);
+    Ok(bytes)
+}
+
+/// Reads a string written by [`put_prefixed`].
+pub(crate) fn read_prefixed_string(
+    buf: &mut impl Buf,
+    mode: LengthMode,
+    width: usize,
+) -> Result<String, DecodeError> {
+    String::from_utf8(read_prefixed(buf, mode, width)?)
+        .map_err(|e| DecodeError::InvalidData(format!("Invalid UTF-8: {e}")))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
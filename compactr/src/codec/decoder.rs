@@ -1,12 +1,27 @@
 //! Decoder for converting binary format to values based on schemas.
 
-use crate::codec::buffer::{decode_binary, decode_string};
-use crate::error::{DecodeError, Result, SchemaError};
-use crate::formats::{datetime, ipaddr, uuid};
-use crate::schema::{IntegerFormat, NumberFormat, SchemaRegistry, SchemaType, StringFormat};
-use crate::value::Value;
-use bytes::Buf;
+use crate::codec::array::{decode_elements, decode_runs, with_expansion_budget};
+use crate::codec::buffer::{read_prefixed, read_prefixed_string};
+use crate::codec::frame::Records;
+use crate::codec::length::LengthMode;
+use crate::codec::object::{read_bitmap, Presence};
+use crate::codec::streaming::missing_bytes;
+use crate::codec::wire::{read_property_size, BINARY_LEN_WIDTH, STRING_LEN_WIDTH};
+use crate::codec::{intern, Decode, DecodeBorrowed, ObjectFormat, ObjectParts};
+use crate::error::{DecodeError, Error, Result, SchemaError};
+#[cfg(feature = "decimal")]
+use crate::formats::decimal;
+use crate::formats::{datetime, geo, ipaddr, uri, uuid};
+use crate::metrics::Operation;
+use crate::schema::{
+    index_order, IntegerFormat, NumberFormat, Projection, Property, SchemaRegistry, SchemaType,
+    StringFormat,
+};
+use crate::value::{Value, REDACTED};
+use bytes::{Buf, Bytes};
 use indexmap::IndexMap;
+use smallvec::smallvec;
+use std::borrow::Cow;
 
 /// Decoder for deserializing values from binary format.
 #[derive(Debug)]
@@ -25,29 +40,197 @@ impl Decoder {
     ///
     /// Returns an error if the buffer doesn't contain valid data for the schema.
     pub fn decode(buf: &mut impl Buf, schema: &SchemaType) -> Result<Value> {
-        Self::decode_with_registry(buf, schema, &SchemaRegistry::new())
+        Self::decode_with_registry(buf, schema, SchemaRegistry::empty())
     }
 
     /// Decodes a value with a schema registry for resolving references.
     ///
+    /// The registry's [`DecoderOptions`](crate::DecoderOptions) bound the
+    /// nesting depth and the sizes of strings and arrays.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the buffer doesn't contain valid data for the schema.
+    /// Returns an error if the buffer doesn't contain valid data for the
+    /// schema or exceeds the registry's decoder limits.
     pub fn decode_with_registry(
         buf: &mut impl Buf,
         schema: &SchemaType,
         registry: &SchemaRegistry,
+    ) -> Result<Value> {
+        let remaining = buf.remaining();
+        let result = with_expansion_budget(|| Self::decode_value(buf, schema, registry, 0));
+        let bytes = remaining - buf.remaining();
+        registry
+            .metrics()
+            .report(Operation::Decode, schema, bytes, &result);
+        result
+    }
+
+    /// Decodes a value that takes up all of `bytes`.
+    ///
+    /// [`decode`](Self::decode) stops after the value and leaves whatever
+    /// follows in the buffer, which hides framing bugs when the input holds
+    /// a single value.
+    ///
+    /// ```rust
+    /// use compactr::{DecodeError, Decoder, SchemaType, Value};
+    /// use compactr::error::Error;
+    ///
+    /// let schema = SchemaType::int32();
+    /// assert_eq!(Decoder::decode_exact(&[0, 0, 0, 7], &schema)?, Value::Integer(7));
+    /// let err = Decoder::decode_exact(&[0, 0, 0, 7, 0], &schema).unwrap_err();
+    /// assert!(matches!(err, Error::Decode(DecodeError::TrailingBytes(1))));
+    /// # Ok::<(), Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::TrailingBytes`] if bytes are left after the
+    /// value, [`DecodeError::Incomplete`] if they end before it, or an error
+    /// if they don't contain valid data for the schema.
+    pub fn decode_exact(bytes: &[u8], schema: &SchemaType) -> Result<Value> {
+        Self::decode_exact_with_registry(bytes, schema, SchemaRegistry::empty())
+    }
+
+    /// Decodes a value that takes up all of `bytes`, with a schema registry
+    /// for resolving references.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::TrailingBytes`] if bytes are left after the
+    /// value, [`DecodeError::Incomplete`] if they end before it, or an error
+    /// if they don't contain valid data for the schema or exceed the
+    /// registry's decoder limits.
+    pub fn decode_exact_with_registry(
+        bytes: &[u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Value> {
+        let (value, len) = Self::decode_counted(bytes, schema, registry)?;
+        if len < bytes.len() {
+            return Err(DecodeError::TrailingBytes(bytes.len() - len).into());
+        }
+        Ok(value)
+    }
+
+    /// Decodes the value at the start of `bytes` and returns it with the
+    /// number of bytes it took, where the next value or frame starts.
+    ///
+    /// When `bytes` end before the value, the error tells how many more to
+    /// read, if the headers received so far do:
+    ///
+    /// ```rust
+    /// use compactr::{DecodeError, Decoder, SchemaRegistry, SchemaType};
+    /// use compactr::error::Error;
+    ///
+    /// // A 2-byte length of 5, then 2 of the 5 bytes
+    /// let partial = [0, 5, b'h', b'e'];
+    /// let result = Decoder::decode_counted(&partial, &SchemaType::string(), &SchemaRegistry::new());
+    /// assert!(matches!(result, Err(Error::Decode(DecodeError::Incomplete(3)))));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::Incomplete`] with the number of bytes missing
+    /// at least, or [`DecodeError::UnexpectedEof`] if that can't be told
+    /// from the headers, when the bytes end before the value. Returns an
+    /// error if they don't start with valid data for the schema or exceed
+    /// the registry's decoder limits.
+    pub fn decode_counted(
+        bytes: &[u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<(Value, usize)> {
+        let mut rest = bytes;
+        match Self::decode_with_registry(&mut rest, schema, registry) {
+            Ok(value) => Ok((value, bytes.len() - rest.len())),
+            Err(Error::Decode(DecodeError::UnexpectedEof)) => {
+                let missing = missing_bytes(bytes, schema, registry);
+                Err(missing
+                    .map_or(DecodeError::UnexpectedEof, DecodeError::Incomplete)
+                    .into())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Decodes a value that is part of a larger one, without reporting it to
+    /// the registry's metrics hook.
+    pub(crate) fn decode_nested(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Value> {
+        with_expansion_budget(|| Self::decode_value(buf, schema, registry, 0))
+    }
+
+    /// Decodes records written by
+    /// [`Encoder::encode_many`](crate::Encoder::encode_many), each preceded
+    /// by its length as a big-endian `u32`.
+    ///
+    /// The iterator stops after the first error, since the framing can't be
+    /// followed past a malformed record.
+    pub fn decode_many<'a>(
+        bytes: &'a [u8],
+        schema: &'a SchemaType,
+    ) -> impl Iterator<Item = Result<Value>> + 'a {
+        Records::new(bytes, schema, Cow::Borrowed(SchemaRegistry::empty()))
+    }
+
+    /// Decodes length-prefixed records with a schema registry for resolving
+    /// references.
+    pub fn decode_many_with_registry<'a>(
+        bytes: &'a [u8],
+        schema: &'a SchemaType,
+        registry: &'a SchemaRegistry,
+    ) -> impl Iterator<Item = Result<Value>> + 'a {
+        Records::new(bytes, schema, Cow::Borrowed(registry))
+    }
+
+    fn decode_value(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+        depth: usize,
+    ) -> Result<Value> {
+        let value = Self::decode_node(buf, schema, registry, depth)?;
+        registry.decode_plugins().apply(schema, value)
+    }
+
+    /// Decodes a value without offering it to the registry's plugins.
+    fn decode_node(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+        depth: usize,
     ) -> Result<Value> {
         match schema {
             SchemaType::Boolean => Self::decode_boolean(buf),
             SchemaType::Integer(format) => Self::decode_integer(buf, *format),
             SchemaType::Number(format) => Self::decode_number(buf, *format),
-            SchemaType::String(format) => Self::decode_string_format(buf, *format),
-            SchemaType::Array(items) => Self::decode_array(buf, items, registry),
-            SchemaType::Object(properties) => Self::decode_object(buf, properties, registry),
+            SchemaType::String(format) => Self::decode_string_format(buf, *format, registry),
+            SchemaType::Array(items) => Self::decode_array(buf, items, registry, depth),
+            SchemaType::RunLengthArray(items) => {
+                let depth = nested(registry, depth)?;
+                let items = decode_runs(
+                    buf,
+                    registry.length_mode(),
+                    registry.decoder_options().max_array_len(),
+                    Buf::copy_to_bytes,
+                    |elem_buf| Self::decode_element(elem_buf, items, registry, depth),
+                )?;
+                Ok(Value::Array(items))
+            }
+            SchemaType::Object(properties) => {
+                let format = registry.object_format();
+                Self::decode_object(buf, properties, registry, format, Selection::ALL, depth)
+            }
             SchemaType::Reference(ref_name) => {
-                let resolved = registry.resolve_ref(ref_name)?;
-                Self::decode_with_registry(buf, &resolved, registry)
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::decode_node(buf, &resolved, registry, depth)
+            }
+            SchemaType::Enum(_) | SchemaType::OneOf { .. } => {
+                Self::decode_tagged(buf, schema, registry, depth)
             }
             SchemaType::Null => Self::decode_null(buf),
         }
@@ -68,6 +251,42 @@ impl Decoder {
 
     fn decode_integer(buf: &mut impl Buf, format: IntegerFormat) -> Result<Value> {
         let value = match format {
+            IntegerFormat::Int128 => {
+                if buf.remaining() < 16 {
+                    return Err(DecodeError::UnexpectedEof.into());
+                }
+                return Ok(Value::Int128(buf.get_i128())); // Big-endian
+            }
+            IntegerFormat::Uint128 => {
+                if buf.remaining() < 16 {
+                    return Err(DecodeError::UnexpectedEof.into());
+                }
+                return Ok(Value::Uint128(buf.get_u128())); // Big-endian
+            }
+            IntegerFormat::Int8 => {
+                if !buf.has_remaining() {
+                    return Err(DecodeError::UnexpectedEof.into());
+                }
+                i64::from(buf.get_i8())
+            }
+            IntegerFormat::Int16 => {
+                if buf.remaining() < 2 {
+                    return Err(DecodeError::UnexpectedEof.into());
+                }
+                i64::from(buf.get_i16()) // Big-endian
+            }
+            IntegerFormat::Uint8 => {
+                if !buf.has_remaining() {
+                    return Err(DecodeError::UnexpectedEof.into());
+                }
+                i64::from(buf.get_u8())
+            }
+            IntegerFormat::Uint16 => {
+                if buf.remaining() < 2 {
+                    return Err(DecodeError::UnexpectedEof.into());
+                }
+                i64::from(buf.get_u16()) // Big-endian
+            }
             IntegerFormat::Int32 => {
                 if buf.remaining() < 4 {
                     return Err(DecodeError::UnexpectedEof.into());
@@ -104,26 +323,47 @@ impl Decoder {
                 }
                 Ok(Value::Double(buf.get_f64())) // Big-endian
             }
+            #[cfg(feature = "decimal")]
+            NumberFormat::Decimal => Ok(Value::Decimal(decimal::decode_decimal(buf)?)),
         }
     }
 
-    fn decode_string_format(buf: &mut impl Buf, format: StringFormat) -> Result<Value> {
+    fn decode_string_format(
+        buf: &mut impl Buf,
+        format: StringFormat,
+        registry: &SchemaRegistry,
+    ) -> Result<Value> {
+        let mode = registry.length_mode();
         match format {
-            StringFormat::Plain => {
-                let s = decode_string(buf)?;
+            StringFormat::Plain | StringFormat::Long => {
+                let width = if format == StringFormat::Plain {
+                    STRING_LEN_WIDTH
+                } else {
+                    BINARY_LEN_WIDTH
+                };
+                let s = read_prefixed_string(buf, mode, width)?;
+                check_string_len(registry, s.len())?;
                 Ok(Value::String(s))
             }
+            #[cfg(feature = "uuid")]
             StringFormat::Uuid => {
                 let u = uuid::decode_uuid(buf)?;
                 Ok(Value::Uuid(u))
             }
-            StringFormat::DateTime => {
-                let dt = datetime::decode_datetime(buf)?;
-                Ok(Value::DateTime(dt))
+            #[cfg(not(feature = "uuid"))]
+            StringFormat::Uuid => Ok(Value::Binary(take_bytes(buf, uuid::uuid_size())?)),
+            StringFormat::DateTime
+            | StringFormat::DateTimeTz
+            | StringFormat::Timestamp(_)
+            | StringFormat::Date
+            | StringFormat::Time => Self::decode_temporal(buf, format),
+            StringFormat::Uri => {
+                let s = uri::decode_uri(buf)?;
+                Ok(Value::String(s))
             }
-            StringFormat::Date => {
-                let d = datetime::decode_date(buf)?;
-                Ok(Value::Date(d))
+            StringFormat::GeoPoint => {
+                let p = geo::decode_geo_point(buf)?;
+                Ok(Value::GeoPoint(p))
             }
             StringFormat::Ipv4 => {
                 let ip = ipaddr::decode_ipv4(buf)?;
@@ -133,71 +373,533 @@ impl Decoder {
                 let ip = ipaddr::decode_ipv6(buf)?;
                 Ok(Value::Ipv6(ip))
             }
+            StringFormat::SocketAddr => {
+                let addr = ipaddr::decode_socket_addr(buf)?;
+                Ok(Value::SocketAddr(addr))
+            }
+            StringFormat::Custom(name) => {
+                let custom = registry.formats().resolve(name)?;
+                custom.decode(buf).map_err(Into::into)
+            }
             StringFormat::Binary => {
-                let data = decode_binary(buf)?;
+                let data = read_prefixed(buf, mode, BINARY_LEN_WIDTH)?;
+                check_string_len(registry, data.len())?;
                 Ok(Value::Binary(data))
             }
         }
     }
 
+    /// Decodes the date and time string formats, to chrono values with the
+    /// `chrono` feature and to integers and bytes without it.
+    fn decode_temporal(buf: &mut impl Buf, format: StringFormat) -> Result<Value> {
+        match format {
+            #[cfg(feature = "chrono")]
+            StringFormat::DateTime => {
+                let dt = datetime::decode_datetime(buf)?;
+                Ok(Value::DateTime(dt))
+            }
+            #[cfg(feature = "chrono")]
+            StringFormat::DateTimeTz => {
+                let dt = datetime::decode_datetime_tz(buf)?;
+                Ok(Value::DateTimeTz(dt))
+            }
+            #[cfg(feature = "chrono")]
+            StringFormat::Timestamp(precision) => {
+                let dt = datetime::decode_timestamp(buf, precision)?;
+                Ok(Value::DateTime(dt))
+            }
+            #[cfg(feature = "chrono")]
+            StringFormat::Date => {
+                let d = datetime::decode_date(buf)?;
+                Ok(Value::Date(d))
+            }
+            #[cfg(feature = "chrono")]
+            StringFormat::Time => {
+                let t = datetime::decode_time(buf)?;
+                Ok(Value::Time(t))
+            }
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::DateTime => Ok(Value::Integer(datetime::decode_datetime_millis(buf)?)),
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::DateTimeTz => {
+                let bytes = take_bytes(buf, datetime::datetime_tz_size())?;
+                datetime::decode_datetime_tz_millis(&mut bytes.as_slice())?;
+                Ok(Value::Binary(bytes))
+            }
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::Timestamp(_) => {
+                if buf.remaining() < datetime::timestamp_size() {
+                    return Err(DecodeError::UnexpectedEof.into());
+                }
+                Ok(Value::Integer(buf.get_i64()))
+            }
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::Date => {
+                if buf.remaining() < datetime::date_size() {
+                    return Err(DecodeError::UnexpectedEof.into());
+                }
+                Ok(Value::Integer(buf.get_i32().into()))
+            }
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::Time => {
+                if buf.remaining() < datetime::time_size() {
+                    return Err(DecodeError::UnexpectedEof.into());
+                }
+                match buf.get_u32() {
+                    // Up to the end of a leap second
+                    millis @ 0..=86_400_999 => Ok(Value::Integer(millis.into())),
+                    millis => {
+                        Err(DecodeError::InvalidData(format!("Invalid time: {millis} ms")).into())
+                    }
+                }
+            }
+            _ => Err(
+                DecodeError::SchemaMismatch(format!("Not a date/time format: {format:?}")).into(),
+            ),
+        }
+    }
+
     fn decode_array(
         buf: &mut impl Buf,
         items_schema: &SchemaType,
         registry: &SchemaRegistry,
+        depth: usize,
     ) -> Result<Value> {
-        // Compactr.js format: Each array element is prefixed with its size
-        // No overall array length - read elements until buffer is exhausted
-        //
-        // Format: [size1, elem1, size2, elem2, ...]
-        // where size is a 1-byte value
+        let depth = nested(registry, depth)?;
+        let items = decode_elements(
+            buf,
+            registry.array_format(),
+            registry.length_mode(),
+            registry.decoder_options().max_array_len(),
+            |elem_buf| Self::decode_element(elem_buf, items_schema, registry, depth),
+            |buf| Self::decode_value(buf, items_schema, registry, depth),
+        )?;
+        Ok(Value::Array(items))
+    }
 
-        let mut items = Vec::new();
+    /// Decodes an array element from its own buffer.
+    fn decode_element(
+        elem_buf: &mut impl Buf,
+        items_schema: &SchemaType,
+        registry: &SchemaRegistry,
+        depth: usize,
+    ) -> Result<Value> {
+        let value = Self::decode_value(elem_buf, items_schema, registry, depth)?;
+        if registry.decoder_options().strict() && elem_buf.has_remaining() {
+            return Err(DecodeError::InvalidData(format!(
+                "{} bytes left over after array element",
+                elem_buf.remaining()
+            ))
+            .into());
+        }
+        Ok(value)
+    }
 
-        while buf.has_remaining() {
-            // Read element size
-            let elem_size = buf.get_u8() as usize;
+    fn decode_object(
+        buf: &mut impl Buf,
+        properties: &IndexMap<String, Property>,
+        registry: &SchemaRegistry,
+        format: ObjectFormat,
+        selection: Selection<'_>,
+        depth: usize,
+    ) -> Result<Value> {
+        let depth = nested(registry, depth)?;
+        let strict = registry.decoder_options().strict();
+        let redact = registry.decoder_options().redact();
+        let mut obj = IndexMap::with_capacity(properties.len());
+        Self::read_properties(
+            buf,
+            properties,
+            format,
+            registry.length_mode(),
+            Buf::copy_to_bytes,
+            |prop_name, prop_schema, prop_buf| {
+                if strict && obj.contains_key(prop_name) {
+                    return Err(DecodeError::InvalidData(format!(
+                        "Duplicate property {prop_name}"
+                    ))
+                    .into());
+                }
+                if redact && properties.get(prop_name).is_some_and(|p| p.sensitive) {
+                    obj.insert(intern(prop_name), Value::from(REDACTED));
+                    return Ok(());
+                }
+                // Decode property value (handles strings without length prefix)
+                let prop_value = Self::decode_property(prop_buf, prop_schema, registry, depth)?;
+                if strict && prop_buf.has_remaining() {
+                    return Err(DecodeError::InvalidData(format!(
+                        "{} bytes left over after property {prop_name}",
+                        prop_buf.remaining()
+                    ))
+                    .into());
+                }
+                obj.insert(intern(prop_name), prop_value);
+                Ok(())
+            },
+            selection,
+        )?;
+        Ok(Value::Object(obj))
+    }
 
-            // Read element data
-            if buf.remaining() < elem_size {
-                return Err(DecodeError::UnexpectedEof.into());
+    /// Decodes an object written by
+    /// [`Encoder::encode_partial`](crate::Encoder::encode_partial), holding
+    /// only some of its properties.
+    ///
+    /// The result contains the properties present on the wire; required
+    /// properties may be missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object or the buffer doesn't
+    /// contain valid data for it.
+    pub fn decode_partial(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Value> {
+        match schema {
+            SchemaType::Object(properties) => {
+                let selection = Selection {
+                    fields: None,
+                    check_required: false,
+                };
+                let format = ObjectFormat::Indexed;
+                with_expansion_budget(|| {
+                    Self::decode_object(buf, properties, registry, format, selection, 0)
+                })
             }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::decode_partial(buf, &resolved, registry)
+            }
+            _ => Err(DecodeError::SchemaMismatch(format!(
+                "expected object schema, got {schema:?}"
+            ))
+            .into()),
+        }
+    }
+
+    /// Decodes the properties of an object kept by a [`Projection`],
+    /// skipping the others.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, a required property
+    /// is missing, or the buffer doesn't contain valid data for it.
+    pub fn decode_projected(buf: &mut impl Buf, projection: &Projection) -> Result<Value> {
+        Self::decode_projected_with_registry(buf, projection, SchemaRegistry::empty())
+    }
 
-            let mut elem_bytes = vec![0u8; elem_size];
-            buf.copy_to_slice(&mut elem_bytes);
-            let mut elem_buf = &elem_bytes[..];
+    /// Decodes the properties kept by a [`Projection`] with a schema
+    /// registry for resolving references.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, a required property
+    /// is missing, or the buffer doesn't contain valid data for it.
+    pub fn decode_projected_with_registry(
+        buf: &mut impl Buf,
+        projection: &Projection,
+        registry: &SchemaRegistry,
+    ) -> Result<Value> {
+        let remaining = buf.remaining();
+        let selection = Selection {
+            fields: Some(projection.fields()),
+            check_required: true,
+        };
+        let schema = projection.schema();
+        let result = Self::decode_selected(buf, schema, registry, selection);
+        let bytes = remaining - buf.remaining();
+        registry
+            .metrics()
+            .report(Operation::Decode, schema, bytes, &result);
+        result
+    }
 
-            let item = Self::decode_with_registry(&mut elem_buf, items_schema, registry)?;
-            items.push(item);
+    fn decode_selected(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+        selection: Selection<'_>,
+    ) -> Result<Value> {
+        match schema {
+            SchemaType::Object(properties) => {
+                let format = registry.object_format();
+                with_expansion_budget(|| {
+                    Self::decode_object(buf, properties, registry, format, selection, 0)
+                })
+            }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::decode_selected(buf, &resolved, registry, selection)
+            }
+            _ => Err(DecodeError::SchemaMismatch(format!(
+                "expected object schema, got {schema:?}"
+            ))
+            .into()),
         }
+    }
 
-        Ok(Value::Array(items))
+    /// Decodes an object whose header and content were encoded separately
+    /// by [`Encoder::encode_parts`](crate::Encoder::encode_parts).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, the header and
+    /// content don't match, or the data is invalid for the schema.
+    pub fn decode_parts(
+        parts: &ObjectParts,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Value> {
+        match schema {
+            SchemaType::Object(properties) => {
+                let format = ObjectFormat::Indexed;
+                let mut buf = parts.join(schema, registry)?;
+                with_expansion_budget(|| {
+                    Self::decode_object(&mut buf, properties, registry, format, Selection::ALL, 0)
+                })
+            }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::decode_parts(parts, &resolved, registry)
+            }
+            _ => Err(DecodeError::SchemaMismatch(format!(
+                "expected object schema, got {schema:?}"
+            ))
+            .into()),
+        }
     }
 
-    fn decode_object(
+    /// Decodes a value directly into `T` without building a [`Value`] tree.
+    ///
+    /// References are resolved before handing the schema to
+    /// [`Decode::decode_with_schema`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer doesn't contain valid data for the schema
+    /// or the data cannot be represented as `T`.
+    pub fn decode_as<T: Decode>(
         buf: &mut impl Buf,
-        properties: &IndexMap<String, crate::schema::Property>,
+        schema: &SchemaType,
         registry: &SchemaRegistry,
-    ) -> Result<Value> {
+    ) -> Result<T> {
+        match schema {
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                T::decode_with_schema(buf, &resolved, registry)
+            }
+            _ => T::decode_with_schema(buf, schema, registry),
+        }
+    }
+
+    /// Decodes an object property into `T`.
+    ///
+    /// Use this from [`Decode`] implementations on the per-property buffers
+    /// handed out by [`Decoder::decode_fields`]: property values are framed
+    /// by their size, so plain strings carry no length prefix.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer doesn't contain valid data for the schema
+    /// or the data cannot be represented as `T`.
+    pub fn decode_property_as<T: Decode>(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<T> {
+        match schema {
+            // Referenced schemas keep their top-level layout inside objects
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                T::decode_with_schema(buf, &resolved, registry)
+            }
+            _ => T::decode_property(buf, schema, registry),
+        }
+    }
+
+    /// Walks the properties of an encoded object, calling `visit` with the
+    /// name, schema and framed bytes of each property present.
+    ///
+    /// This is the building block for [`Decode`] implementations of struct
+    /// types. Properties are visited in wire order and required properties
+    /// are checked once all of them have been read.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, the buffer is
+    /// malformed, a required property is missing, or `visit` fails.
+    pub fn decode_fields<F>(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+        visit: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&str, &SchemaType, &mut Bytes) -> Result<()>,
+    {
+        match schema {
+            SchemaType::Object(properties) => {
+                let (format, mode) = (registry.object_format(), registry.length_mode());
+                Self::decode_properties(buf, properties, format, mode, Buf::copy_to_bytes, visit)
+            }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::decode_fields(buf, &resolved, registry, visit)
+            }
+            _ => Err(DecodeError::SchemaMismatch(format!(
+                "expected object schema, got {schema:?}"
+            ))
+            .into()),
+        }
+    }
+
+    /// Decodes a value into `T`, borrowing from the input where `T` allows.
+    ///
+    /// Borrowed counterpart of [`Decoder::decode_as`]: `&str` and `Cow<str>`
+    /// fields point into `buf` instead of being copied.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer doesn't contain valid data for the schema
+    /// or the data cannot be represented as `T`.
+    pub fn decode_borrowed<'de, T: DecodeBorrowed<'de>>(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<T> {
+        match schema {
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                T::decode_borrowed(buf, &resolved, registry)
+            }
+            _ => T::decode_borrowed(buf, schema, registry),
+        }
+    }
+
+    /// Decodes an object property into `T`, borrowing from the input where
+    /// `T` allows.
+    ///
+    /// Borrowed counterpart of [`Decoder::decode_property_as`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer doesn't contain valid data for the schema
+    /// or the data cannot be represented as `T`.
+    pub fn decode_property_borrowed<'de, T: DecodeBorrowed<'de>>(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<T> {
+        match schema {
+            // Referenced schemas keep their top-level layout inside objects
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                T::decode_borrowed(buf, &resolved, registry)
+            }
+            _ => T::decode_property_borrowed(buf, schema, registry),
+        }
+    }
+
+    /// Walks the properties of an encoded object like
+    /// [`Decoder::decode_fields`], handing out slices of the input instead of
+    /// copies.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, the buffer is
+    /// malformed, a required property is missing, or `visit` fails.
+    pub fn decode_fields_borrowed<'de, F>(
+        buf: &mut &'de [u8],
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+        visit: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&str, &SchemaType, &mut &'de [u8]) -> Result<()>,
+    {
+        match schema {
+            SchemaType::Object(properties) => {
+                let take = |buf: &mut &'de [u8], n| {
+                    let (head, tail) = buf.split_at(n);
+                    *buf = tail;
+                    head
+                };
+                let (format, mode) = (registry.object_format(), registry.length_mode());
+                Self::decode_properties(buf, properties, format, mode, take, visit)
+            }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::decode_fields_borrowed(buf, &resolved, registry, visit)
+            }
+            _ => Err(DecodeError::SchemaMismatch(format!(
+                "expected object schema, got {schema:?}"
+            ))
+            .into()),
+        }
+    }
+
+    /// Reads the properties of an object, using `take` to split each
+    /// property's bytes off the buffer.
+    pub(crate) fn decode_properties<B, P, F>(
+        buf: &mut B,
+        properties: &IndexMap<String, Property>,
+        format: ObjectFormat,
+        mode: LengthMode,
+        take: impl Fn(&mut B, usize) -> P,
+        visit: F,
+    ) -> Result<()>
+    where
+        B: Buf,
+        F: FnMut(&str, &SchemaType, &mut P) -> Result<()>,
+    {
+        Self::read_properties(buf, properties, format, mode, take, visit, Selection::ALL)
+    }
+
+    fn read_properties<B, P, F>(
+        buf: &mut B,
+        properties: &IndexMap<String, Property>,
+        format: ObjectFormat,
+        mode: LengthMode,
+        take: impl Fn(&mut B, usize) -> P,
+        mut visit: F,
+        selection: Selection<'_>,
+    ) -> Result<()>
+    where
+        B: Buf,
+        F: FnMut(&str, &SchemaType, &mut P) -> Result<()>,
+    {
         // Compactr.js 3.x format: Interleaved structure
         // [num_props, index0, size0, value0, index1, size1, value1, ...]
         // Properties are indexed alphabetically by name
 
+        // Create alphabetically sorted property list for index-based access
+        let props_vec = index_order(properties);
+
+        if format == ObjectFormat::Bitmap {
+            // [bitmap..., size0, value0, size1, value1, ...] in alphabetical order
+            let present = read_bitmap(buf, props_vec.iter().map(|(_, property)| *property))?;
+            for ((prop_name, prop_def), _) in props_vec.iter().zip(present).filter(|(_, p)| *p) {
+                if selection.skips(prop_name) {
+                    Self::take_property(buf, prop_def, mode, |buf: &mut B, n| buf.advance(n))?;
+                    continue;
+                }
+                let mut prop_buf = Self::take_property(buf, prop_def, mode, &take)?;
+                visit(prop_name, &prop_def.schema_type, &mut prop_buf)?;
+            }
+            return Ok(());
+        }
+
         if !buf.has_remaining() {
             return Err(DecodeError::UnexpectedEof.into());
         }
 
         // Read number of properties present
         let num_props = buf.get_u8() as usize;
-
-        // Create alphabetically sorted property list for index-based access
-        let mut props_vec: Vec<(String, crate::schema::Property)> = properties
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
-        props_vec.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut seen: Presence = smallvec![false; props_vec.len()];
 
         // Decode each property: index, size, value (interleaved)
-        let mut obj = IndexMap::new();
         for _ in 0..num_props {
             if !buf.has_remaining() {
                 return Err(DecodeError::UnexpectedEof.into());
@@ -209,91 +911,145 @@ impl Decoder {
             if prop_idx >= props_vec.len() {
                 return Err(DecodeError::InvalidData(format!(
                     "Property index {prop_idx} out of range (max {})",
-                    props_vec.len() - 1
+                    props_vec.len().saturating_sub(1)
                 ))
                 .into());
             }
 
-            let (prop_name, prop_def) = &props_vec[prop_idx];
-
-            // Determine if this is a compound type (for future use)
-            let _is_compound = matches!(
-                prop_def.schema_type,
-                SchemaType::Array(_) | SchemaType::Object(_)
-            );
-
-            // Read size with appropriate decoding
-            if !buf.has_remaining() {
-                return Err(DecodeError::UnexpectedEof.into());
+            let (prop_name, prop_def) = props_vec[prop_idx];
+            seen[prop_idx] = true;
+            if selection.skips(prop_name) {
+                Self::take_property(buf, prop_def, mode, |buf: &mut B, n| buf.advance(n))?;
+                continue;
             }
-
-            let size_byte = buf.get_u8();
-            let prop_size = if size_byte == 0 {
-                // Compound type or large value: multi-byte size follows
-                if buf.remaining() < 1 {
-                    return Err(DecodeError::UnexpectedEof.into());
-                }
-                let next_byte = buf.get_u8();
-                if next_byte > 0 || buf.remaining() < 1 {
-                    // Single byte size after 0x00 flag
-                    next_byte as usize
-                } else {
-                    // Two-byte size (u16) after 0x00 flag
-                    if buf.remaining() < 1 {
-                        return Err(DecodeError::UnexpectedEof.into());
-                    }
-                    let high_byte = buf.get_u8();
-                    ((next_byte as usize) << 8) | (high_byte as usize)
-                }
-            } else {
-                size_byte as usize
-            };
-
-            // Read exactly prop_size bytes for this property
-            if buf.remaining() < prop_size {
-                return Err(DecodeError::UnexpectedEof.into());
-            }
-
-            let mut prop_bytes = vec![0u8; prop_size];
-            buf.copy_to_slice(&mut prop_bytes);
-            let mut prop_buf = &prop_bytes[..];
-
-            // Decode property value (handles strings without length prefix)
-            let prop_value =
-                Self::decode_property_value(&mut prop_buf, &prop_def.schema_type, registry)?;
-
-            obj.insert(prop_name.clone(), prop_value);
+            let mut prop_buf = Self::take_property(buf, prop_def, mode, &take)?;
+            visit(prop_name, &prop_def.schema_type, &mut prop_buf)?;
         }
 
         // Check for missing required fields
-        for (prop_name, prop_def) in properties {
-            if prop_def.required && !obj.contains_key(prop_name) {
-                return Err(SchemaError::MissingField(prop_name.clone()).into());
+        for ((prop_name, prop_def), seen) in props_vec.iter().zip(seen) {
+            if selection.check_required && prop_def.required && !seen {
+                return Err(SchemaError::MissingField((*prop_name).clone()).into());
             }
         }
 
-        Ok(Value::Object(obj))
+        Ok(())
+    }
+
+    /// Reads a property's size header and splits its bytes off the buffer.
+    fn take_property<B: Buf, P>(
+        buf: &mut B,
+        property: &Property,
+        mode: LengthMode,
+        take: impl Fn(&mut B, usize) -> P,
+    ) -> Result<P> {
+        // Read size with appropriate decoding
+        let prop_size = read_property_size(buf, property.size_width, mode)?;
+
+        // Read exactly prop_size bytes for this property
+        if buf.remaining() < prop_size {
+            return Err(DecodeError::UnexpectedEof.into());
+        }
+        Ok(take(buf, prop_size))
     }
 
     /// Decodes a property value (strings without length prefix, etc.)
-    fn decode_property_value(
+    pub(crate) fn decode_property_value(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Value> {
+        with_expansion_budget(|| Self::decode_property(buf, schema, registry, 0))
+    }
+
+    fn decode_property(
         buf: &mut impl Buf,
         schema: &SchemaType,
         registry: &SchemaRegistry,
+        depth: usize,
     ) -> Result<Value> {
         match schema {
-            SchemaType::String(StringFormat::Plain) => {
+            SchemaType::String(StringFormat::Plain | StringFormat::Long) => {
                 // For strings in objects: decode raw UTF-8 bytes (no length prefix)
                 let remaining = buf.remaining();
+                check_string_len(registry, remaining)?;
                 let mut bytes = vec![0u8; remaining];
                 buf.copy_to_slice(&mut bytes);
 
-                String::from_utf8(bytes)
+                let value = String::from_utf8(bytes)
                     .map(Value::String)
-                    .map_err(|e| DecodeError::InvalidData(format!("Invalid UTF-8: {e}")).into())
+                    .map_err(|e| DecodeError::InvalidData(format!("Invalid UTF-8: {e}")))?;
+                registry.decode_plugins().apply(schema, value)
             }
             // For all other types, use normal decoding
-            _ => Self::decode_with_registry(buf, schema, registry),
+            _ => Self::decode_value(buf, schema, registry, depth),
+        }
+    }
+
+    fn decode_tagged(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+        depth: usize,
+    ) -> Result<Value> {
+        let (name, payload) = Self::decode_variant(buf, schema)?;
+        let SchemaType::OneOf { discriminator, .. } = schema else {
+            return Ok(Value::String(name.to_owned()));
+        };
+
+        // The discriminator comes first, followed by the payload properties
+        let mut obj = IndexMap::new();
+        obj.insert(intern(discriminator), Value::String(name.to_owned()));
+        if let Some(payload) = payload {
+            // The payload's properties join the variant's object, one level
+            match Self::decode_value(buf, payload, registry, depth)? {
+                Value::Object(fields) => obj.extend(fields),
+                other => {
+                    return Err(DecodeError::SchemaMismatch(format!(
+                        "oneOf variant {name} must be an object, got {other:?}"
+                    ))
+                    .into())
+                }
+            }
+        }
+        Ok(Value::Object(obj))
+    }
+
+    /// Reads the variant index of an enum or `oneOf` schema and returns the
+    /// variant name together with the schema of the payload that follows,
+    /// if any.
+    ///
+    /// This is the building block for [`Decode`] implementations of Rust
+    /// enums, mirroring [`Encoder::encode_variant`](crate::Encoder::encode_variant).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an enum or `oneOf` or the index
+    /// is out of range.
+    pub fn decode_variant<'s>(
+        buf: &mut impl Buf,
+        schema: &'s SchemaType,
+    ) -> Result<(&'s str, Option<&'s SchemaType>)> {
+        if !buf.has_remaining() {
+            return Err(DecodeError::UnexpectedEof.into());
+        }
+        let idx = usize::from(buf.get_u8());
+        let out_of_range = || DecodeError::InvalidData(format!("Variant index {idx} out of range"));
+
+        match schema {
+            SchemaType::Enum(variants) => variants
+                .get(idx)
+                .map(|name| (name.as_str(), None))
+                .ok_or_else(|| out_of_range().into()),
+            SchemaType::OneOf { variants, .. } => match variants.get_index(idx) {
+                Some((name, SchemaType::Null)) => Ok((name.as_str(), None)),
+                Some((name, payload)) => Ok((name.as_str(), Some(payload))),
+                None => Err(out_of_range().into()),
+            },
+            _ => Err(DecodeError::SchemaMismatch(format!(
+                "expected enum or oneOf schema, got {schema:?}"
+            ))
+            .into()),
         }
     }
 
@@ -314,6 +1070,62 @@ impl Decoder {
     }
 }
 
+/// Returns the depth of values nested in one at `depth`, or an error if that
+/// exceeds the registry's [`DecoderOptions::max_depth`](crate::DecoderOptions::max_depth).
+fn nested(registry: &SchemaRegistry, depth: usize) -> Result<usize> {
+    let max_depth = registry.decoder_options().max_depth();
+    if depth >= max_depth {
+        return Err(
+            DecodeError::InvalidData(format!("Nesting deeper than {max_depth} levels")).into(),
+        );
+    }
+    Ok(depth + 1)
+}
+
+/// Reads the `len` bytes of a format decoded raw without its feature.
+#[cfg(any(not(feature = "uuid"), not(feature = "chrono")))]
+fn take_bytes(buf: &mut impl Buf, len: usize) -> Result<Vec<u8>> {
+    if buf.remaining() < len {
+        return Err(DecodeError::UnexpectedEof.into());
+    }
+    let mut bytes = vec![0; len];
+    buf.copy_to_slice(&mut bytes);
+    Ok(bytes)
+}
+
+fn check_string_len(registry: &SchemaRegistry, len: usize) -> Result<()> {
+    let max_len = registry.decoder_options().max_string_len();
+    if len > max_len {
+        return Err(DecodeError::InvalidData(format!(
+            "String of {len} bytes exceeds the limit of {max_len}"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Which properties of an object are decoded.
+#[derive(Debug, Clone, Copy)]
+struct Selection<'a> {
+    /// Names of the properties to decode, skipping the others; `None`
+    /// decodes all of them
+    fields: Option<&'a [String]>,
+    /// Whether every required property must be present
+    check_required: bool,
+}
+
+impl Selection<'_> {
+    const ALL: Selection<'static> = Selection {
+        fields: None,
+        check_required: true,
+    };
+
+    fn skips(&self, name: &str) -> bool {
+        self.fields
+            .is_some_and(|fields| !fields.iter().any(|field| field == name))
+    }
+}
+
 impl Default for Decoder {
     fn default() -> Self {
         Self::new()
@@ -324,6 +1136,7 @@ impl Default for Decoder {
 mod tests {
     use super::*;
     use crate::codec::Encoder;
+    use crate::schema::{SizeWidth, TimestampPrecision};
 
     #[test]
     fn test_decode_boolean() {
@@ -349,6 +1162,74 @@ mod tests {
         assert_eq!(decoded, Value::Integer(42));
     }
 
+    #[test]
+    fn test_decode_exact() {
+        let schema = SchemaType::string();
+        let registry = SchemaRegistry::new();
+        let mut enc = Encoder::new();
+        enc.encode(&Value::from("ab"), &schema).unwrap();
+        enc.encode(&Value::from("c"), &schema).unwrap();
+        let bytes = enc.finish();
+
+        let (first, len) = Decoder::decode_counted(&bytes, &schema, &registry).unwrap();
+        assert_eq!((first, len), (Value::from("ab"), 4));
+        let second = Decoder::decode_exact(&bytes[len..], &schema).unwrap();
+        assert_eq!(second, Value::from("c"));
+        let err = Decoder::decode_exact(&bytes, &schema).unwrap_err();
+        assert!(matches!(err, Error::Decode(DecodeError::TrailingBytes(3))));
+
+        // Truncated input tells how much is missing, where headers do
+        let err = Decoder::decode_exact(&bytes[..3], &schema).unwrap_err();
+        assert!(matches!(err, Error::Decode(DecodeError::Incomplete(1))));
+        let object =
+            SchemaType::object([("id", SchemaType::int32()), ("name", SchemaType::string())]);
+        let mut enc = Encoder::new();
+        let value = Value::object([("id", Value::Integer(7)), ("name", Value::from("abc"))]);
+        enc.encode(&value, &object).unwrap();
+        let bytes = enc.finish();
+        for end in 0..bytes.len() {
+            let result = Decoder::decode_counted(&bytes[..end], &object, &registry);
+            let Err(Error::Decode(DecodeError::Incomplete(missing))) = result else {
+                panic!("{end}: {result:?}");
+            };
+            assert!(end + missing <= bytes.len(), "{end}: {missing}");
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_small_integers() {
+        for (schema, value) in [
+            (SchemaType::int8(), i64::from(i8::MIN)),
+            (SchemaType::int16(), i64::from(i16::MAX)),
+            (SchemaType::uint8(), i64::from(u8::MAX)),
+            (SchemaType::uint16(), i64::from(u16::MAX)),
+        ] {
+            let mut enc = Encoder::new();
+            enc.encode(&Value::Integer(value), &schema).unwrap();
+            let bytes = enc.finish();
+
+            let decoded = Decoder::decode(&mut bytes.as_ref(), &schema).unwrap();
+            assert_eq!(decoded, Value::Integer(value));
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_128_bit_integers() {
+        let cases = [
+            (SchemaType::int128(), Value::Int128(i128::MIN)),
+            (SchemaType::uint128(), Value::Uint128(u128::MAX)),
+        ];
+        for (schema, value) in cases {
+            let mut enc = Encoder::new();
+            enc.encode(&value, &schema).unwrap();
+            let bytes = enc.finish();
+            assert_eq!(bytes.len(), 16);
+
+            let decoded = Decoder::decode(&mut bytes.as_ref(), &schema).unwrap();
+            assert_eq!(decoded, value);
+        }
+    }
+
     #[test]
     fn test_decode_string() {
         let mut enc = Encoder::new();
@@ -380,7 +1261,7 @@ mod tests {
 
     #[test]
     fn test_roundtrip_object() {
-        use crate::schema::Property;
+        use Property;
 
         let mut properties = IndexMap::new();
         properties.insert("name".to_owned(), Property::required(SchemaType::string()));
@@ -389,8 +1270,8 @@ mod tests {
         let schema = SchemaType::object(properties);
 
         let mut obj = IndexMap::new();
-        obj.insert("name".to_owned(), Value::String("Alice".to_owned()));
-        obj.insert("age".to_owned(), Value::Integer(30));
+        obj.insert("name".into(), Value::String("Alice".to_owned()));
+        obj.insert("age".into(), Value::Integer(30));
         let value = Value::Object(obj);
 
         let mut enc = Encoder::new();
@@ -401,4 +1282,302 @@ mod tests {
         let decoded = Decoder::decode(&mut buf, &schema).unwrap();
         assert_eq!(decoded, value);
     }
+
+    #[test]
+    fn test_property_size_widths() {
+        let mut properties = IndexMap::new();
+        properties.insert("a".to_owned(), Property::optional(SchemaType::string()));
+        properties.insert(
+            "b".to_owned(),
+            Property::optional(SchemaType::array(SchemaType::int32())),
+        );
+        properties.insert(
+            "c".to_owned(),
+            Property::optional(SchemaType::string()).with_size_width(SizeWidth::U16),
+        );
+        properties.insert(
+            "d".to_owned(),
+            Property::optional(SchemaType::binary()).with_size_width(SizeWidth::U32),
+        );
+        let schema = SchemaType::object(properties);
+
+        let mut obj = IndexMap::new();
+        obj.insert("a".into(), Value::from(""));
+        obj.insert("b".into(), Value::Array(Vec::new()));
+        obj.insert("c".into(), Value::from("x".repeat(300)));
+        obj.insert("d".into(), Value::Binary(vec![7; 70_000]));
+        let value = Value::Object(obj);
+
+        let mut enc = Encoder::new();
+        enc.encode(&value, &schema).unwrap();
+        let bytes = enc.finish();
+        // Empty values keep the 0x00 flag, so the next index isn't read as a size
+        assert_eq!(bytes[..8], [4, 0, 0, 0, 1, 0, 0, 2]);
+        assert_eq!(bytes[8..10], [0x01, 0x2C]);
+        assert_eq!(
+            Decoder::decode(&mut bytes.as_ref(), &schema).unwrap(),
+            value
+        );
+
+        // The compactr.js header holds at most 255 bytes
+        let mut properties = IndexMap::new();
+        properties.insert("a".to_owned(), Property::required(SchemaType::string()));
+        let mut obj = IndexMap::new();
+        obj.insert("a".into(), Value::from("x".repeat(300)));
+        let result = Encoder::new().encode(&Value::Object(obj), &SchemaType::object(properties));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_raw_formats() {
+        // The values the formats decode to without the chrono and uuid
+        // features encode with or without them
+        let datetime = [0x07, 0xe8, 1, 15, 10, 30, 0, 0x01, 0xf4];
+        let mut datetime_tz = datetime.to_vec();
+        datetime_tz.extend_from_slice(&60_i16.to_be_bytes());
+        let uuid = *b"\x55\x0e\x84\x00\xe2\x9b\x41\xd4\xa7\x16\x44\x66\x55\x44\x00\x00";
+        for (schema, raw, bytes) in [
+            (
+                SchemaType::string_datetime(),
+                Value::Integer(1_705_314_600_500),
+                datetime.to_vec(),
+            ),
+            (
+                SchemaType::string_datetime_tz(),
+                Value::Binary(datetime_tz.clone()),
+                datetime_tz,
+            ),
+            (
+                SchemaType::timestamp(TimestampPrecision::Millis),
+                Value::Integer(1_705_314_600_500),
+                1_705_314_600_500_i64.to_be_bytes().to_vec(),
+            ),
+            (
+                SchemaType::string_date(),
+                Value::Integer(19_737),
+                19_737_i32.to_be_bytes().to_vec(),
+            ),
+            (
+                SchemaType::string_time(),
+                Value::Integer(37_800_500),
+                37_800_500_u32.to_be_bytes().to_vec(),
+            ),
+            (
+                SchemaType::string_uuid(),
+                Value::Binary(uuid.to_vec()),
+                uuid.to_vec(),
+            ),
+        ] {
+            let mut enc = Encoder::new();
+            enc.encode(&raw, &schema).unwrap();
+            assert_eq!(enc.as_bytes(), bytes, "{schema}");
+
+            let decoded = Decoder::decode(&mut bytes.as_slice(), &schema).unwrap();
+            let mut enc = Encoder::new();
+            enc.encode(&decoded, &schema).unwrap();
+            assert_eq!(enc.as_bytes(), bytes, "{schema}");
+        }
+
+        let mut enc = Encoder::new();
+        enc.encode(
+            &Value::from("550e8400-e29b-41d4-a716-446655440000"),
+            &SchemaType::string_uuid(),
+        )
+        .unwrap();
+        assert_eq!(enc.as_bytes(), uuid);
+        for (schema, raw) in [
+            (SchemaType::string_time(), Value::Integer(86_401_000)),
+            (SchemaType::string_date(), Value::Integer(i64::MAX)),
+            (SchemaType::string_uuid(), Value::Binary(vec![0; 15])),
+            (SchemaType::string_datetime_tz(), Value::Binary(vec![0; 11])),
+        ] {
+            assert!(Encoder::new().encode(&raw, &schema).is_err(), "{schema}");
+        }
+    }
+
+    #[test]
+    fn test_long_string() {
+        let registry = SchemaRegistry::new();
+        let text = "log line\n".repeat(10_000);
+        let value = Value::from(text.as_str());
+        assert!(Encoder::new()
+            .encode(&value, &SchemaType::string())
+            .is_err());
+
+        let mut enc = Encoder::new();
+        enc.encode(&value, &SchemaType::long_string()).unwrap();
+        let bytes = enc.finish();
+        assert_eq!(bytes[..4], 90_000u32.to_be_bytes());
+        let decoded = Decoder::decode(&mut bytes.as_ref(), &SchemaType::long_string()).unwrap();
+        assert_eq!(decoded, value);
+        let typed: String =
+            Decoder::decode_as(&mut bytes.as_ref(), &SchemaType::long_string(), &registry).unwrap();
+        assert_eq!(typed, text);
+        let borrowed: &str =
+            Decoder::decode_borrowed(&mut bytes.as_ref(), &SchemaType::long_string(), &registry)
+                .unwrap();
+        assert_eq!(borrowed, text);
+
+        // Inside objects the property size frames the string
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "body".to_owned(),
+            Property::required(SchemaType::long_string()).with_size_width(SizeWidth::U32),
+        );
+        let schema = SchemaType::object(properties);
+        let mut obj = IndexMap::new();
+        obj.insert("body".into(), value);
+        let value = Value::Object(obj);
+        let mut enc = Encoder::new();
+        enc.encode(&value, &schema).unwrap();
+        let bytes = enc.finish();
+        assert_eq!(bytes.len(), 1 + 1 + 4 + text.len());
+        assert_eq!(
+            Decoder::decode(&mut bytes.as_ref(), &schema).unwrap(),
+            value
+        );
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Player {
+        name: Value,
+        level: i32,
+        score: f64,
+    }
+
+    impl Decode for Player {
+        fn decode_with_schema(
+            buf: &mut impl Buf,
+            schema: &SchemaType,
+            registry: &SchemaRegistry,
+        ) -> Result<Self> {
+            let (mut name, mut level, mut score) = (None, None, None);
+            Decoder::decode_fields(buf, schema, registry, |field, schema, buf| {
+                match field {
+                    "name" => name = Some(Decoder::decode_property_as(buf, schema, registry)?),
+                    "level" => level = Some(Decoder::decode_property_as(buf, schema, registry)?),
+                    "score" => score = Some(Decoder::decode_property_as(buf, schema, registry)?),
+                    _ => {}
+                }
+                Ok(())
+            })?;
+            let missing = |field: &str| SchemaError::MissingField(field.to_owned());
+            Ok(Self {
+                name: name.ok_or_else(|| missing("name"))?,
+                level: level.ok_or_else(|| missing("level"))?,
+                score: score.unwrap_or_default(),
+            })
+        }
+    }
+
+    fn player_schema() -> SchemaType {
+        use Property;
+
+        let mut properties = IndexMap::new();
+        properties.insert("name".to_owned(), Property::required(SchemaType::string()));
+        properties.insert("level".to_owned(), Property::required(SchemaType::int32()));
+        properties.insert("score".to_owned(), Property::optional(SchemaType::double()));
+        SchemaType::object(properties)
+    }
+
+    #[test]
+    fn test_decode_as_struct() {
+        let registry = SchemaRegistry::new();
+        registry.register("Player", player_schema()).unwrap();
+
+        let mut obj = IndexMap::new();
+        obj.insert("name".into(), Value::from("Alice"));
+        obj.insert("level".into(), Value::Integer(7));
+        let mut enc = Encoder::new();
+        enc.encode(&Value::Object(obj), &player_schema()).unwrap();
+        let bytes = enc.finish();
+
+        let schema = SchemaType::reference("#/Player");
+        let player: Player = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(
+            player,
+            Player {
+                name: Value::from("Alice"),
+                level: 7,
+                score: 0.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_decode_as_type_mismatch() {
+        let mut enc = Encoder::new();
+        enc.encode(&Value::from("7"), &SchemaType::string())
+            .unwrap();
+        let bytes = enc.finish();
+
+        let result: Result<i32> = Decoder::decode_as(
+            &mut bytes.as_ref(),
+            &SchemaType::string(),
+            &SchemaRegistry::new(),
+        );
+        assert!(result.is_err());
+
+        let result: Result<Player> = Decoder::decode_as(
+            &mut bytes.as_ref(),
+            &SchemaType::string(),
+            &SchemaRegistry::new(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_partial_roundtrip() {
+        let registry = SchemaRegistry::new();
+        let mut obj = IndexMap::new();
+        obj.insert("level".into(), Value::Integer(8));
+        let delta = Value::Object(obj);
+
+        let mut enc = Encoder::new();
+        assert!(enc.encode(&delta, &player_schema()).is_err());
+        enc.encode_partial(&delta, &player_schema(), &registry)
+            .unwrap();
+        let bytes = enc.finish();
+        assert_eq!(&bytes[..], [1, 0, 4, 0, 0, 0, 8]);
+
+        assert!(Decoder::decode(&mut bytes.as_ref(), &player_schema()).is_err());
+        let decoded =
+            Decoder::decode_partial(&mut bytes.as_ref(), &player_schema(), &registry).unwrap();
+        assert_eq!(decoded, delta);
+    }
+
+    #[test]
+    fn test_decode_projected() {
+        let schema = SchemaType::object([
+            ("id", Property::required(SchemaType::int32())),
+            ("name", Property::optional(SchemaType::string())),
+            (
+                "tags",
+                Property::optional(SchemaType::array(SchemaType::string())),
+            ),
+        ]);
+        let value = Value::object([
+            ("tags", Value::from(vec!["a", "b"])),
+            ("name", Value::from("Ann")),
+            ("id", Value::from(7)),
+        ]);
+        let projection = schema.project(&["id", "name", "missing"]);
+        let expected = Value::object([("name", Value::from("Ann")), ("id", Value::from(7))]);
+
+        for format in [ObjectFormat::Indexed, ObjectFormat::Bitmap] {
+            let registry = SchemaRegistry::new().with_object_format(format);
+            let mut enc = Encoder::new();
+            enc.encode_with_registry(&value, &schema, &registry)
+                .unwrap();
+            let bytes = enc.finish();
+            let mut buf = bytes.as_ref();
+            let decoded = Decoder::decode_projected_with_registry(&mut buf, &projection, &registry);
+            assert_eq!(decoded.unwrap(), expected);
+            assert!(buf.is_empty());
+        }
+
+        // Skipped properties still count towards the required ones
+        let bytes = [1, 2, 0, 2, b'a', b'b'];
+        assert!(Decoder::decode_projected(&mut bytes.as_ref(), &projection).is_err());
+    }
 }
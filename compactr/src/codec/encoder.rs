@@ -1,16 +1,30 @@
 //! Encoder for converting values to binary format based on schemas.
 
-use crate::codec::buffer::{encode_binary, encode_string};
+use crate::codec::array::{encode_elements, encode_runs};
+use crate::codec::buffer::put_prefixed;
+use crate::codec::frame::{begin_frame, end_frame};
+use crate::codec::object::put_object_header;
+use crate::codec::wire::{write_property_size, BINARY_LEN_WIDTH, STRING_LEN_WIDTH};
+use crate::codec::{Encode, EncoderOptions, FieldFilter, ObjectFormat, ObjectParts};
 use crate::error::{EncodeError, Result, SchemaError};
-use crate::formats::{datetime, ipaddr, uuid};
-use crate::schema::{IntegerFormat, NumberFormat, SchemaRegistry, SchemaType, StringFormat};
+#[cfg(feature = "decimal")]
+use crate::formats::decimal;
+use crate::formats::{datetime, geo, ipaddr, uri, uuid};
+use crate::metrics::Operation;
+use crate::schema::{
+    index_of, index_order, IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType,
+    StringFormat, INLINE_PROPERTIES,
+};
 use crate::value::Value;
 use bytes::{BufMut, Bytes, BytesMut};
+use smallvec::SmallVec;
+use std::borrow::Cow;
 
 /// Encoder for serializing values to binary format.
 #[derive(Debug)]
 pub struct Encoder {
     buf: BytesMut,
+    options: Option<EncoderOptions>,
 }
 
 impl Default for Encoder {
@@ -25,6 +39,7 @@ impl Encoder {
     pub fn new() -> Self {
         Self {
             buf: BytesMut::new(),
+            options: None,
         }
     }
 
@@ -33,6 +48,36 @@ impl Encoder {
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
             buf: BytesMut::with_capacity(capacity),
+            options: None,
+        }
+    }
+
+    /// Creates a new encoder that writes the layout described by `options`.
+    ///
+    /// The options take precedence over the layout settings of any
+    /// [`SchemaRegistry`] passed to the encoder; the registry is still used
+    /// to resolve references and custom formats.
+    #[must_use]
+    pub fn with_options(options: EncoderOptions) -> Self {
+        Self {
+            buf: BytesMut::new(),
+            options: Some(options),
+        }
+    }
+
+    /// Returns the options this encoder was created with, if any.
+    #[must_use]
+    pub const fn options(&self) -> Option<EncoderOptions> {
+        self.options
+    }
+
+    /// Returns `registry` with this encoder's options applied.
+    fn configure<'r>(&self, registry: &'r SchemaRegistry) -> Cow<'r, SchemaRegistry> {
+        match self.options {
+            Some(options) if options != registry.options() => {
+                Cow::Owned(registry.clone().with_options(options))
+            }
+            _ => Cow::Borrowed(registry),
         }
     }
 
@@ -42,7 +87,7 @@ impl Encoder {
     ///
     /// Returns an error if the value doesn't match the schema or encoding fails.
     pub fn encode(&mut self, value: &Value, schema: &SchemaType) -> Result<()> {
-        self.encode_with_registry(value, schema, &SchemaRegistry::new())
+        self.encode_with_registry(value, schema, SchemaRegistry::empty())
     }
 
     /// Encodes a value with a schema registry for resolving references.
@@ -56,184 +101,387 @@ impl Encoder {
         schema: &SchemaType,
         registry: &SchemaRegistry,
     ) -> Result<()> {
+        let registry = &*self.configure(registry);
+        let start = self.buf.len();
+        let result = self.encode_nested(value, schema, registry);
+        let bytes = self.buf.len() - start;
+        registry
+            .metrics()
+            .report(Operation::Encode, schema, bytes, &result);
+        result
+    }
+
+    /// Encodes a value that is part of a larger one, without reporting it to
+    /// the registry's metrics hook.
+    pub(crate) fn encode_nested(
+        &mut self,
+        value: &Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        if let Value::Custom(custom) = value {
+            return self.encode_nested(custom.repr(), schema, registry);
+        }
         match schema {
             SchemaType::Boolean => self.encode_boolean(value),
-            SchemaType::Integer(format) => self.encode_integer(value, *format),
-            SchemaType::Number(format) => self.encode_number(value, *format),
-            SchemaType::String(format) => self.encode_string_format(value, *format),
+            SchemaType::Integer(format) => self.encode_integer(value, *format, registry),
+            SchemaType::Number(format) => self.encode_number(value, *format, registry),
+            SchemaType::String(format) => self.encode_string_format(value, *format, registry),
             SchemaType::Array(items) => self.encode_array(value, items, registry),
+            SchemaType::RunLengthArray(items) => {
+                let Value::Array(values) = value else {
+                    return Err(type_mismatch("array", value));
+                };
+                encode_runs(
+                    &mut self.buf,
+                    registry.length_mode(),
+                    values,
+                    |item, buf| Self::encode_value_into(buf, item, items, registry),
+                )
+            }
             SchemaType::Object(properties) => self.encode_object(value, properties, registry),
             SchemaType::Reference(ref_name) => {
-                let resolved = registry.resolve_ref(ref_name)?;
-                self.encode_with_registry(value, &resolved, registry)
+                let resolved = registry.resolve_shared(ref_name)?;
+                self.encode_nested(value, &resolved, registry)
+            }
+            SchemaType::Enum(_) | SchemaType::OneOf { .. } => {
+                self.encode_tagged(value, schema, registry)
             }
             SchemaType::Null => self.encode_null(value),
         }
     }
 
+    /// Encodes `values` as consecutive records, each preceded by its length
+    /// as a big-endian `u32`.
+    ///
+    /// Read them back with [`Decoder::decode_many`](crate::Decoder::decode_many).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value doesn't match the schema or encoding fails.
+    pub fn encode_many<'a>(
+        &mut self,
+        values: impl IntoIterator<Item = &'a Value>,
+        schema: &SchemaType,
+    ) -> Result<()> {
+        self.encode_many_with_registry(values, schema, SchemaRegistry::empty())
+    }
+
+    /// Encodes `values` as length-prefixed records with a schema registry
+    /// for resolving references.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value doesn't match the schema or encoding fails.
+    pub fn encode_many_with_registry<'a>(
+        &mut self,
+        values: impl IntoIterator<Item = &'a Value>,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let registry = &*self.configure(registry);
+        for value in values {
+            let start = begin_frame(&mut self.buf);
+            self.encode_with_registry(value, schema, registry)?;
+            end_frame(&mut self.buf, start)?;
+        }
+        Ok(())
+    }
+
     fn encode_boolean(&mut self, value: &Value) -> Result<()> {
         match value {
             Value::Boolean(b) => {
                 self.buf.put_u8(u8::from(*b));
                 Ok(())
             }
-            _ => Err(EncodeError::TypeMismatch {
-                expected: "boolean".to_owned(),
-                actual: value_type_name(value),
-            }
-            .into()),
+            _ => Err(type_mismatch("boolean", value)),
         }
     }
 
-    fn encode_integer(&mut self, value: &Value, format: IntegerFormat) -> Result<()> {
+    fn encode_integer(
+        &mut self,
+        value: &Value,
+        format: IntegerFormat,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
         let int_val = match value {
-            Value::Integer(i) => *i,
-            _ => {
-                return Err(EncodeError::TypeMismatch {
-                    expected: "integer".to_owned(),
-                    actual: value_type_name(value),
-                }
-                .into())
+            Value::Integer(i) => i128::from(*i),
+            Value::Int128(i) => *i,
+            // The only value that doesn't fit into i128 is a large u128
+            Value::Uint128(u) if format == IntegerFormat::Uint128 => {
+                self.buf.put_u128(*u); // Big-endian
+                return Ok(());
             }
+            Value::Uint128(u) => narrow(*u, format)?,
+            _ => return Err(type_mismatch("integer", value)),
         };
 
         match format {
-            IntegerFormat::Int32 => {
-                if int_val < i64::from(i32::MIN) || int_val > i64::from(i32::MAX) {
-                    return Err(EncodeError::InvalidFormat(format!(
-                        "Integer {int_val} out of range for int32"
-                    ))
-                    .into());
-                }
-                #[allow(clippy::cast_possible_truncation)]
-                self.buf.put_i32(int_val as i32); // Big-endian
-            }
+            IntegerFormat::Int8 => self.buf.put_i8(narrow(int_val, format)?),
+            IntegerFormat::Int16 => self.buf.put_i16(narrow(int_val, format)?), // Big-endian
+            IntegerFormat::Int32 => self.buf.put_i32(narrow(int_val, format)?), // Big-endian
             IntegerFormat::Int64 => {
+                let int_val: i64 = narrow(int_val, format)?;
                 // compactr.js encodes int64 as IEEE 754 double (f64) due to JavaScript limitations
+                check_integer_precision(int_val.into(), f64::MANTISSA_DIGITS, registry)?;
                 #[allow(clippy::cast_precision_loss)]
                 self.buf.put_f64(int_val as f64); // Big-endian
             }
+            IntegerFormat::Uint8 => self.buf.put_u8(narrow(int_val, format)?),
+            IntegerFormat::Uint16 => self.buf.put_u16(narrow(int_val, format)?), // Big-endian
+            IntegerFormat::Int128 => self.buf.put_i128(int_val),                 // Big-endian
+            IntegerFormat::Uint128 => self.buf.put_u128(narrow(int_val, format)?), // Big-endian
         }
 
         Ok(())
     }
 
-    fn encode_number(&mut self, value: &Value, format: NumberFormat) -> Result<()> {
+    fn encode_number(
+        &mut self,
+        value: &Value,
+        format: NumberFormat,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
         match format {
-            NumberFormat::Float => match value {
-                Value::Float(f) => {
-                    self.buf.put_f32(*f); // Big-endian
-                    Ok(())
-                }
-                Value::Double(d) => {
-                    #[allow(clippy::cast_possible_truncation)]
-                    self.buf.put_f32(*d as f32); // Big-endian
-                    Ok(())
-                }
-                _ => Err(EncodeError::TypeMismatch {
-                    expected: "float".to_owned(),
-                    actual: value_type_name(value),
-                }
-                .into()),
-            },
-            NumberFormat::Double => match value {
-                Value::Double(d) => {
-                    self.buf.put_f64(*d); // Big-endian
-                    Ok(())
-                }
-                Value::Float(f) => {
-                    self.buf.put_f64(f64::from(*f)); // Big-endian
-                    Ok(())
+            NumberFormat::Float => {
+                let f = match value {
+                    Value::Float(f) => *f,
+                    Value::Double(d) => narrow_float(*d, registry)?,
+                    _ => return Err(type_mismatch("float", value)),
+                };
+                if registry.canonical_floats() {
+                    self.buf.put_f32(canonical_f32(f)); // Big-endian
+                } else {
+                    self.buf.put_f32(f); // Big-endian
                 }
-                _ => Err(EncodeError::TypeMismatch {
-                    expected: "double".to_owned(),
-                    actual: value_type_name(value),
+                Ok(())
+            }
+            NumberFormat::Double => {
+                let d = match value {
+                    Value::Double(d) => *d,
+                    Value::Float(f) => f64::from(*f),
+                    _ => return Err(type_mismatch("double", value)),
+                };
+                if registry.canonical_floats() {
+                    self.buf.put_f64(canonical_f64(d)); // Big-endian
+                } else {
+                    self.buf.put_f64(d); // Big-endian
                 }
-                .into()),
-            },
+                Ok(())
+            }
+            #[cfg(feature = "decimal")]
+            NumberFormat::Decimal => {
+                let d = match value {
+                    Value::Decimal(d) => *d,
+                    Value::Integer(i) => rust_decimal::Decimal::from(*i),
+                    Value::String(s) => decimal::parse_decimal(s)?,
+                    _ => return Err(type_mismatch("decimal", value)),
+                };
+                decimal::encode_decimal(&mut self.buf, &d).map_err(Into::into)
+            }
         }
     }
 
-    fn encode_string_format(&mut self, value: &Value, format: StringFormat) -> Result<()> {
+    fn encode_string_format(
+        &mut self,
+        value: &Value,
+        format: StringFormat,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let mode = registry.length_mode();
         match format {
             StringFormat::Plain => match value {
-                Value::String(s) => encode_string(&mut self.buf, s).map_err(Into::into),
-                _ => Err(EncodeError::TypeMismatch {
-                    expected: "string".to_owned(),
-                    actual: value_type_name(value),
+                Value::String(s) => {
+                    put_prefixed(&mut self.buf, mode, STRING_LEN_WIDTH, s.as_bytes())
+                        .map_err(Into::into)
+                }
+                _ => Err(type_mismatch("string", value)),
+            },
+            StringFormat::Long => match value {
+                Value::String(s) => {
+                    put_prefixed(&mut self.buf, mode, BINARY_LEN_WIDTH, s.as_bytes())
+                        .map_err(Into::into)
                 }
-                .into()),
+                _ => Err(type_mismatch("string", value)),
             },
             StringFormat::Uuid => match value {
+                #[cfg(feature = "uuid")]
                 Value::Uuid(u) => uuid::encode_uuid(&mut self.buf, u).map_err(Into::into),
+                #[cfg(feature = "uuid")]
                 Value::String(s) => {
                     let u = uuid::parse_uuid(s)?;
                     uuid::encode_uuid(&mut self.buf, &u).map_err(Into::into)
                 }
-                _ => Err(EncodeError::TypeMismatch {
-                    expected: "uuid".to_owned(),
-                    actual: value_type_name(value),
+                #[cfg(not(feature = "uuid"))]
+                Value::String(s) => {
+                    self.buf.put_slice(&uuid::parse_uuid_bytes(s)?);
+                    Ok(())
+                }
+                // The raw bytes, as decoded without the uuid feature
+                Value::Binary(bytes) if bytes.len() == uuid::uuid_size() => {
+                    self.buf.put_slice(bytes);
+                    Ok(())
+                }
+                _ => Err(type_mismatch("uuid", value)),
+            },
+            StringFormat::DateTime
+            | StringFormat::DateTimeTz
+            | StringFormat::Timestamp(_)
+            | StringFormat::Date
+            | StringFormat::Time => self.encode_temporal(value, format),
+            StringFormat::Uri => match value {
+                Value::String(s) => uri::encode_uri(&mut self.buf, s).map_err(Into::into),
+                _ => Err(type_mismatch("uri", value)),
+            },
+            StringFormat::GeoPoint => match value {
+                Value::GeoPoint(p) => geo::encode_geo_point(&mut self.buf, p).map_err(Into::into),
+                Value::String(s) => {
+                    let p = geo::parse_geo_point(s)?;
+                    geo::encode_geo_point(&mut self.buf, &p).map_err(Into::into)
+                }
+                _ => Err(type_mismatch("geopoint", value)),
+            },
+            StringFormat::Ipv4 => match value {
+                Value::Ipv4(ip) => ipaddr::encode_ipv4(&mut self.buf, ip).map_err(Into::into),
+                Value::String(s) => {
+                    let ip = ipaddr::parse_ipv4(s)?;
+                    ipaddr::encode_ipv4(&mut self.buf, &ip).map_err(Into::into)
+                }
+                _ => Err(type_mismatch("ipv4", value)),
+            },
+            StringFormat::Ipv6 => match value {
+                Value::Ipv6(ip) => ipaddr::encode_ipv6(&mut self.buf, ip).map_err(Into::into),
+                Value::String(s) => {
+                    let ip = ipaddr::parse_ipv6(s)?;
+                    ipaddr::encode_ipv6(&mut self.buf, &ip).map_err(Into::into)
                 }
-                .into()),
+                _ => Err(type_mismatch("ipv6", value)),
             },
+            StringFormat::SocketAddr => match value {
+                Value::SocketAddr(addr) => {
+                    ipaddr::encode_socket_addr(&mut self.buf, addr).map_err(Into::into)
+                }
+                Value::String(s) => {
+                    let addr = ipaddr::parse_socket_addr(s)?;
+                    ipaddr::encode_socket_addr(&mut self.buf, &addr).map_err(Into::into)
+                }
+                _ => Err(type_mismatch("socket address", value)),
+            },
+            StringFormat::Custom(name) => {
+                let custom = registry.formats().resolve(name)?;
+                custom.encode(value, &mut self.buf).map_err(Into::into)
+            }
+            StringFormat::Binary => match value {
+                Value::Binary(data) => {
+                    put_prefixed(&mut self.buf, mode, BINARY_LEN_WIDTH, data).map_err(Into::into)
+                }
+                _ => Err(type_mismatch("binary", value)),
+            },
+        }
+    }
+
+    /// Encodes the date and time string formats.
+    ///
+    /// Besides chrono values and strings, which need the `chrono` feature,
+    /// this takes the integers and bytes values are decoded to without it.
+    fn encode_temporal(&mut self, value: &Value, format: StringFormat) -> Result<()> {
+        match format {
             StringFormat::DateTime => match value {
+                #[cfg(feature = "chrono")]
                 Value::DateTime(dt) => {
                     datetime::encode_datetime(&mut self.buf, dt).map_err(Into::into)
                 }
+                #[cfg(feature = "chrono")]
                 Value::String(s) => {
                     let dt = datetime::parse_datetime(s)?;
                     datetime::encode_datetime(&mut self.buf, &dt).map_err(Into::into)
                 }
-                _ => Err(EncodeError::TypeMismatch {
-                    expected: "datetime".to_owned(),
-                    actual: value_type_name(value),
+                Value::Integer(millis) => {
+                    datetime::encode_datetime_millis(&mut self.buf, *millis).map_err(Into::into)
                 }
-                .into()),
+                _ => Err(type_mismatch("datetime", value)),
             },
-            StringFormat::Date => match value {
-                Value::Date(d) => datetime::encode_date(&mut self.buf, d).map_err(Into::into),
+            StringFormat::DateTimeTz => match value {
+                #[cfg(feature = "chrono")]
+                Value::DateTimeTz(dt) => {
+                    datetime::encode_datetime_tz(&mut self.buf, dt).map_err(Into::into)
+                }
+                #[cfg(feature = "chrono")]
+                Value::DateTime(dt) => {
+                    datetime::encode_datetime_tz(&mut self.buf, &dt.fixed_offset())
+                        .map_err(Into::into)
+                }
+                #[cfg(feature = "chrono")]
                 Value::String(s) => {
-                    let d = datetime::parse_date(s)?;
-                    datetime::encode_date(&mut self.buf, &d).map_err(Into::into)
+                    let dt = datetime::parse_datetime_tz(s)?;
+                    datetime::encode_datetime_tz(&mut self.buf, &dt).map_err(Into::into)
                 }
-                _ => Err(EncodeError::TypeMismatch {
-                    expected: "date".to_owned(),
-                    actual: value_type_name(value),
+                Value::Binary(bytes) if bytes.len() == datetime::datetime_tz_size() => {
+                    datetime::decode_datetime_tz_millis(&mut bytes.as_slice())
+                        .map_err(|e| EncodeError::InvalidFormat(e.to_string()))?;
+                    self.buf.put_slice(bytes);
+                    Ok(())
                 }
-                .into()),
+                _ => Err(type_mismatch("datetime", value)),
             },
-            StringFormat::Ipv4 => match value {
-                Value::Ipv4(ip) => ipaddr::encode_ipv4(&mut self.buf, ip).map_err(Into::into),
+            #[cfg_attr(not(feature = "chrono"), allow(unused_variables))]
+            StringFormat::Timestamp(precision) => match value {
+                #[cfg(feature = "chrono")]
+                Value::DateTime(dt) => {
+                    datetime::encode_timestamp(&mut self.buf, dt, precision).map_err(Into::into)
+                }
+                #[cfg(feature = "chrono")]
                 Value::String(s) => {
-                    let ip = ipaddr::parse_ipv4(s)?;
-                    ipaddr::encode_ipv4(&mut self.buf, &ip).map_err(Into::into)
+                    let dt = datetime::parse_datetime(s)?;
+                    datetime::encode_timestamp(&mut self.buf, &dt, precision).map_err(Into::into)
                 }
-                _ => Err(EncodeError::TypeMismatch {
-                    expected: "ipv4".to_owned(),
-                    actual: value_type_name(value),
+                // Units of the precision since the epoch
+                Value::Integer(units) => {
+                    self.buf.put_i64(*units);
+                    Ok(())
                 }
-                .into()),
+                _ => Err(type_mismatch("datetime", value)),
             },
-            StringFormat::Ipv6 => match value {
-                Value::Ipv6(ip) => ipaddr::encode_ipv6(&mut self.buf, ip).map_err(Into::into),
+            StringFormat::Date => match value {
+                #[cfg(feature = "chrono")]
+                Value::Date(d) => datetime::encode_date(&mut self.buf, d).map_err(Into::into),
+                #[cfg(feature = "chrono")]
                 Value::String(s) => {
-                    let ip = ipaddr::parse_ipv6(s)?;
-                    ipaddr::encode_ipv6(&mut self.buf, &ip).map_err(Into::into)
+                    let d = datetime::parse_date(s)?;
+                    datetime::encode_date(&mut self.buf, &d).map_err(Into::into)
                 }
-                _ => Err(EncodeError::TypeMismatch {
-                    expected: "ipv6".to_owned(),
-                    actual: value_type_name(value),
+                Value::Integer(days) => {
+                    let days = i32::try_from(*days).map_err(|_| {
+                        EncodeError::InvalidFormat(format!(
+                            "Date out of range: {days} days from epoch"
+                        ))
+                    })?;
+                    self.buf.put_i32(days);
+                    Ok(())
                 }
-                .into()),
+                _ => Err(type_mismatch("date", value)),
             },
-            StringFormat::Binary => match value {
-                Value::Binary(data) => encode_binary(&mut self.buf, data).map_err(Into::into),
-                _ => Err(EncodeError::TypeMismatch {
-                    expected: "binary".to_owned(),
-                    actual: value_type_name(value),
+            StringFormat::Time => match value {
+                #[cfg(feature = "chrono")]
+                Value::Time(t) => datetime::encode_time(&mut self.buf, t).map_err(Into::into),
+                #[cfg(feature = "chrono")]
+                Value::String(s) => {
+                    let t = datetime::parse_time(s)?;
+                    datetime::encode_time(&mut self.buf, &t).map_err(Into::into)
+                }
+                // Up to the end of a leap second
+                Value::Integer(millis @ 0..=86_400_999) => {
+                    self.buf.put_u32(u32::try_from(*millis).unwrap_or_default());
+                    Ok(())
+                }
+                Value::Integer(millis) => {
+                    Err(EncodeError::InvalidFormat(format!("Invalid time: {millis} ms")).into())
                 }
-                .into()),
+                _ => Err(type_mismatch("time", value)),
             },
+            _ => Err(
+                EncodeError::InvalidFormat(format!("Not a date/time format: {format:?}")).into(),
+            ),
         }
     }
 
@@ -244,168 +492,288 @@ impl Encoder {
         registry: &SchemaRegistry,
     ) -> Result<()> {
         let Value::Array(items) = value else {
-            return Err(EncodeError::TypeMismatch {
-                expected: "array".to_owned(),
-                actual: value_type_name(value),
-            }
-            .into());
+            return Err(type_mismatch("array", value));
         };
 
-        // Compactr.js format: Each array element is prefixed with its size
-        // No overall array length - elements are self-describing
-        //
-        // Format: [size1, elem1, size2, elem2, ...]
-        // where size is a variable-length encoding
-
-        for item in items {
-            // Encode element to temp buffer to measure size
-            let mut temp_buf = BytesMut::new();
-            let mut temp_encoder = Encoder::with_buf(temp_buf);
-            temp_encoder.encode_with_registry(item, items_schema, registry)?;
-            temp_buf = temp_encoder.buf;
-
-            let elem_size = temp_buf.len();
-
-            // Encode size prefix (variable length)
-            if elem_size > 255 {
-                return Err(EncodeError::InvalidFormat(format!(
-                    "Array element too large: {elem_size} bytes (max 255)"
-                ))
-                .into());
-            }
-            #[allow(clippy::cast_possible_truncation)]
-            self.buf.put_u8(elem_size as u8);
-
-            // Append element data
-            self.buf.extend_from_slice(&temp_buf);
-        }
-
-        Ok(())
+        encode_elements(
+            &mut self.buf,
+            registry.array_format(),
+            registry.length_mode(),
+            items,
+            |item, buf| Self::encode_value_into(buf, item, items_schema, registry),
+        )
     }
 
     fn encode_object(
         &mut self,
         value: &Value,
-        properties: &indexmap::IndexMap<String, crate::schema::Property>,
+        properties: &indexmap::IndexMap<String, Property>,
         registry: &SchemaRegistry,
     ) -> Result<()> {
         let Value::Object(obj) = value else {
-            return Err(EncodeError::TypeMismatch {
-                expected: "object".to_owned(),
-                actual: value_type_name(value),
-            }
-            .into());
+            return Err(type_mismatch("object", value));
         };
 
-        // Compactr.js 3.x format: Interleaved structure
-        // [num_props, index0, size0, value0, index1, size1, value1, ...]
-        // Properties are indexed alphabetically by name
-
-        // Create alphabetically sorted property list to determine indices
-        let mut sorted_props: Vec<(String, crate::schema::Property)> = properties
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect();
-        sorted_props.sort_by(|a, b| a.0.cmp(&b.0));
-
-        // Build list of present properties with their alphabetical indices
-        // Encode in the order properties appear in the VALUE object
-        let mut present_props: Vec<(usize, &String, &crate::schema::Property, &Value)> = Vec::new();
-
-        // Check for required fields first
-        for (prop_name, prop_def) in properties {
-            if prop_def.required && !obj.contains_key(prop_name) {
-                return Err(SchemaError::MissingField(prop_name.clone()).into());
+        let fields = obj.iter().map(|(k, v)| (&**k, v as &dyn Encode));
+        let format = registry.object_format();
+        encode_properties(
+            &mut self.buf,
+            None,
+            format,
+            properties,
+            registry,
+            fields,
+            true,
+        )
+    }
+
+    /// Encodes only the properties present in `value`, even if the schema
+    /// requires others.
+    ///
+    /// Like compactr.js's `write()` given a subset of the keys, the header
+    /// lists just the provided property indices, which suits delta and patch
+    /// messages. Nested values are encoded normally. Read the result with
+    /// [`Decoder::decode_partial`](crate::Decoder::decode_partial).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, or a provided
+    /// property doesn't match its schema.
+    pub fn encode_partial(
+        &mut self,
+        value: &Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let registry = &*self.configure(registry);
+        match (schema, value) {
+            (SchemaType::Object(properties), Value::Object(obj)) => {
+                let fields = obj.iter().map(|(k, v)| (&**k, v as &dyn Encode));
+                let (buf, format) = (&mut self.buf, ObjectFormat::Indexed);
+                encode_properties(buf, None, format, properties, registry, fields, false)
             }
+            (SchemaType::Object(_), _) => Err(type_mismatch("object", value)),
+            (SchemaType::Reference(ref_name), _) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                self.encode_partial(value, &resolved, registry)
+            }
+            _ => Err(
+                SchemaError::InvalidSchema(format!("expected object schema, got {schema:?}"))
+                    .into(),
+            ),
         }
+    }
 
-        // Iterate over value object keys (preserving insertion order)
-        for (prop_name, prop_value) in obj {
-            // Check if this property is in the schema
-            if let Some(prop_def) = properties.get(prop_name) {
-                // Find the alphabetical index of this property
-                let alpha_idx = sorted_props
-                    .iter()
-                    .position(|(name, _)| name == prop_name)
-                    .unwrap();
+    /// Encodes only the properties of an object that `filter` allows.
+    ///
+    /// The output is a regular object that decodes with the same schema, so
+    /// the filter may not drop required properties.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, the filter drops a
+    /// required property, or the value doesn't match the schema.
+    pub fn encode_filtered(
+        &mut self,
+        value: &Value,
+        schema: &SchemaType,
+        filter: &FieldFilter,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let registry = &*self.configure(registry);
+        let start = self.buf.len();
+        let result = self.encode_object_filtered(value, schema, filter, registry);
+        let bytes = self.buf.len() - start;
+        registry
+            .metrics()
+            .report(Operation::Encode, schema, bytes, &result);
+        result
+    }
 
-                present_props.push((alpha_idx, prop_name, prop_def, prop_value));
+    fn encode_object_filtered(
+        &mut self,
+        value: &Value,
+        schema: &SchemaType,
+        filter: &FieldFilter,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        match (schema, value) {
+            (SchemaType::Object(properties), Value::Object(obj)) => {
+                let dropped = properties
+                    .iter()
+                    .find(|(name, property)| property.required && !filter.allows(name));
+                if let Some((name, _)) = dropped {
+                    return Err(SchemaError::InvalidSchema(format!(
+                        "Field filter drops required property {name}"
+                    ))
+                    .into());
+                }
+                let fields = obj
+                    .iter()
+                    .filter(|(k, _)| filter.allows(k))
+                    .map(|(k, v)| (&**k, v as &dyn Encode));
+                let (buf, format) = (&mut self.buf, registry.object_format());
+                encode_properties(buf, None, format, properties, registry, fields, true)
             }
-            // Ignore properties not in schema
+            (SchemaType::Object(_), _) => Err(type_mismatch("object", value)),
+            (SchemaType::Reference(ref_name), _) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                self.encode_object_filtered(value, &resolved, filter, registry)
+            }
+            _ => Err(
+                SchemaError::InvalidSchema(format!("expected object schema, got {schema:?}"))
+                    .into(),
+            ),
         }
+    }
 
-        // First byte: number of properties present
-        if present_props.len() > 255 {
-            return Err(EncodeError::InvalidFormat(format!(
-                "Too many properties: {} (max 255)",
-                present_props.len()
-            ))
-            .into());
+    /// Encodes an object with its header and content written separately.
+    ///
+    /// This is the counterpart of compactr.js's `headerBuffer()` and
+    /// `contentBuffer()`: the header holds the property count and the
+    /// index and size of every property, the content their values. Streaming
+    /// protocols can send a header once and then only the contents of
+    /// objects with the same properties and value sizes. Use
+    /// [`ObjectParts::join`] or [`Decoder::decode_parts`](crate::Decoder::decode_parts)
+    /// to read them back.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, or the value doesn't
+    /// match it.
+    pub fn encode_parts(
+        value: &Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<ObjectParts> {
+        match (schema, value) {
+            (SchemaType::Object(properties), Value::Object(obj)) => {
+                let mut header = BytesMut::new();
+                let mut content = BytesMut::new();
+                let fields = obj.iter().map(|(k, v)| (&**k, v as &dyn Encode));
+                encode_properties(
+                    &mut header,
+                    Some(&mut content),
+                    ObjectFormat::Indexed,
+                    properties,
+                    registry,
+                    fields,
+                    true,
+                )?;
+                Ok(ObjectParts::new(header.freeze(), content.freeze()))
+            }
+            (SchemaType::Object(_), _) => Err(type_mismatch("object", value)),
+            (SchemaType::Reference(ref_name), _) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::encode_parts(value, &resolved, registry)
+            }
+            _ => Err(
+                SchemaError::InvalidSchema(format!("expected object schema, got {schema:?}"))
+                    .into(),
+            ),
         }
-        #[allow(clippy::cast_possible_truncation)]
-        self.buf.put_u8(present_props.len() as u8);
-
-        // Encode each property: index, size, value (interleaved in alphabetical order)
-        for (idx, _prop_name, prop_def, prop_value) in present_props {
-            // Write property index
-            #[allow(clippy::cast_possible_truncation)]
-            self.buf.put_u8(idx as u8);
-
-            // Encode value to a temporary buffer to calculate size
-            let mut value_buf = BytesMut::new();
-            let mut temp_encoder = Encoder::with_buf(value_buf);
-            temp_encoder.encode_property_value(prop_value, &prop_def.schema_type, registry)?;
-            value_buf = temp_encoder.buf;
-
-            let size = value_buf.len();
-
-            // Determine if this is a compound type
-            let is_compound = matches!(
-                prop_def.schema_type,
-                SchemaType::Array(_) | SchemaType::Object(_)
-            );
+    }
 
-            // Write size with appropriate encoding
-            if is_compound {
-                // Compound types: always use 0x00 prefix, then variable-length
-                self.buf.put_u8(0); // Compound type flag
-                if size < 256 {
-                    #[allow(clippy::cast_possible_truncation)]
-                    self.buf.put_u8(size as u8);
-                } else {
-                    if size > u16::MAX as usize {
-                        return Err(EncodeError::InvalidFormat(format!(
-                            "Property value too large: {size} bytes (max {})",
-                            u16::MAX
-                        ))
-                        .into());
-                    }
-                    #[allow(clippy::cast_possible_truncation)]
-                    self.buf.put_u16(size as u16);
-                }
-            } else if size >= 256 {
-                // Large primitives: 0x00 prefix + u16
-                if size > u16::MAX as usize {
-                    return Err(EncodeError::InvalidFormat(format!(
-                        "Property value too large: {size} bytes (max {})",
-                        u16::MAX
-                    ))
-                    .into());
-                }
-                self.buf.put_u8(0);
-                #[allow(clippy::cast_possible_truncation)]
-                self.buf.put_u16(size as u16);
-            } else {
-                // Small primitives: single-byte encoding
-                #[allow(clippy::cast_possible_truncation)]
-                self.buf.put_u8(size as u8);
+    /// Encodes `value` into the encoder's buffer according to the schema.
+    ///
+    /// This is the typed counterpart of [`Encoder::encode_with_registry`]:
+    /// references are resolved before handing the schema to
+    /// [`Encode::encode_with_schema`], and no [`Value`] tree is built for
+    /// types that implement it directly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value doesn't match the schema or encoding fails.
+    pub fn encode_as<T: Encode + ?Sized>(
+        &mut self,
+        value: &T,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let registry = self.configure(registry);
+        Self::encode_into(&mut self.buf, value, schema, &registry)
+    }
+
+    /// Encodes `value` into an existing buffer according to the schema.
+    ///
+    /// Buffer-level counterpart of [`Encoder::encode_as`] for use inside
+    /// [`Encode`] implementations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value doesn't match the schema or encoding fails.
+    pub fn encode_into<T: Encode + ?Sized>(
+        buf: &mut BytesMut,
+        value: &T,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        match schema {
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                value.encode_with_schema(buf, &resolved, registry)
             }
+            _ => value.encode_with_schema(buf, schema, registry),
+        }
+    }
 
-            // Write value bytes
-            self.buf.extend_from_slice(&value_buf);
+    /// Encodes an object from `(property name, value)` pairs.
+    ///
+    /// This is the building block for [`Encode`] implementations of struct
+    /// types, mirroring [`Decoder::decode_fields`](crate::Decoder::decode_fields).
+    /// Fields are written in the given order; names that are not part of the
+    /// schema are skipped, and omitted fields are treated as absent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, a required property
+    /// is missing, or a field fails to encode.
+    pub fn encode_fields<'a>(
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+        fields: impl IntoIterator<Item = (&'a str, &'a dyn Encode)>,
+    ) -> Result<()> {
+        match schema {
+            SchemaType::Object(properties) => {
+                let format = registry.object_format();
+                encode_properties(buf, None, format, properties, registry, fields, true)
+            }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::encode_fields(buf, &resolved, registry, fields)
+            }
+            _ => Err(
+                SchemaError::InvalidSchema(format!("expected object schema, got {schema:?}"))
+                    .into(),
+            ),
         }
+    }
 
-        Ok(())
+    /// Encodes a value into an existing buffer.
+    pub(crate) fn encode_value_into(
+        buf: &mut BytesMut,
+        value: &Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let mut encoder = Self::with_buf(std::mem::take(buf));
+        let result = encoder.encode_nested(value, schema, registry);
+        *buf = encoder.buf;
+        result
+    }
+
+    /// Encodes a property value into an existing buffer.
+    pub(crate) fn encode_property_into(
+        buf: &mut BytesMut,
+        value: &Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let mut encoder = Self::with_buf(std::mem::take(buf));
+        let result = encoder.encode_property_value(value, schema, registry);
+        *buf = encoder.buf;
+        result
     }
 
     /// Encodes a property value (strings without length prefix, etc.)
@@ -416,36 +784,94 @@ impl Encoder {
         registry: &SchemaRegistry,
     ) -> Result<()> {
         match schema {
-            SchemaType::String(StringFormat::Plain) => {
+            SchemaType::String(StringFormat::Plain | StringFormat::Long) => {
                 // For strings in objects: encode raw UTF-8 bytes (no length prefix)
-                if let Value::String(s) = value {
-                    self.buf.put_slice(s.as_bytes());
-                    Ok(())
-                } else {
-                    Err(EncodeError::TypeMismatch {
-                        expected: "string".to_owned(),
-                        actual: value_type_name(value),
+                match value {
+                    Value::String(s) => {
+                        self.buf.put_slice(s.as_bytes());
+                        Ok(())
+                    }
+                    Value::Custom(custom) => {
+                        self.encode_property_value(custom.repr(), schema, registry)
                     }
-                    .into())
+                    _ => Err(type_mismatch("string", value)),
                 }
             }
             // For all other types, use normal encoding
-            _ => self.encode_with_registry(value, schema, registry),
+            _ => self.encode_nested(value, schema, registry),
         }
     }
 
     // Helper to create encoder with existing buffer
     fn with_buf(buf: BytesMut) -> Self {
-        Self { buf }
+        Self { buf, options: None }
+    }
+
+    fn encode_tagged(
+        &mut self,
+        value: &Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let name = match (value, schema) {
+            // Variants without a payload may be given by name alone
+            (Value::String(s), _) => s.as_str(),
+            (Value::Object(obj), SchemaType::OneOf { discriminator, .. }) => obj
+                .get(discriminator.as_str())
+                .and_then(Value::as_str)
+                .ok_or_else(|| SchemaError::MissingField(discriminator.clone()))?,
+            (_, SchemaType::OneOf { .. }) => return Err(type_mismatch("object", value)),
+            _ => return Err(type_mismatch("string", value)),
+        };
+
+        if let Some(payload) = Self::encode_variant(&mut self.buf, schema, name)? {
+            self.encode_nested(value, payload, registry)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the index of variant `name` of an enum or `oneOf` schema and
+    /// returns the schema of the payload that must follow, if any.
+    ///
+    /// This is the building block for [`Encode`] implementations of Rust
+    /// enums, mirroring [`Decoder::decode_variant`](crate::Decoder::decode_variant).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an enum or `oneOf`, the variant
+    /// is unknown, or the schema has more than 256 variants.
+    pub fn encode_variant<'s>(
+        buf: &mut BytesMut,
+        schema: &'s SchemaType,
+        name: &str,
+    ) -> Result<Option<&'s SchemaType>> {
+        let (idx, payload) = match schema {
+            SchemaType::Enum(variants) => (variants.iter().position(|v| v == name), None),
+            SchemaType::OneOf { variants, .. } => match variants.get_full(name) {
+                Some((idx, _, SchemaType::Null)) => (Some(idx), None),
+                Some((idx, _, payload)) => (Some(idx), Some(payload)),
+                None => (None, None),
+            },
+            _ => {
+                return Err(SchemaError::InvalidSchema(format!(
+                    "expected enum or oneOf schema, got {schema:?}"
+                ))
+                .into())
+            }
+        };
+
+        let idx =
+            idx.ok_or_else(|| EncodeError::InvalidFormat(format!("Unknown variant: {name}")))?;
+        let tag = u8::try_from(idx).map_err(|_| {
+            SchemaError::InvalidSchema(format!("Too many variants: index {idx} exceeds 255"))
+        })?;
+        buf.put_u8(tag);
+        Ok(payload)
     }
 
     fn encode_null(&mut self, value: &Value) -> Result<()> {
         if !value.is_null() {
-            return Err(EncodeError::TypeMismatch {
-                expected: "null".to_owned(),
-                actual: value_type_name(value),
-            }
-            .into());
+            return Err(type_mismatch("null", value));
         }
         // Null is encoded as a single 0 byte
         self.buf.put_u8(0);
@@ -463,23 +889,277 @@ impl Encoder {
     pub fn as_bytes(&self) -> &[u8] {
         &self.buf
     }
+
+    /// Takes the encoded bytes, leaving the encoder empty but ready for
+    /// another value.
+    ///
+    /// Unlike [`Encoder::finish`], the encoder keeps its buffer, which can
+    /// reuse its allocation once the returned bytes are dropped.
+    pub fn take_bytes(&mut self) -> Bytes {
+        self.buf.split().freeze()
+    }
+
+    /// Returns the number of bytes the buffer can hold without reallocating.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+}
+
+/// A field being encoded: the index of its property, whether the field is
+/// under an alias rather than the property's name, the property and the
+/// value.
+type PresentProperty<'a> = (usize, bool, &'a Property, &'a dyn Encode);
+
+/// Writes an object, interleaving values with the header entries unless a
+/// separate `content` buffer is given.
+fn encode_properties<'a>(
+    buf: &mut BytesMut,
+    mut content: Option<&mut BytesMut>,
+    format: ObjectFormat,
+    properties: &indexmap::IndexMap<String, Property>,
+    registry: &SchemaRegistry,
+    fields: impl IntoIterator<Item = (&'a str, &'a dyn Encode)>,
+    check_required: bool,
+) -> Result<()> {
+    // Compactr.js 3.x format: Interleaved structure
+    // [num_props, index0, size0, value0, index1, size1, value1, ...]
+    // Properties are indexed alphabetically by name
+
+    // Create alphabetically sorted property list to determine indices
+    let sorted = index_order(properties);
+
+    // Build list of present properties with their alphabetical indices
+    let mut present_props: SmallVec<[PresentProperty<'_>; INLINE_PROPERTIES]> = SmallVec::new();
+    for (prop_name, prop_value) in fields {
+        if prop_value.is_absent() {
+            continue;
+        }
+        // Ignore properties not in schema
+        let Some(alpha_idx) = index_of(&sorted, prop_name) else {
+            continue;
+        };
+        let (name, prop_def) = sorted[alpha_idx];
+        present_props.push((alpha_idx, name != prop_name, prop_def, prop_value));
+    }
+
+    // Check for required fields
+    for (prop_name, prop_def) in properties {
+        if check_required
+            && prop_def.required
+            && !present_props
+                .iter()
+                .any(|(idx, _, _, _)| sorted[*idx].0 == prop_name)
+        {
+            return Err(SchemaError::MissingField(prop_name.clone()).into());
+        }
+    }
+
+    // Properties follow in index order whatever the order of the fields,
+    // so equal values encode to the same bytes. Each is written once: a
+    // field under the property's name wins over one under an alias, and
+    // the last occurrence of a duplicate wins
+    present_props.reverse();
+    present_props.sort_by_key(|(idx, alias, _, _)| (*idx, *alias));
+    present_props.dedup_by_key(|(idx, _, _, _)| *idx);
+    put_object_header(
+        buf,
+        format,
+        sorted.iter().map(|(_, property)| *property),
+        present_props.iter().map(|(idx, _, _, _)| *idx),
+    )?;
+
+    // Encode each property: index, size, value
+    let mut value_buf = BytesMut::new();
+    for (idx, _, prop_def, prop_value) in present_props {
+        // Encode value to a temporary buffer to calculate size
+        value_buf.clear();
+        match &prop_def.schema_type {
+            // Referenced schemas keep their top-level layout inside objects
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                prop_value.encode_with_schema(&mut value_buf, &resolved, registry)?;
+            }
+            schema => prop_value.encode_property(&mut value_buf, schema, registry)?,
+        }
+
+        put_property_header(buf, format, registry, idx, prop_def, value_buf.len())?;
+        content
+            .as_deref_mut()
+            .unwrap_or(buf)
+            .extend_from_slice(&value_buf);
+    }
+
+    Ok(())
+}
+
+/// Writes a property's index, size header and value bytes.
+#[cfg(feature = "serde")]
+pub(crate) fn put_property(
+    buf: &mut BytesMut,
+    format: ObjectFormat,
+    registry: &SchemaRegistry,
+    idx: usize,
+    property: &Property,
+    value: &[u8],
+) -> Result<()> {
+    put_property_header(buf, format, registry, idx, property, value.len())?;
+    buf.extend_from_slice(value);
+    Ok(())
+}
+
+/// Writes a property's size header, preceded by its index in the indexed
+/// layout.
+pub(crate) fn put_property_header(
+    buf: &mut BytesMut,
+    format: ObjectFormat,
+    registry: &SchemaRegistry,
+    idx: usize,
+    property: &Property,
+    size: usize,
+) -> Result<()> {
+    // Write property index
+    #[allow(clippy::cast_possible_truncation)]
+    if format == ObjectFormat::Indexed {
+        buf.put_u8(idx as u8);
+    }
+
+    // References are resolved, so that extracting a shape into the registry
+    // doesn't change the encoding
+    let compound = matches!(
+        *registry.resolve(&property.schema_type)?,
+        SchemaType::Array(_) | SchemaType::RunLengthArray(_) | SchemaType::Object(_)
+    );
+    let (width, mode) = (property.size_width, registry.length_mode());
+    write_property_size(buf, width, mode, compound, size)?;
+    Ok(())
+}
+
+/// Narrows an integer to a fixed-width type, rejecting out-of-range values.
+fn narrow<S, T>(value: S, format: IntegerFormat) -> Result<T>
+where
+    S: Copy + std::fmt::Display,
+    T: TryFrom<S>,
+{
+    T::try_from(value).map_err(|_| {
+        EncodeError::InvalidFormat(format!("Integer {value} out of range for {format}")).into()
+    })
+}
+
+/// Checks that `n` fits `format`, as encoding it requires.
+#[cfg(feature = "serde")]
+pub(crate) fn check_integer_range(n: i128, format: IntegerFormat) -> Result<()> {
+    match format {
+        IntegerFormat::Int8 => narrow::<_, i8>(n, format).map(drop),
+        IntegerFormat::Int16 => narrow::<_, i16>(n, format).map(drop),
+        IntegerFormat::Int32 => narrow::<_, i32>(n, format).map(drop),
+        IntegerFormat::Int64 => narrow::<_, i64>(n, format).map(drop),
+        IntegerFormat::Uint8 => narrow::<_, u8>(n, format).map(drop),
+        IntegerFormat::Uint16 => narrow::<_, u16>(n, format).map(drop),
+        IntegerFormat::Int128 => Ok(()),
+        IntegerFormat::Uint128 => narrow::<_, u128>(n, format).map(drop),
+    }
+}
+
+/// Narrows a double to a float, rejecting values the float can't hold
+/// exactly unless the registry allows
+/// [lossy floats](SchemaRegistry::lossy_floats).
+#[allow(clippy::cast_possible_truncation, clippy::float_cmp)]
+pub(crate) fn narrow_float(d: f64, registry: &SchemaRegistry) -> Result<f32> {
+    let f = d as f32;
+    // NaNs keep their sign, and their payload as far as it fits
+    if f64::from(f) == d || d.is_nan() || registry.lossy_floats() {
+        return Ok(f);
+    }
+    Err(EncodeError::InvalidFormat(format!(
+        "Double {d} cannot be stored as a float without losing precision"
+    ))
+    .into())
+}
+
+/// Replaces `-0.0` by `0.0` and every NaN by the quiet NaN `0x7fc0_0000`.
+fn canonical_f32(f: f32) -> f32 {
+    if f == 0.0 {
+        0.0
+    } else if f.is_nan() {
+        f32::from_bits(0x7fc0_0000)
+    } else {
+        f
+    }
+}
+
+/// Replaces `-0.0` by `0.0` and every NaN by the quiet NaN
+/// `0x7ff8_0000_0000_0000`.
+fn canonical_f64(d: f64) -> f64 {
+    if d == 0.0 {
+        0.0
+    } else if d.is_nan() {
+        f64::from_bits(0x7ff8_0000_0000_0000)
+    } else {
+        d
+    }
+}
+
+/// Checks that an integer converts to a number with `precision` significant
+/// bits exactly, unless the registry allows
+/// [lossy floats](SchemaRegistry::lossy_floats).
+pub(crate) fn check_integer_precision(
+    n: i128,
+    precision: u32,
+    registry: &SchemaRegistry,
+) -> Result<()> {
+    let magnitude = n.unsigned_abs();
+    // Bits from the highest set bit down to the lowest one
+    let significant = match magnitude {
+        0 => 0,
+        _ => 128 - magnitude.leading_zeros() - magnitude.trailing_zeros(),
+    };
+    if significant <= precision || registry.lossy_floats() {
+        return Ok(());
+    }
+    Err(EncodeError::InvalidFormat(format!(
+        "Integer {n} cannot be stored as a number without losing precision"
+    ))
+    .into())
+}
+
+pub(crate) fn type_mismatch(expected: &str, value: &Value) -> crate::error::Error {
+    EncodeError::TypeMismatch {
+        expected: expected.to_owned(),
+        actual: value_type_name(value),
+    }
+    .into()
 }
 
-fn value_type_name(value: &Value) -> String {
+pub(crate) fn value_type_name(value: &Value) -> String {
     match value {
         Value::Boolean(_) => "boolean",
         Value::Integer(_) => "integer",
+        Value::Int128(_) => "int128",
+        Value::Uint128(_) => "uint128",
         Value::Float(_) => "float",
         Value::Double(_) => "double",
+        #[cfg(feature = "decimal")]
+        Value::Decimal(_) => "decimal",
         Value::String(_) => "string",
+        #[cfg(feature = "uuid")]
         Value::Uuid(_) => "uuid",
+        #[cfg(feature = "chrono")]
         Value::DateTime(_) => "datetime",
+        #[cfg(feature = "chrono")]
+        Value::DateTimeTz(_) => "datetime-tz",
+        #[cfg(feature = "chrono")]
         Value::Date(_) => "date",
+        #[cfg(feature = "chrono")]
+        Value::Time(_) => "time",
+        Value::GeoPoint(_) => "geopoint",
         Value::Ipv4(_) => "ipv4",
         Value::Ipv6(_) => "ipv6",
+        Value::SocketAddr(_) => "socket address",
         Value::Binary(_) => "binary",
         Value::Array(_) => "array",
         Value::Object(_) => "object",
+        Value::Custom(custom) => return value_type_name(custom.repr()),
         Value::Null => "null",
     }
     .to_owned()
@@ -515,6 +1195,115 @@ mod tests {
         assert_eq!(enc.as_bytes().len(), 8);
     }
 
+    #[test]
+    fn test_encode_int64_precision() {
+        use crate::codec::Decoder;
+
+        // int64 travels as a double, exact up to 2^53
+        let schema = SchemaType::int64();
+        for i in [1 << 53, -(1 << 53), 1 << 60, i64::MIN] {
+            let mut enc = Encoder::new();
+            enc.encode(&Value::Integer(i), &schema).unwrap();
+            let decoded = Decoder::decode_exact(&enc.finish(), &schema).unwrap();
+            assert_eq!(decoded, Value::Integer(i));
+        }
+        for i in [(1 << 53) + 1, i64::MAX] {
+            assert!(Encoder::new().encode(&Value::Integer(i), &schema).is_err());
+        }
+
+        let mut enc = Encoder::with_options(EncoderOptions::new().with_lossy_floats(true));
+        enc.encode(&Value::Integer((1 << 53) + 1), &schema).unwrap();
+        let decoded = Decoder::decode_exact(&enc.finish(), &schema).unwrap();
+        assert_eq!(decoded, Value::Integer(1 << 53));
+    }
+
+    #[test]
+    fn test_encode_small_integers() {
+        let mut enc = Encoder::new();
+        enc.encode(&Value::Integer(-2), &SchemaType::int8())
+            .unwrap();
+        enc.encode(&Value::Integer(300), &SchemaType::int16())
+            .unwrap();
+        enc.encode(&Value::Integer(255), &SchemaType::uint8())
+            .unwrap();
+        enc.encode(&Value::Integer(65535), &SchemaType::uint16())
+            .unwrap();
+        assert_eq!(enc.as_bytes(), &[0xFE, 0x01, 0x2C, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn test_encode_small_integer_out_of_range() {
+        assert!(Encoder::new()
+            .encode(&Value::Integer(128), &SchemaType::int8())
+            .is_err());
+        assert!(Encoder::new()
+            .encode(&Value::Integer(-1), &SchemaType::uint16())
+            .is_err());
+    }
+
+    #[test]
+    fn test_encode_double_as_float() {
+        let schema = SchemaType::float();
+        for f in [1.5, -0.0, f32::INFINITY, f32::MAX] {
+            let mut enc = Encoder::new();
+            enc.encode(&Value::Double(f64::from(f)), &schema).unwrap();
+            assert_eq!(enc.as_bytes(), f.to_be_bytes());
+        }
+        for d in [0.1, 1e300, f64::MIN_POSITIVE] {
+            assert!(Encoder::new().encode(&Value::Double(d), &schema).is_err());
+        }
+
+        let mut enc = Encoder::with_options(EncoderOptions::new().with_lossy_floats(true));
+        enc.encode(&Value::Double(0.1), &schema).unwrap();
+        assert_eq!(enc.as_bytes(), 0.1_f32.to_be_bytes());
+    }
+
+    #[test]
+    fn test_encode_canonical_floats() {
+        use crate::codec::Decoder;
+
+        let encode = |value: Value, schema: &SchemaType, canonical: bool| {
+            let options = EncoderOptions::new().with_canonical_floats(canonical);
+            let mut enc = Encoder::with_options(options);
+            enc.encode(&value, schema).unwrap();
+            enc.finish()
+        };
+        let negative_nan = f64::from_bits(0xfff0_0000_0000_0001);
+        for (value, schema, expected) in [
+            (
+                Value::Double(-0.0),
+                SchemaType::double(),
+                0.0_f64.to_be_bytes(),
+            ),
+            (
+                Value::Double(negative_nan),
+                SchemaType::double(),
+                0x7ff8_0000_0000_0000_u64.to_be_bytes(),
+            ),
+            (
+                Value::Double(-1.5),
+                SchemaType::double(),
+                (-1.5_f64).to_be_bytes(),
+            ),
+        ] {
+            assert_eq!(encode(value.clone(), &schema, true).as_ref(), expected);
+            assert_eq!(
+                Decoder::decode(&mut encode(value.clone(), &schema, false).as_ref(), &schema)
+                    .unwrap(),
+                value
+            );
+        }
+        let float = SchemaType::float();
+        assert_eq!(
+            encode(Value::Float(-0.0), &float, true).as_ref(),
+            0.0_f32.to_be_bytes()
+        );
+        assert_eq!(
+            encode(Value::Float(f32::from_bits(0x7f80_0001)), &float, true).as_ref(),
+            0x7fc0_0000_u32.to_be_bytes()
+        );
+    }
+
     #[test]
     fn test_encode_string() {
         let mut enc = Encoder::new();
@@ -537,4 +1326,74 @@ mod tests {
         // Size-prefixed format: 3 * (1 byte size + 4 bytes int32) = 15 bytes
         assert_eq!(enc.as_bytes().len(), 15);
     }
+
+    #[test]
+    fn test_encode_filtered() {
+        use crate::codec::Decoder;
+        use crate::schema::Property;
+
+        let registry = SchemaRegistry::new();
+        let schema = SchemaType::object([
+            ("id", Property::required(SchemaType::int32())),
+            ("name", Property::optional(SchemaType::string())),
+            ("internal", Property::optional(SchemaType::string())),
+        ]);
+        let value = Value::object([
+            ("id", Value::from(1)),
+            ("name", Value::from("Ann")),
+            ("internal", Value::from("x")),
+        ]);
+        let expected = Value::object([("id", Value::from(1)), ("name", Value::from("Ann"))]);
+
+        for filter in [
+            FieldFilter::exclude(["internal"]),
+            FieldFilter::include(["id", "name"]),
+        ] {
+            let mut enc = Encoder::new();
+            enc.encode_filtered(&value, &schema, &filter, &registry)
+                .unwrap();
+            let bytes = enc.finish();
+            let decoded = Decoder::decode(&mut bytes.as_ref(), &schema).unwrap();
+            assert_eq!(decoded, expected);
+        }
+
+        let mut enc = Encoder::new();
+        let dropped = FieldFilter::include(["name"]);
+        assert!(enc
+            .encode_filtered(&value, &schema, &dropped, &registry)
+            .is_err());
+        assert!(enc
+            .encode_filtered(&value, &SchemaType::int32(), &dropped, &registry)
+            .is_err());
+    }
+
+    #[test]
+    fn test_encode_name_and_alias() {
+        use crate::codec::{Decoder, DecoderOptions};
+        use crate::schema::Property;
+
+        let schema = SchemaType::object([(
+            "full_name",
+            Property::required(SchemaType::string()).with_alias("name"),
+        )]);
+        let expected = Value::object([("full_name", "new")]);
+        for value in [
+            Value::object([("full_name", "new"), ("name", "old")]),
+            Value::object([("name", "old"), ("full_name", "new")]),
+        ] {
+            for format in [ObjectFormat::Indexed, ObjectFormat::Bitmap] {
+                // The property is written once, from the field under its name
+                let registry = SchemaRegistry::new()
+                    .with_object_format(format)
+                    .with_decoder_options(DecoderOptions::untrusted());
+                let mut enc = Encoder::new();
+                enc.encode_with_registry(&value, &schema, &registry)
+                    .unwrap();
+                let bytes = enc.finish();
+                let decoded =
+                    Decoder::decode_exact_with_registry(&bytes, &schema, &registry).unwrap();
+                assert_eq!(decoded, expected);
+            }
+        }
+    }
 }
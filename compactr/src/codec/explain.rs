@@ -0,0 +1,170 @@
+//! Breakdown of encoded sizes by field.
+
+use crate::codec::array::put_element_size;
+use crate::codec::encoder::put_property_header;
+use crate::codec::{ArrayFormat, Encoder};
+use crate::error::Result;
+use crate::schema::{index_of, index_order, SchemaRegistry, SchemaType};
+use crate::value::Value;
+use bytes::BytesMut;
+
+/// Encoded size of a value and of the fields it is made of, as returned by
+/// [`Encoder::explain`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeReport {
+    /// Encoded bytes of the value, including its own headers and length
+    /// prefixes
+    pub bytes: usize,
+    /// Sizes of the properties of an object, labeled by name, or of the
+    /// elements of an array, labeled `[index]`. The size of each includes
+    /// its header entry or element size prefix.
+    pub children: Vec<(String, SizeReport)>,
+}
+
+impl SizeReport {
+    /// Returns the report of a property or element by its label.
+    #[must_use]
+    pub fn get(&self, label: &str) -> Option<&Self> {
+        self.children
+            .iter()
+            .find_map(|(name, report)| (name == label).then_some(report))
+    }
+}
+
+impl Encoder {
+    /// Reports how many bytes each field of `value` takes when encoded.
+    ///
+    /// The report is a tree following the value: objects list their
+    /// properties and arrays their elements, which shows which fields
+    /// dominate a payload and would gain most from enums, varints or
+    /// compression.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value doesn't match the schema or encoding
+    /// fails.
+    pub fn explain(value: &Value, schema: &SchemaType) -> Result<SizeReport> {
+        Self::explain_with_registry(value, schema, SchemaRegistry::empty())
+    }
+
+    /// Reports the encoded size of each field of `value`, with a schema
+    /// registry for resolving references and layout settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value doesn't match the schema or encoding
+    /// fails.
+    pub fn explain_with_registry(
+        value: &Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<SizeReport> {
+        let mut buf = BytesMut::new();
+        Self::encode_value_into(&mut buf, value, schema, registry)?;
+        Ok(SizeReport {
+            bytes: buf.len(),
+            children: children(value, schema, registry)?,
+        })
+    }
+}
+
+/// Reports the properties or elements of `value`.
+fn children(
+    value: &Value,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Vec<(String, SizeReport)>> {
+    let mut buf = BytesMut::new();
+    let mut reports = Vec::new();
+    match (schema, value) {
+        (SchemaType::Reference(name), _) => {
+            let resolved = registry.resolve_shared(name)?;
+            return children(value, &resolved, registry);
+        }
+        (SchemaType::Object(properties), Value::Object(obj)) => {
+            let sorted = index_order(properties);
+            for (name, value) in obj {
+                let Some(idx) = index_of(&sorted, name) else {
+                    continue;
+                };
+                let property = sorted[idx].1;
+                buf.clear();
+                let schema = match &property.schema_type {
+                    // Referenced schemas keep their top-level layout inside objects
+                    SchemaType::Reference(name) => {
+                        let resolved = registry.resolve_ref(name)?;
+                        Encoder::encode_value_into(&mut buf, value, &resolved, registry)?;
+                        resolved
+                    }
+                    schema => {
+                        Encoder::encode_property_into(&mut buf, value, schema, registry)?;
+                        schema.clone()
+                    }
+                };
+                let size = buf.len();
+                buf.clear();
+                let format = registry.object_format();
+                put_property_header(&mut buf, format, registry, idx, property, size)?;
+                let report = SizeReport {
+                    bytes: buf.len() + size,
+                    children: children(value, &schema, registry)?,
+                };
+                reports.push((name.to_string(), report));
+            }
+        }
+        (SchemaType::Array(items), Value::Array(values)) => {
+            let format = registry.array_format();
+            for (i, value) in values.iter().enumerate() {
+                buf.clear();
+                Encoder::encode_value_into(&mut buf, value, items, registry)?;
+                let size = buf.len();
+                if format != ArrayFormat::CountPrefixed {
+                    buf.clear();
+                    put_element_size(&mut buf, format, registry.length_mode(), size)?;
+                }
+                let report = SizeReport {
+                    bytes: buf.len() + size,
+                    children: children(value, items, registry)?,
+                };
+                reports.push((format!("[{i}]"), report));
+            }
+        }
+        _ => {}
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain() {
+        let schema = SchemaType::object([
+            ("id", SchemaType::int32()),
+            ("name", SchemaType::string()),
+            ("tags", SchemaType::array(SchemaType::string())),
+        ]);
+        let value = Value::object([
+            ("id", Value::from(7)),
+            ("name", Value::from("Alice")),
+            ("tags", Value::from(vec!["a", "bc"])),
+        ]);
+
+        let report = Encoder::explain(&value, &schema).unwrap();
+        let mut enc = Encoder::new();
+        enc.encode(&value, &schema).unwrap();
+        assert_eq!(report.bytes, enc.finish().len());
+
+        // Index and size byte, then the value
+        assert_eq!(report.get("id").unwrap().bytes, 2 + 4);
+        assert_eq!(report.get("name").unwrap().bytes, 2 + 5);
+        let tags = report.get("tags").unwrap();
+        // Index, 0x00 flag and size, then two size-prefixed strings
+        assert_eq!(tags.bytes, 3 + (1 + 3) + (1 + 4));
+        assert_eq!(tags.get("[1]").unwrap().bytes, 1 + 4);
+        // The object header is the property count
+        let fields: usize = report.children.iter().map(|(_, r)| r.bytes).sum();
+        assert_eq!(report.bytes, 1 + fields);
+    }
+}
@@ -0,0 +1,99 @@
+//! Support for `#[compactr(flatten)]` fields.
+//!
+//! A flattened struct contributes its properties to the object of the
+//! struct containing it instead of being nested as a single property. These
+//! traits are implemented by `#[derive(Compactr)]` and are not meant to be
+//! implemented by hand.
+
+use crate::codec::Encode;
+use crate::error::Result;
+use crate::schema::{Property, Schema, SchemaRegistry, SchemaType};
+use indexmap::IndexMap;
+
+/// Encoding side of a flattened struct.
+pub trait Flatten {
+    /// Appends the wire name and value of every encoded field to `fields`.
+    fn flatten_fields<'a>(&'a self, fields: &mut Vec<(&'a str, FlatField<'a>)>);
+}
+
+/// A field value collected by [`Flatten::flatten_fields`].
+pub enum FlatField<'a> {
+    /// A field encoded through its own [`Encode`] implementation.
+    Borrowed(&'a dyn Encode),
+    /// A field encoded through a wrapper, such as a `with` module.
+    Owned(Box<dyn Encode + 'a>),
+}
+
+impl FlatField<'_> {
+    /// Returns the value to encode.
+    #[must_use]
+    pub fn as_encode(&self) -> &dyn Encode {
+        match self {
+            Self::Borrowed(value) => *value,
+            Self::Owned(value) => value.as_ref(),
+        }
+    }
+}
+
+/// Decoding side of a flattened struct.
+///
+/// `P` is the buffer type handed out for each property: `Bytes` when
+/// decoding through [`Decode`](crate::Decode), `&'de [u8]` when decoding
+/// through [`DecodeBorrowed`](crate::DecodeBorrowed).
+pub trait FlattenDecode<P>: Sized {
+    /// Decoded fields collected while walking the parent's properties.
+    type Slots;
+
+    /// Returns empty slots.
+    fn slots() -> Self::Slots;
+
+    /// Decodes the property `name` into `slots` if it belongs to `Self`.
+    ///
+    /// Returns `false` for properties of other fields.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the property cannot be decoded.
+    fn visit(
+        slots: &mut Self::Slots,
+        name: &str,
+        schema: &SchemaType,
+        buf: &mut P,
+        registry: &SchemaRegistry,
+    ) -> Result<bool>;
+
+    /// Builds the value once every property has been visited.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a required property was missing.
+    fn finish(slots: Self::Slots) -> Result<Self>;
+}
+
+/// Returns the properties that `T` contributes to a parent object.
+///
+/// # Panics
+///
+/// Panics if `T`'s schema is not an object schema, which cannot happen for
+/// structs deriving `Compactr`.
+#[must_use]
+pub fn flattened_properties<T: Schema + ?Sized>() -> IndexMap<String, Property> {
+    match T::schema() {
+        SchemaType::Object(properties) => properties,
+        other => panic!(
+            "cannot flatten {}: expected an object schema, got {other}",
+            std::any::type_name::<T>()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "expected an object schema")]
+    fn test_flatten_non_object() {
+        let _ = flattened_properties::<i32>();
+    }
+}
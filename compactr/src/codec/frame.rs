@@ -0,0 +1,128 @@
+//! Length-prefixed record framing.
+//!
+//! Records are concatenated, each preceded by its length as a big-endian
+//! `u32`, the same framing [`ContainerWriter`](crate::container::ContainerWriter)
+//! uses for its records.
+
+use crate::codec::length::{put_length, read_length, LengthMode};
+use crate::codec::wire::FRAME_LEN_WIDTH;
+use crate::codec::Decoder;
+use crate::error::{DecodeError, EncodeError, Result};
+use crate::schema::{SchemaRegistry, SchemaType};
+use crate::value::Value;
+use bytes::BytesMut;
+use std::borrow::Cow;
+
+/// Reserves the length prefix of a record about to be written to `buf` and
+/// returns its position, to be passed to [`end_frame`].
+pub(crate) fn begin_frame(buf: &mut BytesMut) -> usize {
+    let start = buf.len();
+    buf.extend_from_slice(&[0; FRAME_LEN_WIDTH]);
+    start
+}
+
+/// Fills in the length prefix reserved by [`begin_frame`] at `start`.
+pub(crate) fn end_frame(buf: &mut BytesMut, start: usize) -> Result<(), EncodeError> {
+    let len = buf.len() - start - FRAME_LEN_WIDTH;
+    let mut prefix = BytesMut::with_capacity(FRAME_LEN_WIDTH);
+    put_length(
+        &mut prefix,
+        LengthMode::Fixed,
+        FRAME_LEN_WIDTH,
+        len,
+        "Record length",
+    )?;
+    buf[start..start + FRAME_LEN_WIDTH].copy_from_slice(&prefix);
+    Ok(())
+}
+
+/// Splits the next record off `buf`.
+pub(crate) fn read_frame<'a>(buf: &mut &'a [u8]) -> Result<&'a [u8], DecodeError> {
+    let len = read_length(buf, LengthMode::Fixed, FRAME_LEN_WIDTH)?;
+    if buf.len() < len {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    let (record, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(record)
+}
+
+/// Iterator over the values of length-prefixed records, returned by
+/// [`Decoder::decode_many`].
+pub(crate) struct Records<'a> {
+    bytes: &'a [u8],
+    schema: &'a SchemaType,
+    registry: Cow<'a, SchemaRegistry>,
+}
+
+impl<'a> Records<'a> {
+    pub(crate) fn new(
+        bytes: &'a [u8],
+        schema: &'a SchemaType,
+        registry: Cow<'a, SchemaRegistry>,
+    ) -> Self {
+        Self {
+            bytes,
+            schema,
+            registry,
+        }
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        let result = read_frame(&mut self.bytes)
+            .map_err(Into::into)
+            .and_then(|mut record| {
+                Decoder::decode_with_registry(&mut record, self.schema, &self.registry)
+            });
+        if result.is_err() {
+            // The framing can't be trusted past a broken record
+            self.bytes = &[];
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Encoder;
+
+    #[test]
+    fn test_encode_decode_many() {
+        let schema =
+            SchemaType::object([("id", SchemaType::int32()), ("name", SchemaType::string())]);
+        let values: Vec<Value> = (0..5)
+            .map(|id| {
+                Value::object([
+                    ("id", Value::from(id)),
+                    ("name", Value::from("x".repeat(100))),
+                ])
+            })
+            .collect();
+
+        let mut enc = Encoder::new();
+        enc.encode_many(&values, &schema).unwrap();
+        let bytes = enc.finish();
+        assert_eq!(bytes[..4], [0, 0, 0, 109]);
+
+        let decoded: Vec<Value> = Decoder::decode_many(&bytes, &schema)
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(decoded, values);
+        assert_eq!(Decoder::decode_many(&[], &schema).count(), 0);
+
+        // A truncated record ends the iteration with an error
+        let truncated = &bytes[..bytes.len() - 1];
+        let results: Vec<_> = Decoder::decode_many(truncated, &schema).collect();
+        assert_eq!(results.len(), 5);
+        assert!(results[..4].iter().all(Result::is_ok));
+        assert!(results[4].is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! Interning of decoded property names.
+//!
+//! Every object decoded with a schema has the same property names, so the
+//! decoder, and the conversions from JSON, CBOR, `MessagePack` and serde,
+//! share one allocation per name rather than copying it into each
+//! [`Value::Object`](crate::Value::Object). Names are kept per thread and
+//! live as long as the thread, or until [`MAX_INTERNED`] of them are.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Most names kept per thread. Once full, the table starts over, so that
+/// decoding with many schemas can't grow it without bound.
+const MAX_INTERNED: usize = 4096;
+
+thread_local! {
+    static NAMES: RefCell<HashSet<Arc<str>>> = RefCell::new(HashSet::new());
+}
+
+/// Returns the shared allocation of `name`.
+pub(crate) fn intern(name: &str) -> Arc<str> {
+    NAMES.with(|names| {
+        let mut names = names.borrow_mut();
+        if let Some(interned) = names.get(name) {
+            return Arc::clone(interned);
+        }
+        if names.len() >= MAX_INTERNED {
+            names.clear();
+        }
+        let interned: Arc<str> = Arc::from(name);
+        names.insert(Arc::clone(&interned));
+        interned
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Decoder, Encoder};
+    use crate::schema::{Property, SchemaType};
+    use crate::value::Value;
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_intern() {
+        let a = intern("name");
+        let b = intern(&String::from("name"));
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &intern("other")));
+
+        for i in 0..MAX_INTERNED {
+            intern(&i.to_string());
+        }
+        NAMES.with(|names| assert!(names.borrow().len() <= MAX_INTERNED));
+        assert_eq!(*intern("name"), *a);
+    }
+
+    #[test]
+    fn test_decoded_keys_are_shared() {
+        let mut properties = IndexMap::new();
+        properties.insert("id".to_owned(), Property::required(SchemaType::int32()));
+        let schema = SchemaType::array(SchemaType::object(properties));
+        let value = Value::array([Value::object([("id", 1)]), Value::object([("id", 2)])]);
+        let mut encoder = Encoder::new();
+        encoder.encode(&value, &schema).unwrap();
+        let bytes = encoder.finish();
+
+        let decoded = Decoder::decode(&mut bytes.as_ref(), &schema).unwrap();
+        assert_eq!(decoded, value);
+        let keys: Vec<_> = decoded
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item.as_object().unwrap().get_index(0).unwrap().0)
+            .collect();
+        assert!(Arc::ptr_eq(keys[0], keys[1]));
+
+        let cloned = decoded.clone();
+        let key = cloned.as_array().unwrap()[0]
+            .as_object()
+            .unwrap()
+            .get_index(0)
+            .unwrap()
+            .0;
+        assert!(Arc::ptr_eq(keys[0], key));
+    }
+}
@@ -0,0 +1,210 @@
+//! Length prefixes.
+
+use crate::error::{DecodeError, EncodeError};
+use bytes::{Buf, BufMut, BytesMut};
+
+/// How lengths, sizes and counts are written on the wire.
+///
+/// The mode covers every length prefix the codec writes: string and binary
+/// lengths, array element sizes, counts and runs, and the sizes of object
+/// properties whose [`SizeWidth`](crate::SizeWidth) is `Auto`. Both ends
+/// must agree on it: it is configured on the
+/// [`SchemaRegistry`](crate::SchemaRegistry) handed to the encoder and the
+/// decoder, with [`SchemaRegistry::with_length_mode`](crate::SchemaRegistry::with_length_mode).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LengthMode {
+    /// Fixed-width big-endian prefixes, as in compactr.js: 2 bytes for
+    /// strings, 4 for binary data and long strings, 1 for array element
+    /// and property sizes. Compatible with every other implementation.
+    #[default]
+    Fixed,
+    /// Unsigned LEB128 varints everywhere: one byte for lengths under 128,
+    /// two under 16 KiB, and so on. Smaller for short values and free of
+    /// the fixed widths' limits, but only understood by readers configured
+    /// the same way.
+    Varint,
+}
+
+/// Writes a length prefix: `width` big-endian bytes in
+/// [`LengthMode::Fixed`], a varint in [`LengthMode::Varint`].
+///
+/// `what` names the length in the error returned when it doesn't fit the
+/// fixed width.
+pub(crate) fn put_length(
+    buf: &mut BytesMut,
+    mode: LengthMode,
+    width: usize,
+    len: usize,
+    what: &str,
+) -> Result<(), EncodeError> {
+    if mode == LengthMode::Varint {
+        put_varint(buf, len);
+        return Ok(());
+    }
+    let max = u64::MAX >> (64 - 8 * width);
+    match u64::try_from(len) {
+        Ok(len) if len <= max => {
+            buf.put_uint(len, width);
+            Ok(())
+        }
+        _ => Err(EncodeError::InvalidFormat(format!(
+            "{what} too large: {len} (max {max})"
+        ))),
+    }
+}
+
+/// Reads a length prefix written by [`put_length`].
+pub(crate) fn read_length(
+    buf: &mut impl Buf,
+    mode: LengthMode,
+    width: usize,
+) -> Result<usize, DecodeError> {
+    if mode == LengthMode::Varint {
+        return read_varint(buf);
+    }
+    if buf.remaining() < width {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    usize::try_from(buf.get_uint(width))
+        .map_err(|_| DecodeError::InvalidData("Length overflows usize".to_owned()))
+}
+
+/// Writes `value` as an unsigned LEB128 varint.
+pub(crate) fn put_varint(buf: &mut BytesMut, value: usize) {
+    let mut rest = value;
+    while rest >= 0x80 {
+        #[allow(clippy::cast_possible_truncation)]
+        buf.put_u8((rest & 0x7F) as u8 | 0x80);
+        rest >>= 7;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    buf.put_u8(rest as u8);
+}
+
+/// Reads an unsigned LEB128 varint.
+pub(crate) fn read_varint(buf: &mut impl Buf) -> Result<usize, DecodeError> {
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+        if !buf.has_remaining() {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let byte = buf.get_u8();
+        let bits = usize::from(byte & 0x7F);
+        if shift >= usize::BITS || (bits << shift) >> shift != bits {
+            return Err(DecodeError::InvalidData(
+                "Varint length overflows usize".to_owned(),
+            ));
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{ArrayFormat, DecodeBorrowed, Decoder, Encoder};
+    use crate::schema::{Property, SchemaRegistry, SchemaType};
+    use crate::value::Value;
+    use indexmap::IndexMap;
+
+    fn schema() -> SchemaType {
+        let mut properties = IndexMap::new();
+        properties.insert("name".to_owned(), Property::required(SchemaType::string()));
+        properties.insert(
+            "tags".to_owned(),
+            Property::optional(SchemaType::array(SchemaType::string())),
+        );
+        properties.insert(
+            "flags".to_owned(),
+            Property::optional(SchemaType::run_length_array(SchemaType::boolean())),
+        );
+        properties.insert("data".to_owned(), Property::optional(SchemaType::binary()));
+        SchemaType::object(properties)
+    }
+
+    fn value(name: &str) -> Value {
+        let mut obj = IndexMap::new();
+        obj.insert("name".into(), Value::from(name));
+        obj.insert(
+            "tags".into(),
+            Value::Array(vec![Value::from("a"), Value::from("b".repeat(300))]),
+        );
+        obj.insert("flags".into(), Value::Array(vec![Value::Boolean(true); 3]));
+        obj.insert("data".into(), Value::Binary(vec![7; 4]));
+        Value::Object(obj)
+    }
+
+    #[test]
+    fn test_varint_lengths() {
+        let registry = SchemaRegistry::new().with_length_mode(LengthMode::Varint);
+        let schema = schema();
+        let value = value(&"n".repeat(200));
+
+        // Fixed-width element and property sizes stop at 255 bytes
+        assert!(Encoder::new().encode(&value, &schema).is_err());
+
+        let mut enc = Encoder::new();
+        enc.encode_with_registry(&value, &schema, &registry)
+            .unwrap();
+        let bytes = enc.finish();
+        let decoded = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry);
+        assert_eq!(decoded.unwrap(), value);
+
+        // Count-prefixed arrays take varint counts
+        let counted = registry
+            .clone()
+            .with_array_format(ArrayFormat::CountPrefixed);
+        let mut enc = Encoder::new();
+        enc.encode_with_registry(&value, &schema, &counted).unwrap();
+        let bytes = enc.finish();
+        let decoded = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &counted);
+        assert_eq!(decoded.unwrap(), value);
+    }
+
+    #[test]
+    fn test_varint_strings() {
+        let registry = SchemaRegistry::new().with_length_mode(LengthMode::Varint);
+        let schema = SchemaType::string();
+        let mut enc = Encoder::new();
+        enc.encode_with_registry(&Value::from("hi"), &schema, &registry)
+            .unwrap();
+        let bytes = enc.finish();
+        assert_eq!(bytes.as_ref(), [2, b'h', b'i']);
+
+        // Typed and borrowed decoding follow the registry too
+        let typed: String = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(typed, "hi");
+        let borrowed = <&str>::decode_borrowed(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(borrowed, "hi");
+        let mut buf = BytesMut::new();
+        Encoder::encode_into(&mut buf, "hi", &schema, &registry).unwrap();
+        assert_eq!(buf, bytes);
+
+        // Strings are no longer limited to 64 KiB
+        let long = Value::from("x".repeat(70_000));
+        let mut enc = Encoder::new();
+        enc.encode_with_registry(&long, &schema, &registry).unwrap();
+        let bytes = enc.finish();
+        assert_eq!(bytes[..3], [0xF0, 0xA2, 0x04]);
+        let decoded = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry);
+        assert_eq!(decoded.unwrap(), long);
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        let mut buf = BytesMut::new();
+        assert!(put_length(&mut buf, LengthMode::Fixed, 2, 70_000, "String length").is_err());
+        put_length(&mut buf, LengthMode::Fixed, 2, 300, "String length").unwrap();
+        assert_eq!(buf.as_ref(), [0x01, 0x2C]);
+
+        // Truncated and overlong varints
+        assert!(read_length(&mut [0x80].as_ref(), LengthMode::Varint, 2).is_err());
+        assert!(read_length(&mut [0xFF; 11].as_ref(), LengthMode::Varint, 2).is_err());
+        assert!(read_length(&mut [0x01].as_ref(), LengthMode::Fixed, 2).is_err());
+    }
+}
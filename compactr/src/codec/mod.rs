@@ -1,10 +1,41 @@
 //! Encoding and decoding functionality.
 
+mod array;
+mod borrowed;
 pub mod buffer;
 mod decoder;
 mod encoder;
+mod explain;
+pub(crate) mod flatten;
+pub(crate) mod frame;
+mod intern;
+mod length;
+mod object;
+mod options;
+mod parts;
+mod streaming;
 mod traits;
+pub mod wire;
+pub(crate) mod with;
 
+pub use array::ArrayFormat;
+#[cfg(feature = "serde")]
+pub(crate) use array::{put_count, put_element_size, read_element_size};
+pub use borrowed::DecodeBorrowed;
 pub use decoder::Decoder;
+#[cfg(feature = "serde")]
+pub(crate) use encoder::put_property;
 pub use encoder::Encoder;
+#[cfg(feature = "serde")]
+pub(crate) use encoder::{check_integer_precision, check_integer_range, narrow_float};
+pub(crate) use encoder::{type_mismatch, value_type_name};
+pub use explain::SizeReport;
+pub(crate) use intern::intern;
+pub use length::LengthMode;
+#[cfg(feature = "serde")]
+pub(crate) use object::put_object_header;
+pub use object::ObjectFormat;
+pub use options::{DecoderOptions, EncoderOptions, FieldFilter};
+pub use parts::ObjectParts;
+pub use streaming::{DecodeMachine, Decoded, StreamingDecoder};
 pub use traits::{Decode, Encode};
@@ -0,0 +1,214 @@
+//! Object wire layouts.
+
+use crate::error::{DecodeError, EncodeError};
+use crate::schema::{Property, INLINE_PROPERTIES};
+use bytes::{Buf, BufMut, BytesMut};
+use smallvec::SmallVec;
+
+/// How the properties present in an object are announced on the wire.
+///
+/// Both ends must agree on the layout: it is configured on the
+/// [`SchemaRegistry`](crate::SchemaRegistry) handed to the encoder and the
+/// decoder, with [`SchemaRegistry::with_object_format`](crate::SchemaRegistry::with_object_format).
+/// Partial objects and [`ObjectParts`](crate::ObjectParts) are defined by
+/// their header entries and always use [`ObjectFormat::Indexed`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ObjectFormat {
+    /// A property count, then the alphabetical index, size and value of
+    /// each property present, as in compactr.js:
+    /// `[count, index1, size1, value1, ...]`.
+    #[default]
+    Indexed,
+    /// A presence bitmap with one bit per optional property, in alphabetical
+    /// order and least significant bit first, then the size and value of
+    /// every present property in alphabetical order:
+    /// `[bitmap..., size1, value1, ...]`. Required properties are always
+    /// present and take no bit, which saves most of the header of wide
+    /// objects with many optional properties.
+    Bitmap,
+}
+
+/// Writes the part of an object header that precedes its properties.
+///
+/// `present` yields the alphabetical indices of the properties that
+/// follow, which must be ascending and unique for [`ObjectFormat::Bitmap`].
+pub(crate) fn put_object_header<'a>(
+    buf: &mut BytesMut,
+    format: ObjectFormat,
+    sorted: impl IntoIterator<Item = &'a Property>,
+    present: impl ExactSizeIterator<Item = usize>,
+) -> Result<(), EncodeError> {
+    match format {
+        ObjectFormat::Indexed => {
+            let count = u8::try_from(present.len()).map_err(|_| {
+                EncodeError::InvalidFormat(format!(
+                    "Too many properties: {} (max 255)",
+                    present.len()
+                ))
+            })?;
+            buf.put_u8(count);
+        }
+        ObjectFormat::Bitmap => {
+            let mut present = present.peekable();
+            let (mut byte, mut bit) = (0u8, 0);
+            for (idx, property) in sorted.into_iter().enumerate() {
+                let is_present = present.next_if_eq(&idx).is_some();
+                if property.required {
+                    continue;
+                }
+                if is_present {
+                    byte |= 1 << bit;
+                }
+                bit += 1;
+                if bit == 8 {
+                    buf.put_u8(byte);
+                    (byte, bit) = (0, 0);
+                }
+            }
+            if bit > 0 {
+                buf.put_u8(byte);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Which of the alphabetically sorted properties of an object are present.
+pub(crate) type Presence = SmallVec<[bool; INLINE_PROPERTIES]>;
+
+/// Reads the presence bitmap of an [`ObjectFormat::Bitmap`] object and
+/// returns which of the alphabetically sorted properties follow.
+pub(crate) fn read_bitmap<'a>(
+    buf: &mut impl Buf,
+    sorted: impl IntoIterator<Item = &'a Property>,
+) -> Result<Presence, DecodeError> {
+    let mut present = Presence::new();
+    let (mut byte, mut bit) = (0u8, 8);
+    for property in sorted {
+        if property.required {
+            present.push(true);
+            continue;
+        }
+        if bit == 8 {
+            if !buf.has_remaining() {
+                return Err(DecodeError::UnexpectedEof);
+            }
+            (byte, bit) = (buf.get_u8(), 0);
+        }
+        present.push(byte & (1 << bit) != 0);
+        bit += 1;
+    }
+    // Padding bits must be clear, so every object has a single encoding
+    if bit < 8 && byte >> bit != 0 {
+        return Err(DecodeError::InvalidData(format!(
+            "Presence bitmap {byte:#04x} marks unknown properties"
+        )));
+    }
+    Ok(present)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::codec::{Decoder, Encoder, ObjectFormat};
+    use crate::schema::{IntegerFormat, Property, SchemaRegistry, SchemaType};
+    use crate::value::Value;
+    use indexmap::IndexMap;
+
+    fn schema() -> SchemaType {
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "id".to_owned(),
+            Property::required(SchemaType::Integer(IntegerFormat::Uint8)),
+        );
+        for i in 0..10 {
+            properties.insert(
+                format!("opt{i}"),
+                Property::optional(SchemaType::Integer(IntegerFormat::Uint8)),
+            );
+        }
+        SchemaType::Object(properties)
+    }
+
+    #[test]
+    fn test_bitmap_roundtrip() {
+        let schema = schema();
+        let registry = SchemaRegistry::new().with_object_format(ObjectFormat::Bitmap);
+        let mut obj = IndexMap::new();
+        obj.insert("opt9".into(), Value::Integer(9));
+        obj.insert("id".into(), Value::Integer(1));
+        obj.insert("opt0".into(), Value::Integer(0));
+        let value = Value::Object(obj);
+
+        let mut encoder = Encoder::new();
+        encoder
+            .encode_with_registry(&value, &schema, &registry)
+            .unwrap();
+        let bytes = encoder.finish();
+        // Bitmap over opt0..opt9, then id, opt0 and opt9 in alphabetical order
+        assert_eq!(bytes.as_ref(), [0x01, 0x02, 1, 1, 1, 0, 1, 9]);
+
+        let decoded = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry);
+        let Value::Object(decoded) = decoded.unwrap() else {
+            panic!("expected an object");
+        };
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded["opt9"], Value::Integer(9));
+
+        // The indexed layout spends an index and a size on every property
+        let mut encoder = Encoder::new();
+        encoder.encode(&value, &schema).unwrap();
+        assert_eq!(encoder.finish().len(), 10);
+    }
+
+    #[test]
+    fn test_bitmap_rejects_invalid() {
+        let schema = schema();
+        let registry = SchemaRegistry::new().with_object_format(ObjectFormat::Bitmap);
+
+        // Padding bits beyond the ten optional properties
+        let padded = [0x00, 0x04, 1, 1];
+        assert!(Decoder::decode_with_registry(&mut padded.as_ref(), &schema, &registry).is_err());
+
+        // Required properties are always read
+        let truncated = [0x00, 0x00];
+        assert!(
+            Decoder::decode_with_registry(&mut truncated.as_ref(), &schema, &registry).is_err()
+        );
+
+        let mut obj = IndexMap::new();
+        obj.insert("opt1".into(), Value::Integer(1));
+        let mut encoder = Encoder::new();
+        assert!(encoder
+            .encode_with_registry(&Value::Object(obj), &schema, &registry)
+            .is_err());
+    }
+
+    #[test]
+    fn test_objects_beyond_inline_properties() {
+        let count = crate::schema::INLINE_PROPERTIES * 2 + 1;
+        let properties = (0..count)
+            .map(|i| {
+                let schema = SchemaType::Integer(IntegerFormat::Uint8);
+                (format!("p{i:02}"), Property::optional(schema))
+            })
+            .collect();
+        let schema = SchemaType::Object(properties);
+        let value = Value::object(
+            (0..count)
+                .step_by(3)
+                .map(|i| (format!("p{i:02}"), i64::try_from(i).unwrap())),
+        );
+
+        for format in [ObjectFormat::Indexed, ObjectFormat::Bitmap] {
+            let registry = SchemaRegistry::new().with_object_format(format);
+            let mut encoder = Encoder::new();
+            encoder
+                .encode_with_registry(&value, &schema, &registry)
+                .unwrap();
+            let bytes = encoder.finish();
+            let decoded =
+                Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry).unwrap();
+            assert_eq!(decoded, value);
+        }
+    }
+}
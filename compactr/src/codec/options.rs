@@ -0,0 +1,416 @@
+//! Encoder and decoder options.
+
+use crate::codec::{ArrayFormat, LengthMode, ObjectFormat};
+
+/// Wire layout settings gathered into one builder, along with how values
+/// that don't fit their schema exactly are treated.
+///
+/// Every setting defaults to the compactr.js-compatible layout, so
+/// `EncoderOptions::default()` produces the same bytes as a plain
+/// [`Encoder::new`](crate::Encoder::new). Decoders must be given the same
+/// settings, through a [`SchemaRegistry`](crate::SchemaRegistry) configured
+/// with [`SchemaRegistry::with_options`](crate::SchemaRegistry::with_options).
+///
+/// ```rust
+/// use compactr::{ArrayFormat, Encoder, EncoderOptions, LengthMode};
+///
+/// let options = EncoderOptions::new()
+///     .with_array_format(ArrayFormat::CountPrefixed)
+///     .with_length_mode(LengthMode::Varint);
+/// let encoder = Encoder::with_options(options);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EncoderOptions {
+    array_format: ArrayFormat,
+    object_format: ObjectFormat,
+    length_mode: LengthMode,
+    lossy_floats: bool,
+    canonical_floats: bool,
+}
+
+impl EncoderOptions {
+    /// Creates options with every setting at its default.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            array_format: ArrayFormat::SizePrefixed,
+            object_format: ObjectFormat::Indexed,
+            length_mode: LengthMode::Fixed,
+            lossy_floats: false,
+            canonical_floats: false,
+        }
+    }
+
+    /// Sets the array layout.
+    #[must_use]
+    pub const fn with_array_format(mut self, format: ArrayFormat) -> Self {
+        self.array_format = format;
+        self
+    }
+
+    /// Returns the array layout, [`ArrayFormat::SizePrefixed`] by default.
+    #[must_use]
+    pub const fn array_format(&self) -> ArrayFormat {
+        self.array_format
+    }
+
+    /// Sets the object layout.
+    #[must_use]
+    pub const fn with_object_format(mut self, format: ObjectFormat) -> Self {
+        self.object_format = format;
+        self
+    }
+
+    /// Returns the object layout, [`ObjectFormat::Indexed`] by default.
+    #[must_use]
+    pub const fn object_format(&self) -> ObjectFormat {
+        self.object_format
+    }
+
+    /// Sets how lengths, sizes and counts are written.
+    #[must_use]
+    pub const fn with_length_mode(mut self, mode: LengthMode) -> Self {
+        self.length_mode = mode;
+        self
+    }
+
+    /// Returns the length mode, [`LengthMode::Fixed`] by default.
+    #[must_use]
+    pub const fn length_mode(&self) -> LengthMode {
+        self.length_mode
+    }
+
+    /// Sets whether numbers may be rounded to fit a `float` schema.
+    ///
+    /// By default, a [`Value::Double`](crate::Value::Double), or a number
+    /// read from CBOR, `MessagePack` or serde, that a 32-bit float can't hold
+    /// exactly fails to encode with a `float` schema rather than silently
+    /// losing precision. The same goes for integers beyond 2^53 with an
+    /// `int64` schema, which travels as a double, and for integers read as
+    /// a `double`. With lossy floats, they're rounded to the nearest float
+    /// or double instead. JSON numbers are decimal text and always read as
+    /// the nearest float.
+    #[must_use]
+    pub const fn with_lossy_floats(mut self, lossy: bool) -> Self {
+        self.lossy_floats = lossy;
+        self
+    }
+
+    /// Returns whether numbers may be rounded to fit a `float`, `double` or
+    /// `int64` schema, `false` by default.
+    #[must_use]
+    pub const fn lossy_floats(&self) -> bool {
+        self.lossy_floats
+    }
+
+    /// Sets whether floats and doubles are written in a canonical form.
+    ///
+    /// Values that compare equal as numbers can differ in their bits: `-0.0`
+    /// and `0.0`, or NaNs with different signs and payloads. Both are
+    /// written as is by default, so that they survive a round trip. With
+    /// canonical floats, `-0.0` is written as `0.0` and every NaN as the
+    /// single quiet NaN, so producers that compute the same numbers write
+    /// the same bytes, and hashes and [signatures](crate::signing) over
+    /// them agree.
+    #[must_use]
+    pub const fn with_canonical_floats(mut self, canonical: bool) -> Self {
+        self.canonical_floats = canonical;
+        self
+    }
+
+    /// Returns whether floats and doubles are written in a canonical form,
+    /// `false` by default.
+    #[must_use]
+    pub const fn canonical_floats(&self) -> bool {
+        self.canonical_floats
+    }
+}
+
+/// Limits and checks applied while decoding into [`Value`](crate::Value)s.
+///
+/// The defaults accept anything the format can express, which suits data
+/// written by trusted peers. Services parsing input from the internet should
+/// start from [`DecoderOptions::untrusted`], which bounds nesting and sizes
+/// and rejects inputs a conforming encoder never writes. The options are set
+/// on the [`SchemaRegistry`](crate::SchemaRegistry) handed to the decoder:
+///
+/// ```rust
+/// use compactr::{DecoderOptions, SchemaRegistry};
+///
+/// let registry = SchemaRegistry::new().with_decoder_options(DecoderOptions::untrusted());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecoderOptions {
+    max_depth: usize,
+    max_string_len: usize,
+    max_array_len: usize,
+    strict: bool,
+    redact: bool,
+}
+
+impl Default for DecoderOptions {
+    fn default() -> Self {
+        Self::trusted()
+    }
+}
+
+impl DecoderOptions {
+    /// Creates options with every setting at its default, the same as
+    /// [`DecoderOptions::trusted`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::trusted()
+    }
+
+    /// No limits and no strict checks, for data from trusted sources.
+    #[must_use]
+    pub const fn trusted() -> Self {
+        Self {
+            max_depth: usize::MAX,
+            max_string_len: usize::MAX,
+            max_array_len: usize::MAX,
+            strict: false,
+            redact: false,
+        }
+    }
+
+    /// Tight limits and strict checks, for data from untrusted sources:
+    /// nesting up to 64 levels, strings and binary values up to 1 MiB,
+    /// arrays up to 65536 elements.
+    #[must_use]
+    pub const fn untrusted() -> Self {
+        Self {
+            max_depth: 64,
+            max_string_len: 1024 * 1024,
+            max_array_len: 64 * 1024,
+            strict: true,
+            redact: false,
+        }
+    }
+
+    /// Sets how deeply arrays, objects and `oneOf` payloads may nest.
+    #[must_use]
+    pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Returns the maximum nesting depth.
+    #[must_use]
+    pub const fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Sets the largest string or binary value, in bytes.
+    #[must_use]
+    pub const fn with_max_string_len(mut self, max_string_len: usize) -> Self {
+        self.max_string_len = max_string_len;
+        self
+    }
+
+    /// Returns the largest string or binary value, in bytes.
+    #[must_use]
+    pub const fn max_string_len(&self) -> usize {
+        self.max_string_len
+    }
+
+    /// Sets the largest number of elements in an array.
+    #[must_use]
+    pub const fn with_max_array_len(mut self, max_array_len: usize) -> Self {
+        self.max_array_len = max_array_len;
+        self
+    }
+
+    /// Returns the largest number of elements in an array.
+    #[must_use]
+    pub const fn max_array_len(&self) -> usize {
+        self.max_array_len
+    }
+
+    /// Sets whether to reject duplicate properties and property or element
+    /// bytes left over after their value.
+    #[must_use]
+    pub const fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Returns whether strict checks are enabled.
+    #[must_use]
+    pub const fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Sets whether to replace the values of
+    /// [sensitive](crate::Property::with_sensitive) properties with the
+    /// [`REDACTED`](crate::value::REDACTED) placeholder instead of decoding
+    /// them, for decoders feeding logs and debug tools.
+    #[must_use]
+    pub const fn with_redact(mut self, redact: bool) -> Self {
+        self.redact = redact;
+        self
+    }
+
+    /// Returns whether sensitive properties are redacted.
+    #[must_use]
+    pub const fn redact(&self) -> bool {
+        self.redact
+    }
+}
+
+/// Which properties of an object
+/// [`Encoder::encode_filtered`](crate::Encoder::encode_filtered) writes.
+///
+/// Filtering while encoding drops internal fields before data leaves a
+/// service without cloning and editing the [`Value`](crate::Value) first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldFilter {
+    /// Only the named properties
+    Include(Vec<String>),
+    /// Every property except the named ones
+    Exclude(Vec<String>),
+}
+
+impl FieldFilter {
+    /// Keeps only the named properties.
+    pub fn include<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::Include(names.into_iter().map(Into::into).collect())
+    }
+
+    /// Drops the named properties.
+    pub fn exclude<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::Exclude(names.into_iter().map(Into::into).collect())
+    }
+
+    /// Returns whether the property `name` is written.
+    #[must_use]
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            Self::Include(names) => names.iter().any(|n| n == name),
+            Self::Exclude(names) => !names.iter().any(|n| n == name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Decoder, Encoder};
+    use crate::schema::{Property, SchemaRegistry, SchemaType};
+    use crate::value::Value;
+
+    #[test]
+    fn test_encoder_options() {
+        let options = EncoderOptions::new()
+            .with_array_format(ArrayFormat::CountPrefixed)
+            .with_object_format(ObjectFormat::Bitmap)
+            .with_length_mode(LengthMode::Varint);
+        assert_eq!(EncoderOptions::default(), EncoderOptions::new());
+        assert_eq!(options.length_mode(), LengthMode::Varint);
+        assert!(!options.lossy_floats());
+        assert!(options.with_lossy_floats(true).lossy_floats());
+        assert!(!options.canonical_floats());
+
+        let schema = SchemaType::object([
+            ("name", SchemaType::string()),
+            ("tags", SchemaType::array(SchemaType::string())),
+        ]);
+        let value = Value::object([
+            ("name", Value::from("n".repeat(300))),
+            ("tags", Value::from(vec!["a", "b"])),
+        ]);
+
+        let mut enc = Encoder::with_options(options);
+        enc.encode(&value, &schema).unwrap();
+        let bytes = enc.finish();
+
+        // The options override the layout of a registry passed in
+        let registry = SchemaRegistry::new().with_options(options);
+        let mut enc = Encoder::with_options(options);
+        enc.encode_with_registry(&value, &schema, &SchemaRegistry::new())
+            .unwrap();
+        assert_eq!(enc.finish(), bytes);
+        let mut enc = Encoder::new();
+        enc.encode_with_registry(&value, &schema, &registry)
+            .unwrap();
+        assert_eq!(enc.finish(), bytes);
+
+        let decoded = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry);
+        assert_eq!(decoded.unwrap(), value);
+    }
+
+    #[test]
+    fn test_decoder_limits() {
+        let registry = SchemaRegistry::new().with_decoder_options(DecoderOptions::untrusted());
+        registry
+            .register(
+                "Node",
+                SchemaType::object([(
+                    "children",
+                    SchemaType::array(SchemaType::reference("Node")),
+                )]),
+            )
+            .unwrap();
+        let schema = SchemaType::reference("Node");
+        let decode = |bytes: &[u8], registry: &SchemaRegistry| {
+            Decoder::decode_with_registry(&mut &*bytes, &schema, registry)
+        };
+
+        let mut node = Value::object([("children", Vec::<Value>::new())]);
+        for _ in 0..40 {
+            node = Value::object([("children", vec![node])]);
+        }
+        let mut enc = Encoder::new();
+        enc.encode_with_registry(&node, &schema, &registry).unwrap();
+        let bytes = enc.finish();
+        let err = decode(&bytes, &registry).unwrap_err().to_string();
+        assert!(err.contains("Nesting deeper than 64 levels"), "{err}");
+        let trusted = registry
+            .clone()
+            .with_decoder_options(DecoderOptions::trusted());
+        assert_eq!(decode(&bytes, &trusted).unwrap(), node);
+
+        let limited = SchemaRegistry::new().with_decoder_options(
+            DecoderOptions::untrusted()
+                .with_max_string_len(4)
+                .with_max_array_len(2),
+        );
+        let string = SchemaType::string();
+        let mut enc = Encoder::new();
+        enc.encode(&Value::from("hello"), &string).unwrap();
+        let bytes = enc.finish();
+        assert!(Decoder::decode_with_registry(&mut bytes.as_ref(), &string, &limited).is_err());
+        let array = SchemaType::array(SchemaType::int8());
+        let mut enc = Encoder::new();
+        enc.encode(&Value::from(vec![1, 2, 3]), &array).unwrap();
+        let bytes = enc.finish();
+        assert!(Decoder::decode_with_registry(&mut bytes.as_ref(), &array, &limited).is_err());
+    }
+
+    #[test]
+    fn test_decoder_strict() {
+        let strict = SchemaRegistry::new().with_decoder_options(DecoderOptions::untrusted());
+        let schema = SchemaType::object([("a", Property::optional(SchemaType::int8()))]);
+        let decode = |bytes: &[u8], registry: &SchemaRegistry| {
+            Decoder::decode_with_registry(&mut &*bytes, &schema, registry)
+        };
+
+        // A byte left over after the property value
+        let padded = [1, 0, 2, 5, 0];
+        assert!(decode(&padded, &SchemaRegistry::new()).is_ok());
+        assert!(decode(&padded, &strict).is_err());
+
+        // The same property twice
+        let duplicate = [2, 0, 1, 5, 0, 1, 6];
+        assert!(decode(&duplicate, &SchemaRegistry::new()).is_ok());
+        assert!(decode(&duplicate, &strict).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Objects encoded as a separate header and content.
+
+use crate::codec::wire::read_property_size;
+use crate::codec::LengthMode;
+use crate::error::{DecodeError, Result};
+use crate::schema::{index_order, Property, SchemaRegistry, SchemaType};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use indexmap::IndexMap;
+
+/// An object encoded with its header and content split apart.
+///
+/// The header is the property count followed by the index and size of each
+/// property; the content is the concatenation of the property values in
+/// the same order. Joined back together, entry by entry, they form the
+/// regular object wire format. Produced by [`Encoder::encode_parts`] and
+/// read by [`Decoder::decode_parts`].
+///
+/// [`Encoder::encode_parts`]: crate::Encoder::encode_parts
+/// [`Decoder::decode_parts`]: crate::Decoder::decode_parts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectParts {
+    header: Bytes,
+    content: Bytes,
+}
+
+impl ObjectParts {
+    /// Creates parts from a header and content, such as a header received
+    /// once and the content of a later message.
+    #[must_use]
+    pub fn new(header: Bytes, content: Bytes) -> Self {
+        Self { header, content }
+    }
+
+    /// Returns the header: the property count, indices and sizes.
+    #[must_use]
+    pub fn header(&self) -> &Bytes {
+        &self.header
+    }
+
+    /// Returns the content: the property values.
+    #[must_use]
+    pub fn content(&self) -> &Bytes {
+        &self.content
+    }
+
+    /// Consumes the parts and returns the header and content.
+    #[must_use]
+    pub fn into_inner(self) -> (Bytes, Bytes) {
+        (self.header, self.content)
+    }
+
+    /// Interleaves the header entries with the content, producing the
+    /// regular object wire format.
+    ///
+    /// The schema gives the [size width](crate::SizeWidth) of every header
+    /// entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not an object, the header is
+    /// malformed or the content size doesn't match the sizes recorded in the
+    /// header.
+    pub fn join(&self, schema: &SchemaType, registry: &SchemaRegistry) -> Result<Bytes> {
+        match schema {
+            SchemaType::Object(properties) => {
+                self.join_properties(properties, registry.length_mode())
+            }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                self.join(&resolved, registry)
+            }
+            _ => Err(DecodeError::SchemaMismatch(format!(
+                "expected object schema, got {schema:?}"
+            ))
+            .into()),
+        }
+    }
+
+    fn join_properties(
+        &self,
+        properties: &IndexMap<String, Property>,
+        mode: LengthMode,
+    ) -> Result<Bytes> {
+        // Properties are indexed alphabetically by name
+        let sorted = index_order(properties);
+
+        let mut out = BytesMut::with_capacity(self.header.len() + self.content.len());
+        let mut header = &self.header[..];
+        let mut content = &self.content[..];
+
+        if !header.has_remaining() {
+            return Err(DecodeError::UnexpectedEof.into());
+        }
+        let count = header.get_u8();
+        out.put_u8(count);
+        for _ in 0..count {
+            // Index byte and size header are copied as written
+            let entry = header;
+            if !header.has_remaining() {
+                return Err(DecodeError::UnexpectedEof.into());
+            }
+            let idx = usize::from(header.get_u8());
+            let (_, property) = sorted.get(idx).ok_or_else(|| {
+                DecodeError::InvalidData(format!("Property index {idx} out of range"))
+            })?;
+            let size = read_property_size(&mut header, property.size_width, mode)?;
+            out.put_slice(&entry[..entry.len() - header.len()]);
+
+            if content.len() < size {
+                return Err(DecodeError::UnexpectedEof.into());
+            }
+            out.put_slice(&content[..size]);
+            content.advance(size);
+        }
+
+        if header.has_remaining() || content.has_remaining() {
+            return Err(DecodeError::InvalidData(format!(
+                "{} header and {} content bytes left after {count} properties",
+                header.len(),
+                content.len()
+            ))
+            .into());
+        }
+        Ok(out.freeze())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{Property, SchemaRegistry, SchemaType};
+    use crate::value::Value;
+    use crate::{Decoder, Encoder};
+    use indexmap::IndexMap;
+
+    fn point(x: i64, label: &str) -> (Value, SchemaType) {
+        let mut properties = IndexMap::new();
+        properties.insert("x".to_owned(), Property::required(SchemaType::int32()));
+        properties.insert("label".to_owned(), Property::optional(SchemaType::string()));
+        let mut obj = IndexMap::new();
+        obj.insert("x".into(), Value::Integer(x));
+        obj.insert("label".into(), Value::String(label.to_owned()));
+        (Value::Object(obj), SchemaType::object(properties))
+    }
+
+    #[test]
+    fn test_parts_join_to_regular_encoding() {
+        let registry = SchemaRegistry::new();
+        let (value, schema) = point(7, "ab");
+        let parts = Encoder::encode_parts(&value, &schema, &registry).unwrap();
+        // Properties follow in index order: `label`, then `x`
+        assert_eq!(&parts.header()[..], [2, 0, 2, 1, 4]);
+        assert_eq!(&parts.content()[..], [b'a', b'b', 0, 0, 0, 7]);
+
+        let mut encoder = Encoder::new();
+        encoder.encode(&value, &schema).unwrap();
+        assert_eq!(parts.join(&schema, &registry).unwrap(), encoder.finish());
+        assert_eq!(
+            Decoder::decode_parts(&parts, &schema, &registry).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn test_reuse_header() {
+        let registry = SchemaRegistry::new();
+        let (first, schema) = point(1, "ab");
+        let header = Encoder::encode_parts(&first, &schema, &registry)
+            .unwrap()
+            .into_inner()
+            .0;
+
+        let (second, _) = point(2, "cd");
+        let content = Encoder::encode_parts(&second, &schema, &registry)
+            .unwrap()
+            .into_inner()
+            .1;
+        let parts = super::ObjectParts::new(header.clone(), content);
+        assert_eq!(
+            Decoder::decode_parts(&parts, &schema, &registry).unwrap(),
+            second
+        );
+
+        let short = super::ObjectParts::new(header.clone(), vec![0, 0, 0, 3].into());
+        assert!(short.join(&schema, &registry).is_err());
+        let long = super::ObjectParts::new(header, vec![0; 7].into());
+        assert!(long.join(&schema, &registry).is_err());
+        assert!(
+            Encoder::encode_parts(&Value::Integer(1), &SchemaType::int32(), &registry).is_err()
+        );
+    }
+}
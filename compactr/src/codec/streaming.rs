@@ -0,0 +1,589 @@
+//! Push-based and sans-IO decoding of values arriving in arbitrary chunks.
+
+use crate::codec::length::{read_length, LengthMode};
+use crate::codec::object::{read_bitmap, Presence};
+use crate::codec::wire::{read_property_size, BINARY_LEN_WIDTH, STRING_LEN_WIDTH};
+use crate::codec::{ArrayFormat, Decoder, ObjectFormat};
+use crate::error::{DecodeError, Error, Result, SchemaError};
+use crate::schema::{
+    index_order, IntegerFormat, NumberFormat, SchemaRegistry, SchemaType, StringFormat,
+    INLINE_PROPERTIES,
+};
+use crate::value::Value;
+use bytes::{Buf, BytesMut};
+use smallvec::SmallVec;
+
+/// Decodes a stream of consecutive values from byte chunks as they arrive.
+///
+/// Values are read back to back without any framing: the schema alone tells
+/// where each one ends. Feed whatever a non-blocking read returned with
+/// [`StreamingDecoder::feed`] and call [`StreamingDecoder::next_value`]
+/// until it returns `None`; [`StreamingDecoder::needed`] then tells how
+/// many more bytes the next value takes at least.
+///
+/// ```rust
+/// use compactr::{Encoder, SchemaType, StreamingDecoder, Value};
+///
+/// let schema = SchemaType::object([("name", SchemaType::string())]);
+/// let value = Value::object([("name", "Alice")]);
+/// let mut encoder = Encoder::new();
+/// encoder.encode(&value, &schema)?;
+/// let bytes = encoder.finish();
+///
+/// let mut decoder = StreamingDecoder::new(schema)?;
+/// decoder.feed(&bytes[..3]);
+/// assert_eq!(decoder.next_value()?, None);
+/// assert_eq!(decoder.needed(), bytes.len() - 3);
+/// decoder.feed(&bytes[3..]);
+/// assert_eq!(decoder.next_value()?, Some(value));
+/// # Ok::<(), compactr::error::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct StreamingDecoder {
+    machine: DecodeMachine,
+    buf: BytesMut,
+}
+
+impl StreamingDecoder {
+    /// Creates a decoder for values of `schema`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if values of the schema don't mark their own end,
+    /// see [`DecodeMachine::with_registry`].
+    pub fn new(schema: SchemaType) -> Result<Self> {
+        Self::with_registry(schema, SchemaRegistry::new())
+    }
+
+    /// Creates a decoder for values of `schema`, resolving references and
+    /// layout settings through `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if values of the schema don't mark their own end,
+    /// see [`DecodeMachine::with_registry`].
+    pub fn with_registry(schema: SchemaType, registry: SchemaRegistry) -> Result<Self> {
+        Ok(Self {
+            machine: DecodeMachine::with_registry(schema, registry)?,
+            buf: BytesMut::new(),
+        })
+    }
+
+    /// Appends a chunk of input.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Decodes the next value if all of its bytes have arrived.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffered bytes are not valid for the schema.
+    /// The stream can't be resynchronized after that.
+    pub fn next_value(&mut self) -> Result<Option<Value>> {
+        match self.machine.step(&self.buf)? {
+            Decoded::Complete { value, consumed } => {
+                self.buf.advance(consumed);
+                Ok(Some(value))
+            }
+            Decoded::NeedMoreData(_) => Ok(None),
+        }
+    }
+
+    /// Returns how many more bytes the next value takes at least; zero when
+    /// [`StreamingDecoder::next_value`] may have one ready.
+    ///
+    /// The count is exact for values whose length follows from their
+    /// headers, and a lower bound for the rest.
+    #[must_use]
+    pub fn needed(&self) -> usize {
+        self.machine.needed.saturating_sub(self.buf.len())
+    }
+
+    /// Returns the number of bytes buffered but not yet decoded.
+    #[must_use]
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+}
+
+/// Outcome of a [`DecodeMachine::step`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded {
+    /// The input ends before the value does; at least this many more bytes
+    /// are needed
+    NeedMoreData(usize),
+    /// A value was decoded from the first `consumed` bytes of the input
+    Complete {
+        /// The decoded value
+        value: Value,
+        /// Number of input bytes the value took
+        consumed: usize,
+    },
+}
+
+/// Resumable decoding of values of one schema, independent of any I/O.
+///
+/// The caller owns the bytes: pass everything received for the current
+/// value to [`DecodeMachine::step`] and get back either the value and the
+/// number of bytes it took, or how many more bytes to wait for. Nothing is
+/// buffered or read, so the machine fits into `io_uring` completions, custom
+/// event loops or embedded receive buffers alike.
+///
+/// ```rust
+/// use compactr::{DecodeMachine, Decoded, SchemaType, Value};
+///
+/// let mut machine = DecodeMachine::new(SchemaType::string())?;
+/// assert_eq!(machine.step(&[0, 2, b'h'])?, Decoded::NeedMoreData(1));
+/// assert_eq!(
+///     machine.step(&[0, 2, b'h', b'i'])?,
+///     Decoded::Complete { value: Value::from("hi"), consumed: 4 }
+/// );
+/// # Ok::<(), compactr::error::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DecodeMachine {
+    schema: SchemaType,
+    registry: SchemaRegistry,
+    /// Input length below which the current value can't be complete
+    needed: usize,
+}
+
+impl DecodeMachine {
+    /// Creates a machine for values of `schema`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if values of the schema don't mark their own end,
+    /// see [`DecodeMachine::with_registry`].
+    pub fn new(schema: SchemaType) -> Result<Self> {
+        Self::with_registry(schema, SchemaRegistry::new())
+    }
+
+    /// Creates a machine for values of `schema`, resolving references and
+    /// layout settings through `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a reference can't be resolved or the schema is a
+    /// size-prefixed array, whose elements run to the end of the input.
+    /// Stream arrays with [`ArrayFormat::CountPrefixed`] instead.
+    pub fn with_registry(schema: SchemaType, registry: SchemaRegistry) -> Result<Self> {
+        let mut resolved = &schema;
+        let owned;
+        if let SchemaType::Reference(name) = resolved {
+            owned = registry.resolve_ref(name)?;
+            resolved = &owned;
+        }
+        if matches!(resolved, SchemaType::Array(_))
+            && registry.array_format() != ArrayFormat::CountPrefixed
+        {
+            return Err(SchemaError::InvalidSchema(
+                "size-prefixed arrays can't be streamed without framing".to_owned(),
+            )
+            .into());
+        }
+        Ok(Self {
+            schema,
+            registry,
+            needed: 1,
+        })
+    }
+
+    /// Advances decoding of the current value, given all of its bytes
+    /// received so far.
+    ///
+    /// After a [`Decoded::Complete`] the machine starts over with the next
+    /// value, whose input begins after the consumed bytes. Inputs shorter
+    /// than the last [`Decoded::NeedMoreData`] asked for are answered
+    /// without looking at them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is not valid for the schema, or if the
+    /// value took no bytes, as an empty object in the bitmap layout does:
+    /// such values can't be told apart in a stream.
+    pub fn step(&mut self, input: &[u8]) -> Result<Decoded> {
+        if input.len() < self.needed {
+            return Ok(Decoded::NeedMoreData(self.needed - input.len()));
+        }
+        let (schema, registry) = (&self.schema, &self.registry);
+        let complete = match value_end(input, 0, schema, registry) {
+            Ok(end) => {
+                let value = Decoder::decode_with_registry(&mut &input[..end], schema, registry)?;
+                (value, end)
+            }
+            Err(Stop::Short(needed)) => {
+                self.needed = needed;
+                return Ok(Decoded::NeedMoreData(needed - input.len()));
+            }
+            Err(Stop::Invalid(e)) => return Err(e),
+            // Some formats only reveal their length by decoding them
+            Err(Stop::Unknown) => {
+                let mut rest = input;
+                match Decoder::decode_with_registry(&mut rest, schema, registry) {
+                    Ok(value) => (value, input.len() - rest.len()),
+                    Err(Error::Decode(DecodeError::UnexpectedEof)) => {
+                        self.needed = input.len() + 1;
+                        return Ok(Decoded::NeedMoreData(1));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        };
+        let (value, consumed) = complete;
+        if consumed == 0 {
+            return Err(DecodeError::InvalidData(
+                "values of the schema take no bytes and can't be streamed".to_owned(),
+            )
+            .into());
+        }
+        self.needed = 1;
+        Ok(Decoded::Complete { value, consumed })
+    }
+}
+
+/// Returns how many bytes the value of `schema` at the start of `bytes`
+/// needs at least beyond them, if its headers tell.
+pub(crate) fn missing_bytes(
+    bytes: &[u8],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Option<usize> {
+    match value_end(bytes, 0, schema, registry) {
+        Err(Stop::Short(needed)) => needed.checked_sub(bytes.len()).filter(|&n| n > 0),
+        _ => None,
+    }
+}
+
+/// Why the end of a value couldn't be found.
+enum Stop {
+    /// At least this many bytes must be buffered in total
+    Short(usize),
+    /// The layout can't be followed without decoding the value
+    Unknown,
+    /// The bytes are not valid for the schema
+    Invalid(Error),
+}
+
+impl<E: Into<Error>> From<E> for Stop {
+    fn from(e: E) -> Self {
+        match e.into() {
+            // Prefixes cut short by the end of the buffer are reported
+            // through `Stop::Short` by their callers
+            Error::Decode(DecodeError::UnexpectedEof) => Self::Unknown,
+            e => Self::Invalid(e),
+        }
+    }
+}
+
+/// Finds where the value of `schema` starting at `pos` ends, reading only
+/// headers and length prefixes.
+fn value_end(
+    bytes: &[u8],
+    pos: usize,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> std::result::Result<usize, Stop> {
+    let mode = registry.length_mode();
+    match schema {
+        SchemaType::Boolean | SchemaType::Null | SchemaType::Enum(_) => skip(bytes, pos, 1),
+        SchemaType::Integer(format) => {
+            let width = match format {
+                IntegerFormat::Int8 | IntegerFormat::Uint8 => 1,
+                IntegerFormat::Int16 | IntegerFormat::Uint16 => 2,
+                IntegerFormat::Int32 => 4,
+                IntegerFormat::Int64 => 8,
+                IntegerFormat::Int128 | IntegerFormat::Uint128 => 16,
+            };
+            skip(bytes, pos, width)
+        }
+        SchemaType::Number(NumberFormat::Float) => skip(bytes, pos, 4),
+        SchemaType::Number(NumberFormat::Double) => skip(bytes, pos, 8),
+        SchemaType::String(format) => {
+            let width = match format {
+                StringFormat::Plain => return skip_prefixed(bytes, pos, mode, STRING_LEN_WIDTH),
+                StringFormat::Long | StringFormat::Binary => {
+                    return skip_prefixed(bytes, pos, mode, BINARY_LEN_WIDTH)
+                }
+                StringFormat::Uuid | StringFormat::Ipv6 => 16,
+                StringFormat::DateTime => 9,
+                StringFormat::DateTimeTz => 11,
+                StringFormat::Timestamp(_) | StringFormat::GeoPoint => 8,
+                StringFormat::Date | StringFormat::Time | StringFormat::Ipv4 => 4,
+                _ => return Err(Stop::Unknown),
+            };
+            skip(bytes, pos, width)
+        }
+        SchemaType::Array(items) if registry.array_format() == ArrayFormat::CountPrefixed => {
+            let (count, mut pos) = prefix(bytes, pos, mode, 4)?;
+            for _ in 0..count {
+                let end = value_end(bytes, pos, items, registry)?;
+                // Elements that take no bytes, such as empty objects, all end here
+                if end == pos {
+                    break;
+                }
+                pos = end;
+            }
+            Ok(pos)
+        }
+        SchemaType::RunLengthArray(_) => {
+            let (runs, mut pos) = prefix(bytes, pos, mode, 4)?;
+            for _ in 0..runs {
+                pos = prefix(bytes, pos, mode, 2)?.1;
+                pos = skip_prefixed(bytes, pos, mode, 1)?;
+            }
+            Ok(pos)
+        }
+        SchemaType::Object(properties) => {
+            // Properties are indexed alphabetically by name
+            let sorted: SmallVec<[_; INLINE_PROPERTIES]> = index_order(properties)
+                .into_iter()
+                .map(|(_, property)| property)
+                .collect();
+
+            let mut pos = pos;
+            let mut present = Presence::new();
+            if registry.object_format() == ObjectFormat::Bitmap {
+                let mut rest = &bytes[pos..];
+                present = match read_bitmap(&mut rest, sorted.iter().copied()) {
+                    Err(DecodeError::UnexpectedEof) => return Err(Stop::Short(bytes.len() + 1)),
+                    result => result?,
+                };
+                pos = bytes.len() - rest.len();
+            } else {
+                let count = *bytes.get(pos).ok_or(Stop::Short(pos + 1))?;
+                pos += 1;
+                for _ in 0..count {
+                    let idx = usize::from(*bytes.get(pos).ok_or(Stop::Short(pos + 1))?);
+                    if idx >= sorted.len() {
+                        return Err(DecodeError::InvalidData(format!(
+                            "Property index {idx} out of range"
+                        ))
+                        .into());
+                    }
+                    pos = skip_property(bytes, pos + 1, sorted[idx], mode)?;
+                }
+            }
+            for (property, _) in sorted.iter().zip(present).filter(|(_, p)| *p) {
+                pos = skip_property(bytes, pos, property, mode)?;
+            }
+            Ok(pos)
+        }
+        SchemaType::OneOf { variants, .. } => {
+            let idx = usize::from(*bytes.get(pos).ok_or(Stop::Short(pos + 1))?);
+            match variants.get_index(idx) {
+                Some((_, SchemaType::Null)) => Ok(pos + 1),
+                Some((_, payload)) => value_end(bytes, pos + 1, payload, registry),
+                None => Err(
+                    DecodeError::InvalidData(format!("Variant index {idx} out of range")).into(),
+                ),
+            }
+        }
+        SchemaType::Reference(name) => {
+            let resolved = registry.resolve_shared(name)?;
+            value_end(bytes, pos, &resolved, registry)
+        }
+        _ => Err(Stop::Unknown),
+    }
+}
+
+/// Skips `len` bytes starting at `pos`.
+fn skip(bytes: &[u8], pos: usize, len: usize) -> std::result::Result<usize, Stop> {
+    let end = pos.saturating_add(len);
+    if end > bytes.len() {
+        return Err(Stop::Short(end));
+    }
+    Ok(end)
+}
+
+/// Reads a length prefix at `pos`, returning the length and the position
+/// after the prefix.
+fn prefix(
+    bytes: &[u8],
+    pos: usize,
+    mode: LengthMode,
+    width: usize,
+) -> std::result::Result<(usize, usize), Stop> {
+    let mut rest = bytes.get(pos..).unwrap_or_default();
+    match read_length(&mut rest, mode, width) {
+        Ok(len) => Ok((len, bytes.len() - rest.len())),
+        Err(DecodeError::UnexpectedEof) if mode == LengthMode::Fixed => {
+            Err(Stop::Short(pos + width))
+        }
+        Err(DecodeError::UnexpectedEof) => Err(Stop::Short(bytes.len() + 1)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Skips a length prefix at `pos` and the bytes it counts.
+fn skip_prefixed(
+    bytes: &[u8],
+    pos: usize,
+    mode: LengthMode,
+    width: usize,
+) -> std::result::Result<usize, Stop> {
+    let (len, pos) = prefix(bytes, pos, mode, width)?;
+    skip(bytes, pos, len)
+}
+
+/// Skips an object property's size header and value.
+fn skip_property(
+    bytes: &[u8],
+    pos: usize,
+    property: &crate::schema::Property,
+    mode: LengthMode,
+) -> std::result::Result<usize, Stop> {
+    let mut rest = bytes.get(pos..).unwrap_or_default();
+    let size = match read_property_size(&mut rest, property.size_width, mode) {
+        Err(DecodeError::UnexpectedEof) => return Err(Stop::Short(bytes.len() + 1)),
+        result => result.map_err(Error::from)?,
+    };
+    skip(bytes, bytes.len() - rest.len(), size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Encoder;
+
+    fn stream(values: &[Value], schema: &SchemaType, registry: &SchemaRegistry) -> Vec<u8> {
+        let mut enc = Encoder::new();
+        for value in values {
+            enc.encode_with_registry(value, schema, registry).unwrap();
+        }
+        enc.finish().to_vec()
+    }
+
+    fn decode_chunked(
+        bytes: &[u8],
+        chunk_len: usize,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Vec<Value> {
+        let mut decoder =
+            StreamingDecoder::with_registry(schema.clone(), registry.clone()).unwrap();
+        let mut values = Vec::new();
+        for chunk in bytes.chunks(chunk_len) {
+            decoder.feed(chunk);
+            while let Some(value) = decoder.next_value().unwrap() {
+                values.push(value);
+            }
+            assert!(decoder.needed() > 0);
+        }
+        assert_eq!(decoder.buffered(), 0);
+        values
+    }
+
+    #[test]
+    fn test_streaming_decoder() {
+        let registry = SchemaRegistry::new();
+        let schema = SchemaType::object([
+            ("id", SchemaType::int32()),
+            ("name", SchemaType::string()),
+            ("tags", SchemaType::run_length_array(SchemaType::string())),
+        ]);
+        let values: Vec<Value> = (0..20)
+            .map(|id| {
+                Value::object([
+                    ("id", Value::from(id)),
+                    (
+                        "name",
+                        Value::from("x".repeat(usize::try_from(id).unwrap())),
+                    ),
+                    ("tags", Value::from(vec!["a", "a", "b"])),
+                ])
+            })
+            .collect();
+        let bytes = stream(&values, &schema, &registry);
+        for chunk_len in [1, 2, 7, 64, bytes.len()] {
+            assert_eq!(
+                decode_chunked(&bytes, chunk_len, &schema, &registry),
+                values
+            );
+        }
+
+        // Bitmap objects, count-prefixed arrays and varint lengths
+        let registry = SchemaRegistry::new()
+            .with_object_format(ObjectFormat::Bitmap)
+            .with_array_format(ArrayFormat::CountPrefixed)
+            .with_length_mode(LengthMode::Varint);
+        let schema = SchemaType::array(schema);
+        let values = vec![
+            Value::from(values.clone()),
+            Value::from(values[..3].to_vec()),
+        ];
+        let bytes = stream(&values, &schema, &registry);
+        assert_eq!(decode_chunked(&bytes, 5, &schema, &registry), values);
+    }
+
+    #[test]
+    fn test_streaming_needed() {
+        let schema = SchemaType::string();
+        let mut decoder = StreamingDecoder::new(schema).unwrap();
+        assert_eq!(decoder.needed(), 1);
+        decoder.feed(&[0]);
+        assert_eq!(decoder.next_value().unwrap(), None);
+        assert_eq!(decoder.needed(), 1);
+        decoder.feed(&[5, b'h']);
+        assert_eq!(decoder.next_value().unwrap(), None);
+        assert_eq!(decoder.needed(), 4);
+        decoder.feed(b"ello");
+        assert_eq!(decoder.next_value().unwrap(), Some(Value::from("hello")));
+
+        // Formats without a known length are decoded on every attempt
+        let uri = SchemaType::string_uri();
+        let mut enc = Encoder::new();
+        enc.encode(&Value::from("https://example.com/a"), &uri)
+            .unwrap();
+        let bytes = enc.finish();
+        let registry = SchemaRegistry::new();
+        assert_eq!(decode_chunked(&bytes, 3, &uri, &registry).len(), 1);
+
+        assert!(StreamingDecoder::new(SchemaType::array(SchemaType::int8())).is_err());
+    }
+
+    #[test]
+    fn test_decode_machine() {
+        let schema = SchemaType::object([("id", SchemaType::int32())]);
+        let mut enc = Encoder::new();
+        enc.encode(&Value::object([("id", 7)]), &schema).unwrap();
+        enc.encode(&Value::object([("id", 8)]), &schema).unwrap();
+        let bytes = enc.finish();
+
+        let mut machine = DecodeMachine::new(schema).unwrap();
+        assert_eq!(machine.step(&[]).unwrap(), Decoded::NeedMoreData(1));
+        assert_eq!(machine.step(&bytes[..3]).unwrap(), Decoded::NeedMoreData(4));
+        // Shorter inputs are answered from the last result
+        assert_eq!(machine.step(&bytes[..4]).unwrap(), Decoded::NeedMoreData(3));
+        let Decoded::Complete { value, consumed } = machine.step(&bytes).unwrap() else {
+            panic!("expected a value");
+        };
+        assert_eq!((value, consumed), (Value::object([("id", 7)]), 7));
+        let Decoded::Complete { value, .. } = machine.step(&bytes[consumed..]).unwrap() else {
+            panic!("expected a value");
+        };
+        assert_eq!(value, Value::object([("id", 8)]));
+
+        assert!(machine.step(&[1, 5, 4, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_zero_width_values() {
+        // Empty bitmap objects take no bytes, so every step would complete
+        // without consuming anything
+        let registry = SchemaRegistry::new().with_object_format(ObjectFormat::Bitmap);
+        let schema = SchemaType::Object(indexmap::IndexMap::new());
+        let mut decoder =
+            StreamingDecoder::with_registry(schema.clone(), registry.clone()).unwrap();
+        decoder.feed(&[72]);
+        assert!(matches!(
+            decoder.next_value(),
+            Err(Error::Decode(DecodeError::InvalidData(_)))
+        ));
+
+        let mut machine = DecodeMachine::with_registry(schema, registry).unwrap();
+        assert_eq!(machine.step(&[]).unwrap(), Decoded::NeedMoreData(1));
+        assert!(machine.step(&[72]).is_err());
+    }
+}
@@ -1,29 +1,213 @@
 //! Traits for encoding and decoding values.
 
-use crate::error::{DecodeError, EncodeError};
-use bytes::{Buf, BufMut, BytesMut};
+use crate::codec::array::{decode_elements, decode_runs, encode_elements, encode_runs};
+use crate::codec::buffer::{
+    decode_string, encode_string, put_prefixed, read_prefixed_string, string_size,
+};
+use crate::codec::wire::{BINARY_LEN_WIDTH, STRING_LEN_WIDTH};
+use crate::codec::{ArrayFormat, Decoder, Encoder, LengthMode};
+use crate::error::{DecodeError, EncodeError, Result, SchemaError};
+#[cfg(feature = "chrono")]
+use crate::formats::datetime::{decode_datetime, encode_datetime};
+use crate::formats::ipaddr::{decode_ipv4, decode_ipv6, encode_ipv4, encode_ipv6};
+#[cfg(feature = "uuid")]
+use crate::formats::uuid::{decode_uuid, encode_uuid};
+use crate::schema::{Resolved, SchemaRegistry, SchemaType, StringFormat};
+use crate::value::Value;
+#[cfg(feature = "uuid")]
+use ::uuid::Uuid;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
 /// Trait for types that can be encoded to binary format.
+///
+/// Implementations that override [`Encode::encode_with_schema`] can be
+/// encoded straight to schema-encoded data with [`Encoder::encode_as`],
+/// skipping the intermediate [`Value`] tree.
 pub trait Encode {
-    /// Encodes this value into the provided buffer.
+    /// Encodes this value into the provided buffer without a schema.
+    ///
+    /// The default implementation fails; types whose layout depends on the
+    /// schema (such as structs) only need [`Encode::encode_with_schema`].
     ///
     /// # Errors
     ///
     /// Returns an error if encoding fails.
-    fn encode(&self, buf: &mut BytesMut) -> Result<(), EncodeError>;
+    fn encode(&self, buf: &mut BytesMut) -> Result<(), EncodeError> {
+        let _ = buf;
+        Err(SchemaError::InvalidSchema(format!(
+            "{} can only be encoded with a schema",
+            std::any::type_name::<Self>()
+        ))
+        .into())
+    }
+
+    /// Returns the size in bytes that this value will occupy when encoded
+    /// with [`Encode::encode`].
+    ///
+    /// The default implementation encodes into a scratch buffer and measures
+    /// it, returning 0 if encoding fails.
+    fn encoded_size(&self) -> usize {
+        let mut buf = BytesMut::new();
+        self.encode(&mut buf).map_or(0, |()| buf.len())
+    }
 
-    /// Returns the size in bytes that this value will occupy when encoded.
-    fn encoded_size(&self) -> usize;
+    /// Encodes this value laid out according to `schema`.
+    ///
+    /// The schema is never a reference; [`Encoder::encode_as`] resolves those
+    /// first. The default implementation ignores the schema and calls
+    /// [`Encode::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value doesn't match the schema or encoding fails.
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let _ = (schema, registry);
+        self.encode(buf).map_err(Into::into)
+    }
+
+    /// Returns `true` if this value should be left out of an enclosing object.
+    ///
+    /// [`Encoder::encode_fields`] skips absent fields, which is how `None`
+    /// maps to a missing property.
+    fn is_absent(&self) -> bool {
+        false
+    }
+
+    /// Encodes this value as an object property.
+    ///
+    /// Property values are framed by their size, which changes the layout of
+    /// some types (plain strings carry no length prefix). The default
+    /// implementation calls [`Encode::encode_with_schema`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value doesn't match the schema or encoding fails.
+    fn encode_property(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        self.encode_with_schema(buf, schema, registry)
+    }
 }
 
 /// Trait for types that can be decoded from binary format.
+///
+/// Implementations that override [`Decode::decode_with_schema`] can be
+/// decoded straight from schema-encoded data with [`Decoder::decode_as`],
+/// skipping the intermediate [`Value`] tree.
 pub trait Decode: Sized {
-    /// Decodes a value from the provided buffer.
+    /// Decodes a value from the provided buffer without a schema.
+    ///
+    /// The default implementation fails; types whose layout depends on the
+    /// schema (such as structs) only need [`Decode::decode_with_schema`].
     ///
     /// # Errors
     ///
     /// Returns an error if decoding fails or the buffer doesn't contain valid data.
-    fn decode(buf: &mut impl Buf) -> Result<Self, DecodeError>;
+    fn decode(buf: &mut impl Buf) -> Result<Self, DecodeError> {
+        let _ = buf;
+        Err(DecodeError::SchemaMismatch(format!(
+            "{} can only be decoded with a schema",
+            std::any::type_name::<Self>()
+        )))
+    }
+
+    /// Decodes a value laid out according to `schema`.
+    ///
+    /// The schema is never a reference; [`Decoder::decode_as`] resolves those
+    /// first. The default implementation ignores the schema and calls
+    /// [`Decode::decode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer doesn't contain valid data for the
+    /// schema or the data cannot be represented as `Self`.
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        let _ = (schema, registry);
+        Self::decode(buf).map_err(Into::into)
+    }
+
+    /// Returns the value to use when an object property is missing, or
+    /// `None` if the property is mandatory.
+    ///
+    /// `Option<T>` returns `Some(None)`, so optional fields decode to `None`.
+    #[must_use]
+    fn absent() -> Option<Self> {
+        None
+    }
+
+    /// Decodes a value stored as an object property.
+    ///
+    /// Property values are framed by their size, which changes the layout of
+    /// some types (plain strings carry no length prefix). The default
+    /// implementation calls [`Decode::decode_with_schema`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer doesn't contain valid data for the
+    /// schema or the data cannot be represented as `Self`.
+    fn decode_property(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        Self::decode_with_schema(buf, schema, registry)
+    }
+}
+
+impl Encode for Value {
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        Encoder::encode_value_into(buf, self, schema, registry)
+    }
+
+    fn encode_property(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        Encoder::encode_property_into(buf, self, schema, registry)
+    }
+}
+
+impl Decode for Value {
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        Decoder::decode_nested(buf, schema, registry)
+    }
+
+    fn decode_property(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        Decoder::decode_property_value(buf, schema, registry)
+    }
 }
 
 // Implement for primitive types
@@ -36,6 +220,15 @@ impl Encode for bool {
     fn encoded_size(&self) -> usize {
         1
     }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        Encoder::encode_value_into(buf, &Value::Boolean(*self), schema, registry)
+    }
 }
 
 impl Decode for bool {
@@ -52,6 +245,17 @@ impl Decode for bool {
             ))),
         }
     }
+
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        match Decoder::decode_nested(buf, schema, registry)? {
+            Value::Boolean(b) => Ok(b),
+            other => Err(unexpected("boolean", &other)),
+        }
+    }
 }
 
 impl Encode for i32 {
@@ -63,6 +267,15 @@ impl Encode for i32 {
     fn encoded_size(&self) -> usize {
         4
     }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        Encoder::encode_value_into(buf, &Value::Integer(i64::from(*self)), schema, registry)
+    }
 }
 
 impl Decode for i32 {
@@ -72,6 +285,18 @@ impl Decode for i32 {
         }
         Ok(buf.get_i32()) // Big-endian
     }
+
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        let value = Decoder::decode_nested(buf, schema, registry)?;
+        value
+            .as_i64()
+            .and_then(|i| i32::try_from(i).ok())
+            .ok_or_else(|| unexpected("i32", &value))
+    }
 }
 
 impl Encode for i64 {
@@ -83,6 +308,15 @@ impl Encode for i64 {
     fn encoded_size(&self) -> usize {
         8
     }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        Encoder::encode_value_into(buf, &Value::Integer(*self), schema, registry)
+    }
 }
 
 impl Decode for i64 {
@@ -92,6 +326,15 @@ impl Decode for i64 {
         }
         Ok(buf.get_i64()) // Big-endian
     }
+
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        let value = Decoder::decode_nested(buf, schema, registry)?;
+        value.as_i64().ok_or_else(|| unexpected("i64", &value))
+    }
 }
 
 impl Encode for f32 {
@@ -103,6 +346,15 @@ impl Encode for f32 {
     fn encoded_size(&self) -> usize {
         4
     }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        Encoder::encode_value_into(buf, &Value::Float(*self), schema, registry)
+    }
 }
 
 impl Decode for f32 {
@@ -112,6 +364,17 @@ impl Decode for f32 {
         }
         Ok(buf.get_f32()) // Big-endian
     }
+
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        match Decoder::decode_nested(buf, schema, registry)? {
+            Value::Float(f) => Ok(f),
+            other => Err(unexpected("float", &other)),
+        }
+    }
 }
 
 impl Encode for f64 {
@@ -123,6 +386,15 @@ impl Encode for f64 {
     fn encoded_size(&self) -> usize {
         8
     }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        Encoder::encode_value_into(buf, &Value::Double(*self), schema, registry)
+    }
 }
 
 impl Decode for f64 {
@@ -132,4 +404,635 @@ impl Decode for f64 {
         }
         Ok(buf.get_f64()) // Big-endian
     }
+
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        match Decoder::decode_nested(buf, schema, registry)? {
+            Value::Double(d) => Ok(d),
+            Value::Float(f) => Ok(f64::from(f)),
+            other => Err(unexpected("double", &other)),
+        }
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, buf: &mut BytesMut) -> Result<(), EncodeError> {
+        encode_string(buf, self)
+    }
+
+    fn encoded_size(&self) -> usize {
+        string_size(self)
+    }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let mode = registry.length_mode();
+        match schema {
+            SchemaType::String(StringFormat::Plain) => {
+                put_prefixed(buf, mode, STRING_LEN_WIDTH, self.as_bytes()).map_err(Into::into)
+            }
+            SchemaType::String(StringFormat::Long) => {
+                put_prefixed(buf, mode, BINARY_LEN_WIDTH, self.as_bytes()).map_err(Into::into)
+            }
+            // Formatted strings (UUIDs, dates, ...) are parsed by the encoder
+            _ => Encoder::encode_value_into(buf, &Value::String(self.to_owned()), schema, registry),
+        }
+    }
+
+    fn encode_property(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        if matches!(
+            schema,
+            SchemaType::String(StringFormat::Plain | StringFormat::Long)
+        ) {
+            // For strings in objects: raw UTF-8 bytes (no length prefix)
+            buf.put_slice(self.as_bytes());
+            Ok(())
+        } else {
+            self.encode_with_schema(buf, schema, registry)
+        }
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, buf: &mut BytesMut) -> Result<(), EncodeError> {
+        self.as_str().encode(buf)
+    }
+
+    fn encoded_size(&self) -> usize {
+        self.as_str().encoded_size()
+    }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        self.as_str().encode_with_schema(buf, schema, registry)
+    }
+
+    fn encode_property(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        self.as_str().encode_property(buf, schema, registry)
+    }
+}
+
+impl Decode for String {
+    fn decode(buf: &mut impl Buf) -> Result<Self, DecodeError> {
+        decode_string(buf)
+    }
+
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        let mode = registry.length_mode();
+        match schema {
+            SchemaType::String(StringFormat::Plain) => {
+                return read_prefixed_string(buf, mode, STRING_LEN_WIDTH).map_err(Into::into)
+            }
+            SchemaType::String(StringFormat::Long) => {
+                return read_prefixed_string(buf, mode, BINARY_LEN_WIDTH).map_err(Into::into)
+            }
+            _ => {}
+        }
+        // Formatted values (UUIDs, dates, ...) are rendered in their string form
+        match Decoder::decode_nested(buf, schema, registry)? {
+            Value::String(s) => Ok(s),
+            #[cfg(feature = "uuid")]
+            Value::Uuid(u) => Ok(u.to_string()),
+            #[cfg(feature = "chrono")]
+            Value::DateTime(dt) => Ok(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            #[cfg(feature = "chrono")]
+            Value::DateTimeTz(dt) => Ok(dt.to_rfc3339_opts(SecondsFormat::AutoSi, false)),
+            #[cfg(feature = "chrono")]
+            Value::Date(d) => Ok(d.format("%Y-%m-%d").to_string()),
+            #[cfg(feature = "chrono")]
+            Value::Time(t) => Ok(t.to_string()),
+            Value::Ipv4(ip) => Ok(ip.to_string()),
+            Value::Ipv6(ip) => Ok(ip.to_string()),
+            Value::SocketAddr(addr) => Ok(addr.to_string()),
+            other => Err(unexpected("string", &other)),
+        }
+    }
+
+    fn decode_property(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        if !matches!(
+            schema,
+            SchemaType::String(StringFormat::Plain | StringFormat::Long)
+        ) {
+            return Self::decode_with_schema(buf, schema, registry);
+        }
+        // For strings in objects: the whole framed buffer is UTF-8
+        let mut bytes = vec![0u8; buf.remaining()];
+        buf.copy_to_slice(&mut bytes);
+        Self::from_utf8(bytes)
+            .map_err(|e| DecodeError::InvalidData(format!("Invalid UTF-8: {e}")).into())
+    }
+}
+
+/// `None` encodes to zero bytes, so an empty buffer decodes back to `None`.
+///
+/// This is only unambiguous where values are framed by their size (array
+/// elements and object properties); struct encoders usually omit `None`
+/// fields altogether.
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, buf: &mut BytesMut) -> Result<(), EncodeError> {
+        self.as_ref().map_or(Ok(()), |v| v.encode(buf))
+    }
+
+    fn encoded_size(&self) -> usize {
+        self.as_ref().map_or(0, Encode::encoded_size)
+    }
+
+    fn is_absent(&self) -> bool {
+        self.is_none()
+    }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        self.as_ref()
+            .map_or(Ok(()), |v| v.encode_with_schema(buf, schema, registry))
+    }
+
+    fn encode_property(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        self.as_ref()
+            .map_or(Ok(()), |v| v.encode_property(buf, schema, registry))
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(buf: &mut impl Buf) -> Result<Self, DecodeError> {
+        if buf.has_remaining() {
+            T::decode(buf).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn absent() -> Option<Self> {
+        Some(None)
+    }
+
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        if buf.has_remaining() {
+            T::decode_with_schema(buf, schema, registry).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn decode_property(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        if buf.has_remaining() {
+            T::decode_property(buf, schema, registry).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Forwards every method to the referenced value.
+macro_rules! impl_encode_deref {
+    ($(impl[$($generics:tt)*] $ty:ty),* $(,)?) => {
+        $(
+            impl<$($generics)*> Encode for $ty {
+                fn encode(&self, buf: &mut BytesMut) -> Result<(), EncodeError> {
+                    (**self).encode(buf)
+                }
+
+                fn encoded_size(&self) -> usize {
+                    (**self).encoded_size()
+                }
+
+                fn encode_with_schema(
+                    &self,
+                    buf: &mut BytesMut,
+                    schema: &SchemaType,
+                    registry: &SchemaRegistry,
+                ) -> Result<()> {
+                    (**self).encode_with_schema(buf, schema, registry)
+                }
+
+                fn is_absent(&self) -> bool {
+                    (**self).is_absent()
+                }
+
+                fn encode_property(
+                    &self,
+                    buf: &mut BytesMut,
+                    schema: &SchemaType,
+                    registry: &SchemaRegistry,
+                ) -> Result<()> {
+                    (**self).encode_property(buf, schema, registry)
+                }
+            }
+        )*
+    };
+}
+
+impl_encode_deref!(
+    impl[T: Encode + ?Sized] &T,
+    impl[T: Encode + ToOwned + ?Sized] Cow<'_, T>,
+);
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, buf: &mut BytesMut) -> Result<(), EncodeError> {
+        encode_elements(
+            buf,
+            ArrayFormat::SizePrefixed,
+            LengthMode::Fixed,
+            self,
+            Encode::encode,
+        )
+    }
+
+    fn encoded_size(&self) -> usize {
+        self.iter().map(|item| 1 + item.encoded_size()).sum()
+    }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        encode_array(buf, self, schema, registry)
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(buf: &mut impl Buf) -> Result<Self, DecodeError> {
+        decode_elements(
+            buf,
+            ArrayFormat::SizePrefixed,
+            LengthMode::Fixed,
+            usize::MAX,
+            T::decode,
+            T::decode,
+        )
+    }
+
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        let items = array_items(schema, registry)?;
+        let mode = registry.length_mode();
+        let max_len = registry.decoder_options().max_array_len();
+        let decode = |elem_buf: &mut Bytes| T::decode_with_schema(elem_buf, &items, registry);
+        if matches!(schema, SchemaType::RunLengthArray(_)) {
+            return decode_runs(buf, mode, max_len, Buf::copy_to_bytes, decode);
+        }
+        decode_elements(buf, registry.array_format(), mode, max_len, decode, |buf| {
+            T::decode_with_schema(buf, &items, registry)
+        })
+    }
+}
+
+impl<T: Encode, const N: usize> Encode for [T; N] {
+    fn encode(&self, buf: &mut BytesMut) -> Result<(), EncodeError> {
+        encode_elements(
+            buf,
+            ArrayFormat::SizePrefixed,
+            LengthMode::Fixed,
+            self,
+            Encode::encode,
+        )
+    }
+
+    fn encoded_size(&self) -> usize {
+        self.iter().map(|item| 1 + item.encoded_size()).sum()
+    }
+
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        encode_array(buf, self, schema, registry)
+    }
+}
+
+impl<T: Decode, const N: usize> Decode for [T; N] {
+    fn decode(buf: &mut impl Buf) -> Result<Self, DecodeError> {
+        fixed_length(Vec::decode(buf)?)
+    }
+
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        fixed_length(Vec::decode_with_schema(buf, schema, registry)?).map_err(Into::into)
+    }
+}
+
+/// Maps encode as objects, so they need an object schema listing the keys;
+/// entries whose key is not a property of the schema are skipped.
+impl<T: Encode, S: BuildHasher> Encode for HashMap<String, T, S> {
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        // Sort for deterministic output
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        let fields = entries
+            .into_iter()
+            .map(|(k, v)| (k.as_str(), v as &dyn Encode));
+        Encoder::encode_fields(buf, schema, registry, fields)
+    }
+}
+
+impl<T: Decode, S: BuildHasher + Default> Decode for HashMap<String, T, S> {
+    fn decode_with_schema(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        let mut map = Self::default();
+        Decoder::decode_fields(buf, schema, registry, |name, prop_schema, prop_buf| {
+            let value = Decoder::decode_property_as(prop_buf, prop_schema, registry)?;
+            map.insert(name.to_owned(), value);
+            Ok(())
+        })?;
+        Ok(map)
+    }
+}
+
+/// Implements `Encode`/`Decode` for a formatted type with a fixed-size
+/// schemaless encoding, going through its [`Value`] variant when a schema is
+/// given.
+macro_rules! impl_formatted {
+    ($ty:ty, $variant:ident, $name:literal, $encode:path, $decode:path, $size:expr) => {
+        impl Encode for $ty {
+            fn encode(&self, buf: &mut BytesMut) -> Result<(), EncodeError> {
+                $encode(buf, self)
+            }
+
+            fn encoded_size(&self) -> usize {
+                $size
+            }
+
+            fn encode_with_schema(
+                &self,
+                buf: &mut BytesMut,
+                schema: &SchemaType,
+                registry: &SchemaRegistry,
+            ) -> Result<()> {
+                Encoder::encode_value_into(buf, &Value::$variant(*self), schema, registry)
+            }
+        }
+
+        impl Decode for $ty {
+            fn decode(buf: &mut impl Buf) -> Result<Self, DecodeError> {
+                $decode(buf)
+            }
+
+            fn decode_with_schema(
+                buf: &mut impl Buf,
+                schema: &SchemaType,
+                registry: &SchemaRegistry,
+            ) -> Result<Self> {
+                match Decoder::decode_nested(buf, schema, registry)? {
+                    Value::$variant(v) => Ok(v),
+                    other => Err(unexpected($name, &other)),
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "uuid")]
+impl_formatted!(Uuid, Uuid, "uuid", encode_uuid, decode_uuid, 16);
+#[cfg(feature = "chrono")]
+impl_formatted!(
+    DateTime<Utc>,
+    DateTime,
+    "datetime",
+    encode_datetime,
+    decode_datetime,
+    9
+);
+impl_formatted!(Ipv4Addr, Ipv4, "ipv4", encode_ipv4, decode_ipv4, 4);
+impl_formatted!(Ipv6Addr, Ipv6, "ipv6", encode_ipv6, decode_ipv6, 16);
+
+/// Encodes array elements in the layout given by the schema and registry.
+fn encode_array<T: Encode>(
+    buf: &mut BytesMut,
+    items: &[T],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<()> {
+    let item_schema = array_items(schema, registry)?;
+    let encode = |item: &T, elem_buf: &mut BytesMut| {
+        item.encode_with_schema(elem_buf, &item_schema, registry)
+    };
+    if matches!(schema, SchemaType::RunLengthArray(_)) {
+        return encode_runs(buf, registry.length_mode(), items, encode);
+    }
+    encode_elements(
+        buf,
+        registry.array_format(),
+        registry.length_mode(),
+        items,
+        encode,
+    )
+}
+
+/// Returns the (resolved) item schema of an array schema, run-length
+/// encoded or not.
+pub(super) fn array_items<'s>(
+    schema: &'s SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Resolved<'s>> {
+    let (SchemaType::Array(items) | SchemaType::RunLengthArray(items)) = schema else {
+        return Err(
+            DecodeError::SchemaMismatch(format!("expected array schema, got {schema:?}")).into(),
+        );
+    };
+    registry.resolve(items)
+}
+
+pub(super) fn fixed_length<T, const N: usize>(items: Vec<T>) -> Result<[T; N], DecodeError> {
+    <[T; N]>::try_from(items).map_err(|items| {
+        DecodeError::InvalidData(format!("Expected {N} array elements, got {}", items.len()))
+    })
+}
+
+/// Builds the error for schema-encoded data that doesn't fit the target type.
+fn unexpected(expected: &str, value: &Value) -> crate::error::Error {
+    DecodeError::SchemaMismatch(format!("expected {expected}, got {value:?}")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Property;
+    use indexmap::IndexMap;
+
+    /// Encodes through the typed path and checks the bytes match the `Value`
+    /// path, then decodes them back.
+    fn roundtrip<T>(typed: &T, value: &Value, schema: &SchemaType) -> T
+    where
+        T: Encode + Decode,
+    {
+        let registry = SchemaRegistry::new();
+
+        let mut enc = Encoder::new();
+        enc.encode_as(typed, schema, &registry).unwrap();
+        let typed_bytes = enc.finish();
+
+        let mut enc = Encoder::new();
+        enc.encode(value, schema).unwrap();
+        assert_eq!(typed_bytes, enc.finish());
+
+        Decoder::decode_as(&mut typed_bytes.as_ref(), schema, &registry).unwrap()
+    }
+
+    #[test]
+    fn test_string_and_formats() {
+        let s = "hello".to_owned();
+        assert_eq!(
+            roundtrip(&s, &Value::from("hello"), &SchemaType::string()),
+            s
+        );
+
+        #[cfg(feature = "uuid")]
+        {
+            let id = Uuid::nil();
+            assert_eq!(
+                roundtrip(&id, &Value::Uuid(id), &SchemaType::string_uuid()),
+                id
+            );
+        }
+
+        let ip = Ipv4Addr::LOCALHOST;
+        assert_eq!(
+            roundtrip(&ip, &Value::Ipv4(ip), &SchemaType::string_ipv4()),
+            ip
+        );
+
+        #[cfg(feature = "chrono")]
+        {
+            let dt = crate::formats::datetime::parse_datetime("2024-01-15T10:30:00.123Z").unwrap();
+            assert_eq!(
+                roundtrip(&dt, &Value::DateTime(dt), &SchemaType::string_datetime()),
+                dt
+            );
+        }
+    }
+
+    #[test]
+    fn test_int64_uses_schema_layout() {
+        // int64 is encoded as a double on the wire, unlike the schemaless impl
+        let n = 42_i64;
+        assert_eq!(roundtrip(&n, &Value::Integer(n), &SchemaType::int64()), n);
+    }
+
+    #[test]
+    fn test_vec_and_array() {
+        let schema = SchemaType::array(SchemaType::int32());
+        let value = Value::Array(vec![Value::Integer(1), Value::Integer(2)]);
+
+        assert_eq!(roundtrip(&vec![1_i32, 2], &value, &schema), vec![1, 2]);
+        assert_eq!(roundtrip(&[1_i32, 2], &value, &schema), [1, 2]);
+
+        let bytes = {
+            let mut enc = Encoder::new();
+            enc.encode(&value, &schema).unwrap();
+            enc.finish()
+        };
+        let result: Result<[i32; 3]> =
+            Decoder::decode_as(&mut bytes.as_ref(), &schema, &SchemaRegistry::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_hash_map_as_object() {
+        let mut properties = IndexMap::new();
+        properties.insert("b".to_owned(), Property::optional(SchemaType::string()));
+        properties.insert("a".to_owned(), Property::optional(SchemaType::string()));
+        let schema = SchemaType::object(properties);
+
+        let map: HashMap<String, String> = [("a", "x"), ("b", "y")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        let mut obj = IndexMap::new();
+        obj.insert("a".into(), Value::from("x"));
+        obj.insert("b".into(), Value::from("y"));
+
+        assert_eq!(roundtrip(&map, &Value::Object(obj), &schema), map);
+    }
+
+    #[test]
+    fn test_option_in_array() {
+        let schema = SchemaType::array(SchemaType::int32());
+        let items = vec![Some(1_i32), None, Some(3)];
+
+        let mut enc = Encoder::new();
+        enc.encode_as(&items, &schema, &SchemaRegistry::new())
+            .unwrap();
+        let bytes = enc.finish();
+        assert_eq!(bytes.as_ref(), &[4, 0, 0, 0, 1, 0, 4, 0, 0, 0, 3]);
+
+        let decoded: Vec<Option<i32>> =
+            Decoder::decode_as(&mut bytes.as_ref(), &schema, &SchemaRegistry::new()).unwrap();
+        assert_eq!(decoded, items);
+    }
+
+    #[test]
+    fn test_schemaless_encoding() {
+        let mut buf = BytesMut::new();
+        let items = vec!["a".to_owned(), "bc".to_owned()];
+        items.encode(&mut buf).unwrap();
+        assert_eq!(buf.len(), items.encoded_size());
+        assert_eq!(Vec::<String>::decode(&mut buf).unwrap(), items);
+
+        let map: HashMap<String, i32> = HashMap::new();
+        assert!(map.encode(&mut BytesMut::new()).is_err());
+    }
 }
@@ -0,0 +1,254 @@
+//! Building blocks of the wire format.
+//!
+//! The [`Encoder`](crate::Encoder) and [`Decoder`](crate::Decoder) are
+//! built from the constants and functions here, which are public for
+//! hand-written [`Encode`](crate::Encode) implementations, tools that patch
+//! encoded messages in place, and implementations in other languages that
+//! want to follow this crate byte for byte.
+//!
+//! An object in the default [`ObjectFormat::Indexed`] layout is a header
+//! giving the number of properties present, then for each of them its
+//! index among the properties sorted by name, its size and its value:
+//!
+//! ```rust
+//! use bytes::BytesMut;
+//! use compactr::codec::wire::{self, ObjectHeader};
+//! use compactr::{Decoder, LengthMode, ObjectFormat, Property, SchemaType, SizeWidth, Value};
+//!
+//! let properties = [
+//!     Property::required(SchemaType::uint8()),
+//!     Property::optional(SchemaType::uint8()),
+//! ];
+//! let schema = SchemaType::object([("a", properties[0].clone()), ("b", properties[1].clone())]);
+//!
+//! // {"a": 7}: one property, index 0, one byte, the value
+//! let mut buf = BytesMut::new();
+//! wire::write_object_header(&mut buf, ObjectFormat::Indexed, &properties, [0].into_iter())?;
+//! buf.extend_from_slice(&[0]);
+//! wire::write_property_size(&mut buf, SizeWidth::Auto, LengthMode::Fixed, false, 1)?;
+//! buf.extend_from_slice(&[7]);
+//! assert_eq!(buf.as_ref(), [1, 0, 1, 7]);
+//! assert_eq!(
+//!     Decoder::decode(&mut buf.as_ref(), &schema)?,
+//!     Value::object([("a", 7)])
+//! );
+//!
+//! let mut bytes = buf.as_ref();
+//! let header = wire::read_object_header(&mut bytes, ObjectFormat::Indexed, &properties)?;
+//! assert_eq!(header, ObjectHeader::Count(1));
+//! # Ok::<(), compactr::error::Error>(())
+//! ```
+
+use crate::codec::length::{put_length, read_length, LengthMode};
+use crate::codec::object::{put_object_header, read_bitmap};
+use crate::codec::ObjectFormat;
+use crate::error::{DecodeError, EncodeError};
+use crate::schema::{Property, SizeWidth};
+use bytes::{Buf, BufMut, BytesMut};
+
+/// Width of the length prefix of plain strings in [`LengthMode::Fixed`].
+pub const STRING_LEN_WIDTH: usize = 2;
+
+/// Width of the length prefix of binary data and long strings in
+/// [`LengthMode::Fixed`].
+pub const BINARY_LEN_WIDTH: usize = 4;
+
+/// Width of the size in front of every element of a size-prefixed array in
+/// [`LengthMode::Fixed`].
+pub const ELEMENT_SIZE_WIDTH: usize = 1;
+
+/// Width of the element count of a count-prefixed array in
+/// [`LengthMode::Fixed`].
+pub const ELEMENT_COUNT_WIDTH: usize = 4;
+
+/// Width of the length prefix of every record of a framed stream, such as
+/// the ones [`Decoder::decode_many`](crate::Decoder::decode_many) reads.
+pub const FRAME_LEN_WIDTH: usize = 4;
+
+/// Byte written before the size of a property in the [`SizeWidth::Auto`]
+/// header when the value is an array or an object, or is empty.
+pub const COMPOUND_FLAG: u8 = 0x00;
+
+/// Most properties an object can have in the [`ObjectFormat::Indexed`]
+/// layout, whose count and indices are single bytes.
+pub const MAX_INDEXED_PROPERTIES: usize = 255;
+
+/// The header of an object, as read by [`read_object_header`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectHeader {
+    /// [`ObjectFormat::Indexed`]: the number of properties that follow,
+    /// each preceded by its index
+    Count(usize),
+    /// [`ObjectFormat::Bitmap`]: whether each property, sorted by name,
+    /// follows. Required properties are always present.
+    Present(Vec<bool>),
+}
+
+/// Writes the header of an object, which precedes its properties.
+///
+/// `sorted` are the object's properties sorted by name, and `present` the
+/// indices among them of the properties that follow, ascending and unique
+/// for [`ObjectFormat::Bitmap`].
+///
+/// # Errors
+///
+/// Returns an error if more than [`MAX_INDEXED_PROPERTIES`] properties are
+/// present in the indexed layout.
+pub fn write_object_header<'a>(
+    buf: &mut BytesMut,
+    format: ObjectFormat,
+    sorted: impl IntoIterator<Item = &'a Property>,
+    present: impl ExactSizeIterator<Item = usize>,
+) -> Result<(), EncodeError> {
+    put_object_header(buf, format, sorted, present)
+}
+
+/// Reads the header of an object written by [`write_object_header`].
+///
+/// # Errors
+///
+/// Returns an error if the input ends early, or a bitmap marks properties
+/// the object doesn't have.
+pub fn read_object_header<'a>(
+    buf: &mut impl Buf,
+    format: ObjectFormat,
+    sorted: impl IntoIterator<Item = &'a Property>,
+) -> Result<ObjectHeader, DecodeError> {
+    match format {
+        ObjectFormat::Indexed => {
+            if !buf.has_remaining() {
+                return Err(DecodeError::UnexpectedEof);
+            }
+            Ok(ObjectHeader::Count(buf.get_u8().into()))
+        }
+        ObjectFormat::Bitmap => Ok(ObjectHeader::Present(read_bitmap(buf, sorted)?.into_vec())),
+    }
+}
+
+/// Writes the size of a property's value, the `size` bytes of which
+/// follow.
+///
+/// `compound` tells whether the value is an array or an object, which takes
+/// a [`COMPOUND_FLAG`] before its size in the [`SizeWidth::Auto`] header.
+///
+/// # Errors
+///
+/// Returns an error if `size` doesn't fit the width.
+pub fn write_property_size(
+    buf: &mut BytesMut,
+    width: SizeWidth,
+    mode: LengthMode,
+    compound: bool,
+    size: usize,
+) -> Result<(), EncodeError> {
+    let too_large = |max: u64| {
+        EncodeError::InvalidFormat(format!(
+            "Property value too large: {size} bytes (max {max} with {width} sizes)"
+        ))
+    };
+    match width {
+        SizeWidth::Auto if mode == LengthMode::Varint => {
+            put_length(buf, mode, 1, size, "Property size")?;
+        }
+        SizeWidth::Auto => {
+            let size = u8::try_from(size).map_err(|_| too_large(255))?;
+            if compound || size == 0 {
+                buf.put_u8(COMPOUND_FLAG);
+            }
+            buf.put_u8(size);
+        }
+        SizeWidth::U8 => buf.put_u8(u8::try_from(size).map_err(|_| too_large(255))?),
+        SizeWidth::U16 => buf.put_u16(u16::try_from(size).map_err(|_| too_large(65535))?),
+        SizeWidth::U32 => {
+            buf.put_u32(u32::try_from(size).map_err(|_| too_large(u64::from(u32::MAX)))?);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the size of a property's value written by [`write_property_size`].
+///
+/// # Errors
+///
+/// Returns an error if the input ends early.
+pub fn read_property_size(
+    buf: &mut impl Buf,
+    width: SizeWidth,
+    mode: LengthMode,
+) -> Result<usize, DecodeError> {
+    let width = match width {
+        SizeWidth::Auto if mode == LengthMode::Varint => return read_length(buf, mode, 1),
+        SizeWidth::Auto => {
+            if !buf.has_remaining() {
+                return Err(DecodeError::UnexpectedEof);
+            }
+            return match buf.get_u8() {
+                // Compound type or empty value: the size follows the flag
+                COMPOUND_FLAG if buf.has_remaining() => Ok(buf.get_u8().into()),
+                COMPOUND_FLAG => Err(DecodeError::UnexpectedEof),
+                size => Ok(size.into()),
+            };
+        }
+        SizeWidth::U8 => 1,
+        SizeWidth::U16 => 2,
+        SizeWidth::U32 => 4,
+    };
+    read_length(buf, LengthMode::Fixed, width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_header() {
+        let sorted = [
+            Property::required(crate::SchemaType::uint8()),
+            Property::optional(crate::SchemaType::uint8()),
+            Property::optional(crate::SchemaType::uint8()),
+        ];
+        for (format, expected) in [
+            (ObjectFormat::Indexed, ObjectHeader::Count(2)),
+            (
+                ObjectFormat::Bitmap,
+                ObjectHeader::Present(vec![true, false, true]),
+            ),
+        ] {
+            let mut buf = BytesMut::new();
+            write_object_header(&mut buf, format, &sorted, [0, 2].into_iter()).unwrap();
+            let mut bytes = buf.as_ref();
+            assert_eq!(
+                read_object_header(&mut bytes, format, &sorted).unwrap(),
+                expected
+            );
+            assert!(bytes.is_empty());
+        }
+        assert!(read_object_header(&mut [].as_ref(), ObjectFormat::Indexed, &sorted).is_err());
+    }
+
+    #[test]
+    fn test_property_size() {
+        for (width, mode, compound, size, expected) in [
+            (SizeWidth::Auto, LengthMode::Fixed, false, 3, &[3][..]),
+            (SizeWidth::Auto, LengthMode::Fixed, true, 3, &[0, 3]),
+            (SizeWidth::Auto, LengthMode::Fixed, false, 0, &[0, 0]),
+            (
+                SizeWidth::Auto,
+                LengthMode::Varint,
+                true,
+                300,
+                &[0xac, 0x02],
+            ),
+            (SizeWidth::U16, LengthMode::Fixed, true, 300, &[1, 44]),
+        ] {
+            let mut buf = BytesMut::new();
+            write_property_size(&mut buf, width, mode, compound, size).unwrap();
+            assert_eq!(buf.as_ref(), expected);
+            let mut bytes = buf.as_ref();
+            assert_eq!(read_property_size(&mut bytes, width, mode).unwrap(), size);
+        }
+        let mut buf = BytesMut::new();
+        let err = write_property_size(&mut buf, SizeWidth::Auto, LengthMode::Fixed, false, 256);
+        assert!(err.is_err());
+    }
+}
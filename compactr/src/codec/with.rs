@@ -0,0 +1,65 @@
+//! Support for `#[compactr(with = "module")]` fields.
+//!
+//! The field is encoded and decoded by the module's `encode` and `decode`
+//! functions instead of the field type's own [`Encode`]/[`Decode`]
+//! implementations, which it doesn't need to have.
+//!
+//! [`Decode`]: crate::Decode
+
+use crate::codec::Encode;
+use crate::error::Result;
+use crate::schema::{SchemaRegistry, SchemaType};
+use bytes::{Buf, BytesMut};
+
+/// Signature of a `with` module's `encode` function.
+pub type EncodeFn<T> = fn(&T, &mut BytesMut, &SchemaType, &SchemaRegistry) -> Result<()>;
+
+/// Signature of a `with` module's `decode` function.
+pub type DecodeFn<B, T> = fn(&mut B, &SchemaType, &SchemaRegistry) -> Result<T>;
+
+/// A field value encoded by a `with` module.
+pub struct EncodeWith<'a, T> {
+    value: &'a T,
+    encode: EncodeFn<T>,
+}
+
+impl<'a, T> EncodeWith<'a, T> {
+    /// Pairs `value` with the function encoding it.
+    pub fn new(value: &'a T, encode: EncodeFn<T>) -> Self {
+        Self { value, encode }
+    }
+}
+
+impl<T> Encode for EncodeWith<'_, T> {
+    fn encode_with_schema(
+        &self,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        (self.encode)(self.value, buf, schema, registry)
+    }
+}
+
+/// Decodes a property with a `with` module's `decode` function, resolving
+/// references first like [`Decoder::decode_property_as`] does.
+///
+/// # Errors
+///
+/// Returns an error if a reference cannot be resolved or `decode` fails.
+///
+/// [`Decoder::decode_property_as`]: crate::Decoder::decode_property_as
+pub fn decode_with<B: Buf, T>(
+    buf: &mut B,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+    decode: DecodeFn<B, T>,
+) -> Result<T> {
+    match schema {
+        SchemaType::Reference(ref_name) => {
+            let resolved = registry.resolve_shared(ref_name)?;
+            decode(buf, &resolved, registry)
+        }
+        _ => decode(buf, schema, registry),
+    }
+}
@@ -0,0 +1,280 @@
+//! Golden fixtures for cross-implementation compatibility.
+//!
+//! A fixture is a schema, a value and the bytes another implementation, such
+//! as compactr.js, encoded the value to. [`verify_fixture`] checks that this
+//! crate encodes the value to the same bytes and decodes them back to the
+//! value, and [`verify_fixture_dir`] runs every fixture listed in the
+//! `manifest.json` of a directory:
+//!
+//! ```json
+//! {
+//!   "fixtures": [
+//!     {
+//!       "name": "int32_42",
+//!       "filename": "int32_42.bin",
+//!       "schema": {"type": "object", "properties": {"value": {"type": "integer", "format": "int32"}}, "required": ["value"]},
+//!       "value": {"value": 42}
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Schemas are `OpenAPI` schemas, values are JSON as read by
+//! [`Value::from_json`], and the bytes come from `filename`, relative to the
+//! directory, or from a `hex` string. `tests/fixtures/generate_fixtures.js`
+//! writes such a directory with compactr.js.
+
+use crate::codec::{Decoder, Encoder};
+use crate::schema::SchemaType;
+use crate::testvectors::{hex, unhex};
+use crate::value::Value;
+use serde_json::Value as Json;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+/// Why a fixture failed.
+#[derive(Debug, Error)]
+pub enum FixtureError {
+    /// The fixture lacks a schema, a value or its bytes
+    #[error("missing {0}")]
+    Missing(String),
+
+    /// The schema or the value can't be read
+    #[error("invalid fixture: {0}")]
+    Invalid(crate::error::Error),
+
+    /// The value doesn't encode with the schema
+    #[error("encoding failed: {0}")]
+    Encode(crate::error::Error),
+
+    /// The value encodes to different bytes
+    #[error("first difference at byte {offset}: expected {expected}, got {actual}")]
+    Mismatch {
+        /// Offset of the first differing byte
+        offset: usize,
+        /// Expected bytes in hex
+        expected: String,
+        /// Encoded bytes in hex
+        actual: String,
+    },
+
+    /// The expected bytes don't decode, or leave bytes over
+    #[error("decoding failed: {0}")]
+    Decode(crate::error::Error),
+
+    /// The expected bytes decode to a different value
+    #[error("decoded {actual}, expected {expected}")]
+    Roundtrip {
+        /// The fixture's value
+        expected: Json,
+        /// The decoded value
+        actual: Json,
+    },
+}
+
+/// Checks that `value_json` encodes with `schema_json` to `expected_bytes`
+/// and that `expected_bytes` decode back to it.
+///
+/// ```rust
+/// use compactr::compat::verify_fixture;
+/// use serde_json::json;
+///
+/// let schema = json!({"type": "integer", "format": "int32"});
+/// assert!(verify_fixture(&schema, &json!(42), &[0, 0, 0, 42]).is_ok());
+/// let err = verify_fixture(&schema, &json!(42), &[0, 0, 42, 0]).unwrap_err();
+/// assert_eq!(err.to_string(), "first difference at byte 2: expected 00002a00, got 0000002a");
+/// ```
+///
+/// # Errors
+///
+/// Returns the first check that failed.
+pub fn verify_fixture(
+    schema_json: &Json,
+    value_json: &Json,
+    expected_bytes: &[u8],
+) -> Result<(), FixtureError> {
+    let schema = SchemaType::from_json(schema_json).map_err(FixtureError::Invalid)?;
+    let value = Value::from_json(value_json, &schema).map_err(FixtureError::Invalid)?;
+
+    let mut encoder = Encoder::new();
+    encoder
+        .encode(&value, &schema)
+        .map_err(FixtureError::Encode)?;
+    let actual = encoder.finish();
+    if actual != expected_bytes {
+        let offset = actual
+            .iter()
+            .zip(expected_bytes)
+            .position(|(a, b)| a != b)
+            .unwrap_or_else(|| actual.len().min(expected_bytes.len()));
+        return Err(FixtureError::Mismatch {
+            offset,
+            expected: hex(expected_bytes),
+            actual: hex(&actual),
+        });
+    }
+
+    let decoded = Decoder::decode_exact(expected_bytes, &schema).map_err(FixtureError::Decode)?;
+    if decoded != value {
+        return Err(FixtureError::Roundtrip {
+            expected: value.to_json(),
+            actual: decoded.to_json(),
+        });
+    }
+    Ok(())
+}
+
+/// Outcome of [`verify_fixture_dir`].
+#[derive(Debug, Default)]
+pub struct FixtureReport {
+    /// Names of the fixtures that passed
+    pub passed: Vec<String>,
+    /// Names of the fixtures that failed, with the reason
+    pub failed: Vec<(String, FixtureError)>,
+}
+
+impl FixtureReport {
+    /// Returns whether every fixture passed.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl fmt::Display for FixtureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} passed, {} failed",
+            self.passed.len(),
+            self.failed.len()
+        )?;
+        for (name, err) in &self.failed {
+            write!(f, "\n  {name}: {err}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Verifies every fixture listed in `dir/manifest.json`, see the
+/// [module documentation](self) for the format.
+///
+/// # Errors
+///
+/// Returns an error if the manifest can't be read, isn't a manifest or
+/// lists no fixtures, so that a missing fixture directory fails rather than
+/// passing with nothing checked. Failing fixtures are reported in the
+/// [`FixtureReport`].
+pub fn verify_fixture_dir(dir: impl AsRef<Path>) -> io::Result<FixtureReport> {
+    let dir = dir.as_ref();
+    let path = dir.join("manifest.json");
+    let manifest: Json =
+        serde_json::from_slice(&std::fs::read(&path).map_err(|e| {
+            io::Error::new(e.kind(), format!("cannot read {}: {e}", path.display()))
+        })?)?;
+    let fixtures = manifest
+        .get("fixtures")
+        .and_then(Json::as_array)
+        .filter(|fixtures| !fixtures.is_empty())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} lists no fixtures", path.display()),
+            )
+        })?;
+
+    let mut report = FixtureReport::default();
+    for (i, fixture) in fixtures.iter().enumerate() {
+        let name = fixture
+            .get("name")
+            .and_then(Json::as_str)
+            .map_or_else(|| format!("#{i}"), ToOwned::to_owned);
+        match verify_entry(dir, fixture) {
+            Ok(()) => report.passed.push(name),
+            Err(err) => report.failed.push((name, err)),
+        }
+    }
+    Ok(report)
+}
+
+/// Verifies one manifest entry.
+fn verify_entry(dir: &Path, fixture: &Json) -> Result<(), FixtureError> {
+    let field = |key: &str| {
+        fixture
+            .get(key)
+            .ok_or_else(|| FixtureError::Missing(key.to_owned()))
+    };
+    let bytes = if let Some(filename) = fixture.get("filename").and_then(Json::as_str) {
+        let path = dir.join(filename);
+        std::fs::read(&path).map_err(|_| FixtureError::Missing(path.display().to_string()))?
+    } else {
+        let hex = field("hex")?.as_str().unwrap_or_default();
+        if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(FixtureError::Missing("valid hex".to_owned()));
+        }
+        unhex(hex)
+    };
+    verify_fixture(field("schema")?, field("value")?, &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testvectors::vectors;
+    use serde_json::json;
+
+    #[test]
+    fn test_verify_fixture() {
+        for vector in vectors() {
+            let json = vector.to_json();
+            verify_fixture(&json["schema"], &json["value"], &vector.bytes)
+                .unwrap_or_else(|e| panic!("{}: {e}", vector.name));
+        }
+
+        let schema = json!({"type": "string"});
+        let err = verify_fixture(&schema, &json!("ab"), &[0, 2, b'a', b'b', 0]).unwrap_err();
+        assert!(matches!(err, FixtureError::Mismatch { offset: 4, .. }));
+        let err = verify_fixture(&schema, &json!(1), &[]).unwrap_err();
+        assert!(matches!(err, FixtureError::Invalid(_)));
+    }
+
+    #[test]
+    fn test_verify_fixture_dir() {
+        let dir = std::env::temp_dir().join(format!("compactr-compat-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(verify_fixture_dir(&dir).is_err());
+
+        std::fs::write(dir.join("bool.bin"), [1]).unwrap();
+        let manifest = json!({"fixtures": [
+            {"name": "bool", "filename": "bool.bin", "schema": {"type": "boolean"}, "value": true},
+            {"name": "int8", "hex": "fe", "schema": {"type": "integer", "format": "int8"}, "value": -2},
+            {"name": "gone", "filename": "gone.bin", "schema": {"type": "boolean"}, "value": true},
+            {"name": "wrong", "hex": "00", "schema": {"type": "boolean"}, "value": true},
+            {"name": "bare", "hex": "00"}
+        ]});
+        std::fs::write(dir.join("manifest.json"), manifest.to_string()).unwrap();
+        let report = verify_fixture_dir(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(report.passed, ["bool", "int8"]);
+        let failed: Vec<_> = report
+            .failed
+            .iter()
+            .map(|(name, err)| format!("{name}: {err}"))
+            .collect();
+        assert_eq!(
+            failed,
+            [
+                format!("gone: missing {}", dir.join("gone.bin").display()),
+                "wrong: first difference at byte 0: expected 00, got 01".to_owned(),
+                "bare: missing schema".to_owned(),
+            ]
+        );
+        assert!(!report.is_success());
+        assert!(report
+            .to_string()
+            .starts_with("2 passed, 3 failed\n  gone: "));
+    }
+}
@@ -0,0 +1,366 @@
+//! Client for Confluent-compatible schema registries.
+//!
+//! A [`RegistryClient`] registers Compactr schemas with a schema registry,
+//! as JSON schemas in their [`to_json`](SchemaType::to_json) form, fetches
+//! them back by ID, and encodes and decodes messages in the registry's wire
+//! format, so Compactr payloads fit into existing Kafka infrastructure.
+//!
+//! Wire format:
+//! - 1 byte: magic `0x00`
+//! - u32 BE ID the registry assigned to the schema
+//! - the payload, encoded with the schema
+//!
+//! This is also the layout of a [`Message`](crate::Message) whose schema
+//! has a numeric ID.
+//!
+//! The client doesn't depend on an HTTP library. Requests go through a
+//! [`Transport`], implemented for closures, which sends them with the
+//! [`CONTENT_TYPE`] header to the registry's base URL:
+//!
+//! ```rust,ignore
+//! let client = RegistryClient::new(|method: &str, path: &str, body: Option<&str>| {
+//!     let request = agent
+//!         .request(method, &format!("http://registry:8081{path}"))
+//!         .set("Content-Type", CONTENT_TYPE);
+//!     let response = match body {
+//!         Some(body) => request.send_string(body),
+//!         None => request.call(),
+//!     };
+//!     match response {
+//!         Ok(response) | Err(ureq::Error::Status(_, response)) => {
+//!             Ok((response.status(), response.into_string()?))
+//!         }
+//!         Err(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
+//!     }
+//! });
+//!
+//! let bytes = client.encode("orders-value", &order, &order_schema)?;
+//! let (id, order) = client.decode(&mut bytes.as_ref())?;
+//! ```
+
+use crate::codec::{Decoder, Encoder};
+use crate::error::{DecodeError, Result, SchemaError};
+use crate::schema::{SchemaId, SchemaRegistry, SchemaType};
+use crate::value::Value;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io;
+use std::sync::{Arc, RwLock};
+
+/// Content type of the registry's requests and responses.
+pub const CONTENT_TYPE: &str = "application/vnd.schemaregistry.v1+json";
+
+/// Magic byte starting every message.
+pub const MAGIC_BYTE: u8 = 0x00;
+
+/// Sends HTTP requests to a schema registry.
+pub trait Transport {
+    /// Sends a request with `method` (`GET` or `POST`) to `path`, relative
+    /// to the registry's base URL, with `body` if given, and returns the
+    /// status code and body of the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no response was received. Error statuses are
+    /// responses.
+    fn send(&self, method: &str, path: &str, body: Option<&str>) -> io::Result<(u16, String)>;
+}
+
+impl<F> Transport for F
+where
+    F: Fn(&str, &str, Option<&str>) -> io::Result<(u16, String)>,
+{
+    fn send(&self, method: &str, path: &str, body: Option<&str>) -> io::Result<(u16, String)> {
+        self(method, path, body)
+    }
+}
+
+/// Client of a Confluent-compatible schema registry.
+///
+/// Schemas are cached once registered or fetched, so encoding and decoding
+/// only reach the registry for schemas they haven't seen.
+#[derive(Debug)]
+pub struct RegistryClient<T> {
+    transport: T,
+    registry: SchemaRegistry,
+    by_id: RwLock<HashMap<u32, Arc<SchemaType>>>,
+    ids: RwLock<HashMap<(String, u64), u32>>,
+}
+
+impl<T: Transport> RegistryClient<T> {
+    /// Creates a client sending its requests through `transport`.
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            registry: SchemaRegistry::new(),
+            by_id: RwLock::default(),
+            ids: RwLock::default(),
+        }
+    }
+
+    /// Sets the registry whose formats and options values are encoded and
+    /// decoded with.
+    ///
+    /// Schemas registered with the schema registry must not contain
+    /// references, which its JSON schemas can't resolve.
+    #[must_use]
+    pub fn with_registry(mut self, registry: SchemaRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Registers `schema` under `subject` and returns its ID.
+    ///
+    /// The registry returns the existing ID of a schema registered before.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the registry rejects the
+    /// schema, for instance as incompatible with the subject's previous
+    /// versions.
+    pub fn register(&self, subject: &str, schema: &SchemaType) -> Result<u32> {
+        let key = (subject.to_owned(), schema.fingerprint());
+        if let Some(&id) = read(&self.ids)?.get(&key) {
+            return Ok(id);
+        }
+        let body = json!({
+            "schemaType": "JSON",
+            "schema": schema.to_json().to_string(),
+        });
+        let path = format!("/subjects/{}/versions", encode_path(subject));
+        let response = self
+            .request("POST", &path, Some(&body.to_string()))?
+            .ok_or_else(|| registry_error(format!("POST {path}: 404 not found")))?;
+        let id = response
+            .get("id")
+            .and_then(serde_json::Value::as_u64)
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or_else(|| registry_error(format!("invalid response {response}")))?;
+        write(&self.by_id)?
+            .entry(id)
+            .or_insert_with(|| Arc::new(schema.clone()));
+        write(&self.ids)?.insert(key, id);
+        Ok(id)
+    }
+
+    /// Returns the schema with the ID `id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchemaError::UnknownSchema`] if the registry has no schema
+    /// with that ID, or an error if the request fails or the schema is not a
+    /// JSON schema Compactr can read.
+    pub fn schema(&self, id: u32) -> Result<Arc<SchemaType>> {
+        if let Some(schema) = read(&self.by_id)?.get(&id) {
+            return Ok(Arc::clone(schema));
+        }
+        let response = self
+            .request("GET", &format!("/schemas/ids/{id}"), None)?
+            .ok_or(SchemaError::UnknownSchema(SchemaId::Id(id)))?;
+        let schema_type = response
+            .get("schemaType")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or("AVRO");
+        if schema_type != "JSON" {
+            return Err(registry_error(format!(
+                "schema {id} is a {schema_type} schema, not JSON"
+            )));
+        }
+        let schema = response
+            .get("schema")
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| registry_error(format!("invalid response {response}")))?;
+        let json = serde_json::from_str(schema)
+            .map_err(|e| registry_error(format!("invalid schema {id}: {e}")))?;
+        let schema = Arc::new(SchemaType::from_json(&json)?);
+        write(&self.by_id)?.insert(id, Arc::clone(&schema));
+        Ok(schema)
+    }
+
+    /// Encodes `value` with `schema`, registered under `subject`, prefixed
+    /// with the magic byte and the schema's ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if registering the schema fails or the value
+    /// doesn't match it.
+    pub fn encode(&self, subject: &str, value: &Value, schema: &SchemaType) -> Result<Bytes> {
+        let mut encoder = Encoder::new();
+        encoder.encode_with_registry(value, schema, &self.registry)?;
+        let payload = encoder.finish();
+        let id = self.register(subject, schema)?;
+        let mut buf = BytesMut::with_capacity(5 + payload.len());
+        buf.put_u8(MAGIC_BYTE);
+        buf.put_u32(id);
+        buf.put_slice(&payload);
+        Ok(buf.freeze())
+    }
+
+    /// Decodes a message, fetching its schema from the registry if it isn't
+    /// cached, and returns it with the schema's ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is truncated or doesn't start with the
+    /// magic byte, the schema can't be fetched, or the payload doesn't match
+    /// it.
+    pub fn decode(&self, buf: &mut impl Buf) -> Result<(u32, Value)> {
+        if buf.remaining() < 5 {
+            return Err(DecodeError::UnexpectedEof.into());
+        }
+        let magic = buf.get_u8();
+        if magic != MAGIC_BYTE {
+            return Err(DecodeError::InvalidData(format!("Invalid magic byte: {magic}")).into());
+        }
+        let id = buf.get_u32();
+        let schema = self.schema(id)?;
+        let value = Decoder::decode_with_registry(buf, &schema, &self.registry)?;
+        Ok((id, value))
+    }
+
+    /// Sends a request, returning the JSON body of a successful response, or
+    /// `None` if the registry responds with 404.
+    fn request(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&str>,
+    ) -> Result<Option<serde_json::Value>> {
+        let (status, body) = self
+            .transport
+            .send(method, path, body)
+            .map_err(|e| registry_error(format!("{method} {path} failed: {e}")))?;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+        if status == 404 {
+            return Ok(None);
+        }
+        if !(200..300).contains(&status) {
+            let message = json
+                .get("message")
+                .and_then(serde_json::Value::as_str)
+                .unwrap_or(&body);
+            return Err(registry_error(format!(
+                "{method} {path}: {status} {message}"
+            )));
+        }
+        Ok(Some(json))
+    }
+}
+
+fn registry_error(message: String) -> crate::error::Error {
+    SchemaError::Registry(message).into()
+}
+
+fn read<K, V>(
+    lock: &RwLock<HashMap<K, V>>,
+) -> Result<std::sync::RwLockReadGuard<'_, HashMap<K, V>>> {
+    lock.read()
+        .map_err(|_| SchemaError::InvalidSchema("Failed to acquire read lock".to_owned()).into())
+}
+
+fn write<K, V>(
+    lock: &RwLock<HashMap<K, V>>,
+) -> Result<std::sync::RwLockWriteGuard<'_, HashMap<K, V>>> {
+    lock.write()
+        .map_err(|_| SchemaError::InvalidSchema("Failed to acquire write lock".to_owned()).into())
+}
+
+/// Percent-encodes a subject name for use as a path segment.
+fn encode_path(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    for b in segment.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            encoded.push(char::from(b));
+        } else {
+            let _ = write!(encoded, "%{b:02X}");
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::schema::Property;
+    use std::sync::Mutex;
+
+    /// An in-memory registry, counting the requests it receives.
+    #[derive(Default)]
+    struct MockRegistry {
+        schemas: Mutex<Vec<String>>,
+        requests: Mutex<usize>,
+    }
+
+    impl Transport for &MockRegistry {
+        fn send(&self, method: &str, path: &str, body: Option<&str>) -> io::Result<(u16, String)> {
+            *self.requests.lock().unwrap() += 1;
+            let mut schemas = self.schemas.lock().unwrap();
+            let response = match (method, path.strip_prefix("/schemas/ids/"), body) {
+                ("POST", None, Some(body)) if path == "/subjects/orders%2Fv1/versions" => {
+                    let body: serde_json::Value = serde_json::from_str(body).unwrap();
+                    assert_eq!(body["schemaType"], "JSON");
+                    let schema = body["schema"].as_str().unwrap().to_owned();
+                    if schema.contains("\"reject\"") {
+                        return Ok((409, r#"{"message":"incompatible"}"#.to_owned()));
+                    }
+                    if !schemas.contains(&schema) {
+                        schemas.push(schema.clone());
+                    }
+                    let id = schemas.iter().position(|s| *s == schema).unwrap();
+                    json!({ "id": id + 1 })
+                }
+                ("GET", Some(id), None) => {
+                    let id: usize = id.parse().unwrap();
+                    match schemas.get(id.wrapping_sub(1)) {
+                        Some(schema) => json!({ "schemaType": "JSON", "schema": schema }),
+                        None => return Ok((404, r#"{"message":"not found"}"#.to_owned())),
+                    }
+                }
+                _ => return Ok((400, String::new())),
+            };
+            Ok((200, response.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_registry_client() {
+        let mock = MockRegistry::default();
+        let producer = RegistryClient::new(&mock);
+        let schema = SchemaType::object([
+            ("id", Property::required(SchemaType::int64())),
+            ("note", Property::optional(SchemaType::string())),
+        ]);
+        let value = Value::object([("id", Value::from(7)), ("note", Value::from("hi"))]);
+
+        let bytes = producer.encode("orders/v1", &value, &schema).unwrap();
+        assert_eq!(&bytes[..5], [MAGIC_BYTE, 0, 0, 0, 1]);
+        assert_eq!(producer.register("orders/v1", &schema).unwrap(), 1);
+        assert_eq!(*mock.requests.lock().unwrap(), 1);
+
+        // A consumer fetches the schema by ID, once
+        let consumer = RegistryClient::new(&mock);
+        for _ in 0..2 {
+            let (id, decoded) = consumer.decode(&mut bytes.as_ref()).unwrap();
+            assert_eq!((id, decoded), (1, value.clone()));
+        }
+        assert_eq!(*mock.requests.lock().unwrap(), 2);
+        assert_eq!(*consumer.schema(1).unwrap(), schema);
+
+        // Unknown IDs, bad prefixes and rejected schemas fail
+        assert!(matches!(
+            consumer.decode(&mut [MAGIC_BYTE, 0, 0, 0, 9].as_ref()),
+            Err(Error::Schema(SchemaError::UnknownSchema(SchemaId::Id(9))))
+        ));
+        assert!(consumer.decode(&mut [1, 0, 0, 0, 1].as_ref()).is_err());
+        assert!(consumer.decode(&mut [MAGIC_BYTE, 0].as_ref()).is_err());
+        let rejected = SchemaType::object([("reject", SchemaType::boolean())]);
+        let err = producer.register("orders/v1", &rejected).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Schema registry error: POST /subjects/orders%2Fv1/versions: 409 incompatible"
+        );
+    }
+}
@@ -0,0 +1,656 @@
+//! Self-contained `.cpk` files of Compactr records.
+//!
+//! A container embeds the schemas needed to decode it, so files can be
+//! read without out-of-band schema knowledge.
+//!
+//! Format:
+//! - 4 bytes: magic `CPKF`
+//! - 1 byte: format version
+//! - 1 byte: flags, bit 0 set for [`ArrayFormat::CountPrefixed`] arrays, bit
+//!   1 for [`ObjectFormat::Bitmap`] objects, bit 2 for
+//!   [`ArrayFormat::VarintSizePrefixed`] arrays and bit 3 for
+//!   [`LengthMode::Varint`] lengths
+//! - u16 BE length and UTF-8 name of the schema every record is encoded with
+//! - u32 BE length and a schema bundle (see [`SchemaRegistry::to_bundle`])
+//! - records until the end of the file, each a u32 BE length followed by the
+//!   encoded value
+//!
+//! Custom formats cannot be bundled, so their schemas cannot be embedded.
+//!
+//! A [`ContainerIndex`] of a seekable container gives random access to its
+//! records, so large files can be read from any record on, or scanned in
+//! parallel ranges.
+
+use crate::codec::{ArrayFormat, Decoder, Encoder, LengthMode, ObjectFormat};
+use crate::error::{DecodeError, EncodeError, Result, SchemaError};
+use crate::schema::{SchemaRegistry, SchemaType};
+use crate::value::Value;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Default largest record or schema bundle a [`ContainerReader`] accepts
+/// (16 MiB).
+pub const DEFAULT_MAX_RECORD_LEN: usize = 16 * 1024 * 1024;
+
+const MAGIC: &[u8; 4] = b"CPKF";
+const VERSION: u8 = 1;
+const FLAG_COUNT_PREFIXED: u8 = 1;
+const FLAG_BITMAP_OBJECTS: u8 = 2;
+const FLAG_VARINT_SIZES: u8 = 4;
+const FLAG_VARINT_LENGTHS: u8 = 8;
+
+/// Writes values of one schema to a container.
+///
+/// ```rust,ignore
+/// let mut writer = ContainerWriter::new(File::create("users.cpk")?, &registry, "User")?;
+/// for user in &users {
+///     writer.write(user)?;
+/// }
+/// writer.finish()?;
+/// ```
+#[derive(Debug)]
+pub struct ContainerWriter<W: Write> {
+    writer: W,
+    registry: SchemaRegistry,
+    schema: SchemaType,
+    buf: BytesMut,
+}
+
+impl<W: Write> ContainerWriter<W> {
+    /// Writes the container header: the schema name, the registry's array
+    /// and object formats and all of its schemas.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `schema` is not registered, the registry cannot
+    /// be bundled, or writing fails.
+    pub fn new(mut writer: W, registry: &SchemaRegistry, schema: &str) -> Result<Self> {
+        let resolved = registry
+            .get(schema)?
+            .ok_or_else(|| SchemaError::UnresolvedReference(schema.to_owned()))?;
+        let bundle = registry.to_bundle()?;
+        let name_len = u16::try_from(schema.len())
+            .map_err(|_| EncodeError::InvalidFormat(format!("Schema name too long: {schema}")))?;
+        let flags = match registry.array_format() {
+            ArrayFormat::SizePrefixed => 0,
+            ArrayFormat::CountPrefixed => FLAG_COUNT_PREFIXED,
+            ArrayFormat::VarintSizePrefixed => FLAG_VARINT_SIZES,
+        } | match registry.object_format() {
+            ObjectFormat::Indexed => 0,
+            ObjectFormat::Bitmap => FLAG_BITMAP_OBJECTS,
+        } | match registry.length_mode() {
+            LengthMode::Fixed => 0,
+            LengthMode::Varint => FLAG_VARINT_LENGTHS,
+        };
+
+        let mut header = Vec::with_capacity(12 + schema.len() + bundle.len());
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&[VERSION, flags]);
+        header.extend_from_slice(&name_len.to_be_bytes());
+        header.extend_from_slice(schema.as_bytes());
+        header.extend_from_slice(&len_u32(bundle.len())?.to_be_bytes());
+        header.extend_from_slice(&bundle);
+        writer.write_all(&header).map_err(EncodeError::Io)?;
+
+        Ok(Self {
+            writer,
+            registry: registry.clone(),
+            schema: resolved,
+            buf: BytesMut::new(),
+        })
+    }
+
+    /// Encodes `value` and appends it as a record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value doesn't match the schema or writing
+    /// fails.
+    pub fn write(&mut self, value: &Value) -> Result<()> {
+        self.buf.clear();
+        Encoder::encode_into(&mut self.buf, value, &self.schema, &self.registry)?;
+        let record = std::mem::take(&mut self.buf);
+        let result = self.write_encoded(&record);
+        self.buf = record;
+        result
+    }
+
+    /// Appends an already encoded record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record is larger than `u32::MAX` bytes or
+    /// writing fails.
+    pub fn write_encoded(&mut self, record: &[u8]) -> Result<()> {
+        write_framed(&mut self.writer, record)
+    }
+
+    /// Flushes the container and returns the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if flushing fails.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush().map_err(EncodeError::Io)?;
+        Ok(self.writer)
+    }
+}
+
+/// Reads the records of a container, yielding decoded values when used as
+/// an iterator.
+///
+/// ```rust,ignore
+/// let reader = ContainerReader::new(BufReader::new(File::open("users.cpk")?))?;
+/// for user in reader {
+///     println!("{}", user?.to_json());
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ContainerReader<R: Read> {
+    reader: R,
+    registry: SchemaRegistry,
+    name: String,
+    schema: SchemaType,
+    max_record_len: usize,
+}
+
+impl<R: Read> ContainerReader<R> {
+    /// Reads the container header, accepting records and schema bundles up
+    /// to [`DEFAULT_MAX_RECORD_LEN`] bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a container, uses an unsupported
+    /// version, or its schemas cannot be loaded.
+    pub fn new(reader: R) -> Result<Self> {
+        Self::with_max_record_len(reader, DEFAULT_MAX_RECORD_LEN)
+    }
+
+    /// Reads the container header, accepting records and schema bundles up
+    /// to `max_record_len` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a container, uses an unsupported
+    /// version, or its schemas cannot be loaded.
+    pub fn with_max_record_len(mut reader: R, max_record_len: usize) -> Result<Self> {
+        let mut head = [0; 8];
+        read_exact(&mut reader, &mut head)?;
+        if &head[..4] != MAGIC {
+            return Err(DecodeError::InvalidData("Not a Compactr container".to_owned()).into());
+        }
+        if head[4] != VERSION {
+            return Err(DecodeError::InvalidData(format!(
+                "Unsupported container version {}",
+                head[4]
+            ))
+            .into());
+        }
+        let flags = head[5];
+        let array_format = match flags & !(FLAG_BITMAP_OBJECTS | FLAG_VARINT_LENGTHS) {
+            0 => ArrayFormat::SizePrefixed,
+            FLAG_COUNT_PREFIXED => ArrayFormat::CountPrefixed,
+            FLAG_VARINT_SIZES => ArrayFormat::VarintSizePrefixed,
+            _ => {
+                return Err(
+                    DecodeError::InvalidData(format!("Invalid container flags {flags}")).into(),
+                )
+            }
+        };
+        let object_format = if flags & FLAG_BITMAP_OBJECTS == 0 {
+            ObjectFormat::Indexed
+        } else {
+            ObjectFormat::Bitmap
+        };
+        let length_mode = if flags & FLAG_VARINT_LENGTHS == 0 {
+            LengthMode::Fixed
+        } else {
+            LengthMode::Varint
+        };
+
+        let mut name = vec![0; usize::from(u16::from_be_bytes([head[6], head[7]]))];
+        read_exact(&mut reader, &mut name)?;
+        let name = String::from_utf8(name)
+            .map_err(|e| DecodeError::InvalidData(format!("Invalid schema name: {e}")))?;
+
+        let mut len = [0; 4];
+        read_exact(&mut reader, &mut len)?;
+        let bundle = read_record(&mut reader, u32::from_be_bytes(len), max_record_len)?;
+        let registry = SchemaRegistry::from_bundle(&mut bundle.as_ref())?
+            .with_array_format(array_format)
+            .with_object_format(object_format)
+            .with_length_mode(length_mode);
+        let schema = registry
+            .get(&name)?
+            .ok_or_else(|| SchemaError::UnresolvedReference(name.clone()))?;
+
+        Ok(Self {
+            reader,
+            registry,
+            name,
+            schema,
+            max_record_len,
+        })
+    }
+
+    /// Returns the registry of the schemas embedded in the container.
+    #[must_use]
+    pub const fn registry(&self) -> &SchemaRegistry {
+        &self.registry
+    }
+
+    /// Returns the name of the records' schema.
+    #[must_use]
+    pub fn schema_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the records' schema.
+    #[must_use]
+    pub const fn schema(&self) -> &SchemaType {
+        &self.schema
+    }
+
+    /// Reads the next encoded record, or `None` at the end of the container.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails, the container is truncated, or a
+    /// record is larger than the limit.
+    pub fn next_encoded(&mut self) -> Result<Option<Bytes>> {
+        read_framed(&mut self.reader, self.max_record_len)
+    }
+
+    /// Reads and decodes the next record, or returns `None` at the end of
+    /// the container.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails or the record is invalid for the
+    /// schema.
+    pub fn next_value(&mut self) -> Result<Option<Value>> {
+        let Some(record) = self.next_encoded()? else {
+            return Ok(None);
+        };
+        Decoder::decode_with_registry(&mut record.as_ref(), &self.schema, &self.registry).map(Some)
+    }
+}
+
+impl<R: Read + Seek> ContainerReader<R> {
+    /// Indexes the records from the current one to the end of the
+    /// container, recording the offset of every `interval`th of them, and
+    /// returns to the current record.
+    ///
+    /// Only the lengths of the records are read, the records are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `interval` is 0, seeking or reading fails, or the
+    /// container is truncated.
+    pub fn build_index(&mut self, interval: usize) -> Result<ContainerIndex> {
+        if interval == 0 {
+            return Err(
+                EncodeError::InvalidFormat("Index interval must not be 0".to_owned()).into(),
+            );
+        }
+        let start = self.reader.stream_position().map_err(DecodeError::Io)?;
+        let end = self
+            .reader
+            .seek(SeekFrom::End(0))
+            .map_err(DecodeError::Io)?;
+        self.reader
+            .seek(SeekFrom::Start(start))
+            .map_err(DecodeError::Io)?;
+
+        let mut index = ContainerIndex {
+            interval: interval as u64,
+            records: 0,
+            offsets: Vec::new(),
+            end: start,
+        };
+        let mut offset = start;
+        while let Some(len) = read_len(&mut self.reader)? {
+            if index.records % index.interval == 0 {
+                index.offsets.push(offset);
+            }
+            index.records += 1;
+            offset += 4 + u64::from(len);
+            if offset > end {
+                return Err(DecodeError::UnexpectedEof.into());
+            }
+            self.reader
+                .seek(SeekFrom::Start(offset))
+                .map_err(DecodeError::Io)?;
+        }
+        index.end = offset;
+        self.reader
+            .seek(SeekFrom::Start(start))
+            .map_err(DecodeError::Io)?;
+        Ok(index)
+    }
+
+    /// Moves to the record `record` of `index`, so that it is read next.
+    ///
+    /// Seeks to the closest indexed record before it and skips the records
+    /// in between. Moving to the record after the last indexed one moves to
+    /// the end of the indexed records.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `record` is past the indexed records, or seeking
+    /// or reading fails.
+    pub fn seek_to_record(&mut self, index: &ContainerIndex, record: u64) -> Result<()> {
+        if record > index.records {
+            return Err(DecodeError::InvalidData(format!(
+                "Record {record} is past the {} indexed records",
+                index.records
+            ))
+            .into());
+        }
+        if record == index.records {
+            self.reader
+                .seek(SeekFrom::Start(index.end))
+                .map_err(DecodeError::Io)?;
+            return Ok(());
+        }
+        let (block, skip) = (record / index.interval, record % index.interval);
+        let offset = usize::try_from(block)
+            .ok()
+            .and_then(|block| index.offsets.get(block))
+            .ok_or_else(|| DecodeError::InvalidData("Invalid container index".to_owned()))?;
+        self.reader
+            .seek(SeekFrom::Start(*offset))
+            .map_err(DecodeError::Io)?;
+        for _ in 0..skip {
+            self.skip_record()?;
+        }
+        Ok(())
+    }
+
+    fn skip_record(&mut self) -> Result<()> {
+        let len = read_len(&mut self.reader)?.ok_or(DecodeError::UnexpectedEof)?;
+        self.reader
+            .seek(SeekFrom::Current(i64::from(len)))
+            .map_err(DecodeError::Io)?;
+        Ok(())
+    }
+}
+
+/// Offsets of every Nth record of a container, for random access with
+/// [`ContainerReader::seek_to_record`].
+///
+/// Built by [`ContainerReader::build_index`] in one pass over the record
+/// lengths, and stored next to the container with
+/// [`to_bytes`](Self::to_bytes) so it needn't be built again. Readers of the
+/// same file can then scan disjoint ranges of records in parallel:
+///
+/// ```rust,ignore
+/// let index = ContainerReader::new(BufReader::new(File::open("events.cpk")?))?.build_index(1024)?;
+///
+/// let mut reader = ContainerReader::new(BufReader::new(File::open("events.cpk")?))?;
+/// reader.seek_to_record(&index, 500_000)?;
+/// for event in reader.take(1000) {
+///     handle(event?);
+/// }
+/// ```
+///
+/// Format of [`to_bytes`](Self::to_bytes):
+/// - u64 BE interval
+/// - u64 BE record count
+/// - u64 BE offset of the end of the last record
+/// - u64 BE offset of every `interval`th record
+///
+/// Offsets are from the start of the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerIndex {
+    interval: u64,
+    records: u64,
+    offsets: Vec<u64>,
+    end: u64,
+}
+
+impl ContainerIndex {
+    /// Returns the number of records between two indexed offsets.
+    #[must_use]
+    pub const fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// Returns the number of indexed records.
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.records
+    }
+
+    /// Returns whether the container had no records to index.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    /// Encodes the index.
+    #[must_use]
+    pub fn to_bytes(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(24 + 8 * self.offsets.len());
+        buf.put_u64(self.interval);
+        buf.put_u64(self.records);
+        buf.put_u64(self.end);
+        for &offset in &self.offsets {
+            buf.put_u64(offset);
+        }
+        buf.freeze()
+    }
+
+    /// Decodes an index encoded with [`to_bytes`](Self::to_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is truncated or inconsistent.
+    pub fn from_bytes(mut bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 24 {
+            return Err(DecodeError::UnexpectedEof.into());
+        }
+        let interval = bytes.get_u64();
+        let records = bytes.get_u64();
+        let end = bytes.get_u64();
+        if interval == 0 || bytes.len() % 8 != 0 {
+            return Err(DecodeError::InvalidData("Invalid container index".to_owned()).into());
+        }
+        let offsets: Vec<u64> = bytes.chunks_exact(8).map(|mut b| b.get_u64()).collect();
+        let expected = records / interval + u64::from(records % interval != 0);
+        if offsets.len() as u64 != expected {
+            return Err(DecodeError::InvalidData(format!(
+                "Container index of {records} records has {} offsets",
+                offsets.len()
+            ))
+            .into());
+        }
+        Ok(Self {
+            interval,
+            records,
+            offsets,
+            end,
+        })
+    }
+}
+
+impl<R: Read> Iterator for ContainerReader<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_value().transpose()
+    }
+}
+
+/// Reads a record preceded by its length as a big-endian `u32`, or `None`
+/// if the input ends before the length.
+pub(crate) fn read_framed(reader: &mut impl Read, max_len: usize) -> Result<Option<Bytes>> {
+    match read_len(reader)? {
+        Some(len) => read_record(reader, len, max_len).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Reads the length of the next record, or `None` at the end of the input.
+fn read_len(reader: &mut impl Read) -> Result<Option<u32>> {
+    let mut len = [0; 4];
+    let mut filled = 0;
+    while filled < len.len() {
+        match reader.read(&mut len[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(DecodeError::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(DecodeError::Io(e).into()),
+        }
+    }
+    Ok(Some(u32::from_be_bytes(len)))
+}
+
+/// Writes `record` preceded by its length as a big-endian `u32`.
+pub(crate) fn write_framed(writer: &mut impl Write, record: &[u8]) -> Result<()> {
+    let len = len_u32(record.len())?;
+    writer
+        .write_all(&len.to_be_bytes())
+        .and_then(|()| writer.write_all(record))
+        .map_err(|e| EncodeError::Io(e).into())
+}
+
+fn read_record(reader: &mut impl Read, len: u32, max_len: usize) -> Result<Bytes> {
+    let len = usize::try_from(len).unwrap_or(usize::MAX);
+    if len > max_len {
+        return Err(DecodeError::InvalidData(format!(
+            "Record of {len} bytes exceeds the limit of {max_len}"
+        ))
+        .into());
+    }
+    let mut record = vec![0; len];
+    read_exact(reader, &mut record)?;
+    Ok(record.into())
+}
+
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> Result<()> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => DecodeError::UnexpectedEof.into(),
+        _ => DecodeError::Io(e).into(),
+    })
+}
+
+fn len_u32(len: usize) -> Result<u32> {
+    u32::try_from(len)
+        .map_err(|_| EncodeError::InvalidFormat(format!("Record too large: {len} bytes")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::schema::Property;
+    use indexmap::IndexMap;
+
+    fn registry() -> SchemaRegistry {
+        let mut properties = IndexMap::new();
+        properties.insert("name".to_owned(), Property::required(SchemaType::string()));
+        properties.insert(
+            "tags".to_owned(),
+            Property::optional(SchemaType::array(SchemaType::string())),
+        );
+        let registry = SchemaRegistry::new()
+            .with_array_format(ArrayFormat::CountPrefixed)
+            .with_object_format(ObjectFormat::Bitmap)
+            .with_length_mode(LengthMode::Varint);
+        registry
+            .register("User", SchemaType::object(properties))
+            .unwrap();
+        registry
+    }
+
+    fn user(name: &str) -> Value {
+        let mut obj = IndexMap::new();
+        obj.insert("name".into(), Value::from(name));
+        obj.insert("tags".into(), Value::Array(vec![Value::from("x")]));
+        Value::Object(obj)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut writer = ContainerWriter::new(Vec::new(), &registry(), "User").unwrap();
+        writer.write(&user("Alice")).unwrap();
+        writer.write(&user("Bob")).unwrap();
+        let file = writer.finish().unwrap();
+        assert_eq!(&file[..6], b"CPKF\x01\x0b");
+
+        // Readers need nothing but the file
+        let reader = ContainerReader::new(file.as_slice()).unwrap();
+        assert_eq!(reader.schema_name(), "User");
+        assert_eq!(reader.registry().array_format(), ArrayFormat::CountPrefixed);
+        assert_eq!(reader.registry().object_format(), ObjectFormat::Bitmap);
+        assert_eq!(reader.registry().length_mode(), LengthMode::Varint);
+        let users: Vec<Value> = reader.collect::<Result<_>>().unwrap();
+        assert_eq!(users, [user("Alice"), user("Bob")]);
+    }
+
+    #[test]
+    fn test_invalid_containers() {
+        assert!(ContainerWriter::new(Vec::new(), &registry(), "Missing").is_err());
+        assert!(ContainerReader::new(&b"CPRB\x01\x00\x00\x00"[..]).is_err());
+
+        let mut writer = ContainerWriter::new(Vec::new(), &registry(), "User").unwrap();
+        writer.write(&user("Alice")).unwrap();
+        let file = writer.finish().unwrap();
+
+        let mut truncated = ContainerReader::new(&file[..file.len() - 1]).unwrap();
+        assert!(matches!(
+            truncated.next_value(),
+            Err(Error::Decode(DecodeError::UnexpectedEof))
+        ));
+
+        let Err(Error::Decode(DecodeError::InvalidData(msg))) =
+            ContainerReader::with_max_record_len(file.as_slice(), 8)
+        else {
+            panic!("oversized schema bundle accepted");
+        };
+        assert!(msg.contains("exceeds the limit"));
+    }
+
+    #[test]
+    fn test_index() {
+        let names: Vec<String> = (0..10).map(|i| format!("user{i}")).collect();
+        let mut writer = ContainerWriter::new(Vec::new(), &registry(), "User").unwrap();
+        for name in &names {
+            writer.write(&user(name)).unwrap();
+        }
+        let file = writer.finish().unwrap();
+
+        let mut reader = ContainerReader::new(io::Cursor::new(&file)).unwrap();
+        assert!(reader.build_index(0).is_err());
+        let index = reader.build_index(3).unwrap();
+        assert_eq!((index.interval(), index.len()), (3, 10));
+        // Building the index doesn't move the reader
+        assert_eq!(reader.next_value().unwrap(), Some(user("user0")));
+
+        let index = ContainerIndex::from_bytes(&index.to_bytes()).unwrap();
+        for record in [7, 0, 3, 9, 4] {
+            reader.seek_to_record(&index, record).unwrap();
+            let name = &names[usize::try_from(record).unwrap()];
+            assert_eq!(reader.next_value().unwrap(), Some(user(name)));
+        }
+        reader.seek_to_record(&index, 10).unwrap();
+        assert_eq!(reader.next_value().unwrap(), None);
+        assert!(reader.seek_to_record(&index, 11).is_err());
+
+        // An index of the remaining records, after reading one
+        let mut reader = ContainerReader::new(io::Cursor::new(&file)).unwrap();
+        reader.next_encoded().unwrap();
+        let rest = reader.build_index(4).unwrap();
+        assert_eq!(rest.len(), 9);
+        reader.seek_to_record(&rest, 0).unwrap();
+        assert_eq!(reader.next_value().unwrap(), Some(user("user1")));
+
+        let bytes = index.to_bytes();
+        assert!(ContainerIndex::from_bytes(&bytes[..bytes.len() - 8]).is_err());
+        let truncated = &file[..file.len() - 1];
+        let mut reader = ContainerReader::new(io::Cursor::new(truncated)).unwrap();
+        assert!(reader.build_index(3).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 //! Error types for the Compactr library.
 
+use crate::schema::SchemaId;
 use thiserror::Error;
 
 /// A specialized `Result` type for Compactr operations.
@@ -19,6 +20,11 @@ pub enum Error {
     /// Decoding error
     #[error(transparent)]
     Decode(#[from] DecodeError),
+
+    /// Error reported by a `Serialize` or `Deserialize` implementation
+    #[cfg(feature = "serde")]
+    #[error("{0}")]
+    Custom(String),
 }
 
 /// Errors that can occur during schema operations.
@@ -43,6 +49,23 @@ pub enum SchemaError {
     /// A required field is missing
     #[error("Missing required field: {0}")]
     MissingField(String),
+
+    /// A custom string format is not registered
+    #[error("Unknown format: {0}")]
+    UnknownFormat(String),
+
+    /// No schema is registered under a message's schema ID
+    #[error("Unknown schema: {0}")]
+    UnknownSchema(SchemaId),
+
+    /// Two registries or specs define a schema name differently
+    #[error("Conflicting definitions of schema: {0}")]
+    Conflict(String),
+
+    /// A schema registry request failed or was rejected
+    #[cfg(feature = "confluent")]
+    #[error("Schema registry error: {0}")]
+    Registry(String),
 }
 
 /// Errors that can occur during encoding.
@@ -112,4 +135,16 @@ pub enum DecodeError {
     /// String decoding error
     #[error("String error: {0}")]
     String(#[from] std::string::FromUtf8Error),
+
+    /// The input ends before the value does, and its headers tell that at
+    /// least this many more bytes are needed; reported instead of
+    /// [`DecodeError::UnexpectedEof`] by
+    /// [`Decoder::decode_counted`](crate::Decoder::decode_counted) and
+    /// [`Decoder::decode_exact`](crate::Decoder::decode_exact)
+    #[error("Unexpected end of input, at least {0} more bytes needed")]
+    Incomplete(usize),
+
+    /// Bytes left over after a value that should take the whole input
+    #[error("{0} trailing bytes after the value")]
+    TrailingBytes(usize),
 }
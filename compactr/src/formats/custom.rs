@@ -0,0 +1,221 @@
+//! User-defined string formats.
+//!
+//! Custom formats let applications plug domain-specific compact encodings
+//! into the schema machinery without forking the crate. A format is
+//! registered under a name in a [`FormatRegistry`], attached to a
+//! [`SchemaRegistry`](crate::SchemaRegistry), and referenced from schemas as
+//! [`StringFormat::Custom`](crate::StringFormat::Custom).
+//!
+//! ```
+//! use bytes::{Buf, BufMut};
+//! use compactr::formats::custom::{CustomFormat, FormatRegistry};
+//! use compactr::{DecodeError, Decoder, EncodeError, Encoder, SchemaRegistry, SchemaType, Value};
+//!
+//! // Stock tickers are at most 4 uppercase ASCII letters, packed into a u32
+//! let ticker = CustomFormat::new(
+//!     |value, buf| {
+//!         let s = value.as_str().ok_or(EncodeError::InvalidFormat("expected ticker".into()))?;
+//!         let mut bytes = [0u8; 4];
+//!         bytes[..s.len()].copy_from_slice(s.as_bytes());
+//!         buf.put_slice(&bytes);
+//!         Ok(())
+//!     },
+//!     |buf| {
+//!         if buf.remaining() < 4 {
+//!             return Err(DecodeError::UnexpectedEof);
+//!         }
+//!         let mut bytes = [0u8; 4];
+//!         buf.copy_to_slice(&mut bytes);
+//!         let len = bytes.iter().position(|&b| b == 0).unwrap_or(4);
+//!         Ok(Value::from(String::from_utf8_lossy(&bytes[..len]).into_owned()))
+//!     },
+//!     |s| Ok(Value::from(s)),
+//! );
+//!
+//! let formats = FormatRegistry::new();
+//! formats.register("ticker", ticker).unwrap();
+//! let registry = SchemaRegistry::new().with_formats(formats);
+//!
+//! let schema = SchemaType::custom("ticker");
+//! let mut encoder = Encoder::new();
+//! encoder.encode_with_registry(&Value::from("AAPL"), &schema, &registry).unwrap();
+//! let bytes = encoder.finish();
+//! assert_eq!(bytes.len(), 4);
+//!
+//! let decoded = Decoder::decode_with_registry(&mut &bytes[..], &schema, &registry).unwrap();
+//! assert_eq!(decoded, Value::from("AAPL"));
+//! ```
+
+use crate::error::{DecodeError, EncodeError, Result, SchemaError};
+use crate::value::Value;
+use bytes::{Buf, BytesMut};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Encodes a value into the buffer.
+pub type EncodeFn = dyn Fn(&Value, &mut BytesMut) -> Result<(), EncodeError> + Send + Sync;
+
+/// Decodes a value from the buffer.
+pub type DecodeFn = dyn Fn(&mut dyn Buf) -> Result<Value, DecodeError> + Send + Sync;
+
+/// Parses a value from its textual (JSON) representation.
+pub type ParseFn = dyn Fn(&str) -> Result<Value, EncodeError> + Send + Sync;
+
+/// A user-defined format made of encode, decode and parse functions.
+#[derive(Clone)]
+pub struct CustomFormat {
+    encode: Arc<EncodeFn>,
+    decode: Arc<DecodeFn>,
+    parse: Arc<ParseFn>,
+}
+
+impl CustomFormat {
+    /// Creates a custom format from its encode, decode and parse functions.
+    pub fn new(
+        encode: impl Fn(&Value, &mut BytesMut) -> Result<(), EncodeError> + Send + Sync + 'static,
+        decode: impl Fn(&mut dyn Buf) -> Result<Value, DecodeError> + Send + Sync + 'static,
+        parse: impl Fn(&str) -> Result<Value, EncodeError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            encode: Arc::new(encode),
+            decode: Arc::new(decode),
+            parse: Arc::new(parse),
+        }
+    }
+
+    /// Encodes a value using this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns whatever error the format's encode function produces.
+    pub fn encode(&self, value: &Value, buf: &mut BytesMut) -> Result<(), EncodeError> {
+        (self.encode)(value, buf)
+    }
+
+    /// Decodes a value using this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns whatever error the format's decode function produces.
+    pub fn decode(&self, buf: &mut dyn Buf) -> Result<Value, DecodeError> {
+        (self.decode)(buf)
+    }
+
+    /// Parses a value from a string using this format.
+    ///
+    /// # Errors
+    ///
+    /// Returns whatever error the format's parse function produces.
+    pub fn parse(&self, s: &str) -> Result<Value, EncodeError> {
+        (self.parse)(s)
+    }
+}
+
+impl fmt::Debug for CustomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomFormat").finish_non_exhaustive()
+    }
+}
+
+/// A thread-safe registry of named custom formats.
+#[derive(Debug, Clone, Default)]
+pub struct FormatRegistry {
+    formats: Arc<RwLock<HashMap<String, CustomFormat>>>,
+}
+
+impl FormatRegistry {
+    /// Creates a new empty format registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a custom format under the given name, replacing any
+    /// previous format with the same name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is poisoned (should not happen in normal usage).
+    pub fn register(&self, name: impl Into<String>, format: CustomFormat) -> Result<()> {
+        let mut formats = self
+            .formats
+            .write()
+            .map_err(|_| SchemaError::InvalidSchema("Failed to acquire write lock".to_owned()))?;
+        formats.insert(name.into(), format);
+        Ok(())
+    }
+
+    /// Retrieves a custom format by name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is poisoned.
+    pub fn get(&self, name: &str) -> Result<Option<CustomFormat>> {
+        let formats = self
+            .formats
+            .read()
+            .map_err(|_| SchemaError::InvalidSchema("Failed to acquire read lock".to_owned()))?;
+        Ok(formats.get(name).cloned())
+    }
+
+    /// Retrieves a custom format by name, failing if it is not registered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the format is unknown or the lock is poisoned.
+    pub fn resolve(&self, name: &str) -> Result<CustomFormat> {
+        self.get(name)?
+            .ok_or_else(|| SchemaError::UnknownFormat(name.to_owned()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BufMut;
+
+    fn byte_format() -> CustomFormat {
+        CustomFormat::new(
+            |value, buf| {
+                let n = value
+                    .as_i64()
+                    .and_then(|n| u8::try_from(n).ok())
+                    .ok_or_else(|| EncodeError::InvalidFormat("expected a byte".to_owned()))?;
+                buf.put_u8(n);
+                Ok(())
+            },
+            |buf| {
+                if !buf.has_remaining() {
+                    return Err(DecodeError::UnexpectedEof);
+                }
+                Ok(Value::Integer(i64::from(buf.get_u8())))
+            },
+            |s| {
+                s.parse::<i64>()
+                    .map(Value::Integer)
+                    .map_err(|e| EncodeError::InvalidFormat(e.to_string()))
+            },
+        )
+    }
+
+    #[test]
+    fn test_register_and_resolve() {
+        let registry = FormatRegistry::new();
+        registry.register("byte", byte_format()).unwrap();
+
+        let format = registry.resolve("byte").unwrap();
+        let mut buf = BytesMut::new();
+        format.encode(&Value::Integer(7), &mut buf).unwrap();
+        assert_eq!(buf.as_ref(), &[7]);
+        assert_eq!(format.decode(&mut buf).unwrap(), Value::Integer(7));
+        assert_eq!(format.parse("9").unwrap(), Value::Integer(9));
+    }
+
+    #[test]
+    fn test_unknown_format() {
+        let registry = FormatRegistry::new();
+        assert!(registry.get("missing").unwrap().is_none());
+        assert!(registry.resolve("missing").is_err());
+    }
+}
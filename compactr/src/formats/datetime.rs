@@ -1,8 +1,19 @@
-//! `DateTime` and `Date` format encoding and decoding.
+//! `DateTime`, `Date` and `Time` format encoding and decoding.
+//!
+//! The functions working on chrono types need the `chrono` feature.
+//! Without it, values of these formats are plain integers, and
+//! [`encode_datetime_millis`] and [`decode_datetime_millis`] convert the
+//! calendar layout of `DateTime` from and to milliseconds since the epoch.
 
 use crate::error::{DecodeError, EncodeError};
+#[cfg(feature = "chrono")]
+use crate::schema::TimestampPrecision;
 use bytes::{Buf, BufMut, BytesMut};
-use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Utc};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+
+/// Milliseconds in a day.
+pub(crate) const MILLIS_PER_DAY: i64 = 86_400_000;
 
 /// Encodes a `DateTime` in compactr.js format: 9 bytes (year, month, day, hour, minute, second, milliseconds).
 ///
@@ -18,6 +29,7 @@ use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Timelike, Utc};
 /// # Errors
 ///
 /// Returns an error if the datetime components are out of valid ranges.
+#[cfg(feature = "chrono")]
 pub fn encode_datetime(buf: &mut BytesMut, dt: &DateTime<Utc>) -> Result<(), EncodeError> {
     let year = dt.year();
     if !(0..=65535).contains(&year) {
@@ -52,6 +64,7 @@ pub fn encode_datetime(buf: &mut BytesMut, dt: &DateTime<Utc>) -> Result<(), Enc
 /// Returns an error if:
 /// - The buffer has insufficient data
 /// - The datetime components are invalid
+#[cfg(feature = "chrono")]
 pub fn decode_datetime(buf: &mut impl Buf) -> Result<DateTime<Utc>, DecodeError> {
     if buf.remaining() < 9 {
         return Err(DecodeError::UnexpectedEof);
@@ -75,6 +88,112 @@ pub fn decode_datetime(buf: &mut impl Buf) -> Result<DateTime<Utc>, DecodeError>
         })
 }
 
+/// Encodes a `DateTime` together with its UTC offset (11 bytes).
+///
+/// Format:
+/// - 9 bytes: the UTC instant, as in [`encode_datetime`]
+/// - 2 bytes: UTC offset in minutes (i16 big-endian)
+///
+/// # Errors
+///
+/// Returns an error if the datetime is out of range or the offset is not a
+/// whole number of minutes.
+#[cfg(feature = "chrono")]
+pub fn encode_datetime_tz(
+    buf: &mut BytesMut,
+    dt: &DateTime<FixedOffset>,
+) -> Result<(), EncodeError> {
+    let offset_secs = dt.offset().local_minus_utc();
+    if offset_secs % 60 != 0 {
+        return Err(EncodeError::InvalidFormat(format!(
+            "UTC offset must be a whole number of minutes: {}",
+            dt.offset()
+        )));
+    }
+
+    encode_datetime(buf, &dt.with_timezone(&Utc))?;
+    // |offset| < 24h, so the minute count always fits in an i16
+    #[allow(clippy::cast_possible_truncation)]
+    buf.put_i16((offset_secs / 60) as i16); // Big-endian
+    Ok(())
+}
+
+/// Decodes a `DateTime` with its original UTC offset (11 bytes).
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The buffer has insufficient data
+/// - The datetime components or the offset are invalid
+#[cfg(feature = "chrono")]
+pub fn decode_datetime_tz(buf: &mut impl Buf) -> Result<DateTime<FixedOffset>, DecodeError> {
+    let utc = decode_datetime(buf)?;
+    if buf.remaining() < 2 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let offset_minutes = buf.get_i16(); // Big-endian
+    FixedOffset::east_opt(i32::from(offset_minutes) * 60)
+        .map(|offset| utc.with_timezone(&offset))
+        .ok_or_else(|| {
+            DecodeError::InvalidData(format!("Invalid UTC offset: {offset_minutes} min"))
+        })
+}
+
+/// Encodes a `DateTime` as a count of `precision` units since the Unix epoch
+/// (8 bytes, i64 big-endian).
+///
+/// Sub-unit precision is truncated towards negative infinity.
+///
+/// # Errors
+///
+/// Returns an error if the datetime is out of range for nanosecond precision.
+#[cfg(feature = "chrono")]
+pub fn encode_timestamp(
+    buf: &mut BytesMut,
+    dt: &DateTime<Utc>,
+    precision: TimestampPrecision,
+) -> Result<(), EncodeError> {
+    let units = match precision {
+        TimestampPrecision::Seconds => dt.timestamp(),
+        TimestampPrecision::Millis => dt.timestamp_millis(),
+        TimestampPrecision::Micros => dt.timestamp_micros(),
+        TimestampPrecision::Nanos => dt.timestamp_nanos_opt().ok_or_else(|| {
+            EncodeError::InvalidFormat(format!(
+                "Datetime out of range for nanosecond precision: {dt}"
+            ))
+        })?,
+    };
+    buf.put_i64(units); // Big-endian
+    Ok(())
+}
+
+/// Decodes a `DateTime` from a count of `precision` units since the Unix epoch.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The buffer has insufficient data
+/// - The timestamp is out of the representable range
+#[cfg(feature = "chrono")]
+pub fn decode_timestamp(
+    buf: &mut impl Buf,
+    precision: TimestampPrecision,
+) -> Result<DateTime<Utc>, DecodeError> {
+    if buf.remaining() < 8 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let units = buf.get_i64(); // Big-endian
+    match precision {
+        TimestampPrecision::Seconds => DateTime::from_timestamp(units, 0),
+        TimestampPrecision::Millis => DateTime::from_timestamp_millis(units),
+        TimestampPrecision::Micros => DateTime::from_timestamp_micros(units),
+        TimestampPrecision::Nanos => Some(DateTime::from_timestamp_nanos(units)),
+    }
+    .ok_or_else(|| DecodeError::InvalidData(format!("Timestamp out of range: {units}")))
+}
+
 /// Encodes a `Date` as days since Unix epoch (4 bytes, i32 big-endian).
 ///
 /// # Errors
@@ -82,6 +201,7 @@ pub fn decode_datetime(buf: &mut impl Buf) -> Result<DateTime<Utc>, DecodeError>
 /// Returns an error if:
 /// - The epoch date cannot be created
 /// - The date is out of the representable range (beyond ±`i32::MAX` days from epoch)
+#[cfg(feature = "chrono")]
 pub fn encode_date(buf: &mut BytesMut, date: &NaiveDate) -> Result<(), EncodeError> {
     let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)
         .ok_or_else(|| EncodeError::InvalidFormat("Failed to create epoch date".to_owned()))?;
@@ -107,6 +227,7 @@ pub fn encode_date(buf: &mut BytesMut, date: &NaiveDate) -> Result<(), EncodeErr
 /// - The buffer has insufficient data
 /// - The epoch date cannot be created
 /// - The date offset is invalid or out of range
+#[cfg(feature = "chrono")]
 pub fn decode_date(buf: &mut impl Buf) -> Result<NaiveDate, DecodeError> {
     if buf.remaining() < 4 {
         return Err(DecodeError::UnexpectedEof);
@@ -122,39 +243,238 @@ pub fn decode_date(buf: &mut impl Buf) -> Result<NaiveDate, DecodeError> {
         .ok_or_else(|| DecodeError::InvalidData(format!("Invalid date offset: {days} days")))
 }
 
+/// Encodes a `Time` as milliseconds since midnight (4 bytes, u32 big-endian).
+///
+/// Sub-millisecond precision is truncated.
+///
+/// # Errors
+///
+/// This function currently does not return errors, but the signature uses `Result` for consistency.
+#[cfg(feature = "chrono")]
+pub fn encode_time(buf: &mut BytesMut, time: &NaiveTime) -> Result<(), EncodeError> {
+    // Leap seconds are represented with nanoseconds >= 1_000_000_000 and
+    // therefore map to 86_399_000..86_400_999
+    let millis = time.num_seconds_from_midnight() * 1000 + time.nanosecond() / 1_000_000;
+    buf.put_u32(millis); // Big-endian
+    Ok(())
+}
+
+/// Decodes a `Time` from milliseconds since midnight.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The buffer has insufficient data
+/// - The value exceeds the length of a day (including a leap second)
+#[cfg(feature = "chrono")]
+pub fn decode_time(buf: &mut impl Buf) -> Result<NaiveTime, DecodeError> {
+    if buf.remaining() < 4 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let millis = buf.get_u32(); // Big-endian
+    let secs = (millis / 1000).min(86_399);
+    (millis - secs * 1000)
+        .checked_mul(1_000_000)
+        .and_then(|nanos| NaiveTime::from_num_seconds_from_midnight_opt(secs, nanos))
+        .ok_or_else(|| DecodeError::InvalidData(format!("Invalid time: {millis} ms")))
+}
+
 /// Parses a `DateTime` from an ISO 8601 string.
 ///
 /// # Errors
 ///
 /// Returns an error if the string is not a valid ISO 8601 datetime.
+#[cfg(feature = "chrono")]
 pub fn parse_datetime(s: &str) -> Result<DateTime<Utc>, EncodeError> {
     s.parse::<DateTime<Utc>>()
         .map_err(|e| EncodeError::InvalidFormat(format!("Invalid datetime: {e}")))
 }
 
+/// Parses a `DateTime` from an RFC 3339 string, keeping its UTC offset.
+///
+/// # Errors
+///
+/// Returns an error if the string is not a valid RFC 3339 datetime.
+#[cfg(feature = "chrono")]
+pub fn parse_datetime_tz(s: &str) -> Result<DateTime<FixedOffset>, EncodeError> {
+    DateTime::parse_from_rfc3339(s)
+        .map_err(|e| EncodeError::InvalidFormat(format!("Invalid datetime: {e}")))
+}
+
 /// Parses a `Date` from an ISO 8601 date string (YYYY-MM-DD).
 ///
 /// # Errors
 ///
 /// Returns an error if the string is not a valid date in YYYY-MM-DD format.
+#[cfg(feature = "chrono")]
 pub fn parse_date(s: &str) -> Result<NaiveDate, EncodeError> {
     NaiveDate::parse_from_str(s, "%Y-%m-%d")
         .map_err(|e| EncodeError::InvalidFormat(format!("Invalid date: {e}")))
 }
 
+/// Parses a `Time` from an ISO 8601 time string (HH:MM:SS with optional fraction).
+///
+/// # Errors
+///
+/// Returns an error if the string is not a valid time.
+#[cfg(feature = "chrono")]
+pub fn parse_time(s: &str) -> Result<NaiveTime, EncodeError> {
+    s.parse::<NaiveTime>()
+        .map_err(|e| EncodeError::InvalidFormat(format!("Invalid time: {e}")))
+}
+
+/// Encodes milliseconds since the Unix epoch in the layout of
+/// [`encode_datetime`], without chrono.
+///
+/// # Errors
+///
+/// Returns an error if the year is out of range.
+pub fn encode_datetime_millis(buf: &mut BytesMut, millis: i64) -> Result<(), EncodeError> {
+    let (year, month, day) = civil_from_days(millis.div_euclid(MILLIS_PER_DAY));
+    let year = u16::try_from(year)
+        .map_err(|_| EncodeError::InvalidFormat(format!("Year out of range: {year}")))?;
+    let ms = millis.rem_euclid(MILLIS_PER_DAY);
+
+    buf.put_u16(year); // Big-endian
+    buf.put_u8(month);
+    buf.put_u8(day);
+    // The remainder of a day fits every component
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    {
+        buf.put_u8((ms / 3_600_000) as u8);
+        buf.put_u8((ms / 60_000 % 60) as u8);
+        buf.put_u8((ms / 1000 % 60) as u8);
+        buf.put_u16((ms % 1000) as u16); // Big-endian
+    }
+    Ok(())
+}
+
+/// Decodes a `DateTime` written by [`encode_datetime`] to milliseconds
+/// since the Unix epoch, without chrono.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The buffer has insufficient data
+/// - The datetime components are invalid
+pub fn decode_datetime_millis(buf: &mut impl Buf) -> Result<i64, DecodeError> {
+    if buf.remaining() < 9 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let year = buf.get_u16(); // Big-endian
+    let month = buf.get_u8();
+    let day = buf.get_u8();
+    let hour = buf.get_u8();
+    let minute = buf.get_u8();
+    let second = buf.get_u8();
+    let millis = buf.get_u16(); // Big-endian
+
+    let days = days_from_civil(i64::from(year), month, day);
+    // Dates that don't exist, such as February 30, come back different
+    if civil_from_days(days) != (i64::from(year), month, day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return Err(DecodeError::InvalidData(format!(
+            "Invalid datetime: {year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}.{millis:03}"
+        )));
+    }
+    let secs = (i64::from(hour) * 60 + i64::from(minute)) * 60 + i64::from(second);
+    Ok(days * MILLIS_PER_DAY + secs * 1000 + i64::from(millis))
+}
+
+/// Decodes a `DateTime` written by [`encode_datetime_tz`] to milliseconds
+/// since the Unix epoch and the UTC offset in minutes, without chrono.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The buffer has insufficient data
+/// - The datetime components or the offset are invalid
+pub fn decode_datetime_tz_millis(buf: &mut impl Buf) -> Result<(i64, i16), DecodeError> {
+    let millis = decode_datetime_millis(buf)?;
+    if buf.remaining() < 2 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let offset_minutes = buf.get_i16(); // Big-endian
+                                        // Offsets are less than a day
+    if offset_minutes.unsigned_abs() >= 24 * 60 {
+        return Err(DecodeError::InvalidData(format!(
+            "Invalid UTC offset: {offset_minutes} min"
+        )));
+    }
+    Ok((millis, offset_minutes))
+}
+
+/// Returns the days from the Unix epoch to a date of the proleptic
+/// Gregorian calendar.
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    let (month, day) = (i64::from(month), i64::from(day));
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Returns the year, month and day of a day since the Unix epoch, the
+/// inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    // Months and days are in 1..=12 and 1..=31
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    (year, month as u8, day as u8)
+}
+
 /// Returns the encoded size of a `DateTime` (always 9 bytes).
 #[must_use]
 pub const fn datetime_size() -> usize {
     9
 }
 
+/// Returns the encoded size of a `DateTime` with offset (always 11 bytes).
+#[must_use]
+pub const fn datetime_tz_size() -> usize {
+    11
+}
+
+/// Returns the encoded size of a timestamp (always 8 bytes).
+#[must_use]
+pub const fn timestamp_size() -> usize {
+    8
+}
+
 /// Returns the encoded size of a `Date` (always 4 bytes).
 #[must_use]
 pub const fn date_size() -> usize {
     4
 }
 
-#[cfg(test)]
+/// Returns the encoded size of a `Time` (always 4 bytes).
+#[must_use]
+pub const fn time_size() -> usize {
+    4
+}
+
+#[cfg(all(test, feature = "chrono"))]
 mod tests {
     use super::*;
 
@@ -171,6 +491,47 @@ mod tests {
         assert_eq!(decoded.timestamp_millis(), dt.timestamp_millis());
     }
 
+    #[test]
+    fn test_datetime_millis() {
+        for s in [
+            "1970-01-01T00:00:00Z",
+            "2024-02-29T23:59:59.999Z",
+            "1969-12-31T12:00:00.001Z",
+            "0000-03-01T00:00:00Z",
+            "9999-12-31T00:00:00Z",
+        ] {
+            let dt = parse_datetime(s).unwrap();
+            let mut buf = BytesMut::new();
+            encode_datetime_millis(&mut buf, dt.timestamp_millis()).unwrap();
+            let mut expected = BytesMut::new();
+            encode_datetime(&mut expected, &dt).unwrap();
+            assert_eq!(buf, expected, "{s}");
+            assert_eq!(
+                decode_datetime_millis(&mut buf.as_ref()).unwrap(),
+                dt.timestamp_millis()
+            );
+        }
+
+        let mut buf = BytesMut::new();
+        assert!(encode_datetime_millis(&mut buf, -62_167_219_200_001).is_err());
+        // February 30
+        let invalid = [0x07, 0xe8, 2, 30, 0, 0, 0, 0, 0];
+        assert!(decode_datetime_millis(&mut invalid.as_ref()).is_err());
+        assert!(decode_datetime(&mut invalid.as_ref()).is_err());
+
+        let dt = parse_datetime_tz("2024-06-01T08:00:00-05:30").unwrap();
+        let mut buf = BytesMut::new();
+        encode_datetime_tz(&mut buf, &dt).unwrap();
+        assert_eq!(
+            decode_datetime_tz_millis(&mut buf.as_ref()).unwrap(),
+            (dt.timestamp_millis(), -330)
+        );
+        let mut bad_offset = buf.to_vec();
+        bad_offset[9..].copy_from_slice(&1440_i16.to_be_bytes());
+        assert!(decode_datetime_tz_millis(&mut bad_offset.as_slice()).is_err());
+        assert!(decode_datetime_tz(&mut bad_offset.as_slice()).is_err());
+    }
+
     #[test]
     fn test_parse_datetime() {
         let dt_str = "2024-01-15T10:30:00Z";
@@ -200,6 +561,101 @@ mod tests {
         assert_eq!(decoded, epoch);
     }
 
+    #[test]
+    fn test_datetime_tz_preserves_offset() {
+        let mut buf = BytesMut::new();
+        let dt = parse_datetime_tz("2024-01-15T16:00:00.250+05:30").unwrap();
+
+        encode_datetime_tz(&mut buf, &dt).unwrap();
+        assert_eq!(buf.len(), datetime_tz_size());
+        assert_eq!(&buf[9..], &330i16.to_be_bytes());
+
+        let decoded = decode_datetime_tz(&mut buf).unwrap();
+        assert_eq!(decoded, dt);
+        assert_eq!(decoded.to_rfc3339(), "2024-01-15T16:00:00.250+05:30");
+    }
+
+    #[test]
+    fn test_datetime_tz_negative_offset() {
+        let mut buf = BytesMut::new();
+        let dt = parse_datetime_tz("2024-12-31T23:59:59-08:00").unwrap();
+
+        encode_datetime_tz(&mut buf, &dt).unwrap();
+        assert_eq!(
+            decode_datetime_tz(&mut buf).unwrap().to_rfc3339(),
+            "2024-12-31T23:59:59-08:00"
+        );
+    }
+
+    #[test]
+    fn test_timestamp_precisions() {
+        let dt = parse_datetime("2024-01-15T10:30:00.123456789Z").unwrap();
+        let cases = [
+            (TimestampPrecision::Seconds, "2024-01-15T10:30:00+00:00"),
+            (TimestampPrecision::Millis, "2024-01-15T10:30:00.123+00:00"),
+            (
+                TimestampPrecision::Micros,
+                "2024-01-15T10:30:00.123456+00:00",
+            ),
+            (
+                TimestampPrecision::Nanos,
+                "2024-01-15T10:30:00.123456789+00:00",
+            ),
+        ];
+
+        for (precision, expected) in cases {
+            let mut buf = BytesMut::new();
+            encode_timestamp(&mut buf, &dt, precision).unwrap();
+            assert_eq!(buf.len(), timestamp_size());
+
+            let decoded = decode_timestamp(&mut buf, precision).unwrap();
+            assert_eq!(decoded.to_rfc3339(), expected);
+        }
+    }
+
+    #[test]
+    fn test_timestamp_nanos_out_of_range() {
+        let mut buf = BytesMut::new();
+        let dt = parse_datetime("2300-01-01T00:00:00Z").unwrap();
+        assert!(encode_timestamp(&mut buf, &dt, TimestampPrecision::Nanos).is_err());
+        assert!(encode_timestamp(&mut buf, &dt, TimestampPrecision::Seconds).is_ok());
+    }
+
+    #[test]
+    fn test_time_roundtrip() {
+        let mut buf = BytesMut::new();
+        let time = NaiveTime::from_hms_milli_opt(13, 45, 30, 250).unwrap();
+
+        encode_time(&mut buf, &time).unwrap();
+        assert_eq!(buf.len(), time_size());
+        assert_eq!(buf.as_ref(), &49_530_250u32.to_be_bytes());
+
+        let decoded = decode_time(&mut buf).unwrap();
+        assert_eq!(decoded, time);
+    }
+
+    #[test]
+    fn test_time_leap_second() {
+        let mut buf = BytesMut::new();
+        let time = NaiveTime::from_hms_milli_opt(23, 59, 59, 1500).unwrap();
+
+        encode_time(&mut buf, &time).unwrap();
+        assert_eq!(decode_time(&mut buf).unwrap(), time);
+
+        // Past the leap second
+        for millis in [86_401_000, u32::MAX] {
+            let mut bytes = &millis.to_be_bytes()[..];
+            assert!(decode_time(&mut bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let time = parse_time("08:15:00").unwrap();
+        assert_eq!(time, NaiveTime::from_hms_opt(8, 15, 0).unwrap());
+        assert!(parse_time("25:00:00").is_err());
+    }
+
     #[test]
     fn test_parse_date() {
         let date_str = "2024-01-15";
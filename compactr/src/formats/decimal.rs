@@ -0,0 +1,148 @@
+//! Arbitrary-precision decimal format encoding and decoding.
+
+use crate::error::{DecodeError, EncodeError};
+use bytes::{Buf, BufMut, BytesMut};
+use rust_decimal::Decimal;
+
+/// Encodes a `Decimal` as a scaled integer.
+///
+/// Format:
+/// - 1 byte: scale (number of fractional digits, 0-28)
+/// - 1 byte: mantissa length in bytes (0-16)
+/// - N bytes: mantissa (minimal two's complement, big-endian)
+///
+/// The value is `mantissa / 10^scale`, so `12.34` is stored as scale 2 and
+/// mantissa 1234 in 4 bytes total.
+///
+/// # Errors
+///
+/// This function currently does not return errors, but the signature uses `Result` for consistency.
+pub fn encode_decimal(buf: &mut BytesMut, value: &Decimal) -> Result<(), EncodeError> {
+    let mantissa = value.mantissa().to_be_bytes();
+    let len = minimal_len(&mantissa);
+
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        buf.put_u8(value.scale() as u8);
+        buf.put_u8(len as u8);
+    }
+    buf.put_slice(&mantissa[mantissa.len() - len..]);
+    Ok(())
+}
+
+/// Decodes a `Decimal` from its scaled-integer form.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The buffer has insufficient data
+/// - The scale or mantissa is out of range
+pub fn decode_decimal(buf: &mut impl Buf) -> Result<Decimal, DecodeError> {
+    if buf.remaining() < 2 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let scale = u32::from(buf.get_u8());
+    let len = usize::from(buf.get_u8());
+    if len > 16 {
+        return Err(DecodeError::InvalidData(format!(
+            "Invalid decimal mantissa length: {len}"
+        )));
+    }
+    if buf.remaining() < len {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let mut bytes = [0u8; 16];
+    buf.copy_to_slice(&mut bytes[16 - len..]);
+    // Sign-extend the minimal two's complement representation
+    if len > 0 && bytes[16 - len] & 0x80 != 0 {
+        bytes[..16 - len].fill(0xFF);
+    }
+
+    Decimal::try_from_i128_with_scale(i128::from_be_bytes(bytes), scale)
+        .map_err(|e| DecodeError::InvalidData(format!("Invalid decimal: {e}")))
+}
+
+/// Parses a `Decimal` from a string such as `"-1234.5678"`.
+///
+/// # Errors
+///
+/// Returns an error if the string is not a valid decimal number.
+pub fn parse_decimal(s: &str) -> Result<Decimal, EncodeError> {
+    s.parse::<Decimal>()
+        .map_err(|e| EncodeError::InvalidFormat(format!("Invalid decimal: {e}")))
+}
+
+/// Returns the encoded size of a `Decimal` (2 bytes header + mantissa bytes).
+#[must_use]
+pub fn decimal_size(value: &Decimal) -> usize {
+    2 + minimal_len(&value.mantissa().to_be_bytes())
+}
+
+/// Number of bytes needed to represent a big-endian two's complement integer.
+fn minimal_len(bytes: &[u8; 16]) -> usize {
+    let mut start = 0;
+    while start < bytes.len() {
+        let redundant = match bytes[start] {
+            0x00 => bytes.get(start + 1).map_or(true, |b| b & 0x80 == 0),
+            0xFF => bytes.get(start + 1).is_some_and(|b| b & 0x80 != 0),
+            _ => false,
+        };
+        if !redundant {
+            break;
+        }
+        start += 1;
+    }
+    bytes.len() - start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimal_roundtrip() {
+        for s in [
+            "0",
+            "12.34",
+            "-12.34",
+            "0.0000001",
+            "-1",
+            "127",
+            "128",
+            "-128",
+        ] {
+            let mut buf = BytesMut::new();
+            let value = parse_decimal(s).unwrap();
+
+            encode_decimal(&mut buf, &value).unwrap();
+            assert_eq!(buf.len(), decimal_size(&value));
+
+            let decoded = decode_decimal(&mut buf).unwrap();
+            assert_eq!(decoded, value);
+            assert_eq!(decoded.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_decimal_wire_format() {
+        let mut buf = BytesMut::new();
+        encode_decimal(&mut buf, &parse_decimal("12.34").unwrap()).unwrap();
+        assert_eq!(buf.as_ref(), &[2, 2, 0x04, 0xD2]);
+    }
+
+    #[test]
+    fn test_decimal_extremes() {
+        for value in [Decimal::MAX, Decimal::MIN] {
+            let mut buf = BytesMut::new();
+            encode_decimal(&mut buf, &value).unwrap();
+            assert_eq!(decode_decimal(&mut buf).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_decimal() {
+        assert!(parse_decimal("12.3.4").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Geographic point format encoding and decoding (8 bytes fixed-point).
+
+use crate::error::{DecodeError, EncodeError};
+use bytes::{Buf, BufMut, BytesMut};
+use std::fmt;
+
+/// Fixed-point scale: 1e-7 degrees, roughly 1.1 cm at the equator.
+const SCALE: f64 = 10_000_000.0;
+
+/// A WGS 84 latitude/longitude pair in decimal degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    lat: f64,
+    lon: f64,
+}
+
+impl GeoPoint {
+    /// Creates a point, validating that latitude is within ±90 and longitude
+    /// within ±180 degrees.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either coordinate is out of range or not finite.
+    pub fn new(lat: f64, lon: f64) -> Result<Self, EncodeError> {
+        if !(-90.0..=90.0).contains(&lat) {
+            return Err(EncodeError::InvalidFormat(format!(
+                "Latitude out of range: {lat}"
+            )));
+        }
+        if !(-180.0..=180.0).contains(&lon) {
+            return Err(EncodeError::InvalidFormat(format!(
+                "Longitude out of range: {lon}"
+            )));
+        }
+        Ok(Self { lat, lon })
+    }
+
+    /// Returns the latitude in degrees.
+    #[must_use]
+    pub const fn lat(&self) -> f64 {
+        self.lat
+    }
+
+    /// Returns the longitude in degrees.
+    #[must_use]
+    pub const fn lon(&self) -> f64 {
+        self.lon
+    }
+}
+
+impl fmt::Display for GeoPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.lat, self.lon)
+    }
+}
+
+/// Encodes a point as two fixed-point coordinates (8 bytes).
+///
+/// Format:
+/// - 4 bytes: latitude × 10^7 (i32 big-endian)
+/// - 4 bytes: longitude × 10^7 (i32 big-endian)
+///
+/// Precision beyond 7 decimal places is rounded.
+///
+/// # Errors
+///
+/// This function currently does not return errors, but the signature uses `Result` for consistency.
+pub fn encode_geo_point(buf: &mut BytesMut, point: &GeoPoint) -> Result<(), EncodeError> {
+    // Validated ranges keep the scaled values within ±1.8e9, inside i32
+    #[allow(clippy::cast_possible_truncation)]
+    {
+        buf.put_i32((point.lat * SCALE).round() as i32); // Big-endian
+        buf.put_i32((point.lon * SCALE).round() as i32); // Big-endian
+    }
+    Ok(())
+}
+
+/// Decodes a point from two fixed-point coordinates.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The buffer has insufficient data (less than 8 bytes)
+/// - A coordinate is out of range
+pub fn decode_geo_point(buf: &mut impl Buf) -> Result<GeoPoint, DecodeError> {
+    if buf.remaining() < 8 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let lat = f64::from(buf.get_i32()) / SCALE; // Big-endian
+    let lon = f64::from(buf.get_i32()) / SCALE; // Big-endian
+
+    GeoPoint::new(lat, lon).map_err(|e| DecodeError::InvalidData(e.to_string()))
+}
+
+/// Parses a point from a `"lat,lon"` string such as `"48.8584,2.2945"`.
+///
+/// # Errors
+///
+/// Returns an error if the string is malformed or a coordinate is out of range.
+pub fn parse_geo_point(s: &str) -> Result<GeoPoint, EncodeError> {
+    let invalid = || EncodeError::InvalidFormat(format!("Invalid geo point: {s:?}"));
+
+    let (lat, lon) = s.split_once(',').ok_or_else(invalid)?;
+    let lat = lat.trim().parse().map_err(|_| invalid())?;
+    let lon = lon.trim().parse().map_err(|_| invalid())?;
+    GeoPoint::new(lat, lon)
+}
+
+/// Returns the encoded size of a point (always 8 bytes).
+#[must_use]
+pub const fn geo_point_size() -> usize {
+    8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geo_point_roundtrip() {
+        let mut buf = BytesMut::new();
+        let point = GeoPoint::new(48.858_370_1, 2.294_481_3).unwrap();
+
+        encode_geo_point(&mut buf, &point).unwrap();
+        assert_eq!(buf.len(), geo_point_size());
+
+        let decoded = decode_geo_point(&mut buf).unwrap();
+        assert_eq!(decoded, point);
+    }
+
+    #[test]
+    fn test_geo_point_extremes() {
+        for (lat, lon) in [(90.0, 180.0), (-90.0, -180.0), (0.0, 0.0)] {
+            let mut buf = BytesMut::new();
+            let point = GeoPoint::new(lat, lon).unwrap();
+
+            encode_geo_point(&mut buf, &point).unwrap();
+            assert_eq!(decode_geo_point(&mut buf).unwrap(), point);
+        }
+    }
+
+    #[test]
+    fn test_geo_point_validation() {
+        assert!(GeoPoint::new(90.1, 0.0).is_err());
+        assert!(GeoPoint::new(0.0, -180.5).is_err());
+        assert!(GeoPoint::new(f64::NAN, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_parse_geo_point() {
+        let point = parse_geo_point("48.8584, 2.2945").unwrap();
+        assert_eq!(point.to_string(), "48.8584,2.2945");
+        assert!(parse_geo_point("48.8584").is_err());
+        assert!(parse_geo_point("north,east").is_err());
+    }
+
+    #[test]
+    fn test_decode_out_of_range() {
+        let mut buf = BytesMut::new();
+        buf.put_i32(i32::MAX);
+        buf.put_i32(0);
+        assert!(decode_geo_point(&mut buf).is_err());
+    }
+}
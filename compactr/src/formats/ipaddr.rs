@@ -1,8 +1,8 @@
-//! IP address format encoding and decoding.
+//! IP address and socket address format encoding and decoding.
 
 use crate::error::{DecodeError, EncodeError};
 use bytes::{Buf, BufMut, BytesMut};
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
 
 /// Encodes an IPv4 address (4 bytes).
 ///
@@ -56,6 +56,70 @@ pub fn decode_ipv6(buf: &mut impl Buf) -> Result<Ipv6Addr, DecodeError> {
     Ok(Ipv6Addr::from(octets))
 }
 
+/// Encodes a socket address (7 bytes for IPv4, 19 bytes for IPv6).
+///
+/// Format:
+/// - 1 byte: address family (4 or 6)
+/// - 4 or 16 bytes: IP address
+/// - 2 bytes: port (u16 big-endian)
+///
+/// IPv6 flow info and scope ID are not preserved.
+///
+/// # Errors
+///
+/// This function currently does not return errors, but the signature uses `Result` for consistency.
+pub fn encode_socket_addr(buf: &mut BytesMut, addr: &SocketAddr) -> Result<(), EncodeError> {
+    match addr {
+        SocketAddr::V4(v4) => {
+            buf.put_u8(4);
+            encode_ipv4(buf, v4.ip())?;
+        }
+        SocketAddr::V6(v6) => {
+            buf.put_u8(6);
+            encode_ipv6(buf, v6.ip())?;
+        }
+    }
+    buf.put_u16(addr.port()); // Big-endian
+    Ok(())
+}
+
+/// Decodes a socket address.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The buffer has insufficient data
+/// - The address family byte is neither 4 nor 6
+pub fn decode_socket_addr(buf: &mut impl Buf) -> Result<SocketAddr, DecodeError> {
+    if !buf.has_remaining() {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let addr = match buf.get_u8() {
+        4 => {
+            let ip = decode_ipv4(buf)?;
+            SocketAddr::V4(SocketAddrV4::new(ip, decode_port(buf)?))
+        }
+        6 => {
+            let ip = decode_ipv6(buf)?;
+            SocketAddr::V6(SocketAddrV6::new(ip, decode_port(buf)?, 0, 0))
+        }
+        family => {
+            return Err(DecodeError::InvalidData(format!(
+                "Invalid socket address family: {family}"
+            )))
+        }
+    };
+    Ok(addr)
+}
+
+fn decode_port(buf: &mut impl Buf) -> Result<u16, DecodeError> {
+    if buf.remaining() < 2 {
+        return Err(DecodeError::UnexpectedEof);
+    }
+    Ok(buf.get_u16()) // Big-endian
+}
+
 /// Parses an IPv4 address from a string.
 ///
 /// # Errors
@@ -76,6 +140,16 @@ pub fn parse_ipv6(s: &str) -> Result<Ipv6Addr, EncodeError> {
         .map_err(|e| EncodeError::InvalidFormat(format!("Invalid IPv6 address: {e}")))
 }
 
+/// Parses a socket address such as `10.0.0.1:8080` or `[::1]:443`.
+///
+/// # Errors
+///
+/// Returns an error if the string is not a valid socket address.
+pub fn parse_socket_addr(s: &str) -> Result<SocketAddr, EncodeError> {
+    s.parse::<SocketAddr>()
+        .map_err(|e| EncodeError::InvalidFormat(format!("Invalid socket address: {e}")))
+}
+
 /// Returns the encoded size of an IPv4 address (always 4 bytes).
 #[must_use]
 pub const fn ipv4_size() -> usize {
@@ -88,6 +162,15 @@ pub const fn ipv6_size() -> usize {
     16
 }
 
+/// Returns the encoded size of a socket address (family + IP + port).
+#[must_use]
+pub const fn socket_addr_size(addr: &SocketAddr) -> usize {
+    match addr {
+        SocketAddr::V4(_) => 1 + ipv4_size() + 2,
+        SocketAddr::V6(_) => 1 + ipv6_size() + 2,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -130,6 +213,37 @@ mod tests {
         assert_eq!(addr, Ipv6Addr::new(0x2001, 0x0db8, 0, 0, 0, 0, 0, 1));
     }
 
+    #[test]
+    fn test_socket_addr_roundtrip() {
+        for s in ["10.0.0.1:8080", "[2001:db8::1]:443"] {
+            let mut buf = BytesMut::new();
+            let addr = parse_socket_addr(s).unwrap();
+
+            encode_socket_addr(&mut buf, &addr).unwrap();
+            assert_eq!(buf.len(), socket_addr_size(&addr));
+
+            let decoded = decode_socket_addr(&mut buf).unwrap();
+            assert_eq!(decoded, addr);
+            assert_eq!(decoded.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_socket_addr_wire_format() {
+        let mut buf = BytesMut::new();
+        let addr = parse_socket_addr("127.0.0.1:80").unwrap();
+
+        encode_socket_addr(&mut buf, &addr).unwrap();
+        assert_eq!(buf.as_ref(), &[4, 127, 0, 0, 1, 0, 80]);
+    }
+
+    #[test]
+    fn test_socket_addr_invalid_family() {
+        let mut buf = BytesMut::new();
+        buf.put_slice(&[5, 0, 0, 0, 0, 0, 0]);
+        assert!(decode_socket_addr(&mut buf).is_err());
+    }
+
     #[test]
     fn test_ipv6_localhost() {
         let mut buf = BytesMut::new();
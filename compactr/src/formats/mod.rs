@@ -1,6 +1,11 @@
 //! Format-specific encoding and decoding implementations.
 
 pub mod binary;
+pub mod custom;
 pub mod datetime;
+#[cfg(feature = "decimal")]
+pub mod decimal;
+pub mod geo;
 pub mod ipaddr;
+pub mod uri;
 pub mod uuid;
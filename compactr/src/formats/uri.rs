@@ -0,0 +1,137 @@
+//! URI format encoding and decoding.
+
+use crate::codec::buffer::{decode_string, encode_string, string_size};
+use crate::error::{DecodeError, EncodeError};
+use bytes::{Buf, BufMut, BytesMut};
+
+/// Common URI prefixes that are replaced by a single byte on the wire.
+///
+/// The position in this table (plus one) is the prefix code; code `0` means
+/// the URI is stored in full. Codes must never be reordered or reused.
+const SCHEME_TABLE: &[&str] = &[
+    "https://", "http://", "wss://", "ws://", "ftp://", "mailto:", "urn:", "file://",
+];
+
+/// Encodes a URI with its scheme compressed into a 1-byte prefix code.
+///
+/// Format:
+/// - 1 byte: prefix code (0 = none, otherwise index into the scheme table + 1)
+/// - remainder: the rest of the URI as a length-prefixed string
+///
+/// So `https://example.com` is stored as `0x01` followed by `example.com`.
+///
+/// # Errors
+///
+/// Returns an error if the string is not a valid URI or is too long.
+pub fn encode_uri(buf: &mut BytesMut, uri: &str) -> Result<(), EncodeError> {
+    validate_uri(uri)?;
+
+    let (code, rest) = split_scheme(uri);
+    buf.put_u8(code);
+    encode_string(buf, rest)
+}
+
+/// Decodes a URI, expanding its prefix code.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The buffer has insufficient data
+/// - The prefix code is unknown
+/// - The remainder is not valid UTF-8
+pub fn decode_uri(buf: &mut impl Buf) -> Result<String, DecodeError> {
+    if !buf.has_remaining() {
+        return Err(DecodeError::UnexpectedEof);
+    }
+
+    let code = buf.get_u8();
+    let rest = decode_string(buf)?;
+    match code {
+        0 => Ok(rest),
+        _ => SCHEME_TABLE
+            .get(usize::from(code) - 1)
+            .map(|prefix| format!("{prefix}{rest}"))
+            .ok_or_else(|| DecodeError::InvalidData(format!("Unknown URI prefix code: {code}"))),
+    }
+}
+
+/// Validates that a string is an absolute URI (RFC 3986).
+///
+/// The scheme must start with a letter followed by letters, digits, `+`,
+/// `-` or `.`, and the URI must not contain whitespace or control characters.
+///
+/// # Errors
+///
+/// Returns an error if the string is not a valid URI.
+pub fn validate_uri(s: &str) -> Result<(), EncodeError> {
+    let invalid = |reason: &str| EncodeError::InvalidFormat(format!("Invalid URI {s:?}: {reason}"));
+
+    let (scheme, _) = s.split_once(':').ok_or_else(|| invalid("missing scheme"))?;
+    let mut chars = scheme.chars();
+    if !chars.next().is_some_and(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid("scheme must start with a letter"));
+    }
+    if !chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) {
+        return Err(invalid("invalid character in scheme"));
+    }
+    if s.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(invalid("contains whitespace or control characters"));
+    }
+    Ok(())
+}
+
+/// Returns the encoded size of a URI.
+#[must_use]
+pub fn uri_size(uri: &str) -> usize {
+    1 + string_size(split_scheme(uri).1)
+}
+
+fn split_scheme(uri: &str) -> (u8, &str) {
+    SCHEME_TABLE
+        .iter()
+        .zip(1u8..)
+        .find_map(|(prefix, code)| uri.strip_prefix(prefix).map(|rest| (code, rest)))
+        .unwrap_or((0, uri))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uri_roundtrip() {
+        for uri in [
+            "https://example.com/path?q=1",
+            "http://localhost:8080",
+            "mailto:alice@example.com",
+            "s3://bucket/key",
+        ] {
+            let mut buf = BytesMut::new();
+            encode_uri(&mut buf, uri).unwrap();
+            assert_eq!(buf.len(), uri_size(uri));
+            assert_eq!(decode_uri(&mut buf).unwrap(), uri);
+        }
+    }
+
+    #[test]
+    fn test_uri_scheme_compression() {
+        let mut buf = BytesMut::new();
+        encode_uri(&mut buf, "https://a.io").unwrap();
+        assert_eq!(buf.as_ref(), &[0x01, 0x00, 0x04, b'a', b'.', b'i', b'o']);
+    }
+
+    #[test]
+    fn test_invalid_uri() {
+        assert!(validate_uri("example.com").is_err());
+        assert!(validate_uri("1http://x").is_err());
+        assert!(validate_uri("https://exa mple.com").is_err());
+    }
+
+    #[test]
+    fn test_unknown_prefix_code() {
+        let mut buf = BytesMut::new();
+        buf.put_u8(0xFF);
+        buf.put_u16(0);
+        assert!(decode_uri(&mut buf).is_err());
+    }
+}
@@ -1,7 +1,15 @@
 //! UUID format encoding and decoding (16 bytes compact).
+//!
+//! The functions working on [`Uuid`](::uuid::Uuid) need the `uuid` feature.
+//! Without it, UUIDs are their 16 raw bytes, which [`parse_uuid_bytes`]
+//! reads from the textual form.
 
-use crate::error::{DecodeError, EncodeError};
+#[cfg(feature = "uuid")]
+use crate::error::DecodeError;
+use crate::error::EncodeError;
+#[cfg(feature = "uuid")]
 use bytes::{Buf, BufMut, BytesMut};
+#[cfg(feature = "uuid")]
 use uuid::Uuid;
 
 /// Encodes a UUID in compact 16-byte format.
@@ -9,6 +17,7 @@ use uuid::Uuid;
 /// # Errors
 ///
 /// This function currently does not return errors, but the signature uses `Result` for consistency.
+#[cfg(feature = "uuid")]
 pub fn encode_uuid(buf: &mut BytesMut, uuid: &Uuid) -> Result<(), EncodeError> {
     buf.put_slice(uuid.as_bytes());
     Ok(())
@@ -19,6 +28,7 @@ pub fn encode_uuid(buf: &mut BytesMut, uuid: &Uuid) -> Result<(), EncodeError> {
 /// # Errors
 ///
 /// Returns an error if the buffer has insufficient data (less than 16 bytes).
+#[cfg(feature = "uuid")]
 pub fn decode_uuid(buf: &mut impl Buf) -> Result<Uuid, DecodeError> {
     if buf.remaining() < 16 {
         return Err(DecodeError::UnexpectedEof);
@@ -35,10 +45,40 @@ pub fn decode_uuid(buf: &mut impl Buf) -> Result<Uuid, DecodeError> {
 /// # Errors
 ///
 /// Returns an error if the string is not a valid UUID format.
+#[cfg(feature = "uuid")]
 pub fn parse_uuid(s: &str) -> Result<Uuid, EncodeError> {
     Uuid::parse_str(s).map_err(|e| EncodeError::InvalidFormat(format!("Invalid UUID: {e}")))
 }
 
+/// Parses a UUID in the hyphenated or simple hex form into its bytes,
+/// without the uuid crate.
+///
+/// # Errors
+///
+/// Returns an error if the string is not a valid UUID.
+pub fn parse_uuid_bytes(s: &str) -> Result<[u8; 16], EncodeError> {
+    const HYPHENS: [usize; 4] = [8, 13, 18, 23];
+    let invalid = || EncodeError::InvalidFormat(format!("Invalid UUID: {s}"));
+    let hyphenated = s.len() == 36 && HYPHENS.iter().all(|&i| s.as_bytes()[i] == b'-');
+    if !hyphenated && s.len() != 32 {
+        return Err(invalid());
+    }
+
+    let mut digits = s
+        .chars()
+        .enumerate()
+        .filter(|(i, _)| !hyphenated || !HYPHENS.contains(i))
+        .map(|(_, c)| c.to_digit(16));
+    let mut bytes = [0; 16];
+    for byte in &mut bytes {
+        let (Some(Some(high)), Some(Some(low))) = (digits.next(), digits.next()) else {
+            return Err(invalid());
+        };
+        *byte = u8::try_from(high << 4 | low).map_err(|_| invalid())?;
+    }
+    Ok(bytes)
+}
+
 /// Returns the encoded size of a UUID (always 16 bytes).
 #[must_use]
 pub const fn uuid_size() -> usize {
@@ -49,6 +89,24 @@ pub const fn uuid_size() -> usize {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_uuid_bytes() {
+        let bytes = parse_uuid_bytes("550e8400-e29b-41d4-a716-446655440000").unwrap();
+        assert_eq!(bytes[..4], [0x55, 0x0e, 0x84, 0x00]);
+        assert_eq!(
+            parse_uuid_bytes("550E8400E29B41D4A716446655440000").unwrap(),
+            bytes
+        );
+        for invalid in [
+            "",
+            "550e8400-e29b-41d4-a716-44665544000g",
+            "550e8400e29b-41d4-a716-4466554400000",
+        ] {
+            assert!(parse_uuid_bytes(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[cfg(feature = "uuid")]
     #[test]
     fn test_uuid_roundtrip() {
         let mut buf = BytesMut::new();
@@ -61,13 +119,16 @@ mod tests {
         assert_eq!(decoded, uuid);
     }
 
+    #[cfg(feature = "uuid")]
     #[test]
     fn test_parse_uuid() {
         let uuid_str = "550e8400-e29b-41d4-a716-446655440000";
         let uuid = parse_uuid(uuid_str).unwrap();
         assert_eq!(uuid.to_string(), uuid_str);
+        assert_eq!(parse_uuid_bytes(uuid_str).unwrap(), *uuid.as_bytes());
     }
 
+    #[cfg(feature = "uuid")]
     #[test]
     fn test_parse_invalid_uuid() {
         let result = parse_uuid("not-a-uuid");
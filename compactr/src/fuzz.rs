@@ -0,0 +1,356 @@
+//! [`Arbitrary`] implementations for fuzzing.
+//!
+//! [`SchemaType`] and [`Value`] are generated independently, which exercises
+//! the error paths of the encoder, while [`TypedValue`] pairs a schema with
+//! a value that encodes with it, for round-trip targets:
+//!
+//! ```rust,ignore
+//! libfuzzer_sys::fuzz_target!(|input: compactr::fuzz::TypedValue| {
+//!     let mut encoder = compactr::Encoder::new();
+//!     encoder.encode(&input.value, &input.schema).unwrap();
+//!     let bytes = encoder.finish();
+//!     let decoded = compactr::Decoder::decode(&mut bytes.as_ref(), &input.schema).unwrap();
+//!     assert_eq!(decoded, input.value);
+//! });
+//! ```
+//!
+//! Generated schemas have no references or custom formats, so they need no
+//! registry beyond the wire [`Layout`] to exercise.
+
+use crate::codec::{ArrayFormat, DecoderOptions, LengthMode, ObjectFormat};
+use crate::formats::geo::GeoPoint;
+use crate::schema::{
+    IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType, SizeWidth, StringFormat,
+};
+use crate::value::Value;
+use arbitrary::{Arbitrary, Result, Unstructured};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset, NaiveTime, Utc};
+use indexmap::IndexMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+/// Deepest nesting of generated schemas and values.
+const MAX_DEPTH: usize = 4;
+
+/// Milliseconds from the Unix epoch to the bounds of [`DateTime`], about
+/// 262,000 years.
+#[cfg(feature = "chrono")]
+const MAX_MILLIS: i64 = 8_210_266_876_799_999;
+
+/// Most elements, properties or variants of a generated compound.
+const MAX_LEN: usize = 8;
+
+/// A schema and a value that encodes with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedValue {
+    /// The schema
+    pub schema: SchemaType,
+    /// A value matching the schema
+    pub value: Value,
+}
+
+impl<'a> Arbitrary<'a> for TypedValue {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let schema = SchemaType::arbitrary(u)?;
+        // Schemas whose required properties can't fit their size headers
+        // have no values
+        let value = crate::mock::generate(&schema, u.arbitrary()?)
+            .map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        Ok(Self { schema, value })
+    }
+}
+
+/// A combination of the wire layouts a registry can be configured with.
+///
+/// Values of a [`TypedValue`] encode in every layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// How array elements are delimited
+    pub array_format: ArrayFormat,
+    /// How present properties are announced
+    pub object_format: ObjectFormat,
+    /// How lengths are written
+    pub length_mode: LengthMode,
+}
+
+impl Layout {
+    /// Returns a registry with this layout and the
+    /// [untrusted](DecoderOptions::untrusted) decoder limits.
+    #[must_use]
+    pub fn registry(&self) -> SchemaRegistry {
+        SchemaRegistry::new()
+            .with_array_format(self.array_format)
+            .with_object_format(self.object_format)
+            .with_length_mode(self.length_mode)
+            .with_decoder_options(DecoderOptions::untrusted())
+    }
+}
+
+impl<'a> Arbitrary<'a> for Layout {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Self {
+            array_format: *u.choose(&[
+                ArrayFormat::SizePrefixed,
+                ArrayFormat::CountPrefixed,
+                ArrayFormat::VarintSizePrefixed,
+            ])?,
+            object_format: *u.choose(&[ObjectFormat::Indexed, ObjectFormat::Bitmap])?,
+            length_mode: *u.choose(&[LengthMode::Fixed, LengthMode::Varint])?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for SchemaType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        schema(u, 0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        value(u, 0)
+    }
+}
+
+fn schema(u: &mut Unstructured<'_>, depth: usize) -> Result<SchemaType> {
+    let kinds = if depth < MAX_DEPTH { 10 } else { 6 };
+    Ok(match u.int_in_range(0..=kinds - 1)? {
+        0 => SchemaType::Boolean,
+        1 => SchemaType::Integer(*u.choose(IntegerFormat::ALL)?),
+        2 => SchemaType::Number(*u.choose(NumberFormat::ALL)?),
+        3 => SchemaType::String(*u.choose(StringFormat::BUILTIN)?),
+        4 => SchemaType::Enum(names(u)?),
+        5 => SchemaType::Null,
+        6 => SchemaType::Array(Box::new(schema(u, depth + 1)?)),
+        7 => SchemaType::RunLengthArray(Box::new(schema(u, depth + 1)?)),
+        8 => object(u, depth, None)?,
+        _ => {
+            let discriminator = String::arbitrary(u)?;
+            let mut variants = IndexMap::new();
+            for name in names(u)? {
+                let payload = if u.arbitrary()? {
+                    object(u, depth, Some(&discriminator))?
+                } else {
+                    SchemaType::Null
+                };
+                variants.insert(name, payload);
+            }
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            }
+        }
+    })
+}
+
+/// Generates an object schema, without a property named `discriminator`.
+fn object(
+    u: &mut Unstructured<'_>,
+    depth: usize,
+    discriminator: Option<&str>,
+) -> Result<SchemaType> {
+    let mut properties = IndexMap::new();
+    for _ in 0..u.int_in_range(0..=MAX_LEN)? {
+        let name = String::arbitrary(u)?;
+        let mut property = Property::optional(schema(u, depth + 1)?)
+            .with_size_width(*u.choose(SizeWidth::ALL)?)
+            .with_sensitive(u.arbitrary()?);
+        property.required = u.arbitrary()?;
+        if discriminator != Some(name.as_str()) {
+            properties.insert(name, property);
+        }
+    }
+    Ok(SchemaType::Object(properties))
+}
+
+/// Generates distinct variant names, at least one.
+fn names(u: &mut Unstructured<'_>) -> Result<Vec<String>> {
+    let mut names = vec![String::arbitrary(u)?];
+    for _ in 1..u.int_in_range(1..=MAX_LEN)? {
+        let name = String::arbitrary(u)?;
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+fn value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    let kinds = if depth < MAX_DEPTH { 21 } else { 19 };
+    Ok(match u.int_in_range(0..=kinds - 1)? {
+        0 => Value::Boolean(u.arbitrary()?),
+        1 => Value::Integer(u.arbitrary()?),
+        2 => Value::Int128(u.arbitrary()?),
+        3 => Value::Uint128(u.arbitrary()?),
+        4 => Value::Float(u.arbitrary()?),
+        5 => Value::Double(u.arbitrary()?),
+        #[cfg(feature = "decimal")]
+        6 => Value::Decimal(rust_decimal::Decimal::new(
+            u.arbitrary()?,
+            u.int_in_range(0..=28)?,
+        )),
+        // Decimals need the feature
+        #[cfg(not(feature = "decimal"))]
+        6 => Value::Null,
+        7 => Value::String(u.arbitrary()?),
+        #[cfg(feature = "uuid")]
+        8 => Value::Uuid(Uuid::from_u128(u.arbitrary()?)),
+        // UUIDs and dates need their features
+        #[cfg(not(feature = "uuid"))]
+        8 => Value::Null,
+        #[cfg(not(feature = "chrono"))]
+        9..=12 => Value::Null,
+        #[cfg(feature = "chrono")]
+        9 => Value::DateTime(instant(u)?),
+        #[cfg(feature = "chrono")]
+        10 => {
+            let offset = FixedOffset::east_opt(u.int_in_range(-86_399..=86_399)?)
+                .unwrap_or_else(|| unreachable!());
+            Value::DateTimeTz(instant(u)?.with_timezone(&offset))
+        }
+        #[cfg(feature = "chrono")]
+        11 => Value::Date(instant(u)?.date_naive()),
+        #[cfg(feature = "chrono")]
+        12 => Value::Time(
+            NaiveTime::from_num_seconds_from_midnight_opt(
+                u.int_in_range(0..=86_399)?,
+                u.int_in_range(0..=1_999_999_999)?,
+            )
+            .unwrap_or_default(),
+        ),
+        13 => {
+            let lat = f64::from(u.int_in_range(-900_000_000..=900_000_000)?) / 1e7;
+            let lon = f64::from(u.int_in_range(-1_800_000_000..=1_800_000_000)?) / 1e7;
+            Value::GeoPoint(GeoPoint::new(lat, lon).map_err(|_| arbitrary::Error::IncorrectFormat)?)
+        }
+        14 => Value::Ipv4(Ipv4Addr::from(u32::arbitrary(u)?)),
+        15 => Value::Ipv6(Ipv6Addr::from(u128::arbitrary(u)?)),
+        16 => {
+            let ip = if u.arbitrary()? {
+                IpAddr::V4(Ipv4Addr::from(u32::arbitrary(u)?))
+            } else {
+                IpAddr::V6(Ipv6Addr::from(u128::arbitrary(u)?))
+            };
+            Value::SocketAddr(SocketAddr::new(ip, u.arbitrary()?))
+        }
+        17 => Value::Binary(u.arbitrary()?),
+        18 => Value::Null,
+        19 => Value::Array(
+            (0..u.int_in_range(0..=MAX_LEN)?)
+                .map(|_| value(u, depth + 1))
+                .collect::<Result<_>>()?,
+        ),
+        _ => Value::Object(
+            (0..u.int_in_range(0..=MAX_LEN)?)
+                .map(|_| Ok((String::arbitrary(u)?.into(), value(u, depth + 1)?)))
+                .collect::<Result<_>>()?,
+        ),
+    })
+}
+
+/// Generates an instant within the range of [`DateTime`], in milliseconds.
+#[cfg(feature = "chrono")]
+fn instant(u: &mut Unstructured<'_>) -> Result<DateTime<Utc>> {
+    let millis = u.int_in_range(-MAX_MILLIS..=MAX_MILLIS)?;
+    Ok(DateTime::from_timestamp_millis(millis).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Decoder, Encoder, StreamingDecoder};
+
+    /// Returns pseudo-random bytes to drive [`Unstructured`] with.
+    fn entropy(seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..512)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                state.to_be_bytes()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_arbitrary() {
+        let mut typed = 0;
+        for seed in 0..200 {
+            let bytes = entropy(seed);
+
+            // Unrelated schemas and values fail to encode but never panic
+            let mut u = Unstructured::new(&bytes);
+            let (schema, value) = (SchemaType::arbitrary(&mut u), Value::arbitrary(&mut u));
+            if let (Ok(schema), Ok(value)) = (schema, value) {
+                let _ = Encoder::new().encode(&value, &schema);
+            }
+
+            let mut u = Unstructured::new(&bytes);
+            let (Ok(input), Ok(layout)) =
+                (TypedValue::arbitrary(&mut u), Layout::arbitrary(&mut u))
+            else {
+                continue;
+            };
+            typed += 1;
+            let registry = layout.registry();
+            let mut encoder = Encoder::new();
+            encoder
+                .encode_with_registry(&input.value, &input.schema, &registry)
+                .unwrap();
+            let bytes = encoder.finish();
+            let decoded =
+                Decoder::decode_with_registry(&mut bytes.as_ref(), &input.schema, &registry)
+                    .unwrap();
+            assert_eq!(decoded, input.value, "{:?} {layout:?}", input.schema);
+
+            // Truncated encodings fail or decode to less, but never panic
+            for end in 0..bytes.len() {
+                let mut truncated = &bytes[..end];
+                let _ = Decoder::decode_with_registry(&mut truncated, &input.schema, &registry);
+            }
+        }
+        assert!(typed > 100);
+    }
+
+    /// Decodes `bytes` in chunks as the `decode` target does, checking that
+    /// every value taken from the stream consumes input.
+    fn decode_streaming(schema: SchemaType, layout: Layout, bytes: &[u8]) {
+        let Ok(mut decoder) = StreamingDecoder::with_registry(schema, layout.registry()) else {
+            return;
+        };
+        for chunk in bytes.chunks(7) {
+            decoder.feed(chunk);
+            loop {
+                let buffered = decoder.buffered();
+                match decoder.next_value() {
+                    Ok(Some(_)) => assert!(decoder.buffered() < buffered),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_streaming_arbitrary() {
+        // Empty bitmap objects take no bytes
+        let layout = Layout {
+            array_format: ArrayFormat::CountPrefixed,
+            object_format: ObjectFormat::Bitmap,
+            length_mode: LengthMode::Fixed,
+        };
+        decode_streaming(SchemaType::Object(IndexMap::new()), layout, &[72]);
+
+        for seed in 0..200 {
+            let bytes = entropy(seed);
+            let mut u = Unstructured::new(&bytes);
+            if let (Ok(schema), Ok(layout)) =
+                (SchemaType::arbitrary(&mut u), Layout::arbitrary(&mut u))
+            {
+                decode_streaming(schema, layout, u.take_rest());
+            }
+        }
+    }
+}
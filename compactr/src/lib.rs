@@ -12,12 +12,16 @@
 //! - Zero-copy deserialization where possible
 //! - Support for complex types (arrays, objects, references)
 //! - Built-in formats: `UUID`, `DateTime`, `Date`, `IPv4`, `IPv6`, `Binary`
+//! - `chrono` and `uuid` dependencies behind default features; without
+//!   them, dates and times decode to integers and UUIDs to 16-byte binaries
 //! - Thread-safe schema registry
 //!
 //! ## Quick Start
 //!
-//! ```rust,ignore
-//! use compactr::{Schema, Value, SchemaType};
+//! ```rust
+//! # #[cfg(feature = "uuid")]
+//! # fn main() -> Result<(), compactr::error::Error> {
+//! use compactr::{Decoder, Encoder, SchemaType, Value};
 //!
 //! // Define a schema
 //! let schema = SchemaType::object([
@@ -27,14 +31,21 @@
 //!
 //! // Encode data
 //! let data = Value::object([
-//!     ("id", Value::uuid("550e8400-e29b-41d4-a716-446655440000")),
+//!     ("id", Value::uuid("550e8400-e29b-41d4-a716-446655440000")?),
 //!     ("name", Value::string("Alice")),
 //! ]);
 //!
-//! let encoded = schema.encode(&data)?;
+//! let mut encoder = Encoder::new();
+//! encoder.encode(&data, &schema)?;
+//! let encoded = encoder.finish();
 //!
 //! // Decode data
-//! let decoded = schema.decode(&encoded)?;
+//! let decoded = Decoder::decode(&mut encoded.as_ref(), &schema)?;
+//! assert_eq!(decoded, data);
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "uuid"))]
+//! # fn main() {}
 //! ```
 
 #![warn(missing_docs)]
@@ -43,24 +54,108 @@
 #![allow(clippy::module_name_repetitions)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub mod batch;
 pub mod codec;
+#[cfg(feature = "serde")]
+pub mod compat;
+#[cfg(feature = "confluent")]
+pub mod confluent;
+pub mod container;
+#[cfg(feature = "crypto")]
+pub mod crypto;
 pub mod error;
 pub mod formats;
+#[cfg(feature = "arbitrary")]
+pub mod fuzz;
+pub mod message;
+pub mod metrics;
+pub mod migrate;
+pub mod mock;
+#[cfg(feature = "rayon")]
+pub mod parallel;
+pub mod plugin;
+pub mod pool;
+pub mod rpc;
 pub mod schema;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "ed25519")]
+pub mod signing;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+#[cfg(feature = "serde")]
+pub mod testvectors;
+#[cfg(feature = "serde")]
+pub mod transcode;
 pub mod value;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod ws;
 
 // Re-export commonly used types
-pub use codec::{Decode, Decoder, Encode, Encoder};
+pub use codec::{
+    ArrayFormat, Decode, DecodeBorrowed, DecodeMachine, Decoded, Decoder, DecoderOptions, Encode,
+    Encoder, EncoderOptions, FieldFilter, LengthMode, ObjectFormat, ObjectParts, SizeReport,
+    StreamingDecoder,
+};
 pub use error::{DecodeError, EncodeError, Result, SchemaError};
-pub use schema::{IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType, StringFormat};
+pub use formats::custom::{CustomFormat, FormatRegistry};
+pub use formats::geo::GeoPoint;
+pub use message::Message;
+pub use pool::{encode_pooled, encode_pooled_with_registry};
+pub use schema::{
+    IntegerFormat, NumberFormat, Projection, Property, Schema, SchemaId, SchemaRegistry,
+    SchemaStore, SchemaType, SizeWidth, StringFormat, TimestampPrecision,
+};
 pub use value::Value;
 
+#[cfg(feature = "derive")]
+pub use compactr_derive::Compactr;
+
+/// Re-exports used by code generated with `#[derive(Compactr)]`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    use crate::error::{DecodeError, Error, SchemaError};
+
+    pub use crate::codec::flatten::{flattened_properties, FlatField, Flatten, FlattenDecode};
+    pub use crate::codec::with::{decode_with, EncodeWith};
+    pub use bytes::{Buf, Bytes, BytesMut};
+    pub use indexmap::IndexMap;
+
+    #[must_use]
+    pub fn missing_payload(variant: &str) -> Error {
+        SchemaError::InvalidSchema(format!("variant {variant} has no payload schema")).into()
+    }
+
+    #[must_use]
+    pub fn unknown_variant(variant: &str) -> Error {
+        DecodeError::SchemaMismatch(format!("unknown variant: {variant}")).into()
+    }
+
+    /// Switches an array schema to its run-length encoded layout, for
+    /// `#[compactr(run_length)]` fields; other schemas are left as they are.
+    #[must_use]
+    pub fn run_length(schema: crate::schema::SchemaType) -> crate::schema::SchemaType {
+        match schema {
+            crate::schema::SchemaType::Array(items) => {
+                crate::schema::SchemaType::RunLengthArray(items)
+            }
+            schema => schema,
+        }
+    }
+}
+
 /// Prelude module for convenient imports
 pub mod prelude {
-    pub use crate::codec::{Decode, Decoder, Encode, Encoder};
+    pub use crate::codec::{Decode, DecodeBorrowed, Decoder, Encode, Encoder};
     pub use crate::error::{DecodeError, EncodeError, Result, SchemaError};
+    pub use crate::formats::geo::GeoPoint;
+    pub use crate::message::Message;
     pub use crate::schema::{
-        IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType, StringFormat,
+        IntegerFormat, NumberFormat, Property, Schema, SchemaId, SchemaRegistry, SchemaType,
+        StringFormat, TimestampPrecision,
     };
     pub use crate::value::Value;
+    #[cfg(feature = "derive")]
+    pub use compactr_derive::Compactr;
 }
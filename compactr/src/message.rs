@@ -0,0 +1,213 @@
+//! Self-identifying messages carrying the ID of their schema.
+//!
+//! A [`Message`] prefixes the encoded payload with the [`SchemaId`] of the
+//! schema it was encoded with, so consumers of streams mixing several
+//! schemas (Kafka topics, event buses, ...) can decode each message by
+//! looking the schema up in a [`SchemaRegistry`], without out-of-band
+//! coordination.
+//!
+//! Format:
+//! - 1 byte: ID kind (`0x00` numeric ID, `0x01` fingerprint)
+//! - 4 bytes (u32 BE) numeric ID, or 8 bytes (u64 BE) fingerprint
+//! - the payload, encoded with the identified schema
+
+use crate::codec::{Decoder, Encoder};
+use crate::error::{DecodeError, Result, SchemaError};
+use crate::schema::{SchemaId, SchemaRegistry};
+use crate::value::Value;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+const KIND_ID: u8 = 0x00;
+const KIND_FINGERPRINT: u8 = 0x01;
+
+/// A value together with the name of the registered schema describing it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    schema: String,
+    value: Value,
+}
+
+impl Message {
+    /// Creates a message holding `value`, described by the schema
+    /// registered under `schema`.
+    #[must_use]
+    pub fn new(schema: impl Into<String>, value: Value) -> Self {
+        Self {
+            schema: schema.into(),
+            value,
+        }
+    }
+
+    /// Returns the name of the schema describing the value.
+    #[must_use]
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    /// Returns the value.
+    #[must_use]
+    pub const fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Consumes the message, returning the value.
+    #[must_use]
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Encodes the message, prefixed with the schema's numeric ID if one was
+    /// assigned with [`SchemaRegistry::register_id`], or its fingerprint
+    /// otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not registered or the value doesn't
+    /// match it.
+    pub fn encode(&self, registry: &SchemaRegistry) -> Result<Bytes> {
+        let (id, payload) = self.encode_payload(registry)?;
+        let mut buf = BytesMut::with_capacity(9 + payload.len());
+        write_schema_id(&mut buf, id);
+        buf.put_slice(&payload);
+        Ok(buf.freeze())
+    }
+
+    /// Encodes the value alone, returning it with the ID of its schema.
+    pub(crate) fn encode_payload(&self, registry: &SchemaRegistry) -> Result<(SchemaId, Bytes)> {
+        let schema = registry
+            .get(&self.schema)?
+            .ok_or_else(|| SchemaError::UnresolvedReference(self.schema.clone()))?;
+        let id = match registry.id_of(&self.schema)? {
+            Some(id) => SchemaId::Id(id),
+            None => SchemaId::Fingerprint(schema.fingerprint()),
+        };
+
+        let mut encoder = Encoder::new();
+        encoder.encode_with_registry(&self.value, &schema, registry)?;
+        Ok((id, encoder.finish()))
+    }
+
+    /// Decodes a value encoded with the schema identified by `id`.
+    pub(crate) fn decode_payload(
+        id: SchemaId,
+        buf: &mut impl Buf,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        let (schema, schema_type) = registry.lookup(id)?.ok_or(SchemaError::UnknownSchema(id))?;
+        let value = Decoder::decode_with_registry(buf, &schema_type, registry)?;
+        Ok(Self { schema, value })
+    }
+
+    /// Decodes a message, looking its schema up in `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is truncated, the schema is unknown or
+    /// the payload doesn't match it.
+    pub fn decode(buf: &mut impl Buf, registry: &SchemaRegistry) -> Result<Self> {
+        let id = Self::read_schema_id(buf)?;
+        Self::decode_payload(id, buf, registry)
+    }
+
+    /// Reads the schema ID prefix of an encoded message, leaving `buf` at the
+    /// start of the payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is truncated or the ID kind is invalid.
+    pub fn read_schema_id(buf: &mut impl Buf) -> Result<SchemaId> {
+        if !buf.has_remaining() {
+            return Err(DecodeError::UnexpectedEof.into());
+        }
+        match buf.get_u8() {
+            KIND_ID if buf.remaining() >= 4 => Ok(SchemaId::Id(buf.get_u32())),
+            KIND_FINGERPRINT if buf.remaining() >= 8 => Ok(SchemaId::Fingerprint(buf.get_u64())),
+            KIND_ID | KIND_FINGERPRINT => Err(DecodeError::UnexpectedEof.into()),
+            kind => Err(DecodeError::InvalidData(format!("Invalid schema id kind: {kind}")).into()),
+        }
+    }
+}
+
+/// Writes the schema ID prefix read by [`Message::read_schema_id`].
+pub(crate) fn write_schema_id(buf: &mut BytesMut, id: SchemaId) {
+    match id {
+        SchemaId::Id(id) => {
+            buf.put_u8(KIND_ID);
+            buf.put_u32(id);
+        }
+        SchemaId::Fingerprint(fp) => {
+            buf.put_u8(KIND_FINGERPRINT);
+            buf.put_u64(fp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::schema::{Property, SchemaType};
+    use indexmap::IndexMap;
+
+    fn registry() -> SchemaRegistry {
+        let mut properties = IndexMap::new();
+        properties.insert("name".to_owned(), Property::required(SchemaType::string()));
+        let registry = SchemaRegistry::new();
+        registry
+            .register("User", SchemaType::object(properties))
+            .unwrap();
+        registry.register("Count", SchemaType::int32()).unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_mixed_stream() {
+        let registry = registry();
+        registry.register_id(42, "User").unwrap();
+
+        let mut user = IndexMap::new();
+        user.insert("name".into(), Value::String("Alice".to_owned()));
+        let messages = [
+            Message::new("User", Value::Object(user)),
+            Message::new("Count", Value::Integer(3)),
+        ];
+
+        for message in &messages {
+            let bytes = message.encode(&registry).unwrap();
+            let decoded = Message::decode(&mut bytes.as_ref(), &registry).unwrap();
+            assert_eq!(&decoded, message);
+        }
+
+        let user = messages[0].encode(&registry).unwrap();
+        assert_eq!(&user[..5], [KIND_ID, 0, 0, 0, 42]);
+        let count = messages[1].encode(&registry).unwrap();
+        assert_eq!(
+            Message::read_schema_id(&mut count.as_ref()).unwrap(),
+            SchemaId::Fingerprint(SchemaType::int32().fingerprint())
+        );
+    }
+
+    #[test]
+    fn test_unknown_schema() {
+        let registry = registry();
+        let bytes = Message::new("Count", Value::Integer(3))
+            .encode(&registry)
+            .unwrap();
+
+        let other = SchemaRegistry::new();
+        assert!(matches!(
+            Message::decode(&mut bytes.as_ref(), &other),
+            Err(Error::Schema(SchemaError::UnknownSchema(_)))
+        ));
+        assert!(Message::new("Missing", Value::Null)
+            .encode(&registry)
+            .is_err());
+    }
+
+    #[test]
+    fn test_invalid_prefix() {
+        let registry = registry();
+        assert!(Message::decode(&mut [KIND_ID, 0, 0].as_ref(), &registry).is_err());
+        assert!(Message::decode(&mut [0x7F, 0, 0, 0, 0].as_ref(), &registry).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+//! Hooks reporting encoded and decoded records.
+//!
+//! A hook set with [`SchemaRegistry::with_metrics`](crate::SchemaRegistry::with_metrics)
+//! is called once for every [`Value`](crate::Value) encoded with
+//! [`Encoder::encode_with_registry`](crate::Encoder::encode_with_registry)
+//! and decoded with [`Decoder::decode_with_registry`](crate::Decoder::decode_with_registry),
+//! including through the APIs built on them: framed records, messages,
+//! containers, streaming, serde and transcoding. Values nested in another
+//! one are not reported on their own. Forward the events to whatever
+//! collects your metrics:
+//!
+//! ```rust
+//! use compactr::metrics::Operation;
+//! use compactr::SchemaRegistry;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//! use std::sync::Arc;
+//!
+//! let decoded_bytes = Arc::new(AtomicUsize::new(0));
+//! let counter = Arc::clone(&decoded_bytes);
+//! let registry = SchemaRegistry::new().with_metrics(move |event| {
+//!     if event.operation == Operation::Decode {
+//!         counter.fetch_add(event.bytes, Ordering::Relaxed);
+//!     }
+//! });
+//! ```
+
+use crate::error::Error;
+use crate::schema::SchemaType;
+use std::fmt;
+use std::sync::Arc;
+
+/// Whether a value was encoded or decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// A value was encoded
+    Encode,
+    /// A value was decoded
+    Decode,
+}
+
+/// One encoded or decoded value, as reported to a metrics hook.
+#[derive(Debug, Clone, Copy)]
+pub struct MetricsEvent<'a> {
+    /// Whether the value was encoded or decoded
+    pub operation: Operation,
+    /// Name of the registered schema, when the value's schema was a
+    /// [`SchemaType::Reference`](crate::SchemaType::Reference)
+    pub schema: Option<&'a str>,
+    /// Bytes produced or consumed, up to the error if there was one
+    pub bytes: usize,
+    /// The error the operation failed with, if it did
+    pub error: Option<&'a Error>,
+}
+
+/// Receives a [`MetricsEvent`] for every reported value.
+pub type MetricsFn = dyn Fn(&MetricsEvent<'_>) + Send + Sync;
+
+/// The optional hook of a registry.
+#[derive(Clone, Default)]
+pub(crate) struct MetricsHook(Option<Arc<MetricsFn>>);
+
+impl MetricsHook {
+    pub(crate) fn new(hook: impl Fn(&MetricsEvent<'_>) + Send + Sync + 'static) -> Self {
+        Self(Some(Arc::new(hook)))
+    }
+
+    /// Reports the outcome of encoding or decoding a value of `schema`.
+    pub(crate) fn report<T>(
+        &self,
+        operation: Operation,
+        schema: &SchemaType,
+        bytes: usize,
+        result: &Result<T, Error>,
+    ) {
+        if let Some(hook) = &self.0 {
+            let schema = match schema {
+                SchemaType::Reference(name) => Some(name.as_str()),
+                _ => None,
+            };
+            hook(&MetricsEvent {
+                operation,
+                schema,
+                bytes,
+                error: result.as_ref().err(),
+            });
+        }
+    }
+}
+
+impl fmt::Debug for MetricsHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MetricsHook")
+            .field(&self.0.as_ref().map(|_| "Fn"))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Decoder, Encoder};
+    use crate::schema::SchemaRegistry;
+    use crate::value::Value;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_metrics_hook() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let registry = SchemaRegistry::new().with_metrics(move |event| {
+            sink.lock().unwrap().push((
+                event.operation,
+                event.schema.map(str::to_owned),
+                event.bytes,
+                event.error.is_some(),
+            ));
+        });
+        registry
+            .register(
+                "User",
+                SchemaType::object([
+                    ("name", SchemaType::string()),
+                    ("tags", SchemaType::array(SchemaType::string())),
+                ]),
+            )
+            .unwrap();
+        let schema = SchemaType::reference("User");
+        let value = Value::object([
+            ("name", Value::from("Ann")),
+            ("tags", Value::from(vec!["a"])),
+        ]);
+
+        let mut enc = Encoder::new();
+        enc.encode_with_registry(&value, &schema, &registry)
+            .unwrap();
+        assert!(enc
+            .encode_with_registry(&Value::Null, &schema, &registry)
+            .is_err());
+        let bytes = enc.finish();
+        Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        let string = SchemaType::string();
+        assert!(
+            Decoder::decode_with_registry(&mut [0, 3, b'a'].as_ref(), &string, &registry).is_err()
+        );
+
+        let len = bytes.len();
+        let user = Some("User".to_owned());
+        assert_eq!(
+            *events.lock().unwrap(),
+            [
+                (Operation::Encode, user.clone(), len, false),
+                (Operation::Encode, user.clone(), 0, true),
+                (Operation::Decode, user, len, false),
+                (Operation::Decode, None, 2, true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! Re-encoding stored records after a schema change.
+//!
+//! Compactr records don't carry their schema, so records written with an
+//! old schema must be decoded with it. A [`Migration`] decodes each record
+//! with the old schema, maps its properties onto the new schema and encodes
+//! it again:
+//!
+//! ```rust
+//! use compactr::migrate::Migration;
+//! use compactr::{Decoder, Encoder, SchemaType, Value};
+//!
+//! let old = SchemaType::object([("name", SchemaType::string()), ("age", SchemaType::int32())]);
+//! let new = SchemaType::object([
+//!     ("full_name", SchemaType::string()),
+//!     ("age", SchemaType::int64()),
+//!     ("active", SchemaType::boolean()),
+//! ]);
+//! let migration = Migration::new(old.clone(), new.clone())
+//!     .rename("name", "full_name")
+//!     .transform("active", |_| Ok(Value::Boolean(true)));
+//!
+//! let mut encoder = Encoder::new();
+//! encoder.encode(&Value::object([("name", Value::from("ann")), ("age", Value::from(41))]), &old)?;
+//! let record = migration.migrate(&encoder.finish())?;
+//!
+//! assert_eq!(
+//!     Decoder::decode_exact(&record, &new)?,
+//!     Value::object([
+//!         ("full_name", Value::from("ann")),
+//!         ("age", Value::from(41)),
+//!         ("active", Value::Boolean(true)),
+//!     ])
+//! );
+//! # Ok::<(), compactr::error::Error>(())
+//! ```
+//!
+//! Properties the new schema doesn't have are dropped when encoding. Record
+//! streams are migrated one record at a time with [`Migration::migrate_stream`],
+//! so their size is not limited by memory. Containers carry their own
+//! schemas: build the migration from [`ContainerReader::schema`] and copy
+//! the records with [`ContainerReader::next_encoded`] and
+//! [`ContainerWriter::write_encoded`].
+//!
+//! [`ContainerReader::schema`]: crate::container::ContainerReader::schema
+//! [`ContainerReader::next_encoded`]: crate::container::ContainerReader::next_encoded
+//! [`ContainerWriter::write_encoded`]: crate::container::ContainerWriter::write_encoded
+
+use crate::codec::{Decoder, Encoder};
+use crate::container::{read_framed, write_framed, DEFAULT_MAX_RECORD_LEN};
+use crate::error::{EncodeError, Result};
+use crate::schema::{SchemaRegistry, SchemaType};
+use crate::value::Value;
+use bytes::Bytes;
+use std::fmt;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+type Transform = Arc<dyn Fn(Value) -> Result<Value> + Send + Sync>;
+
+/// Carries records from an old schema to a new one.
+///
+/// Each record is decoded with the old schema, then, if it is an object:
+///
+/// 1. properties are [renamed](Self::rename),
+/// 2. property [transforms](Self::transform) run, in the order they were
+///    added,
+/// 3. the [record transform](Self::transform_record) runs,
+///
+/// and the result is encoded with the new schema.
+#[derive(Clone)]
+pub struct Migration {
+    from: SchemaType,
+    to: SchemaType,
+    from_registry: SchemaRegistry,
+    to_registry: SchemaRegistry,
+    renames: Vec<(String, String)>,
+    transforms: Vec<(String, Transform)>,
+    record: Option<Transform>,
+}
+
+impl Migration {
+    /// Creates a migration from `from` to `to`, which carries the
+    /// properties both schemas share over unchanged.
+    #[must_use]
+    pub fn new(from: SchemaType, to: SchemaType) -> Self {
+        Self {
+            from,
+            to,
+            from_registry: SchemaRegistry::new(),
+            to_registry: SchemaRegistry::new(),
+            renames: Vec::new(),
+            transforms: Vec::new(),
+            record: None,
+        }
+    }
+
+    /// Sets the registries resolving the references of the old and the new
+    /// schema, whose formats the records are read and written in.
+    #[must_use]
+    pub fn with_registries(mut self, from: SchemaRegistry, to: SchemaRegistry) -> Self {
+        self.from_registry = from;
+        self.to_registry = to;
+        self
+    }
+
+    /// Moves the property `from` of the old records to `to`.
+    #[must_use]
+    pub fn rename(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.renames.push((from.into(), to.into()));
+        self
+    }
+
+    /// Replaces the value of the property `name`, after renaming.
+    ///
+    /// `transform` receives [`Value::Null`] when the record lacks the
+    /// property, and leaves it out by returning [`Value::Null`], so it also
+    /// fills in new properties and drops old ones.
+    #[must_use]
+    pub fn transform(
+        mut self,
+        name: impl Into<String>,
+        transform: impl Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    ) -> Self {
+        self.transforms.push((name.into(), Arc::new(transform)));
+        self
+    }
+
+    /// Replaces the whole record, after the property mappings, for changes
+    /// they can't express such as restructuring nested values.
+    #[must_use]
+    pub fn transform_record(
+        mut self,
+        transform: impl Fn(Value) -> Result<Value> + Send + Sync + 'static,
+    ) -> Self {
+        self.record = Some(Arc::new(transform));
+        self
+    }
+
+    /// Maps a value decoded with the old schema to a value of the new one.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of a transform.
+    pub fn migrate_value(&self, mut value: Value) -> Result<Value> {
+        if let Value::Object(fields) = &mut value {
+            for (from, to) in &self.renames {
+                if let Some(field) = fields.shift_remove(from.as_str()) {
+                    fields.insert(to.as_str().into(), field);
+                }
+            }
+            for (name, transform) in &self.transforms {
+                let field = fields.shift_remove(name.as_str()).unwrap_or(Value::Null);
+                match transform(field)? {
+                    Value::Null => {}
+                    field => {
+                        fields.insert(name.as_str().into(), field);
+                    }
+                }
+            }
+        }
+        match &self.record {
+            Some(transform) => transform(value),
+            None => Ok(value),
+        }
+    }
+
+    /// Re-encodes one record written with the old schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record isn't valid for the old schema, a
+    /// transform fails, or the migrated value doesn't match the new schema.
+    pub fn migrate(&self, record: &[u8]) -> Result<Bytes> {
+        let value = Decoder::decode_exact_with_registry(record, &self.from, &self.from_registry)?;
+        let value = self.migrate_value(value)?;
+        let mut encoder = Encoder::new();
+        encoder.encode_with_registry(&value, &self.to, &self.to_registry)?;
+        Ok(encoder.finish())
+    }
+
+    /// Re-encodes the records read from `reader` until it ends, each
+    /// preceded by its length as a big-endian `u32` like the ones
+    /// [`Encoder::encode_many`] writes, and writes them to `writer` in the
+    /// same framing.
+    ///
+    /// Returns the number of records migrated. Records larger than
+    /// [`DEFAULT_MAX_RECORD_LEN`] are rejected.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error reading, migrating or writing a record. The
+    /// records before it have been written.
+    pub fn migrate_stream(&self, mut reader: impl Read, mut writer: impl Write) -> Result<u64> {
+        let mut count = 0;
+        while let Some(record) = read_framed(&mut reader, DEFAULT_MAX_RECORD_LEN)? {
+            write_framed(&mut writer, &self.migrate(&record)?)?;
+            count += 1;
+        }
+        writer.flush().map_err(EncodeError::Io)?;
+        Ok(count)
+    }
+}
+
+impl fmt::Debug for Migration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Migration")
+            .field("from", &self.from)
+            .field("to", &self.to)
+            .field("renames", &self.renames)
+            .field(
+                "transforms",
+                &self
+                    .transforms
+                    .iter()
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{DecodeError, Error};
+    use crate::schema::Property;
+
+    #[test]
+    fn test_migrate_stream() {
+        let registry = SchemaRegistry::new();
+        registry
+            .register("Status", SchemaType::string_enum(["on", "off"]))
+            .unwrap();
+        let old = SchemaType::object([
+            ("id", Property::required(SchemaType::int32())),
+            (
+                "status",
+                Property::required(SchemaType::reference("Status")),
+            ),
+            ("note", Property::optional(SchemaType::string())),
+            ("price", Property::required(SchemaType::double())),
+        ]);
+        let new = SchemaType::object([
+            ("id", Property::required(SchemaType::int64())),
+            ("enabled", Property::required(SchemaType::boolean())),
+            ("cents", Property::required(SchemaType::int64())),
+        ]);
+        let migration = Migration::new(old.clone(), new.clone())
+            .with_registries(registry.clone(), SchemaRegistry::new())
+            .rename("price", "cents")
+            .transform("cents", |price| {
+                let price = price.as_f64().unwrap_or_default();
+                #[allow(clippy::cast_possible_truncation)]
+                Ok(Value::Integer((price * 100.0).round() as i64))
+            })
+            .transform("enabled", |_| Ok(Value::Boolean(false)))
+            .transform_record(|mut record| {
+                let on = record.remove("status").and_then(Value::into_string);
+                if on.as_deref() == Some("on") {
+                    record.insert("enabled", true)?;
+                }
+                Ok(record)
+            });
+
+        let values = [
+            Value::object([
+                ("id", Value::from(1)),
+                ("status", Value::from("on")),
+                ("note", Value::from("dropped")),
+                ("price", Value::Double(2.5)),
+            ]),
+            Value::object([
+                ("id", Value::from(2)),
+                ("status", Value::from("off")),
+                ("price", Value::Double(0.1)),
+            ]),
+        ];
+        let mut encoder = Encoder::new();
+        encoder
+            .encode_many_with_registry(&values, &old, &registry)
+            .unwrap();
+        let input = encoder.finish();
+
+        let mut output = Vec::new();
+        let count = migration
+            .migrate_stream(input.as_ref(), &mut output)
+            .unwrap();
+        assert_eq!(count, 2);
+        let migrated = Decoder::decode_many(&output, &new)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            migrated,
+            [
+                Value::object([
+                    ("id", Value::from(1)),
+                    ("enabled", Value::Boolean(true)),
+                    ("cents", Value::from(250)),
+                ]),
+                Value::object([
+                    ("id", Value::from(2)),
+                    ("enabled", Value::Boolean(false)),
+                    ("cents", Value::from(10)),
+                ]),
+            ]
+        );
+
+        // A truncated stream fails after the records before it
+        let mut output = Vec::new();
+        let err = migration
+            .migrate_stream(&input[..input.len() - 1], &mut output)
+            .unwrap_err();
+        assert!(matches!(err, Error::Decode(DecodeError::UnexpectedEof)));
+        assert_eq!(Decoder::decode_many(&output, &new).count(), 1);
+
+        // Records must satisfy the new schema
+        let missing = Migration::new(old, new).with_registries(registry, SchemaRegistry::new());
+        let mut output = Vec::new();
+        assert!(missing.migrate_stream(input.as_ref(), &mut output).is_err());
+    }
+}
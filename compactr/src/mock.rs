@@ -0,0 +1,575 @@
+//! Random values matching a schema, for load tests and property tests.
+//!
+//! [`generate`] turns a schema and a seed into a value that encodes with
+//! that schema: integers stay within their format, strings come in the
+//! shape of their format, enums and `oneOf`s pick one of their variants, and
+//! property values fit the size their property is framed with. The same
+//! seed always gives the same value, so failures reproduce.
+
+use crate::codec::{intern, DecoderOptions, Encoder, LengthMode};
+use crate::error::{Result, SchemaError};
+#[cfg(not(feature = "chrono"))]
+use crate::formats::datetime;
+use crate::formats::geo::GeoPoint;
+use crate::schema::{
+    IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType, SizeWidth, StringFormat,
+    TimestampPrecision,
+};
+use crate::value::Value;
+#[cfg(not(feature = "chrono"))]
+use bytes::BufMut;
+use bytes::BytesMut;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset, Utc};
+use indexmap::IndexMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Longest strings and binary values generated, in bytes.
+const MAX_SIZE: usize = 16;
+
+/// Depth from which optional properties are left out and arrays are empty,
+/// so that recursive schemas give finite values.
+const SHALLOW_DEPTH: usize = 4;
+
+/// Range of generated instants, from 2000-01-01 to 2100-01-01 in
+/// milliseconds since the Unix epoch.
+const INSTANTS: (i64, i64) = (946_684_800_000, 4_102_444_800_000);
+
+const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Generates a random value matching `schema` from `seed`.
+///
+/// ```rust
+/// use compactr::{Decoder, Encoder, Property, SchemaType};
+///
+/// let schema = SchemaType::object([
+///     ("id", Property::required(SchemaType::string_uuid())),
+///     ("tags", Property::optional(SchemaType::array(SchemaType::string()))),
+/// ]);
+/// let value = compactr::mock::generate(&schema, 42)?;
+/// assert_eq!(compactr::mock::generate(&schema, 42)?, value);
+///
+/// let mut encoder = Encoder::new();
+/// encoder.encode(&value, &schema)?;
+/// let bytes = encoder.finish();
+/// assert_eq!(Decoder::decode(&mut bytes.as_ref(), &schema)?, value);
+/// # Ok::<(), compactr::error::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the schema has a reference or a custom format, or no
+/// value of it can be encoded.
+pub fn generate(schema: &SchemaType, seed: u64) -> Result<Value> {
+    Generator::new(seed).generate(schema)
+}
+
+/// Generates a random value matching `schema` from `seed`, following
+/// references through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if a reference is not registered, the schema has a
+/// custom format, or no value of it can be encoded.
+pub fn generate_with_registry(
+    schema: &SchemaType,
+    seed: u64,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    Generator::new(seed).generate_with_registry(schema, registry)
+}
+
+/// A seeded source of random values, for generating many values from one
+/// seed.
+///
+/// ```rust
+/// use compactr::mock::Generator;
+/// use compactr::SchemaType;
+///
+/// let mut generator = Generator::new(7);
+/// let values = (0..100)
+///     .map(|_| generator.generate(&SchemaType::int32()))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(values.len(), 100);
+/// # Ok::<(), compactr::error::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generator {
+    state: u64,
+    max_depth: usize,
+}
+
+impl Generator {
+    /// Creates a generator from a seed.
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+            max_depth: DecoderOptions::untrusted().max_depth(),
+        }
+    }
+
+    /// Generates the next random value matching `schema`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema has a reference or a custom format,
+    /// or no value of it can be encoded.
+    pub fn generate(&mut self, schema: &SchemaType) -> Result<Value> {
+        self.generate_with_registry(schema, SchemaRegistry::empty())
+    }
+
+    /// Generates the next random value matching `schema`, following
+    /// references through `registry`.
+    ///
+    /// Values nest at most as deep as
+    /// [untrusted](DecoderOptions::untrusted) decoders accept, and property
+    /// values fit the sizes of the registry's
+    /// [length mode](SchemaRegistry::length_mode).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a reference is not registered, the schema has a
+    /// custom format, or no value of it can be encoded.
+    pub fn generate_with_registry(
+        &mut self,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Value> {
+        self.value(schema, registry, 0, MAX_SIZE)
+    }
+
+    /// Returns the next 64 random bits, with the `SplitMix64` generator.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `0..=max`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn up_to(&mut self, max: usize) -> usize {
+        (self.next_u64() % (max as u64 + 1)) as usize
+    }
+
+    /// Returns a random number in `min..=max`.
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    fn between(&mut self, min: i64, max: i64) -> i64 {
+        let span = max.wrapping_sub(min) as u64;
+        match span.checked_add(1) {
+            Some(count) => min.wrapping_add((self.next_u64() % count) as i64),
+            None => self.next_u64() as i64,
+        }
+    }
+
+    fn coin(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    /// Generates a value of `schema` at nesting `depth`, with strings,
+    /// binary values and arrays of at most `size` bytes or items.
+    fn value(
+        &mut self,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+        depth: usize,
+        size: usize,
+    ) -> Result<Value> {
+        if depth > self.max_depth {
+            return Err(SchemaError::InvalidSchema(format!(
+                "No value nests less than {} levels deep",
+                self.max_depth
+            ))
+            .into());
+        }
+        let shallow = depth < SHALLOW_DEPTH;
+        Ok(match schema {
+            SchemaType::Boolean => Value::Boolean(self.coin()),
+            SchemaType::Integer(format) => self.integer(*format),
+            SchemaType::Number(format) => self.number(*format),
+            SchemaType::String(format) => self.string(*format, size)?,
+            SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+                let len = if shallow { self.up_to(size / 4) } else { 0 };
+                let items = (0..len)
+                    .map(|_| self.value(items, registry, depth + 1, size))
+                    .collect::<Result<_>>()?;
+                Value::Array(items)
+            }
+            SchemaType::Object(properties) => {
+                let mut obj = IndexMap::new();
+                for (name, property) in properties {
+                    if property.required || (shallow && size > 0 && self.coin()) {
+                        let value = self.property(name, property, registry, depth + 1, size)?;
+                        obj.insert(intern(name), value);
+                    }
+                }
+                Value::Object(obj)
+            }
+            SchemaType::Reference(name) => {
+                let resolved = registry.resolve_shared(name)?;
+                self.value(&resolved, registry, depth + 1, size)?
+            }
+            SchemaType::Enum(variants) => {
+                if variants.is_empty() {
+                    return Err(
+                        SchemaError::InvalidSchema("Enum has no variants".to_owned()).into(),
+                    );
+                }
+                let idx = self.up_to(variants.len() - 1);
+                Value::String(variants[idx].clone())
+            }
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            } => {
+                if variants.is_empty() {
+                    return Err(
+                        SchemaError::InvalidSchema("oneOf has no variants".to_owned()).into(),
+                    );
+                }
+                let idx = self.up_to(variants.len() - 1);
+                let (name, payload) = variants.get_index(idx).unwrap_or_else(|| unreachable!());
+                let mut obj = IndexMap::new();
+                obj.insert(intern(discriminator), Value::String(name.clone()));
+                match self.value(payload, registry, depth + 1, size)? {
+                    Value::Object(fields) => obj.extend(
+                        fields
+                            .into_iter()
+                            .filter(|(field, _)| **field != **discriminator),
+                    ),
+                    Value::Null => {}
+                    _ => {
+                        return Err(SchemaError::InvalidSchema(format!(
+                            "Variant {name} has no object payload"
+                        ))
+                        .into())
+                    }
+                }
+                Value::Object(obj)
+            }
+            SchemaType::Null => Value::Null,
+        })
+    }
+
+    /// Generates a property value that fits the size it is framed with,
+    /// shrinking it until it does.
+    fn property(
+        &mut self,
+        name: &str,
+        property: &Property,
+        registry: &SchemaRegistry,
+        depth: usize,
+        mut size: usize,
+    ) -> Result<Value> {
+        loop {
+            let value = self.value(&property.schema_type, registry, depth, size)?;
+            if fits(&value, property, registry)? {
+                return Ok(value);
+            }
+            if size == 0 {
+                return Err(SchemaError::InvalidSchema(format!(
+                    "No value of property {name} fits its size header"
+                ))
+                .into());
+            }
+            size /= 2;
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    fn integer(&mut self, format: IntegerFormat) -> Value {
+        let (min, max) = match format {
+            IntegerFormat::Int8 => (i64::from(i8::MIN), i64::from(i8::MAX)),
+            IntegerFormat::Int16 => (i64::from(i16::MIN), i64::from(i16::MAX)),
+            IntegerFormat::Int32 => (i64::from(i32::MIN), i64::from(i32::MAX)),
+            // Stored as a double, which is exact up to 2^53
+            IntegerFormat::Int64 => (-(1 << 53), 1 << 53),
+            IntegerFormat::Uint8 => (0, i64::from(u8::MAX)),
+            IntegerFormat::Uint16 => (0, i64::from(u16::MAX)),
+            IntegerFormat::Int128 => {
+                let bits = u128::from(self.next_u64()) << 64 | u128::from(self.next_u64());
+                return Value::Int128(bits as i128);
+            }
+            IntegerFormat::Uint128 => {
+                let bits = u128::from(self.next_u64()) << 64 | u128::from(self.next_u64());
+                return Value::Uint128(bits);
+            }
+        };
+        Value::Integer(self.between(min, max))
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn number(&mut self, format: NumberFormat) -> Value {
+        match format {
+            // Hundredths and thousandths, which read better than raw bits
+            NumberFormat::Float => {
+                Value::Float(f32::from(self.between(-10_000, 10_000) as i16) / 100.0)
+            }
+            NumberFormat::Double => Value::Double(
+                f64::from(self.between(-1_000_000_000, 1_000_000_000) as i32) / 1000.0,
+            ),
+            #[cfg(feature = "decimal")]
+            NumberFormat::Decimal => {
+                let scale = self.up_to(4) as u32;
+                let mantissa = self.between(-1_000_000_000, 1_000_000_000);
+                Value::Decimal(rust_decimal::Decimal::new(mantissa, scale))
+            }
+        }
+    }
+
+    fn string(&mut self, format: StringFormat, size: usize) -> Result<Value> {
+        Ok(match format {
+            StringFormat::Plain | StringFormat::Long => Value::String(self.word(size)),
+            StringFormat::Uuid => {
+                let bits = u128::from(self.next_u64()) << 64 | u128::from(self.next_u64());
+                let mut bytes = bits.to_be_bytes();
+                // Version 4, RFC 4122 variant
+                bytes[6] = bytes[6] & 0x0f | 0x40;
+                bytes[8] = bytes[8] & 0x3f | 0x80;
+                #[cfg(feature = "uuid")]
+                let value = Value::Uuid(uuid::Uuid::from_bytes(bytes));
+                #[cfg(not(feature = "uuid"))]
+                let value = Value::Binary(bytes.to_vec());
+                value
+            }
+            #[cfg(feature = "chrono")]
+            StringFormat::DateTime => Value::DateTime(self.instant(false)),
+            #[cfg(feature = "chrono")]
+            StringFormat::DateTimeTz => {
+                let minutes = self.offset_minutes();
+                let offset = FixedOffset::east_opt(i32::from(minutes) * 60)
+                    .unwrap_or_else(|| unreachable!());
+                Value::DateTimeTz(self.instant(false).with_timezone(&offset))
+            }
+            #[cfg(feature = "chrono")]
+            StringFormat::Timestamp(precision) => {
+                Value::DateTime(self.instant(precision == TimestampPrecision::Seconds))
+            }
+            #[cfg(feature = "chrono")]
+            StringFormat::Date => Value::Date(self.instant(false).date_naive()),
+            #[cfg(feature = "chrono")]
+            StringFormat::Time => Value::Time(self.instant(false).time()),
+            // The integers and bytes the formats decode to without chrono
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::DateTime => Value::Integer(self.instant_millis(false)),
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::DateTimeTz => {
+                let mut buf = BytesMut::new();
+                datetime::encode_datetime_millis(&mut buf, self.instant_millis(false))?;
+                buf.put_i16(self.offset_minutes());
+                Value::Binary(buf.to_vec())
+            }
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::Timestamp(precision) => {
+                let millis = self.instant_millis(precision == TimestampPrecision::Seconds);
+                Value::Integer(match precision {
+                    TimestampPrecision::Seconds => millis / 1000,
+                    TimestampPrecision::Millis => millis,
+                    TimestampPrecision::Micros => millis * 1000,
+                    TimestampPrecision::Nanos => millis * 1_000_000,
+                })
+            }
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::Date => Value::Integer(
+                self.instant_millis(false)
+                    .div_euclid(datetime::MILLIS_PER_DAY),
+            ),
+            #[cfg(not(feature = "chrono"))]
+            StringFormat::Time => Value::Integer(
+                self.instant_millis(false)
+                    .rem_euclid(datetime::MILLIS_PER_DAY),
+            ),
+            StringFormat::Uri => Value::String(format!("https://example.com/{}", self.word(size))),
+            StringFormat::SocketAddr => {
+                let ip = if self.coin() {
+                    IpAddr::V4(self.ipv4())
+                } else {
+                    IpAddr::V6(self.ipv6())
+                };
+                #[allow(clippy::cast_possible_truncation)]
+                let port = self.next_u64() as u16;
+                Value::SocketAddr(SocketAddr::new(ip, port))
+            }
+            StringFormat::GeoPoint => {
+                // Multiples of the 1e-7 degree resolution the points are
+                // stored with
+                #[allow(clippy::cast_possible_truncation)]
+                let (lat, lon) = (
+                    self.between(-900_000_000, 900_000_000) as i32,
+                    self.between(-1_800_000_000, 1_800_000_000) as i32,
+                );
+                let point = GeoPoint::new(f64::from(lat) / 1e7, f64::from(lon) / 1e7)?;
+                Value::GeoPoint(point)
+            }
+            StringFormat::Ipv4 => Value::Ipv4(self.ipv4()),
+            StringFormat::Ipv6 => Value::Ipv6(self.ipv6()),
+            StringFormat::Binary => {
+                let len = self.up_to(size);
+                #[allow(clippy::cast_possible_truncation)]
+                Value::Binary((0..len).map(|_| self.next_u64() as u8).collect())
+            }
+            StringFormat::Custom(name) => {
+                return Err(SchemaError::InvalidSchema(format!(
+                    "Cannot generate values of custom format {name}"
+                ))
+                .into())
+            }
+        })
+    }
+
+    /// Returns a random lowercase alphanumeric word of at most `size` bytes.
+    fn word(&mut self, size: usize) -> String {
+        let len = self.up_to(size);
+        (0..len)
+            .map(|_| char::from(CHARS[self.up_to(CHARS.len() - 1)]))
+            .collect()
+    }
+
+    /// Returns an instant of this century, in whole milliseconds or seconds.
+    #[cfg(feature = "chrono")]
+    fn instant(&mut self, whole_seconds: bool) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.instant_millis(whole_seconds))
+            .unwrap_or_else(|| unreachable!())
+    }
+
+    /// Returns an instant of this century in milliseconds since the Unix
+    /// epoch, a multiple of 1000 if `whole_seconds`.
+    fn instant_millis(&mut self, whole_seconds: bool) -> i64 {
+        let mut millis = self.between(INSTANTS.0, INSTANTS.1 - 1);
+        if whole_seconds {
+            millis -= millis % 1000;
+        }
+        millis
+    }
+
+    /// Returns a UTC offset in minutes, in whole quarters of an hour as real
+    /// offsets are.
+    #[allow(clippy::cast_possible_truncation)]
+    fn offset_minutes(&mut self) -> i16 {
+        self.between(-14 * 4, 14 * 4) as i16 * 15
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn ipv4(&mut self) -> Ipv4Addr {
+        Ipv4Addr::from(self.next_u64() as u32)
+    }
+
+    fn ipv6(&mut self) -> Ipv6Addr {
+        Ipv6Addr::from(u128::from(self.next_u64()) << 64 | u128::from(self.next_u64()))
+    }
+}
+
+/// Returns whether `value` fits the size its property is framed with in the
+/// object header.
+pub(crate) fn fits(value: &Value, property: &Property, registry: &SchemaRegistry) -> Result<bool> {
+    let max = match (property.size_width, registry.length_mode()) {
+        (SizeWidth::Auto, LengthMode::Varint) | (SizeWidth::U32, _) => return Ok(true),
+        (SizeWidth::Auto | SizeWidth::U8, _) => usize::from(u8::MAX),
+        (SizeWidth::U16, _) => usize::from(u16::MAX),
+    };
+    let mut buf = BytesMut::new();
+    Encoder::encode_property_into(&mut buf, value, &property.schema_type, registry)?;
+    Ok(buf.len() <= max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Decoder;
+
+    #[test]
+    fn test_generate() {
+        let registry = SchemaRegistry::new();
+        registry
+            .register(
+                "Node",
+                SchemaType::object([
+                    ("label", Property::required(SchemaType::string())),
+                    (
+                        "children",
+                        Property::optional(SchemaType::array(SchemaType::reference("Node"))),
+                    ),
+                ]),
+            )
+            .unwrap();
+        let shape = SchemaType::one_of(
+            "kind",
+            [
+                (
+                    "circle".to_owned(),
+                    SchemaType::object([("radius", SchemaType::double())]),
+                ),
+                ("empty".to_owned(), SchemaType::Null),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let schema = SchemaType::object([
+            ("id", Property::required(SchemaType::string_uuid())),
+            ("small", Property::required(SchemaType::int8())),
+            (
+                "counts",
+                Property::optional(SchemaType::array(SchemaType::int64())),
+            ),
+            ("big", Property::required(SchemaType::uint128())),
+            ("ratio", Property::optional(SchemaType::float())),
+            ("at", Property::required(SchemaType::string_datetime())),
+            (
+                "local",
+                Property::optional(SchemaType::string_datetime_tz()),
+            ),
+            (
+                "seen",
+                Property::optional(SchemaType::timestamp(TimestampPrecision::Seconds)),
+            ),
+            ("day", Property::optional(SchemaType::string_date())),
+            ("time", Property::optional(SchemaType::string_time())),
+            ("site", Property::optional(SchemaType::string_uri())),
+            ("peer", Property::optional(SchemaType::string_socket_addr())),
+            ("where", Property::optional(SchemaType::geo_point())),
+            ("ip", Property::optional(SchemaType::string_ipv6())),
+            ("blob", Property::optional(SchemaType::binary())),
+            (
+                "status",
+                Property::required(SchemaType::string_enum(["on", "off"])),
+            ),
+            ("shape", Property::required(shape)),
+            ("tree", Property::required(SchemaType::reference("Node"))),
+            (
+                "matrix",
+                Property::optional(SchemaType::array(SchemaType::array(SchemaType::string()))),
+            ),
+        ]);
+
+        for seed in 0..100 {
+            let value = generate_with_registry(&schema, seed, &registry).unwrap();
+            assert_eq!(
+                generate_with_registry(&schema, seed, &registry).unwrap(),
+                value
+            );
+            let mut encoder = Encoder::new();
+            encoder
+                .encode_with_registry(&value, &schema, &registry)
+                .unwrap();
+            let bytes = encoder.finish();
+            let decoded = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry);
+            assert_eq!(decoded.unwrap(), value, "seed {seed}");
+        }
+        assert_ne!(
+            generate_with_registry(&schema, 1, &registry).unwrap(),
+            generate_with_registry(&schema, 2, &registry).unwrap()
+        );
+
+        // Required recursion has no finite value
+        registry
+            .register(
+                "Loop",
+                SchemaType::object([("next", Property::required(SchemaType::reference("Loop")))]),
+            )
+            .unwrap();
+        assert!(generate_with_registry(&SchemaType::reference("Loop"), 0, &registry).is_err());
+        assert!(generate(&SchemaType::reference("Node"), 0).is_err());
+    }
+}
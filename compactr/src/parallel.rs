@@ -0,0 +1,98 @@
+//! Parallel batch encoding on the rayon thread pool.
+//!
+//! Records are independent, so large exports can encode them on every core.
+//! The output is framed: each record is preceded by its length as a
+//! big-endian `u32`, in the order of the input, and can be read back with
+//! [`Decoder::decode_many`](crate::Decoder::decode_many).
+//!
+//! ```rust
+//! use compactr::{parallel, SchemaType, Value};
+//!
+//! let schema = SchemaType::object([("id", SchemaType::int64())]);
+//! let records: Vec<Value> = (0..10_000).map(|id| Value::object([("id", id)])).collect();
+//! let framed = parallel::encode_batch_parallel(&records, &schema)?;
+//! # Ok::<(), compactr::error::Error>(())
+//! ```
+
+use crate::codec::Encoder;
+use crate::error::Result;
+use crate::schema::{SchemaRegistry, SchemaType};
+use crate::value::Value;
+use bytes::{Bytes, BytesMut};
+use rayon::prelude::*;
+
+/// Number of records each task encodes, large enough to amortize the
+/// scheduling cost over records of a few bytes.
+const CHUNK_LEN: usize = 1024;
+
+/// Encodes `values` in parallel into length-prefixed records, the same
+/// output as [`Encoder::encode_many`] produces on one thread.
+///
+/// # Errors
+///
+/// Returns an error if any value doesn't match the schema or fails to
+/// encode.
+pub fn encode_batch_parallel(values: &[Value], schema: &SchemaType) -> Result<Bytes> {
+    encode_batch_parallel_with_registry(values, schema, SchemaRegistry::empty())
+}
+
+/// Encodes `values` in parallel into length-prefixed records, resolving
+/// references and layout settings through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if any value doesn't match the schema or fails to
+/// encode.
+pub fn encode_batch_parallel_with_registry(
+    values: &[Value],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Bytes> {
+    let chunks = values
+        .par_chunks(CHUNK_LEN)
+        .map(|chunk| {
+            let mut encoder = Encoder::new();
+            encoder.encode_many_with_registry(chunk, schema, registry)?;
+            Ok(encoder.finish())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut out = BytesMut::with_capacity(chunks.iter().map(Bytes::len).sum());
+    for chunk in chunks {
+        out.extend_from_slice(&chunk);
+    }
+    Ok(out.freeze())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Decoder;
+    use bytes::Buf;
+
+    #[test]
+    fn test_encode_batch_parallel() {
+        let schema =
+            SchemaType::object([("id", SchemaType::int64()), ("name", SchemaType::string())]);
+        let values: Vec<Value> = (0..3000)
+            .map(|id| {
+                Value::object([
+                    ("id", Value::from(id)),
+                    ("name", Value::from(format!("n{id}"))),
+                ])
+            })
+            .collect();
+
+        let mut framed = encode_batch_parallel(&values, &schema).unwrap();
+        for value in &values {
+            let len = framed.get_u32() as usize;
+            let mut record = framed.split_to(len);
+            assert_eq!(Decoder::decode(&mut record, &schema).unwrap(), *value);
+        }
+        assert!(framed.is_empty());
+
+        let mut invalid = values;
+        invalid[2500] = Value::Integer(1);
+        assert!(encode_batch_parallel(&invalid, &schema).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Plugins turning decoded values into application types.
+//!
+//! A [`DecodePlugin`] added with
+//! [`SchemaRegistry::with_decode_plugin`](crate::SchemaRegistry::with_decode_plugin)
+//! is offered every value the [`Decoder`](crate::Decoder) decodes with the
+//! registry, from the innermost outwards, and replaces the ones whose
+//! schemas it handles, typically with a [`Value::Custom`] holding a domain
+//! type. Domain types then come out of the decoder directly instead of from
+//! a second pass over the decoded value:
+//!
+//! ```rust
+//! use compactr::plugin::DecodePlugin;
+//! use compactr::value::CustomValue;
+//! use compactr::{Decoder, Encoder, Result, SchemaRegistry, SchemaType, Value};
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct Point {
+//!     x: i32,
+//!     y: i32,
+//! }
+//!
+//! struct Points;
+//!
+//! impl DecodePlugin for Points {
+//!     fn handles(&self, schema: &SchemaType) -> bool {
+//!         *schema == SchemaType::reference("Point")
+//!     }
+//!
+//!     fn decode(&self, _schema: &SchemaType, value: Value) -> Result<Value> {
+//!         let coordinate = |name| value.get(name).and_then(Value::as_i64).unwrap_or(0);
+//!         let point = Point {
+//!             x: i32::try_from(coordinate("x")).unwrap_or(0),
+//!             y: i32::try_from(coordinate("y")).unwrap_or(0),
+//!         };
+//!         Ok(Value::Custom(CustomValue::new(point, value)))
+//!     }
+//! }
+//!
+//! let registry = SchemaRegistry::new().with_decode_plugin(Points);
+//! registry.register(
+//!     "Point",
+//!     SchemaType::object([("x", SchemaType::int32()), ("y", SchemaType::int32())]),
+//! )?;
+//! let schema = SchemaType::array(SchemaType::reference("Point"));
+//!
+//! let value = Value::array([Value::object([("x", 1), ("y", 2)])]);
+//! let mut encoder = Encoder::new();
+//! encoder.encode_with_registry(&value, &schema, &registry)?;
+//! let bytes = encoder.finish();
+//!
+//! let decoded = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry)?;
+//! let points = decoded.as_array().unwrap();
+//! assert_eq!(points[0].downcast_ref::<Point>(), Some(&Point { x: 1, y: 2 }));
+//! // Custom values encode and compare as the value they were made from
+//! assert_eq!(decoded, value);
+//! # Ok::<(), compactr::error::Error>(())
+//! ```
+
+use crate::error::Result;
+use crate::schema::SchemaType;
+use crate::value::Value;
+use std::fmt;
+use std::sync::Arc;
+
+/// Replaces decoded values of the schemas it handles.
+pub trait DecodePlugin: Send + Sync {
+    /// Returns whether values of `schema` are passed to
+    /// [`decode`](Self::decode).
+    ///
+    /// Schemas are offered as written, so a
+    /// [reference](SchemaType::Reference) is offered by its name and not as
+    /// the schema it resolves to.
+    fn handles(&self, schema: &SchemaType) -> bool;
+
+    /// Replaces `value`, decoded with `schema`.
+    ///
+    /// # Errors
+    ///
+    /// An error fails the decoding of the whole value.
+    fn decode(&self, schema: &SchemaType, value: Value) -> Result<Value>;
+}
+
+/// The plugins of a registry, in the order they were added.
+#[derive(Clone, Default)]
+pub(crate) struct DecodePlugins(Vec<Arc<dyn DecodePlugin>>);
+
+impl DecodePlugins {
+    pub(crate) fn push(&mut self, plugin: impl DecodePlugin + 'static) {
+        self.0.push(Arc::new(plugin));
+    }
+
+    /// Passes `value` to the first plugin that handles `schema`, if any.
+    pub(crate) fn apply(&self, schema: &SchemaType, value: Value) -> Result<Value> {
+        match self.0.iter().find(|plugin| plugin.handles(schema)) {
+            Some(plugin) => plugin.decode(schema, value),
+            None => Ok(value),
+        }
+    }
+}
+
+impl fmt::Debug for DecodePlugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("DecodePlugins").field(&self.0.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Decoder, Encoder};
+    use crate::error::DecodeError;
+    use crate::schema::SchemaRegistry;
+    use crate::value::CustomValue;
+
+    /// Upper-cases plain strings.
+    struct Upper;
+
+    impl DecodePlugin for Upper {
+        fn handles(&self, schema: &SchemaType) -> bool {
+            *schema == SchemaType::string()
+        }
+
+        fn decode(&self, _schema: &SchemaType, value: Value) -> Result<Value> {
+            let upper = value.as_str().unwrap_or_default().to_uppercase();
+            Ok(Value::Custom(CustomValue::new(upper, value)))
+        }
+    }
+
+    /// Rejects every value of a reference.
+    struct Reject;
+
+    impl DecodePlugin for Reject {
+        fn handles(&self, schema: &SchemaType) -> bool {
+            matches!(schema, SchemaType::Reference(_) | SchemaType::String(_))
+        }
+
+        fn decode(&self, _schema: &SchemaType, _value: Value) -> Result<Value> {
+            Err(DecodeError::InvalidData("rejected".to_owned()).into())
+        }
+    }
+
+    #[test]
+    fn test_decode_plugins() {
+        let schema = SchemaType::object([
+            ("name", SchemaType::string()),
+            ("tags", SchemaType::array(SchemaType::string())),
+            ("id", SchemaType::int32()),
+        ]);
+        let value = Value::object([
+            ("name", Value::from("ann")),
+            ("tags", Value::from(vec!["a"])),
+            ("id", Value::from(7)),
+        ]);
+        let mut encoder = Encoder::new();
+        encoder.encode(&value, &schema).unwrap();
+        let bytes = encoder.finish();
+
+        // Earlier plugins take precedence
+        let registry = SchemaRegistry::new()
+            .with_decode_plugin(Upper)
+            .with_decode_plugin(Reject);
+        let decoded =
+            Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        let name = decoded.get("name").unwrap();
+        assert_eq!(
+            name.downcast_ref::<String>().map(String::as_str),
+            Some("ANN")
+        );
+        let tag = &decoded.get("tags").unwrap().as_array().unwrap()[0];
+        assert_eq!(tag.downcast_ref::<String>().map(String::as_str), Some("A"));
+        assert_eq!(decoded.get("id"), Some(&Value::from(7)));
+        assert_eq!(decoded, value);
+
+        // Custom values encode as their representation
+        let mut encoder = Encoder::new();
+        encoder.encode(&decoded, &schema).unwrap();
+        assert_eq!(encoder.finish(), bytes);
+
+        // References are offered as written, and errors fail decoding
+        let registry = SchemaRegistry::new().with_decode_plugin(Reject);
+        registry.register("Id", SchemaType::int32()).unwrap();
+        let id = SchemaType::reference("Id");
+        let mut encoder = Encoder::new();
+        encoder
+            .encode_with_registry(&Value::from(1), &id, &registry)
+            .unwrap();
+        let bytes = encoder.finish();
+        assert!(Decoder::decode_with_registry(&mut bytes.as_ref(), &id, &registry).is_err());
+        assert!(Decoder::decode_with_registry(
+            &mut bytes.as_ref(),
+            &SchemaType::int32(),
+            &registry
+        )
+        .is_ok());
+    }
+}
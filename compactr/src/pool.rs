@@ -0,0 +1,92 @@
+//! Encoding with thread-local encoders.
+//!
+//! [`encode_pooled`] reuses one [`Encoder`] per thread, so hot loops get a
+//! warm buffer without keeping an encoder around themselves:
+//!
+//! ```rust
+//! use compactr::{Decoder, SchemaType, Value};
+//!
+//! let schema = SchemaType::object([("id", SchemaType::int32())]);
+//! for id in 0..3 {
+//!     let value = Value::object([("id", id)]);
+//!     let bytes = compactr::encode_pooled(&value, &schema)?;
+//!     assert_eq!(Decoder::decode(&mut bytes.as_ref(), &schema)?, value);
+//! }
+//! # Ok::<(), compactr::error::Error>(())
+//! ```
+
+use crate::codec::Encoder;
+use crate::error::Result;
+use crate::schema::{SchemaRegistry, SchemaType};
+use crate::value::Value;
+use bytes::Bytes;
+use std::cell::RefCell;
+
+/// Largest buffer kept between calls; encoders that grew beyond it for an
+/// unusually large value are dropped rather than pinning the memory.
+const MAX_POOLED_CAPACITY: usize = 1024 * 1024;
+
+thread_local! {
+    static ENCODER: RefCell<Encoder> = RefCell::new(Encoder::new());
+}
+
+/// Encodes `value` with this thread's pooled encoder.
+///
+/// # Errors
+///
+/// Returns an error if the value doesn't match the schema or encoding fails.
+pub fn encode_pooled(value: &Value, schema: &SchemaType) -> Result<Bytes> {
+    encode_pooled_with_registry(value, schema, SchemaRegistry::empty())
+}
+
+/// Encodes `value` with this thread's pooled encoder, resolving references
+/// and layout settings through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the value doesn't match the schema or encoding fails.
+pub fn encode_pooled_with_registry(
+    value: &Value,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Bytes> {
+    ENCODER.with(|cell| {
+        // A nested call (from a custom format, say) gets a fresh encoder
+        let Ok(mut encoder) = cell.try_borrow_mut() else {
+            let mut encoder = Encoder::new();
+            encoder.encode_with_registry(value, schema, registry)?;
+            return Ok(encoder.finish());
+        };
+        let result = encoder.encode_with_registry(value, schema, registry);
+        let bytes = encoder.take_bytes();
+        if encoder.capacity() > MAX_POOLED_CAPACITY {
+            *encoder = Encoder::new();
+        }
+        result.map(|()| bytes)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Decoder;
+
+    #[test]
+    fn test_encode_pooled() {
+        let schema = SchemaType::object([("name", SchemaType::string())]);
+        for name in ["a", "bb", "ccc"] {
+            let value = Value::object([("name", name)]);
+            let bytes = encode_pooled(&value, &schema).unwrap();
+            let mut enc = Encoder::new();
+            enc.encode(&value, &schema).unwrap();
+            assert_eq!(bytes, enc.finish());
+            assert_eq!(Decoder::decode(&mut &*bytes, &schema).unwrap(), value);
+        }
+
+        // A failed encoding leaves nothing behind for the next one
+        assert!(encode_pooled(&Value::Integer(1), &schema).is_err());
+        let value = Value::object([("name", "d")]);
+        let bytes = encode_pooled(&value, &schema).unwrap();
+        assert_eq!(Decoder::decode(&mut &*bytes, &schema).unwrap(), value);
+    }
+}
@@ -0,0 +1,332 @@
+//! Request/response framing for lightweight RPC.
+//!
+//! [`Request`] and [`Response`] envelopes are themselves encoded with
+//! Compactr, against the schemas returned by [`Request::schema`] and
+//! [`Response::schema`], so peers in other languages can implement the
+//! same contract from those schemas. Payloads are carried as opaque bytes
+//! together with the [`SchemaId`] of the schema they were encoded with,
+//! framed like a [`Message`].
+
+use crate::codec::{Decoder, Encoder};
+use crate::error::{DecodeError, Error, Result};
+use crate::message::{write_schema_id, Message};
+use crate::schema::{Property, SchemaId, SchemaRegistry, SchemaType};
+use crate::value::Value;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use indexmap::IndexMap;
+use std::sync::Arc;
+
+/// An encoded value and the ID of its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payload {
+    /// Schema the data is encoded with
+    pub schema: SchemaId,
+    /// Encoded value
+    pub data: Bytes,
+}
+
+impl Payload {
+    /// Encodes `message` with its registered schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is not registered or the value doesn't
+    /// match it.
+    pub fn from_message(message: &Message, registry: &SchemaRegistry) -> Result<Self> {
+        let (schema, data) = message.encode_payload(registry)?;
+        Ok(Self { schema, data })
+    }
+
+    /// Decodes the payload, looking its schema up in `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the schema is unknown or the data doesn't match
+    /// it.
+    pub fn to_message(&self, registry: &SchemaRegistry) -> Result<Message> {
+        Message::decode_payload(self.schema, &mut self.data.as_ref(), registry)
+    }
+}
+
+/// An RPC call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// Method being called
+    pub method: u16,
+    /// Identifier echoed in the matching [`Response`]
+    pub correlation_id: u32,
+    /// Call arguments
+    pub payload: Payload,
+}
+
+/// An error reported by the callee.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcError {
+    /// Application-defined error code
+    pub code: i32,
+    /// Human-readable description
+    pub message: String,
+}
+
+/// The outcome of an RPC call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// Identifier of the [`Request`] this responds to
+    pub correlation_id: u32,
+    /// Returned value or error
+    pub result: std::result::Result<Payload, RpcError>,
+}
+
+impl Request {
+    /// Returns the schema of encoded requests.
+    ///
+    /// `payload` holds the arguments framed like a [`Message`]: the schema
+    /// ID prefix followed by the encoded value.
+    /// Correlation IDs are `u32` so they survive `int64` being encoded as a
+    /// double for compactr.js compatibility.
+    #[must_use]
+    pub fn schema() -> SchemaType {
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "method".to_owned(),
+            Property::required(SchemaType::uint16()),
+        );
+        payload_properties(&mut properties);
+        SchemaType::object(properties)
+    }
+
+    /// Encodes the request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload is too large.
+    pub fn encode(&self) -> Result<Bytes> {
+        let mut fields = IndexMap::new();
+        fields.insert("method".into(), Value::Integer(i64::from(self.method)));
+        insert_payload(&mut fields, self.correlation_id, &self.payload);
+        encode_envelope(&Value::Object(fields), &Self::schema())
+    }
+
+    /// Decodes a request produced by [`Request::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a valid request envelope.
+    pub fn decode(buf: &mut impl Buf) -> Result<Self> {
+        let value = Decoder::decode(buf, &Self::schema())?;
+        let fields = value.as_object().ok_or_else(|| invalid("request"))?;
+        let method = fields
+            .get("method")
+            .and_then(Value::as_i64)
+            .and_then(|m| u16::try_from(m).ok())
+            .ok_or_else(|| invalid("method"))?;
+        Ok(Self {
+            method,
+            correlation_id: correlation_id(fields)?,
+            payload: payload(fields)?,
+        })
+    }
+}
+
+impl Response {
+    /// Returns the schema of encoded responses.
+    ///
+    /// A `oneOf` on `status`: `ok` carries the returned payload like a
+    /// [`Request`] does, `error` carries an error code and message.
+    #[must_use]
+    pub fn schema() -> SchemaType {
+        let mut ok = IndexMap::new();
+        payload_properties(&mut ok);
+
+        let mut error = IndexMap::new();
+        error.insert(
+            "correlationId".into(),
+            Property::required(SchemaType::int64()),
+        );
+        error.insert("code".to_owned(), Property::required(SchemaType::int32()));
+        error.insert(
+            "message".to_owned(),
+            Property::required(SchemaType::string()),
+        );
+
+        let mut variants = IndexMap::new();
+        variants.insert("ok".to_owned(), SchemaType::object(ok));
+        variants.insert("error".to_owned(), SchemaType::object(error));
+        SchemaType::one_of("status", variants)
+    }
+
+    /// Encodes the response.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the payload or error message is too large.
+    pub fn encode(&self) -> Result<Bytes> {
+        let mut fields = IndexMap::new();
+        match &self.result {
+            Ok(payload) => {
+                fields.insert("status".into(), Value::String("ok".to_owned()));
+                insert_payload(&mut fields, self.correlation_id, payload);
+            }
+            Err(error) => {
+                fields.insert("status".into(), Value::String("error".to_owned()));
+                fields.insert(
+                    "correlationId".into(),
+                    Value::Integer(i64::from(self.correlation_id)),
+                );
+                fields.insert("code".into(), Value::Integer(i64::from(error.code)));
+                fields.insert("message".into(), Value::String(error.message.clone()));
+            }
+        }
+        encode_envelope(&Value::Object(fields), &Self::schema())
+    }
+
+    /// Decodes a response produced by [`Response::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a valid response envelope.
+    pub fn decode(buf: &mut impl Buf) -> Result<Self> {
+        let value = Decoder::decode(buf, &Self::schema())?;
+        let fields = value.as_object().ok_or_else(|| invalid("response"))?;
+        let result = match fields.get("status").and_then(Value::as_str) {
+            Some("ok") => Ok(payload(fields)?),
+            Some("error") => Err(RpcError {
+                code: fields
+                    .get("code")
+                    .and_then(Value::as_i64)
+                    .and_then(|c| i32::try_from(c).ok())
+                    .ok_or_else(|| invalid("code"))?,
+                message: fields
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| invalid("message"))?
+                    .to_owned(),
+            }),
+            _ => return Err(invalid("status")),
+        };
+        Ok(Self {
+            correlation_id: correlation_id(fields)?,
+            result,
+        })
+    }
+}
+
+fn payload_properties(properties: &mut IndexMap<String, Property>) {
+    properties.insert(
+        "correlationId".into(),
+        Property::required(SchemaType::int64()),
+    );
+    properties.insert(
+        "payload".to_owned(),
+        Property::required(SchemaType::binary()),
+    );
+}
+
+fn insert_payload(fields: &mut IndexMap<Arc<str>, Value>, correlation_id: u32, payload: &Payload) {
+    fields.insert(
+        "correlationId".into(),
+        Value::Integer(i64::from(correlation_id)),
+    );
+    let mut framed = BytesMut::with_capacity(9 + payload.data.len());
+    write_schema_id(&mut framed, payload.schema);
+    framed.put_slice(&payload.data);
+    fields.insert("payload".into(), Value::Binary(framed.to_vec()));
+}
+
+fn encode_envelope(value: &Value, schema: &SchemaType) -> Result<Bytes> {
+    let mut encoder = Encoder::new();
+    encoder.encode(value, schema)?;
+    Ok(encoder.finish())
+}
+
+fn correlation_id(fields: &IndexMap<Arc<str>, Value>) -> Result<u32> {
+    fields
+        .get("correlationId")
+        .and_then(Value::as_i64)
+        .and_then(|id| u32::try_from(id).ok())
+        .ok_or_else(|| invalid("correlationId"))
+}
+
+fn payload(fields: &IndexMap<Arc<str>, Value>) -> Result<Payload> {
+    let Some(Value::Binary(framed)) = fields.get("payload") else {
+        return Err(invalid("payload"));
+    };
+    let mut buf = framed.as_slice();
+    let schema = Message::read_schema_id(&mut buf)?;
+    Ok(Payload {
+        schema,
+        data: Bytes::copy_from_slice(buf),
+    })
+}
+
+fn invalid(field: &str) -> Error {
+    DecodeError::InvalidData(format!("Invalid RPC envelope field: {field}")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> SchemaRegistry {
+        let registry = SchemaRegistry::new();
+        registry.register("Query", SchemaType::string()).unwrap();
+        registry.register("Count", SchemaType::int32()).unwrap();
+        registry.register_id(1, "Query").unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_request_roundtrip() {
+        let registry = registry();
+        let message = Message::new("Query", Value::String("users".to_owned()));
+        let request = Request {
+            method: 3,
+            correlation_id: u32::MAX,
+            payload: Payload::from_message(&message, &registry).unwrap(),
+        };
+        assert_eq!(request.payload.schema, SchemaId::Id(1));
+
+        let bytes = request.encode().unwrap();
+        let decoded = Request::decode(&mut bytes.as_ref()).unwrap();
+        assert_eq!(decoded, request);
+        assert_eq!(decoded.payload.to_message(&registry).unwrap(), message);
+    }
+
+    #[test]
+    fn test_response_roundtrip() {
+        let registry = registry();
+        let message = Message::new("Count", Value::Integer(42));
+        let ok = Response {
+            correlation_id: 7,
+            result: Ok(Payload::from_message(&message, &registry).unwrap()),
+        };
+        let error = Response {
+            correlation_id: 8,
+            result: Err(RpcError {
+                code: 404,
+                message: "no such table".to_owned(),
+            }),
+        };
+
+        for response in [ok, error] {
+            let bytes = response.encode().unwrap();
+            assert_eq!(Response::decode(&mut bytes.as_ref()).unwrap(), response);
+        }
+    }
+
+    #[test]
+    fn test_invalid_envelope() {
+        // A request envelope is not a response envelope
+        let request = Request {
+            method: 1,
+            correlation_id: 1,
+            payload: Payload {
+                schema: SchemaId::Id(1),
+                data: Bytes::new(),
+            },
+        };
+        let bytes = request.encode().unwrap();
+        assert!(Response::decode(&mut bytes.as_ref()).is_err());
+        assert!(Request::decode(&mut [0x01].as_ref()).is_err());
+    }
+}
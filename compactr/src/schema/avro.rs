@@ -0,0 +1,436 @@
+//! Conversion between [`SchemaType`] and Avro JSON schemas.
+//!
+//! | Avro                                     | Compactr                         |
+//! |------------------------------------------|----------------------------------|
+//! | `int`, `long`                            | `int32`, `int64`                 |
+//! | `float`, `double`                        | `float`, `double`                |
+//! | `string`, `bytes`, `fixed`               | plain string, binary             |
+//! | `record`                                 | object                           |
+//! | `enum`                                   | string enum                      |
+//! | `array`                                  | array                            |
+//! | `["null", T]`                            | `T`, optional inside records     |
+//! | unions of records                        | `oneOf` discriminated by `type`  |
+//! | `uuid`                                   | `uuid`                           |
+//! | `date`, `time-millis`, `time-micros`     | `date`, `time`                   |
+//! | `timestamp-millis`, `-micros`, `-nanos`  | timestamps of the same precision |
+//!
+//! Named types are referenced by their short name, without namespace. Avro
+//! maps have no Compactr counterpart and are rejected; unknown logical types
+//! fall back to their underlying type, as the Avro specification requires.
+
+use super::{
+    IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType, StringFormat,
+    TimestampPrecision,
+};
+use crate::error::{Error, Result, SchemaError};
+use indexmap::IndexMap;
+use serde_json::{json, Map, Value as Json};
+
+/// Property naming the record of a `oneOf` imported from an Avro union.
+const UNION_DISCRIMINATOR: &str = "type";
+
+impl SchemaType {
+    /// Parses an Avro schema.
+    ///
+    /// Record fields whose type is a union with `null` become optional
+    /// properties; elsewhere the `null` branch is dropped. Unions of records
+    /// become `oneOf` schemas whose variants are named after the records and
+    /// discriminated by a `type` property.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is not an Avro schema or uses a
+    /// construct without a Compactr counterpart (maps, unions of
+    /// non-record types).
+    pub fn from_avro(json: &Json) -> Result<Self> {
+        avro_type(json).map(|(schema, _)| schema)
+    }
+
+    /// Converts this schema into an Avro schema.
+    ///
+    /// `name` names the top-level record or enum; nested ones are named
+    /// after their parent and property, as in `User_address`. Formats
+    /// without an Avro counterpart (IP addresses, 128-bit integers,
+    /// decimals, ...) are exported as strings, their JSON form, and
+    /// date-times as `timestamp-millis`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a name or enum symbol is not a valid Avro name.
+    pub fn to_avro(&self, name: &str) -> Result<Json> {
+        match self {
+            Self::Boolean => Ok(json!("boolean")),
+            Self::Integer(IntegerFormat::Int64) => Ok(json!("long")),
+            Self::Integer(IntegerFormat::Int128 | IntegerFormat::Uint128) => Ok(json!("string")),
+            Self::Integer(_) => Ok(json!("int")),
+            Self::Number(NumberFormat::Float) => Ok(json!("float")),
+            Self::Number(NumberFormat::Double) => Ok(json!("double")),
+            #[cfg(feature = "decimal")]
+            Self::Number(NumberFormat::Decimal) => Ok(json!("string")),
+            Self::String(format) => Ok(string_to_avro(*format)),
+            Self::Array(items) | Self::RunLengthArray(items) => {
+                Ok(json!({"type": "array", "items": items.to_avro(name)?}))
+            }
+            Self::Object(properties) => record_to_avro(name, properties),
+            Self::Reference(reference) => Ok(json!(reference_name(reference))),
+            Self::Enum(symbols) => {
+                check_name(name)?;
+                for symbol in symbols {
+                    check_name(symbol)?;
+                }
+                Ok(json!({"type": "enum", "name": name, "symbols": symbols}))
+            }
+            Self::OneOf { variants, .. } => variants
+                .iter()
+                .map(|(variant, payload)| match payload {
+                    Self::Reference(reference) => Ok(json!(reference_name(reference))),
+                    Self::Object(properties) => record_to_avro(variant, properties),
+                    _ => record_to_avro(variant, &IndexMap::new()),
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(Json::Array),
+            Self::Null => Ok(json!("null")),
+        }
+    }
+}
+
+impl SchemaRegistry {
+    /// Registers every named type (records, enums and fixed) of an Avro
+    /// schema, or of a JSON array of Avro schemas, under its short name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a schema cannot be parsed.
+    pub fn register_avro_schemas(&self, json: &Json) -> Result<()> {
+        match json {
+            Json::Array(schemas) => {
+                for schema in schemas {
+                    self.register_avro_schemas(schema)?;
+                }
+            }
+            Json::Object(obj) => {
+                if let Some(name) = obj.get("name").and_then(Json::as_str) {
+                    self.register(short_name(name), SchemaType::from_avro(json)?)?;
+                }
+                // Named types may also be defined inline, deeper down
+                for key in ["type", "items"] {
+                    if let Some(inner) = obj.get(key) {
+                        self.register_avro_schemas(inner)?;
+                    }
+                }
+                let fields = obj.get("fields").and_then(Json::as_array);
+                for field in fields.into_iter().flatten() {
+                    if let Some(ty) = field.get("type") {
+                        self.register_avro_schemas(ty)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Parses an Avro type, returning whether it was a union with `null`.
+fn avro_type(json: &Json) -> Result<(SchemaType, bool)> {
+    match json {
+        Json::String(name) => Ok((named_type(name), false)),
+        Json::Array(branches) => union_from_avro(branches),
+        Json::Object(obj) => complex_from_avro(obj).map(|schema| (schema, false)),
+        _ => Err(invalid(format!("expected an Avro schema, got {json}"))),
+    }
+}
+
+fn primitive(name: &str) -> Option<SchemaType> {
+    Some(match name {
+        "null" => SchemaType::Null,
+        "boolean" => SchemaType::Boolean,
+        "int" => SchemaType::Integer(IntegerFormat::Int32),
+        "long" => SchemaType::Integer(IntegerFormat::Int64),
+        "float" => SchemaType::Number(NumberFormat::Float),
+        "double" => SchemaType::Number(NumberFormat::Double),
+        "bytes" => SchemaType::String(StringFormat::Binary),
+        "string" => SchemaType::String(StringFormat::Plain),
+        _ => return None,
+    })
+}
+
+/// Resolves a primitive type name or a reference to a named type.
+fn named_type(name: &str) -> SchemaType {
+    primitive(name).unwrap_or_else(|| SchemaType::reference(short_name(name)))
+}
+
+fn complex_from_avro(obj: &Map<String, Json>) -> Result<SchemaType> {
+    let ty = obj.get("type").ok_or_else(|| invalid("missing type"))?;
+    let Some(ty) = ty.as_str() else {
+        // `{"type": {...}}` and `{"type": [...]}` wrap another schema
+        return SchemaType::from_avro(ty);
+    };
+    let logical = obj.get("logicalType").and_then(Json::as_str);
+    let timestamp = |precision| Ok(SchemaType::String(StringFormat::Timestamp(precision)));
+    match (ty, logical) {
+        ("string" | "fixed", Some("uuid")) => Ok(SchemaType::String(StringFormat::Uuid)),
+        ("int", Some("date")) => Ok(SchemaType::String(StringFormat::Date)),
+        ("int", Some("time-millis")) | ("long", Some("time-micros")) => {
+            Ok(SchemaType::String(StringFormat::Time))
+        }
+        ("long", Some("timestamp-millis" | "local-timestamp-millis")) => {
+            timestamp(TimestampPrecision::Millis)
+        }
+        ("long", Some("timestamp-micros" | "local-timestamp-micros")) => {
+            timestamp(TimestampPrecision::Micros)
+        }
+        ("long", Some("timestamp-nanos" | "local-timestamp-nanos")) => {
+            timestamp(TimestampPrecision::Nanos)
+        }
+        #[cfg(feature = "decimal")]
+        ("bytes" | "fixed", Some("decimal")) => Ok(SchemaType::Number(NumberFormat::Decimal)),
+        ("record" | "error", _) => record_from_avro(obj),
+        ("enum", _) => {
+            let symbols = obj
+                .get("symbols")
+                .and_then(Json::as_array)
+                .ok_or_else(|| invalid("enum requires symbols"))?
+                .iter()
+                .map(|symbol| {
+                    symbol
+                        .as_str()
+                        .map(ToOwned::to_owned)
+                        .ok_or_else(|| invalid("enum symbols must be strings"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(SchemaType::Enum(symbols))
+        }
+        ("array", _) => {
+            let items = obj
+                .get("items")
+                .ok_or_else(|| invalid("array schema missing items"))?;
+            Ok(SchemaType::array(SchemaType::from_avro(items)?))
+        }
+        ("map", _) => Err(invalid("Avro maps are not supported")),
+        ("fixed", _) => Ok(SchemaType::String(StringFormat::Binary)),
+        (name, _) => Ok(named_type(name)),
+    }
+}
+
+fn record_from_avro(obj: &Map<String, Json>) -> Result<SchemaType> {
+    let fields = obj
+        .get("fields")
+        .and_then(Json::as_array)
+        .ok_or_else(|| invalid("record requires fields"))?;
+    let mut properties = IndexMap::new();
+    for field in fields {
+        let name = field
+            .get("name")
+            .and_then(Json::as_str)
+            .ok_or_else(|| invalid("record fields require a name"))?;
+        let ty = field
+            .get("type")
+            .ok_or_else(|| invalid(format!("field {name} is missing a type")))?;
+        let (schema, nullable) = avro_type(ty)?;
+        let property = if nullable {
+            Property::optional(schema)
+        } else {
+            Property::required(schema)
+        };
+        properties.insert(name.to_owned(), property);
+    }
+    Ok(SchemaType::Object(properties))
+}
+
+fn union_from_avro(branches: &[Json]) -> Result<(SchemaType, bool)> {
+    let nullable = branches.iter().any(|branch| branch == "null");
+    let branches: Vec<&Json> = branches.iter().filter(|b| *b != "null").collect();
+    match branches.as_slice() {
+        [] => Ok((SchemaType::Null, false)),
+        [single] => Ok((avro_type(single)?.0, nullable)),
+        _ => {
+            let mut variants = IndexMap::new();
+            for branch in branches {
+                let (name, payload) = match branch {
+                    Json::String(name) if primitive(name).is_none() => {
+                        let name = short_name(name);
+                        (name, SchemaType::reference(name))
+                    }
+                    Json::Object(obj) if obj.get("type") == Some(&json!("record")) => {
+                        let name = obj
+                            .get("name")
+                            .and_then(Json::as_str)
+                            .ok_or_else(|| invalid("records require a name"))?;
+                        (short_name(name), record_from_avro(obj)?)
+                    }
+                    _ => return Err(invalid("only unions of records and null are supported")),
+                };
+                variants.insert(name.to_owned(), payload);
+            }
+            Ok((SchemaType::one_of(UNION_DISCRIMINATOR, variants), nullable))
+        }
+    }
+}
+
+fn string_to_avro(format: StringFormat) -> Json {
+    let logical = |ty, logical| json!({"type": ty, "logicalType": logical});
+    match format {
+        StringFormat::Uuid => logical("string", "uuid"),
+        StringFormat::DateTime | StringFormat::Timestamp(TimestampPrecision::Millis) => {
+            logical("long", "timestamp-millis")
+        }
+        StringFormat::Timestamp(TimestampPrecision::Micros) => logical("long", "timestamp-micros"),
+        StringFormat::Timestamp(TimestampPrecision::Nanos) => logical("long", "timestamp-nanos"),
+        StringFormat::Date => logical("int", "date"),
+        StringFormat::Time => logical("int", "time-millis"),
+        StringFormat::Binary => json!("bytes"),
+        _ => json!("string"),
+    }
+}
+
+fn record_to_avro(name: &str, properties: &IndexMap<String, Property>) -> Result<Json> {
+    check_name(name)?;
+    let fields = properties
+        .iter()
+        .map(|(field, property)| {
+            check_name(field)?;
+            let ty = property.schema_type.to_avro(&format!("{name}_{field}"))?;
+            Ok(if property.required {
+                json!({"name": field, "type": ty})
+            } else {
+                // `null` comes first so that it can be the default
+                let branches = match ty {
+                    Json::Array(mut branches) => {
+                        branches.insert(0, json!("null"));
+                        branches
+                    }
+                    ty => vec![json!("null"), ty],
+                };
+                json!({"name": field, "type": branches, "default": null})
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(json!({"type": "record", "name": name, "fields": fields}))
+}
+
+/// Checks that `name` matches `[A-Za-z_][A-Za-z0-9_]*`.
+fn check_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid(format!("{name:?} is not a valid Avro name")))
+    }
+}
+
+/// Strips the namespace of a full Avro name.
+fn short_name(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+/// Returns the schema name of a reference such as `#/components/schemas/User`.
+fn reference_name(reference: &str) -> &str {
+    reference.rsplit('/').next().unwrap_or(reference)
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    SchemaError::InvalidSchema(msg.into()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user() -> Json {
+        json!({
+            "type": "record",
+            "name": "User",
+            "namespace": "com.example",
+            "fields": [
+                {"name": "id", "type": {"type": "string", "logicalType": "uuid"}},
+                {"name": "age", "type": "int"},
+                {"name": "email", "type": ["null", "string"], "default": null},
+                {"name": "born", "type": {"type": "int", "logicalType": "date"}},
+                {"name": "seen", "type": {"type": "long", "logicalType": "timestamp-micros"}},
+                {"name": "role", "type": {"type": "enum", "name": "Role", "symbols": ["ADMIN", "MEMBER"]}},
+                {"name": "tags", "type": {"type": "array", "items": "string"}},
+                {"name": "pet", "type": [
+                    {"type": "record", "name": "Dog", "fields": [{"name": "barks", "type": "boolean"}]},
+                    "com.example.Cat"
+                ]}
+            ]
+        })
+    }
+
+    #[test]
+    fn test_from_avro() {
+        let SchemaType::Object(properties) = SchemaType::from_avro(&user()).unwrap() else {
+            panic!("expected an object");
+        };
+        let schema = |name: &str| &properties[name].schema_type;
+        assert_eq!(*schema("id"), SchemaType::string_uuid());
+        assert_eq!(*schema("age"), SchemaType::int32());
+        assert_eq!(*schema("email"), SchemaType::string());
+        assert!(!properties["email"].required);
+        assert!(properties["age"].required);
+        assert_eq!(*schema("born"), SchemaType::string_date());
+        assert_eq!(
+            *schema("seen"),
+            SchemaType::timestamp(TimestampPrecision::Micros)
+        );
+        assert_eq!(
+            *schema("role"),
+            SchemaType::string_enum(["ADMIN", "MEMBER"])
+        );
+        assert_eq!(*schema("tags"), SchemaType::array(SchemaType::string()));
+
+        let SchemaType::OneOf {
+            discriminator,
+            variants,
+        } = schema("pet")
+        else {
+            panic!("expected a oneOf");
+        };
+        assert_eq!(discriminator, "type");
+        assert!(matches!(variants["Dog"], SchemaType::Object(_)));
+        assert_eq!(variants["Cat"], SchemaType::reference("Cat"));
+    }
+
+    #[test]
+    fn test_register_named_types() {
+        let registry = SchemaRegistry::new();
+        registry.register_avro_schemas(&user()).unwrap();
+        assert_eq!(registry.names().unwrap(), ["Dog", "Role", "User"]);
+        assert_eq!(
+            registry.get("Role").unwrap(),
+            Some(SchemaType::string_enum(["ADMIN", "MEMBER"]))
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let schema = SchemaType::from_avro(&user()).unwrap();
+        let avro = schema.to_avro("User").unwrap();
+        assert_eq!(avro["fields"][2]["type"], json!(["null", "string"]));
+        assert_eq!(avro["fields"][5]["type"]["name"], "User_role");
+        assert_eq!(SchemaType::from_avro(&avro).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!(SchemaType::from_avro(&json!({"type": "map", "values": "long"})).is_err());
+        assert!(SchemaType::from_avro(&json!(["int", "string"])).is_err());
+        assert!(SchemaType::from_avro(&json!(42)).is_err());
+
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "user-id".to_owned(),
+            Property::required(SchemaType::int64()),
+        );
+        assert!(SchemaType::object(properties).to_avro("User").is_err());
+        assert_eq!(
+            SchemaType::string_ipv4().to_avro("Ip").unwrap(),
+            json!("string")
+        );
+    }
+}
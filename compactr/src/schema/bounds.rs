@@ -0,0 +1,310 @@
+//! Bounds on the encoded size of schemas.
+
+use crate::codec::{ArrayFormat, LengthMode, ObjectFormat};
+use crate::error::Result;
+use crate::schema::{IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType};
+use crate::schema::{SizeWidth, StringFormat};
+
+/// Smallest and largest encoded size, `None` when there is no upper bound.
+type Bounds = (usize, Option<usize>);
+
+const UNBOUNDED: Bounds = (0, None);
+
+impl SchemaType {
+    /// Returns the smallest and largest number of bytes a value of this
+    /// schema can encode to, with the default layout.
+    ///
+    /// Like [`Iterator::size_hint`], the upper bound is `None` when there is
+    /// none, as for arrays. Use the bounds to pre-allocate buffers, plan
+    /// capacity, or check that messages always fit a datagram. References
+    /// and custom formats are unknown here and count as unbounded; see
+    /// [`SchemaType::size_bounds_with_registry`] to resolve references.
+    ///
+    /// ```rust
+    /// use compactr::{Property, SchemaType};
+    ///
+    /// let point = SchemaType::object([
+    ///     ("x", Property::required(SchemaType::int32())),
+    ///     ("y", Property::required(SchemaType::int32())),
+    /// ]);
+    /// // The property count, then an index, a size and 4 bytes per property
+    /// assert_eq!(point.size_bounds(), (13, Some(13)));
+    /// assert_eq!(SchemaType::array(point).size_bounds(), (0, None));
+    /// ```
+    #[must_use]
+    pub fn size_bounds(&self) -> (usize, Option<usize>) {
+        let registry = SchemaRegistry::new();
+        let mut bounds = SizeBounds {
+            registry: &registry,
+            resolve: false,
+            resolving: Vec::new(),
+        };
+        bounds.of(self).unwrap_or(UNBOUNDED)
+    }
+
+    /// Returns the smallest and largest encoded size of a value of this
+    /// schema, resolving references and using the layout of `registry`.
+    ///
+    /// Recursive references count as unbounded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a reference is not registered.
+    pub fn size_bounds_with_registry(
+        &self,
+        registry: &SchemaRegistry,
+    ) -> Result<(usize, Option<usize>)> {
+        SizeBounds {
+            registry,
+            resolve: true,
+            resolving: Vec::new(),
+        }
+        .of(self)
+    }
+}
+
+struct SizeBounds<'a> {
+    registry: &'a SchemaRegistry,
+    /// Whether to look references up in the registry
+    resolve: bool,
+    /// References being resolved, to stop at recursion
+    resolving: Vec<String>,
+}
+
+impl SizeBounds<'_> {
+    fn of(&mut self, schema: &SchemaType) -> Result<Bounds> {
+        let mode = self.registry.length_mode();
+        let bounds = match schema {
+            SchemaType::Boolean | SchemaType::Enum(_) | SchemaType::Null => exactly(1),
+            SchemaType::Integer(format) => exactly(match format {
+                IntegerFormat::Int8 | IntegerFormat::Uint8 => 1,
+                IntegerFormat::Int16 | IntegerFormat::Uint16 => 2,
+                IntegerFormat::Int32 => 4,
+                IntegerFormat::Int64 => 8,
+                IntegerFormat::Int128 | IntegerFormat::Uint128 => 16,
+            }),
+            SchemaType::Number(NumberFormat::Float) => exactly(4),
+            SchemaType::Number(NumberFormat::Double) => exactly(8),
+            // Scale, length and a mantissa of up to 97 signed bits
+            #[cfg(feature = "decimal")]
+            SchemaType::Number(NumberFormat::Decimal) => (2, Some(2 + 13)),
+            SchemaType::String(format) => string(*format, mode),
+            SchemaType::Array(_) if self.registry.array_format() == ArrayFormat::CountPrefixed => {
+                (prefix_len(mode, 4), None)
+            }
+            SchemaType::Array(_) => UNBOUNDED,
+            SchemaType::RunLengthArray(_) => (prefix_len(mode, 4), None),
+            SchemaType::Object(properties) => self.object(properties)?,
+            SchemaType::Reference(name) => self.reference(name)?,
+            SchemaType::OneOf { variants, .. } => {
+                let mut payloads: Option<Bounds> = None;
+                for payload in variants.values() {
+                    let (min, max) = match payload {
+                        SchemaType::Null => exactly(0),
+                        payload => self.of(payload)?,
+                    };
+                    payloads = Some(match payloads {
+                        Some((lo, hi)) => (lo.min(min), hi.zip(max).map(|(a, b)| a.max(b))),
+                        None => (min, max),
+                    });
+                }
+                let (min, max) = payloads.unwrap_or((0, Some(0)));
+                (
+                    min.saturating_add(1),
+                    max.and_then(|max| max.checked_add(1)),
+                )
+            }
+        };
+        Ok(bounds)
+    }
+
+    fn reference(&mut self, name: &str) -> Result<Bounds> {
+        if !self.resolve || self.resolving.iter().any(|r| r == name) {
+            return Ok(UNBOUNDED);
+        }
+        let resolved = self.registry.resolve_ref(name)?;
+        self.resolving.push(name.to_owned());
+        let bounds = self.of(&resolved);
+        self.resolving.pop();
+        bounds
+    }
+
+    fn object(&mut self, properties: &indexmap::IndexMap<String, Property>) -> Result<Bounds> {
+        let format = self.registry.object_format();
+        let (mut min, mut max) = match format {
+            // The property count
+            ObjectFormat::Indexed => exactly(1),
+            // One bit per optional property
+            ObjectFormat::Bitmap => {
+                let optional = properties.values().filter(|p| !p.required).count();
+                exactly((optional + 7) / 8)
+            }
+        };
+        let index = usize::from(format == ObjectFormat::Indexed);
+        for property in properties.values() {
+            let (lo, hi) = self.property(property)?;
+            if property.required {
+                min = min.saturating_add(index + lo);
+            }
+            max = max.zip(hi).and_then(|(a, b)| a.checked_add(index + b));
+        }
+        Ok((min, max))
+    }
+
+    /// Bounds of a property's size header and value.
+    fn property(&mut self, property: &Property) -> Result<Bounds> {
+        let (min, max) = match &property.schema_type {
+            // Referenced schemas keep their top-level layout inside objects
+            SchemaType::Reference(name) => self.reference(name)?,
+            // Strings are framed by the property size alone
+            SchemaType::String(StringFormat::Plain | StringFormat::Long) => UNBOUNDED,
+            schema => self.of(schema)?,
+        };
+        let mode = self.registry.length_mode();
+        let bounds = match property.size_width {
+            SizeWidth::Auto if mode == LengthMode::Varint => (
+                varint_len(min) + min,
+                max.and_then(|max| max.checked_add(varint_len(max))),
+            ),
+            SizeWidth::Auto => {
+                let max = max.unwrap_or(255).min(255);
+                let is_compound = matches!(
+                    *self.registry.resolve(&property.schema_type)?,
+                    SchemaType::Array(_) | SchemaType::RunLengthArray(_) | SchemaType::Object(_)
+                );
+                if is_compound {
+                    (2 + min.min(255), Some(2 + max))
+                } else {
+                    // Empty values take a 0x00 flag before the size
+                    (1 + min.clamp(1, 255), Some(1 + max.max(1)))
+                }
+            }
+            SizeWidth::U8 => sized(1, min, max),
+            SizeWidth::U16 => sized(2, min, max),
+            SizeWidth::U32 => sized(4, min, max),
+        };
+        Ok(bounds)
+    }
+}
+
+const fn exactly(size: usize) -> Bounds {
+    (size, Some(size))
+}
+
+fn string(format: StringFormat, mode: LengthMode) -> Bounds {
+    let prefixed = |width: usize| {
+        let max = match mode {
+            LengthMode::Fixed => largest(width).checked_add(width),
+            LengthMode::Varint => None,
+        };
+        (prefix_len(mode, width), max)
+    };
+    match format {
+        StringFormat::Plain => prefixed(2),
+        StringFormat::Long | StringFormat::Binary => prefixed(4),
+        StringFormat::Uuid | StringFormat::Ipv6 => exactly(16),
+        StringFormat::DateTime => exactly(9),
+        StringFormat::DateTimeTz => exactly(11),
+        StringFormat::Timestamp(_) | StringFormat::GeoPoint => exactly(8),
+        StringFormat::Date | StringFormat::Time | StringFormat::Ipv4 => exactly(4),
+        // A scheme code, then the rest as a plain string
+        StringFormat::Uri => (3, Some(3 + 0xFFFF)),
+        // Address family, address and port
+        StringFormat::SocketAddr => (1 + 4 + 2, Some(1 + 16 + 2)),
+        StringFormat::Custom(_) => UNBOUNDED,
+    }
+}
+
+/// Bounds of a value framed by a fixed-width size header.
+fn sized(width: usize, min: usize, max: Option<usize>) -> Bounds {
+    let limit = largest(width);
+    let max = max.map_or(limit, |max| max.min(limit));
+    (width.saturating_add(min.min(limit)), width.checked_add(max))
+}
+
+/// Smallest prefix of a length that is `width` bytes wide in fixed mode.
+const fn prefix_len(mode: LengthMode, width: usize) -> usize {
+    match mode {
+        LengthMode::Fixed => width,
+        LengthMode::Varint => 1,
+    }
+}
+
+/// Largest length a `width`-byte prefix holds.
+fn largest(width: usize) -> usize {
+    u32::try_from(width * 8)
+        .ok()
+        .and_then(|bits| 1usize.checked_shl(bits))
+        .map_or(usize::MAX, |limit| limit - 1)
+}
+
+/// Bytes of `value` as a LEB128 varint.
+const fn varint_len(mut value: usize) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Encoder;
+    use crate::value::Value;
+
+    #[test]
+    fn test_size_bounds() {
+        assert_eq!(SchemaType::int64().size_bounds(), (8, Some(8)));
+        assert_eq!(SchemaType::string().size_bounds(), (2, Some(2 + 65535)));
+        assert_eq!(SchemaType::reference("User").size_bounds(), (0, None));
+
+        let schema = SchemaType::object([
+            ("id", Property::required(SchemaType::int32())),
+            ("name", Property::optional(SchemaType::string())),
+            (
+                "tags",
+                Property::optional(SchemaType::array(SchemaType::int8())),
+            ),
+        ]);
+        // Count, then index, size and value of the required id; the optional
+        // name and tags add at most an index, a header and 255 bytes each
+        assert_eq!(schema.size_bounds(), (1 + 6, Some(1 + 6 + 257 + 258)));
+
+        let mut enc = Encoder::new();
+        enc.encode(&Value::object([("id", 1)]), &schema).unwrap();
+        assert_eq!(enc.finish().len(), 7);
+
+        let registry = SchemaRegistry::new().with_length_mode(LengthMode::Varint);
+        registry
+            .register(
+                "Node",
+                SchemaType::object([
+                    ("value", Property::required(SchemaType::uint8())),
+                    ("next", Property::optional(SchemaType::reference("Node"))),
+                ]),
+            )
+            .unwrap();
+        let node = SchemaType::reference("Node");
+        assert_eq!(
+            node.size_bounds_with_registry(&registry).unwrap(),
+            (4, None)
+        );
+        assert!(SchemaType::reference("Missing")
+            .size_bounds_with_registry(&registry)
+            .is_err());
+
+        let event = SchemaType::one_of(
+            "type",
+            [
+                ("ping", SchemaType::null()),
+                ("move", SchemaType::object([("x", SchemaType::int16())])),
+            ]
+            .into_iter()
+            .map(|(name, payload)| (name.to_owned(), payload))
+            .collect(),
+        );
+        assert_eq!(event.size_bounds(), (1, Some(1 + 5)));
+    }
+}
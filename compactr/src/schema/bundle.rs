@@ -0,0 +1,493 @@
+//! Binary bundles of registered schemas.
+//!
+//! A bundle holds every schema of a [`SchemaRegistry`] together with the
+//! numeric IDs assigned to them, so schemas compiled from an `OpenAPI`
+//! document at build time (e.g. with `compactr-cli bundle`) can be loaded
+//! at startup without parsing JSON.
+//!
+//! Format:
+//! - 4 bytes: magic `CPRB`
+//! - 1 byte: format version
+//! - u32 BE: number of schemas, then per schema its name, a 1-byte flag
+//!   followed by a u32 BE numeric ID when one is assigned, and the schema
+//!
+//! Schemas are a 1-byte kind tag followed by their formats, properties or
+//! variants. Strings are a u16 BE length followed by UTF-8 bytes.
+//! Property [metadata](super::Metadata) is documentation and is not kept.
+
+use super::{
+    IntegerFormat, Metadata, NumberFormat, Property, SchemaRegistry, SchemaType, SizeWidth,
+    StringFormat, TimestampPrecision,
+};
+use crate::error::{DecodeError, EncodeError, Result};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use indexmap::IndexMap;
+
+const MAGIC: &[u8; 4] = b"CPRB";
+const VERSION: u8 = 1;
+
+const KIND_BOOLEAN: u8 = 0;
+const KIND_INTEGER: u8 = 1;
+const KIND_NUMBER: u8 = 2;
+const KIND_STRING: u8 = 3;
+const KIND_ARRAY: u8 = 4;
+const KIND_OBJECT: u8 = 5;
+const KIND_REFERENCE: u8 = 6;
+const KIND_ENUM: u8 = 7;
+const KIND_ONE_OF: u8 = 8;
+const KIND_NULL: u8 = 9;
+const KIND_RUN_LENGTH_ARRAY: u8 = 10;
+
+/// Maximum schema nesting accepted when loading, so malicious bundles
+/// cannot exhaust the stack.
+const MAX_DEPTH: usize = 128;
+
+impl SchemaRegistry {
+    /// Serializes every registered schema and numeric ID into a bundle.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a schema uses a custom format, which cannot be
+    /// bundled, if a name or container exceeds the bundle's size limits, or
+    /// the lock is poisoned.
+    pub fn to_bundle(&self) -> Result<Bytes> {
+        let names = self.names()?;
+        let mut buf = BytesMut::new();
+        buf.put_slice(MAGIC);
+        buf.put_u8(VERSION);
+        buf.put_u32(len_u32(names.len())?);
+        for name in &names {
+            let Some(schema) = self.get(name)? else {
+                continue;
+            };
+            put_str(&mut buf, name)?;
+            match self.id_of(name)? {
+                Some(id) => {
+                    buf.put_u8(1);
+                    buf.put_u32(id);
+                }
+                None => buf.put_u8(0),
+            }
+            put_schema(&mut buf, &schema)?;
+        }
+        Ok(buf.freeze())
+    }
+
+    /// Loads a bundle written by [`SchemaRegistry::to_bundle`] into a new
+    /// registry.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is not a valid bundle or uses an
+    /// unsupported format version.
+    pub fn from_bundle(buf: &mut impl Buf) -> Result<Self> {
+        ensure(buf, MAGIC.len() + 1)?;
+        let mut magic = [0; 4];
+        buf.copy_to_slice(&mut magic);
+        if &magic != MAGIC {
+            return Err(invalid("not a schema bundle"));
+        }
+        let version = buf.get_u8();
+        if version != VERSION {
+            return Err(invalid(format!("unsupported bundle version {version}")));
+        }
+
+        let registry = Self::new();
+        ensure(buf, 4)?;
+        for _ in 0..buf.get_u32() {
+            let name = get_str(buf)?;
+            ensure(buf, 1)?;
+            let id = match buf.get_u8() {
+                0 => None,
+                1 => {
+                    ensure(buf, 4)?;
+                    Some(buf.get_u32())
+                }
+                flag => return Err(invalid(format!("invalid id flag {flag}"))),
+            };
+            registry.register(name.clone(), get_schema(buf, 0)?)?;
+            if let Some(id) = id {
+                registry.register_id(id, &name)?;
+            }
+        }
+        Ok(registry)
+    }
+}
+
+fn put_schema(buf: &mut BytesMut, schema: &SchemaType) -> Result<()> {
+    match schema {
+        SchemaType::Boolean => buf.put_u8(KIND_BOOLEAN),
+        SchemaType::Integer(format) => {
+            buf.put_u8(KIND_INTEGER);
+            buf.put_u8(match format {
+                IntegerFormat::Int8 => 0,
+                IntegerFormat::Int16 => 1,
+                IntegerFormat::Int32 => 2,
+                IntegerFormat::Int64 => 3,
+                IntegerFormat::Uint8 => 4,
+                IntegerFormat::Uint16 => 5,
+                IntegerFormat::Int128 => 6,
+                IntegerFormat::Uint128 => 7,
+            });
+        }
+        SchemaType::Number(format) => {
+            buf.put_u8(KIND_NUMBER);
+            buf.put_u8(match format {
+                NumberFormat::Float => 0,
+                NumberFormat::Double => 1,
+                #[cfg(feature = "decimal")]
+                NumberFormat::Decimal => 2,
+            });
+        }
+        SchemaType::String(format) => {
+            buf.put_u8(KIND_STRING);
+            put_string_format(buf, *format)?;
+        }
+        SchemaType::Array(items) => {
+            buf.put_u8(KIND_ARRAY);
+            put_schema(buf, items)?;
+        }
+        SchemaType::Object(properties) => {
+            buf.put_u8(KIND_OBJECT);
+            buf.put_u16(len_u16(properties.len())?);
+            for (name, property) in properties {
+                put_str(buf, name)?;
+                let has_aliases = !property.aliases.is_empty();
+                buf.put_u8(
+                    property_flags(property)
+                        | u8::from(property.sensitive) << 3
+                        | u8::from(has_aliases) << 4,
+                );
+                if has_aliases {
+                    buf.put_u16(len_u16(property.aliases.len())?);
+                    for alias in &property.aliases {
+                        put_str(buf, alias)?;
+                    }
+                }
+                put_schema(buf, &property.schema_type)?;
+            }
+        }
+        SchemaType::Reference(reference) => {
+            buf.put_u8(KIND_REFERENCE);
+            put_str(buf, reference)?;
+        }
+        SchemaType::Enum(variants) => {
+            buf.put_u8(KIND_ENUM);
+            buf.put_u16(len_u16(variants.len())?);
+            for variant in variants {
+                put_str(buf, variant)?;
+            }
+        }
+        SchemaType::OneOf {
+            discriminator,
+            variants,
+        } => {
+            buf.put_u8(KIND_ONE_OF);
+            put_str(buf, discriminator)?;
+            buf.put_u16(len_u16(variants.len())?);
+            for (name, payload) in variants {
+                put_str(buf, name)?;
+                put_schema(buf, payload)?;
+            }
+        }
+        SchemaType::Null => buf.put_u8(KIND_NULL),
+        SchemaType::RunLengthArray(items) => {
+            buf.put_u8(KIND_RUN_LENGTH_ARRAY);
+            put_schema(buf, items)?;
+        }
+    }
+    Ok(())
+}
+
+fn put_string_format(buf: &mut BytesMut, format: StringFormat) -> Result<()> {
+    buf.put_u8(match format {
+        StringFormat::Plain => 0,
+        StringFormat::Uuid => 1,
+        StringFormat::DateTime => 2,
+        StringFormat::DateTimeTz => 3,
+        StringFormat::Timestamp(_) => 4,
+        StringFormat::Date => 5,
+        StringFormat::Time => 6,
+        StringFormat::Uri => 7,
+        StringFormat::SocketAddr => 8,
+        StringFormat::GeoPoint => 9,
+        StringFormat::Ipv4 => 10,
+        StringFormat::Ipv6 => 11,
+        StringFormat::Binary => 12,
+        StringFormat::Long => 13,
+        // Custom formats are resolved by name at runtime, and the names are
+        // `&'static str`s that a loaded bundle could not provide
+        StringFormat::Custom(name) => {
+            return Err(EncodeError::InvalidFormat(format!(
+                "Custom format {name} cannot be bundled"
+            ))
+            .into())
+        }
+    });
+    if let StringFormat::Timestamp(precision) = format {
+        buf.put_u8(match precision {
+            TimestampPrecision::Seconds => 0,
+            TimestampPrecision::Millis => 1,
+            TimestampPrecision::Micros => 2,
+            TimestampPrecision::Nanos => 3,
+        });
+    }
+    Ok(())
+}
+
+fn get_schema(buf: &mut impl Buf, depth: usize) -> Result<SchemaType> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("schema nested too deeply"));
+    }
+    let schema = match get_u8(buf)? {
+        KIND_BOOLEAN => SchemaType::Boolean,
+        KIND_INTEGER => SchemaType::Integer(match get_u8(buf)? {
+            0 => IntegerFormat::Int8,
+            1 => IntegerFormat::Int16,
+            2 => IntegerFormat::Int32,
+            3 => IntegerFormat::Int64,
+            4 => IntegerFormat::Uint8,
+            5 => IntegerFormat::Uint16,
+            6 => IntegerFormat::Int128,
+            7 => IntegerFormat::Uint128,
+            other => return Err(invalid(format!("invalid integer format {other}"))),
+        }),
+        KIND_NUMBER => SchemaType::Number(match get_u8(buf)? {
+            0 => NumberFormat::Float,
+            1 => NumberFormat::Double,
+            #[cfg(feature = "decimal")]
+            2 => NumberFormat::Decimal,
+            other => return Err(invalid(format!("invalid number format {other}"))),
+        }),
+        KIND_STRING => SchemaType::String(get_string_format(buf)?),
+        KIND_ARRAY => SchemaType::array(get_schema(buf, depth + 1)?),
+        KIND_RUN_LENGTH_ARRAY => SchemaType::run_length_array(get_schema(buf, depth + 1)?),
+        KIND_OBJECT => {
+            let len = get_u16(buf)?;
+            let mut properties = IndexMap::with_capacity(usize::from(len));
+            for _ in 0..len {
+                let name = get_str(buf)?;
+                let (required, size_width, sensitive, has_aliases) = get_property_flags(buf)?;
+                let aliases = if has_aliases {
+                    let len = get_u16(buf)?;
+                    (0..len).map(|_| get_str(buf)).collect::<Result<_>>()?
+                } else {
+                    Vec::new()
+                };
+                let schema_type = get_schema(buf, depth + 1)?;
+                properties.insert(
+                    name,
+                    Property {
+                        schema_type,
+                        required,
+                        size_width,
+                        sensitive,
+                        aliases,
+                        metadata: Metadata::new(),
+                    },
+                );
+            }
+            SchemaType::Object(properties)
+        }
+        KIND_REFERENCE => SchemaType::Reference(get_str(buf)?),
+        KIND_ENUM => {
+            let len = get_u16(buf)?;
+            SchemaType::Enum((0..len).map(|_| get_str(buf)).collect::<Result<_>>()?)
+        }
+        KIND_ONE_OF => {
+            let discriminator = get_str(buf)?;
+            let len = get_u16(buf)?;
+            let mut variants = IndexMap::with_capacity(usize::from(len));
+            for _ in 0..len {
+                let name = get_str(buf)?;
+                variants.insert(name, get_schema(buf, depth + 1)?);
+            }
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            }
+        }
+        KIND_NULL => SchemaType::Null,
+        other => return Err(invalid(format!("invalid schema kind {other}"))),
+    };
+    Ok(schema)
+}
+
+fn get_string_format(buf: &mut impl Buf) -> Result<StringFormat> {
+    Ok(match get_u8(buf)? {
+        0 => StringFormat::Plain,
+        1 => StringFormat::Uuid,
+        2 => StringFormat::DateTime,
+        3 => StringFormat::DateTimeTz,
+        4 => StringFormat::Timestamp(match get_u8(buf)? {
+            0 => TimestampPrecision::Seconds,
+            1 => TimestampPrecision::Millis,
+            2 => TimestampPrecision::Micros,
+            3 => TimestampPrecision::Nanos,
+            other => return Err(invalid(format!("invalid timestamp precision {other}"))),
+        }),
+        5 => StringFormat::Date,
+        6 => StringFormat::Time,
+        7 => StringFormat::Uri,
+        8 => StringFormat::SocketAddr,
+        9 => StringFormat::GeoPoint,
+        10 => StringFormat::Ipv4,
+        11 => StringFormat::Ipv6,
+        12 => StringFormat::Binary,
+        13 => StringFormat::Long,
+        other => return Err(invalid(format!("invalid string format {other}"))),
+    })
+}
+
+/// Packs a property's required flag (bit 0) and size width (bits 1-2), the
+/// flags that affect the wire format. Bundles add the sensitive flag (bit 3)
+/// and whether a list of aliases follows (bit 4).
+pub(super) fn property_flags(property: &Property) -> u8 {
+    let width = match property.size_width {
+        SizeWidth::Auto => 0,
+        SizeWidth::U8 => 1,
+        SizeWidth::U16 => 2,
+        SizeWidth::U32 => 3,
+    };
+    u8::from(property.required) | width << 1
+}
+
+fn get_property_flags(buf: &mut impl Buf) -> Result<(bool, SizeWidth, bool, bool)> {
+    let flags = get_u8(buf)?;
+    if flags >> 5 != 0 {
+        return Err(invalid(format!("invalid property flags {flags}")));
+    }
+    let size_width = match (flags >> 1) & 0b11 {
+        0 => SizeWidth::Auto,
+        1 => SizeWidth::U8,
+        2 => SizeWidth::U16,
+        _ => SizeWidth::U32,
+    };
+    let (sensitive, has_aliases) = (flags & 0b1000 != 0, flags & 0b1_0000 != 0);
+    Ok((flags & 1 != 0, size_width, sensitive, has_aliases))
+}
+
+fn put_str(buf: &mut BytesMut, s: &str) -> Result<()> {
+    buf.put_u16(len_u16(s.len())?);
+    buf.put_slice(s.as_bytes());
+    Ok(())
+}
+
+fn get_str(buf: &mut impl Buf) -> Result<String> {
+    let len = usize::from(get_u16(buf)?);
+    ensure(buf, len)?;
+    let mut bytes = vec![0; len];
+    buf.copy_to_slice(&mut bytes);
+    String::from_utf8(bytes).map_err(|e| invalid(format!("invalid UTF-8: {e}")))
+}
+
+fn get_u8(buf: &mut impl Buf) -> Result<u8> {
+    ensure(buf, 1)?;
+    Ok(buf.get_u8())
+}
+
+fn get_u16(buf: &mut impl Buf) -> Result<u16> {
+    ensure(buf, 2)?;
+    Ok(buf.get_u16())
+}
+
+fn ensure(buf: &impl Buf, len: usize) -> Result<()> {
+    if buf.remaining() < len {
+        return Err(DecodeError::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn len_u16(len: usize) -> Result<u16> {
+    u16::try_from(len)
+        .map_err(|_| EncodeError::InvalidFormat(format!("Too long for a bundle: {len}")).into())
+}
+
+fn len_u32(len: usize) -> Result<u32> {
+    u32::try_from(len)
+        .map_err(|_| EncodeError::InvalidFormat(format!("Too long for a bundle: {len}")).into())
+}
+
+fn invalid(msg: impl Into<String>) -> crate::error::Error {
+    DecodeError::InvalidData(format!("Invalid schema bundle: {}", msg.into())).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> SchemaRegistry {
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "id".to_owned(),
+            Property::required(SchemaType::string_uuid())
+                .with_sensitive(true)
+                .with_alias("uuid"),
+        );
+        properties.insert(
+            "at".to_owned(),
+            Property::optional(SchemaType::String(StringFormat::Timestamp(
+                TimestampPrecision::Micros,
+            ))),
+        );
+        properties.insert(
+            "tags".to_owned(),
+            Property::optional(SchemaType::array(SchemaType::string()))
+                .with_size_width(SizeWidth::U16),
+        );
+        properties.insert(
+            "status".to_owned(),
+            Property::required(SchemaType::string_enum(["active", "deleted"])),
+        );
+        let mut variants = IndexMap::new();
+        variants.insert("user".to_owned(), SchemaType::reference("#/User"));
+        variants.insert("anonymous".to_owned(), SchemaType::Null);
+
+        let registry = SchemaRegistry::new();
+        registry
+            .register("User", SchemaType::object(properties))
+            .unwrap();
+        registry
+            .register("Actor", SchemaType::one_of("kind", variants))
+            .unwrap();
+        registry.register_id(7, "User").unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_bundle_roundtrip() {
+        let registry = registry();
+        let bundle = registry.to_bundle().unwrap();
+        assert_eq!(&bundle[..4], MAGIC);
+
+        let loaded = SchemaRegistry::from_bundle(&mut bundle.as_ref()).unwrap();
+        assert_eq!(loaded.names().unwrap(), ["Actor", "User"]);
+        for name in ["Actor", "User"] {
+            assert_eq!(loaded.get(name).unwrap(), registry.get(name).unwrap());
+        }
+        assert_eq!(loaded.id_of("User").unwrap(), Some(7));
+        assert_eq!(loaded.id_of("Actor").unwrap(), None);
+        // Bundles are deterministic
+        assert_eq!(loaded.to_bundle().unwrap(), bundle);
+    }
+
+    #[test]
+    fn test_invalid_bundle() {
+        let bundle = registry().to_bundle().unwrap();
+        assert!(SchemaRegistry::from_bundle(&mut &b"nope\x01"[..]).is_err());
+        assert!(SchemaRegistry::from_bundle(&mut &bundle[..bundle.len() - 1]).is_err());
+
+        let mut version = bundle.to_vec();
+        version[4] = 99;
+        assert!(SchemaRegistry::from_bundle(&mut version.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_custom_format_rejected() {
+        let registry = SchemaRegistry::new();
+        registry
+            .register("Money", SchemaType::String(StringFormat::Custom("money")))
+            .unwrap();
+        assert!(registry.to_bundle().is_err());
+    }
+}
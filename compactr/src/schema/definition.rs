@@ -1,6 +1,8 @@
 //! Schema type definitions.
 
+use crate::value::Value;
 use indexmap::IndexMap;
+use smallvec::SmallVec;
 use std::fmt;
 
 /// Represents the type of a schema field.
@@ -21,12 +23,35 @@ pub enum SchemaType {
     /// Array of items with a specific schema
     Array(Box<SchemaType>),
 
+    /// Array stored as runs of repeated items, for arrays dominated by
+    /// repeated values such as flags or category IDs
+    ///
+    /// Decodes to the same values as [`SchemaType::Array`]; only the layout
+    /// differs.
+    RunLengthArray(Box<SchemaType>),
+
     /// Object with named properties
     Object(IndexMap<String, Property>),
 
     /// Reference to another schema (e.g., `"#/ComponentName"`)
     Reference(String),
 
+    /// String enumeration (stored as a 1-byte index into the variant names)
+    Enum(Vec<String>),
+
+    /// Tagged union mapping to `oneOf` with a discriminator (stored as a
+    /// 1-byte variant index followed by the variant payload)
+    ///
+    /// Values are objects whose discriminator property names the variant;
+    /// the remaining properties are encoded with the variant's schema.
+    /// Variants with a [`SchemaType::Null`] schema carry no payload.
+    OneOf {
+        /// Name of the property holding the variant name
+        discriminator: String,
+        /// Variant names and their payload schemas
+        variants: IndexMap<String, SchemaType>,
+    },
+
     /// Null type
     Null,
 }
@@ -34,10 +59,22 @@ pub enum SchemaType {
 /// Integer format specifications.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum IntegerFormat {
+    /// 8-bit signed integer
+    Int8,
+    /// 16-bit signed integer
+    Int16,
     /// 32-bit signed integer
     Int32,
     /// 64-bit signed integer
     Int64,
+    /// 8-bit unsigned integer
+    Uint8,
+    /// 16-bit unsigned integer
+    Uint16,
+    /// 128-bit signed integer
+    Int128,
+    /// 128-bit unsigned integer
+    Uint128,
 }
 
 /// Number (floating-point) format specifications.
@@ -47,6 +84,9 @@ pub enum NumberFormat {
     Float,
     /// 64-bit IEEE 754 floating point
     Double,
+    /// Arbitrary-precision decimal (stored as a scaled integer)
+    #[cfg(feature = "decimal")]
+    Decimal,
 }
 
 /// String format specifications.
@@ -54,18 +94,73 @@ pub enum NumberFormat {
 pub enum StringFormat {
     /// Plain UTF-8 string
     Plain,
+    /// UTF-8 string with a u32 length prefix instead of u16, for documents
+    /// over 64 KiB
+    Long,
     /// UUID in standard format (stored as 16 bytes)
     Uuid,
-    /// ISO 8601 datetime (stored as Unix timestamp in ms)
+    /// ISO 8601 datetime (stored as 9 calendar component bytes, millisecond precision)
     DateTime,
+    /// ISO 8601 datetime that keeps its UTC offset (stored as the UTC instant
+    /// plus a 2-byte offset in minutes)
+    DateTimeTz,
+    /// ISO 8601 datetime stored as an i64 count of units since the Unix
+    /// epoch at the given precision
+    Timestamp(TimestampPrecision),
     /// ISO 8601 date (stored as days since Unix epoch)
     Date,
+    /// ISO 8601 time of day (stored as milliseconds since midnight)
+    Time,
+    /// Absolute URI (common schemes stored as a 1-byte prefix)
+    Uri,
+    /// IP address and port, e.g. `10.0.0.1:8080`
+    SocketAddr,
+    /// Latitude/longitude pair (stored as two fixed-point i32s)
+    GeoPoint,
     /// IPv4 address (stored as 4 bytes)
     Ipv4,
     /// IPv6 address (stored as 16 bytes)
     Ipv6,
     /// Binary data (Base64 encoded in JSON, raw bytes in binary)
     Binary,
+    /// User-defined format registered in a
+    /// [`FormatRegistry`](crate::formats::custom::FormatRegistry)
+    Custom(&'static str),
+}
+
+/// Resolution of a [`StringFormat::Timestamp`].
+///
+/// All precisions are stored in 8 bytes, so finer precisions trade range for
+/// resolution: seconds cover any representable date while nanoseconds are
+/// limited to roughly the years 1677 to 2262.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TimestampPrecision {
+    /// Whole seconds
+    Seconds,
+    /// Milliseconds
+    #[default]
+    Millis,
+    /// Microseconds
+    Micros,
+    /// Nanoseconds
+    Nanos,
+}
+
+/// Width of the size a property's value is framed with in the object header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SizeWidth {
+    /// The compactr.js header: one byte, or a `0x00` flag and one byte for
+    /// compound and empty values. Values are limited to 255 bytes, unless
+    /// the registry uses [`LengthMode::Varint`](crate::LengthMode::Varint),
+    /// where the size is a varint.
+    #[default]
+    Auto,
+    /// One byte, values up to 255 bytes
+    U8,
+    /// Big-endian u16, values up to 65535 bytes
+    U16,
+    /// Big-endian u32
+    U32,
 }
 
 /// Represents a property in an object schema.
@@ -75,6 +170,83 @@ pub struct Property {
     pub schema_type: SchemaType,
     /// Whether this property is required
     pub required: bool,
+    /// Width of the size recorded for this property's value
+    pub size_width: SizeWidth,
+    /// Whether this property holds sensitive data, such as personal
+    /// information, to be left out of logs and debug output
+    pub sensitive: bool,
+    /// Names this property was previously known by, oldest first
+    pub aliases: Vec<String>,
+    /// Documentation of this property
+    pub metadata: Metadata,
+}
+
+/// Documentation of a property, carried through `OpenAPI` conversions as
+/// the `title`, `description`, `example` and `deprecated` keywords.
+///
+/// Metadata doesn't affect the wire format or the
+/// [fingerprint](SchemaType::fingerprint), but properties with different
+/// metadata are different definitions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// Short name of the property for humans
+    pub title: Option<String>,
+    /// What the property holds
+    pub description: Option<String>,
+    /// A typical value of the property
+    pub example: Option<Value>,
+    /// Whether the property is on its way out and should no longer be set
+    pub deprecated: bool,
+}
+
+impl Metadata {
+    /// Creates empty metadata.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            title: None,
+            description: None,
+            example: None,
+            deprecated: false,
+        }
+    }
+
+    /// Sets the title.
+    #[must_use]
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the description.
+    #[must_use]
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Sets the example value.
+    #[must_use]
+    pub fn with_example(mut self, example: impl Into<Value>) -> Self {
+        self.example = Some(example.into());
+        self
+    }
+
+    /// Marks the property as deprecated.
+    #[must_use]
+    pub const fn with_deprecated(mut self, deprecated: bool) -> Self {
+        self.deprecated = deprecated;
+        self
+    }
+
+    /// Returns whether no metadata is set.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.description.is_none()
+            && self.example.is_none()
+            && !self.deprecated
+    }
 }
 
 impl Property {
@@ -84,6 +256,10 @@ impl Property {
         Self {
             schema_type,
             required: true,
+            size_width: SizeWidth::Auto,
+            sensitive: false,
+            aliases: Vec::new(),
+            metadata: Metadata::new(),
         }
     }
 
@@ -93,8 +269,115 @@ impl Property {
         Self {
             schema_type,
             required: false,
+            size_width: SizeWidth::Auto,
+            sensitive: false,
+            aliases: Vec::new(),
+            metadata: Metadata::new(),
         }
     }
+
+    /// Sets the width of the size recorded for this property, so that values
+    /// over 255 bytes can be stored.
+    #[must_use]
+    pub const fn with_size_width(mut self, size_width: SizeWidth) -> Self {
+        self.size_width = size_width;
+        self
+    }
+
+    /// Marks this property as sensitive, so that
+    /// [`Value::redact`](crate::Value::redact) and redacting decoders replace
+    /// its value with a placeholder. The wire format is unaffected.
+    #[must_use]
+    pub const fn with_sensitive(mut self, sensitive: bool) -> Self {
+        self.sensitive = sensitive;
+        self
+    }
+
+    /// Adds a name this property was previously known by, so that it can be
+    /// renamed without breaking stored data.
+    ///
+    /// Properties are indexed in alphabetical order of their names, and a
+    /// property with aliases is indexed under its first alias, so add the
+    /// original name first. Data written
+    /// under the old name then decodes to the new one, schemas differing
+    /// only by such renames share a [fingerprint](SchemaType::fingerprint),
+    /// and JSON input and [`Value`](crate::Value) objects may still use the
+    /// old name as a key.
+    ///
+    /// ```rust
+    /// use compactr::{Decoder, Encoder, Property, SchemaType, Value};
+    ///
+    /// let old = SchemaType::object([
+    ///     ("name", Property::required(SchemaType::string())),
+    ///     ("zip", Property::required(SchemaType::string())),
+    /// ]);
+    /// let new = SchemaType::object([
+    ///     ("name", Property::required(SchemaType::string())),
+    ///     ("a_postcode", Property::required(SchemaType::string()).with_alias("zip")),
+    /// ]);
+    /// assert_eq!(old.fingerprint(), new.fingerprint());
+    ///
+    /// let mut encoder = Encoder::new();
+    /// encoder.encode(&Value::object([("name", "Ann"), ("zip", "12345")]), &old)?;
+    /// let bytes = encoder.finish();
+    /// let value = Decoder::decode(&mut bytes.as_ref(), &new)?;
+    /// assert_eq!(value, Value::object([("name", "Ann"), ("a_postcode", "12345")]));
+    /// # Ok::<(), compactr::error::Error>(())
+    /// ```
+    #[must_use]
+    pub fn with_alias(mut self, alias: impl Into<String>) -> Self {
+        self.aliases.push(alias.into());
+        self
+    }
+
+    /// Sets the documentation of this property. The wire format is
+    /// unaffected.
+    #[must_use]
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Returns the name that sets this property's index among the properties
+    /// of its object: its first alias, or else `name`.
+    #[must_use]
+    pub fn index_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases.first().map_or(name, String::as_str)
+    }
+}
+
+/// Most properties of an object sorted without allocating.
+pub(crate) const INLINE_PROPERTIES: usize = 16;
+
+/// Returns the properties of an object in the order of their indices.
+///
+/// Objects of up to [`INLINE_PROPERTIES`] properties are sorted on the
+/// stack, as this runs for every object encoded or decoded.
+pub(crate) fn index_order(
+    properties: &IndexMap<String, Property>,
+) -> SmallVec<[(&String, &Property); INLINE_PROPERTIES]> {
+    let mut sorted: SmallVec<[_; INLINE_PROPERTIES]> = properties.iter().collect();
+    sorted.sort_by(|&a, &b| index_key(a).cmp(&index_key(b)));
+    sorted
+}
+
+/// Returns the index of the property named `key`, or aliased `key`, among
+/// properties in [`index_order`].
+pub(crate) fn index_of(sorted: &[(&String, &Property)], key: &str) -> Option<usize> {
+    // Properties without aliases are ordered by name
+    sorted
+        .binary_search_by(|&entry| index_key(entry).cmp(&(key, key)))
+        .ok()
+        .or_else(|| {
+            sorted.iter().position(|(name, property)| {
+                name.as_str() == key || property.aliases.iter().any(|alias| alias == key)
+            })
+        })
+}
+
+/// Orders properties by index name, then by name should several share one.
+fn index_key<'a>((name, property): (&'a String, &'a Property)) -> (&'a str, &'a str) {
+    (property.index_name(name), name)
 }
 
 impl SchemaType {
@@ -104,6 +387,30 @@ impl SchemaType {
         Self::Boolean
     }
 
+    /// Creates an int8 schema.
+    #[must_use]
+    pub const fn int8() -> Self {
+        Self::Integer(IntegerFormat::Int8)
+    }
+
+    /// Creates an int16 schema.
+    #[must_use]
+    pub const fn int16() -> Self {
+        Self::Integer(IntegerFormat::Int16)
+    }
+
+    /// Creates a uint8 schema.
+    #[must_use]
+    pub const fn uint8() -> Self {
+        Self::Integer(IntegerFormat::Uint8)
+    }
+
+    /// Creates a uint16 schema.
+    #[must_use]
+    pub const fn uint16() -> Self {
+        Self::Integer(IntegerFormat::Uint16)
+    }
+
     /// Creates an int32 schema.
     #[must_use]
     pub const fn int32() -> Self {
@@ -116,6 +423,18 @@ impl SchemaType {
         Self::Integer(IntegerFormat::Int64)
     }
 
+    /// Creates an int128 schema.
+    #[must_use]
+    pub const fn int128() -> Self {
+        Self::Integer(IntegerFormat::Int128)
+    }
+
+    /// Creates a uint128 schema.
+    #[must_use]
+    pub const fn uint128() -> Self {
+        Self::Integer(IntegerFormat::Uint128)
+    }
+
     /// Creates a float schema.
     #[must_use]
     pub const fn float() -> Self {
@@ -128,12 +447,25 @@ impl SchemaType {
         Self::Number(NumberFormat::Double)
     }
 
+    /// Creates an arbitrary-precision decimal schema.
+    #[cfg(feature = "decimal")]
+    #[must_use]
+    pub const fn decimal() -> Self {
+        Self::Number(NumberFormat::Decimal)
+    }
+
     /// Creates a plain string schema.
     #[must_use]
     pub const fn string() -> Self {
         Self::String(StringFormat::Plain)
     }
 
+    /// Creates a string schema with a u32 length prefix.
+    #[must_use]
+    pub const fn long_string() -> Self {
+        Self::String(StringFormat::Long)
+    }
+
     /// Creates a UUID string schema.
     #[must_use]
     pub const fn string_uuid() -> Self {
@@ -146,12 +478,48 @@ impl SchemaType {
         Self::String(StringFormat::DateTime)
     }
 
+    /// Creates an offset-preserving datetime string schema.
+    #[must_use]
+    pub const fn string_datetime_tz() -> Self {
+        Self::String(StringFormat::DateTimeTz)
+    }
+
+    /// Creates a timestamp schema with the given precision.
+    #[must_use]
+    pub const fn timestamp(precision: TimestampPrecision) -> Self {
+        Self::String(StringFormat::Timestamp(precision))
+    }
+
     /// Creates a date string schema.
     #[must_use]
     pub const fn string_date() -> Self {
         Self::String(StringFormat::Date)
     }
 
+    /// Creates a time-of-day string schema.
+    #[must_use]
+    pub const fn string_time() -> Self {
+        Self::String(StringFormat::Time)
+    }
+
+    /// Creates a URI string schema.
+    #[must_use]
+    pub const fn string_uri() -> Self {
+        Self::String(StringFormat::Uri)
+    }
+
+    /// Creates a geographic point schema.
+    #[must_use]
+    pub const fn geo_point() -> Self {
+        Self::String(StringFormat::GeoPoint)
+    }
+
+    /// Creates a schema for a user-defined format registered under `name`.
+    #[must_use]
+    pub const fn custom(name: &'static str) -> Self {
+        Self::String(StringFormat::Custom(name))
+    }
+
     /// Creates an IPv4 string schema.
     #[must_use]
     pub const fn string_ipv4() -> Self {
@@ -164,6 +532,12 @@ impl SchemaType {
         Self::String(StringFormat::Ipv6)
     }
 
+    /// Creates a socket address (IP + port) string schema.
+    #[must_use]
+    pub const fn string_socket_addr() -> Self {
+        Self::String(StringFormat::SocketAddr)
+    }
+
     /// Creates a binary string schema.
     #[must_use]
     pub const fn binary() -> Self {
@@ -176,10 +550,64 @@ impl SchemaType {
         Self::Array(Box::new(items))
     }
 
+    /// Creates a run-length encoded array schema with the given item type.
+    #[must_use]
+    pub fn run_length_array(items: SchemaType) -> Self {
+        Self::RunLengthArray(Box::new(items))
+    }
+
     /// Creates an object schema with the given properties.
+    ///
+    /// Properties can be given as an `IndexMap` or any iterator of name and
+    /// [`Property`] pairs; a bare [`SchemaType`] makes an optional property:
+    ///
+    /// ```rust
+    /// use compactr::{Property, SchemaType};
+    ///
+    /// let schema = SchemaType::object([
+    ///     ("id", Property::required(SchemaType::string_uuid())),
+    ///     ("name", SchemaType::string().into()),
+    /// ]);
+    /// let tags: SchemaType = [("tags", SchemaType::array(SchemaType::string()))]
+    ///     .into_iter()
+    ///     .collect();
+    /// ```
+    #[must_use]
+    pub fn object<I, K, P>(properties: I) -> Self
+    where
+        I: IntoIterator<Item = (K, P)>,
+        K: Into<String>,
+        P: Into<Property>,
+    {
+        Self::Object(
+            properties
+                .into_iter()
+                .map(|(name, property)| (name.into(), property.into()))
+                .collect(),
+        )
+    }
+
+    /// Creates a string enumeration schema.
+    #[must_use]
+    pub fn string_enum<I, S>(variants: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self::Enum(variants.into_iter().map(Into::into).collect())
+    }
+
+    /// Creates a tagged union schema whose variant name is stored in the
+    /// `discriminator` property.
     #[must_use]
-    pub fn object(properties: IndexMap<String, Property>) -> Self {
-        Self::Object(properties)
+    pub fn one_of(
+        discriminator: impl Into<String>,
+        variants: IndexMap<String, SchemaType>,
+    ) -> Self {
+        Self::OneOf {
+            discriminator: discriminator.into(),
+            variants,
+        }
     }
 
     /// Creates a reference to another schema.
@@ -195,6 +623,103 @@ impl SchemaType {
     }
 }
 
+/// Collects name and property pairs into an object schema, like
+/// [`SchemaType::object`].
+impl<K: Into<String>, P: Into<Property>> FromIterator<(K, P)> for SchemaType {
+    fn from_iter<I: IntoIterator<Item = (K, P)>>(properties: I) -> Self {
+        Self::object(properties)
+    }
+}
+
+/// Makes an optional property, the `OpenAPI` default.
+impl From<SchemaType> for Property {
+    fn from(schema_type: SchemaType) -> Self {
+        Self::optional(schema_type)
+    }
+}
+
+// Every variant of the formats, for generating schemas to test with
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+impl IntegerFormat {
+    pub(crate) const ALL: &'static [Self] = &[
+        Self::Int8,
+        Self::Int16,
+        Self::Int32,
+        Self::Int64,
+        Self::Uint8,
+        Self::Uint16,
+        Self::Int128,
+        Self::Uint128,
+    ];
+}
+
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+impl NumberFormat {
+    pub(crate) const ALL: &'static [Self] = &[
+        Self::Float,
+        Self::Double,
+        #[cfg(feature = "decimal")]
+        Self::Decimal,
+    ];
+}
+
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+impl StringFormat {
+    /// Every format but [`StringFormat::Custom`]
+    pub(crate) const BUILTIN: &'static [Self] = &[
+        Self::Plain,
+        Self::Long,
+        Self::Uuid,
+        Self::DateTime,
+        Self::DateTimeTz,
+        Self::Timestamp(TimestampPrecision::Seconds),
+        Self::Timestamp(TimestampPrecision::Millis),
+        Self::Timestamp(TimestampPrecision::Micros),
+        Self::Timestamp(TimestampPrecision::Nanos),
+        Self::Date,
+        Self::Time,
+        Self::Uri,
+        Self::SocketAddr,
+        Self::GeoPoint,
+        Self::Ipv4,
+        Self::Ipv6,
+        Self::Binary,
+    ];
+}
+
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+impl SizeWidth {
+    pub(crate) const ALL: &'static [Self] = &[Self::Auto, Self::U8, Self::U16, Self::U32];
+}
+
+impl fmt::Display for SizeWidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Auto => "auto",
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::U32 => "u32",
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for IntegerFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Int8 => "int8",
+            Self::Int16 => "int16",
+            Self::Int32 => "int32",
+            Self::Int64 => "int64",
+            Self::Uint8 => "uint8",
+            Self::Uint16 => "uint16",
+            Self::Int128 => "int128",
+            Self::Uint128 => "uint128",
+        };
+        f.write_str(name)
+    }
+}
+
 impl fmt::Display for SchemaType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -203,8 +728,11 @@ impl fmt::Display for SchemaType {
             Self::Number(format) => write!(f, "number({format:?})"),
             Self::String(format) => write!(f, "string({format:?})"),
             Self::Array(items) => write!(f, "array[{items}]"),
+            Self::RunLengthArray(items) => write!(f, "rle-array[{items}]"),
             Self::Object(_) => write!(f, "object"),
             Self::Reference(r) => write!(f, "ref({r})"),
+            Self::Enum(variants) => write!(f, "enum[{}]", variants.join(", ")),
+            Self::OneOf { discriminator, .. } => write!(f, "oneOf({discriminator})"),
             Self::Null => write!(f, "null"),
         }
     }
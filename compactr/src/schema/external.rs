@@ -0,0 +1,301 @@
+//! Resolution of `$ref`s pointing into other documents.
+//!
+//! Multi-file specs refer to schemas of other files with references such as
+//! `common.yaml#/components/schemas/Money`. [`resolve_external_refs`] loads
+//! those files through a caller-supplied loader, so they may come from disk,
+//! HTTP or memory, copies the referenced schemas into the components of the
+//! root document under the last segment of their pointer, and rewrites the
+//! references to point at the copies.
+
+use super::SchemaRegistry;
+use crate::error::{Result, SchemaError};
+use serde_json::{Map, Value as Json};
+use std::collections::{HashMap, HashSet};
+
+const COMPONENTS_PREFIX: &str = "#/components/schemas/";
+
+/// Returns `doc` with the schemas referenced from other documents added to
+/// its `components.schemas`, so that it has no external references left.
+///
+/// `base` is the location of `doc` itself. Relative locations in references
+/// are resolved against the location of the document they appear in, and
+/// `loader` is called once per document with the resolved location. Like
+/// [`SchemaRegistry::register_json_schemas`], `doc` may also be a bare
+/// object of schemas.
+///
+/// ```rust
+/// use compactr::schema::resolve_external_refs;
+/// use serde_json::json;
+///
+/// let doc = json!({"components": {"schemas": {
+///     "Order": {"type": "object", "properties": {
+///         "total": {"$ref": "common.json#/components/schemas/Money"}
+///     }}
+/// }}});
+/// let common = json!({"components": {"schemas": {"Money": {"type": "integer"}}}});
+///
+/// let resolved = resolve_external_refs(&doc, "specs/orders.json", |location| {
+///     assert_eq!(location, "specs/common.json");
+///     Ok(common.clone())
+/// })?;
+/// assert_eq!(resolved["components"]["schemas"]["Money"], json!({"type": "integer"}));
+/// # Ok::<(), compactr::error::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if `loader` fails, a pointer doesn't exist in its
+/// document, or two different schemas would be copied under the same name,
+/// which is a [`SchemaError::Conflict`].
+pub fn resolve_external_refs(
+    doc: &Json,
+    base: &str,
+    loader: impl FnMut(&str) -> Result<Json>,
+) -> Result<Json> {
+    let mut doc = doc.clone();
+    let pointer = if doc.pointer("/components/schemas").is_some() {
+        "/components/schemas"
+    } else {
+        ""
+    };
+    let schemas = doc
+        .pointer_mut(pointer)
+        .and_then(Json::as_object_mut)
+        .ok_or_else(|| SchemaError::InvalidSchema("expected an object of schemas".to_owned()))?;
+
+    let mut resolver = Resolver {
+        loader,
+        base: normalize(base),
+        documents: HashMap::new(),
+        imported: HashMap::new(),
+        names: schemas.keys().cloned().collect(),
+        components: Map::new(),
+    };
+    for schema in schemas.values_mut() {
+        resolver.rewrite(schema, base)?;
+    }
+    schemas.extend(resolver.components);
+    Ok(doc)
+}
+
+impl SchemaRegistry {
+    /// Registers every schema of an `OpenAPI` document located at `base`,
+    /// loading the documents its external references point into with
+    /// `loader`; see [`resolve_external_refs`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a document cannot be loaded, a reference is
+    /// unresolved, or a schema cannot be parsed.
+    pub fn register_json_schemas_with_loader(
+        &self,
+        json: &Json,
+        base: &str,
+        loader: impl FnMut(&str) -> Result<Json>,
+    ) -> Result<()> {
+        self.register_json_schemas(&resolve_external_refs(json, base, loader)?)
+    }
+}
+
+struct Resolver<F> {
+    loader: F,
+    /// Location of the root document
+    base: String,
+    /// Loaded documents, by location
+    documents: HashMap<String, Json>,
+    /// Names given to external schemas, by location and pointer
+    imported: HashMap<(String, String), String>,
+    /// Component names in use
+    names: HashSet<String>,
+    /// External schemas copied so far
+    components: Map<String, Json>,
+}
+
+impl<F: FnMut(&str) -> Result<Json>> Resolver<F> {
+    /// Rewrites the references in a schema of the document at `location`.
+    fn rewrite(&mut self, json: &mut Json, location: &str) -> Result<()> {
+        match json {
+            Json::Object(obj) => {
+                if let Some(Json::String(reference)) = obj.get("$ref") {
+                    if let Some(local) = self.resolve(&reference.clone(), location)? {
+                        obj.insert("$ref".to_owned(), local.into());
+                    }
+                    return Ok(());
+                }
+                for value in obj.values_mut() {
+                    self.rewrite(value, location)?;
+                }
+            }
+            Json::Array(items) => {
+                for item in items {
+                    self.rewrite(item, location)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Returns the local reference replacing `reference`, if it changes.
+    fn resolve(&mut self, reference: &str, location: &str) -> Result<Option<String>> {
+        let (file, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        let target = if file.is_empty() {
+            location.to_owned()
+        } else {
+            join(location, file)
+        };
+        if target == self.base {
+            return Ok((!file.is_empty()).then(|| format!("#{pointer}")));
+        }
+
+        let key = (target, pointer.to_owned());
+        if let Some(name) = self.imported.get(&key) {
+            return Ok(Some(format!("{COMPONENTS_PREFIX}{name}")));
+        }
+        // Named after the last pointer segment, or the file for whole files
+        let segment = pointer.rsplit('/').next().filter(|s| !s.is_empty());
+        let name = segment.map_or_else(
+            || {
+                let file = key.0.rsplit('/').next().unwrap_or(&key.0);
+                file.split('.').next().unwrap_or(file).to_owned()
+            },
+            |segment| segment.replace("~1", "/").replace("~0", "~"),
+        );
+        if !self.names.insert(name.clone()) {
+            return Err(SchemaError::Conflict(name).into());
+        }
+        self.imported.insert(key.clone(), name.clone());
+
+        let (target, pointer) = key;
+        if !self.documents.contains_key(&target) {
+            let document = (self.loader)(&target)?;
+            self.documents.insert(target.clone(), document);
+        }
+        let mut schema = self.documents[&target]
+            .pointer(pointer.as_str())
+            .cloned()
+            .ok_or_else(|| SchemaError::UnresolvedReference(reference.to_owned()))?;
+        self.rewrite(&mut schema, &target)?;
+        self.components.insert(name.clone(), schema);
+        Ok(Some(format!("{COMPONENTS_PREFIX}{name}")))
+    }
+}
+
+/// Resolves `relative` against the location of the document containing it.
+fn join(location: &str, relative: &str) -> String {
+    let joined = if relative.starts_with('/') || relative.contains("://") {
+        relative.to_owned()
+    } else {
+        match location.rfind('/') {
+            Some(end) => format!("{}{relative}", &location[..=end]),
+            None => relative.to_owned(),
+        }
+    };
+    normalize(&joined)
+}
+
+/// Removes `.` and `..` segments, so that every document has one location.
+fn normalize(location: &str) -> String {
+    // The scheme and host of URLs are kept as they are
+    let host_end = location.find("://").map_or(0, |scheme| {
+        let host = scheme + 3;
+        location[host..]
+            .find('/')
+            .map_or(location.len(), |end| host + end)
+    });
+    let (prefix, path) = location.split_at(host_end);
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "." => {}
+            ".." if segments.last().is_some_and(|s| !s.is_empty() && *s != "..") => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("{prefix}{}", segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::schema::SchemaType;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_external_refs() {
+        let root = json!({"components": {"schemas": {
+            "Order": {"type": "object", "properties": {
+                "customer": {"$ref": "people/customer.json#/components/schemas/Customer"},
+                "total": {"$ref": "common.json#/components/schemas/Money"},
+                "items": {"type": "array", "items": {"$ref": "#/components/schemas/Item"}}
+            }},
+            "Item": {"type": "object", "properties": {
+                "price": {"$ref": "./common.json#/components/schemas/Money"}
+            }}
+        }}});
+        let mut documents = HashMap::new();
+        documents.insert(
+            "https://example.com/specs/common.json",
+            json!({"components": {"schemas": {"Money": {"type": "integer"}}}}),
+        );
+        documents.insert(
+            "https://example.com/specs/people/customer.json",
+            json!({"components": {"schemas": {
+                "Customer": {"type": "object", "properties": {
+                    "address": {"$ref": "#/components/schemas/Address"},
+                    "orders": {"type": "array", "items": {"$ref": "../orders.json#/components/schemas/Order"}}
+                }},
+                "Address": {"type": "string"}
+            }}}),
+        );
+
+        let mut loaded = Vec::new();
+        let registry = SchemaRegistry::new();
+        registry
+            .register_json_schemas_with_loader(
+                &root,
+                "https://example.com/specs/orders.json",
+                |location| {
+                    loaded.push(location.to_owned());
+                    documents
+                        .get(location)
+                        .cloned()
+                        .ok_or_else(|| SchemaError::UnresolvedReference(location.to_owned()).into())
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            registry.names().unwrap(),
+            ["Address", "Customer", "Item", "Money", "Order"]
+        );
+        assert_eq!(
+            loaded,
+            [
+                "https://example.com/specs/common.json",
+                "https://example.com/specs/people/customer.json"
+            ]
+        );
+        let Some(SchemaType::Object(customer)) = registry.get("Customer").unwrap() else {
+            panic!("expected object schema");
+        };
+        assert_eq!(
+            customer["address"].schema_type,
+            SchemaType::reference("#/components/schemas/Address")
+        );
+        assert!(registry.resolve_ref("#/components/schemas/Money").is_ok());
+
+        let conflicting = json!({"components": {"schemas": {
+            "Money": {"type": "number"},
+            "Order": {"$ref": "common.json#/components/schemas/Money"}
+        }}});
+        let err = resolve_external_refs(&conflicting, "specs/orders.json", |_| {
+            Ok(documents["https://example.com/specs/common.json"].clone())
+        })
+        .unwrap_err();
+        assert!(matches!(err, Error::Schema(SchemaError::Conflict(name)) if name == "Money"));
+        assert_eq!(normalize("a/./b/../../../c"), "../c");
+    }
+}
@@ -0,0 +1,335 @@
+//! Extraction of repeated object shapes into registered schemas.
+
+use super::{Property, SchemaRegistry, SchemaType};
+use crate::error::Result;
+use std::collections::HashMap;
+
+/// Replaces every nested object schema that occurs more than once in
+/// `schema` by a reference to a single copy registered with `registry`, and
+/// returns the rewritten schema.
+///
+/// Large inline schemas, such as those generated from deeply nested
+/// documents, often repeat the same shape (an address, a money amount) in
+/// several places. Extracting them keeps one copy in memory and gives the
+/// shape a name, and therefore a [fingerprint](SchemaType::fingerprint),
+/// that doesn't change with the place it's used in. Values encode to the
+/// same bytes with either schema.
+///
+/// Shapes are named after the first property they occur in, in
+/// `PascalCase`, with `Item` appended for array elements and a number
+/// appended if the name is taken by a different schema. The root and the
+/// payloads of `oneOf` variants stay inline, while the objects inside them
+/// are extracted as well.
+///
+/// ```rust
+/// use compactr::schema::extract_shared_objects;
+/// use compactr::{Property, SchemaRegistry, SchemaType};
+///
+/// let address = SchemaType::object([
+///     ("street", Property::required(SchemaType::string())),
+///     ("city", Property::required(SchemaType::string())),
+/// ]);
+/// let order = SchemaType::object([
+///     ("billing_address", Property::required(address.clone())),
+///     ("shipping_address", Property::optional(address.clone())),
+/// ]);
+///
+/// let registry = SchemaRegistry::new();
+/// let extracted = extract_shared_objects(&order, &registry)?;
+/// assert_eq!(
+///     extracted,
+///     SchemaType::object([
+///         ("billing_address", Property::required(SchemaType::reference("#/BillingAddress"))),
+///         ("shipping_address", Property::optional(SchemaType::reference("#/BillingAddress"))),
+///     ])
+/// );
+/// assert_eq!(registry.get("BillingAddress")?, Some(address));
+/// # Ok::<(), compactr::error::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the registry's lock is poisoned.
+pub fn extract_shared_objects(
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<SchemaType> {
+    let mut shapes = Shapes::default();
+    shapes.count_children(schema);
+    shapes.rewrite_children(schema, "", registry)
+}
+
+/// An object shape and how often it occurs.
+struct Shape<'a> {
+    schema: &'a SchemaType,
+    count: usize,
+    /// Registered name, once extracted
+    name: Option<String>,
+}
+
+/// Object shapes of a schema, by fingerprint.
+#[derive(Default)]
+struct Shapes<'a> {
+    by_fingerprint: HashMap<u64, Vec<Shape<'a>>>,
+}
+
+impl<'a> Shapes<'a> {
+    fn get(&mut self, schema: &'a SchemaType) -> &mut Shape<'a> {
+        let shapes = self.by_fingerprint.entry(schema.fingerprint()).or_default();
+        // Equal fingerprints may still differ in sensitivity or aliases
+        let index = shapes
+            .iter()
+            .position(|shape| shape.schema == schema)
+            .unwrap_or_else(|| {
+                shapes.push(Shape {
+                    schema,
+                    count: 0,
+                    name: None,
+                });
+                shapes.len() - 1
+            });
+        &mut shapes[index]
+    }
+
+    /// Counts the objects nested in `schema`. The objects nested in a
+    /// repeated object are counted once, as they are extracted with it.
+    fn count(&mut self, schema: &'a SchemaType) {
+        if let SchemaType::Object(_) = schema {
+            let shape = self.get(schema);
+            shape.count += 1;
+            if shape.count > 1 {
+                return;
+            }
+        }
+        self.count_children(schema);
+    }
+
+    fn count_children(&mut self, schema: &'a SchemaType) {
+        match schema {
+            SchemaType::Object(properties) => {
+                for property in properties.values() {
+                    self.count(&property.schema_type);
+                }
+            }
+            SchemaType::Array(items) | SchemaType::RunLengthArray(items) => self.count(items),
+            // Payloads are merged into the variant's object, so only the
+            // objects inside them can be extracted
+            SchemaType::OneOf { variants, .. } => {
+                for payload in variants.values() {
+                    self.count_children(payload);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Rewrites `schema`, found under the name `hint`, into a reference if
+    /// it is a repeated object.
+    fn rewrite(
+        &mut self,
+        schema: &'a SchemaType,
+        hint: &str,
+        registry: &SchemaRegistry,
+    ) -> Result<SchemaType> {
+        if !matches!(schema, SchemaType::Object(_)) || self.get(schema).count < 2 {
+            return self.rewrite_children(schema, hint, registry);
+        }
+        if let Some(name) = &self.get(schema).name {
+            return Ok(SchemaType::reference(format!("#/{name}")));
+        }
+        let extracted = self.rewrite_children(schema, hint, registry)?;
+        let name = register(registry, hint, extracted)?;
+        self.get(schema).name = Some(name.clone());
+        Ok(SchemaType::reference(format!("#/{name}")))
+    }
+
+    fn rewrite_children(
+        &mut self,
+        schema: &'a SchemaType,
+        hint: &str,
+        registry: &SchemaRegistry,
+    ) -> Result<SchemaType> {
+        Ok(match schema {
+            SchemaType::Object(properties) => SchemaType::Object(
+                properties
+                    .iter()
+                    .map(|(name, property)| {
+                        let schema_type = self.rewrite(&property.schema_type, name, registry)?;
+                        Ok((
+                            name.clone(),
+                            Property {
+                                schema_type,
+                                ..property.clone()
+                            },
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            SchemaType::Array(items) => {
+                SchemaType::array(self.rewrite(items, &format!("{hint}_item"), registry)?)
+            }
+            SchemaType::RunLengthArray(items) => SchemaType::run_length_array(self.rewrite(
+                items,
+                &format!("{hint}_item"),
+                registry,
+            )?),
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            } => SchemaType::OneOf {
+                discriminator: discriminator.clone(),
+                variants: variants
+                    .iter()
+                    .map(|(name, payload)| {
+                        Ok((
+                            name.clone(),
+                            self.rewrite_children(payload, name, registry)?,
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+            },
+            schema => schema.clone(),
+        })
+    }
+}
+
+/// Registers `schema` under a name derived from `hint` and returns the name.
+fn register(registry: &SchemaRegistry, hint: &str, schema: SchemaType) -> Result<String> {
+    let base = type_name(hint);
+    let mut name = base.clone();
+    for suffix in 2.. {
+        match registry.get(&name)? {
+            Some(existing) if existing == schema => return Ok(name),
+            Some(_) => name = format!("{base}{suffix}"),
+            None => break,
+        }
+    }
+    registry.register(name.clone(), schema)?;
+    Ok(name)
+}
+
+/// Converts a property name to `PascalCase`, or `Object` if it has no
+/// letters or digits.
+fn type_name(hint: &str) -> String {
+    let name: String = hint
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect();
+    match name.chars().next() {
+        None => "Object".to_owned(),
+        Some(first) if first.is_ascii_digit() => format!("Object{name}"),
+        Some(_) => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Decoder, Encoder};
+    use crate::value::Value;
+
+    fn point() -> SchemaType {
+        SchemaType::object([
+            ("x", Property::required(SchemaType::int32())),
+            ("y", Property::required(SchemaType::int32())),
+        ])
+    }
+
+    #[test]
+    fn test_extract_shared_objects() {
+        let line = SchemaType::object([
+            ("start", Property::required(point())),
+            ("end", Property::required(point())),
+        ]);
+        let schema = SchemaType::object([
+            ("lines", Property::required(SchemaType::array(line.clone()))),
+            ("longest", Property::optional(line)),
+            ("origin", Property::required(point())),
+            (
+                "label",
+                Property::required(SchemaType::object([(
+                    "text",
+                    Property::required(SchemaType::string()),
+                )])),
+            ),
+        ]);
+
+        let registry = SchemaRegistry::new();
+        // A different schema already named like the points
+        registry.register("Start", SchemaType::string()).unwrap();
+        let extracted = extract_shared_objects(&schema, &registry).unwrap();
+        let SchemaType::Object(properties) = &extracted else {
+            panic!("expected an object, got {extracted:?}");
+        };
+        assert_eq!(
+            properties["lines"].schema_type,
+            SchemaType::array(SchemaType::reference("#/LinesItem"))
+        );
+        assert_eq!(
+            properties["longest"].schema_type,
+            SchemaType::reference("#/LinesItem")
+        );
+        assert_eq!(
+            properties["origin"].schema_type,
+            SchemaType::reference("#/Start2")
+        );
+        // Shapes occurring once stay inline
+        assert!(matches!(
+            properties["label"].schema_type,
+            SchemaType::Object(_)
+        ));
+        assert_eq!(registry.get("Start2").unwrap(), Some(point()));
+        assert_eq!(
+            registry.get("LinesItem").unwrap(),
+            Some(SchemaType::object([
+                (
+                    "start",
+                    Property::required(SchemaType::reference("#/Start2"))
+                ),
+                ("end", Property::required(SchemaType::reference("#/Start2"))),
+            ]))
+        );
+
+        // Values encode the same with both schemas
+        let p = |x: i32, y: i32| Value::object([("x", x), ("y", y)]);
+        let line = Value::object([("start", p(0, 0)), ("end", p(3, 4))]);
+        let value = Value::object([
+            ("lines", Value::array([line.clone(), line.clone()])),
+            ("longest", line),
+            ("origin", p(1, 1)),
+            ("label", Value::object([("text", "a")])),
+        ]);
+        let mut encoder = Encoder::new();
+        encoder.encode(&value, &schema).unwrap();
+        let inline = encoder.finish();
+        let mut encoder = Encoder::new();
+        encoder
+            .encode_with_registry(&value, &extracted, &registry)
+            .unwrap();
+        assert_eq!(encoder.finish(), inline);
+        let decoded =
+            Decoder::decode_with_registry(&mut inline.as_ref(), &extracted, &registry).unwrap();
+        assert_eq!(decoded, value);
+
+        // Extracting again reuses the registered shapes
+        assert_eq!(
+            extract_shared_objects(&schema, &registry).unwrap(),
+            extracted
+        );
+    }
+
+    #[test]
+    fn test_type_name() {
+        assert_eq!(type_name("billing_address"), "BillingAddress");
+        assert_eq!(type_name("lineItems_item"), "LineItemsItem");
+        assert_eq!(type_name(""), "Object");
+        assert_eq!(type_name("2d"), "Object2d");
+    }
+}
@@ -0,0 +1,212 @@
+//! Stable structural fingerprints of schemas.
+
+use super::bundle::property_flags;
+use super::definition::index_order;
+use super::{IntegerFormat, NumberFormat, SchemaType, StringFormat, TimestampPrecision};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a, which unlike `std`'s hashers is stable across releases
+/// and platforms.
+struct Fnv(u64);
+
+impl Fnv {
+    fn byte(&mut self, b: u8) {
+        self.0 ^= u64::from(b);
+        self.0 = self.0.wrapping_mul(FNV_PRIME);
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.byte(b);
+        }
+    }
+
+    /// Length-prefixed so adjacent strings cannot run into each other.
+    fn str(&mut self, s: &str) {
+        self.bytes(&(s.len() as u64).to_be_bytes());
+        self.bytes(s.as_bytes());
+    }
+}
+
+impl SchemaType {
+    /// Returns a 64-bit fingerprint of this schema's structure.
+    ///
+    /// Two schemas have the same fingerprint when they encode values the
+    /// same way: property declaration order doesn't matter (properties are
+    /// encoded alphabetically), nor do sensitivity or renames that keep the
+    /// old name as an [alias](super::Property::with_alias), but names,
+    /// formats, required flags, size widths and variant order do. References
+    /// contribute their name, not the schema they point to. The fingerprint
+    /// is stable across platforms and releases.
+    #[must_use]
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv(FNV_OFFSET);
+        write_schema(&mut hasher, self);
+        hasher.0
+    }
+}
+
+fn write_schema(h: &mut Fnv, schema: &SchemaType) {
+    match schema {
+        SchemaType::Boolean => h.byte(0),
+        SchemaType::Integer(format) => {
+            h.byte(1);
+            h.byte(match format {
+                IntegerFormat::Int8 => 0,
+                IntegerFormat::Int16 => 1,
+                IntegerFormat::Int32 => 2,
+                IntegerFormat::Int64 => 3,
+                IntegerFormat::Uint8 => 4,
+                IntegerFormat::Uint16 => 5,
+                IntegerFormat::Int128 => 6,
+                IntegerFormat::Uint128 => 7,
+            });
+        }
+        SchemaType::Number(format) => {
+            h.byte(2);
+            h.byte(match format {
+                NumberFormat::Float => 0,
+                NumberFormat::Double => 1,
+                #[cfg(feature = "decimal")]
+                NumberFormat::Decimal => 2,
+            });
+        }
+        SchemaType::String(format) => {
+            h.byte(3);
+            write_string_format(h, *format);
+        }
+        SchemaType::Array(items) => {
+            h.byte(4);
+            write_schema(h, items);
+        }
+        SchemaType::Object(properties) => {
+            h.byte(5);
+            let sorted = index_order(properties);
+            h.bytes(&(sorted.len() as u64).to_be_bytes());
+            for (name, property) in sorted {
+                h.str(property.index_name(name));
+                h.byte(property_flags(property));
+                write_schema(h, &property.schema_type);
+            }
+        }
+        SchemaType::Reference(name) => {
+            h.byte(6);
+            h.str(name.strip_prefix("#/").unwrap_or(name));
+        }
+        SchemaType::Enum(variants) => {
+            h.byte(7);
+            h.bytes(&(variants.len() as u64).to_be_bytes());
+            for variant in variants {
+                h.str(variant);
+            }
+        }
+        SchemaType::OneOf {
+            discriminator,
+            variants,
+        } => {
+            h.byte(8);
+            h.str(discriminator);
+            h.bytes(&(variants.len() as u64).to_be_bytes());
+            for (name, payload) in variants {
+                h.str(name);
+                write_schema(h, payload);
+            }
+        }
+        SchemaType::Null => h.byte(9),
+        SchemaType::RunLengthArray(items) => {
+            h.byte(10);
+            write_schema(h, items);
+        }
+    }
+}
+
+fn write_string_format(h: &mut Fnv, format: StringFormat) {
+    match format {
+        StringFormat::Plain => h.byte(0),
+        StringFormat::Uuid => h.byte(1),
+        StringFormat::DateTime => h.byte(2),
+        StringFormat::DateTimeTz => h.byte(3),
+        StringFormat::Timestamp(precision) => {
+            h.byte(4);
+            h.byte(match precision {
+                TimestampPrecision::Seconds => 0,
+                TimestampPrecision::Millis => 1,
+                TimestampPrecision::Micros => 2,
+                TimestampPrecision::Nanos => 3,
+            });
+        }
+        StringFormat::Date => h.byte(5),
+        StringFormat::Time => h.byte(6),
+        StringFormat::Uri => h.byte(7),
+        StringFormat::SocketAddr => h.byte(8),
+        StringFormat::GeoPoint => h.byte(9),
+        StringFormat::Ipv4 => h.byte(10),
+        StringFormat::Ipv6 => h.byte(11),
+        StringFormat::Binary => h.byte(12),
+        StringFormat::Long => h.byte(14),
+        StringFormat::Custom(name) => {
+            h.byte(13);
+            h.str(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Property;
+    use indexmap::IndexMap;
+
+    fn user(order: &[&str]) -> SchemaType {
+        let mut properties = IndexMap::new();
+        for &name in order {
+            let schema = if name == "id" {
+                SchemaType::string_uuid()
+            } else {
+                SchemaType::string()
+            };
+            properties.insert(name.to_owned(), Property::required(schema));
+        }
+        SchemaType::object(properties)
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_declaration_order() {
+        assert_eq!(
+            user(&["id", "name"]).fingerprint(),
+            user(&["name", "id"]).fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_sensitivity() {
+        let schema = |sensitive| {
+            let property = Property::required(SchemaType::string()).with_sensitive(sensitive);
+            SchemaType::object([("email", property)])
+        };
+        assert_eq!(schema(true).fingerprint(), schema(false).fingerprint());
+    }
+
+    #[test]
+    fn test_fingerprint_distinguishes_schemas() {
+        let base = user(&["id", "name"]).fingerprint();
+        assert_ne!(base, user(&["id", "title"]).fingerprint());
+        assert_ne!(base, user(&["id"]).fingerprint());
+        assert_ne!(
+            SchemaType::int32().fingerprint(),
+            SchemaType::int64().fingerprint()
+        );
+        assert_ne!(
+            SchemaType::string_enum(["a", "b"]).fingerprint(),
+            SchemaType::string_enum(["b", "a"]).fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        // Pinned so accidental changes to the canonical form are caught
+        assert_eq!(SchemaType::boolean().fingerprint(), 0xaf63_bd4c_8601_b7df);
+    }
+}
@@ -0,0 +1,637 @@
+//! Conversion between [`SchemaType`] and `OpenAPI`-style JSON schemas.
+
+use super::{
+    IntegerFormat, Metadata, NumberFormat, Property, SchemaRegistry, SchemaType, SizeWidth,
+    StringFormat, TimestampPrecision,
+};
+use crate::error::{Error, Result, SchemaError};
+use crate::value::Value;
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::{json, Map};
+
+impl SchemaType {
+    /// Parses an `OpenAPI` schema object.
+    ///
+    /// Supported keywords are `type`, `format`, `properties`, `required`,
+    /// `items`, `enum` (of strings), `$ref`, and `oneOf` with a
+    /// `discriminator`. `oneOf` variants are either `$ref`s, named by the
+    /// discriminator `mapping` or the last segment of the reference, or
+    /// inline objects whose discriminator property is a single-valued `enum`
+    /// or a `const`. Integers without a format are `int64`, numbers without
+    /// a format are `double`, and unrecognized string formats are plain
+    /// strings. Arrays with `x-compactr-encoding: rle` are
+    /// [run-length encoded](SchemaType::RunLengthArray), plain strings with
+    /// `x-compactr-encoding: long` are [long strings](StringFormat::Long),
+    /// and properties with
+    /// `x-compactr-size: u8`, `u16` or `u32` get that
+    /// [size width](SizeWidth). The `title`, `description`, `example` and
+    /// `deprecated` keywords of properties are kept as their
+    /// [metadata](Metadata).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is not a schema object or uses an
+    /// unsupported construct.
+    pub fn from_json(json: &serde_json::Value) -> Result<Self> {
+        let obj = json
+            .as_object()
+            .ok_or_else(|| invalid(format!("expected a schema object, got {json}")))?;
+
+        if let Some(reference) = obj.get("$ref") {
+            let reference = reference
+                .as_str()
+                .ok_or_else(|| invalid("$ref must be a string"))?;
+            return Ok(Self::reference(reference));
+        }
+        if let Some(variants) = obj.get("oneOf") {
+            return one_of_from_json(obj, variants);
+        }
+        if let Some(variants) = obj.get("enum") {
+            let variants = variants
+                .as_array()
+                .ok_or_else(|| invalid("enum must be an array"))?
+                .iter()
+                .map(|v| {
+                    v.as_str()
+                        .map(ToOwned::to_owned)
+                        .ok_or_else(|| invalid("only string enums are supported"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(Self::Enum(variants));
+        }
+
+        let format = obj.get("format").and_then(serde_json::Value::as_str);
+        let ty = obj
+            .get("type")
+            .and_then(serde_json::Value::as_str)
+            .or_else(|| obj.contains_key("properties").then_some("object"))
+            .ok_or_else(|| invalid("missing type"))?;
+        match ty {
+            "boolean" => Ok(Self::Boolean),
+            "integer" => Ok(Self::Integer(match format {
+                Some("int8") => IntegerFormat::Int8,
+                Some("int16") => IntegerFormat::Int16,
+                Some("int32") => IntegerFormat::Int32,
+                Some("uint8") => IntegerFormat::Uint8,
+                Some("uint16") => IntegerFormat::Uint16,
+                Some("int128") => IntegerFormat::Int128,
+                Some("uint128") => IntegerFormat::Uint128,
+                _ => IntegerFormat::Int64,
+            })),
+            "number" => Ok(Self::Number(match format {
+                Some("float") => NumberFormat::Float,
+                #[cfg(feature = "decimal")]
+                Some("decimal") => NumberFormat::Decimal,
+                _ => NumberFormat::Double,
+            })),
+            "string" => string_from_json(
+                obj,
+                match format {
+                    Some("uuid") => StringFormat::Uuid,
+                    Some("date-time") => StringFormat::DateTime,
+                    Some("date-time-tz") => StringFormat::DateTimeTz,
+                    Some("timestamp" | "timestamp-millis") => {
+                        StringFormat::Timestamp(TimestampPrecision::Millis)
+                    }
+                    Some("timestamp-seconds") => {
+                        StringFormat::Timestamp(TimestampPrecision::Seconds)
+                    }
+                    Some("timestamp-micros") => StringFormat::Timestamp(TimestampPrecision::Micros),
+                    Some("timestamp-nanos") => StringFormat::Timestamp(TimestampPrecision::Nanos),
+                    Some("date") => StringFormat::Date,
+                    Some("time") => StringFormat::Time,
+                    Some("uri") => StringFormat::Uri,
+                    Some("ipv4") => StringFormat::Ipv4,
+                    Some("ipv6") => StringFormat::Ipv6,
+                    Some("socket-addr") => StringFormat::SocketAddr,
+                    Some("geopoint") => StringFormat::GeoPoint,
+                    Some("binary" | "byte") => StringFormat::Binary,
+                    _ => StringFormat::Plain,
+                },
+            ),
+            "array" => {
+                let items = obj
+                    .get("items")
+                    .ok_or_else(|| invalid("array schema missing items"))?;
+                let items = Self::from_json(items)?;
+                match obj
+                    .get("x-compactr-encoding")
+                    .and_then(serde_json::Value::as_str)
+                {
+                    Some("rle") => Ok(Self::run_length_array(items)),
+                    Some(other) => Err(invalid(format!("unsupported array encoding: {other}"))),
+                    None => Ok(Self::array(items)),
+                }
+            }
+            "object" => Ok(Self::Object(properties_from_json(obj)?)),
+            "null" => Ok(Self::Null),
+            other => Err(invalid(format!("unsupported type: {other}"))),
+        }
+    }
+
+    /// Converts this schema into an `OpenAPI` schema object accepted by
+    /// [`SchemaType::from_json`].
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Boolean => json!({"type": "boolean"}),
+            Self::Integer(format) => json!({"type": "integer", "format": format.to_string()}),
+            Self::Number(format) => {
+                let format = match format {
+                    NumberFormat::Float => "float",
+                    NumberFormat::Double => "double",
+                    #[cfg(feature = "decimal")]
+                    NumberFormat::Decimal => "decimal",
+                };
+                json!({"type": "number", "format": format})
+            }
+            Self::String(format) => {
+                let format = match format {
+                    StringFormat::Plain => return json!({"type": "string"}),
+                    StringFormat::Long => {
+                        return json!({"type": "string", "x-compactr-encoding": "long"})
+                    }
+                    StringFormat::Uuid => "uuid",
+                    StringFormat::DateTime => "date-time",
+                    StringFormat::DateTimeTz => "date-time-tz",
+                    StringFormat::Timestamp(TimestampPrecision::Seconds) => "timestamp-seconds",
+                    StringFormat::Timestamp(TimestampPrecision::Millis) => "timestamp-millis",
+                    StringFormat::Timestamp(TimestampPrecision::Micros) => "timestamp-micros",
+                    StringFormat::Timestamp(TimestampPrecision::Nanos) => "timestamp-nanos",
+                    StringFormat::Date => "date",
+                    StringFormat::Time => "time",
+                    StringFormat::Uri => "uri",
+                    StringFormat::SocketAddr => "socket-addr",
+                    StringFormat::GeoPoint => "geopoint",
+                    StringFormat::Ipv4 => "ipv4",
+                    StringFormat::Ipv6 => "ipv6",
+                    StringFormat::Binary => "binary",
+                    StringFormat::Custom(name) => name,
+                };
+                json!({"type": "string", "format": format})
+            }
+            Self::Array(items) => json!({"type": "array", "items": items.to_json()}),
+            Self::RunLengthArray(items) => json!({
+                "type": "array",
+                "items": items.to_json(),
+                "x-compactr-encoding": "rle"
+            }),
+            Self::Object(properties) => object_to_json(properties, None),
+            Self::Reference(reference) => json!({"$ref": reference}),
+            Self::Enum(variants) => json!({"type": "string", "enum": variants}),
+            Self::OneOf {
+                discriminator,
+                variants,
+            } => {
+                let mut mapping = Map::new();
+                let one_of: Vec<_> = variants
+                    .iter()
+                    .map(|(name, payload)| match payload {
+                        Self::Reference(reference) => {
+                            mapping.insert(name.clone(), json!(reference));
+                            json!({"$ref": reference})
+                        }
+                        Self::Object(properties) => {
+                            object_to_json(properties, Some((discriminator, name)))
+                        }
+                        _ => object_to_json(&IndexMap::new(), Some((discriminator, name))),
+                    })
+                    .collect();
+                let mut discriminator = json!({"propertyName": discriminator});
+                if !mapping.is_empty() {
+                    discriminator["mapping"] = mapping.into();
+                }
+                json!({"oneOf": one_of, "discriminator": discriminator})
+            }
+            Self::Null => json!({"type": "null"}),
+        }
+    }
+}
+
+impl SchemaRegistry {
+    /// Registers every schema of an `OpenAPI` `components.schemas` object,
+    /// or of a whole `OpenAPI` document containing one.
+    ///
+    /// References of the form `#/components/schemas/Name` resolve to the
+    /// schema registered as `Name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a schema cannot be parsed.
+    pub fn register_json_schemas(&self, json: &serde_json::Value) -> Result<()> {
+        let schemas = json
+            .pointer("/components/schemas")
+            .unwrap_or(json)
+            .as_object()
+            .ok_or_else(|| invalid("expected an object of schemas"))?;
+        for (name, schema) in schemas {
+            self.register(name.clone(), SchemaType::from_json(schema)?)?;
+        }
+        Ok(())
+    }
+}
+
+fn string_from_json(
+    obj: &Map<String, serde_json::Value>,
+    format: StringFormat,
+) -> Result<SchemaType> {
+    match obj
+        .get("x-compactr-encoding")
+        .and_then(serde_json::Value::as_str)
+    {
+        None => Ok(SchemaType::String(format)),
+        Some("long") if format == StringFormat::Plain => Ok(SchemaType::long_string()),
+        Some(other) => Err(invalid(format!("unsupported string encoding: {other}"))),
+    }
+}
+
+fn properties_from_json(
+    obj: &Map<String, serde_json::Value>,
+) -> Result<IndexMap<String, Property>> {
+    let required: Vec<&str> = match obj.get("required") {
+        Some(required) => required
+            .as_array()
+            .ok_or_else(|| invalid("required must be an array"))?
+            .iter()
+            .filter_map(serde_json::Value::as_str)
+            .collect(),
+        None => Vec::new(),
+    };
+    let mut properties = IndexMap::new();
+    if let Some(props) = obj.get("properties") {
+        let props = props
+            .as_object()
+            .ok_or_else(|| invalid("properties must be an object"))?;
+        for (name, json) in props {
+            let schema = SchemaType::from_json(json)?;
+            let property = if required.contains(&name.as_str()) {
+                Property::required(schema)
+            } else {
+                Property::optional(schema)
+            };
+            let size_width = match json.get("x-compactr-size").map(|w| (w, w.as_str())) {
+                None => SizeWidth::Auto,
+                Some((_, Some("u8"))) => SizeWidth::U8,
+                Some((_, Some("u16"))) => SizeWidth::U16,
+                Some((_, Some("u32"))) => SizeWidth::U32,
+                Some((other, _)) => {
+                    return Err(invalid(format!("unsupported size width: {other}")))
+                }
+            };
+            let sensitive = match json.get("x-compactr-sensitive") {
+                None => false,
+                Some(flag) => flag
+                    .as_bool()
+                    .ok_or_else(|| invalid("x-compactr-sensitive must be a boolean"))?,
+            };
+            let aliases = match json.get("x-compactr-aliases") {
+                None => Vec::new(),
+                Some(aliases) => aliases
+                    .as_array()
+                    .and_then(|aliases| {
+                        aliases
+                            .iter()
+                            .map(|alias| alias.as_str().map(str::to_owned))
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .ok_or_else(|| invalid("x-compactr-aliases must be an array of strings"))?,
+            };
+            let mut property = property
+                .with_size_width(size_width)
+                .with_sensitive(sensitive)
+                .with_metadata(metadata_from_json(json)?);
+            property.aliases = aliases;
+            properties.insert(name.clone(), property);
+        }
+    }
+    Ok(properties)
+}
+
+fn one_of_from_json(
+    obj: &Map<String, serde_json::Value>,
+    variants: &serde_json::Value,
+) -> Result<SchemaType> {
+    let discriminator = obj
+        .get("discriminator")
+        .and_then(|d| d.get("propertyName"))
+        .and_then(serde_json::Value::as_str)
+        .ok_or_else(|| invalid("oneOf requires a discriminator propertyName"))?;
+    let mapping = obj
+        .get("discriminator")
+        .and_then(|d| d.get("mapping"))
+        .and_then(serde_json::Value::as_object);
+
+    let mut out = IndexMap::new();
+    for variant in variants
+        .as_array()
+        .ok_or_else(|| invalid("oneOf must be an array"))?
+    {
+        if let Some(reference) = variant.get("$ref").and_then(serde_json::Value::as_str) {
+            let name = mapping
+                .and_then(|m| m.iter().find(|(_, r)| r.as_str() == Some(reference)))
+                .map_or_else(
+                    || reference.rsplit('/').next().unwrap_or(reference),
+                    |(name, _)| name.as_str(),
+                );
+            out.insert(name.to_owned(), SchemaType::reference(reference));
+            continue;
+        }
+
+        let variant = variant
+            .as_object()
+            .ok_or_else(|| invalid("oneOf variants must be schema objects"))?;
+        let tag = variant
+            .get("properties")
+            .and_then(|p| p.get(discriminator))
+            .and_then(|tag| {
+                tag.get("const")
+                    .or_else(|| tag.get("enum").and_then(|e| e.get(0)))
+            })
+            .and_then(serde_json::Value::as_str)
+            .ok_or_else(|| {
+                invalid(format!(
+                    "inline oneOf variant must fix {discriminator} with enum or const"
+                ))
+            })?;
+        let mut payload = variant.clone();
+        if let Some(serde_json::Value::Object(props)) = payload.get_mut("properties") {
+            props.remove(discriminator);
+        }
+        let properties = properties_from_json(&payload)?;
+        let payload = if properties.is_empty() {
+            SchemaType::Null
+        } else {
+            SchemaType::Object(properties)
+        };
+        out.insert(tag.to_owned(), payload);
+    }
+    Ok(SchemaType::one_of(discriminator, out))
+}
+
+fn object_to_json(
+    properties: &IndexMap<String, Property>,
+    tag: Option<(&String, &String)>,
+) -> serde_json::Value {
+    let mut props = Map::new();
+    let mut required = Vec::new();
+    if let Some((discriminator, name)) = tag {
+        props.insert(
+            discriminator.clone(),
+            json!({"type": "string", "enum": [name]}),
+        );
+        required.push(discriminator.clone());
+    }
+    for (name, property) in properties {
+        let mut schema = property.schema_type.to_json();
+        if property.size_width != SizeWidth::Auto {
+            schema["x-compactr-size"] = property.size_width.to_string().into();
+        }
+        if property.sensitive {
+            schema["x-compactr-sensitive"] = true.into();
+        }
+        if !property.aliases.is_empty() {
+            schema["x-compactr-aliases"] = property.aliases.clone().into();
+        }
+        metadata_to_json(&property.metadata, &mut schema);
+        props.insert(name.clone(), schema);
+        if property.required {
+            required.push(name.clone());
+        }
+    }
+    let mut obj = json!({"type": "object", "properties": props});
+    if !required.is_empty() {
+        obj["required"] = required.into();
+    }
+    obj
+}
+
+fn metadata_from_json(json: &serde_json::Value) -> Result<Metadata> {
+    let text = |key: &str| match json.get(key) {
+        None => Ok(None),
+        Some(text) => text
+            .as_str()
+            .map(|text| Some(text.to_owned()))
+            .ok_or_else(|| invalid(format!("{key} must be a string"))),
+    };
+    Ok(Metadata {
+        title: text("title")?,
+        description: text("description")?,
+        example: json
+            .get("example")
+            .map(|example| {
+                Value::deserialize(example).map_err(|e| invalid(format!("invalid example: {e}")))
+            })
+            .transpose()?,
+        deprecated: match json.get("deprecated") {
+            None => false,
+            Some(flag) => flag
+                .as_bool()
+                .ok_or_else(|| invalid("deprecated must be a boolean"))?,
+        },
+    })
+}
+
+fn metadata_to_json(metadata: &Metadata, schema: &mut serde_json::Value) {
+    if let Some(title) = &metadata.title {
+        schema["title"] = title.as_str().into();
+    }
+    if let Some(description) = &metadata.description {
+        schema["description"] = description.as_str().into();
+    }
+    if let Some(example) = &metadata.example {
+        schema["example"] = example.to_json();
+    }
+    if metadata.deprecated {
+        schema["deprecated"] = true.into();
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    SchemaError::InvalidSchema(msg.into()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_object() {
+        let schema = SchemaType::from_json(&json!({
+            "type": "object",
+            "required": ["id", "tags"],
+            "properties": {
+                "id": {"type": "string", "format": "uuid"},
+                "age": {"type": "integer", "format": "int32"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "role": {"type": "string", "enum": ["admin", "user"]},
+                "owner": {"$ref": "#/components/schemas/User"},
+                "email": {"type": "string", "format": "email"}
+            }
+        }))
+        .unwrap();
+
+        let mut expected = IndexMap::new();
+        expected.insert(
+            "id".to_owned(),
+            Property::required(SchemaType::string_uuid()),
+        );
+        expected.insert("age".to_owned(), Property::optional(SchemaType::int32()));
+        expected.insert(
+            "tags".to_owned(),
+            Property::required(SchemaType::array(SchemaType::string())),
+        );
+        expected.insert(
+            "role".to_owned(),
+            Property::optional(SchemaType::string_enum(["admin", "user"])),
+        );
+        expected.insert(
+            "owner".to_owned(),
+            Property::optional(SchemaType::reference("#/components/schemas/User")),
+        );
+        expected.insert("email".to_owned(), Property::optional(SchemaType::string()));
+        assert_eq!(schema, SchemaType::object(expected));
+    }
+
+    #[test]
+    fn test_parse_one_of() {
+        let schema = SchemaType::from_json(&json!({
+            "oneOf": [
+                {"$ref": "#/components/schemas/Circle"},
+                {"type": "object", "properties": {"kind": {"const": "empty"}}}
+            ],
+            "discriminator": {
+                "propertyName": "kind",
+                "mapping": {"circle": "#/components/schemas/Circle"}
+            }
+        }))
+        .unwrap();
+
+        let mut variants = IndexMap::new();
+        variants.insert(
+            "circle".to_owned(),
+            SchemaType::reference("#/components/schemas/Circle"),
+        );
+        variants.insert("empty".to_owned(), SchemaType::null());
+        assert_eq!(schema, SchemaType::one_of("kind", variants));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut circle = IndexMap::new();
+        circle.insert(
+            "radius".to_owned(),
+            Property::required(SchemaType::double()),
+        );
+        let mut variants = IndexMap::new();
+        variants.insert("circle".to_owned(), SchemaType::object(circle));
+        variants.insert("square".to_owned(), SchemaType::reference("#/Square"));
+        variants.insert("empty".to_owned(), SchemaType::null());
+
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "shape".to_owned(),
+            Property::required(SchemaType::one_of("type", variants)),
+        );
+        properties.insert(
+            "at".to_owned(),
+            Property::optional(SchemaType::timestamp(TimestampPrecision::Micros)),
+        );
+        properties.insert(
+            "counts".to_owned(),
+            Property::required(SchemaType::array(SchemaType::uint16())).with_alias("totals"),
+        );
+        properties.insert(
+            "flags".to_owned(),
+            Property::required(SchemaType::run_length_array(SchemaType::uint8())),
+        );
+        properties.insert(
+            "blob".to_owned(),
+            Property::optional(SchemaType::binary())
+                .with_size_width(SizeWidth::U32)
+                .with_sensitive(true),
+        );
+        properties.insert(
+            "body".to_owned(),
+            Property::required(SchemaType::long_string()).with_size_width(SizeWidth::U32),
+        );
+        properties.insert(
+            "owner".to_owned(),
+            Property::optional(SchemaType::reference("#/User")).with_metadata(
+                Metadata::new()
+                    .with_title("Owner")
+                    .with_description("Who created the shape")
+                    .with_example(Value::object([("name", "Ann")]))
+                    .with_deprecated(true),
+            ),
+        );
+        let schema = SchemaType::object(properties);
+
+        assert_eq!(SchemaType::from_json(&schema.to_json()).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_metadata() {
+        let schema = SchemaType::from_json(&json!({
+            "type": "object",
+            "properties": {
+                "id": {
+                    "type": "integer",
+                    "format": "int32",
+                    "title": "ID",
+                    "description": "Unique identifier",
+                    "example": 42,
+                    "deprecated": true
+                }
+            }
+        }))
+        .unwrap();
+        let SchemaType::Object(properties) = &schema else {
+            panic!("expected an object, got {schema:?}");
+        };
+        assert_eq!(
+            properties["id"].metadata,
+            Metadata::new()
+                .with_title("ID")
+                .with_description("Unique identifier")
+                .with_example(42)
+                .with_deprecated(true)
+        );
+        // Documentation doesn't change the wire format
+        assert_eq!(
+            schema.fingerprint(),
+            SchemaType::object([("id", Property::optional(SchemaType::int32()))]).fingerprint()
+        );
+
+        for invalid in [
+            json!({"type": "string", "description": 1}),
+            json!({"type": "string", "deprecated": "yes"}),
+        ] {
+            let schema = json!({"type": "object", "properties": {"id": invalid}});
+            assert!(SchemaType::from_json(&schema).is_err());
+        }
+    }
+
+    #[test]
+    fn test_register_components() {
+        let registry = SchemaRegistry::new();
+        registry
+            .register_json_schemas(&json!({
+                "openapi": "3.0.0",
+                "components": {"schemas": {"Name": {"type": "string"}}}
+            }))
+            .unwrap();
+        assert_eq!(
+            registry.resolve_ref("#/components/schemas/Name").unwrap(),
+            SchemaType::string()
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(SchemaType::from_json(&json!("string")).is_err());
+        assert!(SchemaType::from_json(&json!({"type": "array"})).is_err());
+        assert!(SchemaType::from_json(&json!({"oneOf": []})).is_err());
+        assert!(SchemaType::from_json(&json!({"enum": [1, 2]})).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Interoperability checks on schemas.
+
+use super::{IntegerFormat, SchemaType, SizeWidth, StringFormat};
+use crate::codec::DecoderOptions;
+use std::fmt;
+
+/// Property count from which objects are reported as close to the limit.
+const MANY_PROPERTIES: usize = 240;
+
+/// Most properties an object can have, as indices are a single byte.
+const MAX_PROPERTIES: usize = 255;
+
+/// A construct of a schema that encodes fine but may cause trouble, found
+/// by [`lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// Where the construct is, empty for the schema itself, `.name` for a
+    /// property, `[]` for array items and `.oneOf[name]` for a variant, as
+    /// in `.orders[].total`
+    pub path: String,
+    /// What was found
+    pub kind: LintKind,
+}
+
+/// The kinds of [`LintWarning`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintKind {
+    /// A 64-bit integer, which compactr.js reads into a JavaScript number
+    /// that loses precision beyond 2^53
+    Int64,
+    /// An object with this many properties, close to or over the 255 that
+    /// a single-byte index can address
+    ManyProperties(usize),
+    /// A string property of unbounded length framed by the default size
+    /// header, which holds at most 255 bytes in [`LengthMode::Fixed`](crate::LengthMode::Fixed)
+    UnboundedString,
+    /// Nesting this deep, which [untrusted](DecoderOptions::untrusted)
+    /// decoders reject
+    DeepNesting(usize),
+    /// A [deprecated](super::Metadata::deprecated) property that is
+    /// required, so that producers can't stop setting it
+    RequiredDeprecated,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        match self.kind {
+            LintKind::Int64 => f.write_str("int64 loses precision beyond 2^53 in compactr.js"),
+            LintKind::ManyProperties(count) if count > MAX_PROPERTIES => {
+                write!(
+                    f,
+                    "{count} properties, objects over {MAX_PROPERTIES} cannot be encoded"
+                )
+            }
+            LintKind::ManyProperties(count) => {
+                write!(
+                    f,
+                    "{count} properties, close to the limit of {MAX_PROPERTIES}"
+                )
+            }
+            LintKind::UnboundedString => f.write_str(
+                "string of unbounded length is limited to 255 bytes by its size header, \
+                 consider a size width",
+            ),
+            LintKind::DeepNesting(depth) => {
+                write!(
+                    f,
+                    "nested {depth} levels deep, rejected by untrusted decoders"
+                )
+            }
+            LintKind::RequiredDeprecated => {
+                f.write_str("deprecated but required, make it optional so it can be left out")
+            }
+        }
+    }
+}
+
+/// Checks a schema for constructs that encode fine but hurt
+/// interoperability or robustness, for use in CI of schema repositories.
+///
+/// References are not followed; lint each registered schema on its own.
+///
+/// ```rust
+/// use compactr::schema::{lint, LintKind};
+/// use compactr::{Property, SchemaType};
+///
+/// let schema = SchemaType::object([
+///     ("id", Property::required(SchemaType::int64())),
+///     ("name", Property::required(SchemaType::string())),
+/// ]);
+/// let kinds: Vec<_> = lint(&schema).into_iter().map(|w| (w.path, w.kind)).collect();
+/// assert_eq!(
+///     kinds,
+///     [(".id".to_owned(), LintKind::Int64), (".name".to_owned(), LintKind::UnboundedString)]
+/// );
+/// ```
+#[must_use]
+pub fn lint(schema: &SchemaType) -> Vec<LintWarning> {
+    let mut linter = Linter {
+        max_depth: DecoderOptions::untrusted().max_depth(),
+        warnings: Vec::new(),
+    };
+    linter.schema(schema, &mut String::new(), 0);
+    linter.warnings
+}
+
+struct Linter {
+    max_depth: usize,
+    warnings: Vec<LintWarning>,
+}
+
+impl Linter {
+    fn warn(&mut self, path: &str, kind: LintKind) {
+        self.warnings.push(LintWarning {
+            path: path.to_owned(),
+            kind,
+        });
+    }
+
+    fn schema(&mut self, schema: &SchemaType, path: &mut String, depth: usize) {
+        let nested = matches!(
+            schema,
+            SchemaType::Array(_)
+                | SchemaType::RunLengthArray(_)
+                | SchemaType::Object(_)
+                | SchemaType::OneOf { .. }
+        );
+        let depth = depth + usize::from(nested);
+        // Reported once, where the limit is reached
+        if nested && depth == self.max_depth {
+            self.warn(path, LintKind::DeepNesting(depth));
+        }
+
+        match schema {
+            SchemaType::Integer(IntegerFormat::Int64) => self.warn(path, LintKind::Int64),
+            SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+                with_segment(path, "[]", |path| self.schema(items, path, depth));
+            }
+            SchemaType::Object(properties) => {
+                if properties.len() >= MANY_PROPERTIES {
+                    self.warn(path, LintKind::ManyProperties(properties.len()));
+                }
+                for (name, property) in properties {
+                    with_segment(path, &format!(".{name}"), |path| {
+                        let unbounded = matches!(
+                            property.schema_type,
+                            SchemaType::String(
+                                StringFormat::Plain | StringFormat::Long | StringFormat::Binary
+                            )
+                        );
+                        if unbounded && property.size_width == SizeWidth::Auto {
+                            self.warn(path, LintKind::UnboundedString);
+                        }
+                        if property.required && property.metadata.deprecated {
+                            self.warn(path, LintKind::RequiredDeprecated);
+                        }
+                        self.schema(&property.schema_type, path, depth);
+                    });
+                }
+            }
+            SchemaType::OneOf { variants, .. } => {
+                for (name, payload) in variants {
+                    with_segment(path, &format!(".oneOf[{name}]"), |path| {
+                        self.schema(payload, path, depth);
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Runs `f` with `segment` appended to `path`.
+fn with_segment(path: &mut String, segment: &str, f: impl FnOnce(&mut String)) {
+    let len = path.len();
+    path.push_str(segment);
+    f(path);
+    path.truncate(len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Metadata, Property};
+
+    #[test]
+    fn test_lint() {
+        assert!(lint(&SchemaType::string()).is_empty());
+
+        let wide = SchemaType::object(
+            (0..250).map(|i| (format!("p{i}"), Property::optional(SchemaType::boolean()))),
+        );
+        let mut deep = SchemaType::int32();
+        for _ in 0..70 {
+            deep = SchemaType::array(deep);
+        }
+        let schema = SchemaType::object([
+            (
+                "notes",
+                Property::optional(SchemaType::long_string()).with_size_width(SizeWidth::U32),
+            ),
+            (
+                "events",
+                Property::required(SchemaType::array(SchemaType::object([(
+                    "at",
+                    SchemaType::int64(),
+                )]))),
+            ),
+            ("wide", Property::optional(wide)),
+            ("deep", Property::optional(deep)),
+            (
+                "legacy",
+                Property::required(SchemaType::boolean())
+                    .with_metadata(Metadata::new().with_deprecated(true)),
+            ),
+        ]);
+
+        let warnings = lint(&schema);
+        let found: Vec<_> = warnings.iter().map(|w| (w.path.as_str(), w.kind)).collect();
+        let deep_path = format!(".deep{}", "[]".repeat(62));
+        assert_eq!(
+            found,
+            [
+                (".events[].at", LintKind::Int64),
+                (".wide", LintKind::ManyProperties(250)),
+                (deep_path.as_str(), LintKind::DeepNesting(64)),
+                (".legacy", LintKind::RequiredDeprecated),
+            ]
+        );
+        assert_eq!(
+            warnings[1].to_string(),
+            ".wide: 250 properties, close to the limit of 255"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! The [`schema!`](crate::schema!) macro.
+
+/// Builds a [`SchemaType`](crate::SchemaType) from a compact description.
+///
+/// Objects are written as `{ name: type, ... }`, with `required` after the
+/// type of required properties; arrays as `[type]`. Scalar types are named
+/// after their [`SchemaType`](crate::SchemaType) constructors, with the
+/// `string_` prefix dropped (`uuid`, `datetime`, `ipv4`, ...). Any other
+/// schema can be spliced in as a parenthesized expression.
+///
+/// ```rust
+/// use compactr::{schema, SchemaType};
+///
+/// let schema = schema!({
+///     id: uuid required,
+///     name: string required,
+///     tags: [string],
+///     address: { city: string required, zip: uint16 },
+///     owner: (SchemaType::reference("User")),
+///     "display-name": string,
+/// });
+/// ```
+#[macro_export]
+macro_rules! schema {
+    ({ $($name:tt : $ty:tt $($flag:ident)*),* $(,)? }) => {{
+        #[allow(unused_mut)]
+        let mut properties = $crate::__private::IndexMap::new();
+        $(
+            properties.insert(
+                ::std::string::String::from($crate::schema!(@name $name)),
+                $crate::schema!(@property $crate::schema!($ty) $(, $flag)*),
+            );
+        )*
+        $crate::SchemaType::Object(properties)
+    }};
+    ([ $ty:tt ]) => {
+        $crate::SchemaType::array($crate::schema!($ty))
+    };
+    (( $schema:expr )) => {
+        $schema
+    };
+    (@name $name:ident) => {
+        ::std::stringify!($name)
+    };
+    (@name $name:literal) => {
+        $name
+    };
+    (@property $schema:expr) => {
+        $crate::Property::optional($schema)
+    };
+    (@property $schema:expr, required) => {
+        $crate::Property::required($schema)
+    };
+    (boolean) => { $crate::SchemaType::boolean() };
+    (int8) => { $crate::SchemaType::int8() };
+    (int16) => { $crate::SchemaType::int16() };
+    (int32) => { $crate::SchemaType::int32() };
+    (int64) => { $crate::SchemaType::int64() };
+    (int128) => { $crate::SchemaType::int128() };
+    (uint8) => { $crate::SchemaType::uint8() };
+    (uint16) => { $crate::SchemaType::uint16() };
+    (uint128) => { $crate::SchemaType::uint128() };
+    (float) => { $crate::SchemaType::float() };
+    (double) => { $crate::SchemaType::double() };
+    (decimal) => { $crate::SchemaType::decimal() };
+    (string) => { $crate::SchemaType::string() };
+    (long_string) => { $crate::SchemaType::long_string() };
+    (uuid) => { $crate::SchemaType::string_uuid() };
+    (datetime) => { $crate::SchemaType::string_datetime() };
+    (datetime_tz) => { $crate::SchemaType::string_datetime_tz() };
+    (date) => { $crate::SchemaType::string_date() };
+    (time) => { $crate::SchemaType::string_time() };
+    (uri) => { $crate::SchemaType::string_uri() };
+    (geo_point) => { $crate::SchemaType::geo_point() };
+    (ipv4) => { $crate::SchemaType::string_ipv4() };
+    (ipv6) => { $crate::SchemaType::string_ipv6() };
+    (socket_addr) => { $crate::SchemaType::string_socket_addr() };
+    (binary) => { $crate::SchemaType::binary() };
+    (null) => { $crate::SchemaType::null() };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schema::{Property, SchemaType};
+    use indexmap::IndexMap;
+
+    #[test]
+    fn test_schema_macro() {
+        let schema = schema!({
+            id: uuid required,
+            tags: [string],
+            address: { city: string required },
+            owner: (SchemaType::reference("User")),
+            "display-name": string,
+        });
+
+        let mut address = IndexMap::new();
+        address.insert("city".to_owned(), Property::required(SchemaType::string()));
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "id".to_owned(),
+            Property::required(SchemaType::string_uuid()),
+        );
+        properties.insert(
+            "tags".to_owned(),
+            Property::optional(SchemaType::array(SchemaType::string())),
+        );
+        properties.insert(
+            "address".to_owned(),
+            Property::optional(SchemaType::object(address)),
+        );
+        properties.insert(
+            "owner".to_owned(),
+            Property::optional(SchemaType::reference("User")),
+        );
+        properties.insert(
+            "display-name".to_owned(),
+            Property::optional(SchemaType::string()),
+        );
+        assert_eq!(schema, SchemaType::object(properties));
+
+        assert_eq!(schema!({}), SchemaType::Object(IndexMap::new()));
+        assert_eq!(
+            schema!([[int32]]),
+            SchemaType::array(SchemaType::array(SchemaType::int32()))
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Loading several `OpenAPI` specs into one registry.
+//!
+//! Platforms aggregating the specs of many services often see the same
+//! component name in several of them. Identical definitions are registered
+//! once; differing ones are either an error or, with
+//! [`ConflictPolicy::Namespace`], registered under a name prefixed with the
+//! namespace of the spec defining them, with the references of that spec
+//! rewritten to match.
+
+use super::{SchemaRegistry, SchemaType};
+use crate::error::{Result, SchemaError};
+use indexmap::IndexMap;
+use serde_json::Value as Json;
+use std::collections::HashMap;
+
+/// How [`SchemaRegistry::register_json_specs`] handles a component name
+/// defined differently by several specs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictPolicy {
+    /// Fail with [`SchemaError::Conflict`]
+    #[default]
+    Error,
+    /// Register later definitions as `namespace.Name`, where `namespace` is
+    /// given with the spec
+    Namespace,
+}
+
+impl SchemaRegistry {
+    /// Registers the `components.schemas` of several `OpenAPI` specs, each
+    /// given with a namespace such as the name of its service.
+    ///
+    /// Components defined identically by several specs, or already
+    /// registered, are kept once. A component defined differently than an
+    /// earlier one is handled according to `policy`; when it is namespaced,
+    /// so are the components of the same spec referring to it. Nothing is
+    /// registered if an error occurs.
+    ///
+    /// ```rust
+    /// use compactr::schema::ConflictPolicy;
+    /// use compactr::SchemaRegistry;
+    /// use serde_json::json;
+    ///
+    /// let users = json!({"components": {"schemas": {"Id": {"type": "string"}}}});
+    /// let billing = json!({"components": {"schemas": {"Id": {"type": "integer"}}}});
+    ///
+    /// let registry = SchemaRegistry::new();
+    /// let specs = [("users", &users), ("billing", &billing)];
+    /// assert!(registry.register_json_specs(specs, ConflictPolicy::Error).is_err());
+    /// registry.register_json_specs(specs, ConflictPolicy::Namespace)?;
+    /// assert_eq!(registry.names()?, ["Id", "billing.Id"]);
+    /// # Ok::<(), compactr::error::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchemaError::Conflict`] for a conflicting component under
+    /// [`ConflictPolicy::Error`], or an error if a schema cannot be parsed.
+    pub fn register_json_specs<'a>(
+        &self,
+        specs: impl IntoIterator<Item = (&'a str, &'a Json)>,
+        policy: ConflictPolicy,
+    ) -> Result<()> {
+        let staged = Self::new();
+        let existing = |name: &str| -> Result<Option<SchemaType>> {
+            Ok(match staged.get(name)? {
+                Some(schema) => Some(schema),
+                None => self.get(name)?,
+            })
+        };
+
+        for (namespace, spec) in specs {
+            let components = spec
+                .pointer("/components/schemas")
+                .unwrap_or(spec)
+                .as_object()
+                .ok_or_else(|| {
+                    SchemaError::InvalidSchema(format!(
+                        "{namespace}: expected an object of schemas"
+                    ))
+                })?;
+            let mut schemas = components
+                .iter()
+                .map(|(name, schema)| Ok((name.clone(), SchemaType::from_json(schema)?)))
+                .collect::<Result<IndexMap<_, _>>>()?;
+
+            // Namespacing a component changes the components referring to
+            // it, which may then conflict in turn
+            let mut renamed = HashMap::new();
+            loop {
+                let mut conflicts = Vec::new();
+                for (name, schema) in &schemas {
+                    let conflicting = !renamed.contains_key(name)
+                        && existing(name)?.is_some_and(|existing| existing != *schema);
+                    if conflicting {
+                        conflicts.push(name.clone());
+                    }
+                }
+                if conflicts.is_empty() {
+                    break;
+                }
+                if policy == ConflictPolicy::Error {
+                    return Err(SchemaError::Conflict(conflicts.swap_remove(0)).into());
+                }
+                for name in conflicts {
+                    renamed.insert(name.clone(), format!("{namespace}.{name}"));
+                }
+                for schema in schemas.values_mut() {
+                    rename_refs(schema, &renamed);
+                }
+            }
+
+            for (name, schema) in schemas {
+                let name = renamed.remove(&name).unwrap_or(name);
+                match existing(&name)? {
+                    Some(existing) if existing == schema => {}
+                    Some(_) => return Err(SchemaError::Conflict(name).into()),
+                    None => staged.register(name, schema)?,
+                }
+            }
+        }
+        self.merge(&staged)
+    }
+}
+
+/// Points references to renamed components at their new names.
+fn rename_refs(schema: &mut SchemaType, renamed: &HashMap<String, String>) {
+    match schema {
+        SchemaType::Reference(reference) => {
+            let name = reference
+                .strip_prefix("#/components/schemas/")
+                .or_else(|| reference.strip_prefix("#/"))
+                .unwrap_or(reference);
+            if let Some(new_name) = renamed.get(name) {
+                *reference = format!("#/components/schemas/{new_name}");
+            }
+        }
+        SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+            rename_refs(items, renamed);
+        }
+        SchemaType::Object(properties) => {
+            for property in properties.values_mut() {
+                rename_refs(&mut property.schema_type, renamed);
+            }
+        }
+        SchemaType::OneOf { variants, .. } => {
+            for payload in variants.values_mut() {
+                rename_refs(payload, renamed);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use serde_json::json;
+
+    fn spec(user: &Json) -> Json {
+        json!({"components": {"schemas": {
+            "Address": {"type": "object", "properties": {"city": {"type": "string"}}},
+            "User": user,
+            "Invoice": {
+                "type": "object",
+                "properties": {"to": {"$ref": "#/components/schemas/User"}}
+            }
+        }}})
+    }
+
+    #[test]
+    fn test_register_json_specs() {
+        let users = spec(&json!({"type": "object", "properties": {"name": {"type": "string"}}}));
+        let billing = spec(&json!({"type": "object", "properties": {"iban": {"type": "string"}}}));
+        let specs = [
+            ("users", &users),
+            ("users-copy", &users),
+            ("billing", &billing),
+        ];
+
+        let registry = SchemaRegistry::new();
+        let err = registry
+            .register_json_specs(specs, ConflictPolicy::Error)
+            .unwrap_err();
+        assert!(matches!(err, Error::Schema(SchemaError::Conflict(name)) if name == "User"));
+        assert!(registry.names().unwrap().is_empty());
+
+        registry
+            .register_json_specs(specs, ConflictPolicy::Namespace)
+            .unwrap();
+        assert_eq!(
+            registry.names().unwrap(),
+            [
+                "Address",
+                "Invoice",
+                "User",
+                "billing.Invoice",
+                "billing.User"
+            ]
+        );
+        let Some(SchemaType::Object(invoice)) = registry.get("billing.Invoice").unwrap() else {
+            panic!("expected object schema");
+        };
+        assert_eq!(
+            invoice["to"].schema_type,
+            SchemaType::reference("#/components/schemas/billing.User")
+        );
+        assert!(registry
+            .resolve_ref("#/components/schemas/billing.User")
+            .is_ok());
+    }
+}
@@ -1,7 +1,40 @@
 //! Schema types and utilities for defining data structures.
 
+#[cfg(feature = "serde")]
+mod avro;
+mod bounds;
+mod bundle;
 mod definition;
+#[cfg(feature = "serde")]
+mod external;
+mod extract;
+mod fingerprint;
+#[cfg(feature = "serde")]
+mod json;
+mod lint;
+mod macros;
+#[cfg(feature = "serde")]
+mod merge;
+mod projection;
 mod registry;
+mod store;
+#[cfg(feature = "serde")]
+mod swagger;
+mod traits;
 
-pub use definition::{IntegerFormat, NumberFormat, Property, SchemaType, StringFormat};
-pub use registry::SchemaRegistry;
+pub(crate) use definition::{index_of, index_order, INLINE_PROPERTIES};
+pub use definition::{
+    IntegerFormat, Metadata, NumberFormat, Property, SchemaType, SizeWidth, StringFormat,
+    TimestampPrecision,
+};
+#[cfg(feature = "serde")]
+pub use external::resolve_external_refs;
+pub use extract::extract_shared_objects;
+pub use lint::{lint, LintKind, LintWarning};
+#[cfg(feature = "serde")]
+pub use merge::ConflictPolicy;
+pub use projection::Projection;
+pub(crate) use registry::Resolved;
+pub use registry::{SchemaId, SchemaRegistry};
+pub use store::SchemaStore;
+pub use traits::Schema;
@@ -0,0 +1,64 @@
+//! Projections of object schemas onto some of their properties.
+
+use super::SchemaType;
+
+/// An object schema restricted to some of its properties, created with
+/// [`SchemaType::project`].
+///
+/// Decoding with [`Decoder::decode_projected`](crate::Decoder::decode_projected)
+/// skips the other properties by their header sizes without decoding them,
+/// so reading a few columns out of wide records costs little more than
+/// reading their bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projection {
+    schema: SchemaType,
+    fields: Vec<String>,
+}
+
+impl Projection {
+    /// Returns the full schema the data was encoded with.
+    #[must_use]
+    pub fn schema(&self) -> &SchemaType {
+        &self.schema
+    }
+
+    /// Returns the names of the properties kept.
+    #[must_use]
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+}
+
+impl SchemaType {
+    /// Projects this object schema onto the properties named in `fields`.
+    ///
+    /// The projection still describes the whole encoded object, since
+    /// property indices depend on every property of the schema, but decodes
+    /// only the named ones. Names the schema doesn't have are never
+    /// returned.
+    ///
+    /// ```rust
+    /// use compactr::{Decoder, Encoder, SchemaType, Value};
+    ///
+    /// let schema = SchemaType::object([
+    ///     ("id", SchemaType::int32()),
+    ///     ("name", SchemaType::string()),
+    ///     ("bio", SchemaType::string()),
+    /// ]);
+    /// let mut encoder = Encoder::new();
+    /// encoder.encode(&Value::object([("id", Value::from(1)), ("bio", Value::from("..."))]), &schema)?;
+    /// let bytes = encoder.finish();
+    ///
+    /// let projection = schema.project(&["id", "name"]);
+    /// let value = Decoder::decode_projected(&mut bytes.as_ref(), &projection)?;
+    /// assert_eq!(value, Value::object([("id", 1)]));
+    /// # Ok::<(), compactr::error::Error>(())
+    /// ```
+    #[must_use]
+    pub fn project(&self, fields: &[&str]) -> Projection {
+        Projection {
+            schema: self.clone(),
+            fields: fields.iter().map(|&field| field.to_owned()).collect(),
+        }
+    }
+}
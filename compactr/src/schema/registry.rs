@@ -1,9 +1,51 @@
 //! Thread-safe schema registry for managing and resolving schema references.
 
 use super::SchemaType;
+use crate::codec::{ArrayFormat, DecoderOptions, EncoderOptions, LengthMode, ObjectFormat};
 use crate::error::{Result, SchemaError};
+use crate::formats::custom::FormatRegistry;
+use crate::metrics::{MetricsEvent, MetricsHook};
+use crate::plugin::{DecodePlugin, DecodePlugins};
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, RwLock};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Identifies a registered schema in a [`Message`](crate::message::Message)
+/// envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SchemaId {
+    /// Numeric ID assigned with [`SchemaRegistry::register_id`]
+    Id(u32),
+    /// Structural fingerprint, see [`SchemaType::fingerprint`]
+    Fingerprint(u64),
+}
+
+impl fmt::Display for SchemaId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "id {id}"),
+            Self::Fingerprint(fp) => write!(f, "fingerprint {fp:016x}"),
+        }
+    }
+}
+
+/// Resolved references, with the number of times the schemas they were
+/// resolved from have been replaced.
+#[derive(Debug, Default)]
+struct ResolvedRefs {
+    generation: u64,
+    schemas: HashMap<String, Arc<SchemaType>>,
+}
+
+/// Lookup tables from [`SchemaId`]s to schema names.
+#[derive(Debug, Default)]
+struct SchemaIds {
+    by_id: HashMap<u32, String>,
+    id_of: HashMap<String, u32>,
+    by_fingerprint: HashMap<u64, String>,
+}
 
 /// A thread-safe registry for storing and resolving schemas.
 ///
@@ -12,6 +54,36 @@ use std::sync::{Arc, RwLock};
 #[derive(Debug, Clone)]
 pub struct SchemaRegistry {
     schemas: Arc<RwLock<HashMap<String, SchemaType>>>,
+    /// Resolved references, so that values repeating a reference don't
+    /// clone its schema every time. Cleared when a schema is replaced.
+    resolved: Arc<RwLock<ResolvedRefs>>,
+    ids: Arc<RwLock<SchemaIds>>,
+    formats: FormatRegistry,
+    options: EncoderOptions,
+    decoder_options: DecoderOptions,
+    metrics: MetricsHook,
+    plugins: DecodePlugins,
+}
+
+/// A schema that isn't a reference, borrowed from the schema being
+/// encoded or decoded or shared with the registry's resolved references.
+#[derive(Debug, Clone)]
+pub(crate) enum Resolved<'a> {
+    /// The schema itself
+    Borrowed(&'a SchemaType),
+    /// The schema a reference resolved to
+    Shared(Arc<SchemaType>),
+}
+
+impl Deref for Resolved<'_> {
+    type Target = SchemaType;
+
+    fn deref(&self) -> &SchemaType {
+        match self {
+            Self::Borrowed(schema) => schema,
+            Self::Shared(schema) => schema,
+        }
+    }
 }
 
 impl Default for SchemaRegistry {
@@ -26,24 +98,324 @@ impl SchemaRegistry {
     pub fn new() -> Self {
         Self {
             schemas: Arc::new(RwLock::new(HashMap::new())),
+            resolved: Arc::default(),
+            ids: Arc::new(RwLock::new(SchemaIds::default())),
+            formats: FormatRegistry::new(),
+            options: EncoderOptions::new(),
+            decoder_options: DecoderOptions::new(),
+            metrics: MetricsHook::default(),
+            plugins: DecodePlugins::default(),
         }
     }
 
+    /// Returns a shared empty registry with the default options, for the
+    /// functions that take no registry, so that they don't allocate one per
+    /// call. Nothing may be registered with it.
+    pub(crate) fn empty() -> &'static Self {
+        static EMPTY: OnceLock<SchemaRegistry> = OnceLock::new();
+        EMPTY.get_or_init(Self::new)
+    }
+
+    /// Attaches the custom formats used to resolve [`StringFormat::Custom`](super::StringFormat::Custom).
+    #[must_use]
+    pub fn with_formats(mut self, formats: FormatRegistry) -> Self {
+        self.formats = formats;
+        self
+    }
+
+    /// Returns the custom formats attached to this registry.
+    #[must_use]
+    pub const fn formats(&self) -> &FormatRegistry {
+        &self.formats
+    }
+
+    /// Sets all wire layout settings used by encoders and decoders given
+    /// this registry at once.
+    #[must_use]
+    pub const fn with_options(mut self, options: EncoderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Returns the wire layout settings.
+    #[must_use]
+    pub const fn options(&self) -> EncoderOptions {
+        self.options
+    }
+
+    /// Sets the array layout used by encoders and decoders given this
+    /// registry.
+    #[must_use]
+    pub const fn with_array_format(mut self, format: ArrayFormat) -> Self {
+        self.options = self.options.with_array_format(format);
+        self
+    }
+
+    /// Returns the array layout, [`ArrayFormat::SizePrefixed`] by default.
+    #[must_use]
+    pub const fn array_format(&self) -> ArrayFormat {
+        self.options.array_format()
+    }
+
+    /// Sets the object layout used by encoders and decoders given this
+    /// registry.
+    #[must_use]
+    pub const fn with_object_format(mut self, format: ObjectFormat) -> Self {
+        self.options = self.options.with_object_format(format);
+        self
+    }
+
+    /// Returns the object layout, [`ObjectFormat::Indexed`] by default.
+    #[must_use]
+    pub const fn object_format(&self) -> ObjectFormat {
+        self.options.object_format()
+    }
+
+    /// Sets how encoders and decoders given this registry write lengths,
+    /// sizes and counts.
+    #[must_use]
+    pub const fn with_length_mode(mut self, mode: LengthMode) -> Self {
+        self.options = self.options.with_length_mode(mode);
+        self
+    }
+
+    /// Returns the length mode, [`LengthMode::Fixed`] by default.
+    #[must_use]
+    pub const fn length_mode(&self) -> LengthMode {
+        self.options.length_mode()
+    }
+
+    /// Sets whether encoders and conversions given this registry may round
+    /// numbers to fit a `float`, `double` or `int64` schema, see
+    /// [`EncoderOptions::with_lossy_floats`].
+    #[must_use]
+    pub const fn with_lossy_floats(mut self, lossy: bool) -> Self {
+        self.options = self.options.with_lossy_floats(lossy);
+        self
+    }
+
+    /// Returns whether numbers may be rounded to fit a `float`, `double` or
+    /// `int64` schema, `false` by default.
+    #[must_use]
+    pub const fn lossy_floats(&self) -> bool {
+        self.options.lossy_floats()
+    }
+
+    /// Sets whether encoders given this registry write floats and doubles
+    /// in a canonical form, see [`EncoderOptions::with_canonical_floats`].
+    #[must_use]
+    pub const fn with_canonical_floats(mut self, canonical: bool) -> Self {
+        self.options = self.options.with_canonical_floats(canonical);
+        self
+    }
+
+    /// Returns whether floats and doubles are written in a canonical form,
+    /// `false` by default.
+    #[must_use]
+    pub const fn canonical_floats(&self) -> bool {
+        self.options.canonical_floats()
+    }
+
+    /// Sets the limits and checks applied by decoders given this registry.
+    #[must_use]
+    pub const fn with_decoder_options(mut self, options: DecoderOptions) -> Self {
+        self.decoder_options = options;
+        self
+    }
+
+    /// Returns the decoder limits, [`DecoderOptions::trusted`] by default.
+    #[must_use]
+    pub const fn decoder_options(&self) -> DecoderOptions {
+        self.decoder_options
+    }
+
+    /// Sets a hook called for every value encoded or decoded with this
+    /// registry, see [`metrics`](crate::metrics).
+    #[must_use]
+    pub fn with_metrics(
+        mut self,
+        hook: impl Fn(&MetricsEvent<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.metrics = MetricsHook::new(hook);
+        self
+    }
+
+    pub(crate) const fn metrics(&self) -> &MetricsHook {
+        &self.metrics
+    }
+
+    /// Adds a plugin replacing values decoded with this registry, see
+    /// [`plugin`](crate::plugin). Plugins added earlier take precedence.
+    #[must_use]
+    pub fn with_decode_plugin(mut self, plugin: impl DecodePlugin + 'static) -> Self {
+        self.plugins.push(plugin);
+        self
+    }
+
+    pub(crate) const fn decode_plugins(&self) -> &DecodePlugins {
+        &self.plugins
+    }
+
     /// Registers a schema with the given name.
     ///
+    /// The schema can also be looked up by its
+    /// [fingerprint](SchemaType::fingerprint).
+    ///
     /// # Errors
     ///
     /// Returns an error if the lock is poisoned (should not happen in normal usage).
     pub fn register(&self, name: impl Into<String>, schema: SchemaType) -> Result<()> {
         let name = name.into();
+        let fingerprint = schema.fingerprint();
+        let mut schemas = self
+            .schemas
+            .write()
+            .map_err(|_| SchemaError::InvalidSchema("Failed to acquire write lock".to_owned()))?;
+        let mut ids = self.write_ids()?;
+        if let Ok(mut resolved) = self.resolved.write() {
+            resolved.generation += 1;
+            resolved.schemas.clear();
+        }
+        if let Some(previous) = schemas.insert(name.clone(), schema) {
+            let previous = previous.fingerprint();
+            if ids.by_fingerprint.get(&previous) == Some(&name) {
+                ids.by_fingerprint.remove(&previous);
+            }
+        }
+        ids.by_fingerprint.insert(fingerprint, name);
+        Ok(())
+    }
+
+    /// Adds every schema of `other` to this registry, together with the
+    /// numeric IDs assigned to them.
+    ///
+    /// Schemas defined identically in both registries are kept once. Nothing
+    /// is added if any name is defined differently or an ID is assigned to
+    /// different schemas. Layout settings, formats and hooks of `other` are
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchemaError::Conflict`] for a name defined differently in
+    /// both registries, or an error if an ID is assigned to different
+    /// schemas or a lock is poisoned.
+    pub fn merge(&self, other: &Self) -> Result<()> {
+        if Arc::ptr_eq(&self.schemas, &other.schemas) {
+            return Ok(());
+        }
+        let incoming = other
+            .schemas
+            .read()
+            .map_err(|_| SchemaError::InvalidSchema("Failed to acquire read lock".to_owned()))?
+            .clone();
+        let incoming_ids = other.read_ids()?.by_id.clone();
+
         let mut schemas = self
             .schemas
             .write()
             .map_err(|_| SchemaError::InvalidSchema("Failed to acquire write lock".to_owned()))?;
-        schemas.insert(name, schema);
+        let mut ids = self.write_ids()?;
+        for (name, schema) in &incoming {
+            if schemas.get(name).is_some_and(|existing| existing != schema) {
+                return Err(SchemaError::Conflict(name.clone()).into());
+            }
+        }
+        for (id, name) in &incoming_ids {
+            let taken = ids.by_id.get(id).filter(|existing| *existing != name);
+            let renumbered = ids.id_of.get(name).is_some_and(|existing| existing != id);
+            if let Some(existing) = taken {
+                return Err(SchemaError::InvalidSchema(format!(
+                    "Schema id {id} is already assigned to {existing}"
+                ))
+                .into());
+            }
+            if renumbered {
+                return Err(SchemaError::Conflict(name.clone()).into());
+            }
+        }
+
+        for (name, schema) in incoming {
+            if let Entry::Vacant(entry) = schemas.entry(name) {
+                ids.by_fingerprint
+                    .insert(schema.fingerprint(), entry.key().clone());
+                entry.insert(schema);
+            }
+        }
+        for (id, name) in incoming_ids {
+            ids.id_of.insert(name.clone(), id);
+            ids.by_id.insert(id, name);
+        }
         Ok(())
     }
 
+    /// Assigns a numeric ID to the schema registered under `name`, for use
+    /// in [`Message`](crate::message::Message) envelopes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `name` is not registered, `id` is already
+    /// assigned to another schema, or the lock is poisoned.
+    pub fn register_id(&self, id: u32, name: &str) -> Result<()> {
+        if self.get(name)?.is_none() {
+            return Err(SchemaError::UnresolvedReference(name.to_owned()).into());
+        }
+        let mut ids = self.write_ids()?;
+        if let Some(existing) = ids.by_id.get(&id) {
+            if existing != name {
+                return Err(SchemaError::InvalidSchema(format!(
+                    "Schema id {id} is already assigned to {existing}"
+                ))
+                .into());
+            }
+        }
+        if let Some(old) = ids.id_of.insert(name.to_owned(), id) {
+            ids.by_id.remove(&old);
+        }
+        ids.by_id.insert(id, name.to_owned());
+        Ok(())
+    }
+
+    /// Returns the numeric ID assigned to the schema registered under `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is poisoned.
+    pub fn id_of(&self, name: &str) -> Result<Option<u32>> {
+        Ok(self.read_ids()?.id_of.get(name).copied())
+    }
+
+    /// Looks up a schema by numeric ID or fingerprint, returning its name
+    /// and definition.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is poisoned.
+    pub fn lookup(&self, id: SchemaId) -> Result<Option<(String, SchemaType)>> {
+        let name = {
+            let ids = self.read_ids()?;
+            match id {
+                SchemaId::Id(id) => ids.by_id.get(&id).cloned(),
+                SchemaId::Fingerprint(fp) => ids.by_fingerprint.get(&fp).cloned(),
+            }
+        };
+        let Some(name) = name else {
+            return Ok(None);
+        };
+        Ok(self.get(&name)?.map(|schema| (name, schema)))
+    }
+
+    fn read_ids(&self) -> Result<std::sync::RwLockReadGuard<'_, SchemaIds>> {
+        self.ids.read().map_err(|_| {
+            SchemaError::InvalidSchema("Failed to acquire read lock".to_owned()).into()
+        })
+    }
+
+    fn write_ids(&self) -> Result<std::sync::RwLockWriteGuard<'_, SchemaIds>> {
+        self.ids.write().map_err(|_| {
+            SchemaError::InvalidSchema("Failed to acquire write lock".to_owned()).into()
+        })
+    }
+
     /// Retrieves a schema by name.
     ///
     /// # Errors
@@ -57,6 +429,21 @@ impl SchemaRegistry {
         Ok(schemas.get(name).cloned())
     }
 
+    /// Returns the names of the registered schemas, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is poisoned.
+    pub fn names(&self) -> Result<Vec<String>> {
+        let schemas = self
+            .schemas
+            .read()
+            .map_err(|_| SchemaError::InvalidSchema("Failed to acquire read lock".to_owned()))?;
+        let mut names: Vec<_> = schemas.keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
     /// Resolves a schema reference, handling circular references.
     ///
     /// # Errors
@@ -70,6 +457,47 @@ impl SchemaRegistry {
         self.resolve_ref_internal(reference, &mut visited)
     }
 
+    /// Resolves a schema reference like [`resolve_ref`](Self::resolve_ref),
+    /// sharing the resolved schema with earlier resolutions of the same
+    /// reference rather than cloning it.
+    pub(crate) fn resolve_shared(&self, reference: &str) -> Result<Arc<SchemaType>> {
+        let mut generation = None;
+        if let Ok(resolved) = self.resolved.read() {
+            if let Some(schema) = resolved.schemas.get(reference) {
+                return Ok(Arc::clone(schema));
+            }
+            generation = Some(resolved.generation);
+        }
+        let schema = Arc::new(self.resolve_ref(reference)?);
+        if let Some(generation) = generation {
+            self.keep_resolved(reference, &schema, generation);
+        }
+        Ok(schema)
+    }
+
+    /// Keeps the resolution of `reference` started at `generation` of the
+    /// resolved references, unless a schema has been replaced since, which
+    /// may have made it stale.
+    fn keep_resolved(&self, reference: &str, schema: &Arc<SchemaType>, generation: u64) {
+        if let Ok(mut resolved) = self.resolved.write() {
+            if resolved.generation == generation {
+                resolved
+                    .schemas
+                    .insert(reference.to_owned(), Arc::clone(schema));
+            }
+        }
+    }
+
+    /// Returns `schema`, or the schema it refers to if it is a reference.
+    pub(crate) fn resolve<'a>(&self, schema: &'a SchemaType) -> Result<Resolved<'a>> {
+        match schema {
+            SchemaType::Reference(reference) => {
+                self.resolve_shared(reference).map(Resolved::Shared)
+            }
+            schema => Ok(Resolved::Borrowed(schema)),
+        }
+    }
+
     fn resolve_ref_internal(
         &self,
         reference: &str,
@@ -97,12 +525,12 @@ impl SchemaRegistry {
     }
 
     fn parse_reference(reference: &str) -> Result<String> {
-        // Support both "#/ComponentName" and "ComponentName" formats
-        let name = if let Some(stripped) = reference.strip_prefix("#/") {
-            stripped
-        } else {
-            reference
-        };
+        // Support "#/components/schemas/ComponentName", "#/ComponentName" and
+        // "ComponentName" formats
+        let name = reference
+            .strip_prefix("#/components/schemas/")
+            .or_else(|| reference.strip_prefix("#/"))
+            .unwrap_or(reference);
 
         if name.is_empty() {
             return Err(SchemaError::InvalidReference(reference.to_owned()).into());
@@ -115,6 +543,43 @@ impl SchemaRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn test_merge() {
+        let registry = SchemaRegistry::new();
+        registry.register("User", SchemaType::string()).unwrap();
+        registry.register_id(1, "User").unwrap();
+
+        let other = SchemaRegistry::new();
+        other.register("User", SchemaType::string()).unwrap();
+        other.register("Order", SchemaType::int32()).unwrap();
+        other.register_id(2, "Order").unwrap();
+        registry.merge(&other).unwrap();
+        assert_eq!(registry.names().unwrap(), ["Order", "User"]);
+        assert_eq!(registry.id_of("Order").unwrap(), Some(2));
+        assert_eq!(
+            registry
+                .lookup(SchemaId::Fingerprint(SchemaType::int32().fingerprint()))
+                .unwrap()
+                .map(|(name, _)| name),
+            Some("Order".to_owned())
+        );
+        registry.merge(&registry.clone()).unwrap();
+
+        let conflicting = SchemaRegistry::new();
+        conflicting.register("Tag", SchemaType::string()).unwrap();
+        conflicting.register("User", SchemaType::int64()).unwrap();
+        let err = registry.merge(&conflicting).unwrap_err();
+        assert!(matches!(err, Error::Schema(SchemaError::Conflict(name)) if name == "User"));
+        // Nothing is merged when any schema conflicts
+        assert_eq!(registry.get("Tag").unwrap(), None);
+
+        let reused = SchemaRegistry::new();
+        reused.register("Tag", SchemaType::string()).unwrap();
+        reused.register_id(1, "Tag").unwrap();
+        assert!(registry.merge(&reused).is_err());
+    }
 
     #[test]
     fn test_register_and_get() {
@@ -125,6 +590,37 @@ mod tests {
         assert_eq!(schema, Some(SchemaType::string()));
     }
 
+    #[test]
+    fn test_lookup_by_id_and_fingerprint() {
+        let registry = SchemaRegistry::new();
+        registry.register("User", SchemaType::string()).unwrap();
+        registry.register_id(7, "User").unwrap();
+
+        let expected = Some(("User".to_owned(), SchemaType::string()));
+        assert_eq!(registry.lookup(SchemaId::Id(7)).unwrap(), expected);
+        assert_eq!(
+            registry
+                .lookup(SchemaId::Fingerprint(SchemaType::string().fingerprint()))
+                .unwrap(),
+            expected
+        );
+        assert_eq!(registry.id_of("User").unwrap(), Some(7));
+        assert_eq!(registry.lookup(SchemaId::Id(8)).unwrap(), None);
+
+        registry.register("Other", SchemaType::boolean()).unwrap();
+        assert!(registry.register_id(7, "Other").is_err());
+        assert!(registry.register_id(9, "Missing").is_err());
+
+        // Re-registering drops the old fingerprint
+        registry.register("User", SchemaType::int32()).unwrap();
+        assert_eq!(
+            registry
+                .lookup(SchemaId::Fingerprint(SchemaType::string().fingerprint()))
+                .unwrap(),
+            None
+        );
+    }
+
     #[test]
     fn test_resolve_simple_reference() {
         let registry = SchemaRegistry::new();
@@ -134,6 +630,47 @@ mod tests {
         assert_eq!(resolved, SchemaType::string());
     }
 
+    #[test]
+    fn test_resolve_shared() {
+        let registry = SchemaRegistry::new();
+        registry.register("Name", SchemaType::string()).unwrap();
+        registry
+            .register("Alias", SchemaType::reference("#/Name"))
+            .unwrap();
+
+        let first = registry.resolve_shared("#/Alias").unwrap();
+        assert!(Arc::ptr_eq(
+            &first,
+            &registry.clone().resolve_shared("#/Alias").unwrap()
+        ));
+        assert_eq!(*first, SchemaType::string());
+
+        // Replacing a schema replaces the resolutions through it
+        registry.register("Name", SchemaType::int32()).unwrap();
+        assert_eq!(
+            *registry.resolve_shared("#/Alias").unwrap(),
+            SchemaType::int32()
+        );
+        assert!(registry.resolve_shared("#/Missing").is_err());
+    }
+
+    #[test]
+    fn test_resolve_shared_while_registering() {
+        let registry = SchemaRegistry::new();
+        registry.register("Name", SchemaType::string()).unwrap();
+
+        // A resolution started before the schema is replaced isn't kept
+        // when it finishes afterwards
+        let generation = registry.resolved.read().unwrap().generation;
+        let stale = Arc::new(registry.resolve_ref("#/Name").unwrap());
+        registry.register("Name", SchemaType::int32()).unwrap();
+        registry.keep_resolved("#/Name", &stale, generation);
+        assert_eq!(
+            *registry.resolve_shared("#/Name").unwrap(),
+            SchemaType::int32()
+        );
+    }
+
     #[test]
     fn test_circular_reference_detection() {
         let registry = SchemaRegistry::new();
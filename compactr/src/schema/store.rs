@@ -0,0 +1,208 @@
+//! Content-addressable storage of schemas by fingerprint.
+
+use super::{SchemaId, SchemaRegistry, SchemaType};
+use crate::codec::{Decoder, Encoder};
+use crate::error::{Result, SchemaError};
+use crate::message::{write_schema_id, Message};
+use crate::value::Value;
+use bytes::{Buf, Bytes, BytesMut};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// A thread-safe store of schemas keyed by their
+/// [fingerprint](SchemaType::fingerprint).
+///
+/// Where a [`SchemaRegistry`] names schemas, a store identifies them by
+/// content: the same schema always gets the same key, in every process, with
+/// no assignment of IDs to coordinate. It backs the fingerprint form of the
+/// [`Message`] envelope, so a consumer finds the exact schema each payload
+/// was written with:
+///
+/// ```rust
+/// use compactr::{SchemaStore, SchemaType, Value};
+///
+/// let store = SchemaStore::new();
+/// let v1 = SchemaType::object([("name", SchemaType::string())]);
+/// let v2 = SchemaType::object([("name", SchemaType::string()), ("age", SchemaType::int32())]);
+///
+/// let old = store.encode(&Value::object([("name", "ann")]), &v1)?;
+/// let new = store.encode(&Value::object([("name", Value::from("bob")), ("age", Value::from(7))]), &v2)?;
+///
+/// let (schema, value) = store.decode(&mut old.as_ref())?;
+/// assert_eq!((&*schema, value), (&v1, Value::object([("name", "ann")])));
+/// let (schema, _) = store.decode(&mut new.as_ref())?;
+/// assert_eq!(*schema, v2);
+/// # Ok::<(), compactr::error::Error>(())
+/// ```
+///
+/// References inside stored schemas contribute only their name to the
+/// fingerprint, and are resolved with the store's registry.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaStore {
+    schemas: Arc<RwLock<HashMap<u64, Arc<SchemaType>>>>,
+    registry: SchemaRegistry,
+}
+
+impl SchemaStore {
+    /// Creates an empty store.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the registry resolving the references of stored schemas, whose
+    /// formats and options values are encoded and decoded with.
+    #[must_use]
+    pub fn with_registry(mut self, registry: SchemaRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Returns the registry resolving references.
+    #[must_use]
+    pub const fn registry(&self) -> &SchemaRegistry {
+        &self.registry
+    }
+
+    /// Stores `schema` unless a schema with the same fingerprint is stored
+    /// already, and returns the fingerprint.
+    ///
+    /// Schemas with the same fingerprint encode values the same way, so the
+    /// first one stored is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is poisoned.
+    pub fn get_or_register(&self, schema: &SchemaType) -> Result<u64> {
+        let fingerprint = schema.fingerprint();
+        if !self.read()?.contains_key(&fingerprint) {
+            self.write()?
+                .entry(fingerprint)
+                .or_insert_with(|| Arc::new(schema.clone()));
+        }
+        Ok(fingerprint)
+    }
+
+    /// Returns the schema stored under `fingerprint`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is poisoned.
+    pub fn get(&self, fingerprint: u64) -> Result<Option<Arc<SchemaType>>> {
+        Ok(self.read()?.get(&fingerprint).cloned())
+    }
+
+    /// Returns the fingerprints of the stored schemas, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock is poisoned.
+    pub fn fingerprints(&self) -> Result<Vec<u64>> {
+        let mut fingerprints: Vec<_> = self.read()?.keys().copied().collect();
+        fingerprints.sort_unstable();
+        Ok(fingerprints)
+    }
+
+    /// Encodes `value` with `schema`, prefixed with the schema's fingerprint
+    /// as in a [`Message`], and stores the schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value doesn't match the schema or the lock
+    /// is poisoned.
+    pub fn encode(&self, value: &Value, schema: &SchemaType) -> Result<Bytes> {
+        let mut encoder = Encoder::new();
+        encoder.encode_with_registry(value, schema, &self.registry)?;
+        let payload = encoder.finish();
+        let fingerprint = self.get_or_register(schema)?;
+        let mut buf = BytesMut::with_capacity(9 + payload.len());
+        write_schema_id(&mut buf, SchemaId::Fingerprint(fingerprint));
+        buf.extend_from_slice(&payload);
+        Ok(buf.freeze())
+    }
+
+    /// Decodes a value prefixed with the fingerprint of its schema, returning
+    /// it with the schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`SchemaError::UnknownSchema`] if the prefix is a numeric ID
+    /// or a fingerprint that isn't stored, or an error if the data is
+    /// truncated or the payload doesn't match the schema.
+    pub fn decode(&self, buf: &mut impl Buf) -> Result<(Arc<SchemaType>, Value)> {
+        let id = Message::read_schema_id(buf)?;
+        let schema = match id {
+            SchemaId::Fingerprint(fingerprint) => self.get(fingerprint)?,
+            SchemaId::Id(_) => None,
+        }
+        .ok_or(SchemaError::UnknownSchema(id))?;
+        let value = Decoder::decode_with_registry(buf, &schema, &self.registry)?;
+        Ok((schema, value))
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, HashMap<u64, Arc<SchemaType>>>> {
+        self.schemas.read().map_err(|_| {
+            SchemaError::InvalidSchema("Failed to acquire read lock".to_owned()).into()
+        })
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, HashMap<u64, Arc<SchemaType>>>> {
+        self.schemas.write().map_err(|_| {
+            SchemaError::InvalidSchema("Failed to acquire write lock".to_owned()).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::schema::Property;
+
+    #[test]
+    fn test_schema_store() {
+        let store = SchemaStore::new();
+        let a = SchemaType::object([
+            ("id", Property::required(SchemaType::int32())),
+            ("name", Property::optional(SchemaType::string())),
+        ]);
+        // Same structure, declared in another order
+        let b = SchemaType::object([
+            ("name", Property::optional(SchemaType::string())),
+            ("id", Property::required(SchemaType::int32())),
+        ]);
+        let fingerprint = store.get_or_register(&a).unwrap();
+        assert_eq!(fingerprint, a.fingerprint());
+        assert_eq!(store.get_or_register(&b).unwrap(), fingerprint);
+        assert_eq!(store.get(fingerprint).unwrap().as_deref(), Some(&a));
+        store.get_or_register(&SchemaType::string()).unwrap();
+        assert_eq!(store.fingerprints().unwrap().len(), 2);
+
+        // Clones share the schemas, like a consumer in another thread
+        let consumer = store.clone();
+        let value = Value::object([("id", 7)]);
+        let err = store.encode(&value, &SchemaType::int64()).unwrap_err();
+        assert!(matches!(err, Error::Encode(_)));
+        assert_eq!(store.get(SchemaType::int64().fingerprint()).unwrap(), None);
+        let bytes = store.encode(&value, &b).unwrap();
+        let (schema, decoded) = consumer.decode(&mut bytes.as_ref()).unwrap();
+        assert_eq!((&*schema, decoded), (&a, value));
+
+        // Unknown fingerprints and numeric IDs aren't found
+        let other = SchemaStore::new();
+        assert!(matches!(
+            other.decode(&mut bytes.as_ref()),
+            Err(Error::Schema(SchemaError::UnknownSchema(_)))
+        ));
+        let registry = SchemaRegistry::new();
+        registry.register("Id", SchemaType::int32()).unwrap();
+        registry.register_id(1, "Id").unwrap();
+        let bytes = Message::new("Id", Value::Integer(1))
+            .encode(&registry)
+            .unwrap();
+        assert!(matches!(
+            store.decode(&mut bytes.as_ref()),
+            Err(Error::Schema(SchemaError::UnknownSchema(SchemaId::Id(1))))
+        ));
+    }
+}
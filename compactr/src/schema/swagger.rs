@@ -0,0 +1,292 @@
+//! Best-effort import of Swagger 2.0 `definitions`.
+//!
+//! Swagger 2.0 schema objects are close to `OpenAPI` 3 ones, so each
+//! definition is rewritten into the form accepted by
+//! [`SchemaType::from_json`]:
+//!
+//! - `#/definitions/Name` references point at the schema registered as `Name`
+//! - `type: file` becomes a binary string
+//! - `allOf` members, inline or referenced, are merged into a single object
+//! - a definition with a `discriminator` becomes a `oneOf` over the
+//!   definitions extending it through `allOf`, tagged with their
+//!   `x-discriminator-value` or their name
+//! - `x-nullable` properties are optional even when listed as `required`
+//!
+//! Other keywords are passed through and ignored like in `OpenAPI` schemas.
+
+use super::{SchemaRegistry, SchemaType};
+use crate::error::{Error, Result, SchemaError};
+use serde_json::{json, Map, Value as Json};
+
+const DEFINITIONS_PREFIX: &str = "#/definitions/";
+
+impl SchemaRegistry {
+    /// Registers every schema of a Swagger 2.0 `definitions` object, or of a
+    /// whole Swagger 2.0 document containing one.
+    ///
+    /// References of the form `#/definitions/Name` resolve to the schema
+    /// registered as `Name`. A polymorphic definition (one with a
+    /// `discriminator`) is registered as a `oneOf` of its subtypes, whose
+    /// own schemas no longer carry the discriminator property.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a definition cannot be parsed or its `allOf`
+    /// chain is unresolved or circular.
+    pub fn register_swagger_definitions(&self, json: &Json) -> Result<()> {
+        let definitions = json
+            .get("definitions")
+            .unwrap_or(json)
+            .as_object()
+            .ok_or_else(|| invalid("expected an object of definitions"))?;
+        let upgrader = Upgrader { definitions };
+        for (name, definition) in definitions {
+            let schema = SchemaType::from_json(&upgrader.definition(name, definition)?)?;
+            self.register(name.clone(), schema)?;
+        }
+        Ok(())
+    }
+}
+
+/// Rewrites Swagger 2.0 schemas into `OpenAPI` 3 ones.
+struct Upgrader<'a> {
+    definitions: &'a Map<String, Json>,
+}
+
+impl Upgrader<'_> {
+    fn definition(&self, name: &str, definition: &Json) -> Result<Json> {
+        let Some(discriminator) = definition.get("discriminator").and_then(Json::as_str) else {
+            return self.schema(definition);
+        };
+        let reference = format!("{DEFINITIONS_PREFIX}{name}");
+        let mut one_of = Vec::new();
+        let mut mapping = Map::new();
+        for (subtype, schema) in self.definitions {
+            let extends = schema
+                .get("allOf")
+                .and_then(Json::as_array)
+                .is_some_and(|members| members.iter().any(|m| m["$ref"] == *reference));
+            if !extends {
+                continue;
+            }
+            let tag = schema
+                .get("x-discriminator-value")
+                .and_then(Json::as_str)
+                .unwrap_or(subtype);
+            let target = upgrade_ref(&format!("{DEFINITIONS_PREFIX}{subtype}"));
+            one_of.push(json!({"$ref": target}));
+            mapping.insert(tag.to_owned(), target.into());
+        }
+        if one_of.is_empty() {
+            // A base without subtypes is just an object
+            return self.schema(definition);
+        }
+        Ok(json!({
+            "oneOf": one_of,
+            "discriminator": {"propertyName": discriminator, "mapping": mapping}
+        }))
+    }
+
+    fn schema(&self, json: &Json) -> Result<Json> {
+        let obj = json
+            .as_object()
+            .ok_or_else(|| invalid(format!("expected a schema object, got {json}")))?;
+        if let Some(reference) = obj.get("$ref") {
+            let reference = reference
+                .as_str()
+                .ok_or_else(|| invalid("$ref must be a string"))?;
+            return Ok(json!({"$ref": upgrade_ref(reference)}));
+        }
+
+        let mut out = obj.clone();
+        out.remove("discriminator");
+        if obj.get("type").and_then(Json::as_str) == Some("file") {
+            out.insert("type".to_owned(), "string".into());
+            out.insert("format".to_owned(), "binary".into());
+        }
+        if let Some(items) = obj.get("items") {
+            out.insert("items".to_owned(), self.schema(items)?);
+        }
+        if obj.contains_key("properties") || obj.contains_key("allOf") {
+            let mut properties = Map::new();
+            let mut required = Vec::new();
+            self.collect(obj, &mut properties, &mut required, &mut Vec::new())?;
+
+            let mut upgraded = Map::new();
+            for (name, property) in &properties {
+                if property.get("x-nullable") == Some(&Json::Bool(true)) {
+                    required.retain(|r| r != name);
+                }
+                upgraded.insert(name.clone(), self.schema(property)?);
+            }
+            out.remove("allOf");
+            out.insert("type".to_owned(), "object".into());
+            out.insert("properties".to_owned(), upgraded.into());
+            out.insert("required".to_owned(), required.into());
+        }
+        Ok(Json::Object(out))
+    }
+
+    /// Gathers the properties and required names of an object and of its
+    /// `allOf` members, dropping the discriminator of polymorphic bases.
+    fn collect(
+        &self,
+        obj: &Map<String, Json>,
+        properties: &mut Map<String, Json>,
+        required: &mut Vec<String>,
+        visiting: &mut Vec<String>,
+    ) -> Result<()> {
+        let members = obj.get("allOf").and_then(Json::as_array);
+        for member in members.into_iter().flatten() {
+            let Some(reference) = member.get("$ref").and_then(Json::as_str) else {
+                let member = member
+                    .as_object()
+                    .ok_or_else(|| invalid("allOf members must be schema objects"))?;
+                self.collect(member, properties, required, visiting)?;
+                continue;
+            };
+            let name = reference
+                .strip_prefix(DEFINITIONS_PREFIX)
+                .ok_or_else(|| invalid(format!("unsupported allOf reference {reference}")))?;
+            if visiting.iter().any(|v| v == name) {
+                return Err(SchemaError::CircularReference(name.to_owned()).into());
+            }
+            let base = self
+                .definitions
+                .get(name)
+                .and_then(Json::as_object)
+                .ok_or_else(|| SchemaError::UnresolvedReference(name.to_owned()))?;
+            visiting.push(name.to_owned());
+            self.collect(base, properties, required, visiting)?;
+            visiting.pop();
+            if let Some(discriminator) = base.get("discriminator").and_then(Json::as_str) {
+                properties.remove(discriminator);
+                required.retain(|r| r != discriminator);
+            }
+        }
+
+        if let Some(props) = obj.get("properties") {
+            let props = props
+                .as_object()
+                .ok_or_else(|| invalid("properties must be an object"))?;
+            properties.extend(props.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        let names = obj.get("required").and_then(Json::as_array);
+        for name in names.into_iter().flatten().filter_map(Json::as_str) {
+            if !required.iter().any(|r| r == name) {
+                required.push(name.to_owned());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rewrites `#/definitions/Name` into `#/components/schemas/Name`.
+fn upgrade_ref(reference: &str) -> String {
+    reference.strip_prefix(DEFINITIONS_PREFIX).map_or_else(
+        || reference.to_owned(),
+        |name| format!("#/components/schemas/{name}"),
+    )
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    SchemaError::InvalidSchema(msg.into()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Property;
+    use indexmap::IndexMap;
+
+    fn document() -> Json {
+        json!({
+            "swagger": "2.0",
+            "definitions": {
+                "Pet": {
+                    "type": "object",
+                    "discriminator": "petType",
+                    "required": ["name", "petType"],
+                    "properties": {
+                        "name": {"type": "string"},
+                        "petType": {"type": "string"}
+                    }
+                },
+                "Cat": {
+                    "allOf": [
+                        {"$ref": "#/definitions/Pet"},
+                        {"properties": {"lives": {"type": "integer", "format": "int32"}}}
+                    ]
+                },
+                "Dog": {
+                    "x-discriminator-value": "dog",
+                    "allOf": [{"$ref": "#/definitions/Pet"}]
+                },
+                "Owner": {
+                    "type": "object",
+                    "required": ["pets", "photo", "nickname"],
+                    "properties": {
+                        "pets": {"type": "array", "items": {"$ref": "#/definitions/Pet"}},
+                        "photo": {"type": "file"},
+                        "nickname": {"type": "string", "x-nullable": true}
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn test_register_definitions() {
+        let registry = SchemaRegistry::new();
+        registry.register_swagger_definitions(&document()).unwrap();
+
+        let mut owner = IndexMap::new();
+        owner.insert(
+            "nickname".to_owned(),
+            Property::optional(SchemaType::string()),
+        );
+        owner.insert(
+            "pets".to_owned(),
+            Property::required(SchemaType::array(SchemaType::reference(
+                "#/components/schemas/Pet",
+            ))),
+        );
+        owner.insert("photo".to_owned(), Property::required(SchemaType::binary()));
+        assert_eq!(
+            registry.get("Owner").unwrap(),
+            Some(SchemaType::object(owner))
+        );
+
+        let mut cat = IndexMap::new();
+        cat.insert("lives".to_owned(), Property::optional(SchemaType::int32()));
+        cat.insert("name".to_owned(), Property::required(SchemaType::string()));
+        assert_eq!(registry.get("Cat").unwrap(), Some(SchemaType::object(cat)));
+
+        let mut variants = IndexMap::new();
+        variants.insert(
+            "Cat".to_owned(),
+            SchemaType::reference("#/components/schemas/Cat"),
+        );
+        variants.insert(
+            "dog".to_owned(),
+            SchemaType::reference("#/components/schemas/Dog"),
+        );
+        assert_eq!(
+            registry.get("Pet").unwrap(),
+            Some(SchemaType::one_of("petType", variants))
+        );
+    }
+
+    #[test]
+    fn test_invalid_all_of() {
+        let registry = SchemaRegistry::new();
+        let circular = json!({
+            "A": {"allOf": [{"$ref": "#/definitions/B"}]},
+            "B": {"allOf": [{"$ref": "#/definitions/A"}]}
+        });
+        assert!(registry.register_swagger_definitions(&circular).is_err());
+
+        let missing = json!({"A": {"allOf": [{"$ref": "#/definitions/Missing"}]}});
+        assert!(registry.register_swagger_definitions(&missing).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Trait for types with a statically known schema.
+
+use super::SchemaType;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use std::borrow::Cow;
+use std::net::{Ipv4Addr, Ipv6Addr};
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+/// Types whose wire layout is described by a fixed schema.
+///
+/// Implemented for the standard types supported by
+/// [`Encode`](crate::Encode)/[`Decode`](crate::Decode) and generated by
+/// `#[derive(Compactr)]` (`derive` feature).
+pub trait Schema {
+    /// Whether values of this type may be absent from an object.
+    ///
+    /// Properties of optional types are not required in derived schemas.
+    const OPTIONAL: bool = false;
+
+    /// Returns the schema describing this type.
+    fn schema() -> SchemaType;
+}
+
+impl Schema for bool {
+    fn schema() -> SchemaType {
+        SchemaType::boolean()
+    }
+}
+
+impl Schema for i32 {
+    fn schema() -> SchemaType {
+        SchemaType::int32()
+    }
+}
+
+impl Schema for i64 {
+    fn schema() -> SchemaType {
+        SchemaType::int64()
+    }
+}
+
+impl Schema for f32 {
+    fn schema() -> SchemaType {
+        SchemaType::float()
+    }
+}
+
+impl Schema for f64 {
+    fn schema() -> SchemaType {
+        SchemaType::double()
+    }
+}
+
+impl Schema for String {
+    fn schema() -> SchemaType {
+        SchemaType::string()
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl Schema for Uuid {
+    fn schema() -> SchemaType {
+        SchemaType::string_uuid()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl Schema for DateTime<Utc> {
+    fn schema() -> SchemaType {
+        SchemaType::string_datetime()
+    }
+}
+
+impl Schema for Ipv4Addr {
+    fn schema() -> SchemaType {
+        SchemaType::string_ipv4()
+    }
+}
+
+impl Schema for Ipv6Addr {
+    fn schema() -> SchemaType {
+        SchemaType::string_ipv6()
+    }
+}
+
+impl Schema for str {
+    fn schema() -> SchemaType {
+        SchemaType::string()
+    }
+}
+
+impl<T: Schema + ?Sized> Schema for &T {
+    const OPTIONAL: bool = T::OPTIONAL;
+
+    fn schema() -> SchemaType {
+        T::schema()
+    }
+}
+
+impl<T: Schema + ToOwned + ?Sized> Schema for Cow<'_, T> {
+    const OPTIONAL: bool = T::OPTIONAL;
+
+    fn schema() -> SchemaType {
+        T::schema()
+    }
+}
+
+impl<T: Schema> Schema for Option<T> {
+    const OPTIONAL: bool = true;
+
+    fn schema() -> SchemaType {
+        T::schema()
+    }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema() -> SchemaType {
+        SchemaType::array(T::schema())
+    }
+}
+
+impl<T: Schema, const N: usize> Schema for [T; N] {
+    fn schema() -> SchemaType {
+        SchemaType::array(T::schema())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_std_schemas() {
+        assert_eq!(
+            Vec::<Ipv4Addr>::schema(),
+            SchemaType::array(SchemaType::string_ipv4())
+        );
+        assert_eq!(Option::<i32>::schema(), SchemaType::int32());
+        assert_eq!([Option::<i32>::OPTIONAL, i32::OPTIONAL], [true, false]);
+    }
+}
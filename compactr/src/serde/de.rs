@@ -0,0 +1,274 @@
+//! `Deserializer` reading a [`Value`] laid out according to a schema.
+
+use super::{property_schema, resolve};
+use crate::error::{Error, Result, SchemaError};
+use crate::schema::{Resolved, SchemaRegistry, SchemaType};
+use crate::value::Value;
+use ::serde::de::value::{SeqDeserializer, StringDeserializer};
+use ::serde::de::{self, DeserializeSeed, Visitor};
+use ::serde::forward_to_deserialize_any;
+use indexmap::IndexMap;
+use std::sync::Arc;
+
+/// Schema of values the schema doesn't describe; they are read as they are.
+static UNKNOWN: SchemaType = SchemaType::null();
+
+/// Deserializer reading a [`Value`] laid out according to a schema.
+///
+/// Values map to the serde data model by their variant; the schema is
+/// needed for `oneOf` values, whose discriminator names the enum variant.
+/// Formatted values (UUIDs, date-times, IP addresses, ...) are presented in
+/// their string form.
+pub struct Deserializer<'a> {
+    value: Value,
+    schema: &'a SchemaType,
+    registry: &'a SchemaRegistry,
+}
+
+impl<'a> Deserializer<'a> {
+    /// Creates a deserializer for `value`, laid out according to `schema`.
+    #[must_use]
+    pub fn new(value: Value, schema: &'a SchemaType, registry: &'a SchemaRegistry) -> Self {
+        Self {
+            value,
+            schema,
+            registry,
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Boolean(b) => visitor.visit_bool(b),
+            Value::Integer(i) => visitor.visit_i64(i),
+            Value::Int128(i) => visitor.visit_i128(i),
+            Value::Uint128(u) => visitor.visit_u128(u),
+            Value::Float(f) => visitor.visit_f32(f),
+            Value::Double(f) => visitor.visit_f64(f),
+            Value::String(s) => visitor.visit_string(s),
+            Value::Binary(data) => visitor.visit_byte_buf(data),
+            Value::Null => visitor.visit_unit(),
+            Value::Array(items) => {
+                let schema = resolve(self.schema, self.registry)?;
+                let items_schema = match &*schema {
+                    SchemaType::Array(items) | SchemaType::RunLengthArray(items) => items,
+                    _ => &UNKNOWN,
+                };
+                let mut seq = SeqAccess {
+                    items: items.into_iter(),
+                    schema: items_schema,
+                    registry: self.registry,
+                };
+                let value = visitor.visit_seq(&mut seq)?;
+                if seq.items.len() > 0 {
+                    return Err(de::Error::invalid_length(
+                        seq.items.len(),
+                        &"fewer elements",
+                    ));
+                }
+                Ok(value)
+            }
+            Value::Object(fields) => {
+                let schema = resolve(self.schema, self.registry)?;
+                let variant = match &*schema {
+                    SchemaType::OneOf { discriminator, .. } => fields
+                        .get(discriminator.as_str())
+                        .and_then(Value::as_str)
+                        .map(ToOwned::to_owned),
+                    _ => None,
+                };
+                visitor.visit_map(MapAccess {
+                    fields: fields.into_iter(),
+                    value: None,
+                    schema: &schema,
+                    variant,
+                    registry: self.registry,
+                })
+            }
+            // Formatted values read as their JSON representation
+            other => other
+                .to_json()
+                .deserialize_any(visitor)
+                .map_err(de::Error::custom),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if self.value == Value::Null {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            // `Vec<u8>` and friends deserialize from a sequence
+            Value::Binary(data) => {
+                visitor.visit_seq(SeqDeserializer::<_, Error>::new(data.into_iter()))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let schema = resolve(self.schema, self.registry)?;
+        match (self.value, &*schema) {
+            (Value::String(variant), _) => {
+                visitor.visit_enum(StringDeserializer::<Error>::new(variant))
+            }
+            (
+                Value::Object(mut fields),
+                SchemaType::OneOf {
+                    discriminator,
+                    variants,
+                },
+            ) => {
+                let Some(Value::String(variant)) = fields.shift_remove(discriminator.as_str())
+                else {
+                    return Err(SchemaError::MissingField(discriminator.clone()).into());
+                };
+                let payload = variants.get(&variant).unwrap_or(&UNKNOWN);
+                visitor.visit_enum(EnumAccess {
+                    variant,
+                    fields,
+                    payload,
+                    registry: self.registry,
+                })
+            }
+            (value, schema) => {
+                Deserializer::new(value, schema, self.registry).deserialize_any(visitor)
+            }
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct identifier
+    }
+}
+
+struct SeqAccess<'a> {
+    items: std::vec::IntoIter<Value>,
+    schema: &'a SchemaType,
+    registry: &'a SchemaRegistry,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        self.items
+            .next()
+            .map(|item| seed.deserialize(Deserializer::new(item, self.schema, self.registry)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.items.len())
+    }
+}
+
+struct MapAccess<'a> {
+    fields: indexmap::map::IntoIter<Arc<str>, Value>,
+    value: Option<(Arc<str>, Value)>,
+    schema: &'a SchemaType,
+    variant: Option<String>,
+    registry: &'a SchemaRegistry,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        let Some((key, value)) = self.fields.next() else {
+            return Ok(None);
+        };
+        let result = seed.deserialize(StringDeserializer::<Error>::new(key.to_string()));
+        self.value = Some((key, value));
+        result.map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| Error::Custom("value requested before key".to_owned()))?;
+        let schema = property_schema(self.schema, self.variant.as_deref(), &key, self.registry)?
+            .unwrap_or(Resolved::Borrowed(&UNKNOWN));
+        seed.deserialize(Deserializer::new(value, &schema, self.registry))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
+struct EnumAccess<'a> {
+    variant: String,
+    fields: IndexMap<Arc<str>, Value>,
+    payload: &'a SchemaType,
+    registry: &'a SchemaRegistry,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumAccess<'a> {
+    type Error = Error;
+    type Variant = VariantAccess<'a>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantAccess<'a>)> {
+        let variant = seed.deserialize(StringDeserializer::<Error>::new(self.variant))?;
+        let payload = Deserializer::new(Value::Object(self.fields), self.payload, self.registry);
+        Ok((variant, VariantAccess { payload }))
+    }
+}
+
+struct VariantAccess<'a> {
+    payload: Deserializer<'a>,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'_> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self.payload)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self.payload, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self.payload, visitor)
+    }
+}
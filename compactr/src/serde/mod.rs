@@ -0,0 +1,194 @@
+//! Serde integration: encode any `Serialize` type and decode any
+//! `Deserialize` type against a schema.
+//!
+//! This is the quickest way to adopt Compactr in a code base that already
+//! derives `Serialize`/`Deserialize`; no `Encode`/`Decode` implementations
+//! are needed.
+//!
+//! ```rust,ignore
+//! use compactr::serde::{from_bytes, to_bytes};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     id: Uuid,
+//!     name: String,
+//! }
+//!
+//! let bytes = to_bytes(&user, &schema)?;
+//! let user: User = from_bytes(&bytes, &schema)?;
+//! ```
+//!
+//! The data model maps onto schemas as follows:
+//! - structs and maps become objects; unknown keys and `None` fields are
+//!   left out
+//! - sequences and tuples become arrays; byte sequences may also target a
+//!   binary schema
+//! - strings are parsed according to the string format (UUID, date-time,
+//!   IP address, ...), so types serializing as strings fit formatted schemas
+//! - unit variants fit string enumerations and `oneOf` variants without
+//!   payload; newtype and struct variants, as well as internally tagged enums
+//!   (`#[serde(tag = "...")]`) using the schema's discriminator, fit `oneOf`
+//!   schemas
+//!
+//! Values go through a [`Value`] tree, so the wire format is exactly the one
+//! [`Encoder`] and [`Decoder`] produce for the same schema.
+
+mod de;
+mod ser;
+
+pub use de::Deserializer;
+pub use ser::Serializer;
+
+use crate::codec::{Decoder, Encoder};
+use crate::error::{Error, Result};
+use crate::schema::{Resolved, SchemaRegistry, SchemaType};
+use crate::value::Value;
+use ::serde::{de::DeserializeOwned, Serialize};
+use bytes::Bytes;
+use std::fmt::Display;
+use std::sync::Arc;
+
+/// Encodes `value` according to `schema`.
+///
+/// # Errors
+///
+/// Returns an error if the value doesn't fit the schema.
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T, schema: &SchemaType) -> Result<Bytes> {
+    to_bytes_with_registry(value, schema, SchemaRegistry::empty())
+}
+
+/// Encodes `value` according to `schema`, resolving references through
+/// `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the value doesn't fit the schema or a reference
+/// cannot be resolved.
+pub fn to_bytes_with_registry<T: Serialize + ?Sized>(
+    value: &T,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Bytes> {
+    let value = to_value_with_registry(value, schema, registry)?;
+    let mut encoder = Encoder::new();
+    encoder.encode_with_registry(&value, schema, registry)?;
+    Ok(encoder.finish())
+}
+
+/// Decodes a `T` from `bytes` laid out according to `schema`.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid for the schema or cannot be
+/// represented as `T`.
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8], schema: &SchemaType) -> Result<T> {
+    from_bytes_with_registry(bytes, schema, SchemaRegistry::empty())
+}
+
+/// Decodes a `T` from `bytes` laid out according to `schema`, resolving
+/// references through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid for the schema, a reference
+/// cannot be resolved, or the data cannot be represented as `T`.
+pub fn from_bytes_with_registry<T: DeserializeOwned>(
+    mut bytes: &[u8],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<T> {
+    let value = Decoder::decode_with_registry(&mut bytes, schema, registry)?;
+    from_value_with_registry(value, schema, registry)
+}
+
+/// Converts `value` into a [`Value`] matching `schema`.
+///
+/// # Errors
+///
+/// Returns an error if the value doesn't fit the schema.
+pub fn to_value<T: Serialize + ?Sized>(value: &T, schema: &SchemaType) -> Result<Value> {
+    to_value_with_registry(value, schema, SchemaRegistry::empty())
+}
+
+/// Converts `value` into a [`Value`] matching `schema`, resolving
+/// references through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the value doesn't fit the schema or a reference
+/// cannot be resolved.
+pub fn to_value_with_registry<T: Serialize + ?Sized>(
+    value: &T,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    value.serialize(Serializer::new(schema, registry))
+}
+
+/// Converts a [`Value`] laid out according to `schema` into a `T`.
+///
+/// # Errors
+///
+/// Returns an error if the value cannot be represented as `T`.
+pub fn from_value<T: DeserializeOwned>(value: Value, schema: &SchemaType) -> Result<T> {
+    from_value_with_registry(value, schema, SchemaRegistry::empty())
+}
+
+/// Converts a [`Value`] laid out according to `schema` into a `T`,
+/// resolving references through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if a reference cannot be resolved or the value cannot
+/// be represented as `T`.
+pub fn from_value_with_registry<T: DeserializeOwned>(
+    value: Value,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<T> {
+    T::deserialize(Deserializer::new(value, schema, registry))
+}
+
+impl ::serde::ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl ::serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+/// Resolves `schema` if it is a reference.
+fn resolve<'s>(schema: &'s SchemaType, registry: &SchemaRegistry) -> Result<Resolved<'s>> {
+    registry.resolve(schema)
+}
+
+/// Returns the schema of property `key` of an object schema, or of the
+/// payload of `variant` for a `oneOf` schema.
+///
+/// Returns `None` for properties the schema doesn't know about.
+fn property_schema<'s>(
+    schema: &'s SchemaType,
+    variant: Option<&str>,
+    key: &str,
+    registry: &SchemaRegistry,
+) -> Result<Option<Resolved<'s>>> {
+    match schema {
+        SchemaType::Object(properties) => Ok(properties
+            .get(key)
+            .map(|p| Resolved::Borrowed(&p.schema_type))),
+        SchemaType::OneOf { variants, .. } => match variant.and_then(|v| variants.get(v)) {
+            Some(payload) => property_schema(payload, None, key, registry),
+            None => Ok(None),
+        },
+        SchemaType::Reference(ref_name) => {
+            let resolved = registry.resolve_shared(ref_name)?;
+            let schema = property_schema(&resolved, variant, key, registry)?;
+            Ok(schema.map(|s| Resolved::Shared(Arc::new(SchemaType::clone(&s)))))
+        }
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,521 @@
+//! `Serializer` building a [`Value`] that matches a schema.
+
+use super::{property_schema, resolve};
+use crate::codec::{check_integer_precision, intern, narrow_float, type_mismatch};
+use crate::error::{EncodeError, Error, Result, SchemaError};
+#[cfg(feature = "decimal")]
+use crate::formats::decimal;
+use crate::schema::{
+    IntegerFormat, NumberFormat, Resolved, SchemaRegistry, SchemaType, StringFormat,
+};
+use crate::value::json::string_from_json;
+use crate::value::Value;
+use ::serde::ser::{self, Impossible, Serialize};
+use indexmap::IndexMap;
+use std::sync::Arc;
+
+/// Serializer producing a [`Value`] laid out according to a schema.
+///
+/// Strings are parsed into the schema's string format and numbers are
+/// converted to the schema's integer or number format, so the resulting
+/// value can be encoded with the same schema.
+pub struct Serializer<'a> {
+    schema: &'a SchemaType,
+    registry: &'a SchemaRegistry,
+}
+
+impl<'a> Serializer<'a> {
+    /// Creates a serializer for values of `schema`.
+    #[must_use]
+    pub fn new(schema: &'a SchemaType, registry: &'a SchemaRegistry) -> Self {
+        Self { schema, registry }
+    }
+
+    fn integer(self, n: i128) -> Result<Value> {
+        let out_of_range = || EncodeError::InvalidFormat(format!("Integer out of range: {n}"));
+        let schema = resolve(self.schema, self.registry)?;
+        #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+        Ok(match &*schema {
+            SchemaType::Integer(IntegerFormat::Int128) => Value::Int128(n),
+            SchemaType::Integer(IntegerFormat::Uint128) => {
+                Value::Uint128(u128::try_from(n).map_err(|_| out_of_range())?)
+            }
+            SchemaType::Integer(_) => Value::Integer(i64::try_from(n).map_err(|_| out_of_range())?),
+            SchemaType::Number(NumberFormat::Float) => {
+                check_integer_precision(n, f32::MANTISSA_DIGITS, self.registry)?;
+                Value::Float(n as f32)
+            }
+            SchemaType::Number(NumberFormat::Double) => {
+                check_integer_precision(n, f64::MANTISSA_DIGITS, self.registry)?;
+                Value::Double(n as f64)
+            }
+            #[cfg(feature = "decimal")]
+            SchemaType::Number(NumberFormat::Decimal) => {
+                Value::Decimal(decimal::parse_decimal(&n.to_string())?)
+            }
+            schema => return Err(mismatch(schema, "integer")),
+        })
+    }
+
+    fn number(self, f: f64) -> Result<Value> {
+        let schema = resolve(self.schema, self.registry)?;
+        Ok(match &*schema {
+            SchemaType::Number(NumberFormat::Float) => {
+                Value::Float(narrow_float(f, self.registry)?)
+            }
+            SchemaType::Number(NumberFormat::Double) => Value::Double(f),
+            #[cfg(feature = "decimal")]
+            SchemaType::Number(NumberFormat::Decimal) => {
+                Value::Decimal(decimal::parse_decimal(&f.to_string())?)
+            }
+            schema => return Err(mismatch(schema, "number")),
+        })
+    }
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = SerializeArray<'a>;
+    type SerializeTuple = SerializeArray<'a>;
+    type SerializeTupleStruct = SerializeArray<'a>;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = SerializeObject<'a>;
+    type SerializeStruct = SerializeObject<'a>;
+    type SerializeStructVariant = SerializeObject<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value> {
+        match &*resolve(self.schema, self.registry)? {
+            SchemaType::Boolean => Ok(Value::Boolean(v)),
+            schema => Err(mismatch(schema, "boolean")),
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value> {
+        self.integer(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value> {
+        self.integer(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value> {
+        self.integer(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value> {
+        self.integer(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Value> {
+        self.integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value> {
+        self.integer(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value> {
+        self.integer(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value> {
+        self.integer(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value> {
+        self.integer(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Value> {
+        if matches!(
+            &*resolve(self.schema, self.registry)?,
+            SchemaType::Integer(IntegerFormat::Uint128)
+        ) {
+            return Ok(Value::Uint128(v));
+        }
+        let n = i128::try_from(v)
+            .map_err(|_| EncodeError::InvalidFormat(format!("Integer out of range: {v}")))?;
+        self.integer(n)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value> {
+        self.number(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value> {
+        self.number(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value> {
+        match &*resolve(self.schema, self.registry)? {
+            SchemaType::String(format) => string_from_json(v, *format, self.registry),
+            SchemaType::Enum(variants) if variants.iter().any(|name| name == v) => {
+                Ok(Value::String(v.to_owned()))
+            }
+            // Variants without payload may be given by name alone
+            SchemaType::OneOf { variants, .. }
+                if variants.get(v).is_some_and(|p| *p == SchemaType::Null) =>
+            {
+                Ok(Value::String(v.to_owned()))
+            }
+            SchemaType::Enum(_) | SchemaType::OneOf { .. } => {
+                Err(EncodeError::InvalidFormat(format!("Unknown variant: {v}")).into())
+            }
+            schema => Err(mismatch(schema, "string")),
+        }
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value> {
+        match &*resolve(self.schema, self.registry)? {
+            SchemaType::String(StringFormat::Binary) => Ok(Value::Binary(v.to_vec())),
+            SchemaType::Array(items) | SchemaType::RunLengthArray(items) => v
+                .iter()
+                .map(|b| Serializer::new(items, self.registry).serialize_u8(*b))
+                .collect::<Result<_>>()
+                .map(Value::Array),
+            schema => Err(mismatch(schema, "bytes")),
+        }
+    }
+
+    fn serialize_none(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value> {
+        let schema = resolve(self.schema, self.registry)?;
+        let SchemaType::OneOf {
+            discriminator,
+            variants,
+        } = &*schema
+        else {
+            return Err(mismatch(&schema, "enum variant"));
+        };
+        let payload = variants
+            .get(variant)
+            .ok_or_else(|| EncodeError::InvalidFormat(format!("Unknown variant: {variant}")))?;
+
+        let mut obj = IndexMap::new();
+        obj.insert(intern(discriminator), Value::String(variant.to_owned()));
+        match value.serialize(Serializer::new(payload, self.registry))? {
+            Value::Object(fields) => obj.extend(fields),
+            Value::Null => {}
+            other => return Err(type_mismatch("object", &other)),
+        }
+        Ok(Value::Object(obj))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray<'a>> {
+        let schema = resolve(self.schema, self.registry)?;
+        match &*schema {
+            SchemaType::Array(_)
+            | SchemaType::RunLengthArray(_)
+            | SchemaType::String(StringFormat::Binary) => {}
+            schema => return Err(mismatch(schema, "sequence")),
+        }
+        Ok(SerializeArray {
+            schema,
+            registry: self.registry,
+            items: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SerializeArray<'a>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(SchemaError::InvalidSchema(format!(
+            "tuple variant {variant} has no schema representation; use a struct variant"
+        ))
+        .into())
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeObject<'a>> {
+        let schema = resolve(self.schema, self.registry)?;
+        match &*schema {
+            SchemaType::Object(_) | SchemaType::OneOf { .. } => {}
+            schema => return Err(mismatch(schema, "map")),
+        }
+        Ok(SerializeObject {
+            schema,
+            registry: self.registry,
+            variant: None,
+            fields: IndexMap::with_capacity(len.unwrap_or_default()),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeObject<'a>> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeObject<'a>> {
+        let mut object = self.serialize_map(Some(len + 1))?;
+        let SchemaType::OneOf { discriminator, .. } = &*object.schema else {
+            return Err(mismatch(&object.schema, "enum variant"));
+        };
+        let discriminator = discriminator.clone();
+        object.select_variant(&discriminator, variant.to_owned())?;
+        Ok(object)
+    }
+}
+
+/// Serializes sequences into [`Value::Array`], or [`Value::Binary`] for
+/// binary schemas.
+pub struct SerializeArray<'a> {
+    schema: Resolved<'a>,
+    registry: &'a SchemaRegistry,
+    items: Vec<Value>,
+}
+
+impl ser::SerializeSeq for SerializeArray<'_> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let byte = SchemaType::uint8();
+        let items = match &*self.schema {
+            SchemaType::Array(items) | SchemaType::RunLengthArray(items) => items,
+            _ => &byte,
+        };
+        self.items
+            .push(value.serialize(Serializer::new(items, self.registry))?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value> {
+        if !matches!(&*self.schema, SchemaType::String(StringFormat::Binary)) {
+            return Ok(Value::Array(self.items));
+        }
+        self.items
+            .into_iter()
+            .map(|item| match item {
+                Value::Integer(b) => u8::try_from(b)
+                    .map_err(|_| EncodeError::InvalidFormat(format!("Invalid byte: {b}")).into()),
+                other => Err(type_mismatch("byte", &other)),
+            })
+            .collect::<Result<_>>()
+            .map(Value::Binary)
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray<'_> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray<'_> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// Serializes maps and structs into [`Value::Object`].
+///
+/// With a `oneOf` schema the discriminator selects the variant whose
+/// payload properties the following fields are matched against, so it must
+/// come first, as it does for internally tagged enums.
+pub struct SerializeObject<'a> {
+    schema: Resolved<'a>,
+    registry: &'a SchemaRegistry,
+    variant: Option<String>,
+    fields: IndexMap<Arc<str>, Value>,
+    key: Option<String>,
+}
+
+impl SerializeObject<'_> {
+    fn select_variant(&mut self, discriminator: &str, variant: String) -> Result<()> {
+        if let SchemaType::OneOf { variants, .. } = &*self.schema {
+            if !variants.contains_key(&variant) {
+                return Err(
+                    EncodeError::InvalidFormat(format!("Unknown variant: {variant}")).into(),
+                );
+            }
+        }
+        self.fields
+            .insert(intern(discriminator), Value::String(variant.clone()));
+        self.variant = Some(variant);
+        Ok(())
+    }
+
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        if let SchemaType::OneOf { discriminator, .. } = &*self.schema {
+            if key == discriminator {
+                let Value::String(variant) =
+                    value.serialize(Serializer::new(&SchemaType::string(), self.registry))?
+                else {
+                    unreachable!("plain string schemas serialize to strings");
+                };
+                return self.select_variant(key, variant);
+            }
+            if self.variant.is_none() {
+                return Err(SchemaError::InvalidSchema(format!(
+                    "discriminator {discriminator} must come before field {key}"
+                ))
+                .into());
+            }
+        }
+
+        // Properties the schema doesn't know about are dropped
+        let Some(schema) =
+            property_schema(&self.schema, self.variant.as_deref(), key, self.registry)?
+        else {
+            return Ok(());
+        };
+        let value = value.serialize(Serializer::new(&schema, self.registry))?;
+        // `None` means "absent" unless the property is explicitly a null type
+        if value != Value::Null || *schema == SchemaType::Null {
+            self.fields.insert(intern(key), value);
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value> {
+        if let SchemaType::OneOf { discriminator, .. } = &*self.schema {
+            if self.variant.is_none() {
+                return Err(SchemaError::MissingField(discriminator.clone()).into());
+            }
+        }
+        Ok(Value::Object(self.fields))
+    }
+}
+
+impl ser::SerializeMap for SerializeObject<'_> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(Serializer::new(&SchemaType::string(), self.registry)) {
+            Ok(Value::String(key)) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => Err(SchemaError::InvalidSchema("map keys must be strings".to_owned()).into()),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| SchemaError::InvalidSchema("map value without a key".to_owned()))?;
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeObject<'_> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeObject<'_> {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<Value> {
+        self.finish()
+    }
+}
+
+fn mismatch(schema: &SchemaType, actual: &str) -> Error {
+    EncodeError::TypeMismatch {
+        expected: schema.to_string(),
+        actual: actual.to_owned(),
+    }
+    .into()
+}
@@ -0,0 +1,331 @@
+//! [Proptest](https://docs.rs/proptest) strategies for schemas and values.
+//!
+//! [`schema_and_value`] generates a random schema with a value that encodes
+//! with it, so round-trip properties of code built on compactr take a few
+//! lines:
+//!
+//! ```rust
+//! use compactr::strategy::schema_and_value;
+//! use compactr::{Decoder, Encoder};
+//! use proptest::prelude::*;
+//!
+//! proptest!(|((schema, value) in schema_and_value())| {
+//!     let mut encoder = Encoder::new();
+//!     encoder.encode(&value, &schema).unwrap();
+//!     let bytes = encoder.finish();
+//!     prop_assert_eq!(Decoder::decode(&mut bytes.as_ref(), &schema).unwrap(), value);
+//! });
+//! ```
+//!
+//! Use [`value`] for values of a fixed schema, such as that of a derived
+//! type. Values shrink towards small numbers, short strings and arrays, and
+//! absent optional properties.
+
+#[cfg(not(feature = "chrono"))]
+use crate::formats::datetime;
+use crate::formats::geo::GeoPoint;
+#[cfg(feature = "chrono")]
+use crate::schema::TimestampPrecision;
+use crate::schema::{
+    IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType, SizeWidth, StringFormat,
+};
+use crate::value::Value;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset, NaiveTime};
+use indexmap::IndexMap;
+use proptest::collection::{btree_set, vec};
+use proptest::prelude::*;
+use proptest::sample::select;
+use proptest::strategy::Union;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+/// Names of generated properties and variants.
+const NAME: &str = "[a-z][a-z0-9_]{0,7}";
+
+/// Range of generated instants, from 2000-01-01 to 2100-01-01 in
+/// milliseconds since the Unix epoch.
+const INSTANTS: std::ops::Range<i64> = 946_684_800_000..4_102_444_800_000;
+
+/// Generates schemas without references or custom formats, nested up to
+/// three levels deep, that have values.
+pub fn schema() -> impl Strategy<Value = SchemaType> {
+    let leaf = prop_oneof![
+        Just(SchemaType::Boolean),
+        select(IntegerFormat::ALL).prop_map(SchemaType::Integer),
+        select(NumberFormat::ALL).prop_map(SchemaType::Number),
+        select(StringFormat::BUILTIN).prop_map(SchemaType::String),
+        btree_set(NAME, 1..4).prop_map(|names| SchemaType::Enum(names.into_iter().collect())),
+        Just(SchemaType::Null),
+    ];
+    leaf.prop_recursive(3, 32, 4, |inner| {
+        let properties = vec(
+            (NAME, inner.clone(), any::<bool>(), select(SizeWidth::ALL)),
+            0..5,
+        )
+        .prop_map(|properties| {
+            properties
+                .into_iter()
+                .map(|(name, schema, required, size_width)| {
+                    let mut property = Property::optional(schema).with_size_width(size_width);
+                    property.required = required;
+                    (name, property)
+                })
+                .collect::<IndexMap<_, _>>()
+        });
+        prop_oneof![
+            inner.clone().prop_map(SchemaType::array),
+            inner.prop_map(SchemaType::run_length_array),
+            properties.clone().prop_map(SchemaType::Object),
+            (NAME, vec((NAME, proptest::option::of(properties)), 1..4)).prop_map(
+                |(discriminator, variants)| {
+                    let variants = variants
+                        .into_iter()
+                        .map(|(name, payload)| {
+                            let payload = payload.map_or(SchemaType::Null, |mut properties| {
+                                properties.shift_remove(&discriminator);
+                                SchemaType::Object(properties)
+                            });
+                            (name, payload)
+                        })
+                        .collect();
+                    SchemaType::one_of(discriminator, variants)
+                }
+            ),
+        ]
+    })
+    // Required properties may not fit their size headers
+    .prop_filter("schema without values", |schema| {
+        crate::mock::generate(schema, 0).is_ok()
+    })
+}
+
+/// Generates values that encode with `schema`.
+///
+/// Property values fit the sizes of the default
+/// [length mode](crate::LengthMode::Fixed).
+///
+/// # Panics
+///
+/// Panics if the schema has a reference, a custom format or an enum
+/// without variants.
+pub fn value(schema: &SchemaType) -> BoxedStrategy<Value> {
+    match schema {
+        SchemaType::Boolean => any::<bool>().prop_map(Value::Boolean).boxed(),
+        SchemaType::Integer(format) => integer(*format),
+        SchemaType::Number(format) => number(*format),
+        SchemaType::String(format) => string(*format),
+        SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+            vec(value(items), 0..4).prop_map(Value::Array).boxed()
+        }
+        SchemaType::Object(properties) => object(properties),
+        SchemaType::Reference(name) => panic!("cannot generate values of reference {name}"),
+        SchemaType::Enum(variants) => select(variants.clone()).prop_map(Value::String).boxed(),
+        SchemaType::OneOf {
+            discriminator,
+            variants,
+        } => {
+            let variants = variants.iter().map(|(name, payload)| {
+                let tag = (
+                    Arc::<str>::from(discriminator.as_str()),
+                    Value::String(name.clone()),
+                );
+                value(payload).prop_map(move |payload| {
+                    let mut obj = IndexMap::from([tag.clone()]);
+                    if let Value::Object(fields) = payload {
+                        obj.extend(fields);
+                    }
+                    Value::Object(obj)
+                })
+            });
+            Union::new(variants).boxed()
+        }
+        SchemaType::Null => Just(Value::Null).boxed(),
+    }
+}
+
+/// Generates a schema from [`schema`] with a value from [`value`].
+pub fn schema_and_value() -> impl Strategy<Value = (SchemaType, Value)> {
+    schema().prop_flat_map(|schema| {
+        let value = value(&schema);
+        (Just(schema), value)
+    })
+}
+
+fn object(properties: &IndexMap<String, Property>) -> BoxedStrategy<Value> {
+    let registry = SchemaRegistry::new();
+    let fields: Vec<_> = properties
+        .iter()
+        .map(|(name, property)| {
+            let name: Arc<str> = name.as_str().into();
+            let fitting = property.clone();
+            let registry = registry.clone();
+            let value = value(&property.schema_type)
+                .prop_filter("value larger than its size header", move |value| {
+                    crate::mock::fits(value, &fitting, &registry).unwrap_or(false)
+                });
+            if property.required {
+                value
+                    .prop_map(move |value| Some((name.clone(), value)))
+                    .boxed()
+            } else {
+                proptest::option::of(value)
+                    .prop_map(move |value| value.map(|value| (name.clone(), value)))
+                    .boxed()
+            }
+        })
+        .collect();
+    fields
+        .prop_map(|fields| Value::Object(fields.into_iter().flatten().collect()))
+        .boxed()
+}
+
+fn integer(format: IntegerFormat) -> BoxedStrategy<Value> {
+    match format {
+        IntegerFormat::Int8 => any::<i8>().prop_map(|i| Value::Integer(i.into())).boxed(),
+        IntegerFormat::Int16 => any::<i16>().prop_map(|i| Value::Integer(i.into())).boxed(),
+        IntegerFormat::Int32 => any::<i32>().prop_map(|i| Value::Integer(i.into())).boxed(),
+        // Stored as a double, which is exact up to 2^53
+        IntegerFormat::Int64 => (-(1_i64 << 53)..=1 << 53).prop_map(Value::Integer).boxed(),
+        IntegerFormat::Uint8 => any::<u8>().prop_map(|i| Value::Integer(i.into())).boxed(),
+        IntegerFormat::Uint16 => any::<u16>().prop_map(|i| Value::Integer(i.into())).boxed(),
+        IntegerFormat::Int128 => any::<i128>().prop_map(Value::Int128).boxed(),
+        IntegerFormat::Uint128 => any::<u128>().prop_map(Value::Uint128).boxed(),
+    }
+}
+
+fn number(format: NumberFormat) -> BoxedStrategy<Value> {
+    match format {
+        NumberFormat::Float => (-1e6_f32..1e6).prop_map(Value::Float).boxed(),
+        NumberFormat::Double => (-1e12_f64..1e12).prop_map(Value::Double).boxed(),
+        #[cfg(feature = "decimal")]
+        NumberFormat::Decimal => (any::<i64>(), 0_u32..=28)
+            .prop_map(|(mantissa, scale)| {
+                Value::Decimal(rust_decimal::Decimal::new(mantissa, scale))
+            })
+            .boxed(),
+    }
+}
+
+fn string(format: StringFormat) -> BoxedStrategy<Value> {
+    #[cfg(feature = "chrono")]
+    let instant = || INSTANTS.prop_map(|millis| DateTime::from_timestamp_millis(millis).unwrap());
+    match format {
+        StringFormat::Plain | StringFormat::Long => "\\PC{0,8}".prop_map(Value::String).boxed(),
+        #[cfg(feature = "uuid")]
+        StringFormat::Uuid => any::<u128>()
+            .prop_map(|bits| Value::Uuid(Uuid::from_u128(bits)))
+            .boxed(),
+        #[cfg(not(feature = "uuid"))]
+        StringFormat::Uuid => any::<u128>()
+            .prop_map(|bits| Value::Binary(bits.to_be_bytes().to_vec()))
+            .boxed(),
+        #[cfg(not(feature = "chrono"))]
+        StringFormat::DateTime
+        | StringFormat::DateTimeTz
+        | StringFormat::Timestamp(_)
+        | StringFormat::Date
+        | StringFormat::Time => raw_temporal(format),
+        #[cfg(feature = "chrono")]
+        StringFormat::DateTimeTz => (instant(), -1439..=1439)
+            .prop_map(|(instant, minutes)| {
+                let offset = FixedOffset::east_opt(minutes * 60).unwrap();
+                Value::DateTimeTz(instant.with_timezone(&offset))
+            })
+            .boxed(),
+        #[cfg(feature = "chrono")]
+        StringFormat::Timestamp(TimestampPrecision::Seconds) => instant()
+            .prop_map(|instant| {
+                Value::DateTime(DateTime::from_timestamp(instant.timestamp(), 0).unwrap())
+            })
+            .boxed(),
+        #[cfg(feature = "chrono")]
+        StringFormat::DateTime | StringFormat::Timestamp(_) => {
+            instant().prop_map(Value::DateTime).boxed()
+        }
+        #[cfg(feature = "chrono")]
+        StringFormat::Date => instant()
+            .prop_map(|instant| Value::Date(instant.date_naive()))
+            .boxed(),
+        #[cfg(feature = "chrono")]
+        StringFormat::Time => (0_u32..86_400_000)
+            .prop_map(|millis| {
+                let time = NaiveTime::from_num_seconds_from_midnight_opt(
+                    millis / 1000,
+                    millis % 1000 * 1_000_000,
+                );
+                Value::Time(time.unwrap())
+            })
+            .boxed(),
+        StringFormat::Uri => "[a-z0-9/]{0,16}"
+            .prop_map(|path| Value::String(format!("https://example.com/{path}")))
+            .boxed(),
+        StringFormat::SocketAddr => (ip(), any::<u16>())
+            .prop_map(|(ip, port)| Value::SocketAddr(SocketAddr::new(ip, port)))
+            .boxed(),
+        // Multiples of the 1e-7 degree resolution the points are stored with
+        StringFormat::GeoPoint => (-900_000_000..=900_000_000, -1_800_000_000..=1_800_000_000)
+            .prop_map(|(lat, lon)| {
+                let point = GeoPoint::new(f64::from(lat) / 1e7, f64::from(lon) / 1e7);
+                Value::GeoPoint(point.unwrap())
+            })
+            .boxed(),
+        StringFormat::Ipv4 => any::<u32>()
+            .prop_map(|bits| Value::Ipv4(Ipv4Addr::from(bits)))
+            .boxed(),
+        StringFormat::Ipv6 => any::<u128>()
+            .prop_map(|bits| Value::Ipv6(Ipv6Addr::from(bits)))
+            .boxed(),
+        StringFormat::Binary => vec(any::<u8>(), 0..16).prop_map(Value::Binary).boxed(),
+        StringFormat::Custom(name) => panic!("cannot generate values of custom format {name}"),
+    }
+}
+
+/// Generates the integers and bytes the date and time formats decode to
+/// without the `chrono` feature.
+#[cfg(not(feature = "chrono"))]
+fn raw_temporal(format: StringFormat) -> BoxedStrategy<Value> {
+    match format {
+        StringFormat::DateTimeTz => (INSTANTS, -1439_i16..=1439)
+            .prop_map(|(millis, minutes)| {
+                let mut buf = bytes::BytesMut::new();
+                datetime::encode_datetime_millis(&mut buf, millis).unwrap();
+                buf.extend_from_slice(&minutes.to_be_bytes());
+                Value::Binary(buf.to_vec())
+            })
+            .boxed(),
+        StringFormat::Date => INSTANTS
+            .prop_map(|millis| Value::Integer(millis.div_euclid(datetime::MILLIS_PER_DAY)))
+            .boxed(),
+        StringFormat::Time => (0..datetime::MILLIS_PER_DAY)
+            .prop_map(Value::Integer)
+            .boxed(),
+        _ => INSTANTS.prop_map(Value::Integer).boxed(),
+    }
+}
+
+fn ip() -> impl Strategy<Value = IpAddr> {
+    prop_oneof![
+        any::<u32>().prop_map(|bits| IpAddr::V4(bits.into())),
+        any::<u128>().prop_map(|bits| IpAddr::V6(bits.into())),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Decoder, Encoder};
+
+    proptest! {
+        #[test]
+        fn test_schema_and_value((schema, value) in schema_and_value()) {
+            let mut encoder = Encoder::new();
+            encoder.encode(&value, &schema).unwrap();
+            let bytes = encoder.finish();
+            let decoded = Decoder::decode(&mut bytes.as_ref(), &schema).unwrap();
+            prop_assert_eq!(decoded, value);
+        }
+    }
+}
@@ -0,0 +1,435 @@
+//! Canonical wire-format test vectors.
+//!
+//! [`vectors`] returns a fixed set of `(schema, value, expected bytes)`
+//! triples covering every type and format, so other implementations
+//! (compactr.js, the WASM and Python bindings) can be cross-checked in code
+//! rather than through ad-hoc fixtures. [`to_json`] exports them with
+//! `OpenAPI` schemas, JSON values and hex bytes, and [`TestVector::verify`]
+//! or [`check`] compare another implementation's output against them:
+//!
+//! ```
+//! use compactr::testvectors;
+//!
+//! let failures = testvectors::check(|vector| {
+//!     let mut encoder = compactr::Encoder::new();
+//!     encoder.encode(&vector.value, &vector.schema)?;
+//!     Ok(encoder.finish().to_vec())
+//! });
+//! assert!(failures.is_empty(), "{failures:?}");
+//! ```
+//!
+//! Vectors use the default [`ArrayFormat`](crate::ArrayFormat) and no
+//! schema registry.
+
+use crate::error::Result;
+use crate::schema::{SchemaType, StringFormat};
+use crate::value::Value;
+use serde_json::json;
+use std::fmt::{self, Write as _};
+
+/// A schema, a value and the bytes it must encode to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestVector {
+    /// Unique name of the vector.
+    pub name: &'static str,
+    /// Schema the value is encoded with.
+    pub schema: SchemaType,
+    /// Value to encode.
+    pub value: Value,
+    /// Expected encoding.
+    pub bytes: Vec<u8>,
+}
+
+/// A difference between a test vector and an implementation's output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Name of the vector.
+    pub name: &'static str,
+    /// What went wrong.
+    pub reason: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.reason)
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+impl TestVector {
+    /// Compares `actual` against the expected bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`Mismatch`] locating the first differing byte.
+    pub fn verify(&self, actual: &[u8]) -> std::result::Result<(), Mismatch> {
+        if actual == self.bytes {
+            return Ok(());
+        }
+        let offset = actual
+            .iter()
+            .zip(&self.bytes)
+            .position(|(a, b)| a != b)
+            .unwrap_or_else(|| actual.len().min(self.bytes.len()));
+        Err(Mismatch {
+            name: self.name,
+            reason: format!(
+                "first difference at byte {offset}: expected {}, got {}",
+                hex(&self.bytes),
+                hex(actual)
+            ),
+        })
+    }
+
+    /// Returns the vector as JSON: its name, `OpenAPI` schema, JSON value
+    /// and expected bytes in lowercase hex.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "name": self.name,
+            "schema": self.schema.to_json(),
+            "value": self.value.to_json(),
+            "hex": hex(&self.bytes),
+        })
+    }
+}
+
+/// Runs `encode` on every vector and returns the vectors it got wrong.
+///
+/// Errors returned by `encode` are reported as mismatches too.
+pub fn check(mut encode: impl FnMut(&TestVector) -> Result<Vec<u8>>) -> Vec<Mismatch> {
+    vectors()
+        .iter()
+        .filter_map(|vector| match encode(vector) {
+            Ok(actual) => vector.verify(&actual).err(),
+            Err(e) => Some(Mismatch {
+                name: vector.name,
+                reason: format!("encoding failed: {e}"),
+            }),
+        })
+        .collect()
+}
+
+/// Returns all vectors as a JSON array, see [`TestVector::to_json`].
+#[must_use]
+pub fn to_json() -> serde_json::Value {
+    vectors().iter().map(TestVector::to_json).collect()
+}
+
+/// Returns the canonical test vectors.
+///
+/// The vectors of the UUID and date and time formats are left out without
+/// the `uuid` and `chrono` features, whose values they are written in.
+///
+/// # Panics
+///
+/// Panics if a built-in vector is malformed, which the crate's own tests
+/// rule out.
+#[must_use]
+pub fn vectors() -> Vec<TestVector> {
+    VECTORS
+        .iter()
+        .filter_map(|(name, schema, value, bytes)| {
+            let schema = SchemaType::from_json(&parse(schema)).expect("valid vector schema");
+            if !has_rich_values(&schema) {
+                return None;
+            }
+            let value = Value::from_json(&parse(value), &schema).expect("valid vector value");
+            Some(TestVector {
+                name,
+                schema,
+                value,
+                bytes: unhex(bytes),
+            })
+        })
+        .collect()
+}
+
+/// Whether values of `schema` decode to their own [`Value`] variant rather
+/// than to the integers and bytes of a disabled feature.
+const fn has_rich_values(schema: &SchemaType) -> bool {
+    match schema {
+        SchemaType::String(StringFormat::Uuid) => cfg!(feature = "uuid"),
+        SchemaType::String(
+            StringFormat::DateTime
+            | StringFormat::DateTimeTz
+            | StringFormat::Timestamp(_)
+            | StringFormat::Date
+            | StringFormat::Time,
+        ) => cfg!(feature = "chrono"),
+        _ => true,
+    }
+}
+
+/// `(name, schema, value, hex)`, with the schema and value as JSON text.
+const VECTORS: &[(&str, &str, &str, &str)] = &[
+    ("boolean_true", r#"{"type":"boolean"}"#, "true", "01"),
+    ("boolean_false", r#"{"type":"boolean"}"#, "false", "00"),
+    ("int8", r#"{"type":"integer","format":"int8"}"#, "-2", "fe"),
+    (
+        "uint8",
+        r#"{"type":"integer","format":"uint8"}"#,
+        "200",
+        "c8",
+    ),
+    (
+        "int16",
+        r#"{"type":"integer","format":"int16"}"#,
+        "-300",
+        "fed4",
+    ),
+    (
+        "uint16",
+        r#"{"type":"integer","format":"uint16"}"#,
+        "60000",
+        "ea60",
+    ),
+    (
+        "int32",
+        r#"{"type":"integer","format":"int32"}"#,
+        "42",
+        "0000002a",
+    ),
+    (
+        "int32_negative",
+        r#"{"type":"integer","format":"int32"}"#,
+        "-1",
+        "ffffffff",
+    ),
+    (
+        "int64",
+        r#"{"type":"integer","format":"int64"}"#,
+        "9007199254740991",
+        "433fffffffffffff",
+    ),
+    (
+        "int128",
+        r#"{"type":"integer","format":"int128"}"#,
+        r#""-170141183460469231731687303715884105728""#,
+        "80000000000000000000000000000000",
+    ),
+    (
+        "uint128",
+        r#"{"type":"integer","format":"uint128"}"#,
+        r#""340282366920938463463374607431768211455""#,
+        "ffffffffffffffffffffffffffffffff",
+    ),
+    (
+        "float",
+        r#"{"type":"number","format":"float"}"#,
+        "1.5",
+        "3fc00000",
+    ),
+    (
+        "double",
+        r#"{"type":"number","format":"double"}"#,
+        "3.141592653589793",
+        "400921fb54442d18",
+    ),
+    (
+        "string",
+        r#"{"type":"string"}"#,
+        r#""Hello""#,
+        "000548656c6c6f",
+    ),
+    ("string_empty", r#"{"type":"string"}"#, r#""""#, "0000"),
+    (
+        "string_utf8",
+        r#"{"type":"string"}"#,
+        r#""héllo ✓""#,
+        "000a68c3a96c6c6f20e29c93",
+    ),
+    (
+        "uuid",
+        r#"{"type":"string","format":"uuid"}"#,
+        r#""550e8400-e29b-41d4-a716-446655440000""#,
+        "550e8400e29b41d4a716446655440000",
+    ),
+    (
+        "datetime",
+        r#"{"type":"string","format":"date-time"}"#,
+        r#""2024-01-02T03:04:05.678Z""#,
+        "07e8010203040502a6",
+    ),
+    (
+        "datetime_tz",
+        r#"{"type":"string","format":"date-time-tz"}"#,
+        r#""2024-01-02T03:04:05+02:00""#,
+        "07e8010201040500000078",
+    ),
+    (
+        "timestamp_seconds",
+        r#"{"type":"string","format":"timestamp-seconds"}"#,
+        r#""2024-01-02T03:04:05Z""#,
+        "0000000065937d25",
+    ),
+    (
+        "timestamp_micros",
+        r#"{"type":"string","format":"timestamp-micros"}"#,
+        r#""2024-01-02T03:04:05.123456Z""#,
+        "00060dedc04fb580",
+    ),
+    (
+        "date",
+        r#"{"type":"string","format":"date"}"#,
+        r#""2024-02-29""#,
+        "00004d46",
+    ),
+    (
+        "time",
+        r#"{"type":"string","format":"time"}"#,
+        r#""13:45:30.250""#,
+        "02f3c58a",
+    ),
+    (
+        "uri",
+        r#"{"type":"string","format":"uri"}"#,
+        r#""https://example.com/a""#,
+        "01000d6578616d706c652e636f6d2f61",
+    ),
+    (
+        "ipv4",
+        r#"{"type":"string","format":"ipv4"}"#,
+        r#""192.168.1.1""#,
+        "c0a80101",
+    ),
+    (
+        "ipv6",
+        r#"{"type":"string","format":"ipv6"}"#,
+        r#""2001:db8::1""#,
+        "20010db8000000000000000000000001",
+    ),
+    (
+        "socket_addr",
+        r#"{"type":"string","format":"socket-addr"}"#,
+        r#""10.0.0.1:8080""#,
+        "040a0000011f90",
+    ),
+    (
+        "geopoint",
+        r#"{"type":"string","format":"geopoint"}"#,
+        r#"{"lat":48.8566,"lon":2.3522}"#,
+        "1d1eecf00166ead0",
+    ),
+    (
+        "binary",
+        r#"{"type":"string","format":"binary"}"#,
+        r#""AQID""#,
+        "00000003010203",
+    ),
+    (
+        "enum",
+        r#"{"type":"string","enum":["red","green","blue"]}"#,
+        r#""green""#,
+        "01",
+    ),
+    ("null", r#"{"type":"null"}"#, "null", "00"),
+    (
+        "array_int32",
+        r#"{"type":"array","items":{"type":"integer","format":"int32"}}"#,
+        "[1,2,3]",
+        "040000000104000000020400000003",
+    ),
+    (
+        "array_string",
+        r#"{"type":"array","items":{"type":"string"}}"#,
+        r#"["a","bc"]"#,
+        "030001610400026263",
+    ),
+    (
+        "array_empty",
+        r#"{"type":"array","items":{"type":"boolean"}}"#,
+        "[]",
+        "",
+    ),
+    (
+        "object",
+        r#"{"type":"object","required":["id","name"],"properties":{"id":{"type":"integer","format":"int32"},"name":{"type":"string"},"active":{"type":"boolean"}}}"#,
+        r#"{"id":7,"name":"Alice","active":true}"#,
+        "030001010104000000070205416c696365",
+    ),
+    (
+        "object_optional_absent",
+        r#"{"type":"object","required":["id"],"properties":{"id":{"type":"integer","format":"int32"},"email":{"type":"string"}}}"#,
+        r#"{"id":1}"#,
+        "01010400000001",
+    ),
+    (
+        "object_nested",
+        r#"{"type":"object","properties":{"owner":{"type":"object","properties":{"name":{"type":"string"}}},"tags":{"type":"array","items":{"type":"string"}}}}"#,
+        r#"{"owner":{"name":"Bob"},"tags":["x"]}"#,
+        "02000006010003426f6201000403000178",
+    ),
+    (
+        "one_of",
+        r#"{"oneOf":[{"type":"object","properties":{"kind":{"const":"circle"},"radius":{"type":"number","format":"double"}},"required":["radius"]},{"type":"object","properties":{"kind":{"const":"empty"}}}],"discriminator":{"propertyName":"kind"}}"#,
+        r#"{"kind":"circle","radius":2.0}"#,
+        "000100084000000000000000",
+    ),
+];
+
+fn parse(text: &str) -> serde_json::Value {
+    serde_json::from_str(text).expect("valid vector JSON")
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, b| {
+        let _ = write!(out, "{b:02x}");
+        out
+    })
+}
+
+pub(crate) fn unhex(text: &str) -> Vec<u8> {
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).expect("valid vector hex"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Decoder, Encoder};
+
+    #[test]
+    fn test_encoder_matches_vectors() {
+        let failures = check(|vector| {
+            let mut encoder = Encoder::new();
+            encoder.encode(&vector.value, &vector.schema)?;
+            Ok(encoder.finish().to_vec())
+        });
+        assert!(failures.is_empty(), "{failures:#?}");
+    }
+
+    #[test]
+    fn test_decoder_matches_vectors() {
+        for vector in vectors() {
+            let decoded = Decoder::decode(&mut vector.bytes.as_slice(), &vector.schema).unwrap();
+            assert_eq!(decoded, vector.value, "{}", vector.name);
+        }
+    }
+
+    #[test]
+    fn test_verify_and_export() {
+        let vector = &vectors()[0];
+        assert!(vector.verify(&[1]).is_ok());
+        let mismatch = vector.verify(&[1, 0]).unwrap_err();
+        assert_eq!(
+            mismatch.to_string(),
+            "boolean_true: first difference at byte 1: expected 01, got 0100"
+        );
+
+        let json = to_json();
+        assert_eq!(json.as_array().unwrap().len(), vectors().len());
+        assert_eq!(
+            json[0],
+            json!({"name": "boolean_true", "schema": {"type": "boolean"}, "value": true, "hex": "01"})
+        );
+
+        let mut names: Vec<_> = VECTORS.iter().map(|v| v.0).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), VECTORS.len());
+    }
+}
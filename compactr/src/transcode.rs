@@ -0,0 +1,965 @@
+//! Direct transcoding between JSON text and the Compactr wire format.
+//!
+//! [`json_to_compactr`] and [`compactr_to_json`] convert between the two
+//! formats without building a [`Value`] tree: objects and arrays are
+//! streamed straight between the JSON parser or writer and the binary
+//! layout, and only scalars briefly become a [`Value`] so that formats are
+//! handled exactly as by [`Value::from_json`] and [`Value::to_json`].
+//!
+//! ```rust,ignore
+//! use compactr::transcode::{compactr_to_json, json_to_compactr};
+//!
+//! let bytes = json_to_compactr(br#"{"id": 1, "name": "Alice"}"#, &schema)?;
+//! let json = compactr_to_json(&bytes, &schema)?;
+//! ```
+//!
+//! The output is byte for byte the one of the `Value` path: properties are
+//! written in alphabetical order, unknown keys are skipped and `null` means
+//! "absent". `oneOf` values are the exception to the streaming, since the
+//! discriminator may come after the payload in JSON; they are buffered as a
+//! whole.
+//!
+//! With the `cbor` feature, [`cbor_to_compactr`] and [`compactr_to_cbor`]
+//! convert CBOR data the same way, going through [`Value::from_cbor`] and
+//! [`Value::to_cbor`] so that UUIDs and date-times keep their CBOR tags.
+//! The `msgpack` feature adds [`msgpack_to_compactr`] and
+//! [`compactr_to_msgpack`] on top of [`Value::from_msgpack`] and
+//! [`Value::to_msgpack`].
+
+use crate::codec::buffer::put_prefixed;
+use crate::codec::wire::STRING_LEN_WIDTH;
+use crate::codec::{
+    put_count, put_element_size, put_object_header, put_property, read_element_size, ArrayFormat,
+    Decoder, Encoder,
+};
+use crate::error::{DecodeError, EncodeError, Error, Result, SchemaError};
+use crate::schema::{index_of, index_order, Property, SchemaRegistry, SchemaType, StringFormat};
+use crate::value::Value;
+use ::serde::de::{self, Deserialize, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use ::serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+use bytes::{BufMut, Bytes, BytesMut};
+use std::cell::RefCell;
+use std::fmt;
+use std::ops::Range;
+
+/// Encodes JSON text according to `schema`.
+///
+/// # Errors
+///
+/// Returns an error if the JSON is malformed or doesn't fit the schema.
+pub fn json_to_compactr(json: &[u8], schema: &SchemaType) -> Result<Bytes> {
+    json_to_compactr_with_registry(json, schema, SchemaRegistry::empty())
+}
+
+/// Encodes JSON text according to `schema`, resolving references through
+/// `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the JSON is malformed, doesn't fit the schema, or a
+/// reference cannot be resolved.
+pub fn json_to_compactr_with_registry(
+    json: &[u8],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Bytes> {
+    let ctx = Context::new(registry);
+    let mut buf = BytesMut::with_capacity(json.len());
+    let mut de = serde_json::Deserializer::from_slice(json);
+    let seed = Transcode {
+        ctx: &ctx,
+        schema,
+        buf: &mut buf,
+        property: false,
+        nullable: false,
+    };
+    seed.deserialize(&mut de)
+        .and_then(|_| de.end())
+        .map_err(|e| {
+            ctx.into_error(e, |e| {
+                EncodeError::InvalidFormat(format!("Invalid JSON: {e}"))
+            })
+        })?;
+    Ok(buf.freeze())
+}
+
+/// Decodes `bytes` laid out according to `schema` into JSON text.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid for the schema.
+pub fn compactr_to_json(bytes: &[u8], schema: &SchemaType) -> Result<Vec<u8>> {
+    compactr_to_json_with_registry(bytes, schema, SchemaRegistry::empty())
+}
+
+/// Decodes `bytes` laid out according to `schema` into JSON text, resolving
+/// references through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid for the schema or a
+/// reference cannot be resolved.
+pub fn compactr_to_json_with_registry(
+    bytes: &[u8],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Vec<u8>> {
+    let ctx = Context::new(registry);
+    let mut out = Vec::with_capacity(bytes.len() * 2);
+    let node = Node {
+        ctx: &ctx,
+        bytes,
+        schema,
+        property: false,
+    };
+    serde_json::to_writer(&mut out, &node)
+        .map_err(|e| ctx.into_error(e, |e| DecodeError::InvalidData(e.to_string())))?;
+    Ok(out)
+}
+
+/// Encodes CBOR data according to `schema`.
+///
+/// # Errors
+///
+/// Returns an error if the CBOR is malformed or doesn't fit the schema.
+#[cfg(feature = "cbor")]
+pub fn cbor_to_compactr(cbor: &[u8], schema: &SchemaType) -> Result<Bytes> {
+    cbor_to_compactr_with_registry(cbor, schema, SchemaRegistry::empty())
+}
+
+/// Encodes CBOR data according to `schema`, resolving references through
+/// `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the CBOR is malformed, doesn't fit the schema, or a
+/// reference cannot be resolved.
+#[cfg(feature = "cbor")]
+pub fn cbor_to_compactr_with_registry(
+    cbor: &[u8],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Bytes> {
+    let cbor: ciborium::Value = ciborium::from_reader(cbor)
+        .map_err(|e| EncodeError::InvalidFormat(format!("Invalid CBOR: {e}")))?;
+    let value = Value::from_cbor_with_registry(&cbor, schema, registry)?;
+    let mut encoder = Encoder::new();
+    encoder.encode_with_registry(&value, schema, registry)?;
+    Ok(encoder.finish())
+}
+
+/// Decodes `bytes` laid out according to `schema` into CBOR data.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid for the schema.
+#[cfg(feature = "cbor")]
+pub fn compactr_to_cbor(bytes: &[u8], schema: &SchemaType) -> Result<Vec<u8>> {
+    compactr_to_cbor_with_registry(bytes, schema, SchemaRegistry::empty())
+}
+
+/// Decodes `bytes` laid out according to `schema` into CBOR data, resolving
+/// references through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid for the schema or a
+/// reference cannot be resolved.
+#[cfg(feature = "cbor")]
+pub fn compactr_to_cbor_with_registry(
+    mut bytes: &[u8],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Vec<u8>> {
+    let value = Decoder::decode_with_registry(&mut bytes, schema, registry)?;
+    let mut out = Vec::new();
+    ciborium::into_writer(&value.to_cbor(), &mut out)
+        .map_err(|e| EncodeError::InvalidFormat(format!("Invalid CBOR: {e}")))?;
+    Ok(out)
+}
+
+/// Encodes `MessagePack` data according to `schema`.
+///
+/// # Errors
+///
+/// Returns an error if the `MessagePack` is malformed or doesn't fit the
+/// schema.
+#[cfg(feature = "msgpack")]
+pub fn msgpack_to_compactr(msgpack: &[u8], schema: &SchemaType) -> Result<Bytes> {
+    msgpack_to_compactr_with_registry(msgpack, schema, SchemaRegistry::empty())
+}
+
+/// Encodes `MessagePack` data according to `schema`, resolving references
+/// through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the `MessagePack` is malformed, doesn't fit the schema,
+/// or a reference cannot be resolved.
+#[cfg(feature = "msgpack")]
+pub fn msgpack_to_compactr_with_registry(
+    mut msgpack: &[u8],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Bytes> {
+    let msgpack = rmpv::decode::read_value(&mut msgpack)
+        .map_err(|e| EncodeError::InvalidFormat(format!("Invalid MessagePack: {e}")))?;
+    let value = Value::from_msgpack_with_registry(&msgpack, schema, registry)?;
+    let mut encoder = Encoder::new();
+    encoder.encode_with_registry(&value, schema, registry)?;
+    Ok(encoder.finish())
+}
+
+/// Decodes `bytes` laid out according to `schema` into `MessagePack` data.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid for the schema.
+#[cfg(feature = "msgpack")]
+pub fn compactr_to_msgpack(bytes: &[u8], schema: &SchemaType) -> Result<Vec<u8>> {
+    compactr_to_msgpack_with_registry(bytes, schema, SchemaRegistry::empty())
+}
+
+/// Decodes `bytes` laid out according to `schema` into `MessagePack` data,
+/// resolving references through `registry`.
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid for the schema or a
+/// reference cannot be resolved.
+#[cfg(feature = "msgpack")]
+pub fn compactr_to_msgpack_with_registry(
+    mut bytes: &[u8],
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+) -> Result<Vec<u8>> {
+    let value = Decoder::decode_with_registry(&mut bytes, schema, registry)?;
+    let mut out = Vec::new();
+    rmpv::encode::write_value(&mut out, &value.to_msgpack())
+        .map_err(|e| EncodeError::InvalidFormat(format!("Invalid MessagePack: {e}")))?;
+    Ok(out)
+}
+
+/// State shared by a whole transcoding run.
+///
+/// serde errors only carry a message, so the original [`Error`] is kept
+/// here and handed back once the run has been unwound.
+struct Context<'r> {
+    registry: &'r SchemaRegistry,
+    error: RefCell<Option<Error>>,
+}
+
+impl<'r> Context<'r> {
+    const fn new(registry: &'r SchemaRegistry) -> Self {
+        Self {
+            registry,
+            error: RefCell::new(None),
+        }
+    }
+
+    /// Records `error` and returns its message for the serde error.
+    fn stash(&self, error: Error) -> String {
+        let msg = error.to_string();
+        *self.error.borrow_mut() = Some(error);
+        msg
+    }
+
+    /// Returns the recorded error, or converts the serde error if it did not
+    /// originate from a stashed one.
+    fn into_error<E: Into<Error>>(
+        self,
+        error: serde_json::Error,
+        convert: impl FnOnce(serde_json::Error) -> E,
+    ) -> Error {
+        self.error
+            .into_inner()
+            .unwrap_or_else(|| convert(error).into())
+    }
+
+    fn resolve(&self, ref_name: &str) -> Result<SchemaType> {
+        self.registry.resolve_ref(ref_name)
+    }
+}
+
+/// Writes the JSON value being parsed into `buf` according to `schema`.
+///
+/// Yields `false` if the value was a `null` read as "absent".
+struct Transcode<'a> {
+    ctx: &'a Context<'a>,
+    schema: &'a SchemaType,
+    buf: &'a mut BytesMut,
+    /// Whether the value is a property, using the property layout.
+    property: bool,
+    /// Whether `null` means "absent".
+    nullable: bool,
+}
+
+impl<'de> DeserializeSeed<'de> for Transcode<'_> {
+    type Value = bool;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<bool, D::Error> {
+        let fail = |e: Error| de::Error::custom(self.ctx.stash(e));
+        match self.schema {
+            SchemaType::Reference(ref_name) => {
+                // Referenced schemas keep their top-level layout inside objects
+                let resolved = self.ctx.resolve(ref_name).map_err(fail)?;
+                Transcode {
+                    ctx: self.ctx,
+                    schema: &resolved,
+                    buf: self.buf,
+                    property: false,
+                    nullable: self.nullable,
+                }
+                .deserialize(deserializer)
+            }
+            SchemaType::Array(items) => deserializer.deserialize_any(ArrayVisitor {
+                ctx: self.ctx,
+                items,
+                buf: self.buf,
+                nullable: self.nullable,
+            }),
+            SchemaType::Object(properties) => deserializer.deserialize_any(ObjectVisitor {
+                ctx: self.ctx,
+                properties,
+                buf: self.buf,
+                nullable: self.nullable,
+            }),
+            SchemaType::String(StringFormat::Plain) => deserializer.deserialize_any(StrVisitor {
+                ctx: self.ctx,
+                buf: self.buf,
+                property: self.property,
+                nullable: self.nullable,
+            }),
+            schema => {
+                // Scalars, `oneOf` values and run-length arrays go through a
+                // `Value`
+                let json = serde_json::Value::deserialize(deserializer)?;
+                if json.is_null() && self.nullable {
+                    return Ok(false);
+                }
+                let value = Value::from_json_with_registry(&json, schema, self.ctx.registry)
+                    .map_err(fail)?;
+                let registry = self.ctx.registry;
+                if self.property {
+                    Encoder::encode_property_into(self.buf, &value, schema, registry)
+                } else {
+                    Encoder::encode_value_into(self.buf, &value, schema, registry)
+                }
+                .map_err(fail)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+struct ArrayVisitor<'a> {
+    ctx: &'a Context<'a>,
+    items: &'a SchemaType,
+    buf: &'a mut BytesMut,
+    nullable: bool,
+}
+
+impl<'de> Visitor<'de> for ArrayVisitor<'_> {
+    type Value = bool;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an array")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<bool, E> {
+        absent(self.nullable, &self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<bool, A::Error> {
+        let registry = self.ctx.registry;
+        if registry.array_format() == ArrayFormat::CountPrefixed {
+            // Elements are collected first, since the count precedes them
+            let mut elems = BytesMut::new();
+            let mut count = 0;
+            loop {
+                let seed = Transcode {
+                    ctx: self.ctx,
+                    schema: self.items,
+                    buf: &mut elems,
+                    property: false,
+                    nullable: false,
+                };
+                if seq.next_element_seed(seed)?.is_none() {
+                    put_count(self.buf, registry.length_mode(), count)
+                        .map_err(|e| de::Error::custom(self.ctx.stash(e.into())))?;
+                    self.buf.extend_from_slice(&elems);
+                    return Ok(true);
+                }
+                count += 1;
+            }
+        }
+
+        // Each element is prefixed with its size, as in `Encoder`
+        let mut elem = BytesMut::new();
+        loop {
+            elem.clear();
+            let seed = Transcode {
+                ctx: self.ctx,
+                schema: self.items,
+                buf: &mut elem,
+                property: false,
+                nullable: false,
+            };
+            if seq.next_element_seed(seed)?.is_none() {
+                return Ok(true);
+            }
+            put_element_size(
+                self.buf,
+                registry.array_format(),
+                registry.length_mode(),
+                elem.len(),
+            )
+            .map_err(|e| de::Error::custom(self.ctx.stash(e.into())))?;
+            self.buf.extend_from_slice(&elem);
+        }
+    }
+}
+
+struct ObjectVisitor<'a> {
+    ctx: &'a Context<'a>,
+    properties: &'a indexmap::IndexMap<String, Property>,
+    buf: &'a mut BytesMut,
+    nullable: bool,
+}
+
+impl<'de> Visitor<'de> for ObjectVisitor<'_> {
+    type Value = bool;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an object")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<bool, E> {
+        absent(self.nullable, &self)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<bool, A::Error> {
+        let fail = |e: Error| de::Error::custom(self.ctx.stash(e));
+
+        // Properties are indexed alphabetically by name
+        let sorted = index_order(self.properties);
+
+        // Values are collected first, since JSON keys may come in any order
+        let mut values = BytesMut::new();
+        let mut present: Vec<(usize, Range<usize>)> = Vec::new();
+        while let Some(idx) = map.next_key_seed(KeyIndex(&sorted))? {
+            let Some(idx) = idx else {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            };
+            let schema = &sorted[idx].1.schema_type;
+            let start = values.len();
+            let seed = Transcode {
+                ctx: self.ctx,
+                schema,
+                buf: &mut values,
+                property: true,
+                // `null` means "absent" unless the property is explicitly a null type
+                nullable: *schema != SchemaType::Null,
+            };
+            // The last occurrence of a duplicate key wins
+            present.retain(|(i, _)| *i != idx);
+            if map.next_value_seed(seed)? {
+                present.push((idx, start..values.len()));
+            }
+        }
+
+        for (name, property) in self.properties {
+            if property.required && !present.iter().any(|(idx, _)| sorted[*idx].0 == name) {
+                return Err(fail(SchemaError::MissingField(name.clone()).into()));
+            }
+        }
+
+        let format = self.ctx.registry.object_format();
+        present.sort_by_key(|(idx, _)| *idx);
+        put_object_header(
+            self.buf,
+            format,
+            sorted.iter().map(|(_, property)| *property),
+            present.iter().map(|(idx, _)| *idx),
+        )
+        .map_err(|e| fail(e.into()))?;
+        for (idx, range) in present {
+            put_property(
+                self.buf,
+                format,
+                self.ctx.registry,
+                idx,
+                sorted[idx].1,
+                &values[range],
+            )
+            .map_err(fail)?;
+        }
+        Ok(true)
+    }
+}
+
+/// Looks up the alphabetical index of an object key, without allocating.
+struct KeyIndex<'a, 'p>(&'a [(&'p String, &'p Property)]);
+
+impl<'de> DeserializeSeed<'de> for KeyIndex<'_, '_> {
+    type Value = Option<usize>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl Visitor<'_> for KeyIndex<'_, '_> {
+    type Value = Option<usize>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a property name")
+    }
+
+    fn visit_str<E: de::Error>(self, key: &str) -> Result<Self::Value, E> {
+        Ok(index_of(self.0, key))
+    }
+}
+
+struct StrVisitor<'a> {
+    ctx: &'a Context<'a>,
+    buf: &'a mut BytesMut,
+    property: bool,
+    nullable: bool,
+}
+
+impl Visitor<'_> for StrVisitor<'_> {
+    type Value = bool;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<bool, E> {
+        absent(self.nullable, &self)
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<bool, E> {
+        if self.property {
+            // Strings in objects have no length prefix
+            self.buf.put_slice(s.as_bytes());
+        } else {
+            let mode = self.ctx.registry.length_mode();
+            put_prefixed(self.buf, mode, STRING_LEN_WIDTH, s.as_bytes())
+                .map_err(|e| E::custom(self.ctx.stash(e.into())))?;
+        }
+        Ok(true)
+    }
+}
+
+/// Accepts a `null` read as "absent", rejecting it otherwise.
+fn absent<E: de::Error>(nullable: bool, expected: &dyn de::Expected) -> Result<bool, E> {
+    if nullable {
+        Ok(false)
+    } else {
+        Err(E::invalid_type(de::Unexpected::Unit, expected))
+    }
+}
+
+/// Writes the value in `bytes` as JSON according to `schema`.
+struct Node<'a> {
+    ctx: &'a Context<'a>,
+    bytes: &'a [u8],
+    schema: &'a SchemaType,
+    /// Whether the value is a property, using the property layout.
+    property: bool,
+}
+
+impl<'a> Serialize for Node<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fail = |e: Error| ser::Error::custom(self.ctx.stash(e));
+        match self.schema {
+            SchemaType::Reference(ref_name) => {
+                let resolved = self.ctx.resolve(ref_name).map_err(fail)?;
+                Node {
+                    ctx: self.ctx,
+                    bytes: self.bytes,
+                    schema: &resolved,
+                    property: false,
+                }
+                .serialize(serializer)
+            }
+            SchemaType::Array(items)
+                if self.ctx.registry.array_format() != ArrayFormat::CountPrefixed =>
+            {
+                // Elements are read until the value's bytes are exhausted
+                let format = self.ctx.registry.array_format();
+                let mode = self.ctx.registry.length_mode();
+                let mut seq = serializer.serialize_seq(None)?;
+                let mut rest = self.bytes;
+                while !rest.is_empty() {
+                    let size =
+                        read_element_size(&mut rest, format, mode).map_err(|e| fail(e.into()))?;
+                    if rest.len() < size {
+                        return Err(fail(DecodeError::UnexpectedEof.into()));
+                    }
+                    let (elem, tail) = rest.split_at(size);
+                    seq.serialize_element(&Node {
+                        ctx: self.ctx,
+                        bytes: elem,
+                        schema: items,
+                        property: false,
+                    })?;
+                    rest = tail;
+                }
+                seq.end()
+            }
+            SchemaType::Object(properties) => {
+                let mut fields: Vec<(&String, &SchemaType, &[u8])> = Vec::new();
+                let take = |buf: &mut &'a [u8], n| {
+                    let (head, tail) = buf.split_at(n);
+                    *buf = tail;
+                    head
+                };
+                let mut rest = self.bytes;
+                let registry = &self.ctx.registry;
+                Decoder::decode_properties(
+                    &mut rest,
+                    properties,
+                    registry.object_format(),
+                    registry.length_mode(),
+                    take,
+                    |name, _, bytes| {
+                        if let Some((name, property)) = properties.get_key_value(name) {
+                            // The last occurrence of a duplicate property wins
+                            fields.retain(|(other, _, _)| *other != name);
+                            fields.push((name, &property.schema_type, *bytes));
+                        }
+                        Ok(())
+                    },
+                )
+                .map_err(fail)?;
+
+                // Keys come out sorted, as in `serde_json::Map`
+                fields.sort_by(|a, b| a.0.cmp(b.0));
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (name, schema, bytes) in fields {
+                    map.serialize_entry(
+                        name,
+                        &Node {
+                            ctx: self.ctx,
+                            bytes,
+                            schema,
+                            property: true,
+                        },
+                    )?;
+                }
+                map.end()
+            }
+            SchemaType::String(StringFormat::Plain | StringFormat::Long) if self.property => {
+                // Strings in objects have no length prefix
+                let s = std::str::from_utf8(self.bytes).map_err(|e| {
+                    fail(DecodeError::InvalidData(format!("Invalid UTF-8: {e}")).into())
+                })?;
+                serializer.serialize_str(s)
+            }
+            schema => {
+                // Scalars, `oneOf` values, count-prefixed and run-length
+                // arrays go through a `Value`
+                let mut bytes = self.bytes;
+                let registry = self.ctx.registry;
+                let value = if self.property {
+                    Decoder::decode_property_value(&mut bytes, schema, registry)
+                } else {
+                    Decoder::decode_with_registry(&mut bytes, schema, registry)
+                }
+                .map_err(fail)?;
+                value.to_json().serialize(serializer)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    use crate::codec::{LengthMode, ObjectFormat};
+    use serde_json::json;
+
+    fn registry() -> SchemaRegistry {
+        let registry = SchemaRegistry::new();
+        registry
+            .register_json_schemas(&json!({
+                "components": {"schemas": {
+                    "Address": {
+                        "type": "object",
+                        "required": ["city"],
+                        "properties": {
+                            "city": {"type": "string"},
+                            "zip": {"type": "integer", "format": "uint16"}
+                        }
+                    },
+                    "Shape": {
+                        "oneOf": [
+                            {"$ref": "#/components/schemas/Circle"},
+                            {"$ref": "#/components/schemas/Empty"}
+                        ],
+                        "discriminator": {"propertyName": "kind"}
+                    },
+                    "Circle": {
+                        "type": "object",
+                        "properties": {"radius": {"type": "number", "format": "double"}}
+                    },
+                    "Empty": {"type": "object", "properties": {}},
+                    "User": {
+                        "type": "object",
+                        "required": ["id", "name"],
+                        "properties": {
+                            "id": {"type": "string", "format": "uuid"},
+                            "name": {"type": "string"},
+                            "active": {"type": "boolean"},
+                            "score": {"type": "number", "format": "float"},
+                            "created": {"type": "string", "format": "date-time"},
+                            "tags": {"type": "array", "items": {"type": "string"}},
+                            "home": {"$ref": "#/components/schemas/Address"},
+                            "offices": {
+                                "type": "array",
+                                "items": {"$ref": "#/components/schemas/Address"}
+                            },
+                            "shape": {"$ref": "#/components/schemas/Shape"},
+                            "role": {"type": "string", "enum": ["admin", "member"]}
+                        }
+                    }
+                }}
+            }))
+            .unwrap();
+        registry
+    }
+
+    fn via_value(
+        json: &serde_json::Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Bytes {
+        let value = Value::from_json_with_registry(json, schema, registry).unwrap();
+        let mut encoder = Encoder::new();
+        encoder
+            .encode_with_registry(&value, schema, registry)
+            .unwrap();
+        encoder.finish()
+    }
+
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    #[test]
+    fn test_matches_value_path() {
+        let registry = registry();
+        let schema = SchemaType::reference("User");
+        let user = json!({
+            "role": "admin",
+            "name": "Alice",
+            "id": "550e8400-e29b-41d4-a716-446655440000",
+            "active": true,
+            "score": 1.5,
+            "created": "2024-01-02T03:04:05Z",
+            "tags": ["a", "b"],
+            "home": {"city": "Paris", "zip": 7500},
+            "offices": [{"city": "Lyon"}, {"zip": 1, "city": "Nice"}],
+            "shape": {"radius": 2.0, "kind": "Circle"},
+            "unknown": {"nested": [1, 2]},
+            "home_phone": null
+        });
+        let text = serde_json::to_vec(&user).unwrap();
+
+        let bytes = json_to_compactr_with_registry(&text, &schema, &registry).unwrap();
+        assert_eq!(bytes, via_value(&user, &schema, &registry));
+
+        let json = compactr_to_json_with_registry(&bytes, &schema, &registry).unwrap();
+        let mut expected = user.clone();
+        expected.as_object_mut().unwrap().remove("unknown");
+        expected.as_object_mut().unwrap().remove("home_phone");
+        assert_eq!(
+            json,
+            serde_json::to_vec(
+                &Value::from_json_with_registry(&expected, &schema, &registry)
+                    .unwrap()
+                    .to_json()
+            )
+            .unwrap()
+        );
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(json, expected);
+    }
+
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    #[test]
+    fn test_registry_layouts() {
+        let schema = SchemaType::reference("User");
+        let user = json!({
+            "id": "550e8400-e29b-41d4-a716-446655440000",
+            "name": "Alice",
+            "tags": ["a", "b"],
+            "offices": [{"city": "Lyon"}, {"zip": 1, "city": "Nice"}]
+        });
+        let text = serde_json::to_vec(&user).unwrap();
+
+        for registry in [
+            registry().with_array_format(ArrayFormat::CountPrefixed),
+            registry().with_array_format(ArrayFormat::VarintSizePrefixed),
+            registry().with_object_format(ObjectFormat::Bitmap),
+            registry().with_length_mode(LengthMode::Varint),
+            registry()
+                .with_array_format(ArrayFormat::CountPrefixed)
+                .with_length_mode(LengthMode::Varint),
+        ] {
+            let bytes = json_to_compactr_with_registry(&text, &schema, &registry).unwrap();
+            assert_eq!(bytes, via_value(&user, &schema, &registry));
+            let json = compactr_to_json_with_registry(&bytes, &schema, &registry).unwrap();
+            assert_eq!(
+                serde_json::from_slice::<serde_json::Value>(&json).unwrap(),
+                user
+            );
+        }
+    }
+
+    #[test]
+    fn test_top_level_values() {
+        let registry = SchemaRegistry::new();
+        for (json, schema) in [
+            (json!("hello"), SchemaType::string()),
+            (json!(42), SchemaType::int32()),
+            (
+                json!([[1, 2], [3]]),
+                SchemaType::array(SchemaType::array(SchemaType::int32())),
+            ),
+            (json!(["x", "yz"]), SchemaType::array(SchemaType::string())),
+        ] {
+            let text = serde_json::to_vec(&json).unwrap();
+            let bytes = json_to_compactr(&text, &schema).unwrap();
+            assert_eq!(bytes, via_value(&json, &schema, &registry));
+            assert_eq!(compactr_to_json(&bytes, &schema).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_null_and_duplicate_keys() {
+        let mut properties = indexmap::IndexMap::new();
+        properties.insert("a".to_owned(), Property::optional(SchemaType::string()));
+        properties.insert("b".to_owned(), Property::optional(SchemaType::Null));
+        let schema = SchemaType::object(properties);
+        let bytes = json_to_compactr(br#"{"a": "x", "a": null, "b": null}"#, &schema).unwrap();
+        assert_eq!(
+            bytes,
+            via_value(&json!({"b": null}), &schema, &SchemaRegistry::new())
+        );
+        assert_eq!(compactr_to_json(&bytes, &schema).unwrap(), br#"{"b":null}"#);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut properties = indexmap::IndexMap::new();
+        properties.insert("id".to_owned(), Property::required(SchemaType::uint8()));
+        properties.insert(
+            "a_label".to_owned(),
+            Property::optional(SchemaType::string()).with_alias("name"),
+        );
+        let schema = SchemaType::object(properties);
+        let registry = SchemaRegistry::new();
+
+        // The renamed property keeps its index after `id`
+        let bytes = json_to_compactr(br#"{"name": "x", "id": 1}"#, &schema).unwrap();
+        assert_eq!(bytes.as_ref(), &[2, 0, 1, 1, 1, 1, b'x']);
+        assert_eq!(
+            bytes,
+            via_value(&json!({"id": 1, "name": "x"}), &schema, &registry)
+        );
+        let renamed = via_value(&json!({"id": 1, "a_label": "x"}), &schema, &registry);
+        for bytes in [bytes, renamed] {
+            assert_eq!(
+                compactr_to_json(&bytes, &schema).unwrap(),
+                br#"{"a_label":"x","id":1}"#
+            );
+        }
+    }
+
+    #[cfg(feature = "cbor")]
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    #[test]
+    fn test_cbor_roundtrip() {
+        let registry = registry();
+        let schema = SchemaType::reference("User");
+        let user = json!({
+            "id": "550e8400-e29b-41d4-a716-446655440000",
+            "name": "Alice",
+            "created": "2024-01-02T03:04:05Z",
+            "home": {"city": "Paris"}
+        });
+        let bytes = via_value(&user, &schema, &registry);
+
+        let cbor = compactr_to_cbor_with_registry(&bytes, &schema, &registry).unwrap();
+        let parsed: ciborium::Value = ciborium::from_reader(cbor.as_slice()).unwrap();
+        let id = parsed
+            .as_map()
+            .unwrap()
+            .iter()
+            .find(|(k, _)| k.as_text() == Some("id"))
+            .map(|(_, v)| v)
+            .unwrap();
+        assert!(matches!(id.as_tag(), Some((37, _))));
+
+        assert_eq!(
+            cbor_to_compactr_with_registry(&cbor, &schema, &registry).unwrap(),
+            bytes
+        );
+        assert!(cbor_to_compactr(&[0xff], &schema).is_err());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    #[test]
+    fn test_msgpack_roundtrip() {
+        let registry = registry();
+        let schema = SchemaType::reference("User");
+        let user = json!({
+            "id": "550e8400-e29b-41d4-a716-446655440000",
+            "name": "Alice",
+            "created": "2024-01-02T03:04:05Z",
+            "offices": [{"city": "Lyon", "zip": 6900}]
+        });
+        let bytes = via_value(&user, &schema, &registry);
+
+        let msgpack = compactr_to_msgpack_with_registry(&bytes, &schema, &registry).unwrap();
+        assert_eq!(
+            msgpack_to_compactr_with_registry(&msgpack, &schema, &registry).unwrap(),
+            bytes
+        );
+        assert!(msgpack_to_compactr(&[0xc1], &schema).is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        let registry = registry();
+        let schema = SchemaType::reference("User");
+        let missing = json_to_compactr_with_registry(br#"{"name": "A"}"#, &schema, &registry);
+        assert!(matches!(
+            missing,
+            Err(Error::Schema(SchemaError::MissingField(field))) if field == "id"
+        ));
+
+        let bad_uuid = br#"{"id": "nope", "name": "A"}"#;
+        assert!(json_to_compactr_with_registry(bad_uuid, &schema, &registry).is_err());
+
+        let malformed = json_to_compactr(b"[1, 2", &SchemaType::array(SchemaType::int32()));
+        assert!(matches!(
+            malformed,
+            Err(Error::Encode(EncodeError::InvalidFormat(msg))) if msg.starts_with("Invalid JSON")
+        ));
+        assert!(json_to_compactr(br#""a" "b""#, &SchemaType::string()).is_err());
+        assert!(json_to_compactr(b"null", &SchemaType::array(SchemaType::int32())).is_err());
+
+        let truncated = compactr_to_json(&[3, 1], &SchemaType::array(SchemaType::int32()));
+        assert!(matches!(
+            truncated,
+            Err(Error::Decode(DecodeError::UnexpectedEof))
+        ));
+    }
+}
@@ -0,0 +1,588 @@
+//! Conversion between [`Value`] and `ciborium::Value`.
+//!
+//! Formatted variants use the standard CBOR tags so that other CBOR
+//! implementations keep their meaning:
+//!
+//! | Variant                     | CBOR                                        |
+//! |-----------------------------|---------------------------------------------|
+//! | `Uuid`                      | tag 37 on a 16-byte string                  |
+//! | `DateTime`, `DateTimeTz`    | tag 0 on an RFC 3339 string                 |
+//! | `Date`                      | tag 1004 on an RFC 3339 full-date string    |
+//! | `Ipv4`, `Ipv6`              | tags 52 and 54 on the address bytes         |
+//! | `GeoPoint`                  | tag 103 on a `[lat, lon]` array             |
+//! | `Int128`, `Uint128`         | integers, or tag 2/3 bignums beyond 64 bits |
+//! | `Binary`                    | byte string                                 |
+//!
+//! When reading, epoch-based date-times (tag 1) and dates (tag 100), URIs
+//! (tag 32), and the untagged and string forms accepted by
+//! [`Value::from_json`] are understood as well.
+
+use super::json::string_from_json;
+use super::Value;
+use crate::codec::{check_integer_precision, intern, narrow_float};
+use crate::error::{EncodeError, Result, SchemaError};
+#[cfg(feature = "decimal")]
+use crate::formats::decimal;
+use crate::formats::geo::GeoPoint;
+use crate::schema::{
+    IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType, StringFormat,
+};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use ciborium::value::{Integer, Value as Cbor};
+use indexmap::IndexMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// RFC 3339 date-time string
+const TAG_DATETIME: u64 = 0;
+/// Seconds since the Unix epoch
+#[cfg(feature = "chrono")]
+const TAG_EPOCH: u64 = 1;
+/// Unsigned bignum
+const TAG_BIGNUM: u64 = 2;
+/// Negative bignum
+const TAG_NEG_BIGNUM: u64 = 3;
+/// URI string
+const TAG_URI: u64 = 32;
+/// Binary UUID
+const TAG_UUID: u64 = 37;
+/// IPv4 address bytes
+const TAG_IPV4: u64 = 52;
+/// IPv6 address bytes
+const TAG_IPV6: u64 = 54;
+/// Days since the Unix epoch
+#[cfg(feature = "chrono")]
+const TAG_EPOCH_DAYS: u64 = 100;
+/// Geographic coordinates
+const TAG_GEO: u64 = 103;
+/// RFC 3339 full-date string
+const TAG_DATE: u64 = 1004;
+
+impl Value {
+    /// Converts a CBOR value into a `Value` guided by the given schema.
+    ///
+    /// Tagged values are checked against the schema's format, and untagged
+    /// strings are parsed as by [`Value::from_json`]. Map entries that are
+    /// not part of the schema, or that are `null`, are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CBOR value doesn't match the schema.
+    pub fn from_cbor(cbor: &Cbor, schema: &SchemaType) -> Result<Self> {
+        Self::from_cbor_with_registry(cbor, schema, SchemaRegistry::empty())
+    }
+
+    /// Converts a CBOR value into a `Value` with a schema registry for
+    /// resolving references.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the CBOR value doesn't match the schema or a
+    /// reference cannot be resolved.
+    pub fn from_cbor_with_registry(
+        cbor: &Cbor,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        match schema {
+            SchemaType::Boolean => cbor
+                .as_bool()
+                .map(Self::Boolean)
+                .ok_or_else(|| mismatch("boolean", cbor)),
+            SchemaType::Integer(format) => integer_from_cbor(cbor, *format),
+            SchemaType::Number(format) => number_from_cbor(cbor, *format, registry),
+            SchemaType::String(format) => string_from_cbor(cbor, *format, registry),
+            SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+                let arr = cbor.as_array().ok_or_else(|| mismatch("array", cbor))?;
+                arr.iter()
+                    .map(|item| Self::from_cbor_with_registry(item, items, registry))
+                    .collect::<Result<Vec<_>>>()
+                    .map(Self::Array)
+            }
+            SchemaType::Object(properties) => {
+                let map = cbor.as_map().ok_or_else(|| mismatch("object", cbor))?;
+                object_from_cbor(map, properties, registry)
+            }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::from_cbor_with_registry(cbor, &resolved, registry)
+            }
+            SchemaType::Enum(variants) => {
+                let s = cbor.as_text().ok_or_else(|| mismatch("string", cbor))?;
+                if !variants.iter().any(|v| v == s) {
+                    return Err(EncodeError::InvalidFormat(format!("Unknown variant: {s}")).into());
+                }
+                Ok(Self::String(s.to_owned()))
+            }
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            } => one_of_from_cbor(cbor, discriminator, variants, registry),
+            SchemaType::Null => {
+                if cbor.is_null() {
+                    Ok(Self::Null)
+                } else {
+                    Err(mismatch("null", cbor))
+                }
+            }
+        }
+    }
+
+    /// Converts this value into a CBOR value.
+    ///
+    /// Formatted variants are tagged as listed in the
+    /// [module documentation](self); times of day, socket addresses and
+    /// decimals have no standard tag and become strings.
+    #[must_use]
+    pub fn to_cbor(&self) -> Cbor {
+        let tag = |tag, value| Cbor::Tag(tag, Box::new(value));
+        match self {
+            Self::Boolean(b) => Cbor::Bool(*b),
+            Self::Integer(i) => Cbor::Integer((*i).into()),
+            Self::Int128(i) => Integer::try_from(*i).map_or_else(
+                |_| {
+                    if *i < 0 {
+                        // Negative bignums hold -1 - n
+                        tag(TAG_NEG_BIGNUM, bignum_bytes(i.unsigned_abs() - 1))
+                    } else {
+                        tag(TAG_BIGNUM, bignum_bytes(i.unsigned_abs()))
+                    }
+                },
+                Cbor::Integer,
+            ),
+            Self::Uint128(u) => Integer::try_from(*u)
+                .map_or_else(|_| tag(TAG_BIGNUM, bignum_bytes(*u)), Cbor::Integer),
+            Self::Float(f) => Cbor::Float(f64::from(*f)),
+            Self::Double(d) => Cbor::Float(*d),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(d) => Cbor::Text(d.to_string()),
+            Self::String(s) => Cbor::Text(s.clone()),
+            #[cfg(feature = "uuid")]
+            Self::Uuid(u) => tag(TAG_UUID, Cbor::Bytes(u.as_bytes().to_vec())),
+            #[cfg(feature = "chrono")]
+            Self::DateTime(dt) => tag(
+                TAG_DATETIME,
+                Cbor::Text(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            ),
+            #[cfg(feature = "chrono")]
+            Self::DateTimeTz(dt) => tag(
+                TAG_DATETIME,
+                Cbor::Text(dt.to_rfc3339_opts(SecondsFormat::AutoSi, false)),
+            ),
+            #[cfg(feature = "chrono")]
+            Self::Date(d) => tag(TAG_DATE, Cbor::Text(d.format("%Y-%m-%d").to_string())),
+            #[cfg(feature = "chrono")]
+            Self::Time(t) => Cbor::Text(t.to_string()),
+            Self::GeoPoint(p) => tag(
+                TAG_GEO,
+                Cbor::Array(vec![Cbor::Float(p.lat()), Cbor::Float(p.lon())]),
+            ),
+            Self::Ipv4(ip) => tag(TAG_IPV4, Cbor::Bytes(ip.octets().to_vec())),
+            Self::Ipv6(ip) => tag(TAG_IPV6, Cbor::Bytes(ip.octets().to_vec())),
+            Self::SocketAddr(addr) => Cbor::Text(addr.to_string()),
+            Self::Binary(data) => Cbor::Bytes(data.clone()),
+            Self::Array(items) => Cbor::Array(items.iter().map(Self::to_cbor).collect()),
+            Self::Object(obj) => Cbor::Map(
+                obj.iter()
+                    .map(|(k, v)| (Cbor::Text(k.to_string()), v.to_cbor()))
+                    .collect(),
+            ),
+            Self::Custom(custom) => custom.repr().to_cbor(),
+            Self::Null => Cbor::Null,
+        }
+    }
+}
+
+fn integer_from_cbor(cbor: &Cbor, format: IntegerFormat) -> Result<Value> {
+    let out_of_range =
+        || EncodeError::InvalidFormat(format!("Integer out of range for {format}")).into();
+    let wide =
+        |s: &str| EncodeError::InvalidFormat(format!("Invalid {format} integer: {s}")).into();
+    match (format, cbor) {
+        (IntegerFormat::Int128 | IntegerFormat::Uint128, Cbor::Tag(tag, inner)) => {
+            let magnitude = match (*tag, inner.as_ref()) {
+                (TAG_BIGNUM | TAG_NEG_BIGNUM, Cbor::Bytes(bytes)) => bignum_from_bytes(bytes),
+                _ => None,
+            }
+            .ok_or_else(|| mismatch("integer", cbor))?;
+            match (format, *tag) {
+                (IntegerFormat::Uint128, TAG_BIGNUM) => Ok(Value::Uint128(magnitude)),
+                (IntegerFormat::Int128, TAG_BIGNUM) => i128::try_from(magnitude)
+                    .map(Value::Int128)
+                    .map_err(|_| out_of_range()),
+                (IntegerFormat::Int128, _) => i128::try_from(magnitude)
+                    .map(|n| Value::Int128(-1 - n))
+                    .map_err(|_| out_of_range()),
+                _ => Err(out_of_range()),
+            }
+        }
+        // Strings are accepted as in JSON
+        (IntegerFormat::Int128, Cbor::Text(s)) => s.parse().map(Value::Int128).map_err(|_| wide(s)),
+        (IntegerFormat::Uint128, Cbor::Text(s)) => {
+            s.parse().map(Value::Uint128).map_err(|_| wide(s))
+        }
+        (_, Cbor::Integer(i)) => {
+            let i = i128::from(*i);
+            match format {
+                IntegerFormat::Int128 => Ok(Value::Int128(i)),
+                IntegerFormat::Uint128 => u128::try_from(i)
+                    .map(Value::Uint128)
+                    .map_err(|_| out_of_range()),
+                _ => i64::try_from(i)
+                    .map(Value::Integer)
+                    .map_err(|_| out_of_range()),
+            }
+        }
+        _ => Err(mismatch("integer", cbor)),
+    }
+}
+
+fn number_from_cbor(cbor: &Cbor, format: NumberFormat, registry: &SchemaRegistry) -> Result<Value> {
+    let as_f64 = || match cbor {
+        Cbor::Float(f) => Ok(*f),
+        Cbor::Integer(i) => {
+            let i = i128::from(*i);
+            check_integer_precision(i, f64::MANTISSA_DIGITS, registry)?;
+            #[allow(clippy::cast_precision_loss)]
+            Ok(i as f64)
+        }
+        _ => Err(mismatch("number", cbor)),
+    };
+    Ok(match format {
+        NumberFormat::Float => Value::Float(narrow_float(as_f64()?, registry)?),
+        NumberFormat::Double => Value::Double(as_f64()?),
+        // Strings preserve full precision; plain numbers are accepted as well
+        #[cfg(feature = "decimal")]
+        NumberFormat::Decimal => Value::Decimal(match cbor {
+            Cbor::Text(s) => decimal::parse_decimal(s)?,
+            Cbor::Integer(i) => decimal::parse_decimal(&i128::from(*i).to_string())?,
+            Cbor::Float(f) => decimal::parse_decimal(&f.to_string())?,
+            _ => return Err(mismatch("decimal", cbor)),
+        }),
+    })
+}
+
+fn string_from_cbor(cbor: &Cbor, format: StringFormat, registry: &SchemaRegistry) -> Result<Value> {
+    let (tag, inner) = match cbor {
+        Cbor::Tag(tag, inner) => (Some(*tag), inner.as_ref()),
+        _ => (None, cbor),
+    };
+    let fail = || mismatch(&SchemaType::String(format).to_string(), cbor);
+    match (format, tag, inner) {
+        #[cfg(feature = "uuid")]
+        (StringFormat::Uuid, None | Some(TAG_UUID), Cbor::Bytes(bytes)) => {
+            uuid::Uuid::from_slice(bytes)
+                .map(Value::Uuid)
+                .map_err(|_| fail())
+        }
+        #[cfg(not(feature = "uuid"))]
+        (StringFormat::Uuid, None | Some(TAG_UUID), Cbor::Bytes(bytes)) if bytes.len() == 16 => {
+            Ok(Value::Binary(bytes.clone()))
+        }
+        #[cfg(feature = "chrono")]
+        (
+            StringFormat::DateTime | StringFormat::DateTimeTz | StringFormat::Timestamp(_),
+            Some(TAG_EPOCH),
+            epoch,
+        ) => {
+            let dt = datetime_from_epoch(epoch).ok_or_else(fail)?;
+            Ok(if format == StringFormat::DateTimeTz {
+                Value::DateTimeTz(dt.fixed_offset())
+            } else {
+                Value::DateTime(dt)
+            })
+        }
+        #[cfg(feature = "chrono")]
+        (StringFormat::Date, Some(TAG_EPOCH_DAYS), Cbor::Integer(days)) => i64::try_from(*days)
+            .ok()
+            .and_then(|days| {
+                NaiveDate::default().checked_add_signed(chrono::Duration::try_days(days)?)
+            })
+            .map(Value::Date)
+            .ok_or_else(fail),
+        (StringFormat::Ipv4, None | Some(TAG_IPV4), Cbor::Bytes(bytes)) => {
+            <[u8; 4]>::try_from(bytes.as_slice())
+                .map(|octets| Value::Ipv4(Ipv4Addr::from(octets)))
+                .map_err(|_| fail())
+        }
+        (StringFormat::Ipv6, None | Some(TAG_IPV6), Cbor::Bytes(bytes)) => {
+            <[u8; 16]>::try_from(bytes.as_slice())
+                .map(|octets| Value::Ipv6(Ipv6Addr::from(octets)))
+                .map_err(|_| fail())
+        }
+        (StringFormat::Binary, None, Cbor::Bytes(bytes)) => Ok(Value::Binary(bytes.clone())),
+        (StringFormat::GeoPoint, None | Some(TAG_GEO), Cbor::Array(coords)) => {
+            match coords.as_slice() {
+                [lat, lon, ..] => {
+                    let lat = lat.as_float().ok_or_else(fail)?;
+                    let lon = lon.as_float().ok_or_else(fail)?;
+                    Ok(Value::GeoPoint(GeoPoint::new(lat, lon)?))
+                }
+                _ => Err(fail()),
+            }
+        }
+        // Points are also accepted in their `{"lat": .., "lon": ..}` map form
+        (StringFormat::GeoPoint, None, Cbor::Map(entries)) => {
+            let coord = |key| {
+                map_get(entries, key)
+                    .and_then(Cbor::as_float)
+                    .ok_or_else(fail)
+            };
+            Ok(Value::GeoPoint(GeoPoint::new(
+                coord("lat")?,
+                coord("lon")?,
+            )?))
+        }
+        (format, tag, Cbor::Text(s)) if tag.is_none() || tag == text_tag(format) => {
+            string_from_json(s, format, registry)
+        }
+        _ => Err(fail()),
+    }
+}
+
+/// Returns the tag marking the string form of `format`, if any.
+const fn text_tag(format: StringFormat) -> Option<u64> {
+    match format {
+        StringFormat::DateTime | StringFormat::DateTimeTz | StringFormat::Timestamp(_) => {
+            Some(TAG_DATETIME)
+        }
+        StringFormat::Date => Some(TAG_DATE),
+        StringFormat::Uri => Some(TAG_URI),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn datetime_from_epoch(epoch: &Cbor) -> Option<DateTime<Utc>> {
+    match epoch {
+        Cbor::Integer(secs) => DateTime::from_timestamp(i64::try_from(*secs).ok()?, 0),
+        Cbor::Float(secs) if secs.is_finite() => {
+            #[allow(clippy::cast_possible_truncation)]
+            let nanos = (secs * 1e9).round() as i64;
+            Some(DateTime::from_timestamp_nanos(nanos))
+        }
+        _ => None,
+    }
+}
+
+/// Returns the big-endian bytes of `n` without leading zeros.
+fn bignum_bytes(n: u128) -> Cbor {
+    let bytes = n.to_be_bytes();
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    Cbor::Bytes(bytes[start..].to_vec())
+}
+
+fn bignum_from_bytes(bytes: &[u8]) -> Option<u128> {
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let bytes = &bytes[start..];
+    if bytes.len() > 16 {
+        return None;
+    }
+    Some(bytes.iter().fold(0, |n, b| (n << 8) | u128::from(*b)))
+}
+
+fn map_get<'a>(entries: &'a [(Cbor, Cbor)], key: &str) -> Option<&'a Cbor> {
+    entries
+        .iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn object_from_cbor(
+    entries: &[(Cbor, Cbor)],
+    properties: &IndexMap<String, Property>,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    let mut obj = IndexMap::with_capacity(entries.len());
+    for (key, cbor) in entries {
+        let Some((key, prop)) = key.as_text().and_then(|k| properties.get_key_value(k)) else {
+            continue;
+        };
+        // `null` means "absent" unless the property is explicitly a null type
+        if cbor.is_null() && prop.schema_type != SchemaType::Null {
+            continue;
+        }
+        let value = Value::from_cbor_with_registry(cbor, &prop.schema_type, registry)?;
+        obj.insert(intern(key), value);
+    }
+    Ok(Value::Object(obj))
+}
+
+fn one_of_from_cbor(
+    cbor: &Cbor,
+    discriminator: &str,
+    variants: &IndexMap<String, SchemaType>,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    let name = match cbor {
+        // Variants without a payload may be given by name alone
+        Cbor::Text(name) => name.as_str(),
+        Cbor::Map(entries) => map_get(entries, discriminator)
+            .and_then(Cbor::as_text)
+            .ok_or_else(|| SchemaError::MissingField(discriminator.to_owned()))?,
+        _ => return Err(mismatch("object", cbor)),
+    };
+    let payload = variants
+        .get(name)
+        .ok_or_else(|| EncodeError::InvalidFormat(format!("Unknown variant: {name}")))?;
+
+    let mut obj = IndexMap::new();
+    obj.insert(intern(discriminator), Value::String(name.to_owned()));
+    if *payload != SchemaType::Null {
+        if let Value::Object(fields) = Value::from_cbor_with_registry(cbor, payload, registry)? {
+            obj.extend(fields);
+        }
+    }
+    Ok(Value::Object(obj))
+}
+
+fn mismatch(expected: &str, cbor: &Cbor) -> crate::error::Error {
+    let actual = match cbor {
+        Cbor::Null => "null".to_owned(),
+        Cbor::Bool(_) => "boolean".to_owned(),
+        Cbor::Integer(_) => "integer".to_owned(),
+        Cbor::Float(_) => "float".to_owned(),
+        Cbor::Text(_) => "string".to_owned(),
+        Cbor::Bytes(_) => "bytes".to_owned(),
+        Cbor::Array(_) => "array".to_owned(),
+        Cbor::Map(_) => "map".to_owned(),
+        Cbor::Tag(tag, _) => format!("tag {tag}"),
+        _ => "unknown".to_owned(),
+    };
+    EncodeError::TypeMismatch {
+        expected: expected.to_owned(),
+        actual,
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(value: &Value, schema: &SchemaType) -> Value {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&value.to_cbor(), &mut bytes).unwrap();
+        let cbor: Cbor = ciborium::from_reader(bytes.as_slice()).unwrap();
+        Value::from_cbor(&cbor, schema).unwrap()
+    }
+
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    #[test]
+    fn test_formats_are_tagged() {
+        let id = uuid::Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
+        let cbor = Value::Uuid(id).to_cbor();
+        assert_eq!(
+            cbor,
+            Cbor::Tag(TAG_UUID, Box::new(Cbor::Bytes(id.as_bytes().to_vec())))
+        );
+        assert_eq!(
+            Value::from_cbor(&cbor, &SchemaType::string_uuid()).unwrap(),
+            Value::Uuid(id)
+        );
+
+        let dt = crate::formats::datetime::parse_datetime("2024-01-15T10:30:00.5Z").unwrap();
+        let cbor = Value::DateTime(dt).to_cbor();
+        assert_eq!(
+            cbor,
+            Cbor::Tag(
+                TAG_DATETIME,
+                Box::new(Cbor::Text("2024-01-15T10:30:00.500Z".to_owned()))
+            )
+        );
+        assert_eq!(
+            roundtrip(&Value::DateTime(dt), &SchemaType::string_datetime()),
+            Value::DateTime(dt)
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_roundtrip_object() {
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "ip".to_owned(),
+            Property::required(SchemaType::string_ipv6()),
+        );
+        properties.insert("data".to_owned(), Property::optional(SchemaType::binary()));
+        properties.insert(
+            "where".to_owned(),
+            Property::optional(SchemaType::geo_point()),
+        );
+        properties.insert(
+            "day".to_owned(),
+            Property::optional(SchemaType::string_date()),
+        );
+        let schema = SchemaType::object(properties);
+
+        let mut obj = IndexMap::new();
+        obj.insert("ip".into(), Value::Ipv6("::1".parse().unwrap()));
+        obj.insert("data".into(), Value::Binary(vec![0, 1, 255]));
+        obj.insert(
+            "where".into(),
+            Value::GeoPoint(GeoPoint::new(48.8584, 2.2945).unwrap()),
+        );
+        obj.insert(
+            "day".into(),
+            Value::Date(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+        );
+        let value = Value::Object(obj);
+        assert_eq!(roundtrip(&value, &schema), value);
+    }
+
+    #[test]
+    fn test_int128_bignums() {
+        for n in [i128::MIN, -1 - i128::from(u64::MAX), -7, i128::MAX] {
+            assert_eq!(
+                roundtrip(&Value::Int128(n), &SchemaType::int128()),
+                Value::Int128(n)
+            );
+        }
+        assert_eq!(
+            roundtrip(&Value::Uint128(u128::MAX), &SchemaType::uint128()),
+            Value::Uint128(u128::MAX)
+        );
+        assert!(
+            Value::from_cbor(&Value::Uint128(u128::MAX).to_cbor(), &SchemaType::int128()).is_err()
+        );
+        assert!(Value::from_cbor(&Cbor::Integer(300.into()), &SchemaType::int32()).is_ok());
+    }
+
+    #[test]
+    fn test_number_precision() {
+        let float = SchemaType::float();
+        assert_eq!(
+            Value::from_cbor(&Cbor::Float(0.5), &float).unwrap(),
+            Value::Float(0.5)
+        );
+        assert!(Value::from_cbor(&Cbor::Float(0.1), &float).is_err());
+        assert!(Value::from_cbor(&Cbor::Integer(u64::MAX.into()), &SchemaType::double()).is_err());
+
+        let registry = SchemaRegistry::new().with_lossy_floats(true);
+        assert_eq!(
+            Value::from_cbor_with_registry(&Cbor::Float(0.1), &float, &registry).unwrap(),
+            Value::Float(0.1)
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_epoch_forms() {
+        let epoch = Cbor::Tag(TAG_EPOCH, Box::new(Cbor::Integer(86_400.into())));
+        let Value::DateTime(dt) = Value::from_cbor(&epoch, &SchemaType::string_datetime()).unwrap()
+        else {
+            panic!("expected a date-time");
+        };
+        assert_eq!(dt.to_rfc3339(), "1970-01-02T00:00:00+00:00");
+
+        let days = Cbor::Tag(TAG_EPOCH_DAYS, Box::new(Cbor::Integer(1.into())));
+        assert_eq!(
+            Value::from_cbor(&days, &SchemaType::string_date()).unwrap(),
+            Value::Date(NaiveDate::from_ymd_opt(1970, 1, 2).unwrap())
+        );
+
+        // A tag that doesn't match the format is rejected
+        let wrong = Cbor::Tag(TAG_UUID, Box::new(Cbor::Text("2024-01-01".to_owned())));
+        assert!(Value::from_cbor(&wrong, &SchemaType::string_date()).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! Conversions out of [`Value`].
+
+use super::Value;
+use crate::codec::value_type_name;
+use crate::error::{DecodeError, Error, Result};
+#[cfg(feature = "chrono")]
+use crate::formats::datetime;
+use crate::formats::ipaddr;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, NaiveDate, Utc};
+use std::net::{Ipv4Addr, Ipv6Addr};
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+/// Implements `TryFrom<Value>`, taking the listed variants and rejecting
+/// the others as a mismatch with `$expected`.
+macro_rules! impl_try_from_value {
+    ($($ty:ty, $expected:literal { $($pat:pat => $convert:expr),+ $(,)? })*) => {
+        $(
+            impl TryFrom<Value> for $ty {
+                type Error = Error;
+
+                fn try_from(value: Value) -> Result<Self> {
+                    match value {
+                        $($pat => $convert,)+
+                        other => Err(mismatch($expected, &other)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_try_from_value! {
+    bool, "boolean" {
+        Value::Boolean(b) => Ok(b),
+    }
+    i32, "i32" {
+        Value::Integer(n) => i32::try_from(n).map_err(|_| out_of_range(n, "i32")),
+    }
+    i64, "i64" {
+        Value::Integer(n) => Ok(n),
+    }
+    f32, "float" {
+        Value::Float(f) => Ok(f),
+    }
+    f64, "double" {
+        Value::Double(d) => Ok(d),
+        Value::Float(f) => Ok(f64::from(f)),
+    }
+    String, "string" {
+        Value::String(s) => Ok(s),
+    }
+    Ipv4Addr, "ipv4" {
+        Value::Ipv4(ip) => Ok(ip),
+        Value::String(s) => Ok(ipaddr::parse_ipv4(&s)?),
+    }
+    Ipv6Addr, "ipv6" {
+        Value::Ipv6(ip) => Ok(ip),
+        Value::String(s) => Ok(ipaddr::parse_ipv6(&s)?),
+    }
+    Vec<u8>, "binary" {
+        Value::Binary(bytes) => Ok(bytes),
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl_try_from_value! {
+    Uuid, "uuid" {
+        Value::Uuid(u) => Ok(u),
+        Value::String(s) => Ok(crate::formats::uuid::parse_uuid(&s)?),
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl_try_from_value! {
+    DateTime<Utc>, "datetime" {
+        Value::DateTime(dt) => Ok(dt),
+        Value::DateTimeTz(dt) => Ok(dt.with_timezone(&Utc)),
+        Value::String(s) => Ok(datetime::parse_datetime(&s)?),
+    }
+    NaiveDate, "date" {
+        Value::Date(d) => Ok(d),
+        Value::String(s) => Ok(datetime::parse_date(&s)?),
+    }
+}
+
+fn mismatch(expected: &str, value: &Value) -> Error {
+    DecodeError::SchemaMismatch(format!(
+        "expected {expected}, got {}",
+        value_type_name(value)
+    ))
+    .into()
+}
+
+fn out_of_range(n: i64, target: &str) -> Error {
+    DecodeError::InvalidData(format!("Integer {n} out of range for {target}")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_from_value() {
+        assert!(bool::try_from(Value::Boolean(true)).unwrap());
+        assert_eq!(i32::try_from(Value::Integer(-7)).unwrap(), -7);
+        assert!(f64::try_from(Value::Float(1.5)).is_ok_and(|f| (f - 1.5).abs() < f64::EPSILON));
+        assert_eq!(String::try_from(Value::from("a")).unwrap(), "a");
+        assert_eq!(
+            Vec::<u8>::try_from(Value::Binary(vec![1, 2])).unwrap(),
+            [1, 2]
+        );
+
+        // Format types also parse their string form
+        #[cfg(feature = "uuid")]
+        {
+            let id = "550e8400-e29b-41d4-a716-446655440000";
+            assert_eq!(
+                Uuid::try_from(Value::from(id)).unwrap(),
+                Uuid::parse_str(id).unwrap()
+            );
+        }
+        assert_eq!(
+            Ipv4Addr::try_from(Value::from("10.0.0.1")).unwrap(),
+            Ipv4Addr::new(10, 0, 0, 1)
+        );
+        #[cfg(feature = "chrono")]
+        {
+            let dt = DateTime::try_from(Value::from("2024-01-01T12:00:00+02:00")).unwrap();
+            assert_eq!(dt.to_rfc3339(), "2024-01-01T10:00:00+00:00");
+            assert!(NaiveDate::try_from(Value::from("2023-02-30")).is_err());
+        }
+
+        let err = i32::try_from(Value::from("1")).unwrap_err();
+        assert_eq!(err.to_string(), "Schema mismatch: expected i32, got string");
+        let err = i32::try_from(Value::Integer(1 << 40)).unwrap_err();
+        assert!(err.to_string().contains("out of range for i32"));
+        assert!(Ipv6Addr::try_from(Value::Null).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! Application values carried inside a [`Value`].
+
+use super::Value;
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+/// A value of an application type, together with the plain [`Value`] it
+/// stands for.
+///
+/// [Decode plugins](crate::plugin::DecodePlugin) wrap the types they produce
+/// in [`Value::Custom`]. Encoders, conversions and comparisons see the
+/// plain representation, so a custom value encodes to the same bytes it was
+/// decoded from and compares equal to the value it stands for.
+///
+/// ```rust
+/// use compactr::value::CustomValue;
+/// use compactr::Value;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Cents(i64);
+///
+/// let value = Value::Custom(CustomValue::new(Cents(250), Value::Integer(250)));
+/// assert_eq!(value.downcast_ref::<Cents>(), Some(&Cents(250)));
+/// assert_eq!(value.as_custom().map(CustomValue::repr), Some(&Value::Integer(250)));
+/// ```
+#[derive(Clone)]
+pub struct CustomValue {
+    value: Arc<dyn Any + Send + Sync>,
+    repr: Box<Value>,
+}
+
+impl CustomValue {
+    /// Wraps `value`, which `repr` represents.
+    pub fn new<T: Any + Send + Sync>(value: T, repr: Value) -> Self {
+        Self {
+            value: Arc::new(value),
+            repr: Box::new(repr),
+        }
+    }
+
+    /// Returns the application value if it is a `T`.
+    #[must_use]
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+
+    /// Returns whether the application value is a `T`.
+    #[must_use]
+    pub fn is<T: Any>(&self) -> bool {
+        self.value.is::<T>()
+    }
+
+    /// Returns the plain representation of the value.
+    #[must_use]
+    pub fn repr(&self) -> &Value {
+        &self.repr
+    }
+
+    /// Returns the plain representation of the value, dropping the
+    /// application value.
+    #[must_use]
+    pub fn into_repr(self) -> Value {
+        *self.repr
+    }
+}
+
+impl fmt::Debug for CustomValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomValue")
+            .field("repr", &self.repr)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,156 @@
+//! Equality and hashing of values.
+//!
+//! Floats, doubles and geographic points compare by bit pattern rather than
+//! numerically, which makes equality reflexive and lets values implement
+//! [`Eq`] and [`Hash`]: `NaN` equals a `NaN` with the same bits, while `0.0`
+//! and `-0.0` differ. Encoding preserves bit patterns, so a value still
+//! equals itself after a round trip. Objects are equal when they hold the
+//! same properties, in any order. Custom values compare as their plain
+//! representations.
+
+use super::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Boolean(a), Self::Boolean(b)) => a == b,
+            (Self::Integer(a), Self::Integer(b)) => a == b,
+            (Self::Int128(a), Self::Int128(b)) => a == b,
+            (Self::Uint128(a), Self::Uint128(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::Double(a), Self::Double(b)) => a.to_bits() == b.to_bits(),
+            #[cfg(feature = "decimal")]
+            (Self::Decimal(a), Self::Decimal(b)) => a == b,
+            (Self::String(a), Self::String(b)) => a == b,
+            #[cfg(feature = "uuid")]
+            (Self::Uuid(a), Self::Uuid(b)) => a == b,
+            #[cfg(feature = "chrono")]
+            (Self::DateTime(a), Self::DateTime(b)) => a == b,
+            #[cfg(feature = "chrono")]
+            (Self::DateTimeTz(a), Self::DateTimeTz(b)) => a == b,
+            #[cfg(feature = "chrono")]
+            (Self::Date(a), Self::Date(b)) => a == b,
+            #[cfg(feature = "chrono")]
+            (Self::Time(a), Self::Time(b)) => a == b,
+            (Self::GeoPoint(a), Self::GeoPoint(b)) => {
+                a.lat().to_bits() == b.lat().to_bits() && a.lon().to_bits() == b.lon().to_bits()
+            }
+            (Self::Ipv4(a), Self::Ipv4(b)) => a == b,
+            (Self::Ipv6(a), Self::Ipv6(b)) => a == b,
+            (Self::SocketAddr(a), Self::SocketAddr(b)) => a == b,
+            (Self::Binary(a), Self::Binary(b)) => a == b,
+            (Self::Array(a), Self::Array(b)) => a == b,
+            (Self::Object(a), Self::Object(b)) => a == b,
+            (Self::Custom(custom), other) | (other, Self::Custom(custom)) => custom.repr() == other,
+            (Self::Null, Self::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if let Self::Custom(custom) = self {
+            return custom.repr().hash(state);
+        }
+        mem::discriminant(self).hash(state);
+        match self {
+            Self::Boolean(b) => b.hash(state),
+            Self::Integer(i) => i.hash(state),
+            Self::Int128(i) => i.hash(state),
+            Self::Uint128(u) => u.hash(state),
+            Self::Float(f) => f.to_bits().hash(state),
+            Self::Double(d) => d.to_bits().hash(state),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(d) => d.hash(state),
+            Self::String(s) => s.hash(state),
+            #[cfg(feature = "uuid")]
+            Self::Uuid(u) => u.hash(state),
+            #[cfg(feature = "chrono")]
+            Self::DateTime(dt) => dt.hash(state),
+            // Hashes the instant, which is what equality compares
+            #[cfg(feature = "chrono")]
+            Self::DateTimeTz(dt) => dt.hash(state),
+            #[cfg(feature = "chrono")]
+            Self::Date(d) => d.hash(state),
+            #[cfg(feature = "chrono")]
+            Self::Time(t) => t.hash(state),
+            Self::GeoPoint(p) => {
+                p.lat().to_bits().hash(state);
+                p.lon().to_bits().hash(state);
+            }
+            Self::Ipv4(ip) => ip.hash(state),
+            Self::Ipv6(ip) => ip.hash(state),
+            Self::SocketAddr(addr) => addr.hash(state),
+            Self::Binary(b) => b.hash(state),
+            Self::Array(items) => items.hash(state),
+            Self::Object(fields) => {
+                // Summing the hashes of the fields ignores their order, like
+                // equality does
+                let sum = fields
+                    .iter()
+                    .map(|field| {
+                        let mut hasher = DefaultHasher::new();
+                        field.hash(&mut hasher);
+                        hasher.finish()
+                    })
+                    .fold(0_u64, u64::wrapping_add);
+                fields.len().hash(state);
+                sum.hash(state);
+            }
+            // Hashed as its representation, above
+            Self::Custom(_) => unreachable!(),
+            Self::Null => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::CustomValue;
+    use std::collections::HashSet;
+
+    fn hash(value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_eq_and_hash() {
+        assert_eq!(Value::Double(f64::NAN), Value::Double(f64::NAN));
+        assert_ne!(Value::Double(0.0), Value::Double(-0.0));
+        assert_ne!(Value::Float(1.0), Value::Double(1.0));
+        assert_ne!(Value::Integer(1), Value::Int128(1));
+
+        let a = Value::object([("x", 1), ("y", 2)]);
+        let b = Value::object([("y", 2), ("x", 1)]);
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(hash(&a), hash(&Value::object([("x", 2), ("y", 1)])));
+
+        let custom = Value::Custom(CustomValue::new((), a.clone()));
+        assert_eq!(custom, b);
+        assert_eq!(b, custom);
+        assert_eq!(hash(&custom), hash(&b));
+
+        let values: HashSet<_> = [
+            Value::Double(f64::NAN),
+            Value::Double(f64::NAN),
+            Value::Double(0.0),
+            Value::Double(-0.0),
+            a,
+            b,
+            Value::array([Value::Null]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(values.len(), 5);
+    }
+}
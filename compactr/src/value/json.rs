@@ -0,0 +1,541 @@
+//! Conversion between [`Value`] and `serde_json::Value`.
+
+use super::Value;
+use crate::codec::{check_integer_precision, check_integer_range, intern};
+use crate::error::{EncodeError, Result, SchemaError};
+#[cfg(feature = "chrono")]
+use crate::formats::datetime;
+#[cfg(feature = "decimal")]
+use crate::formats::decimal;
+use crate::formats::{geo, ipaddr, uri, uuid};
+use crate::schema::{
+    IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType, StringFormat,
+};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+#[cfg(feature = "chrono")]
+use chrono::SecondsFormat;
+use indexmap::IndexMap;
+use serde_json::{Map, Number};
+
+impl Value {
+    /// Converts a JSON value into a `Value` guided by the given schema.
+    ///
+    /// Formatted strings (UUID, date-time, date, IP addresses) are parsed into
+    /// their dedicated variants and binary data is decoded from Base64.
+    /// Object properties that are not part of the schema, or that are `null`,
+    /// are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON value doesn't match the schema.
+    pub fn from_json(json: &serde_json::Value, schema: &SchemaType) -> Result<Self> {
+        Self::from_json_with_registry(json, schema, SchemaRegistry::empty())
+    }
+
+    /// Converts a JSON value into a `Value` with a schema registry for
+    /// resolving references.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON value doesn't match the schema or a
+    /// reference cannot be resolved.
+    pub fn from_json_with_registry(
+        json: &serde_json::Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        match schema {
+            SchemaType::Boolean => json
+                .as_bool()
+                .map(Self::Boolean)
+                .ok_or_else(|| mismatch("boolean", json)),
+            SchemaType::Integer(format) => integer_from_json(json, *format),
+            SchemaType::Number(format) => number_from_json(json, *format, registry),
+            // Points are also accepted in their `{"lat": .., "lon": ..}` object form
+            SchemaType::String(StringFormat::GeoPoint) if json.is_object() => {
+                geo_point_from_json(json)
+            }
+            SchemaType::String(format) => {
+                let s = json.as_str().ok_or_else(|| mismatch("string", json))?;
+                string_from_json(s, *format, registry)
+            }
+            SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+                let arr = json.as_array().ok_or_else(|| mismatch("array", json))?;
+                arr.iter()
+                    .map(|item| Self::from_json_with_registry(item, items, registry))
+                    .collect::<Result<Vec<_>>>()
+                    .map(Self::Array)
+            }
+            SchemaType::Object(properties) => {
+                let map = json.as_object().ok_or_else(|| mismatch("object", json))?;
+                object_from_json(map, properties, registry)
+            }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::from_json_with_registry(json, &resolved, registry)
+            }
+            SchemaType::Enum(variants) => {
+                let s = json.as_str().ok_or_else(|| mismatch("string", json))?;
+                if !variants.iter().any(|v| v == s) {
+                    return Err(EncodeError::InvalidFormat(format!("Unknown variant: {s}")).into());
+                }
+                Ok(Self::String(s.to_owned()))
+            }
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            } => one_of_from_json(json, discriminator, variants, registry),
+            SchemaType::Null => {
+                if json.is_null() {
+                    Ok(Self::Null)
+                } else {
+                    Err(mismatch("null", json))
+                }
+            }
+        }
+    }
+
+    /// Converts this value into a JSON value.
+    ///
+    /// Formatted variants are rendered as their canonical string forms
+    /// (RFC 3339 for date-times, Base64 for binary data). Non-finite floats
+    /// become `null` since JSON cannot represent them.
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Boolean(b) => serde_json::Value::Bool(*b),
+            Self::Integer(i) => serde_json::Value::from(*i),
+            Self::Int128(i) => i64::try_from(*i)
+                .map_or_else(|_| serde_json::Value::String(i.to_string()), Into::into),
+            Self::Uint128(u) => u64::try_from(*u)
+                .map_or_else(|_| serde_json::Value::String(u.to_string()), Into::into),
+            Self::Float(f) => float_to_json(f64::from(*f)),
+            Self::Double(d) => float_to_json(*d),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(d) => serde_json::Value::String(d.to_string()),
+            Self::String(s) => serde_json::Value::String(s.clone()),
+            #[cfg(feature = "uuid")]
+            Self::Uuid(u) => serde_json::Value::String(u.to_string()),
+            #[cfg(feature = "chrono")]
+            Self::DateTime(dt) => {
+                serde_json::Value::String(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+            #[cfg(feature = "chrono")]
+            Self::DateTimeTz(dt) => {
+                serde_json::Value::String(dt.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+            }
+            #[cfg(feature = "chrono")]
+            Self::Date(d) => serde_json::Value::String(d.format("%Y-%m-%d").to_string()),
+            #[cfg(feature = "chrono")]
+            Self::Time(t) => serde_json::Value::String(t.to_string()),
+            Self::GeoPoint(p) => {
+                let mut map = Map::new();
+                map.insert("lat".to_owned(), float_to_json(p.lat()));
+                map.insert("lon".to_owned(), float_to_json(p.lon()));
+                serde_json::Value::Object(map)
+            }
+            Self::Ipv4(ip) => serde_json::Value::String(ip.to_string()),
+            Self::Ipv6(ip) => serde_json::Value::String(ip.to_string()),
+            Self::SocketAddr(addr) => serde_json::Value::String(addr.to_string()),
+            Self::Binary(data) => serde_json::Value::String(BASE64.encode(data)),
+            Self::Array(items) => {
+                serde_json::Value::Array(items.iter().map(Self::to_json).collect())
+            }
+            Self::Object(obj) => serde_json::Value::Object(
+                obj.iter()
+                    .map(|(k, v)| (k.to_string(), v.to_json()))
+                    .collect::<Map<_, _>>(),
+            ),
+            Self::Custom(custom) => custom.repr().to_json(),
+            Self::Null => serde_json::Value::Null,
+        }
+    }
+}
+
+fn integer_from_json(json: &serde_json::Value, format: IntegerFormat) -> Result<Value> {
+    // 128-bit integers may not fit in a JSON number, so strings are accepted too
+    let wide =
+        |s: &str| EncodeError::InvalidFormat(format!("Invalid {format} integer: {s}")).into();
+    match (format, json) {
+        (IntegerFormat::Int128, serde_json::Value::String(s)) => {
+            s.parse().map(Value::Int128).map_err(|_| wide(s))
+        }
+        (IntegerFormat::Uint128, serde_json::Value::String(s)) => {
+            s.parse().map(Value::Uint128).map_err(|_| wide(s))
+        }
+        (IntegerFormat::Int128, _) => json
+            .as_i64()
+            .map(|i| Value::Int128(i128::from(i)))
+            .or_else(|| json.as_u64().map(|u| Value::Int128(i128::from(u))))
+            .ok_or_else(|| mismatch("integer", json)),
+        (IntegerFormat::Uint128, _) => json
+            .as_u64()
+            .map(|u| Value::Uint128(u128::from(u)))
+            .ok_or_else(|| mismatch("integer", json)),
+        _ => {
+            let i = json
+                .as_i64()
+                .map(i128::from)
+                .or_else(|| json.as_u64().map(i128::from))
+                .ok_or_else(|| mismatch("integer", json))?;
+            check_integer_range(i, format)?;
+            // Every format but the 128-bit ones fits in an i64
+            Ok(Value::Integer(i64::try_from(i).unwrap_or_default()))
+        }
+    }
+}
+
+fn number_from_json(
+    json: &serde_json::Value,
+    format: NumberFormat,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    let as_f64 = || json.as_f64().ok_or_else(|| mismatch("number", json));
+    Ok(match format {
+        // Parsing the decimal text rounds once, to the nearest float, where
+        // going through a double could round twice
+        NumberFormat::Float => {
+            let n = json.as_number().ok_or_else(|| mismatch("number", json))?;
+            match n.to_string().parse::<f32>() {
+                Ok(f) if f.is_finite() => Value::Float(f),
+                _ => {
+                    return Err(EncodeError::InvalidFormat(format!(
+                        "Number {n} out of range for float"
+                    ))
+                    .into())
+                }
+            }
+        }
+        NumberFormat::Double => {
+            let int = json.as_i64().map(i128::from);
+            if let Some(i) = int.or_else(|| json.as_u64().map(i128::from)) {
+                check_integer_precision(i, f64::MANTISSA_DIGITS, registry)?;
+            }
+            Value::Double(as_f64()?)
+        }
+        // Strings preserve full precision; plain numbers are accepted as well
+        #[cfg(feature = "decimal")]
+        NumberFormat::Decimal => Value::Decimal(match json {
+            serde_json::Value::String(s) => decimal::parse_decimal(s)?,
+            serde_json::Value::Number(n) => decimal::parse_decimal(&n.to_string())?,
+            _ => return Err(mismatch("decimal", json)),
+        }),
+    })
+}
+
+pub(crate) fn string_from_json(
+    s: &str,
+    format: StringFormat,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    Ok(match format {
+        StringFormat::Plain | StringFormat::Long => Value::String(s.to_owned()),
+        #[cfg(feature = "uuid")]
+        StringFormat::Uuid => Value::Uuid(uuid::parse_uuid(s)?),
+        #[cfg(not(feature = "uuid"))]
+        StringFormat::Uuid => Value::Binary(uuid::parse_uuid_bytes(s)?.to_vec()),
+        #[cfg(feature = "chrono")]
+        StringFormat::DateTime | StringFormat::Timestamp(_) => {
+            Value::DateTime(datetime::parse_datetime(s)?)
+        }
+        #[cfg(feature = "chrono")]
+        StringFormat::DateTimeTz => Value::DateTimeTz(datetime::parse_datetime_tz(s)?),
+        #[cfg(feature = "chrono")]
+        StringFormat::Date => Value::Date(datetime::parse_date(s)?),
+        #[cfg(feature = "chrono")]
+        StringFormat::Time => Value::Time(datetime::parse_time(s)?),
+        #[cfg(not(feature = "chrono"))]
+        StringFormat::DateTime
+        | StringFormat::DateTimeTz
+        | StringFormat::Timestamp(_)
+        | StringFormat::Date
+        | StringFormat::Time => {
+            return Err(EncodeError::InvalidFormat(format!(
+                "Parsing {} requires the `chrono` feature",
+                SchemaType::String(format)
+            ))
+            .into())
+        }
+        StringFormat::Uri => {
+            uri::validate_uri(s)?;
+            Value::String(s.to_owned())
+        }
+        StringFormat::GeoPoint => Value::GeoPoint(geo::parse_geo_point(s)?),
+        StringFormat::Ipv4 => Value::Ipv4(ipaddr::parse_ipv4(s)?),
+        StringFormat::Ipv6 => Value::Ipv6(ipaddr::parse_ipv6(s)?),
+        StringFormat::SocketAddr => Value::SocketAddr(ipaddr::parse_socket_addr(s)?),
+        StringFormat::Custom(name) => registry.formats().resolve(name)?.parse(s)?,
+        StringFormat::Binary => Value::Binary(
+            BASE64
+                .decode(s)
+                .map_err(|e| EncodeError::InvalidFormat(format!("Invalid base64: {e}")))?,
+        ),
+    })
+}
+
+fn geo_point_from_json(json: &serde_json::Value) -> Result<Value> {
+    let coord = |key| {
+        json.get(key)
+            .and_then(serde_json::Value::as_f64)
+            .ok_or_else(|| mismatch("geopoint", json))
+    };
+    Ok(Value::GeoPoint(geo::GeoPoint::new(
+        coord("lat")?,
+        coord("lon")?,
+    )?))
+}
+
+fn object_from_json(
+    map: &Map<String, serde_json::Value>,
+    properties: &IndexMap<String, Property>,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    let mut obj = IndexMap::with_capacity(map.len());
+    for (key, json) in map {
+        // Keys may still use a property's old name
+        let found = properties.get_key_value(key).or_else(|| {
+            properties
+                .iter()
+                .find(|(_, prop)| prop.aliases.iter().any(|alias| alias == key))
+        });
+        let Some((name, prop)) = found else {
+            continue;
+        };
+        // `null` means "absent" unless the property is explicitly a null type
+        if json.is_null() && prop.schema_type != SchemaType::Null {
+            continue;
+        }
+        let value = Value::from_json_with_registry(json, &prop.schema_type, registry)?;
+        obj.insert(intern(name), value);
+    }
+    Ok(Value::Object(obj))
+}
+
+fn one_of_from_json(
+    json: &serde_json::Value,
+    discriminator: &str,
+    variants: &IndexMap<String, SchemaType>,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    let name = json
+        .get(discriminator)
+        .and_then(serde_json::Value::as_str)
+        .ok_or_else(|| SchemaError::MissingField(discriminator.to_owned()))?;
+    let payload = variants
+        .get(name)
+        .ok_or_else(|| EncodeError::InvalidFormat(format!("Unknown variant: {name}")))?;
+
+    let mut obj = IndexMap::new();
+    obj.insert(intern(discriminator), Value::String(name.to_owned()));
+    if *payload != SchemaType::Null {
+        if let Value::Object(fields) = Value::from_json_with_registry(json, payload, registry)? {
+            obj.extend(fields);
+        }
+    }
+    Ok(Value::Object(obj))
+}
+
+fn float_to_json(f: f64) -> serde_json::Value {
+    Number::from_f64(f).map_or(serde_json::Value::Null, serde_json::Value::Number)
+}
+
+fn mismatch(expected: &str, json: &serde_json::Value) -> crate::error::Error {
+    let actual = match json {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "boolean",
+        serde_json::Value::Number(_) => "number",
+        serde_json::Value::String(_) => "string",
+        serde_json::Value::Array(_) => "array",
+        serde_json::Value::Object(_) => "object",
+    };
+    EncodeError::TypeMismatch {
+        expected: expected.to_owned(),
+        actual: actual.to_owned(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    fn user_schema() -> SchemaType {
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "id".to_owned(),
+            Property::required(SchemaType::string_uuid()),
+        );
+        properties.insert("name".to_owned(), Property::required(SchemaType::string()));
+        properties.insert(
+            "created_at".to_owned(),
+            Property::optional(SchemaType::string_datetime()),
+        );
+        properties.insert(
+            "ip".to_owned(),
+            Property::optional(SchemaType::string_ipv4()),
+        );
+        SchemaType::object(properties)
+    }
+
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    #[test]
+    fn test_from_json_parses_formats() {
+        let json = json!({
+            "id": "550e8400-e29b-41d4-a716-446655440000",
+            "name": "Alice",
+            "created_at": "2024-01-15T10:30:00Z",
+            "ip": "10.0.0.1",
+            "unknown": 1
+        });
+
+        let value = Value::from_json(&json, &user_schema()).unwrap();
+        assert!(matches!(value.get("id"), Some(Value::Uuid(_))));
+        assert!(matches!(value.get("created_at"), Some(Value::DateTime(_))));
+        assert!(matches!(value.get("ip"), Some(Value::Ipv4(_))));
+        assert!(value.get("unknown").is_none());
+    }
+
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    #[test]
+    fn test_json_roundtrip() {
+        let json = json!({
+            "id": "550e8400-e29b-41d4-a716-446655440000",
+            "name": "Alice",
+            "created_at": "2024-01-15T10:30:00Z",
+            "ip": "10.0.0.1"
+        });
+
+        let value = Value::from_json(&json, &user_schema()).unwrap();
+        assert_eq!(value.to_json(), json);
+    }
+
+    #[test]
+    fn test_binary_as_base64() {
+        let value = Value::from_json(&json!("AQID"), &SchemaType::binary()).unwrap();
+        assert_eq!(value, Value::Binary(vec![1, 2, 3]));
+        assert_eq!(value.to_json(), json!("AQID"));
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimal_from_json() {
+        let schema = SchemaType::decimal();
+        let value = Value::from_json(&json!("1234.5678"), &schema).unwrap();
+        assert_eq!(value.to_json(), json!("1234.5678"));
+        assert!(Value::from_json(&json!(12.5), &schema).is_ok());
+    }
+
+    #[test]
+    fn test_int128_json_as_string() {
+        let big = i128::from(i64::MAX) * 4;
+        let json = Value::Int128(big).to_json();
+        assert_eq!(json, json!(big.to_string()));
+        assert_eq!(
+            Value::from_json(&json, &SchemaType::int128()).unwrap(),
+            Value::Int128(big)
+        );
+        assert_eq!(Value::Uint128(7).to_json(), json!(7));
+    }
+
+    #[test]
+    fn test_geo_point_json_forms() {
+        let schema = SchemaType::geo_point();
+        let from_object =
+            Value::from_json(&json!({"lat": 48.8584, "lon": 2.2945}), &schema).unwrap();
+        let from_string = Value::from_json(&json!("48.8584,2.2945"), &schema).unwrap();
+
+        assert_eq!(from_object, from_string);
+        assert_eq!(
+            from_object.to_json(),
+            json!({"lat": 48.8584, "lon": 2.2945})
+        );
+        assert!(Value::from_json(&json!({"lat": 91.0, "lon": 0.0}), &schema).is_err());
+    }
+
+    #[test]
+    fn test_one_of_json() {
+        let mut variants = IndexMap::new();
+        variants.insert("none".to_owned(), SchemaType::null());
+        variants.insert(
+            "some".to_owned(),
+            SchemaType::object([("n", Property::required(SchemaType::int32()))]),
+        );
+        let schema = SchemaType::one_of("type", variants);
+
+        let json = json!({"type": "some", "n": 3});
+        let value = Value::from_json(&json, &schema).unwrap();
+        assert_eq!(value.to_json(), json);
+        assert_eq!(
+            Value::from_json(&json!({"type": "none"}), &schema)
+                .unwrap()
+                .to_json(),
+            json!({"type": "none"})
+        );
+        assert!(Value::from_json(&json!({"type": "other"}), &schema).is_err());
+        assert!(Value::from_json(&json!({"n": 3}), &schema).is_err());
+    }
+
+    #[test]
+    fn test_float_from_json() {
+        let float = SchemaType::float();
+        assert_eq!(
+            Value::from_json(&json!(0.1), &float).unwrap(),
+            Value::Float(0.1)
+        );
+        assert_eq!(
+            Value::from_json(&json!(16_777_217), &float).unwrap(),
+            Value::Float(16_777_216.0)
+        );
+        assert!(Value::from_json(&json!(1e300), &float).is_err());
+    }
+
+    #[test]
+    fn test_double_from_json() {
+        let double = SchemaType::double();
+        let exact = 1_u64 << 53;
+        assert_eq!(
+            Value::from_json(&json!(exact), &double).unwrap(),
+            Value::Double(9_007_199_254_740_992.0)
+        );
+        assert!(Value::from_json(&json!(exact + 1), &double).is_err());
+        assert!(Value::from_json(&json!(u64::MAX), &double).is_err());
+
+        let registry = SchemaRegistry::new().with_lossy_floats(true);
+        assert_eq!(
+            Value::from_json_with_registry(&json!(exact + 1), &double, &registry).unwrap(),
+            Value::Double(9_007_199_254_740_992.0)
+        );
+    }
+
+    #[test]
+    fn test_integer_from_json_range() {
+        for (json, schema) in [
+            (json!(300), SchemaType::int8()),
+            (json!(-1), SchemaType::uint16()),
+            (json!(1_u64 << 40), SchemaType::int32()),
+            (json!(u64::MAX), SchemaType::int64()),
+        ] {
+            assert!(
+                Value::from_json(&json, &schema).is_err(),
+                "{json} {schema:?}"
+            );
+        }
+        assert_eq!(
+            Value::from_json(&json!(-128), &SchemaType::int8()).unwrap(),
+            Value::Integer(-128)
+        );
+        assert_eq!(
+            Value::from_json(&json!(65535), &SchemaType::uint16()).unwrap(),
+            Value::Integer(65535)
+        );
+    }
+
+    #[test]
+    fn test_from_json_type_mismatch() {
+        let result = Value::from_json(&json!("42"), &SchemaType::int32());
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,382 @@
+//! Deep merging of values and merge patches.
+
+use super::Value;
+use crate::codec::Encoder;
+use crate::error::{EncodeError, Result};
+use crate::schema::{SchemaRegistry, SchemaType};
+use bytes::BytesMut;
+
+/// How arrays are combined when both sides of a merge hold an array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ArrayMergeStrategy {
+    /// The incoming array replaces the existing one
+    #[default]
+    Replace,
+    /// The incoming elements are appended to the existing array
+    Concat,
+}
+
+impl Value {
+    /// Deep-merges `other` into this value, replacing arrays.
+    ///
+    /// See [`Value::merge_with`] for the merge rules.
+    pub fn merge(&mut self, other: &Value) {
+        self.merge_with(other, ArrayMergeStrategy::Replace);
+    }
+
+    /// Deep-merges `other` into this value using the given array strategy.
+    ///
+    /// When both values are objects, each property of `other` is merged
+    /// recursively into the matching property of `self` (or inserted if
+    /// missing). When both values are arrays they are combined according to
+    /// `arrays`. In every other case `other` replaces `self`.
+    pub fn merge_with(&mut self, other: &Value, arrays: ArrayMergeStrategy) {
+        match (self, other) {
+            (Self::Object(base), Self::Object(overrides)) => {
+                for (key, value) in overrides {
+                    match base.get_mut(key) {
+                        Some(existing) => existing.merge_with(value, arrays),
+                        None => {
+                            base.insert(key.clone(), value.clone());
+                        }
+                    }
+                }
+            }
+            (Self::Array(base), Self::Array(items)) if arrays == ArrayMergeStrategy::Concat => {
+                base.extend(items.iter().cloned());
+            }
+            (this, other) => *this = other.clone(),
+        }
+    }
+
+    /// Applies an [RFC 7396](https://www.rfc-editor.org/rfc/rfc7396) merge
+    /// patch to this value.
+    ///
+    /// When `patch` is an object, each of its properties is merge-patched
+    /// into the matching property of `self`, which becomes an object if it
+    /// isn't one, except that [`Value::Null`] removes the property. Any other
+    /// patch replaces `self`. A merge patch therefore can't set a property to
+    /// null.
+    ///
+    /// ```rust
+    /// use compactr::Value;
+    ///
+    /// let mut user = Value::object([("name", Value::from("ann")), ("nick", Value::from("a"))]);
+    /// user.merge_patch(&Value::object([("nick", Value::Null), ("age", Value::from(41))]));
+    /// assert_eq!(user, Value::object([("name", Value::from("ann")), ("age", Value::from(41))]));
+    /// ```
+    pub fn merge_patch(&mut self, patch: &Value) {
+        let Self::Object(patch) = patch else {
+            *self = patch.clone();
+            return;
+        };
+        if let Self::Custom(custom) = self {
+            *self = custom.repr().clone();
+        }
+        if !self.is_object() {
+            *self = Self::Object(indexmap::IndexMap::new());
+        }
+        if let Self::Object(target) = self {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.shift_remove(key);
+                } else {
+                    target
+                        .entry(key.clone())
+                        .or_insert(Self::Null)
+                        .merge_patch(value);
+                }
+            }
+        }
+    }
+
+    /// Applies a merge patch like [`Value::merge_patch`], then checks that
+    /// the result is valid for `schema`, as PATCH endpoints do.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error encoding the result with `schema` would, such as a
+    /// type mismatch or a missing required property, or an error if the
+    /// result has a property the schema doesn't declare, and leaves this
+    /// value unchanged.
+    pub fn merge_patch_validated(
+        &mut self,
+        patch: &Value,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        let mut patched = self.clone();
+        patched.merge_patch(patch);
+        Encoder::encode_into(&mut BytesMut::new(), &patched, schema, registry)?;
+        check_declared(&patched, schema, registry, None)?;
+        *self = patched;
+        Ok(())
+    }
+}
+
+/// Checks that every property of `value` is declared by `schema`, which
+/// encoding alone doesn't, as it skips undeclared properties. Objects may
+/// also hold the `discriminator` of the `oneOf` they are the payload of.
+fn check_declared(
+    value: &Value,
+    schema: &SchemaType,
+    registry: &SchemaRegistry,
+    discriminator: Option<&str>,
+) -> Result<()> {
+    match (schema, value) {
+        (_, Value::Custom(custom)) => {
+            check_declared(custom.repr(), schema, registry, discriminator)?;
+        }
+        (SchemaType::Reference(name), value) => {
+            let resolved = registry.resolve_shared(name)?;
+            check_declared(value, &resolved, registry, discriminator)?;
+        }
+        (SchemaType::Object(properties), Value::Object(obj)) => {
+            for (name, value) in obj {
+                if discriminator == Some(&**name) {
+                    continue;
+                }
+                let property = properties.get(&**name).or_else(|| {
+                    properties
+                        .values()
+                        .find(|property| property.aliases.iter().any(|alias| **alias == **name))
+                });
+                let Some(property) = property else {
+                    return Err(unknown_property(name));
+                };
+                check_declared(value, &property.schema_type, registry, None)?;
+            }
+        }
+        // Variants without a payload hold only the discriminator
+        (SchemaType::Null, Value::Object(obj)) if discriminator.is_some() => {
+            if let Some(name) = obj.keys().find(|name| Some(&***name) != discriminator) {
+                return Err(unknown_property(name));
+            }
+        }
+        (SchemaType::Array(items) | SchemaType::RunLengthArray(items), Value::Array(values)) => {
+            for value in values {
+                check_declared(value, items, registry, None)?;
+            }
+        }
+        (
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            },
+            value,
+        ) => {
+            let payload = value
+                .get(discriminator)
+                .and_then(Value::as_str)
+                .and_then(|name| variants.get(name));
+            if let Some(payload) = payload {
+                check_declared(value, payload, registry, Some(discriminator))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn unknown_property(name: &str) -> crate::error::Error {
+    EncodeError::InvalidFormat(format!("Unknown property: {name}")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Property;
+    use indexmap::IndexMap;
+
+    fn object(entries: Vec<(&str, Value)>) -> Value {
+        Value::Object(
+            entries
+                .into_iter()
+                .map(|(k, v)| (k.into(), v))
+                .collect::<IndexMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn test_merge_nested_objects() {
+        let mut base = object(vec![
+            ("name", Value::from("default")),
+            (
+                "meta",
+                object(vec![("a", Value::from(1)), ("b", Value::from(2))]),
+            ),
+        ]);
+        let overrides = object(vec![
+            (
+                "meta",
+                object(vec![("b", Value::from(3)), ("c", Value::from(4))]),
+            ),
+            ("extra", Value::Boolean(true)),
+        ]);
+
+        base.merge(&overrides);
+
+        assert_eq!(
+            base,
+            object(vec![
+                ("name", Value::from("default")),
+                (
+                    "meta",
+                    object(vec![
+                        ("a", Value::from(1)),
+                        ("b", Value::from(3)),
+                        ("c", Value::from(4)),
+                    ])
+                ),
+                ("extra", Value::Boolean(true)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_merge_arrays() {
+        let base = object(vec![("tags", Value::Array(vec![Value::from("a")]))]);
+        let overrides = object(vec![("tags", Value::Array(vec![Value::from("b")]))]);
+
+        let mut replaced = base.clone();
+        replaced.merge(&overrides);
+        assert_eq!(replaced.get("tags"), overrides.get("tags"));
+
+        let mut concatenated = base;
+        concatenated.merge_with(&overrides, ArrayMergeStrategy::Concat);
+        assert_eq!(
+            concatenated.get("tags"),
+            Some(&Value::Array(vec![Value::from("a"), Value::from("b")]))
+        );
+    }
+
+    #[test]
+    fn test_merge_replaces_mismatched_types() {
+        let mut base = object(vec![("a", Value::from(1))]);
+        base.merge(&Value::from("scalar"));
+        assert_eq!(base, Value::from("scalar"));
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        // Examples from RFC 7396, appendix A
+        let s = Value::from;
+        for (target, patch, expected) in [
+            (
+                object(vec![("a", s("b"))]),
+                object(vec![("a", s("c"))]),
+                object(vec![("a", s("c"))]),
+            ),
+            (
+                object(vec![("a", s("b"))]),
+                object(vec![("b", s("c"))]),
+                object(vec![("a", s("b")), ("b", s("c"))]),
+            ),
+            (
+                object(vec![("a", s("b")), ("b", s("c"))]),
+                object(vec![("a", Value::Null)]),
+                object(vec![("b", s("c"))]),
+            ),
+            (
+                object(vec![("a", Value::array([s("b")]))]),
+                object(vec![("a", s("c"))]),
+                object(vec![("a", s("c"))]),
+            ),
+            (
+                object(vec![("a", object(vec![("b", s("c"))]))]),
+                object(vec![("a", object(vec![("b", s("d")), ("c", Value::Null)]))]),
+                object(vec![("a", object(vec![("b", s("d"))]))]),
+            ),
+            (
+                object(vec![("a", s("b"))]),
+                Value::array([s("c")]),
+                Value::array([s("c")]),
+            ),
+            (object(vec![("a", s("foo"))]), Value::Null, Value::Null),
+            (
+                object(vec![("e", Value::Null)]),
+                object(vec![("a", Value::from(1))]),
+                object(vec![("e", Value::Null), ("a", Value::from(1))]),
+            ),
+            (
+                Value::array([Value::from(1), Value::from(2)]),
+                object(vec![("a", s("b")), ("c", Value::Null)]),
+                object(vec![("a", s("b"))]),
+            ),
+            (
+                object(vec![]),
+                object(vec![(
+                    "a",
+                    object(vec![("bb", object(vec![("ccc", Value::Null)]))]),
+                )]),
+                object(vec![("a", object(vec![("bb", object(vec![]))]))]),
+            ),
+        ] {
+            let mut value = target.clone();
+            value.merge_patch(&patch);
+            assert_eq!(value, expected, "{target:?} patched with {patch:?}");
+        }
+    }
+
+    #[test]
+    fn test_merge_patch_validated() {
+        let registry = SchemaRegistry::new();
+        let schema = SchemaType::object([
+            ("name", Property::required(SchemaType::string())),
+            ("age", Property::optional(SchemaType::int32())),
+        ]);
+        let mut user = object(vec![("name", Value::from("ann"))]);
+
+        let patch = object(vec![("age", Value::from(41))]);
+        user.merge_patch_validated(&patch, &schema, &registry)
+            .unwrap();
+        assert_eq!(user.get("age"), Some(&Value::from(41)));
+
+        // Invalid results leave the value unchanged
+        for patch in [
+            object(vec![("name", Value::Null)]),
+            object(vec![("age", Value::from("old"))]),
+            object(vec![("nick", Value::from("a"))]),
+        ] {
+            let err = user.merge_patch_validated(&patch, &schema, &registry);
+            assert!(err.is_err());
+            assert_eq!(user.get("age"), Some(&Value::from(41)));
+            assert_eq!(user.get("name"), Some(&Value::from("ann")));
+            assert_eq!(user.get("nick"), None);
+        }
+
+        // Nested objects and `oneOf` payloads are checked as well
+        let mut variants = IndexMap::new();
+        variants.insert("none".to_owned(), SchemaType::null());
+        variants.insert(
+            "text".to_owned(),
+            SchemaType::object([("body", Property::required(SchemaType::string()))]),
+        );
+        let schema = SchemaType::object([
+            ("name", Property::required(SchemaType::string())),
+            (
+                "note",
+                Property::optional(SchemaType::one_of("kind", variants)),
+            ),
+        ]);
+        let patch = object(vec![(
+            "note",
+            object(vec![
+                ("kind", Value::from("text")),
+                ("body", Value::from("hi")),
+            ]),
+        )]);
+        user.remove("age");
+        user.merge_patch_validated(&patch, &schema, &registry)
+            .unwrap();
+        for note in [
+            object(vec![("extra", Value::from(1))]),
+            object(vec![("kind", Value::from("none"))]),
+        ] {
+            let patch = object(vec![("note", note)]);
+            assert!(user
+                .merge_patch_validated(&patch, &schema, &registry)
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,820 @@
+//! Dynamic value type for runtime representation of data.
+
+use crate::codec::type_mismatch;
+use crate::error::Result;
+#[cfg(feature = "chrono")]
+use crate::formats::datetime;
+use crate::formats::geo::GeoPoint;
+use crate::formats::ipaddr;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Utc};
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashMap};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+
+#[cfg(feature = "cbor")]
+mod cbor;
+mod convert;
+mod custom;
+mod eq;
+#[cfg(feature = "serde")]
+pub(crate) mod json;
+mod merge;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod patch;
+mod redact;
+mod self_describing;
+#[cfg(feature = "serde")]
+mod serde_impl;
+
+pub use custom::CustomValue;
+pub use merge::ArrayMergeStrategy;
+pub use patch::{PatchOp, PathSegment, ValuePatch};
+pub use redact::REDACTED;
+
+/// A dynamic value that can represent any type supported by Compactr.
+///
+/// This enum provides a way to work with values at runtime without
+/// compile-time type information.
+///
+/// Values implement [`Eq`] and [`Hash`], so they can be keys of maps and
+/// sets: floats compare by bit pattern, so that `NaN` equals itself and
+/// `0.0` differs from `-0.0`, and objects compare regardless of the order
+/// of their properties.
+#[derive(Debug, Clone)]
+pub enum Value {
+    /// Boolean value
+    Boolean(bool),
+
+    /// 32-bit signed integer
+    Integer(i64),
+
+    /// 128-bit signed integer
+    Int128(i128),
+
+    /// 128-bit unsigned integer
+    Uint128(u128),
+
+    /// 32-bit floating point number
+    Float(f32),
+
+    /// 64-bit floating point number
+    Double(f64),
+
+    /// Arbitrary-precision decimal number
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
+
+    /// UTF-8 string
+    String(String),
+
+    /// UUID (stored in compact binary form). Without the `uuid` feature,
+    /// UUIDs are [`Value::Binary`] of 16 bytes
+    #[cfg(feature = "uuid")]
+    Uuid(Uuid),
+
+    /// Date and time with timezone. Without the `chrono` feature, dates
+    /// and times are [`Value::Integer`]s: milliseconds or timestamp units
+    /// since the Unix epoch, days since the epoch for dates and
+    /// milliseconds since midnight for times, while datetimes with an
+    /// offset are their 11 encoded bytes as [`Value::Binary`]
+    #[cfg(feature = "chrono")]
+    DateTime(DateTime<Utc>),
+
+    /// Date and time with its original UTC offset
+    #[cfg(feature = "chrono")]
+    DateTimeTz(DateTime<FixedOffset>),
+
+    /// Date without time
+    #[cfg(feature = "chrono")]
+    Date(NaiveDate),
+
+    /// Time of day without date
+    #[cfg(feature = "chrono")]
+    Time(NaiveTime),
+
+    /// Geographic latitude/longitude point
+    GeoPoint(GeoPoint),
+
+    /// IPv4 address
+    Ipv4(Ipv4Addr),
+
+    /// IPv6 address
+    Ipv6(Ipv6Addr),
+
+    /// Socket address (IP + port)
+    SocketAddr(SocketAddr),
+
+    /// Binary data
+    Binary(Vec<u8>),
+
+    /// Array of values
+    Array(Vec<Value>),
+
+    /// Object with string keys and value values
+    /// Uses `IndexMap` to preserve insertion order. Keys are reference
+    /// counted, so cloning a value doesn't copy its property names, and
+    /// objects decoded or converted with a schema share the names' allocations
+    Object(IndexMap<Arc<str>, Value>),
+
+    /// Application value produced by a
+    /// [decode plugin](crate::plugin::DecodePlugin), which encodes and
+    /// converts as its plain representation
+    Custom(CustomValue),
+
+    /// Null value
+    Null,
+}
+
+impl Value {
+    /// Creates an object from name and value pairs, in order.
+    #[must_use]
+    pub fn object<I, K, V>(fields: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<Arc<str>>,
+        V: Into<Value>,
+    {
+        Self::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        )
+    }
+
+    /// Creates an array from its elements.
+    #[must_use]
+    pub fn array<I>(items: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Value>,
+    {
+        Self::Array(items.into_iter().map(Into::into).collect())
+    }
+
+    /// Creates a string value.
+    #[must_use]
+    pub fn string(s: impl Into<String>) -> Self {
+        Self::String(s.into())
+    }
+
+    /// Parses a UUID in its hyphenated, simple or braced form.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `s` is not a valid UUID.
+    #[cfg(feature = "uuid")]
+    pub fn uuid(s: &str) -> Result<Self> {
+        Ok(Self::Uuid(crate::formats::uuid::parse_uuid(s)?))
+    }
+
+    /// Parses an ISO 8601 date and time, converted to UTC.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `s` is not a valid ISO 8601 date and time.
+    #[cfg(feature = "chrono")]
+    pub fn datetime(s: &str) -> Result<Self> {
+        Ok(Self::DateTime(datetime::parse_datetime(s)?))
+    }
+
+    /// Parses a `YYYY-MM-DD` date.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `s` is not a valid date.
+    #[cfg(feature = "chrono")]
+    pub fn date(s: &str) -> Result<Self> {
+        Ok(Self::Date(datetime::parse_date(s)?))
+    }
+
+    /// Parses a dotted-decimal IPv4 address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `s` is not a valid IPv4 address.
+    pub fn ipv4(s: &str) -> Result<Self> {
+        Ok(Self::Ipv4(ipaddr::parse_ipv4(s)?))
+    }
+
+    /// Parses an IPv6 address.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `s` is not a valid IPv6 address.
+    pub fn ipv6(s: &str) -> Result<Self> {
+        Ok(Self::Ipv6(ipaddr::parse_ipv6(s)?))
+    }
+
+    /// Returns `true` if the value is `Null`.
+    #[must_use]
+    pub const fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Returns `true` if the value is a `Boolean`.
+    #[must_use]
+    pub const fn is_boolean(&self) -> bool {
+        matches!(self, Self::Boolean(_))
+    }
+
+    /// Returns `true` if the value is an `Integer`.
+    #[must_use]
+    pub const fn is_integer(&self) -> bool {
+        matches!(self, Self::Integer(_))
+    }
+
+    /// Returns `true` if the value is a `Float`.
+    #[must_use]
+    pub const fn is_float(&self) -> bool {
+        matches!(self, Self::Float(_))
+    }
+
+    /// Returns `true` if the value is a `Double`.
+    #[must_use]
+    pub const fn is_double(&self) -> bool {
+        matches!(self, Self::Double(_))
+    }
+
+    /// Returns `true` if the value is a `String`.
+    #[must_use]
+    pub const fn is_string(&self) -> bool {
+        matches!(self, Self::String(_))
+    }
+
+    /// Returns `true` if the value is an `Array`.
+    #[must_use]
+    pub const fn is_array(&self) -> bool {
+        matches!(self, Self::Array(_))
+    }
+
+    /// Returns `true` if the value is an `Object`.
+    #[must_use]
+    pub const fn is_object(&self) -> bool {
+        matches!(self, Self::Object(_))
+    }
+
+    /// Attempts to get the value as a `bool`.
+    #[must_use]
+    pub const fn as_bool(&self) -> Option<bool> {
+        if let Self::Boolean(b) = self {
+            Some(*b)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as an `i64`.
+    #[must_use]
+    pub const fn as_i64(&self) -> Option<i64> {
+        if let Self::Integer(i) = self {
+            Some(*i)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as an `i128`.
+    #[must_use]
+    pub const fn as_i128(&self) -> Option<i128> {
+        if let Self::Int128(i) = self {
+            Some(*i)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as a `u128`.
+    #[must_use]
+    pub const fn as_u128(&self) -> Option<u128> {
+        if let Self::Uint128(u) = self {
+            Some(*u)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as an `f32`.
+    #[must_use]
+    pub const fn as_f32(&self) -> Option<f32> {
+        if let Self::Float(f) = self {
+            Some(*f)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as an `f64`.
+    #[must_use]
+    pub const fn as_f64(&self) -> Option<f64> {
+        if let Self::Double(d) = self {
+            Some(*d)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as a `Decimal`.
+    #[cfg(feature = "decimal")]
+    #[must_use]
+    pub const fn as_decimal(&self) -> Option<rust_decimal::Decimal> {
+        if let Self::Decimal(d) = self {
+            Some(*d)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as a string slice.
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        if let Self::String(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as an array slice.
+    #[must_use]
+    pub fn as_array(&self) -> Option<&[Value]> {
+        if let Self::Array(arr) = self {
+            Some(arr)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as an object reference.
+    #[must_use]
+    pub fn as_object(&self) -> Option<&IndexMap<Arc<str>, Value>> {
+        if let Self::Object(obj) = self {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as a custom value.
+    #[must_use]
+    pub const fn as_custom(&self) -> Option<&CustomValue> {
+        if let Self::Custom(custom) = self {
+            Some(custom)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the application value of a custom value as a `T`.
+    #[must_use]
+    pub fn downcast_ref<T: std::any::Any>(&self) -> Option<&T> {
+        self.as_custom()?.downcast_ref()
+    }
+
+    /// Attempts to get a field from an object by key.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_object()?.get(key)
+    }
+
+    /// Attempts to get the value as a mutable array.
+    #[must_use]
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        if let Self::Array(arr) = self {
+            Some(arr)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get the value as a mutable object.
+    #[must_use]
+    pub fn as_object_mut(&mut self) -> Option<&mut IndexMap<Arc<str>, Value>> {
+        if let Self::Object(obj) = self {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+
+    /// Attempts to get a mutable reference to a field of an object by key.
+    #[must_use]
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    /// Inserts a field into an object, returning the previous value if any.
+    ///
+    /// A `Null` value is turned into an empty object first.
+    ///
+    /// # Errors
+    ///
+    /// Returns a type mismatch if the value is neither an object nor `Null`,
+    /// and leaves it unchanged.
+    pub fn insert(
+        &mut self,
+        key: impl Into<Arc<str>>,
+        value: impl Into<Value>,
+    ) -> Result<Option<Value>> {
+        Ok(self
+            .object_mut_or_promote()?
+            .insert(key.into(), value.into()))
+    }
+
+    /// Removes a field from an object, preserving the order of the remaining
+    /// fields. Returns `None` if the field is absent or the value is not an
+    /// object.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.as_object_mut()?.shift_remove(key)
+    }
+
+    /// Returns the map entry for `key` for in-place manipulation.
+    ///
+    /// A `Null` value is turned into an empty object first.
+    ///
+    /// # Errors
+    ///
+    /// Returns a type mismatch if the value is neither an object nor `Null`.
+    pub fn entry(
+        &mut self,
+        key: impl Into<Arc<str>>,
+    ) -> Result<indexmap::map::Entry<'_, Arc<str>, Value>> {
+        Ok(self.object_mut_or_promote()?.entry(key.into()))
+    }
+
+    /// Appends an element to an array.
+    ///
+    /// A `Null` value is turned into an empty array first.
+    ///
+    /// # Errors
+    ///
+    /// Returns a type mismatch if the value is neither an array nor `Null`,
+    /// and leaves it unchanged.
+    pub fn push(&mut self, value: impl Into<Value>) -> Result<()> {
+        if self.is_null() {
+            *self = Self::Array(Vec::new());
+        }
+        match self {
+            Self::Array(arr) => {
+                arr.push(value.into());
+                Ok(())
+            }
+            other => Err(type_mismatch("array", other)),
+        }
+    }
+
+    fn object_mut_or_promote(&mut self) -> Result<&mut IndexMap<Arc<str>, Value>> {
+        if self.is_null() {
+            *self = Self::Object(IndexMap::new());
+        }
+        match self {
+            Self::Object(obj) => Ok(obj),
+            other => Err(type_mismatch("object", other)),
+        }
+    }
+
+    /// Consumes the value and returns the owned string, if it is a `String`.
+    #[must_use]
+    pub fn into_string(self) -> Option<String> {
+        if let Self::String(s) = self {
+            Some(s)
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the value and returns the owned elements, if it is an `Array`.
+    #[must_use]
+    pub fn into_array(self) -> Option<Vec<Value>> {
+        if let Self::Array(arr) = self {
+            Some(arr)
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the value and returns the owned map, if it is an `Object`.
+    #[must_use]
+    pub fn into_object(self) -> Option<IndexMap<Arc<str>, Value>> {
+        if let Self::Object(obj) = self {
+            Some(obj)
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the value and returns the owned bytes, if it is `Binary`.
+    #[must_use]
+    pub fn into_binary(self) -> Option<Vec<u8>> {
+        if let Self::Binary(bytes) = self {
+            Some(bytes)
+        } else {
+            None
+        }
+    }
+
+    /// Takes the value out, leaving `Null` in its place.
+    #[must_use]
+    pub fn take(&mut self) -> Value {
+        std::mem::replace(self, Self::Null)
+    }
+}
+
+// Convenient From implementations
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Boolean(b)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(i: i32) -> Self {
+        Self::Integer(i64::from(i))
+    }
+}
+
+impl From<i64> for Value {
+    fn from(i: i64) -> Self {
+        Self::Integer(i)
+    }
+}
+
+impl From<i128> for Value {
+    fn from(i: i128) -> Self {
+        Self::Int128(i)
+    }
+}
+
+impl From<u128> for Value {
+    fn from(u: u128) -> Self {
+        Self::Uint128(u)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(f: f32) -> Self {
+        Self::Float(f)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(d: f64) -> Self {
+        Self::Double(d)
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<rust_decimal::Decimal> for Value {
+    fn from(d: rust_decimal::Decimal) -> Self {
+        Self::Decimal(d)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_owned())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<Uuid> for Value {
+    fn from(uuid: Uuid) -> Self {
+        Self::Uuid(uuid)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<DateTime<Utc>> for Value {
+    fn from(dt: DateTime<Utc>) -> Self {
+        Self::DateTime(dt)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<DateTime<FixedOffset>> for Value {
+    fn from(dt: DateTime<FixedOffset>) -> Self {
+        Self::DateTimeTz(dt)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<NaiveDate> for Value {
+    fn from(date: NaiveDate) -> Self {
+        Self::Date(date)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<NaiveTime> for Value {
+    fn from(time: NaiveTime) -> Self {
+        Self::Time(time)
+    }
+}
+
+impl From<GeoPoint> for Value {
+    fn from(point: GeoPoint) -> Self {
+        Self::GeoPoint(point)
+    }
+}
+
+impl From<Ipv4Addr> for Value {
+    fn from(ip: Ipv4Addr) -> Self {
+        Self::Ipv4(ip)
+    }
+}
+
+impl From<Ipv6Addr> for Value {
+    fn from(ip: Ipv6Addr) -> Self {
+        Self::Ipv6(ip)
+    }
+}
+
+impl From<SocketAddr> for Value {
+    fn from(addr: SocketAddr) -> Self {
+        Self::SocketAddr(addr)
+    }
+}
+
+/// Bytes become `Binary`, unlike other vectors.
+impl From<Vec<u8>> for Value {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Binary(bytes)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(arr: Vec<T>) -> Self {
+        Self::array(arr)
+    }
+}
+
+impl<K: Into<Arc<str>>, V: Into<Value>, S> From<IndexMap<K, V, S>> for Value {
+    fn from(obj: IndexMap<K, V, S>) -> Self {
+        Self::object(obj)
+    }
+}
+
+/// Fields keep the map's iteration order, which is unspecified.
+impl<K: Into<Arc<str>>, V: Into<Value>, S> From<HashMap<K, V, S>> for Value {
+    fn from(obj: HashMap<K, V, S>) -> Self {
+        Self::object(obj)
+    }
+}
+
+/// Fields are sorted by key.
+impl<K: Into<Arc<str>>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(obj: BTreeMap<K, V>) -> Self {
+        Self::object(obj)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(opt: Option<T>) -> Self {
+        match opt {
+            Some(val) => val.into(),
+            None => Self::Null,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(all(feature = "chrono", feature = "uuid"))]
+    fn test_constructors() {
+        let value = Value::object([
+            (
+                "id",
+                Value::uuid("550e8400-e29b-41d4-a716-446655440000").unwrap(),
+            ),
+            ("tags", Value::array(["a", "b"])),
+            ("ip", Value::ipv4("10.0.0.1").unwrap()),
+        ]);
+        assert_eq!(
+            value.get("ip"),
+            Some(&Value::Ipv4(Ipv4Addr::new(10, 0, 0, 1)))
+        );
+        assert_eq!(
+            value.get("tags"),
+            Some(&Value::Array(vec![Value::from("a"), Value::from("b")]))
+        );
+        assert!(value
+            .get("id")
+            .is_some_and(|id| matches!(id, Value::Uuid(_))));
+
+        assert_eq!(
+            Value::date("2024-02-29").unwrap(),
+            Value::Date(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap())
+        );
+        assert!(Value::datetime("2024-01-01T12:00:00+02:00").is_ok());
+        assert!(Value::uuid("not-a-uuid").is_err());
+        assert!(Value::date("2023-02-29").is_err());
+        assert!(Value::ipv6("::1::").is_err());
+    }
+
+    #[test]
+    fn test_from_collections() {
+        assert_eq!(
+            Value::from(vec![1, 2]),
+            Value::Array(vec![Value::Integer(1), Value::Integer(2)])
+        );
+        assert_eq!(Value::from(vec![1u8, 2]), Value::Binary(vec![1, 2]));
+        assert_eq!(
+            Value::from(vec![Some("a"), None]),
+            Value::Array(vec![Value::from("a"), Value::Null])
+        );
+
+        let mut labels = HashMap::new();
+        labels.insert("env".to_owned(), "prod".to_owned());
+        assert_eq!(Value::from(labels), Value::object([("env", "prod")]));
+        let counts = BTreeMap::from([("b", 2), ("a", 1)]);
+        assert_eq!(Value::from(counts), Value::object([("a", 1), ("b", 2)]));
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut value = Value::Null;
+        assert_eq!(value.insert("trace_id", "abc").unwrap(), None);
+        assert_eq!(value.insert("user", 1).unwrap(), None);
+        assert_eq!(
+            value.insert("trace_id", "def").unwrap(),
+            Some(Value::from("abc"))
+        );
+
+        assert_eq!(value.remove("trace_id"), Some(Value::from("def")));
+        assert_eq!(value.get("trace_id"), None);
+        assert_eq!(value.get("user"), Some(&Value::Integer(1)));
+    }
+
+    #[test]
+    fn test_into_and_take() {
+        assert_eq!(Value::from("a").into_string(), Some("a".to_owned()));
+        assert_eq!(Value::Integer(1).into_string(), None);
+        assert_eq!(
+            Value::from(vec![1, 2]).into_array(),
+            Some(vec![Value::from(1), Value::from(2)])
+        );
+        assert_eq!(Value::from("a").into_array(), None);
+        let object = Value::object([("a", 1)]).into_object().unwrap();
+        assert_eq!(object.get("a"), Some(&Value::from(1)));
+        assert_eq!(Value::Null.into_object(), None);
+        assert_eq!(Value::Binary(vec![1, 2]).into_binary(), Some(vec![1, 2]));
+        assert_eq!(Value::from("a").into_binary(), None);
+
+        let mut value = Value::object([("tags", vec!["x"])]);
+        let tags = value.get_mut("tags").unwrap().take();
+        assert_eq!(tags, Value::from(vec!["x"]));
+        assert_eq!(value.get("tags"), Some(&Value::Null));
+        assert_eq!(value.take(), Value::object([("tags", Value::Null)]));
+        assert_eq!(value, Value::Null);
+    }
+
+    #[test]
+    fn test_get_mut_and_entry() {
+        let mut value = Value::Null;
+        value.insert("count", 1).unwrap();
+
+        if let Some(Value::Integer(count)) = value.get_mut("count") {
+            *count += 1;
+        }
+        value
+            .entry("tags")
+            .unwrap()
+            .or_insert_with(|| Value::Array(Vec::new()));
+        value.get_mut("tags").unwrap().push("new").unwrap();
+
+        assert_eq!(value.get("count"), Some(&Value::Integer(2)));
+        assert_eq!(
+            value.get("tags"),
+            Some(&Value::Array(vec![Value::from("new")]))
+        );
+    }
+
+    #[test]
+    fn test_mutate_wrong_variant() {
+        let mut value = Value::Integer(1);
+        assert!(value.insert("a", 1).is_err());
+        assert!(value.entry("a").is_err());
+        assert!(value.push(1).is_err());
+        assert_eq!(value, Value::Integer(1));
+
+        let mut value = Value::Array(Vec::new());
+        assert!(value.insert("a", 1).is_err());
+        let mut value = Value::object([("a", 1)]);
+        assert!(value.push(1).is_err());
+        assert_eq!(value, Value::object([("a", 1)]));
+    }
+}
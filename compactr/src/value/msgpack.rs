@@ -0,0 +1,507 @@
+//! Conversion between [`Value`] and `rmpv::Value`.
+//!
+//! `MessagePack` has no standard representation for most formats, so the
+//! mapping favors what msgpack-rpc peers commonly expect:
+//!
+//! | Variant             | `MessagePack`                               |
+//! |---------------------|---------------------------------------------|
+//! | `Float`, `Double`   | float 32 and float 64                       |
+//! | `Int128`, `Uint128` | integers, or decimal strings beyond 64 bits |
+//! | `Uuid`, `Binary`    | bin (16 bytes for UUIDs)                    |
+//! | `DateTime`          | timestamp extension (type -1)               |
+//! | `GeoPoint`          | `{"lat": .., "lon": ..}` map                |
+//! | other formats       | canonical strings, as in JSON               |
+//!
+//! When reading, the string forms accepted by [`Value::from_json`] are
+//! understood for every format as well.
+
+use super::json::string_from_json;
+use super::Value;
+use crate::codec::{check_integer_precision, intern, narrow_float};
+use crate::error::{EncodeError, Result, SchemaError};
+#[cfg(feature = "decimal")]
+use crate::formats::decimal;
+use crate::formats::geo::GeoPoint;
+use crate::schema::{
+    IntegerFormat, NumberFormat, Property, SchemaRegistry, SchemaType, StringFormat,
+};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, SecondsFormat, Utc};
+use indexmap::IndexMap;
+use rmpv::Value as Msgpack;
+
+/// Extension type of the standard timestamp.
+#[cfg(feature = "chrono")]
+const EXT_TIMESTAMP: i8 = -1;
+
+impl Value {
+    /// Converts a `MessagePack` value into a `Value` guided by the given schema.
+    ///
+    /// Map entries that are not part of the schema, or that are `nil`, are
+    /// dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `MessagePack` value doesn't match the schema.
+    pub fn from_msgpack(msgpack: &Msgpack, schema: &SchemaType) -> Result<Self> {
+        Self::from_msgpack_with_registry(msgpack, schema, SchemaRegistry::empty())
+    }
+
+    /// Converts a `MessagePack` value into a `Value` with a schema registry for
+    /// resolving references.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `MessagePack` value doesn't match the schema or
+    /// a reference cannot be resolved.
+    pub fn from_msgpack_with_registry(
+        msgpack: &Msgpack,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        match schema {
+            SchemaType::Boolean => msgpack
+                .as_bool()
+                .map(Self::Boolean)
+                .ok_or_else(|| mismatch("boolean", msgpack)),
+            SchemaType::Integer(format) => integer_from_msgpack(msgpack, *format),
+            SchemaType::Number(format) => number_from_msgpack(msgpack, *format, registry),
+            SchemaType::String(format) => string_from_msgpack(msgpack, *format, registry),
+            SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+                let arr = msgpack
+                    .as_array()
+                    .ok_or_else(|| mismatch("array", msgpack))?;
+                arr.iter()
+                    .map(|item| Self::from_msgpack_with_registry(item, items, registry))
+                    .collect::<Result<Vec<_>>>()
+                    .map(Self::Array)
+            }
+            SchemaType::Object(properties) => {
+                let map = msgpack
+                    .as_map()
+                    .ok_or_else(|| mismatch("object", msgpack))?;
+                object_from_msgpack(map, properties, registry)
+            }
+            SchemaType::Reference(ref_name) => {
+                let resolved = registry.resolve_shared(ref_name)?;
+                Self::from_msgpack_with_registry(msgpack, &resolved, registry)
+            }
+            SchemaType::Enum(variants) => {
+                let s = msgpack
+                    .as_str()
+                    .ok_or_else(|| mismatch("string", msgpack))?;
+                if !variants.iter().any(|v| v == s) {
+                    return Err(EncodeError::InvalidFormat(format!("Unknown variant: {s}")).into());
+                }
+                Ok(Self::String(s.to_owned()))
+            }
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            } => one_of_from_msgpack(msgpack, discriminator, variants, registry),
+            SchemaType::Null => {
+                if msgpack.is_nil() {
+                    Ok(Self::Null)
+                } else {
+                    Err(mismatch("null", msgpack))
+                }
+            }
+        }
+    }
+
+    /// Converts this value into a `MessagePack` value.
+    ///
+    /// Formats are mapped as listed in the [module documentation](self).
+    #[must_use]
+    pub fn to_msgpack(&self) -> Msgpack {
+        let text = |s: String| Msgpack::String(s.into());
+        match self {
+            Self::Boolean(b) => Msgpack::Boolean(*b),
+            Self::Integer(i) => Msgpack::from(*i),
+            // Integers beyond 64 bits become decimal strings, as in JSON
+            Self::Int128(i) => i64::try_from(*i)
+                .map(Msgpack::from)
+                .or_else(|_| u64::try_from(*i).map(Msgpack::from))
+                .unwrap_or_else(|_| text(i.to_string())),
+            Self::Uint128(u) => {
+                u64::try_from(*u).map_or_else(|_| text(u.to_string()), Msgpack::from)
+            }
+            Self::Float(f) => Msgpack::F32(*f),
+            Self::Double(d) => Msgpack::F64(*d),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(d) => text(d.to_string()),
+            Self::String(s) => text(s.clone()),
+            #[cfg(feature = "uuid")]
+            Self::Uuid(u) => Msgpack::Binary(u.as_bytes().to_vec()),
+            #[cfg(feature = "chrono")]
+            Self::DateTime(dt) => Msgpack::Ext(EXT_TIMESTAMP, timestamp_bytes(dt)),
+            #[cfg(feature = "chrono")]
+            Self::DateTimeTz(dt) => text(dt.to_rfc3339_opts(SecondsFormat::AutoSi, false)),
+            #[cfg(feature = "chrono")]
+            Self::Date(d) => text(d.format("%Y-%m-%d").to_string()),
+            #[cfg(feature = "chrono")]
+            Self::Time(t) => text(t.to_string()),
+            Self::GeoPoint(p) => Msgpack::Map(vec![
+                (text("lat".to_owned()), Msgpack::F64(p.lat())),
+                (text("lon".to_owned()), Msgpack::F64(p.lon())),
+            ]),
+            Self::Ipv4(ip) => text(ip.to_string()),
+            Self::Ipv6(ip) => text(ip.to_string()),
+            Self::SocketAddr(addr) => text(addr.to_string()),
+            Self::Binary(data) => Msgpack::Binary(data.clone()),
+            Self::Array(items) => Msgpack::Array(items.iter().map(Self::to_msgpack).collect()),
+            Self::Object(obj) => Msgpack::Map(
+                obj.iter()
+                    .map(|(k, v)| (text(k.to_string()), v.to_msgpack()))
+                    .collect(),
+            ),
+            Self::Custom(custom) => custom.repr().to_msgpack(),
+            Self::Null => Msgpack::Nil,
+        }
+    }
+}
+
+fn integer_from_msgpack(msgpack: &Msgpack, format: IntegerFormat) -> Result<Value> {
+    let out_of_range =
+        || EncodeError::InvalidFormat(format!("Integer out of range for {format}")).into();
+    // 128-bit integers may not fit in a MessagePack integer, so strings are accepted too
+    let wide =
+        |s: &str| EncodeError::InvalidFormat(format!("Invalid {format} integer: {s}")).into();
+    match (format, msgpack) {
+        (IntegerFormat::Int128, Msgpack::String(s)) => {
+            let s = s.as_str().ok_or_else(|| mismatch("integer", msgpack))?;
+            s.parse().map(Value::Int128).map_err(|_| wide(s))
+        }
+        (IntegerFormat::Uint128, Msgpack::String(s)) => {
+            let s = s.as_str().ok_or_else(|| mismatch("integer", msgpack))?;
+            s.parse().map(Value::Uint128).map_err(|_| wide(s))
+        }
+        (_, Msgpack::Integer(n)) => {
+            let n = n
+                .as_i64()
+                .map(i128::from)
+                .or_else(|| n.as_u64().map(i128::from))
+                .ok_or_else(|| mismatch("integer", msgpack))?;
+            match format {
+                IntegerFormat::Int128 => Ok(Value::Int128(n)),
+                IntegerFormat::Uint128 => u128::try_from(n)
+                    .map(Value::Uint128)
+                    .map_err(|_| out_of_range()),
+                _ => i64::try_from(n)
+                    .map(Value::Integer)
+                    .map_err(|_| out_of_range()),
+            }
+        }
+        _ => Err(mismatch("integer", msgpack)),
+    }
+}
+
+fn number_from_msgpack(
+    msgpack: &Msgpack,
+    format: NumberFormat,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    let as_f64 = || match msgpack {
+        Msgpack::F32(f) => Ok(f64::from(*f)),
+        Msgpack::F64(f) => Ok(*f),
+        Msgpack::Integer(n) => {
+            let i = n
+                .as_i64()
+                .map(i128::from)
+                .or_else(|| n.as_u64().map(i128::from))
+                .ok_or_else(|| mismatch("number", msgpack))?;
+            check_integer_precision(i, f64::MANTISSA_DIGITS, registry)?;
+            #[allow(clippy::cast_precision_loss)]
+            Ok(i as f64)
+        }
+        _ => Err(mismatch("number", msgpack)),
+    };
+    Ok(match format {
+        NumberFormat::Float => Value::Float(narrow_float(as_f64()?, registry)?),
+        NumberFormat::Double => Value::Double(as_f64()?),
+        // Strings preserve full precision; plain numbers are accepted as well
+        #[cfg(feature = "decimal")]
+        NumberFormat::Decimal => Value::Decimal(match msgpack {
+            Msgpack::String(s) => {
+                decimal::parse_decimal(s.as_str().ok_or_else(|| mismatch("decimal", msgpack))?)?
+            }
+            Msgpack::Integer(n) => decimal::parse_decimal(&n.to_string())?,
+            Msgpack::F32(f) => decimal::parse_decimal(&f.to_string())?,
+            Msgpack::F64(f) => decimal::parse_decimal(&f.to_string())?,
+            _ => return Err(mismatch("decimal", msgpack)),
+        }),
+    })
+}
+
+fn string_from_msgpack(
+    msgpack: &Msgpack,
+    format: StringFormat,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    let fail = || mismatch(&SchemaType::String(format).to_string(), msgpack);
+    match (format, msgpack) {
+        #[cfg(feature = "uuid")]
+        (StringFormat::Uuid, Msgpack::Binary(bytes)) => uuid::Uuid::from_slice(bytes)
+            .map(Value::Uuid)
+            .map_err(|_| fail()),
+        #[cfg(not(feature = "uuid"))]
+        (StringFormat::Uuid, Msgpack::Binary(bytes)) if bytes.len() == 16 => {
+            Ok(Value::Binary(bytes.clone()))
+        }
+        #[cfg(feature = "chrono")]
+        (
+            StringFormat::DateTime | StringFormat::DateTimeTz | StringFormat::Timestamp(_),
+            Msgpack::Ext(EXT_TIMESTAMP, bytes),
+        ) => {
+            let dt = timestamp_from_bytes(bytes).ok_or_else(fail)?;
+            Ok(if format == StringFormat::DateTimeTz {
+                Value::DateTimeTz(dt.fixed_offset())
+            } else {
+                Value::DateTime(dt)
+            })
+        }
+        (StringFormat::Binary, Msgpack::Binary(bytes)) => Ok(Value::Binary(bytes.clone())),
+        // Points are also accepted in their `{"lat": .., "lon": ..}` map form
+        (StringFormat::GeoPoint, Msgpack::Map(entries)) => {
+            let coord = |key| {
+                map_get(entries, key)
+                    .and_then(Msgpack::as_f64)
+                    .ok_or_else(fail)
+            };
+            Ok(Value::GeoPoint(GeoPoint::new(
+                coord("lat")?,
+                coord("lon")?,
+            )?))
+        }
+        (format, Msgpack::String(s)) => {
+            let s = s.as_str().ok_or_else(fail)?;
+            string_from_json(s, format, registry)
+        }
+        _ => Err(fail()),
+    }
+}
+
+/// Encodes a timestamp extension in its smallest form.
+#[cfg(feature = "chrono")]
+fn timestamp_bytes(dt: &DateTime<Utc>) -> Vec<u8> {
+    let secs = dt.timestamp();
+    let nanos = dt.timestamp_subsec_nanos();
+    match (u32::try_from(secs), u64::try_from(secs)) {
+        // timestamp 32: unsigned seconds
+        (Ok(secs), _) if nanos == 0 => secs.to_be_bytes().to_vec(),
+        // timestamp 64: 30-bit nanoseconds and 34-bit unsigned seconds
+        (_, Ok(secs)) if secs < 1 << 34 => ((u64::from(nanos) << 34) | secs).to_be_bytes().to_vec(),
+        // timestamp 96: 32-bit nanoseconds and signed 64-bit seconds
+        _ => {
+            let mut bytes = nanos.to_be_bytes().to_vec();
+            bytes.extend_from_slice(&secs.to_be_bytes());
+            bytes
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn timestamp_from_bytes(bytes: &[u8]) -> Option<DateTime<Utc>> {
+    let (secs, nanos) = match bytes.len() {
+        4 => (i64::from(u32::from_be_bytes(bytes.try_into().ok()?)), 0),
+        8 => {
+            let n = u64::from_be_bytes(bytes.try_into().ok()?);
+            #[allow(clippy::cast_possible_truncation)]
+            let nanos = (n >> 34) as u32;
+            (i64::try_from(n & ((1 << 34) - 1)).ok()?, nanos)
+        }
+        12 => (
+            i64::from_be_bytes(bytes[4..].try_into().ok()?),
+            u32::from_be_bytes(bytes[..4].try_into().ok()?),
+        ),
+        _ => return None,
+    };
+    DateTime::from_timestamp(secs, nanos)
+}
+
+fn map_get<'a>(entries: &'a [(Msgpack, Msgpack)], key: &str) -> Option<&'a Msgpack> {
+    entries
+        .iter()
+        .find(|(k, _)| k.as_str() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn object_from_msgpack(
+    entries: &[(Msgpack, Msgpack)],
+    properties: &IndexMap<String, Property>,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    let mut obj = IndexMap::with_capacity(entries.len());
+    for (key, msgpack) in entries {
+        let Some((key, prop)) = key.as_str().and_then(|k| properties.get_key_value(k)) else {
+            continue;
+        };
+        // `nil` means "absent" unless the property is explicitly a null type
+        if msgpack.is_nil() && prop.schema_type != SchemaType::Null {
+            continue;
+        }
+        let value = Value::from_msgpack_with_registry(msgpack, &prop.schema_type, registry)?;
+        obj.insert(intern(key), value);
+    }
+    Ok(Value::Object(obj))
+}
+
+fn one_of_from_msgpack(
+    msgpack: &Msgpack,
+    discriminator: &str,
+    variants: &IndexMap<String, SchemaType>,
+    registry: &SchemaRegistry,
+) -> Result<Value> {
+    let name = match msgpack {
+        // Variants without a payload may be given by name alone
+        Msgpack::String(_) => msgpack
+            .as_str()
+            .ok_or_else(|| mismatch("string", msgpack))?,
+        Msgpack::Map(entries) => map_get(entries, discriminator)
+            .and_then(Msgpack::as_str)
+            .ok_or_else(|| SchemaError::MissingField(discriminator.to_owned()))?,
+        _ => return Err(mismatch("object", msgpack)),
+    };
+    let payload = variants
+        .get(name)
+        .ok_or_else(|| EncodeError::InvalidFormat(format!("Unknown variant: {name}")))?;
+
+    let mut obj = IndexMap::new();
+    obj.insert(intern(discriminator), Value::String(name.to_owned()));
+    if *payload != SchemaType::Null {
+        if let Value::Object(fields) =
+            Value::from_msgpack_with_registry(msgpack, payload, registry)?
+        {
+            obj.extend(fields);
+        }
+    }
+    Ok(Value::Object(obj))
+}
+
+fn mismatch(expected: &str, msgpack: &Msgpack) -> crate::error::Error {
+    let actual = match msgpack {
+        Msgpack::Nil => "nil".to_owned(),
+        Msgpack::Boolean(_) => "boolean".to_owned(),
+        Msgpack::Integer(_) => "integer".to_owned(),
+        Msgpack::F32(_) | Msgpack::F64(_) => "float".to_owned(),
+        Msgpack::String(_) => "string".to_owned(),
+        Msgpack::Binary(_) => "bin".to_owned(),
+        Msgpack::Array(_) => "array".to_owned(),
+        Msgpack::Map(_) => "map".to_owned(),
+        Msgpack::Ext(ty, _) => format!("ext {ty}"),
+    };
+    EncodeError::TypeMismatch {
+        expected: expected.to_owned(),
+        actual,
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(value: &Value, schema: &SchemaType) -> Value {
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &value.to_msgpack()).unwrap();
+        let msgpack = rmpv::decode::read_value(&mut bytes.as_slice()).unwrap();
+        Value::from_msgpack(&msgpack, schema).unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip_object() {
+        let mut properties = IndexMap::new();
+        properties.insert(
+            "id".to_owned(),
+            Property::required(SchemaType::string_uuid()),
+        );
+        properties.insert("score".to_owned(), Property::optional(SchemaType::float()));
+        properties.insert(
+            "ip".to_owned(),
+            Property::optional(SchemaType::string_ipv4()),
+        );
+        properties.insert(
+            "where".to_owned(),
+            Property::optional(SchemaType::geo_point()),
+        );
+        properties.insert("big".to_owned(), Property::optional(SchemaType::uint128()));
+        let schema = SchemaType::object(properties);
+
+        let mut obj = IndexMap::new();
+        // The raw bytes without the uuid feature
+        #[cfg(feature = "uuid")]
+        let id = Value::Uuid(uuid::Uuid::from_u128(7));
+        #[cfg(not(feature = "uuid"))]
+        let id = Value::Binary(7_u128.to_be_bytes().to_vec());
+        obj.insert("id".into(), id);
+        obj.insert("score".into(), Value::Float(1.5));
+        obj.insert("ip".into(), Value::Ipv4("10.0.0.1".parse().unwrap()));
+        obj.insert(
+            "where".into(),
+            Value::GeoPoint(GeoPoint::new(48.8584, 2.2945).unwrap()),
+        );
+        obj.insert("big".into(), Value::Uint128(u128::MAX));
+        let value = Value::Object(obj);
+        assert_eq!(roundtrip(&value, &schema), value);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_timestamp_extension() {
+        let schema = SchemaType::string_datetime();
+        for s in [
+            "2024-01-15T10:30:00Z",
+            "2024-01-15T10:30:00.123456789Z",
+            "1969-07-20T20:17:40.5Z",
+            "2600-01-01T00:00:00Z",
+        ] {
+            let dt = crate::formats::datetime::parse_datetime(s).unwrap();
+            let msgpack = Value::DateTime(dt).to_msgpack();
+            assert!(matches!(msgpack, Msgpack::Ext(EXT_TIMESTAMP, _)));
+            assert_eq!(
+                roundtrip(&Value::DateTime(dt), &schema),
+                Value::DateTime(dt)
+            );
+        }
+        let Msgpack::Ext(_, bytes) = Value::DateTime(DateTime::UNIX_EPOCH).to_msgpack() else {
+            unreachable!();
+        };
+        assert_eq!(bytes.len(), 4);
+
+        // String forms are accepted too
+        assert!(Value::from_msgpack(&Msgpack::from("2024-01-15T10:30:00Z"), &schema).is_ok());
+        assert!(Value::from_msgpack(&Msgpack::Ext(EXT_TIMESTAMP, vec![0; 5]), &schema).is_err());
+    }
+
+    #[test]
+    fn test_integer_ranges() {
+        assert_eq!(
+            Value::from_msgpack(&Msgpack::from(u64::MAX), &SchemaType::uint128()).unwrap(),
+            Value::Uint128(u128::from(u64::MAX))
+        );
+        assert!(Value::from_msgpack(&Msgpack::from(u64::MAX), &SchemaType::int64()).is_err());
+        assert!(Value::from_msgpack(&Msgpack::from(-1), &SchemaType::uint128()).is_err());
+        assert!(Value::from_msgpack(&Msgpack::Nil, &SchemaType::int32()).is_err());
+    }
+
+    #[test]
+    fn test_number_precision() {
+        let float = SchemaType::float();
+        assert_eq!(
+            Value::from_msgpack(&Msgpack::F32(0.1), &float).unwrap(),
+            Value::Float(0.1)
+        );
+        assert!(Value::from_msgpack(&Msgpack::F64(0.1), &float).is_err());
+        assert!(Value::from_msgpack(&Msgpack::from(u64::MAX), &SchemaType::double()).is_err());
+        assert_eq!(
+            Value::from_msgpack(&Msgpack::from(1_u64 << 63), &SchemaType::double()).unwrap(),
+            Value::Double(9_223_372_036_854_775_808.0)
+        );
+
+        let registry = SchemaRegistry::new().with_lossy_floats(true);
+        assert_eq!(
+            Value::from_msgpack_with_registry(&Msgpack::F64(0.1), &float, &registry).unwrap(),
+            Value::Float(0.1)
+        );
+    }
+}
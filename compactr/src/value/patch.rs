@@ -0,0 +1,452 @@
+//! Structural diffs between values and their compact binary encoding.
+
+use super::Value;
+use crate::codec::{Decoder, Encoder};
+use crate::error::{DecodeError, EncodeError, Result, SchemaError};
+use crate::schema::{index_of, index_order, SchemaRegistry, SchemaType};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::fmt;
+
+const OP_SET: u8 = 0;
+const OP_REMOVE: u8 = 1;
+
+/// A single step in a path into a nested value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// Object property name
+    Key(String),
+    /// Array element index
+    Index(usize),
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "/{key}"),
+            Self::Index(idx) => write!(f, "/{idx}"),
+        }
+    }
+}
+
+/// A single patch operation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOp {
+    /// Sets the value at `path`, inserting object properties as needed
+    Set {
+        /// Location of the value
+        path: Vec<PathSegment>,
+        /// New value
+        value: Value,
+    },
+    /// Removes the object property at `path`
+    Remove {
+        /// Location of the property
+        path: Vec<PathSegment>,
+    },
+}
+
+/// A list of operations transforming one value into another.
+///
+/// Created with [`Value::diff`] and applied with [`ValuePatch::apply`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValuePatch {
+    ops: Vec<PatchOp>,
+}
+
+impl Value {
+    /// Computes the patch that transforms this value into `other`.
+    ///
+    /// Objects are diffed property by property and equal-length arrays element
+    /// by element; everything else that differs is replaced wholesale.
+    #[must_use]
+    pub fn diff(&self, other: &Value) -> ValuePatch {
+        let mut patch = ValuePatch::default();
+        diff_into(self, other, &mut Vec::new(), &mut patch.ops);
+        patch
+    }
+}
+
+fn diff_into(old: &Value, new: &Value, path: &mut Vec<PathSegment>, ops: &mut Vec<PatchOp>) {
+    match (old, new) {
+        (Value::Object(old_obj), Value::Object(new_obj)) => {
+            for key in old_obj.keys().filter(|k| !new_obj.contains_key(*k)) {
+                path.push(PathSegment::Key(key.to_string()));
+                ops.push(PatchOp::Remove { path: path.clone() });
+                path.pop();
+            }
+            for (key, new_val) in new_obj {
+                path.push(PathSegment::Key(key.to_string()));
+                match old_obj.get(key) {
+                    Some(old_val) => diff_into(old_val, new_val, path, ops),
+                    None => ops.push(PatchOp::Set {
+                        path: path.clone(),
+                        value: new_val.clone(),
+                    }),
+                }
+                path.pop();
+            }
+        }
+        (Value::Array(old_arr), Value::Array(new_arr)) if old_arr.len() == new_arr.len() => {
+            for (idx, (old_val, new_val)) in old_arr.iter().zip(new_arr).enumerate() {
+                path.push(PathSegment::Index(idx));
+                diff_into(old_val, new_val, path, ops);
+                path.pop();
+            }
+        }
+        _ if old == new => {}
+        _ => ops.push(PatchOp::Set {
+            path: path.clone(),
+            value: new.clone(),
+        }),
+    }
+}
+
+impl ValuePatch {
+    /// Creates a patch from a list of operations.
+    #[must_use]
+    pub fn new(ops: Vec<PatchOp>) -> Self {
+        Self { ops }
+    }
+
+    /// Returns the operations in this patch.
+    #[must_use]
+    pub fn ops(&self) -> &[PatchOp] {
+        &self.ops
+    }
+
+    /// Returns `true` if the patch contains no operations.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Applies this patch to `target` in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a path does not exist in `target`.
+    pub fn apply(&self, target: &mut Value) -> Result<()> {
+        for op in &self.ops {
+            match op {
+                PatchOp::Set { path, value } => {
+                    let Some((last, parent)) = path.split_last() else {
+                        *target = value.clone();
+                        continue;
+                    };
+                    match (navigate(target, parent)?, last) {
+                        (Value::Object(obj), PathSegment::Key(key)) => {
+                            obj.insert(key.as_str().into(), value.clone());
+                        }
+                        (Value::Array(arr), PathSegment::Index(idx)) if *idx < arr.len() => {
+                            arr[*idx] = value.clone();
+                        }
+                        _ => return Err(path_error(path)),
+                    }
+                }
+                PatchOp::Remove { path } => {
+                    let Some((PathSegment::Key(key), parent)) = path.split_last() else {
+                        return Err(path_error(path));
+                    };
+                    let Value::Object(obj) = navigate(target, parent)? else {
+                        return Err(path_error(path));
+                    };
+                    obj.shift_remove(key.as_str());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Encodes this patch against the schema of the patched document.
+    ///
+    /// Format: op count (u16 BE), then per op: opcode (1 byte), path depth
+    /// (1 byte), path segments (property index as 1 byte, array index as u32
+    /// BE), and for `Set` a u32 BE length followed by the value encoded with
+    /// the schema found at the path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a path doesn't exist in the schema or a value
+    /// doesn't match its schema.
+    pub fn encode(&self, schema: &SchemaType, registry: &SchemaRegistry) -> Result<Bytes> {
+        let mut buf = BytesMut::new();
+        let count = u16::try_from(self.ops.len()).map_err(|_| {
+            EncodeError::InvalidFormat(format!(
+                "Too many patch operations: {} (max {})",
+                self.ops.len(),
+                u16::MAX
+            ))
+        })?;
+        buf.put_u16(count);
+
+        for op in &self.ops {
+            let (opcode, path) = match op {
+                PatchOp::Set { path, .. } => (OP_SET, path),
+                PatchOp::Remove { path } => (OP_REMOVE, path),
+            };
+            buf.put_u8(opcode);
+            let depth = u8::try_from(path.len()).map_err(|_| {
+                EncodeError::InvalidFormat(format!("Patch path too deep: {}", path.len()))
+            })?;
+            buf.put_u8(depth);
+
+            let mut current = schema.clone();
+            for segment in path {
+                current = resolve(current, registry)?;
+                current = match (&current, segment) {
+                    (SchemaType::Object(properties), PathSegment::Key(key)) => {
+                        let (idx, prop) = sorted_property(properties, key)
+                            .ok_or_else(|| SchemaError::MissingField(key.clone()))?;
+                        let idx = u8::try_from(idx).map_err(|_| {
+                            EncodeError::InvalidFormat(format!("Property index too large: {idx}"))
+                        })?;
+                        buf.put_u8(idx);
+                        prop.schema_type.clone()
+                    }
+                    (
+                        SchemaType::Array(items) | SchemaType::RunLengthArray(items),
+                        PathSegment::Index(idx),
+                    ) => {
+                        let idx = u32::try_from(*idx).map_err(|_| {
+                            EncodeError::InvalidFormat(format!("Array index too large: {idx}"))
+                        })?;
+                        buf.put_u32(idx);
+                        (**items).clone()
+                    }
+                    _ => {
+                        return Err(SchemaError::InvalidSchema(format!(
+                            "Patch path segment {segment} does not match schema {current}"
+                        ))
+                        .into())
+                    }
+                };
+            }
+
+            if let PatchOp::Set { value, .. } = op {
+                let mut encoder = Encoder::new();
+                encoder.encode_nested(value, &current, registry)?;
+                let bytes = encoder.finish();
+                let len = u32::try_from(bytes.len())
+                    .map_err(|_| EncodeError::InvalidFormat("Patch value too large".to_owned()))?;
+                buf.put_u32(len);
+                buf.put_slice(&bytes);
+            }
+        }
+
+        Ok(buf.freeze())
+    }
+
+    /// Decodes a patch produced by [`ValuePatch::encode`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is truncated or doesn't match the schema.
+    pub fn decode(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<Self> {
+        if buf.remaining() < 2 {
+            return Err(DecodeError::UnexpectedEof.into());
+        }
+        let count = buf.get_u16();
+        let mut ops = Vec::with_capacity(usize::from(count));
+
+        for _ in 0..count {
+            if buf.remaining() < 2 {
+                return Err(DecodeError::UnexpectedEof.into());
+            }
+            let opcode = buf.get_u8();
+            let depth = buf.get_u8();
+
+            let mut path = Vec::with_capacity(usize::from(depth));
+            let mut current = schema.clone();
+            for _ in 0..depth {
+                current = resolve(current, registry)?;
+                current = match &current {
+                    SchemaType::Object(properties) => {
+                        if !buf.has_remaining() {
+                            return Err(DecodeError::UnexpectedEof.into());
+                        }
+                        let idx = usize::from(buf.get_u8());
+                        let sorted = index_order(properties);
+                        let (name, property) = sorted.get(idx).ok_or_else(|| {
+                            DecodeError::InvalidData(format!(
+                                "Property index {idx} out of range in patch path"
+                            ))
+                        })?;
+                        path.push(PathSegment::Key((*name).clone()));
+                        property.schema_type.clone()
+                    }
+                    SchemaType::Array(items) | SchemaType::RunLengthArray(items) => {
+                        if buf.remaining() < 4 {
+                            return Err(DecodeError::UnexpectedEof.into());
+                        }
+                        path.push(PathSegment::Index(buf.get_u32() as usize));
+                        (**items).clone()
+                    }
+                    other => {
+                        return Err(DecodeError::SchemaMismatch(format!(
+                            "Patch path descends into non-container schema {other}"
+                        ))
+                        .into())
+                    }
+                };
+            }
+
+            match opcode {
+                OP_SET => {
+                    if buf.remaining() < 4 {
+                        return Err(DecodeError::UnexpectedEof.into());
+                    }
+                    let len = buf.get_u32() as usize;
+                    if buf.remaining() < len {
+                        return Err(DecodeError::UnexpectedEof.into());
+                    }
+                    let mut value_buf = buf.copy_to_bytes(len);
+                    let value = Decoder::decode_nested(&mut value_buf, &current, registry)?;
+                    ops.push(PatchOp::Set { path, value });
+                }
+                OP_REMOVE => ops.push(PatchOp::Remove { path }),
+                _ => {
+                    return Err(
+                        DecodeError::InvalidData(format!("Invalid patch opcode: {opcode}")).into(),
+                    )
+                }
+            }
+        }
+
+        Ok(Self { ops })
+    }
+}
+
+fn navigate<'a>(mut value: &'a mut Value, path: &[PathSegment]) -> Result<&'a mut Value> {
+    for (depth, segment) in path.iter().enumerate() {
+        value = match (value, segment) {
+            (Value::Object(obj), PathSegment::Key(key)) => obj.get_mut(key.as_str()),
+            (Value::Array(arr), PathSegment::Index(idx)) => arr.get_mut(*idx),
+            _ => None,
+        }
+        .ok_or_else(|| path_error(&path[..=depth]))?;
+    }
+    Ok(value)
+}
+
+fn resolve(schema: SchemaType, registry: &SchemaRegistry) -> Result<SchemaType> {
+    match schema {
+        SchemaType::Reference(ref_name) => registry.resolve_ref(&ref_name),
+        other => Ok(other),
+    }
+}
+
+fn sorted_property<'a>(
+    properties: &'a indexmap::IndexMap<String, crate::schema::Property>,
+    key: &str,
+) -> Option<(usize, &'a crate::schema::Property)> {
+    let sorted = index_order(properties);
+    let idx = index_of(&sorted, key)?;
+    Some((idx, sorted[idx].1))
+}
+
+fn path_error(path: &[PathSegment]) -> crate::error::Error {
+    let rendered: String = path.iter().map(ToString::to_string).collect();
+    DecodeError::InvalidData(format!("Patch path not found: {rendered}")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Property;
+    use indexmap::IndexMap;
+
+    fn schema() -> SchemaType {
+        let mut properties = IndexMap::new();
+        properties.insert("name".to_owned(), Property::required(SchemaType::string()));
+        properties.insert("age".to_owned(), Property::optional(SchemaType::int32()));
+        properties.insert(
+            "scores".to_owned(),
+            Property::optional(SchemaType::array(SchemaType::int32())),
+        );
+        SchemaType::object(properties)
+    }
+
+    fn user(name: &str, age: Option<i32>, scores: &[i32]) -> Value {
+        let mut obj = IndexMap::new();
+        obj.insert("name".into(), Value::from(name));
+        if let Some(age) = age {
+            obj.insert("age".into(), Value::from(age));
+        }
+        obj.insert(
+            "scores".into(),
+            Value::Array(scores.iter().map(|s| Value::from(*s)).collect()),
+        );
+        Value::Object(obj)
+    }
+
+    #[test]
+    fn test_diff_and_apply() {
+        let old = user("Alice", Some(30), &[1, 2, 3]);
+        let new = user("Alicia", None, &[1, 5, 3]);
+
+        let patch = old.diff(&new);
+        assert_eq!(patch.ops().len(), 3);
+
+        let mut patched = old.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(patched, new);
+    }
+
+    #[test]
+    fn test_diff_equal_values_is_empty() {
+        let value = user("Alice", Some(30), &[1]);
+        assert!(value.diff(&value).is_empty());
+    }
+
+    #[test]
+    fn test_patch_binary_roundtrip() {
+        let old = user("Alice", Some(30), &[1, 2, 3]);
+        let new = user("Bob", None, &[1, 2, 4]);
+        let patch = old.diff(&new);
+
+        let registry = SchemaRegistry::new();
+        let bytes = patch.encode(&schema(), &registry).unwrap();
+        let decoded = ValuePatch::decode(&mut bytes.as_ref(), &schema(), &registry).unwrap();
+        assert_eq!(decoded, patch);
+
+        let mut patched = old;
+        decoded.apply(&mut patched).unwrap();
+        assert_eq!(patched, new);
+    }
+
+    #[test]
+    fn test_apply_missing_path() {
+        let patch = ValuePatch::new(vec![PatchOp::Remove {
+            path: vec![
+                PathSegment::Key("a".to_owned()),
+                PathSegment::Key("b".to_owned()),
+            ],
+        }]);
+        let mut target = Value::Object(IndexMap::new());
+        assert!(patch.apply(&mut target).is_err());
+    }
+
+    #[test]
+    fn test_encode_property_index_too_large() {
+        let schema = SchemaType::object(
+            (0..300).map(|i| (format!("p{i:03}"), Property::optional(SchemaType::int32()))),
+        );
+        let registry = SchemaRegistry::new();
+        let set = |key: &str| {
+            ValuePatch::new(vec![PatchOp::Set {
+                path: vec![PathSegment::Key(key.to_owned())],
+                value: Value::from(1),
+            }])
+        };
+        assert!(set("p255").encode(&schema, &registry).is_ok());
+        let err = set("p256").encode(&schema, &registry).unwrap_err();
+        assert!(matches!(
+            err,
+            crate::error::Error::Encode(EncodeError::InvalidFormat(_))
+        ));
+    }
+}
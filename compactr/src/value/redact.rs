@@ -0,0 +1,148 @@
+//! Redaction of sensitive properties.
+
+use super::Value;
+use crate::error::Result;
+use crate::schema::{SchemaRegistry, SchemaType};
+
+/// Placeholder that replaces the values of sensitive properties.
+pub const REDACTED: &str = "[REDACTED]";
+
+impl Value {
+    /// Replaces the values of properties marked
+    /// [sensitive](crate::Property::with_sensitive) in `schema` with the
+    /// [`REDACTED`] placeholder string, so the value can be logged or dumped
+    /// without leaking personal data.
+    ///
+    /// Nested objects, array elements and `oneOf` payloads are redacted too.
+    /// References can't be resolved without a registry and are left as they
+    /// are; use [`Value::redact_with_registry`] for schemas with references.
+    /// The redacted value generally no longer matches the schema.
+    ///
+    /// ```rust
+    /// use compactr::{Property, SchemaType, Value};
+    ///
+    /// let schema = SchemaType::object([
+    ///     ("id", Property::required(SchemaType::int32())),
+    ///     ("email", Property::required(SchemaType::string()).with_sensitive(true)),
+    /// ]);
+    /// let mut user = Value::object([("id", Value::from(1)), ("email", Value::from("a@b.c"))]);
+    /// user.redact(&schema);
+    /// assert_eq!(user.get("email"), Some(&Value::from("[REDACTED]")));
+    /// ```
+    pub fn redact(&mut self, schema: &SchemaType) {
+        // Nothing is looked up, so this can't fail
+        let _ = redact(self, schema, None);
+    }
+
+    /// Replaces the values of sensitive properties with the [`REDACTED`]
+    /// placeholder, following references through `registry`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a reference is not registered.
+    pub fn redact_with_registry(
+        &mut self,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> Result<()> {
+        redact(self, schema, Some(registry))
+    }
+}
+
+fn redact(value: &mut Value, schema: &SchemaType, registry: Option<&SchemaRegistry>) -> Result<()> {
+    match (schema, value) {
+        (SchemaType::Reference(name), value) => {
+            if let Some(registry) = registry {
+                let resolved = registry.resolve_shared(name)?;
+                redact(value, &resolved, Some(registry))?;
+            }
+        }
+        (SchemaType::Object(properties), Value::Object(obj)) => {
+            for (name, value) in obj {
+                match properties.get(&**name) {
+                    Some(property) if property.sensitive => *value = Value::from(REDACTED),
+                    Some(property) => redact(value, &property.schema_type, registry)?,
+                    None => {}
+                }
+            }
+        }
+        (SchemaType::Array(items) | SchemaType::RunLengthArray(items), Value::Array(values)) => {
+            for value in values {
+                redact(value, items, registry)?;
+            }
+        }
+        (
+            SchemaType::OneOf {
+                discriminator,
+                variants,
+            },
+            value,
+        ) => {
+            let payload = value
+                .get(discriminator)
+                .and_then(Value::as_str)
+                .and_then(|name| variants.get(name));
+            if let Some(payload) = payload {
+                redact(value, payload, registry)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Decoder, DecoderOptions, Encoder};
+    use crate::schema::Property;
+
+    #[test]
+    fn test_redact() {
+        let registry = SchemaRegistry::new();
+        registry
+            .register(
+                "User",
+                SchemaType::object([
+                    ("name", Property::required(SchemaType::string())),
+                    (
+                        "ssn",
+                        Property::optional(SchemaType::string()).with_sensitive(true),
+                    ),
+                ]),
+            )
+            .unwrap();
+        let schema =
+            SchemaType::object([("users", SchemaType::array(SchemaType::reference("User")))]);
+        let value = Value::object([(
+            "users",
+            vec![
+                Value::object([("name", "Ann"), ("ssn", "123-45-6789")]),
+                Value::object([("name", "Bob")]),
+            ],
+        )]);
+        let expected = Value::object([(
+            "users",
+            vec![
+                Value::object([("name", "Ann"), ("ssn", REDACTED)]),
+                Value::object([("name", "Bob")]),
+            ],
+        )]);
+
+        let mut redacted = value.clone();
+        redacted.redact(&schema);
+        assert_eq!(redacted, value);
+        redacted.redact_with_registry(&schema, &registry).unwrap();
+        assert_eq!(redacted, expected);
+
+        let mut enc = Encoder::new();
+        enc.encode_with_registry(&value, &schema, &registry)
+            .unwrap();
+        let bytes = enc.finish();
+        let redacting = registry
+            .clone()
+            .with_decoder_options(DecoderOptions::new().with_redact(true));
+        let decoded = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &redacting);
+        assert_eq!(decoded.unwrap(), expected);
+    }
+}
@@ -0,0 +1,377 @@
+//! Self-describing encoding of values, decodable without a schema.
+//!
+//! Every value is prefixed with a 1-byte type tag, and containers carry
+//! their own lengths and property names. The output is larger than the
+//! schema-driven encoding but needs no agreed schema, which makes it useful
+//! for logging, debugging and ad-hoc messaging.
+
+use super::Value;
+use crate::codec::buffer::{decode_binary, decode_string, encode_binary, encode_string};
+use crate::error::{DecodeError, EncodeError, Result};
+#[cfg(feature = "chrono")]
+use crate::formats::datetime::{
+    decode_date, decode_datetime, decode_datetime_tz, decode_time, encode_date, encode_datetime,
+    encode_datetime_tz, encode_time,
+};
+#[cfg(feature = "decimal")]
+use crate::formats::decimal::{decode_decimal, encode_decimal};
+use crate::formats::geo::{decode_geo_point, encode_geo_point};
+use crate::formats::ipaddr::{
+    decode_ipv4, decode_ipv6, decode_socket_addr, encode_ipv4, encode_ipv6, encode_socket_addr,
+};
+#[cfg(feature = "uuid")]
+use crate::formats::uuid::{decode_uuid, encode_uuid};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use indexmap::IndexMap;
+
+const TAG_NULL: u8 = 0x00;
+const TAG_FALSE: u8 = 0x01;
+const TAG_TRUE: u8 = 0x02;
+const TAG_INTEGER: u8 = 0x03;
+const TAG_INT128: u8 = 0x04;
+const TAG_UINT128: u8 = 0x05;
+const TAG_FLOAT: u8 = 0x06;
+const TAG_DOUBLE: u8 = 0x07;
+const TAG_DECIMAL: u8 = 0x08;
+const TAG_STRING: u8 = 0x10;
+const TAG_BINARY: u8 = 0x11;
+const TAG_UUID: u8 = 0x12;
+const TAG_DATETIME: u8 = 0x13;
+const TAG_DATETIME_TZ: u8 = 0x14;
+const TAG_DATE: u8 = 0x15;
+const TAG_TIME: u8 = 0x16;
+const TAG_GEO_POINT: u8 = 0x17;
+const TAG_IPV4: u8 = 0x18;
+const TAG_IPV6: u8 = 0x19;
+const TAG_SOCKET_ADDR: u8 = 0x1A;
+const TAG_ARRAY: u8 = 0x20;
+const TAG_OBJECT: u8 = 0x21;
+
+/// Maximum nesting of arrays and objects accepted when decoding, so
+/// malicious input cannot exhaust the stack.
+const MAX_DEPTH: usize = 128;
+
+impl Value {
+    /// Encodes this value in the self-describing format.
+    ///
+    /// Format: a 1-byte type tag followed by the payload. Scalars and
+    /// formatted values use the same payloads as the schema-driven encoding,
+    /// except that strings carry a u32 BE length like binary data. Arrays are
+    /// a u32 BE element count followed by the tagged elements; objects are a
+    /// u32 BE property count followed by, per property, the name (u16 BE
+    /// length + UTF-8) and the tagged value, in insertion order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a container or property name is too long, or a
+    /// formatted value cannot be encoded.
+    pub fn encode_self_describing(&self) -> Result<Bytes> {
+        let mut buf = BytesMut::new();
+        encode_tagged(self, &mut buf)?;
+        Ok(buf.freeze())
+    }
+
+    /// Decodes a value produced by [`Value::encode_self_describing`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the data is truncated, has an unknown type tag or
+    /// is nested too deeply.
+    pub fn decode_self_describing(buf: &mut impl Buf) -> Result<Self> {
+        decode_tagged(buf, 0)
+    }
+}
+
+fn encode_tagged(value: &Value, buf: &mut BytesMut) -> Result<()> {
+    match value {
+        Value::Custom(custom) => encode_tagged(custom.repr(), buf)?,
+        Value::Null => buf.put_u8(TAG_NULL),
+        Value::Boolean(b) => buf.put_u8(if *b { TAG_TRUE } else { TAG_FALSE }),
+        Value::Integer(i) => {
+            buf.put_u8(TAG_INTEGER);
+            buf.put_i64(*i);
+        }
+        Value::Int128(i) => {
+            buf.put_u8(TAG_INT128);
+            buf.put_i128(*i);
+        }
+        Value::Uint128(u) => {
+            buf.put_u8(TAG_UINT128);
+            buf.put_u128(*u);
+        }
+        Value::Float(f) => {
+            buf.put_u8(TAG_FLOAT);
+            buf.put_f32(*f);
+        }
+        Value::Double(f) => {
+            buf.put_u8(TAG_DOUBLE);
+            buf.put_f64(*f);
+        }
+        #[cfg(feature = "decimal")]
+        Value::Decimal(d) => {
+            buf.put_u8(TAG_DECIMAL);
+            encode_decimal(buf, d)?;
+        }
+        Value::String(s) => {
+            buf.put_u8(TAG_STRING);
+            encode_binary(buf, s.as_bytes())?;
+        }
+        Value::Binary(data) => {
+            buf.put_u8(TAG_BINARY);
+            encode_binary(buf, data)?;
+        }
+        #[cfg(feature = "uuid")]
+        Value::Uuid(uuid) => {
+            buf.put_u8(TAG_UUID);
+            encode_uuid(buf, uuid)?;
+        }
+        #[cfg(feature = "chrono")]
+        Value::DateTime(dt) => {
+            buf.put_u8(TAG_DATETIME);
+            encode_datetime(buf, dt)?;
+        }
+        #[cfg(feature = "chrono")]
+        Value::DateTimeTz(dt) => {
+            buf.put_u8(TAG_DATETIME_TZ);
+            encode_datetime_tz(buf, dt)?;
+        }
+        #[cfg(feature = "chrono")]
+        Value::Date(date) => {
+            buf.put_u8(TAG_DATE);
+            encode_date(buf, date)?;
+        }
+        #[cfg(feature = "chrono")]
+        Value::Time(time) => {
+            buf.put_u8(TAG_TIME);
+            encode_time(buf, time)?;
+        }
+        Value::GeoPoint(point) => {
+            buf.put_u8(TAG_GEO_POINT);
+            encode_geo_point(buf, point)?;
+        }
+        Value::Ipv4(addr) => {
+            buf.put_u8(TAG_IPV4);
+            encode_ipv4(buf, addr)?;
+        }
+        Value::Ipv6(addr) => {
+            buf.put_u8(TAG_IPV6);
+            encode_ipv6(buf, addr)?;
+        }
+        Value::SocketAddr(addr) => {
+            buf.put_u8(TAG_SOCKET_ADDR);
+            encode_socket_addr(buf, addr)?;
+        }
+        Value::Array(items) => {
+            buf.put_u8(TAG_ARRAY);
+            put_len(buf, items.len())?;
+            for item in items {
+                encode_tagged(item, buf)?;
+            }
+        }
+        Value::Object(fields) => {
+            buf.put_u8(TAG_OBJECT);
+            put_len(buf, fields.len())?;
+            for (key, value) in fields {
+                encode_string(buf, key)?;
+                encode_tagged(value, buf)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn put_len(buf: &mut BytesMut, len: usize) -> Result<()> {
+    let len = u32::try_from(len)
+        .map_err(|_| EncodeError::InvalidFormat(format!("Container too large: {len} entries")))?;
+    buf.put_u32(len);
+    Ok(())
+}
+
+fn get_len(buf: &mut impl Buf) -> Result<usize> {
+    if buf.remaining() < 4 {
+        return Err(DecodeError::UnexpectedEof.into());
+    }
+    Ok(buf.get_u32() as usize)
+}
+
+fn ensure(buf: &impl Buf, len: usize) -> Result<()> {
+    if buf.remaining() < len {
+        return Err(DecodeError::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+fn decode_tagged(buf: &mut impl Buf, depth: usize) -> Result<Value> {
+    ensure(buf, 1)?;
+    let value = match buf.get_u8() {
+        TAG_NULL => Value::Null,
+        TAG_FALSE => Value::Boolean(false),
+        TAG_TRUE => Value::Boolean(true),
+        TAG_INTEGER => {
+            ensure(buf, 8)?;
+            Value::Integer(buf.get_i64())
+        }
+        TAG_INT128 => {
+            ensure(buf, 16)?;
+            Value::Int128(buf.get_i128())
+        }
+        TAG_UINT128 => {
+            ensure(buf, 16)?;
+            Value::Uint128(buf.get_u128())
+        }
+        TAG_FLOAT => {
+            ensure(buf, 4)?;
+            Value::Float(buf.get_f32())
+        }
+        TAG_DOUBLE => {
+            ensure(buf, 8)?;
+            Value::Double(buf.get_f64())
+        }
+        #[cfg(feature = "decimal")]
+        TAG_DECIMAL => Value::Decimal(decode_decimal(buf)?),
+        #[cfg(not(feature = "decimal"))]
+        TAG_DECIMAL => {
+            return Err(DecodeError::InvalidData(
+                "Decimal values require the `decimal` feature".to_owned(),
+            )
+            .into())
+        }
+        TAG_STRING => {
+            Value::String(String::from_utf8(decode_binary(buf)?).map_err(DecodeError::from)?)
+        }
+        TAG_BINARY => Value::Binary(decode_binary(buf)?),
+        #[cfg(feature = "uuid")]
+        TAG_UUID => Value::Uuid(decode_uuid(buf)?),
+        #[cfg(not(feature = "uuid"))]
+        TAG_UUID => {
+            return Err(DecodeError::InvalidData(
+                "UUID values require the `uuid` feature".to_owned(),
+            )
+            .into())
+        }
+        #[cfg(feature = "chrono")]
+        TAG_DATETIME => Value::DateTime(decode_datetime(buf)?),
+        #[cfg(feature = "chrono")]
+        TAG_DATETIME_TZ => Value::DateTimeTz(decode_datetime_tz(buf)?),
+        #[cfg(feature = "chrono")]
+        TAG_DATE => Value::Date(decode_date(buf)?),
+        #[cfg(feature = "chrono")]
+        TAG_TIME => Value::Time(decode_time(buf)?),
+        #[cfg(not(feature = "chrono"))]
+        TAG_DATETIME | TAG_DATETIME_TZ | TAG_DATE | TAG_TIME => {
+            return Err(DecodeError::InvalidData(
+                "Date and time values require the `chrono` feature".to_owned(),
+            )
+            .into())
+        }
+        TAG_GEO_POINT => Value::GeoPoint(decode_geo_point(buf)?),
+        TAG_IPV4 => Value::Ipv4(decode_ipv4(buf)?),
+        TAG_IPV6 => Value::Ipv6(decode_ipv6(buf)?),
+        TAG_SOCKET_ADDR => Value::SocketAddr(decode_socket_addr(buf)?),
+        tag @ (TAG_ARRAY | TAG_OBJECT) => {
+            if depth >= MAX_DEPTH {
+                return Err(DecodeError::InvalidData(format!(
+                    "Nesting deeper than {MAX_DEPTH} levels"
+                ))
+                .into());
+            }
+            let len = get_len(buf)?;
+            // Every entry takes at least one byte; don't trust the count for
+            // preallocation beyond that
+            let capacity = len.min(buf.remaining());
+            if tag == TAG_ARRAY {
+                let mut items = Vec::with_capacity(capacity);
+                for _ in 0..len {
+                    items.push(decode_tagged(buf, depth + 1)?);
+                }
+                Value::Array(items)
+            } else {
+                let mut fields = IndexMap::with_capacity(capacity);
+                for _ in 0..len {
+                    let key = decode_string(buf)?;
+                    fields.insert(key.into(), decode_tagged(buf, depth + 1)?);
+                }
+                Value::Object(fields)
+            }
+        }
+        tag => {
+            return Err(DecodeError::InvalidData(format!("Invalid type tag: 0x{tag:02x}")).into())
+        }
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::geo::GeoPoint;
+    #[cfg(feature = "chrono")]
+    use chrono::{NaiveDate, TimeZone, Utc};
+    #[cfg(feature = "uuid")]
+    use uuid::Uuid;
+
+    #[test]
+    fn test_roundtrip() {
+        let mut inner = IndexMap::new();
+        inner.insert("ok".into(), Value::Boolean(true));
+        inner.insert("missing".into(), Value::Null);
+
+        let mut fields = IndexMap::new();
+        #[cfg(feature = "uuid")]
+        fields.insert("id".into(), Value::Uuid(Uuid::nil()));
+        fields.insert("name".into(), Value::String("Alice".to_owned()));
+        fields.insert("count".into(), Value::Integer(-42));
+        fields.insert("big".into(), Value::Uint128(u128::MAX));
+        fields.insert("ratio".into(), Value::Double(0.25));
+        #[cfg(feature = "chrono")]
+        fields.insert(
+            "at".into(),
+            Value::DateTime(Utc.with_ymd_and_hms(2024, 1, 15, 10, 30, 0).unwrap()),
+        );
+        #[cfg(feature = "chrono")]
+        fields.insert(
+            "day".into(),
+            Value::Date(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+        );
+        fields.insert(
+            "where".into(),
+            Value::GeoPoint(GeoPoint::new(51.5, -0.12).unwrap()),
+        );
+        fields.insert("ip".into(), Value::Ipv4("10.0.0.1".parse().unwrap()));
+        fields.insert("blob".into(), Value::Binary(vec![1, 2, 3]));
+        fields.insert(
+            "list".into(),
+            Value::Array(vec![Value::Float(1.5), Value::Object(inner)]),
+        );
+        let value = Value::Object(fields);
+
+        let bytes = value.encode_self_describing().unwrap();
+        let decoded = Value::decode_self_describing(&mut bytes.as_ref()).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_layout() {
+        let value = Value::Array(vec![Value::Boolean(true), Value::String("hi".to_owned())]);
+        let bytes = value.encode_self_describing().unwrap();
+        assert_eq!(
+            bytes.as_ref(),
+            [0x20, 0, 0, 0, 2, 0x02, 0x10, 0, 0, 0, 2, b'h', b'i']
+        );
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(Value::decode_self_describing(&mut [0xFF].as_ref()).is_err());
+        assert!(Value::decode_self_describing(&mut [TAG_INTEGER, 0, 0].as_ref()).is_err());
+        // Claims a billion elements but holds none
+        assert!(
+            Value::decode_self_describing(&mut [TAG_ARRAY, 0x3B, 0x9A, 0xCA, 0x00].as_ref())
+                .is_err()
+        );
+
+        let mut nested = [TAG_ARRAY, 0, 0, 0, 1].repeat(MAX_DEPTH + 1);
+        nested.push(TAG_NULL);
+        assert!(Value::decode_self_describing(&mut nested.as_slice()).is_err());
+    }
+}
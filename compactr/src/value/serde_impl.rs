@@ -0,0 +1,225 @@
+//! `Serialize`/`Deserialize` implementations for [`Value`].
+//!
+//! The representation is schema-less and mirrors [`Value::to_json`]:
+//! formatted variants (UUID, date-time, IP addresses, ...) serialize as
+//! strings and binary data as Base64 in human-readable formats. Since no
+//! schema is available when deserializing, strings always come back as
+//! [`Value::String`]; use [`Value::from_json`] to recover formatted variants.
+
+use super::Value;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+#[cfg(feature = "chrono")]
+use chrono::SecondsFormat;
+use indexmap::IndexMap;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Caps the preallocation of sequences and maps, whose length hints come
+/// from untrusted input.
+const MAX_PREALLOCATED: usize = 4096;
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Boolean(b) => serializer.serialize_bool(*b),
+            Self::Integer(i) => serializer.serialize_i64(*i),
+            Self::Int128(i) => serializer.serialize_i128(*i),
+            Self::Uint128(u) => serializer.serialize_u128(*u),
+            Self::Float(f) => serializer.serialize_f32(*f),
+            Self::Double(d) => serializer.serialize_f64(*d),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(d) => serializer.collect_str(d),
+            Self::String(s) => serializer.serialize_str(s),
+            #[cfg(feature = "uuid")]
+            Self::Uuid(u) => serializer.collect_str(u),
+            #[cfg(feature = "chrono")]
+            Self::DateTime(dt) => {
+                serializer.serialize_str(&dt.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            }
+            #[cfg(feature = "chrono")]
+            Self::DateTimeTz(dt) => {
+                serializer.serialize_str(&dt.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+            }
+            #[cfg(feature = "chrono")]
+            Self::Date(d) => serializer.collect_str(&d.format("%Y-%m-%d")),
+            #[cfg(feature = "chrono")]
+            Self::Time(t) => serializer.collect_str(t),
+            Self::GeoPoint(p) => {
+                let mut map = serializer.serialize_map(Some(2))?;
+                map.serialize_entry("lat", &p.lat())?;
+                map.serialize_entry("lon", &p.lon())?;
+                map.end()
+            }
+            Self::Ipv4(ip) => serializer.collect_str(ip),
+            Self::Ipv6(ip) => serializer.collect_str(ip),
+            Self::SocketAddr(addr) => serializer.collect_str(addr),
+            Self::Binary(data) => {
+                if serializer.is_human_readable() {
+                    serializer.serialize_str(&BASE64.encode(data))
+                } else {
+                    serializer.serialize_bytes(data)
+                }
+            }
+            Self::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Self::Object(obj) => {
+                let mut map = serializer.serialize_map(Some(obj.len()))?;
+                for (k, v) in obj {
+                    map.serialize_entry(&**k, v)?;
+                }
+                map.end()
+            }
+            Self::Custom(custom) => custom.repr().serialize(serializer),
+            Self::Null => serializer.serialize_unit(),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("any Compactr value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(i64::try_from(v).map_or(Value::Uint128(u128::from(v)), Value::Integer))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<Value, E> {
+        Ok(Value::Int128(v))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<Value, E> {
+        Ok(Value::Uint128(v))
+    }
+
+    fn visit_f32<E: de::Error>(self, v: f32) -> Result<Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Double(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Binary(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Binary(v))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(MAX_PREALLOCATED));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut obj = IndexMap::with_capacity(map.size_hint().unwrap_or(0).min(MAX_PREALLOCATED));
+        while let Some((k, v)) = map.next_entry::<String, Value>()? {
+            obj.insert(k.into(), v);
+        }
+        Ok(Value::Object(obj))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_serialize_matches_to_json() {
+        let mut obj = IndexMap::new();
+        obj.insert("name".into(), Value::from("Alice"));
+        obj.insert("ip".into(), Value::from(Ipv4Addr::new(10, 0, 0, 1)));
+        obj.insert("data".into(), Value::Binary(vec![1, 2, 3]));
+        obj.insert("tags".into(), Value::Array(vec![Value::from("a")]));
+        let value = Value::Object(obj);
+
+        let serialized = serde_json::to_value(&value).unwrap();
+        assert_eq!(serialized, value.to_json());
+        assert_eq!(serialized["ip"], json!("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_deserialize_schemaless() {
+        let value: Value =
+            serde_json::from_str(r#"{"a": 1, "b": [true, null], "c": 1.5, "d": "x"}"#).unwrap();
+
+        assert_eq!(value.get("a"), Some(&Value::Integer(1)));
+        assert_eq!(
+            value.get("b"),
+            Some(&Value::Array(vec![Value::Boolean(true), Value::Null]))
+        );
+        assert_eq!(value.get("c"), Some(&Value::Double(1.5)));
+        assert_eq!(value.get("d"), Some(&Value::from("x")));
+    }
+
+    #[test]
+    fn test_roundtrip_preserves_key_order() {
+        let value: Value = serde_json::from_str(r#"{"z": 1, "a": 2}"#).unwrap();
+        let keys: Vec<_> = value.as_object().unwrap().keys().map(|k| &**k).collect();
+        assert_eq!(keys, ["z", "a"]);
+    }
+
+    #[test]
+    fn test_deserialize_huge_length_header() {
+        // MessagePack array32 and map32 headers claiming 2^32 - 1 entries
+        for header in [
+            [0xdd, 0xff, 0xff, 0xff, 0xff],
+            [0xdf, 0xff, 0xff, 0xff, 0xff],
+        ] {
+            assert!(rmp_serde::from_slice::<Value>(&header).is_err());
+        }
+    }
+}
@@ -0,0 +1,154 @@
+//! `wasm-bindgen` exports for browser and Node.js clients.
+//!
+//! Schemas are described in `OpenAPI` JSON (see [`SchemaType::from_json`])
+//! and values are exchanged as JSON strings, so JavaScript clients produce
+//! and consume exactly the bytes the Rust side does, and can be checked for
+//! parity against compactr.js.
+//!
+//! ```js
+//! import { Codec } from "compactr";
+//!
+//! const codec = new Codec(JSON.stringify(openapi));
+//! const bytes = codec.encode("User", JSON.stringify(user));
+//! const user = JSON.parse(codec.decode("User", bytes));
+//! ```
+
+use crate::codec::{Decoder, Encoder};
+use crate::error::{EncodeError, Result, SchemaError};
+use crate::schema::{SchemaRegistry, SchemaType};
+use crate::value::Value;
+use wasm_bindgen::prelude::*;
+
+/// Encodes a JSON value with a standalone JSON schema.
+///
+/// # Errors
+///
+/// Throws if either argument is not valid JSON, the schema is not supported
+/// or the value doesn't match it.
+#[wasm_bindgen]
+pub fn encode(schema: &str, value: &str) -> std::result::Result<Vec<u8>, JsError> {
+    let schema = parse_schema(schema)?;
+    Ok(encode_json(&schema, value, SchemaRegistry::empty())?)
+}
+
+/// Decodes bytes with a standalone JSON schema, returning the value as JSON.
+///
+/// # Errors
+///
+/// Throws if the schema is not valid or the bytes don't match it.
+#[wasm_bindgen]
+pub fn decode(schema: &str, bytes: &[u8]) -> std::result::Result<String, JsError> {
+    let schema = parse_schema(schema)?;
+    Ok(decode_json(&schema, bytes, SchemaRegistry::empty())?)
+}
+
+/// Encoder/decoder for the named schemas of an `OpenAPI` document.
+#[wasm_bindgen]
+pub struct Codec {
+    registry: SchemaRegistry,
+}
+
+#[wasm_bindgen]
+impl Codec {
+    /// Registers the schemas of an `OpenAPI` document, or of its
+    /// `components.schemas` object.
+    ///
+    /// # Errors
+    ///
+    /// Throws if the document is not valid JSON or a schema is not supported.
+    #[wasm_bindgen(constructor)]
+    pub fn new(schemas: &str) -> std::result::Result<Codec, JsError> {
+        let json: serde_json::Value = serde_json::from_str(schemas)?;
+        let registry = SchemaRegistry::new();
+        registry.register_json_schemas(&json)?;
+        Ok(Self { registry })
+    }
+
+    /// Encodes a JSON value with the schema registered as `name`.
+    ///
+    /// # Errors
+    ///
+    /// Throws if the schema is unknown or the value doesn't match it.
+    pub fn encode(&self, name: &str, value: &str) -> std::result::Result<Vec<u8>, JsError> {
+        let schema = self.schema(name)?;
+        Ok(encode_json(&schema, value, &self.registry)?)
+    }
+
+    /// Decodes bytes with the schema registered as `name`, returning the
+    /// value as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Throws if the schema is unknown or the bytes don't match it.
+    pub fn decode(&self, name: &str, bytes: &[u8]) -> std::result::Result<String, JsError> {
+        let schema = self.schema(name)?;
+        Ok(decode_json(&schema, bytes, &self.registry)?)
+    }
+}
+
+impl Codec {
+    fn schema(&self, name: &str) -> Result<SchemaType> {
+        self.registry
+            .get(name)?
+            .ok_or_else(|| SchemaError::UnresolvedReference(name.to_owned()).into())
+    }
+}
+
+fn parse_schema(schema: &str) -> std::result::Result<SchemaType, JsError> {
+    let json: serde_json::Value = serde_json::from_str(schema)?;
+    Ok(SchemaType::from_json(&json)?)
+}
+
+fn encode_json(schema: &SchemaType, value: &str, registry: &SchemaRegistry) -> Result<Vec<u8>> {
+    let json: serde_json::Value = serde_json::from_str(value)
+        .map_err(|e| EncodeError::InvalidFormat(format!("Invalid JSON: {e}")))?;
+    let value = Value::from_json_with_registry(&json, schema, registry)?;
+    let mut encoder = Encoder::new();
+    encoder.encode_with_registry(&value, schema, registry)?;
+    Ok(encoder.finish().to_vec())
+}
+
+fn decode_json(schema: &SchemaType, mut bytes: &[u8], registry: &SchemaRegistry) -> Result<String> {
+    let value = Decoder::decode_with_registry(&mut bytes, schema, registry)?;
+    Ok(value.to_json().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_roundtrip() {
+        let registry = SchemaRegistry::new();
+        registry
+            .register_json_schemas(&json!({
+                "User": {
+                    "type": "object",
+                    "required": ["id", "name"],
+                    "properties": {
+                        "id": {"type": "string", "format": "uuid"},
+                        "name": {"type": "string"}
+                    }
+                }
+            }))
+            .unwrap();
+        let schema = SchemaType::reference("#/components/schemas/User");
+
+        let user = json!({"id": "550e8400-e29b-41d4-a716-446655440000", "name": "Alice"});
+        let bytes = encode_json(&schema, &user.to_string(), &registry).unwrap();
+        let decoded = decode_json(&schema, &bytes, &registry).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&decoded).unwrap(),
+            user
+        );
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let schema = SchemaType::int32();
+        let registry = SchemaRegistry::new();
+        assert!(encode_json(&schema, "not json", &registry).is_err());
+        assert!(encode_json(&schema, "\"text\"", &registry).is_err());
+    }
+}
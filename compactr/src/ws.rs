@@ -0,0 +1,197 @@
+//! Length-delimited binary messages for WebSocket transports.
+//!
+//! Each frame is a u32 BE length followed by a [`Message`] (schema ID
+//! prefix and payload). Frames may be batched into one WebSocket message or
+//! split across several; a [`Reassembler`] accepts the binary data as it
+//! arrives and yields complete messages.
+
+use crate::error::{DecodeError, EncodeError, Result};
+use crate::message::Message;
+use crate::schema::SchemaRegistry;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+/// Default largest frame a [`Reassembler`] accepts (16 MiB).
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Encodes `message` as a length-delimited frame.
+///
+/// # Errors
+///
+/// Returns an error if the message cannot be encoded or is larger than
+/// `u32::MAX` bytes.
+pub fn encode_frame(message: &Message, registry: &SchemaRegistry) -> Result<Bytes> {
+    let encoded = message.encode(registry)?;
+    let len = u32::try_from(encoded.len()).map_err(|_| {
+        EncodeError::InvalidFormat(format!("Frame too large: {} bytes", encoded.len()))
+    })?;
+    let mut buf = BytesMut::with_capacity(4 + encoded.len());
+    buf.put_u32(len);
+    buf.put_slice(&encoded);
+    Ok(buf.freeze())
+}
+
+/// Incremental decoder of length-delimited frames.
+///
+/// ```rust,ignore
+/// let mut reassembler = Reassembler::new();
+/// while let Some(data) = socket.next_binary().await {
+///     reassembler.push(&data);
+///     while let Some(message) = reassembler.next_message(&registry)? {
+///         handle(message);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Reassembler {
+    buf: BytesMut,
+    max_frame_len: usize,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reassembler {
+    /// Creates a reassembler accepting frames up to
+    /// [`DEFAULT_MAX_FRAME_LEN`] bytes.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            buf: BytesMut::new(),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    /// Sets the largest frame accepted; longer frames are rejected before
+    /// they are buffered.
+    #[must_use]
+    pub const fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Appends received data.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Returns the number of buffered bytes not yet decoded.
+    #[must_use]
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns the next complete frame, without its length prefix, or `None`
+    /// if more data is needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is longer than the configured maximum.
+    /// The reassembler cannot resynchronize after that and should be
+    /// discarded along with the connection.
+    pub fn next_frame(&mut self) -> Result<Option<Bytes>> {
+        if self.buf.len() < 4 {
+            return Ok(None);
+        }
+        let len = (&self.buf[..4]).get_u32() as usize;
+        if len > self.max_frame_len {
+            return Err(DecodeError::InvalidData(format!(
+                "Frame too large: {len} bytes (max {})",
+                self.max_frame_len
+            ))
+            .into());
+        }
+        if self.buf.len() < 4 + len {
+            // Reserve up front so a large frame arriving in many fragments
+            // doesn't reallocate repeatedly
+            self.buf.reserve(4 + len - self.buf.len());
+            return Ok(None);
+        }
+        self.buf.advance(4);
+        Ok(Some(self.buf.split_to(len).freeze()))
+    }
+
+    /// Decodes the next complete message, or returns `None` if more data is
+    /// needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the frame is too long or is not a valid message.
+    pub fn next_message(&mut self, registry: &SchemaRegistry) -> Result<Option<Message>> {
+        let Some(frame) = self.next_frame()? else {
+            return Ok(None);
+        };
+        let mut buf = frame.as_ref();
+        let message = Message::decode(&mut buf, registry)?;
+        if buf.has_remaining() {
+            return Err(DecodeError::TrailingBytes(buf.remaining()).into());
+        }
+        Ok(Some(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::SchemaType;
+    use crate::value::Value;
+
+    fn registry() -> SchemaRegistry {
+        let registry = SchemaRegistry::new();
+        registry.register("Name", SchemaType::string()).unwrap();
+        registry.register("Count", SchemaType::int32()).unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_fragmented_frames() {
+        let registry = registry();
+        let messages = [
+            Message::new("Name", Value::String("Alice".to_owned())),
+            Message::new("Count", Value::Integer(7)),
+        ];
+        let mut stream = Vec::new();
+        for message in &messages {
+            stream.extend_from_slice(&encode_frame(message, &registry).unwrap());
+        }
+
+        // Deliver one byte at a time
+        let mut reassembler = Reassembler::new();
+        let mut decoded = Vec::new();
+        for byte in stream {
+            reassembler.push(&[byte]);
+            while let Some(message) = reassembler.next_message(&registry).unwrap() {
+                decoded.push(message);
+            }
+        }
+        assert_eq!(decoded, messages);
+        assert_eq!(reassembler.buffered(), 0);
+    }
+
+    #[test]
+    fn test_batched_frames() {
+        let registry = registry();
+        let mut batch = BytesMut::new();
+        for n in 0..3 {
+            let message = Message::new("Count", Value::Integer(n));
+            batch.extend_from_slice(&encode_frame(&message, &registry).unwrap());
+        }
+
+        let mut reassembler = Reassembler::new();
+        reassembler.push(&batch);
+        for n in 0..3 {
+            let message = reassembler.next_message(&registry).unwrap().unwrap();
+            assert_eq!(message.value(), &Value::Integer(n));
+        }
+        assert!(reassembler.next_message(&registry).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let mut reassembler = Reassembler::new().with_max_frame_len(8);
+        reassembler.push(&[0, 0, 0, 9]);
+        assert!(reassembler.next_frame().is_err());
+    }
+}
@@ -3,6 +3,8 @@
 //! These tests verify that the binary format produced by the Rust implementation
 //! matches the specification and can be used to validate compatibility with compactr.js.
 
+#![cfg(all(feature = "chrono", feature = "uuid"))]
+
 use chrono::TimeZone;
 use compactr::{Decoder, Encoder, Property, SchemaRegistry, SchemaType, Value};
 use indexmap::IndexMap;
@@ -295,7 +297,7 @@ fn test_array_binary_format() {
     encoder.encode(&Value::Array(vec![]), &schema).unwrap();
     let bytes = encoder.finish();
     assert_eq!(bytes.len(), 0);
-    assert_eq!(&bytes[..], &[]);
+    assert_eq!(&bytes[..], &[] as &[u8]);
 
     // Test array with 2 elements: [1, 2]
     // New format: [size1, elem1, size2, elem2]
@@ -325,8 +327,8 @@ fn test_object_binary_format() {
     let schema = SchemaType::object(properties);
 
     let mut obj = IndexMap::new();
-    obj.insert("x".to_owned(), Value::Integer(10));
-    obj.insert("y".to_owned(), Value::Integer(20));
+    obj.insert("x".into(), Value::Integer(10));
+    obj.insert("y".into(), Value::Integer(20));
 
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
@@ -370,10 +372,10 @@ fn test_deterministic_encoding() {
     let dt = Utc.timestamp_millis_opt(1_609_459_200_000).unwrap(); // 2021-01-01 00:00:00 UTC
 
     let mut obj = IndexMap::new();
-    obj.insert("id".to_owned(), Value::Uuid(uuid));
-    obj.insert("name".to_owned(), Value::String("Test".to_owned()));
-    obj.insert("count".to_owned(), Value::Integer(42));
-    obj.insert("created".to_owned(), Value::DateTime(dt));
+    obj.insert("id".into(), Value::Uuid(uuid));
+    obj.insert("name".into(), Value::String("Test".to_owned()));
+    obj.insert("count".into(), Value::Integer(42));
+    obj.insert("created".into(), Value::DateTime(dt));
 
     // Encode multiple times
     let mut encoder1 = Encoder::new();
@@ -417,8 +419,8 @@ fn test_property_order_matters() {
 
     // Same value for both
     let mut obj = IndexMap::new();
-    obj.insert("x".to_owned(), Value::Integer(10));
-    obj.insert("y".to_owned(), Value::Integer(20));
+    obj.insert("x".into(), Value::Integer(10));
+    obj.insert("y".into(), Value::Integer(20));
 
     // Encode with schema1
     let mut encoder1 = Encoder::new();
@@ -459,8 +461,8 @@ fn test_optional_fields_binary_format() {
 
     // Test with optional field present
     let mut obj1 = IndexMap::new();
-    obj1.insert("id".to_owned(), Value::Integer(1));
-    obj1.insert("name".to_owned(), Value::String("Alice".to_owned()));
+    obj1.insert("id".into(), Value::Integer(1));
+    obj1.insert("name".into(), Value::String("Alice".to_owned()));
 
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj1), &schema).unwrap();
@@ -468,7 +470,7 @@ fn test_optional_fields_binary_format() {
 
     // Test with optional field missing (should encode as null)
     let mut obj2 = IndexMap::new();
-    obj2.insert("id".to_owned(), Value::Integer(1));
+    obj2.insert("id".into(), Value::Integer(1));
     // name is missing
 
     let mut encoder = Encoder::new();
@@ -506,13 +508,13 @@ fn test_nested_structure_format() {
 
     // Create nested value
     let mut address = IndexMap::new();
-    address.insert("street".to_owned(), Value::String("123 Main".to_owned()));
-    address.insert("city".to_owned(), Value::String("NYC".to_owned()));
+    address.insert("street".into(), Value::String("123 Main".to_owned()));
+    address.insert("city".into(), Value::String("NYC".to_owned()));
 
     let mut user = IndexMap::new();
-    user.insert("name".to_owned(), Value::String("Bob".to_owned()));
-    user.insert("age".to_owned(), Value::Integer(25));
-    user.insert("address".to_owned(), Value::Object(address));
+    user.insert("name".into(), Value::String("Bob".to_owned()));
+    user.insert("age".into(), Value::Integer(25));
+    user.insert("address".into(), Value::Object(address));
 
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(user), &schema).unwrap();
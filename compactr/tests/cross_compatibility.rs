@@ -11,6 +11,8 @@
 //!
 //! Or run the setup script: `./scripts/setup-fixtures.sh`
 
+#![cfg(all(feature = "chrono", feature = "uuid"))]
+
 use compactr::{Decoder, Encoder, Property, SchemaType, Value};
 use indexmap::IndexMap;
 use std::path::Path;
@@ -57,7 +59,7 @@ fn test_boolean_compatibility() {
 
     // Test true
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::Boolean(true));
+    obj.insert("value".into(), Value::Boolean(true));
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
     let rust_bytes = encoder.finish();
@@ -74,7 +76,7 @@ fn test_boolean_compatibility() {
 
     // Test false
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::Boolean(false));
+    obj.insert("value".into(), Value::Boolean(false));
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
     let rust_bytes = encoder.finish();
@@ -98,7 +100,7 @@ fn test_integer_compatibility() {
 
     // Test 42
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::Integer(42));
+    obj.insert("value".into(), Value::Integer(42));
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
     let rust_bytes = encoder.finish();
@@ -117,7 +119,7 @@ fn test_integer_compatibility() {
     let schema = SchemaType::object(properties);
 
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::Integer(9_007_199_254_740_991)); // Max safe JS integer
+    obj.insert("value".into(), Value::Integer(9_007_199_254_740_991)); // Max safe JS integer
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
     let rust_bytes = encoder.finish();
@@ -137,7 +139,7 @@ fn test_string_compatibility() {
 
     // Test "Hello"
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::String("Hello".to_owned()));
+    obj.insert("value".into(), Value::String("Hello".to_owned()));
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
     let rust_bytes = encoder.finish();
@@ -152,7 +154,7 @@ fn test_string_compatibility() {
 
     // Test empty string
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::String("".to_owned()));
+    obj.insert("value".into(), Value::String("".to_owned()));
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
     let rust_bytes = encoder.finish();
@@ -177,7 +179,7 @@ fn test_uuid_compatibility() {
 
     let uuid = Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap();
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::Uuid(uuid));
+    obj.insert("value".into(), Value::Uuid(uuid));
 
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
@@ -207,7 +209,7 @@ fn test_ipv4_compatibility() {
 
     let ip = Ipv4Addr::new(192, 168, 1, 1);
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::Ipv4(ip));
+    obj.insert("value".into(), Value::Ipv4(ip));
 
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
@@ -236,7 +238,7 @@ fn test_array_compatibility() {
     // Test [1, 2, 3]
     let mut obj = IndexMap::new();
     obj.insert(
-        "value".to_owned(),
+        "value".into(),
         Value::Array(vec![
             Value::Integer(1),
             Value::Integer(2),
@@ -258,7 +260,7 @@ fn test_array_compatibility() {
 
     // Test empty array
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::Array(vec![]));
+    obj.insert("value".into(), Value::Array(vec![]));
 
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
@@ -278,8 +280,8 @@ fn test_object_compatibility() {
     let schema = SchemaType::object(properties);
 
     let mut obj = IndexMap::new();
-    obj.insert("x".to_owned(), Value::Integer(10));
-    obj.insert("y".to_owned(), Value::Integer(20));
+    obj.insert("x".into(), Value::Integer(10));
+    obj.insert("y".into(), Value::Integer(20));
 
     let mut encoder = Encoder::new();
     encoder.encode(&Value::Object(obj), &schema).unwrap();
@@ -329,17 +331,17 @@ fn test_user_object_compatibility() {
 
     let mut obj = IndexMap::new();
     obj.insert(
-        "id".to_owned(),
+        "id".into(),
         Value::Uuid(Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap()),
     );
-    obj.insert("name".to_owned(), Value::String("Alice Johnson".to_owned()));
+    obj.insert("name".into(), Value::String("Alice Johnson".to_owned()));
     obj.insert(
-        "email".to_owned(),
+        "email".into(),
         Value::String("alice@example.com".to_owned()),
     );
-    obj.insert("age".to_owned(), Value::Integer(28));
+    obj.insert("age".into(), Value::Integer(28));
     obj.insert(
-        "created_at".to_owned(),
+        "created_at".into(),
         Value::DateTime(Utc.timestamp_millis_opt(1_705_314_600_000).unwrap()), // 2024-01-15T10:30:00Z
     );
 
@@ -528,8 +530,8 @@ use compactr::{Encoder, SchemaType, Value, Property};
 
 // ... setup schema ...
 let mut obj = IndexMap::new();
-obj.insert("id".to_owned(), Value::Integer(1));
-obj.insert("name".to_owned(), Value::String("Alice".to_owned()));
+obj.insert("id".into(), Value::Integer(1));
+obj.insert("name".into(), Value::String("Alice".to_owned()));
 
 let mut encoder = Encoder::new();
 encoder.encode(&Value::Object(obj), &schema)?;
@@ -0,0 +1,667 @@
+//! Tests for `#[derive(Compactr)]`.
+
+#![cfg(all(feature = "derive", feature = "chrono", feature = "uuid"))]
+
+use compactr::{
+    Compactr, Decoder, Encoder, Property, Schema, SchemaRegistry, SchemaType, SizeWidth,
+    TimestampPrecision, Value,
+};
+use indexmap::IndexMap;
+use std::borrow::Cow;
+use std::time::Duration;
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Compactr)]
+#[compactr(rename_all = "camelCase")]
+struct User {
+    #[compactr(format = "uuid")]
+    id: String,
+    display_name: String,
+    #[compactr(rename = "years")]
+    age: i32,
+    #[compactr(default)]
+    login_count: i32,
+    nickname: Option<String>,
+    #[compactr(skip)]
+    cached: Option<String>,
+}
+
+fn user() -> User {
+    User {
+        id: "550e8400-e29b-41d4-a716-446655440000".to_owned(),
+        display_name: "Alice".to_owned(),
+        age: 30,
+        login_count: 7,
+        nickname: None,
+        cached: Some("ignored".to_owned()),
+    }
+}
+
+fn encode<T: compactr::Encode>(value: &T, schema: &SchemaType) -> bytes::Bytes {
+    let mut encoder = Encoder::new();
+    encoder
+        .encode_as(value, schema, &SchemaRegistry::new())
+        .unwrap();
+    encoder.finish()
+}
+
+#[test]
+fn test_derived_schema() {
+    let mut properties = IndexMap::new();
+    properties.insert(
+        "id".to_owned(),
+        Property::required(SchemaType::string_uuid()),
+    );
+    properties.insert(
+        "displayName".to_owned(),
+        Property::required(SchemaType::string()),
+    );
+    properties.insert("years".to_owned(), Property::required(SchemaType::int32()));
+    properties.insert(
+        "loginCount".to_owned(),
+        Property::optional(SchemaType::int32()),
+    );
+    properties.insert(
+        "nickname".to_owned(),
+        Property::optional(SchemaType::string()),
+    );
+
+    assert_eq!(User::schema(), SchemaType::object(properties));
+}
+
+#[test]
+fn test_derived_roundtrip() {
+    let schema = User::schema();
+    let bytes = encode(&user(), &schema);
+
+    let decoded: User =
+        Decoder::decode_as(&mut bytes.as_ref(), &schema, &SchemaRegistry::new()).unwrap();
+    assert_eq!(
+        decoded,
+        User {
+            cached: None,
+            ..user()
+        }
+    );
+}
+
+#[test]
+fn test_derived_matches_value_encoding() {
+    let mut obj = IndexMap::new();
+    obj.insert(
+        "id".into(),
+        Value::Uuid(Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap()),
+    );
+    obj.insert("displayName".into(), Value::from("Alice"));
+    obj.insert("years".into(), Value::from(30_i32));
+    obj.insert("loginCount".into(), Value::from(7_i32));
+
+    let schema = User::schema();
+    let mut encoder = Encoder::new();
+    encoder.encode(&Value::Object(obj), &schema).unwrap();
+
+    assert_eq!(encode(&user(), &schema), encoder.finish());
+}
+
+#[test]
+fn test_derived_missing_fields() {
+    let schema = User::schema();
+    let registry = SchemaRegistry::new();
+
+    // `loginCount` is `default` and `nickname` is an Option: both may be absent
+    let mut obj = IndexMap::new();
+    obj.insert(
+        "id".into(),
+        Value::from("550e8400-e29b-41d4-a716-446655440000"),
+    );
+    obj.insert("displayName".into(), Value::from("Bob"));
+    obj.insert("years".into(), Value::from(41_i32));
+    let mut encoder = Encoder::new();
+    encoder.encode(&Value::Object(obj), &schema).unwrap();
+    let bytes = encoder.finish();
+
+    let decoded: User = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded.login_count, 0);
+    assert_eq!(decoded.nickname, None);
+
+    // A required field missing from the data is an error
+    let mut properties = IndexMap::new();
+    properties.insert(
+        "id".to_owned(),
+        Property::required(SchemaType::string_uuid()),
+    );
+    let partial = SchemaType::object(properties);
+    let bytes = encode(&user(), &partial);
+    assert!(Decoder::decode_as::<User>(&mut bytes.as_ref(), &partial, &registry).is_err());
+}
+
+#[test]
+fn test_derived_timestamp_format() {
+    #[derive(Debug, PartialEq, Compactr)]
+    struct Event {
+        #[compactr(format = "timestamp-seconds")]
+        at: chrono::DateTime<chrono::Utc>,
+    }
+
+    let schema = Event::schema();
+    assert_eq!(
+        schema,
+        SchemaType::object([(
+            "at",
+            Property::required(SchemaType::timestamp(TimestampPrecision::Seconds))
+        )])
+    );
+
+    let event = Event {
+        at: chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+    };
+    let bytes = encode(&event, &schema);
+    let decoded: Event =
+        Decoder::decode_as(&mut bytes.as_ref(), &schema, &SchemaRegistry::new()).unwrap();
+    assert_eq!(decoded, event);
+}
+
+#[test]
+fn test_derived_nested() {
+    #[derive(Debug, PartialEq, Compactr)]
+    struct Team {
+        name: String,
+        members: Vec<User>,
+    }
+
+    let team = Team {
+        name: "core".to_owned(),
+        members: vec![User {
+            cached: None,
+            ..user()
+        }],
+    };
+    let registry = SchemaRegistry::new();
+    let schema = Team::schema();
+    let bytes = encode(&team, &schema);
+
+    let decoded: Team = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, team);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Compactr)]
+#[compactr(rename_all = "lowercase")]
+enum Color {
+    Red,
+    Green,
+    #[compactr(rename = "azure")]
+    Blue,
+}
+
+#[derive(Debug, PartialEq, Compactr)]
+struct Polygon {
+    sides: i32,
+}
+
+#[derive(Debug, PartialEq, Compactr)]
+#[compactr(tag = "kind", rename_all = "snake_case")]
+enum Shape {
+    Empty,
+    Circle {
+        radius: f64,
+        #[compactr(default)]
+        color: Option<Color>,
+    },
+    RegularPolygon(Polygon),
+}
+
+#[test]
+fn test_derived_unit_enum() {
+    assert_eq!(
+        Color::schema(),
+        SchemaType::string_enum(["red", "green", "azure"])
+    );
+
+    let bytes = encode(&Color::Blue, &Color::schema());
+    assert_eq!(bytes.as_ref(), &[2]);
+
+    let decoded: Color = Decoder::decode_as(
+        &mut bytes.as_ref(),
+        &Color::schema(),
+        &SchemaRegistry::new(),
+    )
+    .unwrap();
+    assert_eq!(decoded, Color::Blue);
+
+    let value = Decoder::decode(&mut bytes.as_ref(), &Color::schema()).unwrap();
+    assert_eq!(value, Value::from("azure"));
+}
+
+#[test]
+fn test_derived_data_enum() {
+    let schema = Shape::schema();
+    let registry = SchemaRegistry::new();
+    let SchemaType::OneOf {
+        discriminator,
+        variants,
+    } = &schema
+    else {
+        panic!("expected a oneOf schema");
+    };
+    assert_eq!(discriminator, "kind");
+    assert_eq!(
+        variants.keys().collect::<Vec<_>>(),
+        ["empty", "circle", "regular_polygon"]
+    );
+
+    for shape in [
+        Shape::Empty,
+        Shape::Circle {
+            radius: 2.5,
+            color: Some(Color::Green),
+        },
+        Shape::Circle {
+            radius: 1.0,
+            color: None,
+        },
+        Shape::RegularPolygon(Polygon { sides: 6 }),
+    ] {
+        let bytes = encode(&shape, &schema);
+        let decoded: Shape = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+        assert_eq!(decoded, shape);
+    }
+    assert_eq!(encode(&Shape::Empty, &schema).as_ref(), &[0]);
+}
+
+#[test]
+fn test_derived_enum_matches_value_encoding() {
+    let schema = Shape::schema();
+    let shape = Shape::Circle {
+        radius: 2.5,
+        color: Some(Color::Green),
+    };
+
+    let mut obj = IndexMap::new();
+    obj.insert("kind".into(), Value::from("circle"));
+    obj.insert("radius".into(), Value::Double(2.5));
+    obj.insert("color".into(), Value::from("green"));
+    let value = Value::Object(obj);
+
+    let mut encoder = Encoder::new();
+    encoder.encode(&value, &schema).unwrap();
+    let bytes = encoder.finish();
+    assert_eq!(encode(&shape, &schema), bytes);
+    assert_eq!(
+        Decoder::decode(&mut bytes.as_ref(), &schema).unwrap(),
+        value
+    );
+}
+
+#[derive(Debug, PartialEq, Compactr)]
+struct Envelope<T> {
+    id: i64,
+    payload: T,
+    tags: Vec<T>,
+}
+
+#[test]
+fn test_derived_generic() {
+    let registry = SchemaRegistry::new();
+    let envelope = Envelope {
+        id: 42,
+        payload: Color::Blue,
+        tags: vec![Color::Red, Color::Green],
+    };
+    let schema = Envelope::<Color>::schema();
+    let bytes = encode(&envelope, &schema);
+    let decoded: Envelope<Color> =
+        Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, envelope);
+
+    let envelope = Envelope {
+        id: 7,
+        payload: user(),
+        tags: Vec::new(),
+    };
+    let schema = Envelope::<User>::schema();
+    let bytes = encode(&envelope, &schema);
+    let decoded: Envelope<User> =
+        Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded.payload.display_name, "Alice");
+}
+
+#[derive(Debug, PartialEq, Compactr)]
+struct Borrowed<'a> {
+    name: &'a str,
+    bio: Cow<'a, str>,
+    #[compactr(format = "uuid")]
+    id: Cow<'a, str>,
+    aliases: Vec<&'a str>,
+    nickname: Option<&'a str>,
+}
+
+#[test]
+fn test_derived_borrowed() {
+    let registry = SchemaRegistry::new();
+    let value = Borrowed {
+        name: "Alice",
+        bio: Cow::Borrowed("Likes compact encodings"),
+        id: Cow::Borrowed("550e8400-e29b-41d4-a716-446655440000"),
+        aliases: vec!["al", "ally"],
+        nickname: None,
+    };
+    let schema = Borrowed::schema();
+    let bytes = encode(&value, &schema);
+
+    let decoded: Borrowed =
+        Decoder::decode_borrowed(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, value);
+    // Plain strings point into the encoded bytes, formatted ones are owned
+    let range = bytes.as_ptr_range();
+    assert!(range.contains(&decoded.name.as_ptr()));
+    assert!(range.contains(&decoded.aliases[1].as_ptr()));
+    assert!(matches!(decoded.bio, Cow::Borrowed(_)));
+    assert!(matches!(decoded.id, Cow::Owned(_)));
+}
+
+#[test]
+fn test_derived_borrowed_generic() {
+    let registry = SchemaRegistry::new();
+    let envelope = Envelope {
+        id: 1,
+        payload: "hello",
+        tags: vec!["a", "b"],
+    };
+    let schema = Envelope::<&str>::schema();
+    let bytes = encode(&envelope, &schema);
+    let decoded: Envelope<&str> =
+        Decoder::decode_borrowed(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, envelope);
+}
+
+#[derive(Debug, PartialEq, Compactr)]
+#[compactr(rename_all = "camelCase")]
+struct Header {
+    request_id: i64,
+    trace: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Compactr)]
+struct Request {
+    #[compactr(flatten)]
+    header: Header,
+    path: String,
+}
+
+#[derive(Debug, PartialEq, Compactr)]
+struct FlatRequest {
+    #[compactr(rename = "requestId")]
+    request_id: i64,
+    trace: Option<String>,
+    path: String,
+}
+
+#[test]
+fn test_derived_flatten() {
+    let registry = SchemaRegistry::new();
+    let schema = Request::schema();
+    assert_eq!(schema, FlatRequest::schema());
+
+    let request = Request {
+        header: Header {
+            request_id: 9,
+            trace: Some("abc".to_owned()),
+        },
+        path: "/users".to_owned(),
+    };
+    let flat = FlatRequest {
+        request_id: 9,
+        trace: Some("abc".to_owned()),
+        path: "/users".to_owned(),
+    };
+    let bytes = encode(&request, &schema);
+    assert_eq!(bytes, encode(&flat, &schema));
+
+    let decoded: Request = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, request);
+
+    // Missing properties of the flattened struct are reported
+    let partial = SchemaType::object([("path", Property::required(SchemaType::string()))]);
+    let bytes = encode(&request, &partial);
+    let result: compactr::Result<Request> =
+        Decoder::decode_as(&mut bytes.as_ref(), &partial, &registry);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_derived_flatten_nested_and_borrowed() {
+    #[derive(Debug, PartialEq, Compactr)]
+    struct Tagged<'a> {
+        tag: &'a str,
+        #[compactr(flatten)]
+        request: Request,
+    }
+
+    let registry = SchemaRegistry::new();
+    let schema = Tagged::schema();
+    let SchemaType::Object(properties) = &schema else {
+        panic!("expected an object schema");
+    };
+    let names: Vec<&str> = properties.keys().map(String::as_str).collect();
+    assert_eq!(names, ["tag", "requestId", "trace", "path"]);
+
+    let tagged = Tagged {
+        tag: "v1",
+        request: Request {
+            header: Header {
+                request_id: 1,
+                trace: None,
+            },
+            path: "/".to_owned(),
+        },
+    };
+    let bytes = encode(&tagged, &schema);
+    let decoded: Tagged =
+        Decoder::decode_borrowed(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, tagged);
+}
+
+/// Stores a `Duration` as whole milliseconds.
+mod duration_ms {
+    use bytes::{Buf, BytesMut};
+    use compactr::{DecodeError, Decoder, Encoder, SchemaRegistry, SchemaType};
+    use std::time::Duration;
+
+    pub fn schema() -> SchemaType {
+        SchemaType::int64()
+    }
+
+    pub fn encode(
+        value: &Duration,
+        buf: &mut BytesMut,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> compactr::Result<()> {
+        let millis = i64::try_from(value.as_millis()).unwrap_or(i64::MAX);
+        Encoder::encode_into(buf, &millis, schema, registry)
+    }
+
+    pub fn decode(
+        buf: &mut impl Buf,
+        schema: &SchemaType,
+        registry: &SchemaRegistry,
+    ) -> compactr::Result<Duration> {
+        let millis: i64 = Decoder::decode_as(buf, schema, registry)?;
+        u64::try_from(millis)
+            .map(Duration::from_millis)
+            .map_err(|_| DecodeError::InvalidData(format!("negative duration: {millis}")).into())
+    }
+}
+
+#[derive(Debug, PartialEq, Compactr)]
+struct Job {
+    name: String,
+    #[compactr(with = "duration_ms")]
+    timeout: Duration,
+}
+
+#[test]
+fn test_derived_with() {
+    #[derive(Compactr)]
+    struct RawJob {
+        name: String,
+        timeout: i64,
+    }
+
+    let registry = SchemaRegistry::new();
+    let schema = Job::schema();
+    assert_eq!(schema, RawJob::schema());
+
+    let job = Job {
+        name: "backup".to_owned(),
+        timeout: Duration::from_millis(1500),
+    };
+    let raw = RawJob {
+        name: "backup".to_owned(),
+        timeout: 1500,
+    };
+    let bytes = encode(&job, &schema);
+    assert_eq!(bytes, encode(&raw, &schema));
+    let decoded: Job = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, job);
+
+    // The module's errors surface from decoding
+    let bytes = encode(&RawJob { timeout: -1, ..raw }, &schema);
+    assert!(Decoder::decode_as::<Job>(&mut bytes.as_ref(), &schema, &registry).is_err());
+}
+
+#[test]
+fn test_derived_with_flattened() {
+    #[derive(Debug, PartialEq, Compactr)]
+    struct Scheduled<'a> {
+        cron: &'a str,
+        #[compactr(flatten)]
+        job: Job,
+    }
+
+    let registry = SchemaRegistry::new();
+    let schema = Scheduled::schema();
+    let scheduled = Scheduled {
+        cron: "0 * * * *",
+        job: Job {
+            name: "backup".to_owned(),
+            timeout: Duration::from_secs(60),
+        },
+    };
+    let bytes = encode(&scheduled, &schema);
+    let decoded: Scheduled =
+        Decoder::decode_borrowed(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, scheduled);
+}
+
+#[test]
+fn test_derived_run_length() {
+    #[derive(Debug, PartialEq, Compactr)]
+    struct Readings {
+        #[compactr(run_length)]
+        quality: Vec<i32>,
+        #[compactr(run_length)]
+        categories: Option<Vec<String>>,
+    }
+
+    let registry = SchemaRegistry::new();
+    let schema = Readings::schema();
+    let SchemaType::Object(properties) = &schema else {
+        panic!("expected object schema");
+    };
+    assert_eq!(
+        properties["quality"],
+        Property::required(SchemaType::run_length_array(SchemaType::int32()))
+    );
+
+    let readings = Readings {
+        quality: vec![1; 50],
+        categories: Some(vec!["a".to_owned(), "a".to_owned(), "b".to_owned()]),
+    };
+    let bytes = encode(&readings, &schema);
+    let decoded: Readings = Decoder::decode_as(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    assert_eq!(decoded, readings);
+    let value = Decoder::decode_with_registry(&mut bytes.as_ref(), &schema, &registry).unwrap();
+    let quality = value.get("quality").and_then(Value::as_array).unwrap();
+    assert_eq!(quality.len(), 50);
+}
+
+#[test]
+fn test_derived_size_width() {
+    #[derive(Debug, PartialEq, Compactr)]
+    struct Post {
+        title: String,
+        #[compactr(size_width = "u16")]
+        body: String,
+    }
+
+    let schema = Post::schema();
+    let SchemaType::Object(properties) = &schema else {
+        panic!("expected object schema");
+    };
+    assert_eq!(properties["body"].size_width, SizeWidth::U16);
+
+    let post = Post {
+        title: "Hello".to_owned(),
+        body: "text ".repeat(100),
+    };
+    let bytes = encode(&post, &schema);
+    let decoded: Post =
+        Decoder::decode_as(&mut bytes.as_ref(), &schema, &SchemaRegistry::new()).unwrap();
+    assert_eq!(decoded, post);
+}
+
+#[test]
+fn test_derived_sensitive() {
+    #[derive(Debug, PartialEq, Compactr)]
+    struct Account {
+        name: String,
+        #[compactr(sensitive)]
+        password: String,
+    }
+
+    let schema = Account::schema();
+    let SchemaType::Object(properties) = &schema else {
+        panic!("expected object schema");
+    };
+    assert!(properties["password"].sensitive);
+    assert!(!properties["name"].sensitive);
+}
+
+#[test]
+fn test_derived_alias() {
+    #[derive(Debug, PartialEq, Compactr)]
+    struct Before {
+        id: i32,
+        zip: String,
+    }
+
+    #[derive(Debug, PartialEq, Compactr)]
+    struct After {
+        id: i32,
+        #[compactr(alias = "zip")]
+        a_postcode: String,
+    }
+
+    let SchemaType::Object(properties) = After::schema() else {
+        panic!("expected object schema");
+    };
+    assert_eq!(properties["a_postcode"].aliases, ["zip"]);
+    assert_eq!(
+        Before::schema().fingerprint(),
+        After::schema().fingerprint()
+    );
+
+    let before = Before {
+        id: 1,
+        zip: "12345".to_owned(),
+    };
+    let bytes = encode(&before, &Before::schema());
+    let after: After = Decoder::decode_as(
+        &mut bytes.as_ref(),
+        &After::schema(),
+        &SchemaRegistry::new(),
+    )
+    .unwrap();
+    assert_eq!(after.a_postcode, before.zip);
+}
@@ -31,6 +31,52 @@ console.log('Generating binary test fixtures...\n');
 // Track generated fixtures
 const fixtures = [];
 
+// compactr.js type names as OpenAPI type and format, for the manifest
+const openApiTypes = {
+  boolean: { type: 'boolean' },
+  int32: { type: 'integer', format: 'int32' },
+  int64: { type: 'integer', format: 'int64' },
+  float: { type: 'number', format: 'float' },
+  double: { type: 'number', format: 'double' },
+  string: { type: 'string' },
+  uuid: { type: 'string', format: 'uuid' },
+  'date-time': { type: 'string', format: 'date-time' },
+  date: { type: 'string', format: 'date' },
+  ipv4: { type: 'string', format: 'ipv4' },
+  ipv6: { type: 'string', format: 'ipv6' },
+  binary: { type: 'string', format: 'binary' }
+};
+
+/**
+ * Converts a compactr.js schema (an object of properties) to the OpenAPI
+ * schema compactr.rs reads fixtures with.
+ */
+function toOpenApi(properties) {
+  const converted = {};
+  const required = [];
+  for (const [name, property] of Object.entries(properties)) {
+    converted[name] = propertyToOpenApi(property);
+    if (!property.optional) {
+      required.push(name);
+    }
+  }
+  return { type: 'object', properties: converted, required };
+}
+
+function propertyToOpenApi(property) {
+  if (property.type === 'object') {
+    return toOpenApi(property.schema);
+  }
+  if (property.type === 'array') {
+    return { type: 'array', items: propertyToOpenApi(property.items) };
+  }
+  const type = openApiTypes[property.type];
+  if (!type) {
+    throw new Error(`No OpenAPI equivalent for type ${property.type}`);
+  }
+  return { ...type };
+}
+
 function generateFixture(name, schemaObj, value, description) {
   try {
     const s = schema(schemaObj);
@@ -46,7 +92,9 @@ function generateFixture(name, schemaObj, value, description) {
       filename,
       size: buffer.length,
       hex: buffer.toString('hex'),
-      description
+      description,
+      schema: toOpenApi(schemaObj),
+      value
     });
 
     console.log(`✓ ${filename.padEnd(30)} ${buffer.length} bytes`);
@@ -335,4 +383,4 @@ fs.writeFileSync(
 
 console.log(`✓ manifest.json\n`);
 console.log(`Generated ${fixtures.length} fixtures successfully!`);
-console.log(`\nRun Rust tests with: cargo test --test cross_compatibility`);
+console.log(`\nRun Rust tests with: cargo test --all-features -- --include-ignored`);
@@ -1,5 +1,7 @@
 //! Integration tests for the Compactr library.
 
+#![cfg(all(feature = "chrono", feature = "uuid"))]
+
 use compactr::{Decoder, Encoder, Property, SchemaType, Value};
 use indexmap::IndexMap;
 
@@ -32,9 +34,12 @@ fn test_integers() {
     let decoded = Decoder::decode(&mut buf, &schema).unwrap();
     assert_eq!(decoded, value);
 
-    // Int64
+    // Int64, carried as a double: i64::MAX would round to 2^63
     let schema = SchemaType::int64();
-    let value = Value::Integer(i64::MAX);
+    assert!(Encoder::new()
+        .encode(&Value::Integer(i64::MAX), &schema)
+        .is_err());
+    let value = Value::Integer(i64::MIN);
 
     let mut encoder = Encoder::new();
     encoder.encode(&value, &schema).unwrap();
@@ -222,9 +227,9 @@ fn test_object() {
     let schema = SchemaType::object(properties);
 
     let mut obj = IndexMap::new();
-    obj.insert("name".to_owned(), Value::String("Alice".to_owned()));
-    obj.insert("age".to_owned(), Value::Integer(30));
-    obj.insert("active".to_owned(), Value::Boolean(true));
+    obj.insert("name".into(), Value::String("Alice".to_owned()));
+    obj.insert("age".into(), Value::Integer(30));
+    obj.insert("active".into(), Value::Boolean(true));
 
     let value = Value::Object(obj);
 
@@ -255,12 +260,12 @@ fn test_nested_object() {
     let schema = SchemaType::object(user_props);
 
     let mut address = IndexMap::new();
-    address.insert("street".to_owned(), Value::String("123 Main St".to_owned()));
-    address.insert("city".to_owned(), Value::String("Springfield".to_owned()));
+    address.insert("street".into(), Value::String("123 Main St".to_owned()));
+    address.insert("city".into(), Value::String("Springfield".to_owned()));
 
     let mut user = IndexMap::new();
-    user.insert("name".to_owned(), Value::String("Bob".to_owned()));
-    user.insert("address".to_owned(), Value::Object(address));
+    user.insert("name".into(), Value::String("Bob".to_owned()));
+    user.insert("address".into(), Value::Object(address));
 
     let value = Value::Object(user);
 
@@ -299,18 +304,18 @@ fn test_complex_structure() {
     // Create a complex value
     let mut obj = IndexMap::new();
     obj.insert(
-        "id".to_owned(),
+        "id".into(),
         Value::Uuid(Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap()),
     );
-    obj.insert("name".to_owned(), Value::String("Test Item".to_owned()));
+    obj.insert("name".into(), Value::String("Test Item".to_owned()));
     obj.insert(
-        "tags".to_owned(),
+        "tags".into(),
         Value::Array(vec![
             Value::String("rust".to_owned()),
             Value::String("serialization".to_owned()),
         ]),
     );
-    obj.insert("created_at".to_owned(), Value::DateTime(Utc::now()));
+    obj.insert("created_at".into(), Value::DateTime(Utc::now()));
 
     let value = Value::Object(obj);
 
@@ -344,3 +349,65 @@ fn test_complex_structure() {
         panic!("Expected object values");
     }
 }
+
+#[test]
+fn test_enum() {
+    let schema = SchemaType::string_enum(["red", "green", "blue"]);
+    let value = Value::from("green");
+
+    let mut encoder = Encoder::new();
+    encoder.encode(&value, &schema).unwrap();
+    let bytes = encoder.finish();
+    assert_eq!(bytes.as_ref(), &[1]);
+
+    let mut buf = bytes.as_ref();
+    let decoded = Decoder::decode(&mut buf, &schema).unwrap();
+    assert_eq!(decoded, value);
+
+    let mut encoder = Encoder::new();
+    assert!(encoder.encode(&Value::from("purple"), &schema).is_err());
+    assert!(Decoder::decode(&mut [3u8].as_ref(), &schema).is_err());
+}
+
+#[test]
+fn test_one_of() {
+    let mut circle = IndexMap::new();
+    circle.insert(
+        "radius".to_string(),
+        Property::required(SchemaType::double()),
+    );
+    let mut variants = IndexMap::new();
+    variants.insert("empty".to_string(), SchemaType::null());
+    variants.insert("circle".to_string(), SchemaType::object(circle));
+    let schema = SchemaType::one_of("kind", variants);
+
+    let mut obj = IndexMap::new();
+    obj.insert("kind".into(), Value::from("circle"));
+    obj.insert("radius".into(), Value::Double(1.5));
+    let value = Value::Object(obj);
+
+    let mut encoder = Encoder::new();
+    encoder.encode(&value, &schema).unwrap();
+    let bytes = encoder.finish();
+    // Tag, property count, index, size, f64
+    assert_eq!(bytes.len(), 1 + 1 + 1 + 1 + 8);
+    assert_eq!(bytes[0], 1);
+
+    let mut buf = bytes.as_ref();
+    let decoded = Decoder::decode(&mut buf, &schema).unwrap();
+    assert_eq!(decoded, value);
+
+    // Variants without a payload are a single byte
+    let mut obj = IndexMap::new();
+    obj.insert("kind".into(), Value::from("empty"));
+    let value = Value::Object(obj);
+
+    let mut encoder = Encoder::new();
+    encoder.encode(&value, &schema).unwrap();
+    let bytes = encoder.finish();
+    assert_eq!(bytes.as_ref(), &[0]);
+
+    let mut buf = bytes.as_ref();
+    let decoded = Decoder::decode(&mut buf, &schema).unwrap();
+    assert_eq!(decoded, value);
+}
@@ -0,0 +1,255 @@
+//! Tests for the serde backend in `compactr::serde`.
+
+#![cfg(all(feature = "serde", feature = "chrono", feature = "uuid"))]
+
+use chrono::{DateTime, TimeZone, Utc};
+use compactr::serde::{from_bytes, from_bytes_with_registry, to_bytes, to_bytes_with_registry};
+use compactr::{Encoder, Property, SchemaRegistry, SchemaType, Value};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct User {
+    id: Uuid,
+    name: String,
+    age: i32,
+    created: DateTime<Utc>,
+    nickname: Option<String>,
+    tags: Vec<String>,
+}
+
+fn user_schema() -> SchemaType {
+    let mut properties = IndexMap::new();
+    properties.insert(
+        "id".to_owned(),
+        Property::required(SchemaType::string_uuid()),
+    );
+    properties.insert("name".to_owned(), Property::required(SchemaType::string()));
+    properties.insert("age".to_owned(), Property::required(SchemaType::int32()));
+    properties.insert(
+        "created".to_owned(),
+        Property::required(SchemaType::string_datetime()),
+    );
+    properties.insert(
+        "nickname".to_owned(),
+        Property::optional(SchemaType::string()),
+    );
+    properties.insert(
+        "tags".to_owned(),
+        Property::required(SchemaType::array(SchemaType::string())),
+    );
+    SchemaType::object(properties)
+}
+
+fn user() -> User {
+    User {
+        id: Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000").unwrap(),
+        name: "Alice".to_owned(),
+        age: 30,
+        created: Utc.with_ymd_and_hms(2024, 1, 15, 10, 30, 0).unwrap(),
+        nickname: None,
+        tags: vec!["admin".to_owned(), "ops".to_owned()],
+    }
+}
+
+#[test]
+fn test_struct_roundtrip() {
+    let schema = user_schema();
+    let bytes = to_bytes(&user(), &schema).unwrap();
+    let decoded: User = from_bytes(&bytes, &schema).unwrap();
+    assert_eq!(decoded, user());
+
+    let with_nickname = User {
+        nickname: Some("al".to_owned()),
+        ..user()
+    };
+    let bytes = to_bytes(&with_nickname, &schema).unwrap();
+    assert_eq!(from_bytes::<User>(&bytes, &schema).unwrap(), with_nickname);
+}
+
+#[test]
+fn test_matches_value_encoding() {
+    let schema = user_schema();
+    let user = user();
+
+    let mut fields = IndexMap::new();
+    fields.insert("id".into(), Value::Uuid(user.id));
+    fields.insert("name".into(), Value::String(user.name.clone()));
+    fields.insert("age".into(), Value::Integer(30));
+    fields.insert("created".into(), Value::DateTime(user.created));
+    fields.insert(
+        "tags".into(),
+        Value::Array(vec![
+            Value::String("admin".to_owned()),
+            Value::String("ops".to_owned()),
+        ]),
+    );
+    let mut encoder = Encoder::new();
+    encoder.encode(&Value::Object(fields), &schema).unwrap();
+
+    assert_eq!(to_bytes(&user, &schema).unwrap(), encoder.finish());
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum Shape {
+    #[serde(rename = "circle")]
+    Circle { radius: f64 },
+    #[serde(rename = "empty")]
+    Empty,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Event {
+    #[serde(rename = "circle")]
+    Circle { radius: f64 },
+    #[serde(rename = "empty")]
+    Empty,
+}
+
+fn shape_schema() -> SchemaType {
+    let mut circle = IndexMap::new();
+    circle.insert(
+        "radius".to_owned(),
+        Property::required(SchemaType::double()),
+    );
+    let mut variants = IndexMap::new();
+    variants.insert("circle".to_owned(), SchemaType::object(circle));
+    variants.insert("empty".to_owned(), SchemaType::null());
+    SchemaType::one_of("type", variants)
+}
+
+#[test]
+fn test_internally_tagged_enum() {
+    let schema = shape_schema();
+    for shape in [Shape::Circle { radius: 2.5 }, Shape::Empty] {
+        let bytes = to_bytes(&shape, &schema).unwrap();
+        assert_eq!(from_bytes::<Shape>(&bytes, &schema).unwrap(), shape);
+    }
+}
+
+#[test]
+fn test_externally_tagged_enum() {
+    let schema = shape_schema();
+    for event in [Event::Circle { radius: 2.5 }, Event::Empty] {
+        let bytes = to_bytes(&event, &schema).unwrap();
+        assert_eq!(from_bytes::<Event>(&bytes, &schema).unwrap(), event);
+    }
+    // Both enum representations share the wire format
+    assert_eq!(
+        to_bytes(&Event::Circle { radius: 2.5 }, &schema).unwrap(),
+        to_bytes(&Shape::Circle { radius: 2.5 }, &schema).unwrap()
+    );
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Active,
+    Suspended,
+}
+
+#[test]
+fn test_unit_enum() {
+    let schema = SchemaType::string_enum(["active", "suspended"]);
+    let bytes = to_bytes(&Status::Suspended, &schema).unwrap();
+    assert_eq!(
+        from_bytes::<Status>(&bytes, &schema).unwrap(),
+        Status::Suspended
+    );
+    assert!(to_bytes(&"deleted", &schema).is_err());
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Blob {
+    #[serde(with = "serde_bytes_vec")]
+    data: Vec<u8>,
+    checksum: Vec<u8>,
+}
+
+/// Serializes a `Vec<u8>` as bytes rather than a sequence.
+mod serde_bytes_vec {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(data)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        Vec::<u8>::deserialize(deserializer)
+    }
+}
+
+#[test]
+fn test_binary() {
+    let mut properties = IndexMap::new();
+    properties.insert("data".to_owned(), Property::required(SchemaType::binary()));
+    properties.insert(
+        "checksum".to_owned(),
+        Property::required(SchemaType::binary()),
+    );
+    let schema = SchemaType::object(properties);
+
+    let blob = Blob {
+        data: vec![1, 2, 3],
+        checksum: vec![0xde, 0xad],
+    };
+    let bytes = to_bytes(&blob, &schema).unwrap();
+    assert_eq!(from_bytes::<Blob>(&bytes, &schema).unwrap(), blob);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Team {
+    lead: User,
+    members: Vec<User>,
+}
+
+#[test]
+fn test_references() {
+    let registry = SchemaRegistry::new();
+    registry.register("User", user_schema()).unwrap();
+    let mut properties = IndexMap::new();
+    properties.insert(
+        "lead".to_owned(),
+        Property::required(SchemaType::reference("User")),
+    );
+    properties.insert(
+        "members".to_owned(),
+        Property::required(SchemaType::array(SchemaType::reference("User"))),
+    );
+    let schema = SchemaType::object(properties);
+
+    let team = Team {
+        lead: user(),
+        members: vec![user()],
+    };
+    let bytes = to_bytes_with_registry(&team, &schema, &registry).unwrap();
+    let decoded: Team = from_bytes_with_registry(&bytes, &schema, &registry).unwrap();
+    assert_eq!(decoded, team);
+}
+
+#[test]
+fn test_type_mismatch() {
+    let schema = user_schema();
+    assert!(to_bytes(&42, &schema).is_err());
+    assert!(to_bytes(&"not a uuid", &SchemaType::string_uuid()).is_err());
+
+    let bytes = to_bytes(&user(), &schema).unwrap();
+    assert!(from_bytes::<Vec<String>>(&bytes, &schema).is_err());
+}
+
+#[test]
+fn test_float_precision() {
+    let float = SchemaType::float();
+    let bytes = to_bytes(&1.5_f64, &float).unwrap();
+    assert_eq!(from_bytes::<f32>(&bytes, &float).unwrap(), 1.5);
+    assert!(to_bytes(&0.1_f64, &float).is_err());
+    assert!(to_bytes(&16_777_217_i64, &float).is_err());
+    assert!(to_bytes(&(1_i64 << 60), &float).is_ok());
+    assert!(to_bytes(&u64::MAX, &SchemaType::double()).is_err());
+
+    let registry = SchemaRegistry::new().with_lossy_floats(true);
+    let bytes = to_bytes_with_registry(&0.1_f64, &float, &registry).unwrap();
+    assert_eq!(from_bytes::<f32>(&bytes, &float).unwrap(), 0.1);
+}
@@ -12,7 +12,7 @@ fn test_simple_int32_object() {
 
     // Create value: {value: 42}
     let mut obj = IndexMap::new();
-    obj.insert("value".to_owned(), Value::Integer(42));
+    obj.insert("value".into(), Value::Integer(42));
     let value = Value::Object(obj);
 
     // Encode
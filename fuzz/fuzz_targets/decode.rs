@@ -0,0 +1,27 @@
+//! Decoding arbitrary bytes fails cleanly, in every layout and with every
+//! decoder, without panicking or allocating beyond the untrusted limits.
+
+#![no_main]
+
+use compactr::fuzz::Layout;
+use compactr::{Decoder, SchemaType, StreamingDecoder, Value};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (SchemaType, Layout, Vec<u8>)| {
+    let (schema, layout, bytes) = input;
+    let registry = layout.registry();
+    let _ = Decoder::decode_with_registry(&mut bytes.as_slice(), &schema, &registry);
+    let _ = Decoder::decode_borrowed::<Value>(&mut bytes.as_slice(), &schema, &registry);
+    let _ = Value::decode_self_describing(&mut bytes.as_slice());
+    if let Ok(mut decoder) = StreamingDecoder::with_registry(schema, registry) {
+        for chunk in bytes.chunks(7) {
+            decoder.feed(chunk);
+            // Every value takes at least one byte
+            for _ in 0..=decoder.buffered() {
+                if !matches!(decoder.next_value(), Ok(Some(_))) {
+                    break;
+                }
+            }
+        }
+    }
+});
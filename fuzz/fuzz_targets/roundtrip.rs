@@ -0,0 +1,24 @@
+//! Values encoded with their schema decode back to themselves in every
+//! layout, and truncating the encoding never panics.
+
+#![no_main]
+
+use compactr::fuzz::{Layout, TypedValue};
+use compactr::{Decoder, Encoder};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (TypedValue, Layout)| {
+    let (input, layout) = input;
+    let registry = layout.registry();
+    let mut encoder = Encoder::new();
+    encoder
+        .encode_with_registry(&input.value, &input.schema, &registry)
+        .unwrap();
+    let bytes = encoder.finish();
+    let decoded =
+        Decoder::decode_with_registry(&mut bytes.as_ref(), &input.schema, &registry).unwrap();
+    assert_eq!(decoded, input.value);
+    for end in 0..bytes.len() {
+        let _ = Decoder::decode_with_registry(&mut &bytes[..end], &input.schema, &registry);
+    }
+});
@@ -41,4 +41,5 @@ echo
 echo "You can now run cross-compatibility tests:"
 echo "  cargo test --test cross_compatibility"
 echo "  cargo test --test binary_format_compatibility"
+echo "  cargo test --features serde --test test_decode_js_fixture -- --ignored"
 echo